@@ -0,0 +1,50 @@
+use crate::path::Path;
+
+/// What an operation changes, as far as positions in the document are
+/// concerned: where it inserts, removes, merges, splits or moves nodes, and
+/// how much text it inserts or removes where.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit<'a> {
+    InsertNode {
+        path: &'a Path,
+    },
+    InsertText {
+        path: &'a Path,
+        offset: usize,
+        len: usize,
+    },
+    MergeNode {
+        path: &'a Path,
+        position: usize,
+    },
+    MoveNode {
+        path: &'a Path,
+        new_path: &'a Path,
+    },
+    RemoveNode {
+        path: &'a Path,
+    },
+    RemoveText {
+        path: &'a Path,
+        offset: usize,
+        len: usize,
+    },
+    SplitNode {
+        path: &'a Path,
+        position: usize,
+    },
+    /// An operation that doesn't move any position, like setting the
+    /// properties of a node.
+    None,
+}
+
+/// Operations that paths, points and ranges can be transformed through.
+pub trait AsEdit {
+    fn as_edit(&self) -> Edit<'_>;
+}
+
+impl AsEdit for Edit<'_> {
+    fn as_edit(&self) -> Edit<'_> {
+        *self
+    }
+}
@@ -0,0 +1,21 @@
+//! The positions in a Slate document, paths, points and ranges, and how
+//! operations move them.
+//!
+//! Without the default `std` feature this builds with only `core` and
+//! `alloc`, so the transform math can run where the standard library isn't
+//! available. Operations are seen through `Edit`, which leaves out the nodes
+//! and text they carry, so the full document model isn't needed either.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod edit;
+mod path;
+mod point;
+mod range;
+
+pub use edit::{AsEdit, Edit};
+pub use path::{Affinity, Path, TransformError};
+pub use point::Point;
+pub use range::{Affinity as RangeAffinity, Range};
@@ -0,0 +1,471 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{min, Ord, Ordering};
+use core::fmt;
+
+use crate::edit::{AsEdit, Edit};
+
+/// Errors returned when an operation can't be transformed against a path,
+/// which means it couldn't have been applied to a document containing it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransformError {
+    /// The operation targets the root, which no node operation can.
+    RootOperation,
+    /// An index of the path would go below zero.
+    IndexUnderflow(Path),
+    /// An index of the path would go past `usize::MAX`.
+    IndexOverflow(Path),
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransformError::RootOperation => write!(f, "operation targets the root"),
+            TransformError::IndexUnderflow(p) => write!(f, "index underflow transforming {:?}", p),
+            TransformError::IndexOverflow(p) => write!(f, "index overflow transforming {:?}", p),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransformError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Affinity {
+    #[default]
+    Forward,
+    Backward,
+    None,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct Path(Vec<usize>);
+
+impl From<Vec<usize>> for Path {
+    fn from(val: Vec<usize>) -> Self {
+        Path(val)
+    }
+}
+
+impl Path {
+    pub fn concat(&self, i: usize) -> Self {
+        let mut copy = self.clone();
+        copy.0.push(i);
+        copy
+    }
+}
+
+impl Path {
+    pub fn new(inner: Vec<usize>) -> Self {
+        Self(inner)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Get the index of the node a path refers to within its parent.
+    pub fn last(&self) -> Option<usize> {
+        self.0.last().copied()
+    }
+
+    pub fn as_slice(&self) -> &[usize] {
+        &self.0
+    }
+
+    pub fn get(&self, i: usize) -> Option<usize> {
+        self.0.get(i).copied()
+    }
+
+    /// Get a list of ancestor paths for a given path.
+    pub fn ancestors(&self, reverse: bool) -> Vec<Path> {
+        let mut paths = self.levels(reverse);
+
+        if reverse {
+            paths = paths[1..].into();
+        } else {
+            paths = paths[..paths.len() - 1].into();
+        }
+
+        paths
+    }
+
+    /// Get the common ancestor path of two paths.
+    pub fn common(&self, other: &Path) -> Path {
+        let mut common = vec![];
+        for (a, b) in self.0.iter().zip(other.0.iter()) {
+            if a != b {
+                break;
+            }
+            common.push(*a);
+        }
+        Path(common)
+    }
+
+    pub fn ends_after(&self, other: &Path) -> bool {
+        let i = self.0.len() - 1;
+        if i > other.0.len() {
+            return false;
+        }
+        self.0[0..i] == other.0[0..i] && self.0[i] > other.0[i]
+    }
+
+    pub fn ends_at(&self, Path(b): &Path) -> bool {
+        let i = self.0.len();
+        if i > b.len() {
+            return false;
+        }
+        self.0[0..i] == b[0..i]
+    }
+
+    pub fn ends_before(&self, Path(b): &Path) -> bool {
+        let i = self.0.len() - 1;
+        if i >= b.len() {
+            return false;
+        }
+        self.0[0..i] == b[0..i] && self.0[i] < b[i]
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.0[self.0.len() - 1] > 0
+    }
+
+    pub fn is_after(&self, other: &Path) -> bool {
+        self > other
+    }
+
+    pub fn is_ancestor(&self, b: &Path) -> bool {
+        self.0.len() < b.0.len() && self.cmp(b) == Ordering::Equal
+    }
+
+    pub fn is_before(&self, b: &Path) -> bool {
+        self < b
+    }
+
+    pub fn is_child(&self, b: &Path) -> bool {
+        self.0.len() == b.0.len() + 1 && self.cmp(b) == Ordering::Equal
+    }
+
+    pub fn is_common(&self, b: &Path) -> bool {
+        self.0.len() <= b.0.len() && self.cmp(b) == Ordering::Equal
+    }
+
+    pub fn is_descendant(&self, other: &Path) -> bool {
+        self.0.len() > other.0.len() && self.cmp(other) == Ordering::Equal
+    }
+
+    pub fn is_parent(&self, other: &Path) -> bool {
+        self.0.len() + 1 == other.0.len() && self.cmp(other) == Ordering::Equal
+    }
+
+    pub fn is_sibling(&self, other: &Path) -> bool {
+        if self.0.len() != other.0.len() {
+            return false;
+        }
+
+        let i = self.0.len() - 1;
+        self.0[i] != other.0[i] && self.0[..i] == other.0[..i]
+    }
+
+    pub fn levels(&self, reverse: bool) -> Vec<Path> {
+        let mut list: Vec<Path> = vec![];
+        for i in 0..=self.0.len() {
+            list.push(Path(self.0[..i].into()));
+        }
+        if reverse {
+            list.reverse();
+        }
+        list
+    }
+
+    pub fn next(&self) -> Option<Path> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        let last = self.0[self.0.len() - 1];
+        let mut n: Vec<_> = self.0[..self.0.len() - 1].into();
+        n.push(last + 1);
+
+        Some(Path(n))
+    }
+
+    pub fn parent(&self) -> Option<Path> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        Some(Path(self.0[..self.0.len() - 1].into()))
+    }
+
+    pub fn previous(&self) -> Option<Path> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        let last = self.0[self.0.len() - 1];
+        let prev = last.checked_sub(1)?;
+        let mut n: Vec<_> = self.0[..self.0.len() - 1].into();
+        n.push(prev);
+
+        Some(Path(n))
+    }
+
+    /// Get a path relative to an ancestor.
+    pub fn relative(&self, ancestor: &Path) -> Option<Path> {
+        if !Path::is_ancestor(ancestor, self) && self != ancestor {
+            return None;
+        }
+
+        let (Path(path), Path(ancestor)) = (self, ancestor);
+
+        Some(Path(path[ancestor.len()..].into()))
+    }
+
+    /// Transform a path through an operation. Returns `None` if the operation
+    /// removes the node the path refers to, and an error if the operation
+    /// couldn't have been applied to a document with that node.
+    pub fn transform<O: AsEdit + ?Sized>(
+        path: &Path,
+        operation: &O,
+        affinity: Affinity,
+    ) -> Result<Option<Path>, TransformError> {
+        let mut path = path.clone();
+
+        if path.transform_mut(operation, affinity)? {
+            Ok(Some(path))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Transform a path through a sequence of operations applied in order,
+    /// like a backlog loaded from a server. Returns `None` as soon as one of
+    /// them removes the node the path refers to.
+    pub fn transform_through<O: AsEdit>(
+        &self,
+        ops: &[O],
+        affinity: Affinity,
+    ) -> Result<Option<Path>, TransformError> {
+        let mut path = self.clone();
+
+        for op in ops {
+            if !path.transform_mut(op, affinity)? {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(path))
+    }
+
+    /// Overwrite a path with other indexes, reusing its allocation.
+    pub fn reset(&mut self, indexes: &[usize]) {
+        self.0.clear();
+        self.0.extend_from_slice(indexes);
+    }
+
+    /// Transform a path in place, without allocating unless the path moves to
+    /// a deeper level. Returns `false` if the operation removes the node the
+    /// path refers to. After either `false` or an error, the path is left in
+    /// an unspecified state.
+    pub fn transform_mut<O: AsEdit + ?Sized>(
+        &mut self,
+        operation: &O,
+        affinity: Affinity,
+    ) -> Result<bool, TransformError> {
+        let path = self;
+
+        // PERF: Exit early if the operation is guaranteed not to have an effect.
+        if path.0.is_empty() {
+            return Ok(true);
+        }
+
+        // Operations on nodes can't target the root, and the checks below
+        // assume that they don't.
+        let root = Path(vec![]);
+        let edit = operation.as_edit();
+        match edit {
+            Edit::InsertNode { path: op }
+            | Edit::RemoveNode { path: op }
+            | Edit::MergeNode { path: op, .. }
+            | Edit::SplitNode { path: op, .. }
+            | Edit::MoveNode { path: op, .. }
+                if *op == root =>
+            {
+                return Err(TransformError::RootOperation);
+            }
+            Edit::MoveNode { new_path, .. } if *new_path == root => {
+                return Err(TransformError::RootOperation);
+            }
+            _ => {}
+        }
+
+        match edit {
+            Edit::InsertNode { path: op }
+                if (*op == *path || op.ends_before(path) || op.is_ancestor(path)) =>
+            {
+                path.add(op.level(), 1)?;
+            }
+            Edit::RemoveNode { path: op } => {
+                if *op == *path || op.is_ancestor(path) {
+                    return Ok(false);
+                } else if op.ends_before(path) {
+                    path.sub(op.level(), 1)?;
+                }
+            }
+            Edit::MergeNode { path: op, position } => {
+                if *op == *path || op.ends_before(path) {
+                    path.sub(op.level(), 1)?;
+                } else if op.is_ancestor(path) {
+                    path.sub(op.level(), 1)?;
+                    path.add(op.0.len(), position)?;
+                }
+            }
+            Edit::SplitNode { path: op, position } => {
+                if *op == *path {
+                    if matches!(affinity, Affinity::Forward) {
+                        path.add(op.level(), 1)?;
+                    } else if matches!(affinity, Affinity::Backward) {
+                        // Nothing, because it still refers to the right path.
+                    } else {
+                        return Ok(false);
+                    }
+                } else if op.ends_before(path) {
+                    path.add(op.level(), 1)?;
+                } else if op.is_ancestor(path) && path.0[op.0.len()] >= position {
+                    path.add(op.level(), 1)?;
+                    path.sub(op.0.len(), position)?;
+                }
+            }
+            Edit::MoveNode {
+                path: op,
+                new_path: onp,
+            } => {
+                // If the old and new path are the same, it's a no-op.
+                if op == onp {
+                    return Ok(true);
+                }
+                let (level, new_level) = (op.level(), onp.level());
+
+                if op.is_ancestor(path) || *op == *path {
+                    // Swap the moved node's path for its new one, keeping the
+                    // rest of the path below it.
+                    path.0.splice(..op.0.len(), onp.0.iter().copied());
+
+                    if op.ends_before(onp) && op.0.len() < onp.0.len() {
+                        path.sub(level, 1)?;
+                    }
+                } else if op.is_sibling(onp) && (onp.is_ancestor(path) || *onp == *path) {
+                    if op.ends_before(path) {
+                        path.sub(level, 1)?;
+                    } else {
+                        path.add(level, 1)?;
+                    }
+                } else if onp.ends_before(path) || *onp == *path || onp.is_ancestor(path) {
+                    if op.ends_before(path) {
+                        path.sub(level, 1)?;
+                    }
+
+                    path.add(new_level, 1)?;
+                } else if op.ends_before(path) {
+                    if *onp == *path {
+                        path.add(new_level, 1)?;
+                    }
+
+                    path.sub(level, 1)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    /// The level of the last index of a path that isn't the root.
+    fn level(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    fn add(&mut self, level: usize, n: usize) -> Result<(), TransformError> {
+        match self.0[level].checked_add(n) {
+            Some(index) => self.0[level] = index,
+            None => return Err(TransformError::IndexOverflow(self.clone())),
+        }
+        Ok(())
+    }
+
+    fn sub(&mut self, level: usize, n: usize) -> Result<(), TransformError> {
+        match self.0[level].checked_sub(n) {
+            Some(index) => self.0[level] = index,
+            None => return Err(TransformError::IndexUnderflow(self.clone())),
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Path {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = min(self.0.len(), other.0.len());
+        for i in 0..len {
+            let cmp = self.0[i].cmp(&other.0[i]);
+            if cmp != Ordering::Equal {
+                return cmp;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl PartialOrd for Path {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_through_edits() {
+        let (first, second) = (Path(vec![0]), Path(vec![1, 1]));
+        let edits = [
+            Edit::InsertNode { path: &first },
+            Edit::MergeNode {
+                path: &second,
+                position: 2,
+            },
+            Edit::None,
+        ];
+        assert_eq!(
+            Path(vec![0, 1, 3]).transform_through(&edits, Affinity::Forward),
+            Ok(Some(Path(vec![1, 0, 5])))
+        );
+        assert_eq!(
+            Path(vec![0, 1, 3]).transform_through(&edits[..1], Affinity::Forward),
+            Ok(Some(Path(vec![1, 1, 3])))
+        );
+        assert_eq!(
+            Path::transform(
+                &Path(vec![0]),
+                &Edit::RemoveNode { path: &first },
+                Affinity::Forward
+            ),
+            Ok(None)
+        );
+        assert_eq!(
+            Path::transform(
+                &first,
+                &Edit::InsertNode {
+                    path: &Path(vec![])
+                },
+                Affinity::Forward
+            ),
+            Err(TransformError::RootOperation)
+        );
+    }
+}
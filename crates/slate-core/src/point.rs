@@ -0,0 +1,148 @@
+use core::cmp::{min, Ord, Ordering};
+
+use crate::edit::{AsEdit, Edit};
+use crate::path::{Affinity, Path};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Point {
+    pub path: Path,
+    pub offset: usize,
+}
+
+impl Ord for Point {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let result = self.path.cmp(&other.path);
+        if result == Ordering::Equal {
+            return self.offset.cmp(&other.offset);
+        }
+        result
+    }
+}
+
+impl PartialOrd for Point {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Point {
+    pub fn is_after(&self, another: &Point) -> bool {
+        self > another
+    }
+
+    pub fn is_before(&self, another: &Point) -> bool {
+        self < another
+    }
+
+    /// Transform a point through an operation, as Slate.js does. Returns
+    /// `None` if the operation removes the point or couldn't have been
+    /// applied to a document containing it.
+    ///
+    /// The affinity decides where a point goes when text is inserted or a
+    /// node is split exactly at it: `Forward` moves it after the inserted text
+    /// or into the new node, `Backward` leaves it where it is, and `None`
+    /// leaves it before inserted text but removes it at a split.
+    pub fn transform<O: AsEdit + ?Sized>(
+        point: &Point,
+        op: &O,
+        affinity: Affinity,
+    ) -> Option<Point> {
+        let Point { path, offset } = point;
+        let offset = *offset;
+        let mut point = point.clone();
+
+        match op.as_edit() {
+            Edit::InsertNode { .. } | Edit::MoveNode { .. } => {
+                point.path = Path::transform(path, op, affinity).ok()??;
+            }
+            Edit::InsertText {
+                path: op_path,
+                offset: op_offset,
+                len,
+            } if op_path == path
+                && (op_offset < offset
+                    || (op_offset == offset && affinity == Affinity::Forward)) =>
+            {
+                point.offset += len;
+            }
+            Edit::MergeNode {
+                path: op_path,
+                position,
+            } => {
+                if op_path == path {
+                    point.offset += position;
+                }
+                point.path = Path::transform(path, op, affinity).ok()??;
+            }
+            Edit::RemoveText {
+                path: op_path,
+                offset: op_offset,
+                len,
+            } if op_path == path && op_offset <= offset => {
+                point.offset -= min(offset - op_offset, len);
+            }
+            Edit::RemoveNode { path: op_path } => {
+                if op_path == path || op_path.is_ancestor(path) {
+                    return None;
+                }
+                point.path = Path::transform(path, op, affinity).ok()??;
+            }
+            Edit::SplitNode {
+                path: op_path,
+                position,
+            } if op_path == path => {
+                if position == offset && affinity == Affinity::None {
+                    return None;
+                } else if position < offset || (position == offset && affinity == Affinity::Forward)
+                {
+                    point.offset -= position;
+                    point.path = Path::transform(path, op, Affinity::Forward).ok()??;
+                }
+            }
+            Edit::SplitNode { .. } => {
+                point.path = Path::transform(path, op, affinity).ok()??;
+            }
+            _ => {}
+        }
+
+        Some(point)
+    }
+
+    /// Transform a point through a sequence of operations applied in order.
+    /// Returns `None` as soon as one of them removes the point.
+    pub fn transform_through<O: AsEdit>(&self, ops: &[O], affinity: Affinity) -> Option<Point> {
+        ops.iter().try_fold(self.clone(), |point, op| {
+            Point::transform(&point, op, affinity)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn transform_through_text_edits() {
+        let path = Path::new(vec![0, 0]);
+        let point = Point {
+            path: path.clone(),
+            offset: 3,
+        };
+        let insert = Edit::InsertText {
+            path: &path,
+            offset: 1,
+            len: 4,
+        };
+        let remove = Edit::RemoveText {
+            path: &path,
+            offset: 0,
+            len: 2,
+        };
+        assert_eq!(
+            point.transform_through(&[insert, remove], Affinity::Forward),
+            Some(Point { path, offset: 5 })
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use crate::edit::AsEdit;
+use crate::path::{self, Path};
+use crate::point::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Affinity {
+    Forward,
+    Backward,
+    Outward,
+    #[default]
+    Inward,
+    None,
+}
+
+impl Affinity {
+    /// Get the affinities of a range's anchor and focus. A collapsed range has
+    /// no inside, so moving inward keeps both points together, moving forward.
+    pub fn point_affinities(self, range: &Range) -> (path::Affinity, path::Affinity) {
+        match self {
+            Affinity::Inward if range.is_collapsed() => {
+                (path::Affinity::Forward, path::Affinity::Forward)
+            }
+            Affinity::Inward => {
+                if range.is_forward() {
+                    (path::Affinity::Forward, path::Affinity::Backward)
+                } else {
+                    (path::Affinity::Backward, path::Affinity::Forward)
+                }
+            }
+            Affinity::Outward => {
+                if range.is_forward() {
+                    (path::Affinity::Backward, path::Affinity::Forward)
+                } else {
+                    (path::Affinity::Forward, path::Affinity::Backward)
+                }
+            }
+            Affinity::Forward => (path::Affinity::Forward, path::Affinity::Forward),
+            Affinity::Backward => (path::Affinity::Backward, path::Affinity::Backward),
+            Affinity::None => (path::Affinity::None, path::Affinity::None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    anchor: Point,
+    focus: Point,
+}
+
+impl Range {
+    pub fn new(anchor: Point, focus: Point) -> Self {
+        Self { anchor, focus }
+    }
+
+    pub fn anchor(&self) -> &Point {
+        &self.anchor
+    }
+
+    pub fn focus(&self) -> &Point {
+        &self.focus
+    }
+
+    pub fn edges(&self, reverse: bool) -> (&Point, &Point) {
+        if self.is_backward() == reverse {
+            (&self.anchor, &self.focus)
+        } else {
+            (&self.focus, &self.anchor)
+        }
+    }
+
+    pub fn includes_path(&self, target: &Path) -> bool {
+        let (start, end) = self.edges(false);
+        let is_after_start = target >= &start.path;
+        let is_before_end = target <= &end.path;
+        is_after_start && is_before_end
+    }
+
+    pub fn includes_point(&self, target: &Point) -> bool {
+        let (start, end) = self.edges(false);
+        let is_after_start = target >= start;
+        let is_before_end = target <= end;
+        is_after_start && is_before_end
+    }
+
+    pub fn includes_range(&self, target: &Range) -> bool {
+        if self.includes_point(&target.anchor) || self.includes_point(&target.focus) {
+            return true;
+        }
+        let (rs, re) = self.edges(false);
+        let (ts, te) = target.edges(false);
+        rs.is_before(ts) && re.is_before(te)
+    }
+
+    pub fn intersection(&self, another: &Range) -> Option<Range> {
+        let (s1, e1) = self.edges(false);
+        let (s2, e2) = another.edges(false);
+        let start = if s1.is_before(s2) { s2 } else { s1 };
+        let end = if e1.is_before(e2) { e1 } else { e2 };
+        if end.is_before(start) {
+            None
+        } else {
+            Some(Range {
+                anchor: start.clone(),
+                focus: end.clone(),
+            })
+        }
+    }
+
+    pub fn is_backward(&self) -> bool {
+        self.anchor.is_after(&self.focus)
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.anchor == self.focus
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        !self.is_collapsed()
+    }
+
+    pub fn is_forward(&self) -> bool {
+        !self.is_backward()
+    }
+
+    pub fn start(&self) -> Point {
+        let (s, _) = self.edges(false);
+        s.clone()
+    }
+
+    pub fn end(&self) -> Point {
+        let (_, e) = self.edges(false);
+        e.clone()
+    }
+
+    pub fn transform<O: AsEdit + ?Sized>(
+        range: &Range,
+        op: &O,
+        affinity: Affinity,
+    ) -> Option<Range> {
+        let (affinity_anchor, affinity_focus) = affinity.point_affinities(range);
+
+        let range = range.clone();
+
+        let anchor = Point::transform(&range.anchor, op, affinity_anchor);
+        let focus = Point::transform(&range.focus, op, affinity_focus);
+
+        Some(Range {
+            anchor: anchor?,
+            focus: focus?,
+        })
+    }
+
+    /// Transform a range through a sequence of operations applied in order,
+    /// like a saved bookmark after loading the operations made since. Returns
+    /// `None` as soon as one of them removes either of its points.
+    pub fn transform_through<O: AsEdit>(&self, ops: &[O], affinity: Affinity) -> Option<Range> {
+        ops.iter().try_fold(self.clone(), |range, op| {
+            Range::transform(&range, op, affinity)
+        })
+    }
+
+    pub fn points(&self) -> (&Point, &Point) {
+        (&self.anchor, &self.focus)
+    }
+}
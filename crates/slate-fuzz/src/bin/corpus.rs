@@ -0,0 +1,31 @@
+//! Write the seed cases into the corpus of each fuzz target, under the
+//! directory given, or `corpus` in the crate's directory, where `cargo fuzz`
+//! looks for it.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use slate_fuzz::corpus;
+
+const TARGETS: &[&str] = &["apply", "transform", "converge"];
+
+fn main() -> io::Result<()> {
+    let dir = env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("corpus"));
+    let seeds = corpus::seeds();
+
+    for target in TARGETS {
+        let dir = dir.join(target);
+        fs::create_dir_all(&dir)?;
+        for (i, case) in seeds.iter().enumerate() {
+            fs::write(dir.join(format!("seed-{}.json", i)), case.to_json())?;
+        }
+    }
+
+    println!("wrote {} seeds to {}", seeds.len(), dir.display());
+    Ok(())
+}
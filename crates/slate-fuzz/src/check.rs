@@ -0,0 +1,336 @@
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use slate::testing;
+use slate::{
+    Affinity, ApplyError, Descendant, Editor, NodeProperties, Operation, Path, Point, Range,
+    RangeAffinity, TransformError,
+};
+
+use crate::generate::{entries, points};
+use crate::Case;
+
+const AFFINITIES: [Affinity; 3] = [Affinity::Forward, Affinity::Backward, Affinity::None];
+const RANGE_AFFINITIES: [RangeAffinity; 5] = [
+    RangeAffinity::Forward,
+    RangeAffinity::Backward,
+    RangeAffinity::Outward,
+    RangeAffinity::Inward,
+    RangeAffinity::None,
+];
+
+/// Ways a case can fail a check. Operations are referred to by their index
+/// in the case's `ops`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    /// Applying or transforming panicked.
+    Panic(String),
+    /// An operation's selection points outside of the document it made.
+    InvalidSelection(usize),
+    /// The inverse of an applied operation was rejected.
+    Inverse(usize, ApplyError),
+    /// Applying the inverses of the operations didn't restore the document.
+    NotRestored,
+    /// Transforming a path of the document through an operation failed.
+    Transform(usize, Path, TransformError),
+    /// A path of the document transformed through an operation isn't in the
+    /// document it made.
+    PathOutside(usize, Path, Path),
+    /// A point of the document transformed through an operation isn't in the
+    /// document it made.
+    PointOutside(usize, Point, Point),
+    /// Applying `ops` then `concurrent` rebased onto them made a different
+    /// document than applying `concurrent` then `ops` rebased onto them.
+    Diverged,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Panic(message) => write!(f, "panicked: {}", message),
+            Failure::InvalidSelection(i) => write!(f, "operation {} left an invalid selection", i),
+            Failure::Inverse(i, e) => write!(f, "inverse of operation {} was rejected: {}", i, e),
+            Failure::NotRestored => write!(f, "inverse operations didn't restore the document"),
+            Failure::Transform(i, path, e) => {
+                write!(f, "transforming {:?} through operation {}: {}", path, i, e)
+            }
+            Failure::PathOutside(i, path, to) => write!(
+                f,
+                "operation {} transformed {:?} to {:?}, outside of the document",
+                i, path, to
+            ),
+            Failure::PointOutside(i, point, to) => write!(
+                f,
+                "operation {} transformed {:?} to {:?}, outside of the document",
+                i, point, to
+            ),
+            Failure::Diverged => write!(f, "concurrent operations diverged"),
+        }
+    }
+}
+
+impl Error for Failure {}
+
+/// Apply a case's operations, skipping those the editor rejects, and then
+/// the inverses of the applied ones in reverse.
+///
+/// Applying must not panic, must leave the selection in the document, and
+/// the inverses must restore it. Operations that don't carry the content
+/// they remove or the properties they replace can't be inverted, so they're
+/// skipped too.
+pub fn check_apply(case: &Case) -> Result<(), Failure> {
+    catch(|| {
+        let mut editor = case.document.clone();
+        let mut applied = vec![];
+
+        for (i, op) in case.ops.iter().enumerate() {
+            if apply(&mut editor, op) {
+                check_selection(&editor, i)?;
+                applied.push((i, op.clone()));
+            }
+        }
+
+        for (i, op) in applied.into_iter().rev() {
+            editor
+                .apply(op.inverse())
+                .map_err(|e| Failure::Inverse(i, e))?;
+        }
+
+        if editor.content_eq(&case.document) {
+            Ok(())
+        } else {
+            Err(Failure::NotRestored)
+        }
+    })
+}
+
+/// Transform every path and point of the document, and its selection,
+/// through each of a case's operations the editor applies, with each
+/// affinity.
+///
+/// Transforming must not panic, and what isn't removed must be in the
+/// document the operation made.
+pub fn check_transform(case: &Case) -> Result<(), Failure> {
+    catch(|| {
+        let mut doc = case.document.clone();
+
+        for (i, op) in case.ops.iter().enumerate() {
+            let mut next = doc.clone();
+            if !apply(&mut next, op) {
+                continue;
+            }
+            check_paths(&doc, &next, op, i)?;
+            check_points(&doc, &next, op, i)?;
+            check_range(&doc, &next, op, i)?;
+            doc = next;
+        }
+
+        Ok(())
+    })
+}
+
+/// Rebase a case's `ops` and `concurrent` onto each other, dropping the
+/// operations the editor rejects first.
+///
+/// Rebasing must not panic, and applying either set followed by the other
+/// rebased onto it must make the same document.
+pub fn check_converge(case: &Case) -> Result<(), Failure> {
+    catch(|| {
+        let ops = applied(&case.document, &case.ops);
+        let concurrent = applied(&case.document, &case.concurrent);
+
+        if testing::converges(&case.document, &ops, &concurrent) {
+            Ok(())
+        } else {
+            Err(Failure::Diverged)
+        }
+    })
+}
+
+fn check_paths(doc: &Editor, next: &Editor, op: &Operation, i: usize) -> Result<(), Failure> {
+    let paths: Vec<_> = entries(next).into_iter().map(|(path, _)| path).collect();
+
+    for (path, _) in entries(doc) {
+        for affinity in AFFINITIES.iter() {
+            match Path::transform(&path, op, *affinity) {
+                Err(e) => return Err(Failure::Transform(i, path, e)),
+                Ok(Some(to)) if !paths.contains(&to) => {
+                    return Err(Failure::PathOutside(i, path, to))
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_points(doc: &Editor, next: &Editor, op: &Operation, i: usize) -> Result<(), Failure> {
+    let after = points(next);
+
+    for point in points(doc) {
+        for affinity in AFFINITIES.iter() {
+            match Point::transform(&point, op, *affinity) {
+                Some(to) if !after.contains(&to) => {
+                    return Err(Failure::PointOutside(i, point, to))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_range(doc: &Editor, next: &Editor, op: &Operation, i: usize) -> Result<(), Failure> {
+    let range = match doc.selection() {
+        Some(range) => range,
+        None => return Ok(()),
+    };
+    let after = points(next);
+
+    for affinity in RANGE_AFFINITIES.iter() {
+        if let Some(to) = Range::transform(range, op, *affinity) {
+            for (point, to) in [(range.anchor(), to.anchor()), (range.focus(), to.focus())].iter() {
+                if !after.contains(to) {
+                    return Err(Failure::PointOutside(i, (*point).clone(), (*to).clone()));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_selection(editor: &Editor, i: usize) -> Result<(), Failure> {
+    let selection = match editor.selection() {
+        Some(selection) => selection,
+        None => return Ok(()),
+    };
+    let points = points(editor);
+
+    if points.contains(selection.anchor()) && points.contains(selection.focus()) {
+        Ok(())
+    } else {
+        Err(Failure::InvalidSelection(i))
+    }
+}
+
+/// Get the operations the editor applies to a document, in order, dropping
+/// the ones it rejects.
+fn applied(doc: &Editor, ops: &[Operation]) -> Vec<Operation> {
+    let mut editor = doc.clone();
+    ops.iter()
+        .filter(|op| apply(&mut editor, op))
+        .cloned()
+        .collect()
+}
+
+/// Apply an operation if it carries what it removes or replaces, returning
+/// whether the editor applied it.
+fn apply(editor: &mut Editor, op: &Operation) -> bool {
+    faithful(editor, op) && editor.apply(op.clone()).is_ok()
+}
+
+/// Check that an operation carries the content it removes and the properties
+/// it replaces, as its inverse needs to restore them, and that a selection it
+/// sets is in the document. Operations that the editor would reject anyway
+/// pass.
+fn faithful(doc: &Editor, op: &Operation) -> bool {
+    match op {
+        Operation::RemoveNode { path, node } => {
+            node_at(doc, path).is_none_or(|n| node.clone().into_descendant().as_ref() == Some(n))
+        }
+        Operation::RemoveText { path, offset, text } => match node_at(doc, path) {
+            Some(Descendant::Text(t)) => t
+                .text()
+                .get(*offset..offset.saturating_add(text.len()))
+                .is_none_or(|removed| removed == text),
+            _ => true,
+        },
+        Operation::MergeNode {
+            path,
+            position,
+            properties,
+        } => {
+            let previous = path.previous().and_then(|p| node_at(doc, &p));
+            match (previous, node_at(doc, path)) {
+                (Some(previous), Some(node)) => {
+                    let len = match previous {
+                        Descendant::Text(t) => t.text().len(),
+                        Descendant::Element(e) => e.num_children(),
+                    };
+                    *position == len && *properties == NodeProperties::between(previous, node)
+                }
+                _ => true,
+            }
+        }
+        Operation::SetNode {
+            path,
+            properties,
+            new_properties,
+        } => node_at(doc, path).is_none_or(|node| *properties == new_properties.current(node)),
+        // The editor takes any selection, but one outside of the document
+        // can't be transformed into it.
+        Operation::SetSelection {
+            new_properties: Some(range),
+            ..
+        } => [range.anchor(), range.focus()].iter().all(|point| {
+            matches!(node_at(doc, &point.path), Some(Descendant::Text(t)) if t.text().is_char_boundary(point.offset))
+        }),
+        _ => true,
+    }
+}
+
+fn node_at<'a>(doc: &'a Editor, path: &Path) -> Option<&'a Descendant> {
+    let (first, rest) = path.as_slice().split_first()?;
+    rest.iter()
+        .try_fold(doc.child(*first)?, |node, i| match node {
+            Descendant::Element(e) => e.child(*i),
+            Descendant::Text(_) => None,
+        })
+}
+
+/// Run a check, turning a panic into a failure.
+fn catch(check: impl FnOnce() -> Result<(), Failure>) -> Result<(), Failure> {
+    panic::catch_unwind(AssertUnwindSafe(check)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(Failure::Panic(message))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use slate::{Element, Text};
+
+    use super::*;
+
+    fn remove_text(path: Vec<usize>, offset: usize, text: &str) -> Operation {
+        Operation::RemoveText {
+            path: Path::new(path),
+            offset,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn skip_rejected_and_unfaithful_ops() {
+        let document = Editor::new().add_child(Element::new().add_child(Text::new("abc")));
+        let case = Case::new(document.clone()).ops(vec![
+            remove_text(vec![0, 0], 0, "x"),
+            remove_text(vec![0, 1], 0, "a"),
+            remove_text(vec![0, 0], 1, "b"),
+        ]);
+
+        assert!(!faithful(&document, &case.ops[0]));
+        assert_eq!(applied(&document, &case.ops), &case.ops[2..]);
+        assert_eq!(check_apply(&case), Ok(()));
+        assert_eq!(check_transform(&case), Ok(()));
+        assert_eq!(check_converge(&case), Ok(()));
+    }
+}
@@ -0,0 +1,133 @@
+//! Seed cases for the fuzz targets, recorded from editing sessions that use
+//! the editor's own transforms, so that fuzzing starts from the operations
+//! real edits make rather than from ones it would take long to find.
+//!
+//! Each seed records two sessions editing the same document at the same
+//! time, one as the case's `ops` and the other as its `concurrent`.
+
+use slate::testing::{Key, Session};
+use slate::{Editor, Element, Marks, Operation, Path, Point, Range, Text};
+
+use crate::Case;
+
+/// Get the seed cases.
+pub fn seeds() -> Vec<Case> {
+    let two_blocks = || {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("hello world")))
+            .add_child(
+                Element::new()
+                    .add_child(Text::with_marks("bold", Marks::BOLD))
+                    .add_child(Text::new(" and plain")),
+            )
+    };
+    let list = || {
+        let item = |text| Element::with_kind("list-item").add_child(Text::new(text));
+        Editor::new().add_child(
+            Element::with_kind("bulleted-list")
+                .add_child(item("one"))
+                .add_child(item("two")),
+        )
+    };
+
+    vec![
+        seed(
+            Editor::with_default(),
+            |s| {
+                s.type_text("hello\nworld")
+                    .press(Key::Home)
+                    .press(Key::Backspace);
+            },
+            |s| {
+                s.type_text("ab");
+            },
+        ),
+        seed(
+            two_blocks(),
+            |s| {
+                s.select(Path::new(vec![0])).shortcut("Mod-b");
+            },
+            |s| {
+                s.select(point(vec![0, 0], 5))
+                    .type_text(",")
+                    .press(Key::Delete);
+            },
+        ),
+        seed(
+            two_blocks(),
+            |s| {
+                s.select(Range::new(point(vec![0, 0], 6), point(vec![1, 0], 2)))
+                    .press(Key::Backspace);
+            },
+            |s| {
+                s.select(point(vec![1, 1], 0)).press(Key::Enter);
+            },
+        ),
+        seed(
+            two_blocks(),
+            |s| {
+                s.select(point(vec![1, 1], 4))
+                    .paste("<p>a <em>b</em></p><p>c</p>");
+            },
+            |s| {
+                s.select(Path::new(vec![1])).cut();
+            },
+        ),
+        seed(
+            list(),
+            |s| {
+                s.select(point(vec![0, 1, 0], 3))
+                    .press(Key::Tab)
+                    .press(Key::Enter)
+                    .type_text("three");
+            },
+            |s| {
+                s.select(point(vec![0, 1, 0], 0)).press(Key::Backspace);
+            },
+        ),
+    ]
+}
+
+/// Record two sessions editing a document.
+fn seed(
+    document: Editor,
+    ops: impl FnOnce(&mut Session),
+    concurrent: impl FnOnce(&mut Session),
+) -> Case {
+    let (start, ops) = record(&document, ops);
+    let (_, concurrent) = record(&document, concurrent);
+    Case::new(start).ops(ops).concurrent(concurrent)
+}
+
+/// Record a session editing a document, returning the document it started
+/// from, with the caret placed, and the operations it made.
+fn record(document: &Editor, edit: impl FnOnce(&mut Session)) -> (Editor, Vec<Operation>) {
+    let mut session = Session::new(document.clone());
+    let start = session.editor().clone();
+    edit(&mut session);
+    (start, session.history().batches().concat())
+}
+
+fn point(path: Vec<usize>, offset: usize) -> Point {
+    Point {
+        path: Path::new(path),
+        offset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{check_apply, check_converge, check_transform};
+
+    use super::*;
+
+    #[test]
+    fn seeds_pass() {
+        for case in seeds() {
+            assert!(!case.ops.is_empty() && !case.concurrent.is_empty());
+            assert_eq!(check_apply(&case), Ok(()), "{}", case.to_json());
+            assert_eq!(check_transform(&case), Ok(()), "{}", case.to_json());
+            assert_eq!(check_converge(&case), Ok(()), "{}", case.to_json());
+        }
+    }
+}
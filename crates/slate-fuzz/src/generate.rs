@@ -0,0 +1,320 @@
+//! Generating cases from a fuzzer's bytes.
+//!
+//! Operations are mostly made against the nodes and offsets of the document
+//! they're applied to, with the content they remove and the properties they
+//! replace, as the editor's own transforms make them, so that they get past
+//! `Editor::apply`'s checks. Some are made wild, with their paths and offsets
+//! bumped, to reach the paths that reject them too.
+
+use arbitrary::{Result, Unstructured};
+use slate::{
+    Descendant, Editor, Element, Marks, NodeProperties, Operation, Path, Point, Range, Text,
+};
+
+use crate::Case;
+
+const KINDS: &[&str] = &["paragraph", "heading", "quote"];
+const TEXTS: &[&str] = &["", "a", "bc", " d", "é", "日本", "👍🏽", "\u{200f}"];
+const MARKS: &[Marks] = &[Marks::BOLD, Marks::ITALIC, Marks::UNDERLINE, Marks::CODE];
+const MAX_OPS: usize = 8;
+
+pub(crate) fn case(u: &mut Unstructured) -> Result<Case> {
+    let document = document(u)?;
+
+    let mut ops = vec![];
+    let mut after = document.clone();
+    for _ in 0..u.int_in_range(0..=MAX_OPS)? {
+        let op = operation(u, &after)?;
+        // Rejected operations are kept, since rejecting them is checked too,
+        // but the next ones are made against the document as it is.
+        let _ = after.apply(op.clone());
+        ops.push(op);
+    }
+
+    let mut concurrent = vec![];
+    let mut other = document.clone();
+    for _ in 0..u.int_in_range(0..=MAX_OPS / 2)? {
+        let op = operation(u, &other)?;
+        let _ = other.apply(op.clone());
+        concurrent.push(op);
+    }
+
+    Ok(Case {
+        document,
+        ops,
+        concurrent,
+    })
+}
+
+fn document(u: &mut Unstructured) -> Result<Editor> {
+    let mut editor = Editor::new();
+    for _ in 0..u.int_in_range(1..=3)? {
+        editor = editor.add_child(element(u, 0)?);
+    }
+
+    let points = points(&editor);
+    if u.ratio(3, 4)? {
+        let anchor = u.choose(&points)?.clone();
+        let focus = u.choose(&points)?.clone();
+        editor.select(Range::new(anchor, focus));
+    }
+    editor.flush();
+
+    Ok(editor)
+}
+
+/// Make a block holding either text or, near the top of the document, other
+/// blocks, like the items of a list.
+fn element(u: &mut Unstructured, depth: usize) -> Result<Element> {
+    let mut element = Element::with_kind(*u.choose(KINDS)?);
+    if u.ratio(1, 4)? {
+        element = element.add_property("align", "center");
+    }
+
+    if depth < 2 && u.ratio(1, 4)? {
+        for _ in 0..u.int_in_range(1..=2)? {
+            element = element.add_child(element_of(u, depth + 1)?);
+        }
+    } else {
+        for _ in 0..u.int_in_range(1..=3)? {
+            element = element.add_child(text(u)?);
+        }
+    }
+
+    Ok(element)
+}
+
+fn element_of(u: &mut Unstructured, depth: usize) -> Result<Descendant> {
+    Ok(Descendant::Element(element(u, depth)?))
+}
+
+fn text(u: &mut Unstructured) -> Result<Text> {
+    Ok(Text::with_marks(*u.choose(TEXTS)?, marks(u)?))
+}
+
+fn marks(u: &mut Unstructured) -> Result<Marks> {
+    let mut marks = Marks::empty();
+    for mark in MARKS {
+        if u.ratio(1, 3)? {
+            marks |= *mark;
+        }
+    }
+    Ok(marks)
+}
+
+/// Make an operation against a document.
+fn operation(u: &mut Unstructured, doc: &Editor) -> Result<Operation> {
+    let entries = entries(doc);
+    let (path, node) = u.choose(&entries)?.clone();
+
+    let op = match u.int_in_range(0..=8)? {
+        0 => match &node {
+            Descendant::Text(t) => Operation::InsertText {
+                path,
+                offset: *u.choose(&boundaries(t.text()))?,
+                text: u.choose(TEXTS)?.to_string(),
+            },
+            Descendant::Element(_) => insert_node(u, path, node)?,
+        },
+        1 => match &node {
+            Descendant::Text(t) => {
+                let boundaries = boundaries(t.text());
+                let start = *u.choose(&boundaries)?;
+                let end = *u.choose(&boundaries)?;
+                let (start, end) = (start.min(end), start.max(end));
+                Operation::RemoveText {
+                    path,
+                    offset: start,
+                    text: t.text()[start..end].to_string(),
+                }
+            }
+            Descendant::Element(_) => Operation::RemoveNode {
+                path,
+                node: node.into(),
+            },
+        },
+        2 => insert_node(u, path, node)?,
+        3 => Operation::RemoveNode {
+            path,
+            node: node.into(),
+        },
+        4 => Operation::SplitNode {
+            position: match &node {
+                Descendant::Text(t) => *u.choose(&boundaries(t.text()))?,
+                Descendant::Element(e) => u.int_in_range(0..=e.num_children())?,
+            },
+            path,
+            properties: NodeProperties::new(),
+        },
+        5 => {
+            let previous = path
+                .previous()
+                .and_then(|p| entries.iter().find(|(q, _)| *q == p));
+            match previous {
+                Some((_, previous)) => Operation::MergeNode {
+                    position: match previous {
+                        Descendant::Text(t) => t.text().len(),
+                        Descendant::Element(e) => e.num_children(),
+                    },
+                    properties: NodeProperties::between(previous, &node),
+                    path,
+                },
+                None => insert_node(u, path, node)?,
+            }
+        }
+        6 => {
+            let (to, _) = u.choose(&entries)?;
+            let new_path = match to.next() {
+                Some(next) if u.arbitrary()? => next,
+                _ => to.clone(),
+            };
+            Operation::MoveNode { path, new_path }
+        }
+        7 => {
+            let new_properties = match &node {
+                Descendant::Text(_) => NodeProperties::new().marks(marks(u)?),
+                Descendant::Element(_) if u.arbitrary()? => {
+                    NodeProperties::new().kind(*u.choose(KINDS)?)
+                }
+                Descendant::Element(_) => NodeProperties::new()
+                    .property("align", u.choose(&[None, Some("right")])?.as_deref()),
+            };
+            Operation::SetNode {
+                path,
+                properties: new_properties.current(&node),
+                new_properties,
+            }
+        }
+        _ => {
+            let points = points(doc);
+            let new_properties = if points.is_empty() || u.ratio(1, 4)? {
+                None
+            } else {
+                Some(Range::new(
+                    u.choose(&points)?.clone(),
+                    u.choose(&points)?.clone(),
+                ))
+            };
+            Operation::SetSelection {
+                path: Path::new(vec![]),
+                properties: doc.selection().cloned(),
+                new_properties,
+            }
+        }
+    };
+
+    if u.ratio(1, 8)? {
+        wild(u, &entries, op)
+    } else {
+        Ok(op)
+    }
+}
+
+/// Insert a copy of a node before or after it, so that it's of the kind its
+/// siblings are.
+fn insert_node(u: &mut Unstructured, path: Path, node: Descendant) -> Result<Operation> {
+    let path = match path.next() {
+        Some(next) if u.arbitrary()? => next,
+        _ => path,
+    };
+    Ok(Operation::InsertNode {
+        path,
+        node: node.into(),
+    })
+}
+
+/// Move an operation's path or offset past the end of what the document
+/// holds there, so that applying it is rejected. Operations that would still
+/// apply could remove content other than what they carry, and then their
+/// inverses wouldn't restore the document.
+fn wild(
+    u: &mut Unstructured,
+    entries: &[(Path, Descendant)],
+    mut op: Operation,
+) -> Result<Operation> {
+    let bump: usize = u.int_in_range(1..=3)?;
+
+    match &mut op {
+        Operation::InsertText { path, offset, .. }
+        | Operation::RemoveText { path, offset, .. }
+        | Operation::SplitNode {
+            path,
+            position: offset,
+            ..
+        } if u.arbitrary()? => {
+            let len = match entries.iter().find(|(p, _)| p == path) {
+                Some((_, Descendant::Text(t))) => t.text().len(),
+                Some((_, Descendant::Element(e))) => e.num_children(),
+                None => 0,
+            };
+            *offset = len + bump;
+        }
+        Operation::InsertNode { path, .. }
+        | Operation::InsertText { path, .. }
+        | Operation::MergeNode { path, .. }
+        | Operation::MoveNode { path, .. }
+        | Operation::RemoveNode { path, .. }
+        | Operation::RemoveText { path, .. }
+        | Operation::SetNode { path, .. }
+        | Operation::SplitNode { path, .. } => {
+            let parent = path.parent().unwrap_or_else(|| Path::new(vec![]));
+            let siblings = entries
+                .iter()
+                .filter(|(p, _)| p.parent().as_ref() == Some(&parent))
+                .count();
+            let mut indices = parent.as_slice().to_vec();
+            indices.push(siblings + bump);
+            *path = Path::new(indices);
+        }
+        Operation::SetSelection { .. } => {}
+    }
+
+    Ok(op)
+}
+
+/// Get every node of a document with its path, in document order.
+pub(crate) fn entries(doc: &Editor) -> Vec<(Path, Descendant)> {
+    fn walk(children: Vec<Descendant>, parent: &[usize], out: &mut Vec<(Path, Descendant)>) {
+        for (i, child) in children.into_iter().enumerate() {
+            let mut path = parent.to_vec();
+            path.push(i);
+            let grandchildren = match &child {
+                Descendant::Element(e) => e.children(),
+                Descendant::Text(_) => vec![],
+            };
+            out.push((Path::new(path.clone()), child));
+            walk(grandchildren, &path, out);
+        }
+    }
+
+    let mut out = vec![];
+    walk(doc.children(), &[], &mut out);
+    out
+}
+
+/// Get every point of a document: each offset in each of its texts that
+/// isn't inside a character.
+pub(crate) fn points(doc: &Editor) -> Vec<Point> {
+    entries(doc)
+        .into_iter()
+        .filter_map(|(path, node)| match node {
+            Descendant::Text(t) => Some((path, t)),
+            Descendant::Element(_) => None,
+        })
+        .flat_map(|(path, text)| {
+            boundaries(text.text())
+                .into_iter()
+                .map(move |offset| Point {
+                    path: path.clone(),
+                    offset,
+                })
+        })
+        .collect()
+}
+
+fn boundaries(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
@@ -0,0 +1,171 @@
+//! Fuzzing for applying operations and transforming positions through them.
+//!
+//! A [`Case`] is a document with operations made against it. Fuzz targets
+//! decode one from their input, either as the JSON a case is saved as or by
+//! generating it from the input's bytes, and run one of the checks on it:
+//!
+//! - [`check_apply`] applies the operations one by one and then their
+//!   inverses, which must not panic and must restore the document.
+//! - [`check_transform`] transforms every path and point of the document,
+//!   and its selection, through each operation, which must leave them in the
+//!   document the operation makes.
+//! - [`check_converge`] rebases concurrent operations onto each other, which
+//!   must converge.
+//!
+//! A failing case is shrunk by [`shrink`] to a minimal one before it's
+//! reported, as JSON that can be replayed or saved as a regression test. The
+//! [`corpus`] module builds seed cases from real editing sessions.
+//!
+//! The targets, `apply`, `transform` and `converge`, are run on a nightly
+//! toolchain with `cargo fuzz run --fuzz-dir crates/slate-fuzz --features
+//! libfuzzer <target>`, after building a corpus with `cargo run -p slate-fuzz
+//! --bin corpus`.
+
+use std::mem;
+
+use serde_json::{json, Value};
+use slate::serialize::json as slate_json;
+use slate::{Editor, Operation};
+
+mod check;
+pub mod corpus;
+mod generate;
+mod shrink;
+
+pub use check::{check_apply, check_converge, check_transform, Failure};
+pub use shrink::shrink;
+
+/// A document and operations made against it.
+///
+/// `ops` are applied in order, each to the document the previous one made.
+/// `concurrent` are made against the same document at the same time, as by
+/// a collaborator, and only rebased onto `ops`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    pub document: Editor,
+    pub ops: Vec<Operation>,
+    pub concurrent: Vec<Operation>,
+}
+
+impl Case {
+    pub fn new(document: Editor) -> Self {
+        Self {
+            document,
+            ops: vec![],
+            concurrent: vec![],
+        }
+    }
+
+    pub fn ops(mut self, ops: Vec<Operation>) -> Self {
+        self.ops = ops;
+        self
+    }
+
+    pub fn concurrent(mut self, concurrent: Vec<Operation>) -> Self {
+        self.concurrent = concurrent;
+        self
+    }
+
+    /// Decode a fuzzer's input: a case saved as JSON, or otherwise one
+    /// generated from its bytes.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        std::str::from_utf8(data)
+            .ok()
+            .and_then(Self::from_json)
+            .or_else(|| generate::case(&mut arbitrary::Unstructured::new(data)).ok())
+    }
+
+    /// Serialize a case to JSON, with the document as `serialize::json`
+    /// writes it and the operations in the shape of Slate.js operations.
+    pub fn to_json(&self) -> String {
+        let document: Value =
+            serde_json::from_str(&slate_json::to_string(&self.document)).unwrap_or(Value::Null);
+        let ops = |ops: &[Operation]| {
+            ops.iter()
+                .map(slate_json::operation_to_value)
+                .collect::<Vec<_>>()
+        };
+
+        let case = json!({
+            "document": document,
+            "ops": ops(&self.ops),
+            "concurrent": ops(&self.concurrent),
+        });
+        serde_json::to_string_pretty(&case).unwrap_or_default()
+    }
+
+    /// Deserialize a case from JSON. The document is imported leniently, and
+    /// operations that can't be read are dropped, so that fuzzers mutating
+    /// saved cases still get one.
+    pub fn from_json(json: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        let (document, _) = slate_json::from_string_lossy(&value.get("document")?.to_string());
+        let ops = |key: &str| -> Vec<Operation> {
+            value
+                .get(key)
+                .and_then(Value::as_array)
+                .map(|ops| {
+                    ops.iter()
+                        .filter_map(slate_json::operation_from_value)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        Some(Self {
+            document,
+            ops: ops("ops"),
+            concurrent: ops("concurrent"),
+        })
+    }
+}
+
+/// Run a check on a fuzzer's input. If it fails, the case is shrunk to a
+/// minimal one failing the same way, and this panics with the failure and
+/// the shrunk case, for the fuzzer to report.
+pub fn fuzz(data: &[u8], check: fn(&Case) -> Result<(), Failure>) {
+    let case = match Case::decode(data) {
+        Some(case) => case,
+        None => return,
+    };
+    let failure = match check(&case) {
+        Ok(()) => return,
+        Err(failure) => failure,
+    };
+
+    let kind = mem::discriminant(&failure);
+    let fails_alike = |case: &Case| check(case).err().map(|f| mem::discriminant(&f)) == Some(kind);
+    let case = shrink(case, fails_alike);
+    let failure = check(&case).err().unwrap_or(failure);
+    panic!("{}\n{}", failure, case.to_json());
+}
+
+#[cfg(test)]
+mod tests {
+    use slate::{Element, Path, Text};
+
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let document = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        let case = Case::new(document).ops(vec![Operation::InsertText {
+            path: Path::new(vec![0, 0]),
+            offset: 1,
+            text: "x".into(),
+        }]);
+
+        let decoded = Case::decode(case.to_json().as_bytes()).unwrap();
+        assert!(decoded.document.content_eq(&case.document));
+        assert_eq!(decoded.ops, case.ops);
+        assert!(decoded.concurrent.is_empty());
+    }
+
+    #[test]
+    fn generate_from_bytes() {
+        let data: Vec<u8> = (0..=255).collect();
+        let case = Case::decode(&data).unwrap();
+        assert!(case.document.has_children());
+        assert_eq!(Case::decode(&data), Some(case));
+    }
+}
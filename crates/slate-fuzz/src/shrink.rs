@@ -0,0 +1,161 @@
+use std::panic;
+
+use slate::{Descendant, Operation, Path};
+
+use crate::generate::entries;
+use crate::Case;
+
+/// Shrink a case for which `fails` holds to a smaller one for which it still
+/// does, by dropping operations, the selection, nodes of the document and
+/// characters of its texts until none can be dropped.
+///
+/// Content is dropped from the document through operations, and the case's
+/// operations are rebased onto them, so that they still point at the same
+/// nodes where those remain. Panic messages are silenced while shrinking,
+/// since `fails` is expected to panic.
+pub fn shrink(mut case: Case, mut fails: impl FnMut(&Case) -> bool) -> Case {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    while let Some(smaller) = smaller(&case).into_iter().find(|c| fails(c)) {
+        case = smaller;
+    }
+
+    panic::set_hook(hook);
+    case
+}
+
+/// Get the cases one step smaller than a case, the ones dropping the most
+/// first.
+fn smaller(case: &Case) -> Vec<Case> {
+    let mut out = vec![];
+
+    for i in 0..case.ops.len() {
+        let mut ops = case.ops.clone();
+        ops.remove(i);
+        out.push(case.clone().ops(ops));
+    }
+    for i in 0..case.concurrent.len() {
+        let mut concurrent = case.concurrent.clone();
+        concurrent.remove(i);
+        out.push(case.clone().concurrent(concurrent));
+    }
+
+    if let Some(selection) = case.document.selection() {
+        out.extend(edit(
+            case,
+            Operation::SetSelection {
+                path: Path::new(vec![]),
+                properties: Some(selection.clone()),
+                new_properties: None,
+            },
+        ));
+    }
+
+    let entries = entries(&case.document);
+    for (path, node) in &entries {
+        // Elements keep a child, as the editor expects them to, but the
+        // document can be left empty.
+        let parent = path.parent();
+        let siblings = entries.iter().filter(|(p, _)| p.parent() == parent).count();
+        if siblings > 1 || parent.is_some_and(|p| p.as_slice().is_empty()) {
+            out.extend(edit(
+                case,
+                Operation::RemoveNode {
+                    path: path.clone(),
+                    node: node.clone().into(),
+                },
+            ));
+        }
+    }
+
+    for (path, node) in &entries {
+        let text = match node {
+            Descendant::Text(t) if !t.text().is_empty() => t.text(),
+            _ => continue,
+        };
+        let last = text.char_indices().last().map_or(0, |(i, _)| i);
+        for offset in [0, last].iter() {
+            out.extend(edit(
+                case,
+                Operation::RemoveText {
+                    path: path.clone(),
+                    offset: *offset,
+                    text: text[*offset..].to_string(),
+                },
+            ));
+        }
+    }
+
+    out
+}
+
+/// Edit a case's document, rebasing its operations onto the edit.
+fn edit(case: &Case, edit: Operation) -> Option<Case> {
+    let mut document = case.document.clone();
+    document.apply(edit.clone()).ok()?;
+    document.flush();
+
+    let edit = [edit];
+    let (_, ops) = Operation::transform_all(&edit, &case.ops);
+    let (_, concurrent) = Operation::transform_all(&edit, &case.concurrent);
+
+    Some(Case {
+        document,
+        ops,
+        concurrent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use slate::{Editor, Element, Text};
+
+    use super::*;
+
+    #[test]
+    fn shrink_to_failing_content() {
+        let document = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("ab"))
+                    .add_child(Text::new("cd")),
+            )
+            .add_child(Element::new().add_child(Text::new("ef")));
+        let case = Case::new(document).ops(vec![
+            Operation::InsertText {
+                path: Path::new(vec![1, 0]),
+                offset: 0,
+                text: "x".into(),
+            },
+            Operation::InsertText {
+                path: Path::new(vec![0, 1]),
+                offset: 1,
+                text: "!".into(),
+            },
+        ]);
+
+        // Fails while "!" ends up after a "c".
+        let shrunk = shrink(case, |case| {
+            let mut editor = case.document.clone();
+            for op in &case.ops {
+                let _ = editor.apply(op.clone());
+            }
+            entries(&editor)
+                .iter()
+                .any(|(_, node)| matches!(node, Descendant::Text(t) if t.text().contains("c!")))
+        });
+
+        assert!(shrunk
+            .document
+            .content_eq(&Editor::new().add_child(Element::new().add_child(Text::new("c")))));
+        assert_eq!(
+            shrunk.ops,
+            vec![Operation::InsertText {
+                path: Path::new(vec![0, 0]),
+                offset: 1,
+                text: "!".into(),
+            }]
+        );
+    }
+}
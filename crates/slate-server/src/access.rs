@@ -0,0 +1,101 @@
+//! Who the server's clients are, and what they may do.
+
+use std::fmt;
+use std::sync::Arc;
+
+use slate::Policy;
+use tokio_tungstenite::tungstenite::handshake::server::{
+    Callback, ErrorResponse, Request, Response,
+};
+use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+
+/// Tells who a client is from the token it connects with, sent as a bearer
+/// token in the `Authorization` header of its WebSocket handshake.
+pub trait Authenticator: Send + Sync {
+    /// Get the id of the user a token was issued to, or `None` to refuse the
+    /// connection.
+    fn authenticate(&self, token: &str) -> Option<String>;
+}
+
+impl<F> Authenticator for F
+where
+    F: Fn(&str) -> Option<String> + Send + Sync,
+{
+    fn authenticate(&self, token: &str) -> Option<String> {
+        self(token)
+    }
+}
+
+/// Who may connect to the server, and what they may do to the documents
+/// they join.
+///
+/// Without an authenticator, every client is anonymous, with an empty id.
+/// The operations clients submit are authorized by the policy as the users
+/// they connected as, and are all allowed without one.
+#[derive(Clone, Default)]
+pub struct Access {
+    pub authenticator: Option<Arc<dyn Authenticator>>,
+    pub policy: Option<Arc<dyn Policy>>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn authenticator(mut self, authenticator: impl Authenticator + 'static) -> Self {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    pub fn policy(mut self, policy: impl Policy + 'static) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Get the user a handshake request connects as, or `None` if it isn't
+    /// allowed to connect.
+    fn user(&self, request: &Request) -> Option<String> {
+        let authenticator = match &self.authenticator {
+            Some(authenticator) => authenticator,
+            None => return Some(String::new()),
+        };
+        let token = request
+            .headers()
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        authenticator.authenticate(token)
+    }
+}
+
+impl fmt::Debug for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Access")
+            .field("authenticator", &self.authenticator.is_some())
+            .field("policy", &self.policy.is_some())
+            .finish()
+    }
+}
+
+/// The handshake of a connecting client, which finds out who it is and
+/// refuses it with `401 Unauthorized` if it isn't allowed to connect.
+pub(crate) struct Handshake<'a> {
+    pub(crate) access: &'a Access,
+    pub(crate) user: &'a mut Option<String>,
+}
+
+impl Callback for Handshake<'_> {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        *self.user = self.access.user(request);
+        if self.user.is_some() {
+            return Ok(response);
+        }
+
+        let mut refusal = ErrorResponse::new(Some("unauthorized".into()));
+        *refusal.status_mut() = StatusCode::UNAUTHORIZED;
+        Err(refusal)
+    }
+}
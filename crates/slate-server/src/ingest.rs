@@ -0,0 +1,186 @@
+//! How the server takes in the operations clients submit.
+
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use slate::MarkRegistry;
+
+/// How the server batches and limits submitted operations.
+///
+/// Submissions to a document wait to be sequenced together, until enough
+/// operations are waiting or the first has waited long enough, so that each
+/// batch is stored with one call to the store. By default each submission is
+/// sequenced on its own, right away, and clients aren't limited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestOptions {
+    /// Sequence the waiting submissions once they hold this many operations.
+    pub max_batch: usize,
+    /// Sequence the waiting submissions once the first has waited this long.
+    pub max_delay: Duration,
+    /// How fast each client may submit operations.
+    pub rate_limit: Option<RateLimit>,
+    /// The names of the marks in messages, which clients need to share.
+    pub mark_registry: MarkRegistry,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            max_batch: 1,
+            max_delay: Duration::from_millis(0),
+            rate_limit: None,
+            mark_registry: MarkRegistry::default(),
+        }
+    }
+}
+
+impl IngestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_batch(mut self, max_batch: usize) -> Self {
+        self.max_batch = max_batch;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    pub fn mark_registry(mut self, registry: MarkRegistry) -> Self {
+        self.mark_registry = registry;
+        self
+    }
+}
+
+/// Errors returned when a rate limit wouldn't let any operation through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitError {
+    /// The rate isn't a positive, finite number of operations per second.
+    Rate(f64),
+    /// The burst is zero.
+    Burst,
+}
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateLimitError::Rate(rate) => write!(f, "invalid rate of {} operations a second", rate),
+            RateLimitError::Burst => write!(f, "a burst must allow at least one operation"),
+        }
+    }
+}
+
+impl Error for RateLimitError {}
+
+/// How many operations a client may submit: `burst` at once, refilled at
+/// `per_second`. Submissions over the limit are refused as throttled, so a
+/// client flooding a document can't hold up the others editing it, and
+/// submissions of more than `burst` operations are refused outright.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    per_second: f64,
+    burst: usize,
+}
+
+impl RateLimit {
+    pub fn new(per_second: f64, burst: usize) -> Result<Self, RateLimitError> {
+        if !per_second.is_finite() || per_second <= 0.0 {
+            return Err(RateLimitError::Rate(per_second));
+        }
+        if burst == 0 {
+            return Err(RateLimitError::Burst);
+        }
+        Ok(Self { per_second, burst })
+    }
+
+    pub fn per_second(&self) -> f64 {
+        self.per_second
+    }
+
+    pub fn burst(&self) -> usize {
+        self.burst
+    }
+}
+
+/// The operations a client may still submit under a rate limit.
+#[derive(Debug, Clone)]
+pub(crate) struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    pub(crate) fn new(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            last: now,
+        }
+    }
+
+    /// Take the tokens for submitting some operations, one per operation, or
+    /// get how long to wait until there are enough. Batches bigger than the
+    /// burst never fit, so they're to be refused before taking.
+    pub(crate) fn take(
+        &mut self,
+        limit: &RateLimit,
+        operations: usize,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        self.last = now;
+
+        let cost = operations as f64;
+        if self.tokens >= cost {
+            self.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (cost - self.tokens) / limit.per_second,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limit = RateLimit::new(10.0, 5).unwrap();
+        let start = Instant::now();
+        let mut bucket = Bucket::new(&limit, start);
+
+        assert_eq!(bucket.take(&limit, 3, start), Ok(()));
+        assert_eq!(
+            bucket.take(&limit, 3, start),
+            Err(Duration::from_millis(100))
+        );
+        assert_eq!(
+            bucket.take(&limit, 3, start + Duration::from_millis(100)),
+            Ok(())
+        );
+
+        // Every operation is charged, up to the whole burst.
+        let later = start + Duration::from_secs(1);
+        assert_eq!(bucket.take(&limit, 5, later), Ok(()));
+        assert!(bucket.take(&limit, 1, later).is_err());
+    }
+
+    #[test]
+    fn limits_must_let_operations_through() {
+        assert_eq!(RateLimit::new(0.0, 5), Err(RateLimitError::Rate(0.0)));
+        assert!(RateLimit::new(f64::NAN, 5).is_err());
+        assert_eq!(RateLimit::new(1.0, 0), Err(RateLimitError::Burst));
+    }
+}
@@ -0,0 +1,459 @@
+//! A reference collaboration server, where clients edit documents together
+//! over WebSockets.
+//!
+//! Each document has a sequencer putting its clients' operations in one
+//! order. Sequenced operations are appended to a `DocumentStore` and then
+//! sent to the other clients, in the order they were sequenced. The
+//! messages are described in [`protocol`], and how submissions are batched
+//! and limited in [`IngestOptions`], and who may connect and what they may
+//! submit in [`Access`].
+//!
+//! Clients whose documents transclude content from another document are
+//! told when a batch sequenced for that document changes the content.
+//!
+//! Each client is served by its own task, which the runtime may run on any of
+//! its threads. Documents stay loaded once a client has joined them.
+
+mod access;
+mod ingest;
+pub mod protocol;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use futures_util::{SinkExt, StreamExt};
+use slate::plugins::transclusion::Source;
+use slate::store::{DocumentStore, Snapshot, Stored};
+use slate::{Editor, Operation, Sequencer};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{Mutex, Notify};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use access::Handshake;
+pub use access::{Access, Authenticator};
+use ingest::Bucket;
+pub use ingest::{IngestOptions, RateLimit, RateLimitError};
+use protocol::{ClientMessage, ServerMessage};
+
+/// How many batches a client can fall behind on before it's disconnected.
+const BACKLOG: usize = 256;
+
+/// A batch of operations sequenced for a client, or a notice for every
+/// client of a document without one.
+#[derive(Debug, Clone)]
+struct Batch {
+    client: Option<usize>,
+    /// The messages telling the client how its submission went.
+    replies: Vec<String>,
+    /// The message telling the other clients about the operations, if they
+    /// were sequenced.
+    message: Option<String>,
+}
+
+/// Operations a client submitted, waiting to be sequenced.
+struct Submission {
+    client: usize,
+    /// The user the client connected as.
+    user: String,
+    revision: u64,
+    operations: Vec<Operation>,
+}
+
+/// A document being edited.
+struct Room {
+    /// Held while a batch is sequenced and stored, so batches are stored in
+    /// the order they're sequenced.
+    sequencer: Mutex<Sequencer>,
+    pending: std::sync::Mutex<Vec<Submission>>,
+    /// Notified when the waiting submissions are taken to be sequenced.
+    taken: Notify,
+    batches: broadcast::Sender<Batch>,
+    /// What the document transcludes from other documents.
+    sources: std::sync::Mutex<Vec<Source>>,
+}
+
+/// The document a client has joined.
+struct Joined {
+    id: String,
+    room: Arc<Room>,
+    batches: broadcast::Receiver<Batch>,
+}
+
+pub struct Server<S> {
+    store: S,
+    options: IngestOptions,
+    access: Access,
+    rooms: std::sync::Mutex<HashMap<String, Arc<Room>>>,
+    next_client: AtomicUsize,
+}
+
+impl<S> Server<S>
+where
+    S: DocumentStore + 'static,
+    S::Error: Display,
+{
+    pub fn new(store: S) -> Arc<Self> {
+        Self::with_options(store, IngestOptions::default())
+    }
+
+    /// Serve documents from a store, batching and limiting submissions with
+    /// `options`.
+    pub fn with_options(store: S, options: IngestOptions) -> Arc<Self> {
+        Self::with_access(store, options, Access::default())
+    }
+
+    /// Serve documents from a store like `with_options`, to the clients
+    /// `access` lets connect, with their submissions authorized by its policy.
+    pub fn with_access(store: S, options: IngestOptions, access: Access) -> Arc<Self> {
+        Arc::new(Self {
+            store,
+            options,
+            access,
+            rooms: Default::default(),
+            next_client: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Accept clients until listening fails.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = Arc::clone(&self);
+            tokio::spawn(async move {
+                // A client whose connection fails has simply left.
+                let _ = server.connect(stream).await;
+            });
+        }
+    }
+
+    async fn connect(&self, stream: TcpStream) -> Result<(), tungstenite::Error> {
+        let mut user = None;
+        let handshake = Handshake {
+            access: &self.access,
+            user: &mut user,
+        };
+        let mut socket = tokio_tungstenite::accept_hdr_async(stream, handshake).await?;
+        let user = user.unwrap_or_default();
+        let client = self.next_client.fetch_add(1, Ordering::Relaxed);
+        let mut joined: Option<Joined> = None;
+        let now = Instant::now();
+        let mut bucket = self
+            .options
+            .rate_limit
+            .map(|limit| Bucket::new(&limit, now));
+
+        loop {
+            tokio::select! {
+                message = socket.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None => return Ok(()),
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => return Err(e),
+                    };
+                    let reply = self.handle(client, &user, &text, &mut joined, &mut bucket).await;
+                    if let Some(reply) = reply {
+                        socket.send(Message::text(reply.to_json())).await?;
+                    }
+                }
+                batch = next_batch(&mut joined) => {
+                    let message = match batch {
+                        Ok(batch) if batch.client == Some(client) => {
+                            for reply in batch.replies {
+                                socket.send(Message::text(reply)).await?;
+                            }
+                            continue;
+                        }
+                        Ok(batch) => match batch.message {
+                            Some(message) => message,
+                            None => continue,
+                        },
+                        Err(_) => {
+                            let message = "fell too far behind".to_string();
+                            let error = ServerMessage::Error { message }.to_json();
+                            socket.send(Message::text(error)).await?;
+                            return socket.close(None).await;
+                        }
+                    };
+                    socket.send(Message::text(message)).await?;
+                }
+            }
+        }
+    }
+
+    /// Handle a message from a client, returning the reply to send it. The
+    /// replies to submitted operations come through the batches instead, so
+    /// they're in order with those of other clients.
+    async fn handle(
+        &self,
+        client: usize,
+        user: &str,
+        text: &str,
+        joined: &mut Option<Joined>,
+        bucket: &mut Option<Bucket>,
+    ) -> Option<ServerMessage> {
+        let error = |message: String| Some(ServerMessage::Error { message });
+
+        let registry = &self.options.mark_registry;
+        match (
+            ClientMessage::from_json_with(text, registry),
+            joined.as_ref(),
+        ) {
+            (None, _) => error("invalid message".into()),
+            (Some(ClientMessage::Join { .. }), Some(_)) => error("already joined".into()),
+            (Some(ClientMessage::Join { document }), None) => {
+                let room = match self.room(&document).await {
+                    Ok(room) => room,
+                    Err(message) => return error(message),
+                };
+
+                // Subscribe while holding the sequencer, so no batch is
+                // missed or sent twice.
+                let sequencer = room.sequencer.lock().await;
+                let reply = ServerMessage::Joined {
+                    revision: sequencer.revision(),
+                    document: Box::new(
+                        sequencer
+                            .document()
+                            .clone()
+                            .with_mark_registry(registry.clone()),
+                    ),
+                };
+                let batches = room.batches.subscribe();
+                drop(sequencer);
+
+                *joined = Some(Joined {
+                    id: document,
+                    room,
+                    batches,
+                });
+                Some(reply)
+            }
+            (Some(ClientMessage::Submit { .. }), None) => error("not joined".into()),
+            (
+                Some(ClientMessage::Submit {
+                    revision,
+                    operations,
+                }),
+                Some(joined),
+            ) => {
+                if let (Some(bucket), Some(limit)) = (bucket, &self.options.rate_limit) {
+                    if operations.len() > limit.burst() {
+                        return error(format!(
+                            "a submission can't have more than {} operations",
+                            limit.burst()
+                        ));
+                    }
+                    if let Err(wait) = bucket.take(limit, operations.len(), Instant::now()) {
+                        let retry_after_ms = wait.as_millis() as u64 + 1;
+                        return Some(ServerMessage::Throttled { retry_after_ms });
+                    }
+                }
+
+                let room = &joined.room;
+                let waiting = {
+                    let mut pending = lock(&room.pending);
+                    pending.push(Submission {
+                        client,
+                        user: user.into(),
+                        revision,
+                        operations,
+                    });
+                    pending.iter().map(|s| s.operations.len()).sum::<usize>()
+                };
+                // Whoever's submission fills the batch, or waits the longest,
+                // sequences everything waiting, so nothing is left behind.
+                if waiting < self.options.max_batch && !self.options.max_delay.is_zero() {
+                    tokio::select! {
+                        _ = tokio::time::sleep(self.options.max_delay) => {}
+                        _ = room.taken.notified() => return None,
+                    }
+                }
+                self.sequence(&joined.id, room).await;
+                None
+            }
+        }
+    }
+
+    /// Sequence and store the submissions waiting in a room, then send each
+    /// client the outcome of its own and the others the operations.
+    async fn sequence(&self, id: &str, room: &Room) {
+        let mut sequencer = room.sequencer.lock().await;
+        let pending = std::mem::take(&mut *lock(&room.pending));
+        if pending.is_empty() {
+            return;
+        }
+        room.taken.notify_waiters();
+
+        // Hash what other documents transclude from this one, to tell what
+        // the batch changes of it.
+        let watched = self.watched(id);
+        let hashes: Vec<_> = watched
+            .iter()
+            .map(|source| source.hash(sequencer.document()))
+            .collect();
+
+        let mut batches = vec![];
+        let mut sequenced = vec![];
+        for submission in pending {
+            let submitted = sequencer.submit_as(
+                &submission.user,
+                submission.revision,
+                &submission.operations,
+            );
+            let batch = match submitted {
+                Ok(operations) => {
+                    let revision = sequencer.revision();
+                    sequenced.extend(operations.iter().cloned());
+                    let message = ServerMessage::Operations {
+                        revision,
+                        operations,
+                    };
+                    Batch {
+                        client: Some(submission.client),
+                        replies: vec![ServerMessage::Ack { revision }.to_json()],
+                        message: Some(message.to_json_with(&self.options.mark_registry)),
+                    }
+                }
+                Err(e) => Batch {
+                    client: Some(submission.client),
+                    replies: vec![error(e.to_string())],
+                    message: None,
+                },
+            };
+            batches.push(batch);
+        }
+
+        // The sequencer has the operations either way, so they're still sent
+        // out and the clients are only told the store failed.
+        if !sequenced.is_empty() {
+            *lock(&room.sources) = sources(sequencer.document());
+            if let Err(e) = self.store.append_operations(id, sequenced).await {
+                for batch in batches.iter_mut().filter(|b| b.message.is_some()) {
+                    batch.replies.push(error(e.to_string()));
+                }
+            }
+        }
+
+        for batch in batches {
+            // Every client in the room is subscribed, including the
+            // submitters, so there's always a receiver.
+            let _ = room.batches.send(batch);
+        }
+
+        let mut changed: Vec<&str> = vec![];
+        for (source, hash) in watched.iter().zip(hashes) {
+            if source.hash(sequencer.document()) != hash && !changed.contains(&&*source.key) {
+                changed.push(&source.key);
+            }
+        }
+        for key in changed {
+            self.source_changed(id, key);
+        }
+    }
+
+    /// Get what the loaded documents transclude from a document.
+    fn watched(&self, id: &str) -> Vec<Source> {
+        let mut watched: Vec<Source> = vec![];
+        for room in lock(&self.rooms).values() {
+            for source in lock(&room.sources).iter() {
+                if source.document == id && !watched.contains(source) {
+                    watched.push(source.clone());
+                }
+            }
+        }
+        watched
+    }
+
+    /// Tell the clients of the documents transcluding the content with a key
+    /// in a document that it changed.
+    fn source_changed(&self, id: &str, key: &str) {
+        let message = ServerMessage::SourceChanged {
+            document: id.into(),
+            key: key.into(),
+        };
+        let batch = Batch {
+            client: None,
+            replies: vec![],
+            message: Some(message.to_json()),
+        };
+        for room in lock(&self.rooms).values() {
+            let sources = lock(&room.sources);
+            if sources.iter().any(|s| s.document == id && s.key == key) {
+                // A document nobody has joined has no one to tell.
+                let _ = room.batches.send(batch.clone());
+            }
+        }
+    }
+
+    /// Get the room of a document, loading it from the store if needed.
+    async fn room(&self, id: &str) -> Result<Arc<Room>, String> {
+        let loaded = lock(&self.rooms).get(id).cloned();
+        if let Some(room) = loaded {
+            return Ok(room);
+        }
+
+        let stored = self.store.load(id).await.map_err(|e| e.to_string())?;
+        let stored = stored.unwrap_or(Stored {
+            snapshot: Snapshot {
+                revision: 0,
+                children: vec![],
+            },
+            operations: vec![],
+        });
+        let mut sequencer = Sequencer::new(stored).map_err(|e| e.to_string())?;
+        if let Some(policy) = &self.access.policy {
+            sequencer = sequencer.with_policy(Arc::clone(policy));
+        }
+        let sources = sources(sequencer.document());
+
+        // Another client may have loaded the document in the meantime.
+        let mut rooms = lock(&self.rooms);
+        let room = rooms.entry(id.to_string()).or_insert_with(|| {
+            Arc::new(Room {
+                sequencer: Mutex::new(sequencer),
+                pending: Default::default(),
+                taken: Notify::new(),
+                batches: broadcast::channel(BACKLOG).0,
+                sources: std::sync::Mutex::new(sources),
+            })
+        });
+        Ok(Arc::clone(room))
+    }
+}
+
+fn sources(document: &Editor) -> Vec<Source> {
+    document
+        .transclusions()
+        .into_iter()
+        .map(|(_, source)| source)
+        .collect()
+}
+
+/// Lock state that's only held between awaits, ignoring panics of other
+/// clients' tasks while they held it.
+fn lock<T>(mutex: &std::sync::Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn error(message: String) -> String {
+    ServerMessage::Error { message }.to_json()
+}
+
+/// Wait for the next batch of the joined document, or forever without one.
+async fn next_batch(joined: &mut Option<Joined>) -> Result<Batch, RecvError> {
+    match joined {
+        Some(joined) => joined.batches.recv().await,
+        None => future::pending().await,
+    }
+}
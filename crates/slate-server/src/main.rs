@@ -0,0 +1,21 @@
+//! Run the reference server with documents kept in memory, listening on the
+//! address given as the first argument.
+
+use std::env;
+use std::io;
+
+use slate::store::MemoryStore;
+use slate_server::Server;
+use tokio::net::TcpListener;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    let address = env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDRESS.into());
+    let listener = TcpListener::bind(&address).await?;
+    println!("listening on {}", listener.local_addr()?);
+
+    let server = Server::new(MemoryStore::new());
+    server.serve(listener).await
+}
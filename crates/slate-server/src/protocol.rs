@@ -0,0 +1,275 @@
+//! The messages clients and the server exchange, as JSON text frames.
+//!
+//! A client connecting to a server that authenticates its clients sends a
+//! bearer token in the `Authorization` header of its WebSocket handshake, and
+//! is refused with `401 Unauthorized` without a valid one.
+//!
+//! A client joins a document and gets it at its latest revision. It then
+//! submits its operations a batch at a time, each made on top of the last
+//! revision it has seen, and waits for the server to acknowledge a batch
+//! before submitting the next. Meanwhile the server sends it the operations
+//! of other clients as they're sequenced, which it rebases its pending
+//! operations onto.
+//!
+//! A client submitting faster than the server allows has its batch refused
+//! as `throttled`, and submits it again once the time given has passed.
+//!
+//! A client whose document transcludes content from another document is
+//! sent `source_changed` when that content changes, so it can fetch the
+//! source and refresh its transclusions.
+//!
+//! Operations are in the shape of Slate.js operations, and documents in the
+//! shape Slate.js stores them. Marks are named by a `MarkRegistry`, which
+//! the server and its clients need to agree on for custom marks.
+
+use serde_json::{json, Value};
+use slate::serialize::json as slate_json;
+use slate::{Editor, MarkRegistry, Operation};
+
+/// A message from a client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    /// Start editing a document, creating it if it doesn't exist.
+    Join { document: String },
+    /// Operations made on top of a revision, to be sequenced.
+    Submit {
+        revision: u64,
+        operations: Vec<Operation>,
+    },
+}
+
+/// A message from the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    /// The document a client joined, at its latest revision.
+    Joined {
+        revision: u64,
+        document: Box<Editor>,
+    },
+    /// The client's submitted operations were sequenced, bringing the
+    /// document to a revision.
+    Ack { revision: u64 },
+    /// Other clients' operations were sequenced, bringing the document to a
+    /// revision.
+    Operations {
+        revision: u64,
+        operations: Vec<Operation>,
+    },
+    /// The client's submitted operations were refused because it's
+    /// submitting too fast, and can be submitted again after some
+    /// milliseconds.
+    Throttled { retry_after_ms: u64 },
+    /// The content with a key in another document, which the client's
+    /// document transcludes, was changed.
+    SourceChanged { document: String, key: String },
+    /// A message couldn't be handled.
+    Error { message: String },
+}
+
+fn operations_value(operations: &[Operation], registry: &MarkRegistry) -> Value {
+    operations
+        .iter()
+        .map(|op| slate_json::operation_to_value_with(op, registry))
+        .collect()
+}
+
+fn read_operations(value: &Value, registry: &MarkRegistry) -> Option<Vec<Operation>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|op| slate_json::operation_from_value_with(op, registry))
+        .collect()
+}
+
+impl ClientMessage {
+    pub fn to_json(&self) -> String {
+        self.to_json_with(&MarkRegistry::default())
+    }
+
+    /// Write a message, naming marks with a registry.
+    pub fn to_json_with(&self, registry: &MarkRegistry) -> String {
+        let value = match self {
+            ClientMessage::Join { document } => json!({ "type": "join", "document": document }),
+            ClientMessage::Submit {
+                revision,
+                operations,
+            } => json!({
+                "type": "submit",
+                "revision": revision,
+                "operations": operations_value(operations, registry),
+            }),
+        };
+        value.to_string()
+    }
+
+    /// Read a message, or `None` if it isn't a valid one.
+    pub fn from_json(json: &str) -> Option<Self> {
+        Self::from_json_with(json, &MarkRegistry::default())
+    }
+
+    /// Read a message like `from_json`, reading marks by the names in a
+    /// registry.
+    pub fn from_json_with(json: &str, registry: &MarkRegistry) -> Option<Self> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        match value.get("type")?.as_str()? {
+            "join" => Some(ClientMessage::Join {
+                document: value.get("document")?.as_str()?.into(),
+            }),
+            "submit" => Some(ClientMessage::Submit {
+                revision: value.get("revision")?.as_u64()?,
+                operations: read_operations(value.get("operations")?, registry)?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn to_json(&self) -> String {
+        self.to_json_with(&MarkRegistry::default())
+    }
+
+    /// Write a message, naming marks with a registry. A joined document is
+    /// written with its own registry.
+    pub fn to_json_with(&self, registry: &MarkRegistry) -> String {
+        let value = match self {
+            ServerMessage::Joined { revision, document } => {
+                let document: Value = serde_json::from_str(&slate_json::to_string(document))
+                    .expect("a serialized document to be valid JSON");
+                json!({ "type": "joined", "revision": revision, "document": document })
+            }
+            ServerMessage::Ack { revision } => json!({ "type": "ack", "revision": revision }),
+            ServerMessage::Operations {
+                revision,
+                operations,
+            } => json!({
+                "type": "operations",
+                "revision": revision,
+                "operations": operations_value(operations, registry),
+            }),
+            ServerMessage::Throttled { retry_after_ms } => {
+                json!({ "type": "throttled", "retry_after_ms": retry_after_ms })
+            }
+            ServerMessage::SourceChanged { document, key } => {
+                json!({ "type": "source_changed", "document": document, "key": key })
+            }
+            ServerMessage::Error { message } => json!({ "type": "error", "message": message }),
+        };
+        value.to_string()
+    }
+
+    /// Read a message, or `None` if it isn't a valid one.
+    pub fn from_json(json: &str) -> Option<Self> {
+        Self::from_json_with(json, &MarkRegistry::default())
+    }
+
+    /// Read a message like `from_json`, reading marks by the names in a
+    /// registry, which a joined document is given.
+    pub fn from_json_with(json: &str, registry: &MarkRegistry) -> Option<Self> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        match value.get("type")?.as_str()? {
+            "joined" => {
+                let document = value.get("document")?.to_string();
+                Some(ServerMessage::Joined {
+                    revision: value.get("revision")?.as_u64()?,
+                    document: Box::new(slate_json::from_string_with(&document, registry).ok()?),
+                })
+            }
+            "ack" => Some(ServerMessage::Ack {
+                revision: value.get("revision")?.as_u64()?,
+            }),
+            "operations" => Some(ServerMessage::Operations {
+                revision: value.get("revision")?.as_u64()?,
+                operations: read_operations(value.get("operations")?, registry)?,
+            }),
+            "throttled" => Some(ServerMessage::Throttled {
+                retry_after_ms: value.get("retry_after_ms")?.as_u64()?,
+            }),
+            "source_changed" => Some(ServerMessage::SourceChanged {
+                document: value.get("document")?.as_str()?.into(),
+                key: value.get("key")?.as_str()?.into(),
+            }),
+            "error" => Some(ServerMessage::Error {
+                message: value.get("message")?.as_str()?.into(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slate::{Element, Node, Path, Text};
+
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let operations = vec![Operation::InsertText {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+            text: "a".into(),
+        }];
+
+        let client = vec![
+            ClientMessage::Join {
+                document: "doc".into(),
+            },
+            ClientMessage::Submit {
+                revision: 3,
+                operations: operations.clone(),
+            },
+        ];
+        for message in client {
+            assert_eq!(ClientMessage::from_json(&message.to_json()), Some(message));
+        }
+
+        let server = vec![
+            ServerMessage::Joined {
+                revision: 1,
+                document: Box::new(
+                    Editor::new().add_child(Element::new().add_child(Text::new("a"))),
+                ),
+            },
+            ServerMessage::Ack { revision: 2 },
+            ServerMessage::Operations {
+                revision: 4,
+                operations,
+            },
+            ServerMessage::Throttled {
+                retry_after_ms: 250,
+            },
+            ServerMessage::SourceChanged {
+                document: "notes".into(),
+                key: "intro".into(),
+            },
+            ServerMessage::Error {
+                message: "no".into(),
+            },
+        ];
+        for message in server {
+            assert_eq!(ServerMessage::from_json(&message.to_json()), Some(message));
+        }
+
+        assert_eq!(ClientMessage::from_json(r#"{"type":"submit"}"#), None);
+    }
+
+    #[test]
+    fn custom_marks_round_trip() {
+        let mut registry = MarkRegistry::new();
+        let strike = registry.register("strikethrough").unwrap();
+        let message = ClientMessage::Submit {
+            revision: 0,
+            operations: vec![Operation::InsertNode {
+                path: Path::new(vec![0, 1]),
+                node: Node::Text(Text::with_marks("a", strike)),
+            }],
+        };
+        let json = message.to_json_with(&registry);
+        assert_eq!(
+            ClientMessage::from_json_with(&json, &registry),
+            Some(message)
+        );
+        assert_eq!(ClientMessage::from_json(&json), None);
+    }
+}
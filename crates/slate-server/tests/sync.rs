@@ -0,0 +1,332 @@
+use std::sync::Arc;
+
+use futures_util::{SinkExt, StreamExt};
+use slate::plugins::transclusion::{transclusion, Source};
+use slate::store::{DocumentStore, MemoryStore};
+use slate::{Decision, Editor, Element, Node, Operation, Path, Policy, Text};
+use slate_server::protocol::{ClientMessage, ServerMessage};
+use std::time::Duration;
+
+use slate_server::{Access, IngestOptions, RateLimit, Server};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
+use tokio_tungstenite::tungstenite::{Error, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+struct Client(Socket);
+
+impl Client {
+    async fn connect(address: &str) -> Self {
+        let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", address))
+            .await
+            .unwrap();
+        Client(socket)
+    }
+
+    /// Connect with a bearer token, or get the status the server refused the
+    /// connection with.
+    async fn connect_with(address: &str, token: &str) -> Result<Self, u16> {
+        let mut request = format!("ws://{}", address).into_client_request().unwrap();
+        let bearer = format!("Bearer {}", token).parse().unwrap();
+        request.headers_mut().insert(AUTHORIZATION, bearer);
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((socket, _)) => Ok(Client(socket)),
+            Err(Error::Http(response)) => Err(response.status().as_u16()),
+            Err(e) => panic!("expected to connect, got {:?}", e),
+        }
+    }
+
+    async fn send(&mut self, message: ClientMessage) {
+        self.0.send(Message::text(message.to_json())).await.unwrap();
+    }
+
+    async fn receive(&mut self) -> ServerMessage {
+        loop {
+            match self.0.next().await.unwrap().unwrap() {
+                Message::Text(text) => return ServerMessage::from_json(&text).unwrap(),
+                _ => continue,
+            }
+        }
+    }
+
+    async fn join(&mut self, document: &str) -> (u64, Editor) {
+        self.send(ClientMessage::Join {
+            document: document.into(),
+        })
+        .await;
+        match self.receive().await {
+            ServerMessage::Joined { revision, document } => (revision, *document),
+            message => panic!("expected to join, got {:?}", message),
+        }
+    }
+
+    async fn submit(&mut self, revision: u64, operations: Vec<Operation>) {
+        self.send(ClientMessage::Submit {
+            revision,
+            operations,
+        })
+        .await;
+    }
+}
+
+fn insert_text(offset: usize, text: &str) -> Operation {
+    Operation::InsertText {
+        path: Path::new(vec![0, 0]),
+        offset,
+        text: text.into(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_edit_together() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = Server::new(MemoryStore::new());
+
+    tokio::spawn(Arc::clone(&server).serve(listener));
+
+    let mut a = Client::connect(&address).await;
+    assert_eq!(a.join("doc").await.0, 0);
+    a.submit(
+        0,
+        vec![Operation::InsertNode {
+            path: Path::new(vec![0]),
+            node: Node::Element(Element::new().add_child(Text::new(""))),
+        }],
+    )
+    .await;
+    assert_eq!(a.receive().await, ServerMessage::Ack { revision: 1 });
+
+    let mut b = Client::connect(&address).await;
+    assert_eq!(b.join("doc").await.0, 1);
+
+    // A and B both edit revision 1, and A's edit is sequenced first.
+    a.submit(1, vec![insert_text(0, "hello")]).await;
+    assert_eq!(a.receive().await, ServerMessage::Ack { revision: 2 });
+    b.submit(1, vec![insert_text(0, "!")]).await;
+    assert_eq!(
+        b.receive().await,
+        ServerMessage::Operations {
+            revision: 2,
+            operations: vec![insert_text(0, "hello")],
+        }
+    );
+    assert_eq!(b.receive().await, ServerMessage::Ack { revision: 3 });
+    assert_eq!(
+        a.receive().await,
+        ServerMessage::Operations {
+            revision: 3,
+            operations: vec![insert_text(5, "!")],
+        }
+    );
+
+    let mut c = Client::connect(&address).await;
+    let (revision, document) = c.join("doc").await;
+    assert_eq!(revision, 3);
+    assert_eq!(
+        document.block_text(&Path::new(vec![0])),
+        Some("hello!".into())
+    );
+
+    let stored = server.store().load("doc").await.unwrap().unwrap();
+    assert_eq!(stored.revision(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn submitting_before_joining() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = Server::new(MemoryStore::new());
+
+    tokio::spawn(server.serve(listener));
+
+    let mut client = Client::connect(&address).await;
+    client.submit(0, vec![insert_text(0, "a")]).await;
+    assert_eq!(
+        client.receive().await,
+        ServerMessage::Error {
+            message: "not joined".into()
+        }
+    );
+}
+
+/// Lets only Ann edit.
+struct AnnOnly;
+
+impl Policy for AnnOnly {
+    fn authorize(&self, _: &Editor, user: &str, _: &Operation, _: &[Path]) -> Decision {
+        match user {
+            "ann" => Decision::Allow,
+            _ => Decision::Deny,
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_submit_as_the_users_they_authenticate_as() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let access = Access::new()
+        .authenticator(|token: &str| match token {
+            "ann-token" | "bob-token" => Some(token.trim_end_matches("-token").to_string()),
+            _ => None,
+        })
+        .policy(AnnOnly);
+    let server = Server::with_access(MemoryStore::new(), IngestOptions::new(), access);
+
+    tokio::spawn(server.serve(listener));
+
+    assert_eq!(
+        Client::connect_with(&address, "eve-token").await.err(),
+        Some(401)
+    );
+
+    let mut ann = Client::connect_with(&address, "ann-token").await.unwrap();
+    ann.join("doc").await;
+    ann.submit(0, vec![insert_block(0, "a")]).await;
+    assert_eq!(ann.receive().await, ServerMessage::Ack { revision: 1 });
+
+    let mut bob = Client::connect_with(&address, "bob-token").await.unwrap();
+    bob.join("doc").await;
+    bob.submit(1, vec![insert_text(1, "b")]).await;
+    match bob.receive().await {
+        ServerMessage::Error { message } => assert!(message.starts_with("operation denied")),
+        message => panic!("expected to be denied, got {:?}", message),
+    }
+}
+
+fn insert_block(index: usize, text: &str) -> Operation {
+    Operation::InsertNode {
+        path: Path::new(vec![index]),
+        node: Node::Element(Element::new().add_child(Text::new(text))),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fast_clients_are_throttled() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let limit = RateLimit::new(1.0, 2).unwrap();
+    let server = Server::with_options(MemoryStore::new(), IngestOptions::new().rate_limit(limit));
+
+    tokio::spawn(server.serve(listener));
+
+    let mut client = Client::connect(&address).await;
+    client.join("doc").await;
+    client
+        .submit(0, vec![insert_block(0, "a"), insert_text(0, "b")])
+        .await;
+    assert_eq!(client.receive().await, ServerMessage::Ack { revision: 2 });
+
+    client.submit(2, vec![insert_text(0, "c")]).await;
+    match client.receive().await {
+        ServerMessage::Throttled { retry_after_ms } => {
+            assert!(retry_after_ms > 900 && retry_after_ms <= 1001)
+        }
+        message => panic!("expected to be throttled, got {:?}", message),
+    }
+
+    // Submissions bigger than the burst are never let through.
+    let operations = (0..3).map(|i| insert_text(i, "d")).collect();
+    client.submit(2, operations).await;
+    assert_eq!(
+        client.receive().await,
+        ServerMessage::Error {
+            message: "a submission can't have more than 2 operations".into()
+        }
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn submissions_are_sequenced_in_batches() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let options = IngestOptions::new()
+        .max_batch(2)
+        .max_delay(Duration::from_secs(60));
+    let server = Server::with_options(MemoryStore::new(), options);
+
+    tokio::spawn(Arc::clone(&server).serve(listener));
+
+    let mut a = Client::connect(&address).await;
+    let mut b = Client::connect(&address).await;
+    a.join("doc").await;
+    b.join("doc").await;
+
+    // A's submission waits for B's to fill the batch.
+    a.submit(0, vec![insert_block(0, "a")]).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    b.submit(0, vec![insert_block(0, "b")]).await;
+    assert_eq!(a.receive().await, ServerMessage::Ack { revision: 1 });
+    assert_eq!(
+        a.receive().await,
+        ServerMessage::Operations {
+            revision: 2,
+            operations: vec![insert_block(1, "b")],
+        }
+    );
+    assert_eq!(
+        b.receive().await,
+        ServerMessage::Operations {
+            revision: 1,
+            operations: vec![insert_block(0, "a")],
+        }
+    );
+    assert_eq!(b.receive().await, ServerMessage::Ack { revision: 2 });
+
+    let stored = server.store().load("doc").await.unwrap().unwrap();
+    assert_eq!(stored.revision(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transcluding_clients_hear_of_source_changes() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = Server::new(MemoryStore::new());
+
+    tokio::spawn(server.serve(listener));
+
+    let mut notes = Client::connect(&address).await;
+    notes.join("notes").await;
+    let reference = transclusion(&Source::new("source", "quote"));
+    notes
+        .submit(
+            0,
+            vec![Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(reference),
+            }],
+        )
+        .await;
+    assert_eq!(notes.receive().await, ServerMessage::Ack { revision: 1 });
+
+    let mut source = Client::connect(&address).await;
+    source.join("source").await;
+    source.submit(0, vec![insert_block(0, "intro")]).await;
+    assert_eq!(source.receive().await, ServerMessage::Ack { revision: 1 });
+    let quote = Element::new()
+        .add_property("id", "quote")
+        .add_child(Text::new("to be"));
+    source
+        .submit(
+            1,
+            vec![Operation::InsertNode {
+                path: Path::new(vec![1]),
+                node: Node::Element(quote),
+            }],
+        )
+        .await;
+    assert_eq!(source.receive().await, ServerMessage::Ack { revision: 2 });
+
+    // Only the second batch changed the transcluded content.
+    assert_eq!(
+        notes.receive().await,
+        ServerMessage::SourceChanged {
+            document: "source".into(),
+            key: "quote".into(),
+        }
+    );
+}
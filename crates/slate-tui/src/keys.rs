@@ -0,0 +1,151 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use slate::{Editor, Range, Unit};
+
+/// Edit a document for a key press, as a text field would. Returns whether
+/// the key was handled; releasing a key never is.
+///
+/// Arrows move by characters and lines, or by words while holding control or
+/// alt, and home and end move to the line's boundaries. Holding shift extends
+/// the selection instead. Enter splits the block, and tab and keys pressed
+/// with control run the editor's commands bound to them, like `Mod-b`.
+pub fn handle_key(editor: &mut Editor, key: KeyEvent) -> bool {
+    if key.kind == KeyEventKind::Release {
+        return false;
+    }
+    let control = key.modifiers.contains(KeyModifiers::CONTROL);
+    let by_word = control || key.modifiers.contains(KeyModifiers::ALT);
+    let extend = key.modifiers.contains(KeyModifiers::SHIFT);
+
+    match key.code {
+        KeyCode::Char(c) if control => return editor.run_key(&format!("Mod-{}", c)),
+        KeyCode::Char(c) => editor.insert_text(c.encode_utf8(&mut [0; 4]), None),
+        KeyCode::Enter => {
+            // Only a break past the editor's limits is rejected.
+            let _ = editor.insert_break();
+        }
+        KeyCode::Backspace if by_word => editor.delete_word(true),
+        KeyCode::Backspace => editor.delete_backward(),
+        KeyCode::Delete if by_word => editor.delete_word(false),
+        KeyCode::Delete => editor.delete_forward(),
+        KeyCode::Left | KeyCode::Right => {
+            let unit = if by_word { Unit::Word } else { Unit::Character };
+            move_by(editor, unit, key.code == KeyCode::Left, extend);
+        }
+        KeyCode::Up | KeyCode::Down => move_by(editor, Unit::Line, key.code == KeyCode::Up, extend),
+        KeyCode::Home | KeyCode::End => move_by(
+            editor,
+            Unit::LineBoundary,
+            key.code == KeyCode::Home,
+            extend,
+        ),
+        KeyCode::Tab => return editor.run_key("Tab"),
+        KeyCode::BackTab => return editor.run_key("Shift-Tab"),
+        _ => return false,
+    }
+    true
+}
+
+/// Move the caret, or only the focus of the selection to `extend` it.
+fn move_by(editor: &mut Editor, unit: Unit, reverse: bool, extend: bool) {
+    let selection = match editor.selection() {
+        Some(selection) if extend => selection.clone(),
+        _ => return editor.move_by(unit, reverse),
+    };
+
+    editor.select(selection.focus().clone());
+    editor.move_by(unit, reverse);
+    if let Some(focus) = editor.selection().map(|s| s.focus().clone()) {
+        editor.select(Range::new(selection.anchor().clone(), focus));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slate::{Descendant, Marks, Path, Point};
+
+    use super::*;
+
+    fn press(editor: &mut Editor, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        handle_key(editor, KeyEvent::new(code, modifiers))
+    }
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            press(editor, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+    }
+
+    fn editor() -> Editor {
+        let mut editor = Editor::with_default();
+        editor.select(Point {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+        });
+        editor
+    }
+
+    #[test]
+    fn type_and_delete() {
+        let mut editor = editor();
+        type_text(&mut editor, "hello world");
+        press(&mut editor, KeyCode::Backspace, KeyModifiers::CONTROL);
+        assert_eq!(
+            editor.block_text(&Path::new(vec![0])),
+            Some("hello ".into())
+        );
+
+        press(&mut editor, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Delete, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Right, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("llo ".into()));
+
+        assert!(!press(
+            &mut editor,
+            KeyCode::Char('q'),
+            KeyModifiers::CONTROL
+        ));
+        assert!(!press(&mut editor, KeyCode::Esc, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn split_blocks() {
+        let mut editor = editor();
+        type_text(&mut editor, "ab");
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Enter, KeyModifiers::NONE);
+        type_text(&mut editor, "c");
+
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("a".into()));
+        assert_eq!(editor.block_text(&Path::new(vec![1])), Some("cb".into()));
+
+        // Deleting at the end of a block merges the next one into it.
+        press(&mut editor, KeyCode::Up, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::End, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("acb".into()));
+        assert_eq!(editor.num_children(), 1);
+    }
+
+    #[test]
+    fn toggle_marks() {
+        let mut editor = editor();
+        type_text(&mut editor, "ab");
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Home, KeyModifiers::SHIFT);
+        press(&mut editor, KeyCode::Char('b'), KeyModifiers::CONTROL);
+        press(&mut editor, KeyCode::Char('i'), KeyModifiers::CONTROL);
+        press(&mut editor, KeyCode::Char('b'), KeyModifiers::CONTROL);
+
+        let block = editor.child(0).and_then(Descendant::as_element).unwrap();
+        let marks: Vec<_> = block
+            .children()
+            .iter()
+            .filter_map(|child| child.as_text().map(|t| (t.text().to_string(), t.marks())))
+            .collect();
+        assert_eq!(
+            marks,
+            vec![("a".into(), Marks::ITALIC), ("b".into(), Marks::empty())]
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! A terminal editor for Slate documents, built on ratatui.
+//!
+//! [`EditorView`] renders a document and the cursors of other users, and
+//! [`handle_key`] edits it as a text field would on a key press. Together
+//! they make a headless editor, as the `slate-tui` binary shows.
+
+mod keys;
+mod view;
+
+pub use keys::handle_key;
+pub use view::{text_style, EditorView};
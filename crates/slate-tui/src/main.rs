@@ -0,0 +1,90 @@
+//! Edit a document stored as Slate JSON in the terminal.
+//!
+//! Usage: `slate-tui <file>`. The file is created when saving if it doesn't
+//! exist. Control with S saves, and escape or control with Q quits.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::text::Line;
+use ratatui::DefaultTerminal;
+use slate::serialize::json;
+use slate::{Editor, Path};
+use slate_tui::{handle_key, EditorView};
+
+fn main() -> io::Result<()> {
+    let file = match env::args().nth(1) {
+        Some(file) => file,
+        None => {
+            eprintln!("usage: slate-tui <file>");
+            process::exit(2);
+        }
+    };
+
+    let mut editor = match fs::read_to_string(&file) {
+        Ok(contents) => json::from_string(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Editor::with_default(),
+        Err(e) => return Err(e),
+    };
+    if let Some(start) = editor.start(Path::new(vec![])) {
+        editor.select(start);
+    }
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut editor, &file);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, editor: &mut Editor, file: &str) -> io::Result<()> {
+    let mut scroll = 0;
+    let mut status = format!("{}: ctrl+s saves, esc quits", file);
+
+    loop {
+        terminal.draw(|frame| {
+            let [area, footer] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+            // Scroll just far enough to keep the caret in view.
+            let view = EditorView::new(editor);
+            let focus = editor.selection().map(|s| s.focus().clone());
+            if let Some(line) = focus.and_then(|f| view.line_of(&f)) {
+                let height = usize::from(area.height.max(1));
+                scroll = scroll.clamp(line.saturating_sub(height - 1), line);
+            }
+
+            let view = view.scroll(scroll);
+            if let Some(caret) = view.caret(area) {
+                frame.set_cursor_position(caret);
+            }
+            frame.render_widget(view, area);
+            frame.render_widget(Line::from(status.as_str()), footer);
+        })?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => continue,
+        };
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('q') if control => return Ok(()),
+            KeyCode::Char('s') if control => {
+                status = match fs::write(file, json::to_string(editor)) {
+                    Ok(()) => format!("saved {}", file),
+                    Err(e) => format!("couldn't save {}: {}", file, e),
+                };
+            }
+            _ => {
+                handle_key(editor, key);
+                // Nothing is sent anywhere, so the operations can be dropped.
+                editor.flush();
+            }
+        }
+    }
+}
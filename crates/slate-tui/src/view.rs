@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Widget;
+use slate::awareness::AwarenessState;
+use slate::{Decoration, Descendant, Editor, Marks, Path, Point, Range, Text};
+use slate::{BACKGROUND_MARK, COLOR_MARK};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The color of a remote cursor whose CSS color isn't one a terminal has.
+const FALLBACK_CURSOR_COLOR: Color = Color::Magenta;
+
+/// How many columns each level of nesting indents a block by.
+const INDENT_WIDTH: u16 = 2;
+
+/// A widget rendering a document, one line per block.
+///
+/// Marks are rendered as text styles, and decorations as highlights. The
+/// selections of other users, as shared in their awareness states, are drawn
+/// in their colors. Blocks longer than the area are cut off rather than
+/// wrapped.
+#[derive(Debug, Clone)]
+pub struct EditorView<'a> {
+    pub editor: &'a Editor,
+    pub decorations: Vec<Decoration>,
+    /// The style of decorations without one of their own.
+    pub highlight: Style,
+    pub decoration_styles: HashMap<String, Style>,
+    /// The style of the selected text. The caret of a collapsed selection is
+    /// left to the terminal's cursor, placed with [`EditorView::caret`].
+    pub selection_style: Style,
+    pub cursors: Vec<&'a AwarenessState>,
+    /// The number of lines scrolled past.
+    pub scroll: usize,
+}
+
+impl<'a> EditorView<'a> {
+    pub fn new(editor: &'a Editor) -> Self {
+        Self {
+            editor,
+            decorations: vec![],
+            highlight: Style::new().bg(Color::Yellow).fg(Color::Black),
+            decoration_styles: HashMap::new(),
+            selection_style: Style::new().add_modifier(Modifier::REVERSED),
+            cursors: vec![],
+            scroll: 0,
+        }
+    }
+
+    pub fn decorations(mut self, decorations: Vec<Decoration>) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    pub fn highlight(mut self, style: Style) -> Self {
+        self.highlight = style;
+        self
+    }
+
+    /// Style the decorations with a name, instead of highlighting them.
+    pub fn decoration_style(mut self, name: impl Into<String>, style: Style) -> Self {
+        self.decoration_styles.insert(name.into(), style);
+        self
+    }
+
+    pub fn selection_style(mut self, style: Style) -> Self {
+        self.selection_style = style;
+        self
+    }
+
+    /// Show other users' cursors. States without a selection aren't shown.
+    pub fn cursors(mut self, cursors: impl IntoIterator<Item = &'a AwarenessState>) -> Self {
+        self.cursors = cursors.into_iter().collect();
+        self
+    }
+
+    pub fn scroll(mut self, scroll: usize) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    /// Get the index of the line a point is rendered on, counting from the
+    /// first line of the document rather than the first one shown.
+    pub fn line_of(&self, point: &Point) -> Option<usize> {
+        self.lines()
+            .iter()
+            .position(|line| line.offset(point).is_some())
+    }
+
+    /// Get where the terminal's cursor goes in an area to show the caret,
+    /// which is at the focus of the selection. Returns `None` without a
+    /// selection, or when the caret is scrolled or cut off out of the area.
+    pub fn caret(&self, area: Rect) -> Option<Position> {
+        let focus = self.editor.selection()?.focus();
+        let lines = self.lines();
+        let index = lines.iter().position(|line| line.offset(focus).is_some())?;
+        let row = u16::try_from(index.checked_sub(self.scroll)?).ok()?;
+
+        let line = &lines[index];
+        let offset = line.offset(focus)?;
+        let column = line.indent + Span::raw(&line.text[..offset]).width() as u16;
+        if row >= area.height || column >= area.width {
+            return None;
+        }
+        Some(Position::new(area.x + column, area.y + row))
+    }
+
+    fn lines(&self) -> Vec<Layout> {
+        let mut lines = vec![];
+        for i in 0..self.editor.num_children() {
+            if let Some(child) = self.editor.child(i) {
+                layout(child, Path::new(vec![i]), 0, &mut lines);
+            }
+        }
+        lines
+    }
+
+    /// Split a line into spans of the same style.
+    fn spans(&self, line: &Layout) -> Vec<Span<'static>> {
+        // Styles patched over ranges of the line, by offset, in order.
+        let mut layers: Vec<(usize, usize, Style)> = vec![];
+
+        for (range, names) in &self.decorations {
+            if let Some((start, end)) = line.span(range) {
+                let mut names: Vec<_> = names.iter().collect();
+                names.sort();
+                for name in names {
+                    let style = self.decoration_styles.get(name).unwrap_or(&self.highlight);
+                    layers.push((start, end, *style));
+                }
+            }
+        }
+
+        for state in &self.cursors {
+            let selection = match &state.selection {
+                Some(selection) => selection,
+                None => continue,
+            };
+            let color = Color::from_str(&state.color).unwrap_or(FALLBACK_CURSOR_COLOR);
+            if let Some((start, end)) = line.span(selection) {
+                layers.push((start, end, Style::new().bg(color)));
+            }
+            // The caret covers the grapheme after it, or a space past the end
+            // of the line.
+            if let Some(caret) = line.offset(selection.focus()) {
+                let end = next_boundary(&line.text, caret);
+                let style = Style::new().bg(color).fg(Color::Black);
+                layers.push((caret, end.max(caret + 1), style));
+            }
+        }
+
+        if let Some(selection) = self.editor.selection().filter(|s| s.is_expanded()) {
+            if let Some((start, end)) = line.span(selection) {
+                layers.push((start, end, self.selection_style));
+            }
+        }
+
+        let mut boundaries: Vec<usize> = line.leaves.iter().map(|leaf| leaf.start).collect();
+        boundaries.push(line.text.len());
+        for (start, end, _) in &layers {
+            boundaries.push(*start);
+            boundaries.push(*end);
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut spans = vec![];
+        for edges in boundaries.windows(2) {
+            let (start, end) = (edges[0], edges[1]);
+            let mut style = line
+                .leaves
+                .iter()
+                .find(|leaf| start < leaf.start + leaf.len)
+                .map_or_else(Style::new, |leaf| leaf.style);
+            for (_, _, layer) in layers.iter().filter(|(s, e, _)| *s <= start && end <= *e) {
+                style = style.patch(*layer);
+            }
+
+            // Only a caret at the end of the line reaches past its text.
+            let text = line.text.get(start..end).unwrap_or(" ");
+            spans.push(Span::styled(text.to_string(), style));
+        }
+        spans
+    }
+}
+
+impl Widget for EditorView<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for (row, line) in self.lines().iter().skip(self.scroll).enumerate() {
+            let row = match u16::try_from(row) {
+                Ok(row) if row < area.height => row,
+                _ => break,
+            };
+            if line.indent >= area.width {
+                continue;
+            }
+
+            let area = Rect::new(
+                area.x + line.indent,
+                area.y + row,
+                area.width - line.indent,
+                1,
+            );
+            Line::from(self.spans(line)).render(area, buf);
+        }
+    }
+}
+
+/// Get the style text is rendered in for its marks.
+pub fn text_style(text: &Text) -> Style {
+    let mut style = Style::new();
+    let marks = text.marks();
+    if marks.contains(Marks::BOLD) {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if marks.contains(Marks::ITALIC) {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if marks.contains(Marks::UNDERLINE) {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    if marks.contains(Marks::CODE) {
+        style = style.fg(Color::Cyan);
+    }
+    if let Some(color) = text.mark_value(COLOR_MARK).and_then(|c| c.parse().ok()) {
+        style = style.fg(color);
+    }
+    if let Some(color) = text
+        .mark_value(BACKGROUND_MARK)
+        .and_then(|c| c.parse().ok())
+    {
+        style = style.bg(color);
+    }
+    style
+}
+
+/// A block laid out as a line.
+#[derive(Debug)]
+struct Layout {
+    indent: u16,
+    /// The text of all of the block's leaves.
+    text: String,
+    leaves: Vec<Leaf>,
+}
+
+#[derive(Debug)]
+struct Leaf {
+    path: Path,
+    /// The offset of the leaf's text in the line.
+    start: usize,
+    len: usize,
+    style: Style,
+}
+
+impl Layout {
+    /// Get the offset of a point in the line, if it's in one of its leaves.
+    fn offset(&self, point: &Point) -> Option<usize> {
+        let leaf = self.leaves.iter().find(|leaf| leaf.path == point.path)?;
+        Some(leaf.start + point.offset.min(leaf.len))
+    }
+
+    /// Get the offsets in the line a range covers, if any.
+    fn span(&self, range: &Range) -> Option<(usize, usize)> {
+        let (first, last) = (self.leaves.first()?, self.leaves.last()?);
+        let (start, end) = range.edges(false);
+        if end.path < first.path || start.path > last.path {
+            return None;
+        }
+
+        let start = match self.offset(start) {
+            Some(offset) => offset,
+            None if start.path < first.path => 0,
+            None => return None,
+        };
+        let end = match self.offset(end) {
+            Some(offset) => offset,
+            None if end.path > last.path => self.text.len(),
+            None => return None,
+        };
+        Some((start, end))
+    }
+}
+
+/// Lay out a block, or the blocks in it, as lines. A block holding text is a
+/// line of all of its leaves, including those of inline elements.
+fn layout(node: &Descendant, path: Path, depth: u16, lines: &mut Vec<Layout>) {
+    let element = match node {
+        Descendant::Element(element) => element,
+        Descendant::Text(_) => return,
+    };
+    let children = (0..element.num_children()).filter_map(|i| element.child(i));
+
+    if element.num_children() == 0 || children.clone().any(|c| c.as_text().is_some()) {
+        let mut line = Layout {
+            indent: depth * INDENT_WIDTH,
+            text: String::new(),
+            leaves: vec![],
+        };
+        leaves(node, path, &mut line);
+        lines.push(line);
+        return;
+    }
+
+    for (i, child) in children.enumerate() {
+        layout(child, path.concat(i), depth + 1, lines);
+    }
+}
+
+fn leaves(node: &Descendant, path: Path, line: &mut Layout) {
+    match node {
+        Descendant::Text(text) => {
+            line.leaves.push(Leaf {
+                path,
+                start: line.text.len(),
+                len: text.text().len(),
+                style: text_style(text),
+            });
+            line.text.push_str(text.text());
+        }
+        Descendant::Element(element) => {
+            for i in 0..element.num_children() {
+                if let Some(child) = element.child(i) {
+                    leaves(child, path.concat(i), line);
+                }
+            }
+        }
+    }
+}
+
+/// Get the end of the grapheme starting at an offset.
+fn next_boundary(text: &str, offset: usize) -> usize {
+    text.get(offset..)
+        .and_then(|rest| rest.graphemes(true).next())
+        .map_or(offset, |g| offset + g.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use slate::Element;
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: Path::new(path),
+            offset,
+        }
+    }
+
+    fn render(view: EditorView, width: u16, height: u16) -> Buffer {
+        let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+        view.render(buf.area, &mut buf);
+        buf
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("plain "))
+                    .add_child(Text::with_marks("bold", Marks::BOLD)),
+            )
+            .add_child(Element::with_kind("list").add_child(
+                Element::new().add_child(Text::new("item").add_mark_value(COLOR_MARK, "red")),
+            ))
+    }
+
+    #[test]
+    fn render_marks() {
+        let editor = editor();
+        let buf = render(EditorView::new(&editor), 12, 3);
+
+        let mut expected = Buffer::with_lines(["plain bold  ", "  item      ", "            "]);
+        expected.set_style(
+            Rect::new(6, 0, 4, 1),
+            Style::new().add_modifier(Modifier::BOLD),
+        );
+        expected.set_style(Rect::new(2, 1, 4, 1), Style::new().fg(Color::Red));
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn render_decorations_and_cursors() {
+        let editor = editor();
+        let decorations = vec![(
+            Range::new(point(vec![0, 0], 2), point(vec![0, 1], 2)),
+            ["found".to_string()].iter().cloned().collect(),
+        )];
+        let cursor = AwarenessState::new("ada", "#00ff00").selection(Some(Range::new(
+            point(vec![1, 0, 0], 4),
+            point(vec![1, 0, 0], 4),
+        )));
+        let view = EditorView::new(&editor)
+            .decorations(decorations)
+            .decoration_style("found", Style::new().bg(Color::Blue))
+            .cursors(vec![&cursor]);
+        let buf = render(view, 10, 2);
+
+        assert_eq!(buf[(1, 0)].bg, Color::Reset);
+        assert_eq!(buf[(2, 0)].bg, Color::Blue);
+        assert_eq!(buf[(7, 0)].bg, Color::Blue);
+        assert!(buf[(7, 0)].modifier.contains(Modifier::BOLD));
+        assert_eq!(buf[(8, 0)].bg, Color::Reset);
+
+        // The caret is past the end of the line.
+        assert_eq!(buf[(6, 1)].symbol(), " ");
+        assert_eq!(buf[(6, 1)].bg, Color::Rgb(0, 255, 0));
+    }
+
+    #[test]
+    fn caret_position() {
+        let mut editor = editor();
+        editor.select(point(vec![1, 0, 0], 3));
+        let view = EditorView::new(&editor);
+        assert_eq!(view.line_of(&point(vec![1, 0, 0], 3)), Some(1));
+        assert_eq!(
+            view.caret(Rect::new(1, 1, 10, 5)),
+            Some(Position::new(6, 2))
+        );
+        assert_eq!(view.clone().scroll(2).caret(Rect::new(0, 0, 10, 5)), None);
+
+        editor.select(point(vec![0, 1], 2));
+        let view = EditorView::new(&editor).scroll(0);
+        assert_eq!(
+            view.caret(Rect::new(0, 0, 10, 5)),
+            Some(Position::new(8, 0))
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use crate::types::{element_entries, Editor, NodeProperties, Path, Point};
+
+/// The property of a heading holding its anchor, which links to it.
+pub const ANCHOR_PROPERTY: &str = "anchor";
+/// The property of a heading holding the anchors it had before its text
+/// changed, separated by spaces, so links made before still reach it.
+pub const ANCHOR_ALIASES_PROPERTY: &str = "anchor-aliases";
+
+/// The kinds of headings, which get anchors.
+const HEADINGS: &[&str] = &[
+    "heading-one",
+    "heading-two",
+    "heading-three",
+    "heading-four",
+    "heading-five",
+    "heading-six",
+];
+
+/// Turn the text of a heading into an anchor: lowercase letters and digits,
+/// with runs of anything else turned into single hyphens.
+pub fn slug(text: &str) -> String {
+    let mut out = String::new();
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.is_empty() && !out.ends_with('-') && (c.is_whitespace() || c == '-') {
+            out.push('-');
+        }
+    }
+
+    let out = out.trim_end_matches('-');
+    if out.is_empty() {
+        "section".into()
+    } else {
+        out.into()
+    }
+}
+
+impl Editor {
+    /// Give headings anchors made from their text when normalizing, and keep
+    /// them up to date as the text changes.
+    pub fn with_heading_anchors(mut self, heading_anchors: bool) -> Self {
+        self.heading_anchors = heading_anchors;
+        self
+    }
+
+    pub fn heading_anchors(&self) -> bool {
+        self.heading_anchors
+    }
+
+    /// Find where a link into the document points: the start of the element
+    /// with `id` as its id, or else of the heading with `id` as its anchor,
+    /// as one of its previous anchors, or with text that has it as its slug.
+    pub fn resolve_anchor(&self, id: &str) -> Option<Point> {
+        let elements = element_entries(&self.children, &Path::new(vec![]));
+        let headings = || {
+            elements
+                .iter()
+                .filter(|(e, _)| HEADINGS.contains(&e.kind()))
+        };
+
+        let found = elements
+            .iter()
+            .find(|(e, _)| e.property("id") == Some(id))
+            .or_else(|| headings().find(|(e, _)| e.property(ANCHOR_PROPERTY) == Some(id)))
+            .or_else(|| {
+                headings().find(|(e, _)| {
+                    e.property(ANCHOR_ALIASES_PROPERTY)
+                        .is_some_and(|aliases| aliases.split(' ').any(|a| a == id))
+                })
+            })
+            .or_else(|| {
+                let id = slug(id);
+                headings()
+                    .find(|(_, path)| self.block_text(path).is_some_and(|text| slug(&text) == id))
+            });
+
+        self.start(found?.1.clone())
+    }
+
+    /// Give every heading an anchor made from its text that's unique in the
+    /// document. A heading keeps its anchor while its text has the same slug,
+    /// and otherwise the old one becomes an alias.
+    pub(crate) fn normalize_anchors(&mut self) {
+        if !self.heading_anchors {
+            return;
+        }
+
+        let elements = element_entries(&self.children, &Path::new(vec![]));
+        let mut taken: HashSet<String> = elements
+            .iter()
+            .filter_map(|(e, _)| e.property("id"))
+            .map(String::from)
+            .collect();
+        let mut changes = vec![];
+
+        for (e, path) in elements {
+            if !HEADINGS.contains(&e.kind()) {
+                continue;
+            }
+            let base = slug(&self.block_text(&path).unwrap_or_default());
+            let current = e.property(ANCHOR_PROPERTY);
+
+            let keep = current.filter(|anchor| {
+                !taken.contains(*anchor)
+                    && (*anchor == base
+                        || anchor
+                            .strip_prefix(&base)
+                            .and_then(|n| n.strip_prefix('-'))
+                            .is_some_and(|n| n.parse::<usize>().is_ok()))
+            });
+            let anchor = match keep {
+                Some(anchor) => anchor.to_string(),
+                None => (0..)
+                    .map(|n| match n {
+                        0 => base.clone(),
+                        n => format!("{}-{}", base, n),
+                    })
+                    .find(|anchor| !taken.contains(anchor))
+                    .unwrap(),
+            };
+            taken.insert(anchor.clone());
+            if current == Some(anchor.as_str()) {
+                continue;
+            }
+
+            let mut aliases: Vec<&str> = e
+                .property(ANCHOR_ALIASES_PROPERTY)
+                .map_or(vec![], |a| a.split(' ').collect());
+            aliases.extend(current);
+            aliases.retain(|a| *a != anchor);
+            let aliases = Some(aliases.join(" ")).filter(|a| !a.is_empty());
+
+            changes.push((
+                path,
+                NodeProperties::new()
+                    .property(ANCHOR_PROPERTY, Some(anchor))
+                    .property(ANCHOR_ALIASES_PROPERTY, aliases),
+            ));
+        }
+
+        // Setting properties doesn't move any nodes, so the paths stay valid.
+        for (path, properties) in changes {
+            self.set_node(&path, properties)
+                .expect("setting the anchor of a heading to apply");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Descendant, Element, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn heading(text: &str) -> Element {
+        Element::with_kind("heading-two").add_child(Text::new(text))
+    }
+
+    fn anchor(editor: &Editor, i: usize) -> Option<String> {
+        match editor.child(i) {
+            Some(Descendant::Element(e)) => e.property(ANCHOR_PROPERTY).map(String::from),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slug("Getting Started!"), "getting-started");
+        assert_eq!(slug("  Ça  -- marche? "), "ça-marche");
+        assert_eq!(slug("?!"), "section");
+    }
+
+    #[test]
+    fn headings_get_unique_anchors() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_property("id", "intro")
+                    .add_child(Text::new("a")),
+            )
+            .add_child(heading("Intro"))
+            .add_child(heading("Intro"))
+            .with_heading_anchors(true);
+        editor.normalize();
+
+        assert_eq!(anchor(&editor, 1), Some("intro-1".into()));
+        assert_eq!(anchor(&editor, 2), Some("intro-2".into()));
+        assert_eq!(editor.resolve_anchor("intro"), Some(point(vec![0, 0], 0)));
+        assert_eq!(editor.resolve_anchor("intro-2"), Some(point(vec![2, 0], 0)));
+        assert_eq!(editor.resolve_anchor("nowhere"), None);
+    }
+
+    #[test]
+    fn anchors_survive_edits() {
+        let mut editor = Editor::new()
+            .add_child(heading("Setup"))
+            .with_heading_anchors(true);
+        editor.normalize();
+
+        editor.select(point(vec![0, 0], 5));
+        editor.insert_text("!", None);
+        editor.normalize();
+        assert_eq!(anchor(&editor, 0), Some("setup".into()));
+
+        editor.insert_text(" guide", None);
+        editor.normalize();
+        assert_eq!(anchor(&editor, 0), Some("setup-guide".into()));
+        assert_eq!(editor.resolve_anchor("setup"), Some(point(vec![0, 0], 0)));
+        assert_eq!(
+            editor.resolve_anchor("Setup guide!"),
+            Some(point(vec![0, 0], 0))
+        );
+    }
+}
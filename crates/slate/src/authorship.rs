@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+
+use crate::transforms::ApplyError;
+use crate::types::{text_entries, Descendant, Editor, Node, Operation, Path, Point, Range};
+
+impl Editor {
+    /// Attribute content the editor's own edits insert from now on to a
+    /// user, like typed or pasted text. Inserted text nodes without an author
+    /// are attributed to them, so that restored or moved content keeps its
+    /// author, and text inserted into someone else's text becomes a text node
+    /// of its own. Operations applied with `apply`, like a collaborator's,
+    /// are left as they are.
+    ///
+    /// Text nodes are only merged when they have the same author, so
+    /// attribution survives normalization and undo.
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into().into_boxed_str());
+        self
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    /// Count the characters attributed to each author. Text without an
+    /// author isn't counted.
+    pub fn authorship(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+
+        for (text, _) in text_entries(&self.children, &Path::new(vec![])) {
+            if let Some(author) = text.author() {
+                *counts.entry(author.to_string()).or_insert(0) += text.text().chars().count();
+            }
+        }
+
+        counts
+    }
+
+    /// Apply an operation of a local edit, rewritten so that the content it
+    /// inserts is attributed to the editor's author. The rewrite may turn it
+    /// into several operations, which are applied together or not at all.
+    ///
+    /// Operations from elsewhere are applied with `apply`, as they are.
+    pub(crate) fn apply_attributed(&mut self, op: Operation) -> Result<(), ApplyError> {
+        let mut ops = self.attribute(op);
+        if ops.len() == 1 {
+            return self.apply(ops.pop().unwrap());
+        }
+
+        let (len, previous) = (self.operations.len(), self.previous.clone());
+        let logged = self.fork_log.as_ref().map(|log| log.0.len());
+        for (i, op) in ops.iter().enumerate() {
+            if let Err(e) = self.apply(op.clone()) {
+                for op in ops[..i].iter().rev() {
+                    self.apply(op.clone().inverse())
+                        .expect("inverse of an applied operation to apply");
+                }
+                self.operations.truncate(len);
+                self.previous = previous;
+                if let (Some(log), Some(logged)) = (&mut self.fork_log, logged) {
+                    log.0.truncate(logged);
+                }
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rewrite an operation so that the content it inserts is attributed to
+    /// the editor's author.
+    fn attribute(&self, op: Operation) -> Vec<Operation> {
+        let author = match self.author() {
+            Some(author) => author,
+            None => return vec![op],
+        };
+
+        match op {
+            Operation::InsertNode { node, path } => {
+                let node = match node {
+                    Node::Element(_) | Node::Text(_) => {
+                        let mut node = node.into_descendant().unwrap();
+                        set_author(&mut node, author);
+                        node.into()
+                    }
+                    // Left for `apply` to reject.
+                    Node::Editor(_) => node,
+                };
+                vec![Operation::InsertNode { node, path }]
+            }
+            Operation::InsertText { path, offset, text } => {
+                self.attribute_text(path, offset, text, author)
+            }
+            op => vec![op],
+        }
+    }
+
+    fn attribute_text(
+        &self,
+        path: Path,
+        offset: usize,
+        text: String,
+        author: &str,
+    ) -> Vec<Operation> {
+        let current = match self.text_at(&path) {
+            Some(current) if current.author() != Some(author) => current,
+            _ => return vec![Operation::InsertText { path, offset, text }],
+        };
+        let authored = |p: &Path| self.text_at(p).filter(|t| t.author() == Some(author));
+        let len = current.text().len();
+        let node = Node::Text(current.with_text(text.as_str()).authored_by(author));
+
+        // At the edges of the text, the author's own text next to it can be
+        // extended instead.
+        if offset == 0 {
+            if let Some(prev) = path.previous().filter(|p| authored(p).is_some()) {
+                let offset = authored(&prev).unwrap().text().len();
+                return vec![Operation::InsertText {
+                    path: prev,
+                    offset,
+                    text,
+                }];
+            }
+            return vec![Operation::InsertNode { node, path }];
+        }
+
+        // A caret at the end of the text would stay in front of new text
+        // inserted after it, rather than moving past it as it does for plain
+        // insertions.
+        let caret = Point {
+            path: path.clone(),
+            offset: len,
+        };
+        let move_caret = offset == len && self.selection == Some(Range::new(caret.clone(), caret));
+
+        let next = path.next().unwrap();
+        let mut ops = if offset < len {
+            vec![
+                Operation::SplitNode {
+                    position: offset,
+                    properties: self.properties_at(&path),
+                    path,
+                },
+                Operation::InsertNode {
+                    node,
+                    path: next.clone(),
+                },
+            ]
+        } else if self
+            .next_sibling_ref(&path)
+            .and_then(Descendant::as_text)
+            .is_some_and(|t| t.author() == Some(author))
+        {
+            vec![Operation::InsertText {
+                path: next.clone(),
+                offset: 0,
+                text: text.clone(),
+            }]
+        } else {
+            vec![Operation::InsertNode {
+                node,
+                path: next.clone(),
+            }]
+        };
+
+        if move_caret {
+            let end = Point {
+                path: next,
+                offset: text.len(),
+            };
+            ops.push(Operation::SetSelection {
+                path: Path::new(vec![]),
+                properties: self.selection.clone(),
+                new_properties: Some(Range::new(end.clone(), end)),
+            });
+        }
+
+        ops
+    }
+}
+
+/// Attribute the text nodes in a node that don't have an author yet.
+fn set_author(node: &mut Descendant, author: &str) {
+    match node {
+        Descendant::Text(t) if t.author().is_none() => *t.author_mut() = Some(author.into()),
+        Descendant::Text(_) => {}
+        Descendant::Element(e) => {
+            for i in 0..e.num_children() {
+                set_author(e.children_mut().get_mut(i).unwrap(), author);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, NodeProperties, Text};
+
+    use super::*;
+
+    fn insert_text(path: Vec<usize>, offset: usize, text: &str) -> Operation {
+        Operation::InsertText {
+            path: path.into(),
+            offset,
+            text: text.into(),
+        }
+    }
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn caret(path: Vec<usize>, offset: usize) -> Range {
+        Range::new(point(path.clone(), offset), point(path, offset))
+    }
+
+    #[test]
+    fn inserted_nodes_are_attributed() {
+        let mut editor = Editor::new().with_author("ann");
+        let node = Element::new()
+            .add_child(Text::new("new"))
+            .add_child(Text::new("kept").authored_by("bob"));
+        editor
+            .insert_nodes(vec![Node::Element(node)], Path::new(vec![0]).into())
+            .unwrap();
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("new").authored_by("ann"))
+                .add_child(Text::new("kept").authored_by("bob"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn text_inserted_into_another_authors_text_is_split_out() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("hello").authored_by("bob")))
+            .with_author("ann");
+
+        editor.insert_text("XY", Some(point(vec![0, 0], 2).into()));
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("he").authored_by("bob"))
+                .add_child(Text::new("XY").authored_by("ann"))
+                .add_child(Text::new("llo").authored_by("bob"))
+                .into()]
+        );
+
+        // Own text is extended in place.
+        editor.insert_text("Z", Some(point(vec![0, 1], 2).into()));
+        assert_eq!(editor.authorship().get("ann"), Some(&3));
+        assert_eq!(editor.authorship().get("bob"), Some(&5));
+    }
+
+    #[test]
+    fn caret_follows_text_appended_to_another_authors_text() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("hi").authored_by("bob")))
+            .with_author("ann");
+        editor.select(caret(vec![0, 0], 2));
+
+        editor.insert_text("!", None);
+        editor.insert_text("?", None);
+
+        assert_eq!(editor.selection, Some(caret(vec![0, 1], 2)));
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("hi").authored_by("bob"))
+                .add_child(Text::new("!?").authored_by("ann"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn applied_operations_are_not_attributed() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("hello").authored_by("bob")))
+            .with_author("ann");
+
+        let op = insert_text(vec![0, 0], 2, "XY");
+        editor.apply(op.clone()).unwrap();
+        assert_eq!(editor.operations(), &[op]);
+        assert_eq!(editor.authorship().get("bob"), Some(&7));
+
+        let op = Operation::InsertNode {
+            path: Path::new(vec![1]),
+            node: Node::Editor(Editor::new()),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::InvalidNode(Path::new(vec![1])))
+        );
+    }
+
+    #[test]
+    fn texts_by_different_authors_are_not_merged() {
+        let mut editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::new("a").authored_by("ann"))
+                .add_child(Text::new("b").authored_by("bob")),
+        );
+        let before = editor.children();
+
+        editor.normalize();
+        assert_eq!(editor.children(), before);
+        assert_eq!(
+            editor.merge_nodes(Path::new(vec![0, 1]).into(), Default::default()),
+            Err(ApplyError::InvalidNode(Path::new(vec![0, 1])))
+        );
+        assert_eq!(editor.children(), before);
+
+        // Merges from elsewhere are applied, keeping the first author.
+        let merge = Operation::MergeNode {
+            path: Path::new(vec![0, 1]),
+            position: 1,
+            properties: NodeProperties::new(),
+        };
+        editor.apply(merge).unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("ab").authored_by("ann"))
+                .into()]
+        );
+    }
+}
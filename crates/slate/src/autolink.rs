@@ -0,0 +1,363 @@
+use std::collections::HashSet;
+
+use crate::types::{Descendant, Editor, Element, Node, Operation, Path, Text};
+
+/// The kind of the elements URLs are wrapped in.
+const LINK: &str = "link";
+
+/// Characters trailing a URL that are taken to be punctuation after it.
+const TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '}', '\'', '"'];
+
+/// Get the URL a word links to, and how long the linked part of it is, if it
+/// looks like a web address or an e-mail address.
+fn link_url(word: &str) -> Option<(usize, String)> {
+    let word = word.trim_end_matches(TRAILING);
+
+    let url = if ["http://", "https://"]
+        .iter()
+        .any(|p| word.len() > p.len() && word.starts_with(p))
+    {
+        word.to_string()
+    } else if word.starts_with("www.") && is_domain(&word[4..]) {
+        format!("https://{}", word)
+    } else if is_email(word) {
+        format!("mailto:{}", word)
+    } else {
+        return None;
+    };
+
+    Some((word.len(), url))
+}
+
+fn is_domain(domain: &str) -> bool {
+    let host = domain.split('/').next().unwrap_or_default();
+    let labels: Vec<&str> = host.split('.').collect();
+
+    labels.len() > 1
+        && labels
+            .iter()
+            .all(|l| !l.is_empty() && l.chars().all(|c| c.is_alphanumeric() || c == '-'))
+}
+
+fn is_email(word: &str) -> bool {
+    let (local, domain) = match word.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || "._%+-".contains(c))
+        && !domain.contains('/')
+        && is_domain(domain)
+}
+
+/// Wrap the URLs and e-mail addresses in a fragment in links. Text already in
+/// a link or in one of `code_kinds` is left alone.
+pub(crate) fn autolink_fragment(
+    nodes: Vec<Descendant>,
+    code_kinds: &HashSet<String>,
+) -> Vec<Descendant> {
+    nodes
+        .into_iter()
+        .flat_map(|node| match node {
+            Descendant::Element(e) if e.kind() == LINK || code_kinds.contains(e.kind()) => {
+                vec![Descendant::Element(e)]
+            }
+            Descendant::Element(mut e) => {
+                let children = std::mem::take(e.children_mut()).to_vec();
+                *e.children_mut() = autolink_fragment(children, code_kinds).into();
+                vec![Descendant::Element(e)]
+            }
+            Descendant::Text(t) => autolink_text(t),
+        })
+        .collect()
+}
+
+fn autolink_text(text: Text) -> Vec<Descendant> {
+    let content = text.text();
+    let mut out = vec![];
+    let mut last = 0;
+
+    for (start, word) in words(content) {
+        if let Some((len, url)) = link_url(word) {
+            if start > last {
+                out.push(text.with_text(&content[last..start]).into());
+            }
+            out.push(link(text.with_text(&word[..len]), url).into());
+            last = start + len;
+        }
+    }
+
+    if last == 0 {
+        return vec![text.into()];
+    }
+    if last < content.len() {
+        out.push(text.with_text(&content[last..]).into());
+    }
+    out
+}
+
+/// Split text into the runs of characters between whitespace, with their
+/// offsets.
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let base = text.as_ptr() as usize;
+    text.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - base, word))
+}
+
+fn link(text: Text, url: String) -> Element {
+    Element::with_kind(LINK)
+        .add_property("url", url)
+        .add_child(text)
+}
+
+impl Editor {
+    /// Wrap URLs and e-mail addresses in links as they're typed with
+    /// `insert_text`, once the whitespace after them is, and when they're
+    /// pasted.
+    pub fn with_autolink(mut self, autolink: bool) -> Self {
+        self.autolink = autolink;
+        self
+    }
+
+    pub fn autolink(&self) -> bool {
+        self.autolink
+    }
+
+    /// Turn the link the editor made most recently back into plain text, as
+    /// an undo right after typing a link would. Nothing is changed if that
+    /// link has been edited or removed since, or if the editor hasn't made one
+    /// since the last text was inserted.
+    ///
+    /// Returns whether the link was reverted.
+    pub fn revert_autolink(&mut self) -> bool {
+        let path = match self.autolinked.take() {
+            Some(path) => *path,
+            None => return false,
+        };
+        let text = match self.descendant_ref(&path) {
+            Some(Descendant::Element(e)) if e.kind() == LINK && e.num_children() == 1 => {
+                match e.children_ref().get(0) {
+                    Some(Descendant::Text(t)) => t.clone(),
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        };
+
+        self.remove_node(&path);
+        self.apply(Operation::InsertNode {
+            path: path.clone(),
+            node: Node::Text(text),
+        })
+        .expect("insert in place of a link to apply");
+
+        // Join the text back up with the text around it.
+        let next = path.next().unwrap();
+        for p in [next, path] {
+            let mergeable = match (
+                p.previous().and_then(|prev| self.text_at(&prev)),
+                self.text_at(&p),
+            ) {
+                (Some(prev), Some(t)) => prev.same_format(t),
+                _ => false,
+            };
+            if mergeable {
+                self.merge_node(&p).expect("merge of two texts to apply");
+            }
+        }
+        true
+    }
+
+    /// Link the URL ending at an offset of the text at a path, if there is
+    /// one, splitting it out of the text into a link element.
+    pub(crate) fn autolink_before(&mut self, path: &Path, end: usize) {
+        if !self.autolink || self.in_code(path) {
+            return;
+        }
+        let parent = path.parent().unwrap_or_default();
+        if self.block_path(path) != Some(parent) {
+            // Text in links and other inlines isn't linked again.
+            return;
+        }
+        let text = match self.text_at(path) {
+            Some(text) => text.text(),
+            None => return,
+        };
+
+        let start = text[..end]
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + text[i..].chars().next().unwrap().len_utf8());
+        let (len, url) = match link_url(&text[start..end]) {
+            Some(found) => found,
+            None => return,
+        };
+        let len_all = text.len();
+
+        let mut link_path = path.clone();
+        if start + len < len_all {
+            self.split_text(path, start + len);
+        }
+        if start > 0 {
+            self.split_text(path, start);
+            link_path = path.next().unwrap();
+        }
+
+        let node = match self.text_at(&link_path) {
+            Some(text) => text.clone(),
+            None => return,
+        };
+        self.remove_node(&link_path);
+        self.apply(Operation::InsertNode {
+            path: link_path.clone(),
+            node: Descendant::from(link(node, url)).into(),
+        })
+        .expect("insert in place of a text to apply");
+        self.autolinked = Some(Box::new(link_path));
+    }
+
+    fn split_text(&mut self, path: &Path, position: usize) {
+        self.apply(Operation::SplitNode {
+            path: path.clone(),
+            position,
+            properties: self.properties_at(path),
+        })
+        .expect("split of a text to apply");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Marks, Point, Range};
+    use crate::ClipboardPayload;
+
+    use super::*;
+
+    fn caret(path: Vec<usize>, offset: usize) -> Range {
+        let point = Point {
+            path: path.into(),
+            offset,
+        };
+        Range::new(point.clone(), point)
+    }
+
+    fn typed(text: &str) -> Editor {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .with_autolink(true);
+        editor.select(caret(vec![0, 0], 0));
+        for c in text.chars() {
+            editor.insert_text(&c.to_string(), None);
+        }
+        editor
+    }
+
+    #[test]
+    fn link_urls() {
+        assert_eq!(
+            link_url("https://example.com/a)."),
+            Some((21, "https://example.com/a".into()))
+        );
+        assert_eq!(
+            link_url("www.example.com"),
+            Some((15, "https://www.example.com".into()))
+        );
+        assert_eq!(
+            link_url("ann@example.com,"),
+            Some((15, "mailto:ann@example.com".into()))
+        );
+        assert_eq!(link_url("https://"), None);
+        assert_eq!(link_url("www.example"), None);
+        assert_eq!(link_url("a@b"), None);
+    }
+
+    #[test]
+    fn typed_urls_are_linked_at_word_boundaries() {
+        let mut editor = typed("see www.example.com");
+        assert_eq!(editor.num_children(), 1);
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("see www.example.com"))
+                .into()]
+        );
+
+        editor.insert_text(" ", None);
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("see "))
+                .add_child(link(
+                    Text::new("www.example.com"),
+                    "https://www.example.com".into()
+                ))
+                .add_child(Text::new(" "))
+                .into()]
+        );
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 2], 1)));
+
+        assert!(editor.revert_autolink());
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("see www.example.com "))
+                .into()]
+        );
+        assert!(!editor.revert_autolink());
+    }
+
+    #[test]
+    fn pasted_urls_are_linked() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .with_autolink(true);
+        editor.select(caret(vec![0, 0], 0));
+
+        editor
+            .paste(ClipboardPayload {
+                plain_text: Some("mail ann@example.com now".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        editor.normalize();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("mail "))
+                .add_child(link(
+                    Text::new("ann@example.com"),
+                    "mailto:ann@example.com".into()
+                ))
+                .add_child(Text::new(" now"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn code_and_links_are_left_alone() {
+        let fragment = vec![
+            Element::with_kind("code-block")
+                .add_child(Text::new("https://a.b"))
+                .into(),
+            Element::new()
+                .add_child(Text::with_marks("https://a.b x", Marks::BOLD))
+                .into(),
+        ];
+        let code_kinds = vec!["code-block".to_string()].into_iter().collect();
+        let linked = autolink_fragment(fragment.clone(), &code_kinds);
+
+        assert_eq!(linked[0], fragment[0]);
+        assert_eq!(
+            linked[1],
+            Element::new()
+                .add_child(link(
+                    Text::with_marks("https://a.b", Marks::BOLD),
+                    "https://a.b".into()
+                ))
+                .add_child(Text::with_marks(" x", Marks::BOLD))
+                .into()
+        );
+    }
+}
@@ -0,0 +1,493 @@
+//! Presence of the users editing a document, exchanged as awareness updates
+//! in the encoding of y-protocols, so it interoperates with Yjs frontends.
+//!
+//! An update lists clients, each with a clock and its state as JSON, or
+//! `null` once it has left. A state is replaced only by one with a newer
+//! clock. Clients renew their state periodically, and states that aren't
+//! renewed within the timeout are dropped.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Map, Value};
+
+use crate::types::{Path, Point, Range};
+
+/// How long a state lasts without being renewed, as in y-protocols.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a client shares about its user.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AwarenessState {
+    pub name: String,
+    /// A CSS color for the user's cursor and selection.
+    pub color: String,
+    pub selection: Option<Range>,
+    pub typing: bool,
+    /// Any other data an app shares, which must be a JSON object.
+    pub custom: Map<String, Value>,
+}
+
+impl AwarenessState {
+    pub fn new(name: impl Into<String>, color: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            color: color.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn selection(mut self, selection: Option<Range>) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    pub fn typing(mut self, typing: bool) -> Self {
+        self.typing = typing;
+        self
+    }
+
+    pub fn custom(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.custom.insert(key.into(), value);
+        self
+    }
+
+    /// The state as JSON. The user is under `user` as Yjs bindings expect,
+    /// and custom data is kept at the top level.
+    fn to_json(&self) -> Value {
+        let mut object = self.custom.clone();
+        object.insert(
+            "user".into(),
+            json!({ "name": self.name, "color": self.color }),
+        );
+        object.insert(
+            "selection".into(),
+            match &self.selection {
+                Some(s) => {
+                    json!({ "anchor": point_json(s.anchor()), "focus": point_json(s.focus()) })
+                }
+                None => Value::Null,
+            },
+        );
+        object.insert("typing".into(), self.typing.into());
+        Value::Object(object)
+    }
+
+    /// Read a state from JSON. Missing fields get their defaults, so states
+    /// from frontends that don't share them are still understood.
+    fn from_json(value: Value) -> Option<Self> {
+        let mut object = match value {
+            Value::Object(object) => object,
+            _ => return None,
+        };
+
+        let user = object.remove("user").unwrap_or(Value::Null);
+        let string = |key| {
+            user.get(key)
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string()
+        };
+        let selection = object.remove("selection").and_then(|s| {
+            Some(Range::new(
+                point_from_json(s.get("anchor")?)?,
+                point_from_json(s.get("focus")?)?,
+            ))
+        });
+        let typing = object.remove("typing").and_then(|t| t.as_bool());
+
+        Some(Self {
+            name: string("name"),
+            color: string("color"),
+            selection,
+            typing: typing.unwrap_or(false),
+            custom: object,
+        })
+    }
+}
+
+fn point_json(point: &Point) -> Value {
+    json!({ "path": point.path.as_slice(), "offset": point.offset })
+}
+
+fn point_from_json(value: &Value) -> Option<Point> {
+    let path = value
+        .get("path")?
+        .as_array()?
+        .iter()
+        .map(|i| i.as_u64().map(|i| i as usize))
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Point {
+        path: Path::new(path),
+        offset: value.get("offset")?.as_u64()? as usize,
+    })
+}
+
+/// Errors returned when an awareness update can't be decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AwarenessError {
+    /// The update ends in the middle of a value.
+    UnexpectedEnd,
+    /// A number doesn't fit in 53 bits, the most lib0 encodes.
+    NumberTooLarge,
+    /// A state isn't UTF-8 JSON holding an object or `null`.
+    InvalidState(u64),
+}
+
+impl fmt::Display for AwarenessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AwarenessError::UnexpectedEnd => write!(f, "awareness update ends unexpectedly"),
+            AwarenessError::NumberTooLarge => write!(f, "number in awareness update too large"),
+            AwarenessError::InvalidState(c) => write!(f, "invalid state for client {}", c),
+        }
+    }
+}
+
+impl Error for AwarenessError {}
+
+/// The clients whose states an update or expiry changed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AwarenessChange {
+    pub added: Vec<u64>,
+    pub updated: Vec<u64>,
+    pub removed: Vec<u64>,
+}
+
+impl AwarenessChange {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Meta {
+    clock: u64,
+    last_updated: Instant,
+}
+
+/// The states of the clients editing a document, including this one.
+///
+/// Times are passed in rather than read from the clock, so that apps decide
+/// when states expire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Awareness {
+    client_id: u64,
+    states: HashMap<u64, AwarenessState>,
+    meta: HashMap<u64, Meta>,
+    timeout: Duration,
+}
+
+impl Awareness {
+    pub fn new(client_id: u64) -> Self {
+        Self {
+            client_id,
+            states: HashMap::new(),
+            meta: HashMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set how long states last without being renewed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn client_id(&self) -> u64 {
+        self.client_id
+    }
+
+    pub fn local_state(&self) -> Option<&AwarenessState> {
+        self.states.get(&self.client_id)
+    }
+
+    /// Set this client's state, or clear it with `None` when leaving. Returns
+    /// the update to send to other clients.
+    pub fn set_local_state(&mut self, state: Option<AwarenessState>, now: Instant) -> Vec<u8> {
+        let id = self.client_id;
+        let clock = self.meta.get(&id).map_or(0, |m| m.clock + 1);
+        self.meta.insert(
+            id,
+            Meta {
+                clock,
+                last_updated: now,
+            },
+        );
+
+        match state {
+            Some(state) => self.states.insert(id, state),
+            None => self.states.remove(&id),
+        };
+
+        self.encode_update(&[id])
+    }
+
+    /// Get the states of every client, including this one.
+    pub fn states(&self) -> &HashMap<u64, AwarenessState> {
+        &self.states
+    }
+
+    /// Encode an update with the states of some clients. Clients without a
+    /// state are sent as having left.
+    pub fn encode_update(&self, clients: &[u64]) -> Vec<u8> {
+        let mut out = vec![];
+        write_number(&mut out, clients.len() as u64);
+
+        for id in clients {
+            let state = match self.states.get(id) {
+                Some(state) => state.to_json().to_string(),
+                None => "null".to_string(),
+            };
+
+            write_number(&mut out, *id);
+            write_number(&mut out, self.meta.get(id).map_or(0, |m| m.clock));
+            write_number(&mut out, state.len() as u64);
+            out.extend_from_slice(state.as_bytes());
+        }
+
+        out
+    }
+
+    /// Apply an update from another client.
+    pub fn apply_update(
+        &mut self,
+        update: &[u8],
+        now: Instant,
+    ) -> Result<AwarenessChange, AwarenessError> {
+        let mut reader = Reader(update);
+        let mut change = AwarenessChange::default();
+
+        for _ in 0..reader.number()? {
+            let id = reader.number()?;
+            let clock = reader.number()?;
+            let len = reader.number()? as usize;
+            let state = std::str::from_utf8(reader.bytes(len)?)
+                .ok()
+                .and_then(|s| serde_json::from_str::<Value>(s).ok())
+                .ok_or(AwarenessError::InvalidState(id))?;
+            let state = match state {
+                Value::Null => None,
+                state => {
+                    Some(AwarenessState::from_json(state).ok_or(AwarenessError::InvalidState(id))?)
+                }
+            };
+
+            let current = self.meta.get(&id).map(|m| m.clock);
+            let exists = self.states.contains_key(&id);
+            let newer =
+                current.is_none_or(|c| c < clock || (c == clock && state.is_none() && exists));
+            if !newer {
+                continue;
+            }
+
+            if id == self.client_id {
+                // Another client thinks this one left, so it renews its state
+                // to show it's still here.
+                if state.is_none() && exists {
+                    self.meta.insert(
+                        id,
+                        Meta {
+                            clock: clock + 1,
+                            last_updated: now,
+                        },
+                    );
+                }
+                continue;
+            }
+
+            self.meta.insert(
+                id,
+                Meta {
+                    clock,
+                    last_updated: now,
+                },
+            );
+            match state {
+                Some(state) => {
+                    let previous = self.states.insert(id, state.clone());
+                    match previous {
+                        None => change.added.push(id),
+                        Some(previous) if previous != state => change.updated.push(id),
+                        Some(_) => {}
+                    }
+                }
+                None if self.states.remove(&id).is_some() => change.removed.push(id),
+                None => {}
+            }
+        }
+
+        Ok(change)
+    }
+
+    /// Drop the states of other clients that weren't renewed within the
+    /// timeout.
+    pub fn remove_outdated(&mut self, now: Instant) -> AwarenessChange {
+        let timeout = self.timeout;
+        let outdated: Vec<u64> = self
+            .meta
+            .iter()
+            .filter(|(id, m)| {
+                **id != self.client_id && now.duration_since(m.last_updated) >= timeout
+            })
+            .map(|(id, _)| *id)
+            .filter(|id| self.states.contains_key(id))
+            .collect();
+
+        for id in &outdated {
+            self.states.remove(id);
+        }
+
+        AwarenessChange {
+            removed: outdated,
+            ..AwarenessChange::default()
+        }
+    }
+
+    /// Renew this client's state if half of the timeout has passed since it
+    /// was last sent, returning the update to send.
+    pub fn renew(&mut self, now: Instant) -> Option<Vec<u8>> {
+        let state = self.local_state()?.clone();
+        let last_updated = self.meta.get(&self.client_id)?.last_updated;
+
+        if now.duration_since(last_updated) >= self.timeout / 2 {
+            Some(self.set_local_state(Some(state), now))
+        } else {
+            None
+        }
+    }
+}
+
+/// Write a number as a lib0 variable-length unsigned integer: seven bits per
+/// byte, least significant first, with the high bit set on all but the last.
+fn write_number(out: &mut Vec<u8>, mut n: u64) {
+    while n > 0x7f {
+        out.push(0x80 | (n & 0x7f) as u8);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn number(&mut self) -> Result<u64, AwarenessError> {
+        let mut n = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let (byte, rest) = self.0.split_first().ok_or(AwarenessError::UnexpectedEnd)?;
+            self.0 = rest;
+            if shift > 49 {
+                return Err(AwarenessError::NumberTooLarge);
+            }
+
+            n |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], AwarenessError> {
+        if self.0.len() < len {
+            return Err(AwarenessError::UnexpectedEnd);
+        }
+
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn exchange_states() {
+        let now = Instant::now();
+        let mut alice = Awareness::new(1);
+        let mut bob = Awareness::new(300);
+
+        let state = AwarenessState::new("Alice", "#f00")
+            .selection(Some(Range::new(point(vec![0, 0], 1), point(vec![0, 0], 3))))
+            .typing(true)
+            .custom("avatar", json!("a.png"));
+        let update = alice.set_local_state(Some(state.clone()), now);
+
+        let change = bob.apply_update(&update, now).unwrap();
+        assert_eq!(change.added, vec![1]);
+        assert_eq!(bob.states().get(&1), Some(&state));
+
+        // An old update doesn't replace a newer state.
+        let newer = alice.set_local_state(Some(state.clone().typing(false)), now);
+        assert_eq!(bob.apply_update(&newer, now).unwrap().updated, vec![1]);
+        assert!(bob.apply_update(&update, now).unwrap().is_empty());
+
+        let left = alice.set_local_state(None, now);
+        assert_eq!(bob.apply_update(&left, now).unwrap().removed, vec![1]);
+        assert!(bob.states().is_empty());
+    }
+
+    #[test]
+    fn y_protocols_encoding() {
+        let now = Instant::now();
+        let mut awareness = Awareness::new(300);
+        awareness.set_local_state(Some(AwarenessState::new("a", "b")), now);
+
+        let update = awareness.set_local_state(None, now);
+        assert_eq!(update, [1, 0xac, 0x02, 1, 4, b'n', b'u', b'l', b'l']);
+
+        let yjs = br#"{"user":{"name":"Yjs"},"cursor":{"anchor":1}}"#;
+        let mut update = vec![1, 7, 3, yjs.len() as u8];
+        update.extend_from_slice(yjs);
+        let state = awareness
+            .apply_update(&update, now)
+            .map(|_| awareness.states()[&7].clone());
+        assert_eq!(
+            state,
+            Ok(AwarenessState::new("Yjs", "").custom("cursor", json!({ "anchor": 1 })))
+        );
+
+        assert_eq!(
+            awareness.apply_update(&[1, 8, 0, 5, b'n'], now),
+            Err(AwarenessError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn expiry_and_renewal() {
+        let start = Instant::now();
+        let mut alice = Awareness::new(1).timeout(Duration::from_secs(10));
+        let mut bob = Awareness::new(2).timeout(Duration::from_secs(10));
+        let update = alice.set_local_state(Some(AwarenessState::new("Alice", "#f00")), start);
+        bob.set_local_state(Some(AwarenessState::new("Bob", "#00f")), start);
+        bob.apply_update(&update, start).unwrap();
+
+        assert!(alice.renew(start + Duration::from_secs(4)).is_none());
+        let renewal = alice.renew(start + Duration::from_secs(5)).unwrap();
+        bob.apply_update(&renewal, start + Duration::from_secs(5))
+            .unwrap();
+
+        assert!(bob
+            .remove_outdated(start + Duration::from_secs(12))
+            .is_empty());
+        assert_eq!(
+            bob.remove_outdated(start + Duration::from_secs(15)).removed,
+            vec![1]
+        );
+        assert!(bob.local_state().is_some());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::input::InputType;
+use crate::schema::Schema;
+use crate::serialize::html::VOID_KINDS;
+use crate::transforms::ApplyError;
+use crate::types::{Children, Descendant, Editor, Element, Path};
+
+/// Behavior of the editor that apps and plugins can override, the way
+/// slate.js plugins override editor methods.
+///
+/// Every method has the editor's own behavior as its default, so an
+/// implementation only overrides what it changes. An override that wants
+/// the built-in behavior in some cases calls it on [`DefaultBehavior`], or
+/// on another behavior it wraps, to compose plugins. Calling the editor's
+/// method instead would call the override again.
+pub trait EditorBehavior: Send + Sync {
+    /// Whether an element is void: content the user can't edit inside of,
+    /// like an image or an emoji, with an empty text as its only child.
+    fn is_void(&self, editor: &Editor, element: &Element) -> bool {
+        let _ = editor;
+        VOID_KINDS.contains(&element.kind())
+    }
+
+    /// Whether a node is inline content, as the editor's schema decides.
+    fn is_inline(&self, editor: &Editor, node: &Descendant) -> bool {
+        match editor.schema() {
+            Some(schema) => schema.is_inline(node),
+            None => Schema::default().is_inline(node),
+        }
+    }
+
+    /// Fix the element at a path when normalizing, after the built-in
+    /// normalization of each pass. Passes are made until nothing is fixed,
+    /// so this only needs to fix what it finds. Nothing is fixed by default.
+    fn normalize_node(&self, editor: &mut Editor, path: &Path) {
+        let _ = (editor, path);
+    }
+
+    /// Split the block at the selection, as `Editor::insert_break` does.
+    fn insert_break(&self, editor: &mut Editor) -> Result<(), ApplyError> {
+        editor.input(InputType::Typing, |editor| editor.break_block())
+    }
+
+    /// Delete backward from the selection, as `Editor::delete_backward` does.
+    fn delete_backward(&self, editor: &mut Editor) {
+        editor.input(InputType::Typing, |editor| editor.delete_before_caret());
+    }
+
+    /// Delete forward from the selection, as `Editor::delete_forward` does.
+    fn delete_forward(&self, editor: &mut Editor) {
+        editor.input(InputType::Typing, |editor| editor.delete_after_caret());
+    }
+}
+
+/// The editor's own behavior, for overrides to fall back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DefaultBehavior;
+
+impl EditorBehavior for DefaultBehavior {}
+
+/// The behavior an editor was configured with.
+#[derive(Clone)]
+pub(crate) struct BehaviorHandle(pub(crate) Arc<dyn EditorBehavior>);
+
+impl fmt::Debug for BehaviorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EditorBehavior")
+    }
+}
+
+// Behaviors can't be compared, and they don't change what the document
+// holds, so they never make two editors unequal.
+impl PartialEq for BehaviorHandle {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Editor {
+    /// Override some of the editor's behavior. This replaces any behavior
+    /// set before, so to combine several, make one that wraps the others.
+    pub fn with_behavior(mut self, behavior: impl EditorBehavior + 'static) -> Self {
+        self.behavior = Some(Box::new(BehaviorHandle(Arc::new(behavior))));
+        self
+    }
+
+    /// Get the behavior the editor was configured with, or its own.
+    pub(crate) fn behavior(&self) -> Arc<dyn EditorBehavior> {
+        match &self.behavior {
+            Some(handle) => handle.0.clone(),
+            None => Arc::new(DefaultBehavior),
+        }
+    }
+
+    /// Whether an element is void, as the editor's behavior decides.
+    pub fn is_void(&self, element: &Element) -> bool {
+        self.behavior().is_void(self, element)
+    }
+
+    /// Whether a node is inline content, as the editor's behavior decides.
+    pub fn is_inline(&self, node: &Descendant) -> bool {
+        self.behavior().is_inline(self, node)
+    }
+
+    /// Run the behavior's `normalize_node` on each element, last first so
+    /// that fixing one doesn't move those still to be fixed.
+    pub(crate) fn normalize_nodes(&mut self) {
+        let behavior = match &self.behavior {
+            Some(handle) => handle.0.clone(),
+            None => return,
+        };
+
+        let mut paths = vec![];
+        element_paths(&self.children, &Path::new(vec![]), &mut paths);
+        for path in paths.iter().rev() {
+            if let Some(Descendant::Element(_)) = self.descendant_ref(path) {
+                behavior.normalize_node(self, path);
+            }
+        }
+    }
+}
+
+fn element_paths(children: &Children, parent: &Path, out: &mut Vec<Path>) {
+    for (i, child) in children.iter().enumerate() {
+        if let Descendant::Element(element) = child {
+            let path = parent.concat(i);
+            out.push(path.clone());
+            element_paths(element.children_ref(), &path, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{NodeProperties, Operation, Point, Text};
+
+    /// Breaks lines in code blocks rather than splitting them, turns nested
+    /// code blocks into paragraphs, and makes every element with an `src`
+    /// void.
+    struct CodeBlocks;
+
+    impl EditorBehavior for CodeBlocks {
+        fn is_void(&self, editor: &Editor, element: &Element) -> bool {
+            element.property("src").is_some() || DefaultBehavior.is_void(editor, element)
+        }
+
+        fn insert_break(&self, editor: &mut Editor) -> Result<(), ApplyError> {
+            let caret = editor.selection().map(|s| s.anchor().path.clone());
+            let block = caret.and_then(|path| editor.block_path(&path));
+            match block {
+                Some(block) if kind_at(editor, &block) == Some("code") => {
+                    editor.insert_text("\n", None);
+                    Ok(())
+                }
+                _ => DefaultBehavior.insert_break(editor),
+            }
+        }
+
+        fn normalize_node(&self, editor: &mut Editor, path: &Path) {
+            if kind_at(editor, path) == Some("code") && path.len() > 1 {
+                let new = NodeProperties {
+                    kind: Some("paragraph".into()),
+                    ..Default::default()
+                };
+                let old = NodeProperties {
+                    kind: Some("code".into()),
+                    ..Default::default()
+                };
+                editor
+                    .apply(Operation::SetNode {
+                        path: path.clone(),
+                        properties: old,
+                        new_properties: new,
+                    })
+                    .unwrap();
+            }
+        }
+    }
+
+    fn kind_at<'a>(editor: &'a Editor, path: &Path) -> Option<&'a str> {
+        match editor.descendant_ref(path)? {
+            Descendant::Element(element) => Some(element.kind()),
+            Descendant::Text(_) => None,
+        }
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(Element::with_kind("code").add_child(Text::new("let a")))
+            .add_child(Element::with_kind("paragraph").add_child(Text::new("text")))
+            .with_behavior(CodeBlocks)
+    }
+
+    fn caret(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn overridden_insert_break() {
+        let mut editor = editor();
+        editor.select(caret(vec![0, 0], 5));
+        editor.insert_break().unwrap();
+        assert_eq!(editor.children.len(), 2);
+        assert_eq!(editor.block_text(&Path::new(vec![0])).unwrap(), "let a\n");
+
+        // Elsewhere the built-in behavior is kept.
+        editor.select(caret(vec![1, 0], 2));
+        editor.insert_break().unwrap();
+        assert_eq!(editor.children.len(), 3);
+    }
+
+    #[test]
+    fn overridden_queries() {
+        let editor = editor();
+        let image = Element::with_kind("image").add_property("src", "a.png");
+        assert!(editor.is_void(&image));
+        assert!(editor.is_void(&Element::with_kind("emoji")));
+        assert!(!Editor::new().is_void(&image));
+        assert!(editor.is_inline(&Descendant::from(Element::with_kind("link"))));
+    }
+
+    #[test]
+    fn normalize_with_behavior() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::with_kind("block-quote")
+                    .add_child(Element::with_kind("code").add_child(Text::new("a"))),
+            )
+            .with_behavior(CodeBlocks);
+
+        let report = editor.normalize();
+        assert_eq!(report.fixes, 1);
+        assert_eq!(kind_at(&editor, &Path::new(vec![0, 0])), Some("paragraph"));
+    }
+}
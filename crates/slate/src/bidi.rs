@@ -0,0 +1,187 @@
+use std::ops::Range;
+
+use unicode_bidi::{Level, ParagraphBidiInfo};
+
+use crate::transforms::Unit;
+use crate::types::{Descendant, Editor, Path, Point};
+
+/// The property of a block that sets its base direction, to `"ltr"` or
+/// `"rtl"`.
+pub const DIRECTION_PROPERTY: &str = "dir";
+
+/// The direction text is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+impl Direction {
+    /// Parse the value of a block's direction property.
+    pub fn from_property(value: &str) -> Option<Self> {
+        match value {
+            "ltr" => Some(Direction::Ltr),
+            "rtl" => Some(Direction::Rtl),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+        }
+    }
+
+    fn of(level: Level) -> Self {
+        if level.is_rtl() {
+            Direction::Rtl
+        } else {
+            Direction::Ltr
+        }
+    }
+
+    fn level(self) -> Level {
+        match self {
+            Direction::Ltr => Level::ltr(),
+            Direction::Rtl => Level::rtl(),
+        }
+    }
+}
+
+impl Editor {
+    /// Get the base direction of the block at a path: the one set by its
+    /// direction property, or else the direction of its first strong
+    /// character. Blocks without any are left to right.
+    pub fn direction(&self, block: &Path) -> Direction {
+        if let Some(dir) = self.block_direction_property(block) {
+            return dir;
+        }
+
+        match self.block_text(block) {
+            Some(text) if !text.is_empty() => {
+                Direction::of(ParagraphBidiInfo::new(&text, None).paragraph_level)
+            }
+            _ => Direction::Ltr,
+        }
+    }
+
+    /// Split the text of the block at a path into runs of one direction, in
+    /// the order they're displayed from left to right. Each run is a range of
+    /// offsets into the block's text.
+    pub fn visual_runs(&self, block: &Path) -> Vec<(Range<usize>, Direction)> {
+        let text = match self.block_text(block) {
+            Some(text) if !text.is_empty() => text,
+            _ => return vec![],
+        };
+        let level = self.block_direction_property(block).map(Direction::level);
+        let info = ParagraphBidiInfo::new(&text, level);
+
+        let (levels, runs) = info.visual_runs(0..text.len());
+        runs.into_iter()
+            .map(|run| {
+                let dir = Direction::of(levels[run.start]);
+                (run, dir)
+            })
+            .collect()
+    }
+
+    /// Move the caret a character to the left or right on screen, collapsing
+    /// the selection. In right-to-left text, moving left moves forward.
+    ///
+    /// The direction is that of the character after the caret, or of the one
+    /// before it at the end of a block.
+    pub fn move_visually(&mut self, left: bool) {
+        let caret = match &self.selection {
+            Some(selection) if left => selection.start(),
+            Some(selection) => selection.end(),
+            None => return,
+        };
+
+        let rtl = self.direction_at(&caret) == Direction::Rtl;
+        self.move_by(Unit::Character, left != rtl);
+    }
+
+    /// Get the direction of the text at a point.
+    fn direction_at(&self, point: &Point) -> Direction {
+        let (block, offset) = match self.block_offset(point) {
+            Some(found) => found,
+            None => return Direction::Ltr,
+        };
+        let text = match self.block_text(&block) {
+            Some(text) if !text.is_empty() => text,
+            _ => return self.direction(&block),
+        };
+        let level = self.block_direction_property(&block).map(Direction::level);
+        let info = ParagraphBidiInfo::new(&text, level);
+
+        let at = if offset < text.len() {
+            offset
+        } else {
+            text[..offset].char_indices().last().map_or(0, |(i, _)| i)
+        };
+        Direction::of(info.levels[at])
+    }
+
+    fn block_direction_property(&self, block: &Path) -> Option<Direction> {
+        match self.descendant_ref(block)? {
+            Descendant::Element(e) => Direction::from_property(e.property(DIRECTION_PROPERTY)?),
+            Descendant::Text(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Text};
+
+    use super::*;
+
+    const HEBREW: &str = "\u{5e9}\u{5dc}\u{5d5}\u{5dd}";
+
+    fn caret(offset: usize) -> crate::types::Range {
+        let point = Point {
+            path: Path::new(vec![0, 0]),
+            offset,
+        };
+        crate::types::Range::new(point.clone(), point)
+    }
+
+    #[test]
+    fn direction_from_property_or_text() {
+        let editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new(HEBREW)))
+            .add_child(
+                Element::new()
+                    .add_property("dir", "rtl")
+                    .add_child(Text::new("abc")),
+            )
+            .add_child(Element::new().add_child(Text::new("")));
+
+        assert_eq!(editor.direction(&Path::new(vec![0])), Direction::Rtl);
+        assert_eq!(editor.direction(&Path::new(vec![1])), Direction::Rtl);
+        assert_eq!(editor.direction(&Path::new(vec![2])), Direction::Ltr);
+    }
+
+    #[test]
+    fn visual_runs_of_mixed_text() {
+        let text = format!("ab {}", HEBREW);
+        let editor = Editor::new().add_child(Element::new().add_child(Text::new(text.as_str())));
+
+        assert_eq!(
+            editor.visual_runs(&Path::new(vec![0])),
+            vec![(0..3, Direction::Ltr), (3..text.len(), Direction::Rtl)]
+        );
+    }
+
+    #[test]
+    fn move_visually_in_rtl_text() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new(HEBREW)));
+        editor.select(caret(2));
+
+        editor.move_visually(true);
+        assert_eq!(editor.selection(), Some(&caret(4)));
+        editor.move_visually(false);
+        assert_eq!(editor.selection(), Some(&caret(2)));
+    }
+}
@@ -0,0 +1,377 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::commands::Commands;
+use crate::history::History;
+use crate::limits::{Limit, Limits};
+use crate::mark_rules::MarkRules;
+use crate::references::ReferenceRules;
+use crate::sanitize::SanitizeOptions;
+use crate::schema::Schema;
+use crate::transforms::SelectionAffinities;
+use crate::types::{Descendant, Editor, MarkRegistry, Point, Range};
+use crate::typography::Typography;
+
+/// Errors returned when the initial state given to an `EditorBuilder` isn't
+/// valid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// A point of the selection doesn't refer to a text node, or its offset
+    /// lies outside of it.
+    InvalidSelection(Point),
+    /// The content is bigger than the editor's limits allow.
+    LimitExceeded(Limit),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::InvalidSelection(p) => write!(f, "invalid selection point {:?}", p),
+            BuildError::LimitExceeded(limit) => {
+                write!(f, "initial content exceeds limit {:?}", limit)
+            }
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/// Configuration installed on an editor as it's built, like its schema or the
+/// history recording its batches. Functions from an editor to an editor are
+/// plugins too, for configuration set with `Editor`'s `with_` methods, like
+/// its behavior or segmenter.
+pub trait Plugin {
+    fn install(self, editor: Editor) -> Editor;
+}
+
+impl<F: FnOnce(Editor) -> Editor> Plugin for F {
+    fn install(self, editor: Editor) -> Editor {
+        self(editor)
+    }
+}
+
+impl Plugin for Schema {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_schema(self)
+    }
+}
+
+impl Plugin for Limits {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_limits(self)
+    }
+}
+
+impl Plugin for MarkRules {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_mark_rules(self)
+    }
+}
+
+impl Plugin for MarkRegistry {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_mark_registry(self)
+    }
+}
+
+impl Plugin for SanitizeOptions {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_paste_sanitizer(self)
+    }
+}
+
+impl Plugin for Commands {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_commands(self)
+    }
+}
+
+impl Plugin for Typography {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_typography(self)
+    }
+}
+
+impl Plugin for ReferenceRules {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_reference_rules(self)
+    }
+}
+
+impl Plugin for History {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_history(self)
+    }
+}
+
+impl Plugin for SelectionAffinities {
+    fn install(self, editor: Editor) -> Editor {
+        editor.with_selection_affinities(self)
+    }
+}
+
+/// A plugin waiting to be installed, which can be installed again by clones
+/// of the builder.
+#[derive(Clone)]
+struct PluginHandle(Arc<dyn Fn(Editor) -> Editor + Send + Sync>);
+
+impl fmt::Debug for PluginHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Plugin")
+    }
+}
+
+/// Configuration for a new editor, with its initial content.
+#[derive(Debug, Clone)]
+pub struct EditorBuilder {
+    children: Vec<Descendant>,
+    selection: Option<Range>,
+    limits: Option<Limits>,
+    mark_rules: Option<MarkRules>,
+    paste_sanitizer: Option<SanitizeOptions>,
+    plugins: Vec<PluginHandle>,
+    normalizing: bool,
+}
+
+impl Default for EditorBuilder {
+    fn default() -> Self {
+        Self {
+            children: vec![],
+            selection: None,
+            limits: None,
+            mark_rules: None,
+            paste_sanitizer: None,
+            plugins: vec![],
+            normalizing: true,
+        }
+    }
+}
+
+impl Editor {
+    pub fn builder() -> EditorBuilder {
+        EditorBuilder::default()
+    }
+}
+
+impl EditorBuilder {
+    pub fn children<I, D>(mut self, children: I) -> Self
+    where
+        I: IntoIterator<Item = D>,
+        D: Into<Descendant>,
+    {
+        self.children = children.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn child(mut self, child: impl Into<Descendant>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    pub fn selection(mut self, selection: impl Into<Option<Range>>) -> Self {
+        self.selection = selection.into();
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    pub fn mark_rules(mut self, rules: MarkRules) -> Self {
+        self.mark_rules = Some(rules);
+        self
+    }
+
+    pub fn paste_sanitizer(mut self, options: SanitizeOptions) -> Self {
+        self.paste_sanitizer = Some(options);
+        self
+    }
+
+    /// Install a plugin on the editor, after the configuration set on the
+    /// builder and the plugins added before it.
+    pub fn plugin(mut self, plugin: impl Plugin + Clone + Send + Sync + 'static) -> Self {
+        let install = move |editor| plugin.clone().install(editor);
+        self.plugins.push(PluginHandle(Arc::new(install)));
+        self
+    }
+
+    /// Whether to normalize the initial content when building. This is on by
+    /// default, and can be turned off for content known to be normalized.
+    pub fn normalizing(mut self, normalizing: bool) -> Self {
+        self.normalizing = normalizing;
+        self
+    }
+
+    /// Create the editor, checking the selection against the initial
+    /// content, then normalizing the content like `Editor::normalize`, with
+    /// the editor's configuration and plugins, and checking it against the
+    /// limits. The selection is moved along with the content it points into.
+    ///
+    /// The editor starts without pending operations, so normalizing the
+    /// initial content isn't an edit to flush.
+    pub fn build(self) -> Result<Editor, BuildError> {
+        let mut editor = Editor::new();
+        editor.replace_children(self.children);
+        editor.mark_rules = self.mark_rules.map(Box::new);
+        editor.paste_sanitizer = self.paste_sanitizer.map(Box::new);
+        if let Some(limits) = self.limits {
+            editor = editor.with_limits(limits);
+        }
+        for plugin in &self.plugins {
+            editor = (plugin.0)(editor);
+        }
+
+        if let Some(selection) = &self.selection {
+            for point in [selection.anchor(), selection.focus()] {
+                match editor.text_at(&point.path) {
+                    Some(t) if t.text().is_char_boundary(point.offset) => {}
+                    _ => return Err(BuildError::InvalidSelection(point.clone())),
+                }
+            }
+        }
+        editor.selection = self.selection;
+
+        // The limits are checked once the content is normalized, rather than
+        // against each fix.
+        let limits = editor.limits.take().map(|enforced| enforced.limits);
+        if self.normalizing {
+            editor.normalize();
+            editor.operations.clear();
+            editor.previous = None;
+            *editor.actions = Default::default();
+        }
+        if let Some(limit) = limits
+            .as_ref()
+            .and_then(|l| l.exceeded_by(editor.children.iter()))
+        {
+            return Err(BuildError::LimitExceeded(limit));
+        }
+        if let Some(limits) = limits {
+            editor = editor.with_limits(limits);
+        }
+
+        Ok(editor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, Marks, Text};
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn build_normalizes() {
+        let editor = Editor::builder()
+            .child(Element::new())
+            .child(
+                Element::new()
+                    .add_child(Text::new("a"))
+                    .add_child(Text::new("b")),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new().add_child(Text::new("")).into(),
+                Element::new().add_child(Text::new("ab")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn build_without_normalizing() {
+        let editor = Editor::builder()
+            .child(Element::new())
+            .normalizing(false)
+            .build()
+            .unwrap();
+        assert_eq!(editor.children(), vec![Element::new().into()]);
+    }
+
+    #[test]
+    fn build_normalizes_marks() {
+        let editor = Editor::builder()
+            .child(
+                Element::new()
+                    .add_child(Text::with_marks("a", Marks::BOLD | Marks::ITALIC))
+                    .add_child(Text::with_marks("b", Marks::BOLD)),
+            )
+            .mark_rules(MarkRules::new().exclusive(Marks::BOLD, Marks::ITALIC))
+            .build()
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::with_marks("ab", Marks::BOLD))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn build_invalid_selection() {
+        let result = Editor::builder()
+            .child(Element::new().add_child(Text::new("ab")))
+            .selection(Range::new(point(vec![0, 0], 0), point(vec![0, 0], 3)))
+            .build();
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidSelection(point(vec![0, 0], 3)))
+        );
+    }
+
+    #[test]
+    fn build_moves_the_selection_with_normalization() {
+        let editor = Editor::builder()
+            .child(
+                Element::new()
+                    .add_child(Text::new("a"))
+                    .add_child(Text::new("b")),
+            )
+            .selection(Range::new(point(vec![0, 1], 1), point(vec![0, 1], 1)))
+            .build()
+            .unwrap();
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 0], 2), point(vec![0, 0], 2)))
+        );
+        assert!(editor.operations().is_empty());
+    }
+
+    #[test]
+    fn build_with_plugins() {
+        let schema = Schema::default();
+        let editor = Editor::builder()
+            .child(Text::new("loose"))
+            .plugin(schema.clone())
+            .plugin(|editor: Editor| editor.with_author("ann"))
+            .plugin(History::new())
+            .build()
+            .unwrap();
+        assert_eq!(editor.schema(), Some(&schema));
+        assert_eq!(editor.author(), Some("ann"));
+        assert_eq!(editor.history(), Some(&History::new()));
+        assert_eq!(
+            editor.children(),
+            vec![Element::new().add_child(Text::new("loose")).into()]
+        );
+    }
+
+    #[test]
+    fn build_over_limits() {
+        let result = Editor::builder()
+            .child(Element::new().add_child(Text::new("abc")))
+            .limits(Limits::default().max_text_length(2))
+            .build();
+        assert_eq!(result, Err(BuildError::LimitExceeded(Limit::TextLength(2))));
+    }
+}
@@ -0,0 +1,195 @@
+//! Migrating many stored documents at once, like running a schema change
+//! over a whole database, spread over all cores.
+//!
+//! Each document is read from the JSON `serialize::json` writes, migrated,
+//! normalized, checked against limits and written back, and gets a result of
+//! its own, so one broken document doesn't stop the rest.
+
+use std::error::Error;
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::serialize::json::{self, ImportWarning};
+use crate::types::Editor;
+use crate::{Limit, Limits, Schema};
+
+/// How to migrate documents.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BulkOptions {
+    /// The schema documents are normalized under.
+    pub schema: Option<Schema>,
+    /// Limits migrated documents must stay within.
+    pub limits: Option<Limits>,
+    /// Whether documents that need repairs to be read fail instead of being
+    /// repaired.
+    pub strict: bool,
+}
+
+impl BulkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// Why a document couldn't be migrated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentError {
+    /// The document needed a repair to be read, with strict options.
+    Import(ImportWarning),
+    /// The migrated document exceeds one of the limits.
+    LimitExceeded(Limit),
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DocumentError::Import(warning) => write!(f, "cannot import document: {}", warning),
+            DocumentError::LimitExceeded(limit) => {
+                write!(f, "migrated document exceeds {:?}", limit)
+            }
+        }
+    }
+}
+
+impl Error for DocumentError {}
+
+/// A migrated document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrated {
+    /// The document's JSON.
+    pub json: String,
+    /// The repairs made to read the document.
+    pub warnings: Vec<ImportWarning>,
+    /// Whether the document was repaired, migrated or normalized into
+    /// something different, and so needs storing again.
+    pub changed: bool,
+}
+
+/// Read, normalize, check and write documents in parallel. The results are
+/// in the same order as the documents.
+pub fn migrate<S: AsRef<str> + Sync>(
+    documents: &[S],
+    options: &BulkOptions,
+) -> Vec<Result<Migrated, DocumentError>> {
+    migrate_with(documents, options, |_| {})
+}
+
+/// Like `migrate`, running `migration` on each document after reading it and
+/// before normalizing it.
+pub fn migrate_with<S, F>(
+    documents: &[S],
+    options: &BulkOptions,
+    migration: F,
+) -> Vec<Result<Migrated, DocumentError>>
+where
+    S: AsRef<str> + Sync,
+    F: Fn(&mut Editor) + Sync,
+{
+    documents
+        .par_iter()
+        .map(|document| migrate_one(document.as_ref(), options, &migration))
+        .collect()
+}
+
+fn migrate_one(
+    document: &str,
+    options: &BulkOptions,
+    migration: &impl Fn(&mut Editor),
+) -> Result<Migrated, DocumentError> {
+    let (editor, mut warnings) = json::from_string_lossy(document);
+    if options.strict && !warnings.is_empty() {
+        return Err(DocumentError::Import(warnings.remove(0)));
+    }
+
+    let mut editor = match &options.schema {
+        Some(schema) => editor.with_schema(schema.clone()),
+        None => editor,
+    };
+    migration(&mut editor);
+    editor.normalize();
+
+    if let Some(limit) = options
+        .limits
+        .and_then(|limits| limits.exceeded_by(&editor.children))
+    {
+        return Err(DocumentError::LimitExceeded(limit));
+    }
+
+    Ok(Migrated {
+        json: json::to_string(&editor),
+        changed: !warnings.is_empty() || !editor.operations().is_empty(),
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, NodeProperties, Path, Text};
+
+    use super::*;
+
+    fn document(text: &str) -> String {
+        json::to_string(&Editor::new().add_child(Element::new().add_child(Text::new(text))))
+    }
+
+    #[test]
+    fn migrate_documents() {
+        let documents = vec![
+            document("a"),
+            r#"[{ "text": "loose" }]"#.to_string(),
+            "not json".to_string(),
+            document("too long"),
+        ];
+        let options = BulkOptions::new().limits(Limits::default().max_text_length(5));
+
+        let results = migrate_with(&documents, &options, |editor| {
+            editor
+                .set_node(
+                    &Path::new(vec![0]),
+                    NodeProperties::new().kind("heading-one"),
+                )
+                .unwrap();
+        });
+        assert_eq!(results.len(), 4);
+
+        let migrated = results[0].as_ref().unwrap();
+        assert!(migrated.changed);
+        assert!(migrated.json.contains("heading-one"));
+
+        let repaired = results[1].as_ref().unwrap();
+        assert_eq!(
+            repaired.warnings,
+            vec![ImportWarning::LooseText(Path::new(vec![0]))]
+        );
+        assert!(results[2].is_ok());
+        assert_eq!(
+            results[3],
+            Err(DocumentError::LimitExceeded(Limit::TextLength(5)))
+        );
+
+        let strict = migrate(&documents[..2], &BulkOptions::new().strict(true));
+        assert!(!strict[0].as_ref().unwrap().changed);
+        assert_eq!(
+            strict[1],
+            Err(DocumentError::Import(ImportWarning::LooseText(Path::new(
+                vec![0]
+            ))))
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use crate::autolink::autolink_fragment;
+use crate::serialize::{html, plain};
+use crate::transforms::ApplyError;
+use crate::types::{Descendant, Editor, Location};
+
+/// The formats a cut or copy produces for the system clipboard. When pasting,
+/// any of them may be missing depending on where the content came from.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ClipboardPayload {
+    pub fragment: Option<Vec<Descendant>>,
+    pub plain_text: Option<String>,
+    pub html: Option<String>,
+}
+
+impl ClipboardPayload {
+    pub fn from_fragment(fragment: Vec<Descendant>) -> Self {
+        Self {
+            plain_text: Some(plain::to_string(&fragment)),
+            html: Some(html::to_string(&fragment)),
+            fragment: Some(fragment),
+        }
+    }
+}
+
+impl Editor {
+    /// Copy the content at a location, or at the selection if none is given.
+    pub fn copy(&self, at: Option<Location>) -> Option<ClipboardPayload> {
+        let range = match at {
+            Some(at) => self.range(at)?,
+            None => self.selection.clone()?,
+        };
+
+        Some(ClipboardPayload::from_fragment(self.fragment(range)))
+    }
+
+    /// Copy the content at a location, or at the selection if none is given,
+    /// and then delete it.
+    pub fn cut(&mut self, at: Option<Location>) -> Option<ClipboardPayload> {
+        let range = match at {
+            Some(at) => self.range(at)?,
+            None => self.selection.clone()?,
+        };
+
+        let payload = ClipboardPayload::from_fragment(self.fragment(range.clone()));
+        self.delete_range(&range);
+        Some(payload)
+    }
+
+    /// Insert clipboard content at the selection, using the richest format the
+    /// payload has. The content is sanitized if the editor has a paste
+    /// sanitizer, and URLs in it are linked if the editor autolinks.
+    pub fn paste(&mut self, payload: ClipboardPayload) -> Result<(), ApplyError> {
+        let fragment = if let Some(fragment) = payload.fragment {
+            fragment
+        } else if let Some(html) = payload.html {
+            html::from_string(&html)
+        } else if let Some(text) = payload.plain_text {
+            plain::from_string(&text)
+        } else {
+            return Ok(());
+        };
+        let fragment = if self.autolink {
+            autolink_fragment(fragment, &self.code_kinds())
+        } else {
+            fragment
+        };
+
+        self.insert_fragment_data(fragment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, Marks, Point, Range, Text};
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("one "))
+                    .add_child(Text::with_marks("two", Marks::BOLD)),
+            )
+            .add_child(Element::new().add_child(Text::new("three")))
+    }
+
+    #[test]
+    fn copy_selection() {
+        let mut editor = editor();
+        editor.select(Range::new(point(vec![0, 1], 1), point(vec![1, 0], 2)));
+        assert_eq!(
+            editor.copy(None),
+            Some(ClipboardPayload {
+                fragment: Some(vec![
+                    Element::new()
+                        .add_child(Text::with_marks("wo", Marks::BOLD))
+                        .into(),
+                    Element::new().add_child(Text::new("th")).into(),
+                ]),
+                plain_text: Some("wo\nth".into()),
+                html: Some("<p><strong>wo</strong></p><p>th</p>".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn copy_without_selection() {
+        assert_eq!(editor().copy(None), None);
+    }
+
+    #[test]
+    fn cut_path() {
+        let mut editor = editor();
+        let payload = editor.cut(Some(crate::types::Path::new(vec![1]).into()));
+        assert_eq!(payload.unwrap().plain_text, Some("three".into()));
+        assert_eq!(
+            editor.child(1),
+            Some(&Element::new().add_child(Text::new("")).into())
+        );
+    }
+
+    #[test]
+    fn cut_outside_of_the_text() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("éé")));
+        let before = editor.children();
+
+        let mid_character = Range::new(point(vec![0, 0], 1), point(vec![0, 0], 4));
+        assert_eq!(editor.cut(Some(mid_character.into())), None);
+        let past_end = Range::new(point(vec![0, 0], 0), point(vec![0, 0], 9));
+        assert_eq!(editor.cut(Some(past_end.into())), None);
+        assert_eq!(editor.children(), before);
+    }
+
+    #[test]
+    fn paste_at_an_invalid_selection() {
+        let payload = ClipboardPayload {
+            plain_text: Some("a".into()),
+            ..Default::default()
+        };
+
+        // Selecting a point outside of the text is ignored.
+        let mut editor = editor();
+        editor.select(point(vec![1, 0], 99));
+        assert_eq!(editor.selection(), None);
+        assert_eq!(editor.paste(payload.clone()), Ok(()));
+
+        // A selection restored from elsewhere is checked before pasting.
+        editor.selection = Some(Range::new(point(vec![1, 0], 99), point(vec![1, 0], 99)));
+        assert_eq!(
+            editor.paste(payload),
+            Err(ApplyError::OffsetOutOfRange(vec![1, 0].into(), 99))
+        );
+        assert_eq!(editor.children(), self::editor().children());
+    }
+
+    #[test]
+    fn paste_fragment_over_html() {
+        let mut editor = editor();
+        editor.select(point(vec![1, 0], 5));
+        editor
+            .paste(ClipboardPayload {
+                fragment: Some(vec![Element::new().add_child(Text::new("!")).into()]),
+                plain_text: Some("?".into()),
+                html: Some("<p>?</p>".into()),
+            })
+            .unwrap();
+        assert_eq!(
+            editor.child(1),
+            Some(
+                &Element::new()
+                    .add_child(Text::new("three"))
+                    .add_child(Text::new("!"))
+                    .add_child(Text::new(""))
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn paste_html() {
+        let mut editor = editor();
+        editor.select(point(vec![1, 0], 0));
+        editor
+            .paste(ClipboardPayload {
+                html: Some("<p><em>new</em></p><h1>title</h1>".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            editor.children()[1..],
+            [
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(Text::with_marks("new", Marks::ITALIC))
+                    .into(),
+                Element::with_kind("heading-one")
+                    .add_child(Text::new("title"))
+                    .add_child(Text::new("three"))
+                    .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn paste_plain_text() {
+        let mut editor = editor();
+        editor.select(point(vec![1, 0], 2));
+        editor
+            .paste(ClipboardPayload {
+                plain_text: Some("a\nb".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            editor.children()[1..],
+            [
+                Element::new()
+                    .add_child(Text::new("th"))
+                    .add_child(Text::new("a"))
+                    .into(),
+                Element::new()
+                    .add_child(Text::new("b"))
+                    .add_child(Text::new("ree"))
+                    .into(),
+            ]
+        );
+    }
+}
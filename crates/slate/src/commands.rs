@@ -0,0 +1,315 @@
+//! Named commands, like `"toggle_bold"` or `"indent"`, with the keys bound to
+//! them, so that toolbars, menus and keymaps share one set of actions.
+//!
+//! Keys are written as in ProseMirror: modifiers and a key joined by dashes,
+//! like `"Mod-b"` or `"Shift-Tab"`. `Mod` stands for command on Apple
+//! platforms and control elsewhere, which frontends resolve before looking a
+//! key up. Modifiers can be given in any order, and keys in any case.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::transforms::Alignment;
+use crate::types::{Editor, Marks, Path};
+
+type Handler = Arc<dyn Fn(&mut Editor) + Send + Sync>;
+
+/// Errors returned when running a command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// No command is registered under a name.
+    Unknown(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Unknown(name) => write!(f, "unknown command {:?}", name),
+        }
+    }
+}
+
+impl Error for CommandError {}
+
+/// A set of commands and the keys bound to them.
+///
+/// `Commands::default()` holds the built-in commands, which only use the
+/// editor's own transforms, and `Commands::new()` none. Undo isn't built in,
+/// since the editor doesn't keep a history, so apps register their own.
+#[derive(Clone)]
+pub struct Commands {
+    handlers: BTreeMap<String, Handler>,
+    /// Command names by normalized key.
+    keymap: HashMap<String, String>,
+}
+
+impl fmt::Debug for Commands {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Commands")
+            .field("commands", &self.handlers.keys().collect::<Vec<_>>())
+            .field("keymap", &self.keymap)
+            .finish()
+    }
+}
+
+// Handlers can't be compared, so commands are equal when they have the same
+// names and keys.
+impl PartialEq for Commands {
+    fn eq(&self, other: &Self) -> bool {
+        self.handlers.keys().eq(other.handlers.keys()) && self.keymap == other.keymap
+    }
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        let align = |alignment| move |editor: &mut Editor| editor.set_alignment(Some(alignment));
+
+        Self::new()
+            .register("toggle_bold", |e| toggle_mark(e, Marks::BOLD))
+            .bind("Mod-b", "toggle_bold")
+            .register("toggle_italic", |e| toggle_mark(e, Marks::ITALIC))
+            .bind("Mod-i", "toggle_italic")
+            .register("toggle_underline", |e| toggle_mark(e, Marks::UNDERLINE))
+            .bind("Mod-u", "toggle_underline")
+            .register("indent", Editor::increase_indent)
+            .bind("Tab", "indent")
+            .register("outdent", Editor::decrease_indent)
+            .bind("Shift-Tab", "outdent")
+            .register("align_left", align(Alignment::Left))
+            .bind("Mod-Shift-l", "align_left")
+            .register("align_center", align(Alignment::Center))
+            .bind("Mod-Shift-e", "align_center")
+            .register("align_right", align(Alignment::Right))
+            .bind("Mod-Shift-r", "align_right")
+            .register("align_justify", align(Alignment::Justify))
+            .bind("Mod-Shift-j", "align_justify")
+            .register("delete_backward", Editor::delete_backward)
+            .bind("Backspace", "delete_backward")
+            .register("delete_word_backward", |e| e.delete_word(true))
+            .bind("Alt-Backspace", "delete_word_backward")
+            .register("delete_word_forward", |e| e.delete_word(false))
+            .bind("Alt-Delete", "delete_word_forward")
+            .register("select_all", |e| e.select(Path::new(vec![])))
+            .bind("Mod-a", "select_all")
+    }
+}
+
+impl Commands {
+    /// Create a set without any commands.
+    pub fn new() -> Self {
+        Self {
+            handlers: BTreeMap::new(),
+            keymap: HashMap::new(),
+        }
+    }
+
+    /// Register a command, replacing any of the same name.
+    pub fn register(
+        mut self,
+        name: impl Into<String>,
+        handler: impl Fn(&mut Editor) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers.insert(name.into(), Arc::new(handler));
+        self
+    }
+
+    /// Remove a command and the keys bound to it.
+    pub fn unregister(mut self, name: &str) -> Self {
+        self.handlers.remove(name);
+        self.keymap.retain(|_, command| command != name);
+        self
+    }
+
+    /// Bind a key to a command, replacing the command it was bound to.
+    pub fn bind(mut self, key: &str, name: impl Into<String>) -> Self {
+        self.keymap.insert(normalize_key(key), name.into());
+        self
+    }
+
+    pub fn unbind(mut self, key: &str) -> Self {
+        self.keymap.remove(&normalize_key(key));
+        self
+    }
+
+    /// Get the names of the commands, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.handlers.contains_key(name)
+    }
+
+    /// Get the command bound to a key.
+    pub fn command_for_key(&self, key: &str) -> Option<&str> {
+        self.keymap.get(&normalize_key(key)).map(String::as_str)
+    }
+
+    /// Get the keys bound to a command, normalized and sorted, as a menu
+    /// would list them.
+    pub fn keys_for(&self, name: &str) -> Vec<&str> {
+        let mut keys: Vec<_> = self
+            .keymap
+            .iter()
+            .filter(|(_, command)| *command == name)
+            .map(|(key, _)| key.as_str())
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+}
+
+impl Editor {
+    /// Use a set of commands instead of the built-in ones.
+    pub fn with_commands(mut self, commands: Commands) -> Self {
+        self.commands = Some(Box::new(commands));
+        self
+    }
+
+    /// Get the editor's commands, or `None` if it uses the built-in ones.
+    pub fn commands(&self) -> Option<&Commands> {
+        self.commands.as_deref()
+    }
+
+    /// Run a command by name.
+    pub fn run_command(&mut self, name: &str) -> Result<(), CommandError> {
+        let handler = match &self.commands {
+            Some(commands) => commands.handlers.get(name).cloned(),
+            None => BUILTIN.with(|commands| commands.handlers.get(name).cloned()),
+        };
+        let handler = handler.ok_or_else(|| CommandError::Unknown(name.into()))?;
+        handler(self);
+        Ok(())
+    }
+
+    /// Run the command bound to a key. Returns whether one was, so frontends
+    /// can let the key through otherwise.
+    pub fn run_key(&mut self, key: &str) -> bool {
+        let name = match &self.commands {
+            Some(commands) => commands.command_for_key(key).map(String::from),
+            None => BUILTIN.with(|commands| commands.command_for_key(key).map(String::from)),
+        };
+        name.is_some_and(|name| self.run_command(&name).is_ok())
+    }
+}
+
+thread_local! {
+    /// The built-in commands, shared by editors that aren't given their own.
+    static BUILTIN: Commands = Commands::default();
+}
+
+/// Add marks to the selection, or remove them if the text at its start
+/// already has them all. At a collapsed selection, the marks the next
+/// inserted text gets are toggled instead.
+fn toggle_mark(editor: &mut Editor, marks: Marks) {
+    let selection = match &editor.selection {
+        Some(selection) => selection.clone(),
+        None => return,
+    };
+    let pending = editor.marks.filter(|_| selection.is_collapsed());
+    let active = pending.or_else(|| editor.text_at(&selection.start().path).map(|t| t.marks()));
+
+    if active.is_some_and(|active| active.contains(marks)) {
+        editor.remove_mark(marks);
+    } else {
+        editor.add_mark(marks);
+    }
+}
+
+/// Write a key with its modifiers in a standard order and casing, so that
+/// `"shift-mod-B"` and `"Mod-Shift-b"` are the same key.
+fn normalize_key(key: &str) -> String {
+    // A trailing dash is the minus key rather than a separator.
+    let (modifiers, key) = match key.strip_suffix("--") {
+        Some(rest) => (rest, "-"),
+        None => match key.rfind('-') {
+            Some(i) if i + 1 < key.len() => (&key[..i], &key[i + 1..]),
+            _ => ("", key),
+        },
+    };
+
+    let mut modifiers: Vec<&str> = modifiers
+        .split('-')
+        .filter(|m| !m.is_empty())
+        .map(|m| match m.to_lowercase().as_str() {
+            "alt" | "option" => "Alt",
+            "ctrl" | "control" => "Ctrl",
+            "meta" | "cmd" | "command" => "Meta",
+            "mod" => "Mod",
+            "shift" => "Shift",
+            _ => "",
+        })
+        .filter(|m| !m.is_empty())
+        .collect();
+    modifiers.sort_unstable();
+    modifiers.dedup();
+
+    modifiers.push(key);
+    modifiers.join("-").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Point, Range, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn editor() -> Editor {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abc")));
+        editor.select(Range::new(point(vec![0, 0], 0), point(vec![0, 0], 3)));
+        editor
+    }
+
+    fn marks(editor: &Editor) -> Option<Marks> {
+        editor.text_at(&Path::new(vec![0, 0])).map(Text::marks)
+    }
+
+    #[test]
+    fn normalize_keys() {
+        assert_eq!(normalize_key("Mod-Shift-B"), "mod-shift-b");
+        assert_eq!(normalize_key("shift-mod-b"), "mod-shift-b");
+        assert_eq!(normalize_key("Cmd-Alt-Backspace"), "alt-meta-backspace");
+        assert_eq!(normalize_key("Mod--"), "mod--");
+        assert_eq!(normalize_key("-"), "-");
+    }
+
+    #[test]
+    fn run_builtin_commands() {
+        let mut editor = editor();
+        editor.run_command("toggle_bold").unwrap();
+        assert_eq!(marks(&editor), Some(Marks::BOLD));
+        assert!(editor.run_key("mod-B"));
+        assert_eq!(marks(&editor), Some(Marks::empty()));
+
+        assert!(!editor.run_key("Mod-q"));
+        assert_eq!(
+            editor.run_command("undo"),
+            Err(CommandError::Unknown("undo".into()))
+        );
+    }
+
+    #[test]
+    fn custom_commands() {
+        let commands = Commands::default()
+            .register("shout", |e: &mut Editor| e.insert_text("!", None))
+            .bind("Mod-b", "shout")
+            .unregister("indent");
+        assert_eq!(commands.command_for_key("Tab"), None);
+        assert_eq!(commands.keys_for("shout"), vec!["mod-b"]);
+        assert!(!commands.contains("indent"));
+
+        let mut editor = editor().with_commands(commands);
+        assert!(editor.run_key("Mod-b"));
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("!".into()));
+        assert_eq!(marks(&editor), Some(Marks::empty()));
+    }
+}
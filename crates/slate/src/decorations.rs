@@ -0,0 +1,182 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::types::{Decoration, Descendant, Editor, Element, Path, Point, Range};
+
+/// Computes the decorations of a block, like syntax or search highlights,
+/// for renderers to split its text by with `Text::decorations`.
+pub trait Decorator: Send + Sync {
+    /// Get the decorations of the block at `path`, with ranges in its texts.
+    fn decorate(&self, block: &Element, path: &Path) -> Vec<Decoration>;
+}
+
+/// The decorator an editor was configured with.
+#[derive(Clone)]
+pub(crate) struct DecoratorHandle(Arc<dyn Decorator>);
+
+impl fmt::Debug for DecoratorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Decorator")
+    }
+}
+
+/// The decorations of a block, with paths relative to it so that they stay
+/// valid as the block moves, and the version they were computed for.
+#[derive(Debug, Clone, PartialEq)]
+struct BlockDecorations {
+    version: u64,
+    decorations: Vec<Decoration>,
+}
+
+impl Editor {
+    /// Decorate blocks with `decorator`, caching the decorations of each one
+    /// until it changes.
+    pub fn with_decorator(mut self, decorator: impl Decorator + 'static) -> Self {
+        self.caches.decorator = Some(DecoratorHandle(Arc::new(decorator)));
+        self
+    }
+
+    /// Get the decorations of the block at a path, computing them if they
+    /// aren't cached for `version` yet. Nothing is decorated without a
+    /// decorator, or if there's no element at the path.
+    ///
+    /// Cached decorations are dropped as operations changing their block are
+    /// applied, so typing in one block only recomputes that block's. The
+    /// version is the caller's, to bump when the decorations change for
+    /// reasons other than the document, like a new search.
+    pub fn decorations_for(&mut self, path: &Path, version: u64) -> Vec<Decoration> {
+        let decorator = match &self.caches.decorator {
+            Some(decorator) => decorator.clone(),
+            None => return vec![],
+        };
+        let block = match self.descendant_ref(path) {
+            Some(Descendant::Element(block)) => block,
+            _ => return vec![],
+        };
+
+        let root = Path::new(vec![]);
+        let cached = self
+            .cache::<BlockDecorations>()
+            .and_then(|cache| cache.get(path))
+            .filter(|cached| cached.version == version);
+        let relative = match cached {
+            Some(cached) => cached.decorations.clone(),
+            None => {
+                let decorations: Vec<_> = decorator
+                    .0
+                    .decorate(block, path)
+                    .into_iter()
+                    .filter_map(|(range, tags)| Some((rebase(&range, path, &root)?, tags)))
+                    .collect();
+                self.cache_mut::<BlockDecorations>().insert(
+                    path.clone(),
+                    BlockDecorations {
+                        version,
+                        decorations: decorations.clone(),
+                    },
+                );
+                decorations
+            }
+        };
+
+        relative
+            .into_iter()
+            .filter_map(|(range, tags)| Some((rebase(&range, &root, path)?, tags)))
+            .collect()
+    }
+}
+
+/// Move a range from under one path to under another, or get `None` if it
+/// isn't under the first.
+fn rebase(range: &Range, from: &Path, to: &Path) -> Option<Range> {
+    let point = |point: &Point| {
+        let rest = point.path.as_slice().strip_prefix(from.as_slice())?;
+        Some(Point {
+            path: Path::new(to.as_slice().iter().chain(rest).copied().collect()),
+            offset: point.offset,
+        })
+    };
+    Some(Range::new(point(range.anchor())?, point(range.focus())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::types::{Node, Operation, Text};
+
+    use super::*;
+
+    /// Highlights every "a", counting the blocks it decorates.
+    struct Highlight(Arc<AtomicUsize>);
+
+    impl Decorator for Highlight {
+        fn decorate(&self, block: &Element, path: &Path) -> Vec<Decoration> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            let text = match block.child(0) {
+                Some(Descendant::Text(text)) => text.text(),
+                _ => return vec![],
+            };
+            let point = |offset| Point {
+                path: path.concat(0),
+                offset,
+            };
+            text.match_indices('a')
+                .map(|(i, _)| {
+                    let tags: HashSet<String> = Some("a".to_string()).into_iter().collect();
+                    (Range::new(point(i), point(i + 1)), tags)
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn recompute_changed_blocks_only() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("ab")))
+            .add_child(Element::new().add_child(Text::new("ba")))
+            .with_decorator(Highlight(calls.clone()));
+        let (first, second) = (Path::new(vec![0]), Path::new(vec![1]));
+
+        assert_eq!(editor.decorations_for(&first, 0).len(), 1);
+        assert_eq!(editor.decorations_for(&second, 0).len(), 1);
+        assert_eq!(editor.decorations_for(&second, 0).len(), 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        editor
+            .apply(Operation::InsertText {
+                path: Path::new(vec![0, 0]),
+                offset: 0,
+                text: "a".into(),
+            })
+            .unwrap();
+        editor.flush();
+        assert_eq!(editor.decorations_for(&first, 0).len(), 2);
+        assert_eq!(editor.decorations_for(&second, 0).len(), 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        editor.decorations_for(&second, 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn cached_decorations_move_with_their_block() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("a")))
+            .with_decorator(Highlight(calls.clone()));
+        editor.decorations_for(&Path::new(vec![0]), 0);
+
+        editor
+            .apply(Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(Element::new().add_child(Text::new("b"))),
+            })
+            .unwrap();
+        let decorations = editor.decorations_for(&Path::new(vec![1]), 0);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(decorations[0].0.anchor().path, Path::new(vec![1, 0]));
+    }
+}
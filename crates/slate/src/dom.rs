@@ -0,0 +1,371 @@
+//! Mapping between points and the text positions of platform views, like a
+//! DOM node and offset, an `NSRange` or a UTF-16 index, so that frontend
+//! bindings share the math instead of each getting it subtly wrong.
+//!
+//! Points count offsets in bytes of UTF-8, where platforms count UTF-16 code
+//! units. A frontend renders each text node as one or more leaves, split at
+//! the edges of its decorations as in Slate React, and each leaf as a node of
+//! its own, so a point is found in a platform node by walking the leaves of
+//! its text.
+
+use crate::types::{text_entries, Decoration, Descendant, Editor, Path, Point, Range, Text};
+
+/// The number of UTF-16 code units needed to encode some text.
+pub fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// Convert a byte offset in some text to a UTF-16 offset. Returns `None` if
+/// the offset isn't on a character boundary.
+pub fn to_utf16(text: &str, offset: usize) -> Option<usize> {
+    Some(utf16_len(text.get(..offset)?))
+}
+
+/// Convert a UTF-16 offset in some text to a byte offset. Returns `None` if the
+/// offset is past the end of the text or between the halves of a surrogate
+/// pair.
+pub fn from_utf16(text: &str, offset: usize) -> Option<usize> {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= offset {
+            return (units == offset).then_some(i);
+        }
+        units += c.len_utf16();
+    }
+    (units == offset).then_some(text.len())
+}
+
+/// What a platform node renders.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomTarget {
+    /// A leaf of the text node at a path, by its index among the text's
+    /// leaves.
+    Leaf { path: Path, leaf: usize },
+    /// A node at a path, as an element rendering it. An offset in the
+    /// platform node counts the node's children before it.
+    Node(Path),
+}
+
+/// A position in a platform view: a node and a UTF-16 offset in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomPoint<N> {
+    pub node: N,
+    pub offset: usize,
+}
+
+/// How a frontend renders a document, implemented by its binding.
+pub trait DomMapping {
+    /// A node in the platform's view, like a DOM node.
+    type Node;
+
+    /// Get the platform node rendering a leaf of the text node at a path.
+    fn leaf_node(&self, path: &Path, leaf: usize) -> Option<Self::Node>;
+
+    /// Find what a platform node renders, or `None` if it's no part of the
+    /// document.
+    fn resolve(&self, node: &Self::Node) -> Option<DomTarget>;
+
+    /// Get the decorations the text at a path is split into leaves by, with
+    /// offsets in the text. Text isn't decorated by default.
+    fn decorations(&self, _path: &Path, _text: &Text) -> Vec<Decoration> {
+        vec![]
+    }
+}
+
+impl Editor {
+    /// Get the lengths of the leaves a mapping renders the text at a path as.
+    fn leaf_lengths<M: DomMapping>(&self, mapping: &M, path: &Path) -> Option<Vec<usize>> {
+        let text = self.text_at(path)?;
+        let leaves = text.clone().decorations(mapping.decorations(path, text));
+        Some(leaves.iter().map(|leaf| leaf.text().len()).collect())
+    }
+
+    /// Get the position of a point in a platform view. A point on the
+    /// boundary between two leaves is put at the end of the first, as
+    /// browsers do.
+    pub fn to_dom_point<M: DomMapping>(
+        &self,
+        mapping: &M,
+        point: &Point,
+    ) -> Option<DomPoint<M::Node>> {
+        let text = self.text_at(&point.path)?.text();
+        let mut start = 0;
+
+        for (leaf, len) in self
+            .leaf_lengths(mapping, &point.path)?
+            .into_iter()
+            .enumerate()
+        {
+            if point.offset <= start + len {
+                let offset = to_utf16(text.get(start..)?, point.offset - start)?;
+                let node = mapping.leaf_node(&point.path, leaf)?;
+                return Some(DomPoint { node, offset });
+            }
+            start += len;
+        }
+
+        None
+    }
+
+    /// Get the point at a position in a platform view. Offsets past the end
+    /// of a leaf, like after the zero-width character rendered for empty
+    /// text, are clamped to it, and those between the halves of a surrogate
+    /// pair are moved to the start of the pair.
+    pub fn to_slate_point<M: DomMapping>(
+        &self,
+        mapping: &M,
+        point: &DomPoint<M::Node>,
+    ) -> Option<Point> {
+        let (path, leaf) = match mapping.resolve(&point.node)? {
+            DomTarget::Leaf { path, leaf } => (path, leaf),
+            DomTarget::Node(path) => return self.point_in_node(&path, point.offset),
+        };
+
+        let text = self.text_at(&path)?.text();
+        let lengths = self.leaf_lengths(mapping, &path)?;
+        let start: usize = lengths.iter().take(leaf).sum();
+        let leaf_text = text.get(start..start + lengths.get(leaf)?)?;
+
+        let units = point.offset.min(utf16_len(leaf_text));
+        let offset = (0..=units)
+            .rev()
+            .find_map(|units| from_utf16(leaf_text, units))?;
+        Some(Point {
+            path,
+            offset: start + offset,
+        })
+    }
+
+    /// Get the point before a node's child at an index, or at the end of the
+    /// node past its last child.
+    fn point_in_node(&self, path: &Path, index: usize) -> Option<Point> {
+        let len = match path.as_slice() {
+            [] => self.children.len(),
+            _ => match self.descendant_ref(path)? {
+                Descendant::Element(e) => e.children_ref().len(),
+                Descendant::Text(t) => {
+                    let offset = from_utf16(t.text(), index).unwrap_or(t.text().len());
+                    return Some(Point {
+                        path: path.clone(),
+                        offset,
+                    });
+                }
+            },
+        };
+
+        if index < len {
+            self.start(path.concat(index))
+        } else {
+            self.end(path.clone())
+        }
+    }
+
+    /// Get the block containing a point, and the point's UTF-16 offset in the
+    /// block's text.
+    pub fn utf16_offset(&self, point: &Point) -> Option<(Path, usize)> {
+        let (block, offset) = self.block_offset(point)?;
+        let offset = to_utf16(&self.block_text(&block)?, offset)?;
+        Some((block, offset))
+    }
+
+    /// Get the point at a UTF-16 offset in the text of a block.
+    pub fn point_at_utf16_offset(&self, block: &Path, offset: usize) -> Option<Point> {
+        let offset = from_utf16(&self.block_text(block)?, offset)?;
+        self.point_at_offset(block, offset)
+    }
+
+    /// Get the paths of the blocks holding text, in document order.
+    fn text_blocks(&self) -> Vec<Path> {
+        let mut blocks: Vec<Path> = vec![];
+        for (_, path) in text_entries(self.children.iter(), &Path::new(vec![])) {
+            if let Some(block) = self.block_path(&path) {
+                if blocks.last() != Some(&block) {
+                    blocks.push(block);
+                }
+            }
+        }
+        blocks
+    }
+
+    /// Get a range as the location and length of an `NSRange` in the text of
+    /// the document, with blocks separated by line breaks, as a native text
+    /// view on Apple platforms holds it.
+    pub fn to_ns_range(&self, range: &Range) -> Option<(usize, usize)> {
+        let (start, end) = range.edges(false);
+        let (start, end) = (self.document_utf16(start)?, self.document_utf16(end)?);
+        Some((start, end - start))
+    }
+
+    /// Get the range an `NSRange` covers in the text of the document, as
+    /// returned by [`Editor::to_ns_range`].
+    pub fn from_ns_range(&self, location: usize, length: usize) -> Option<Range> {
+        Some(Range::new(
+            self.point_at_document_utf16(location)?,
+            self.point_at_document_utf16(location + length)?,
+        ))
+    }
+
+    fn document_utf16(&self, point: &Point) -> Option<usize> {
+        let (block, offset) = self.utf16_offset(point)?;
+        let mut start = 0;
+        for path in self.text_blocks() {
+            if path == block {
+                return Some(start + offset);
+            }
+            start += utf16_len(&self.block_text(&path)?) + 1;
+        }
+        None
+    }
+
+    fn point_at_document_utf16(&self, offset: usize) -> Option<Point> {
+        let mut start = 0;
+        for path in self.text_blocks() {
+            let len = utf16_len(&self.block_text(&path)?);
+            if offset <= start + len {
+                return self.point_at_utf16_offset(&path, offset - start);
+            }
+            start += len + 1;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::types::Element;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: Path::new(path),
+            offset,
+        }
+    }
+
+    /// Renders each leaf as a node named by its path and index, and elements
+    /// by their path. The second text is decorated from byte 3 to 5.
+    struct Nodes;
+
+    impl DomMapping for Nodes {
+        type Node = String;
+
+        fn leaf_node(&self, path: &Path, leaf: usize) -> Option<String> {
+            Some(format!("{:?}:{}", path.as_slice(), leaf))
+        }
+
+        fn resolve(&self, node: &String) -> Option<DomTarget> {
+            let leaves: HashMap<String, DomTarget> = [
+                (vec![0, 0], 0),
+                (vec![1, 0], 0),
+                (vec![1, 0], 1),
+                (vec![1, 0], 2),
+            ]
+            .iter()
+            .map(|(path, leaf)| {
+                let path = Path::new(path.clone());
+                let target = DomTarget::Leaf {
+                    path: path.clone(),
+                    leaf: *leaf,
+                };
+                (self.leaf_node(&path, *leaf).unwrap(), target)
+            })
+            .collect();
+            match node.as_str() {
+                "root" => Some(DomTarget::Node(Path::new(vec![]))),
+                node => leaves.get(node).cloned(),
+            }
+        }
+
+        fn decorations(&self, path: &Path, _: &Text) -> Vec<Decoration> {
+            if path.as_slice() != [1, 0] {
+                return vec![];
+            }
+            let range = Range::new(point(vec![], 3), point(vec![], 5));
+            vec![(range, ["find".to_string()].iter().cloned().collect())]
+        }
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("a😀b")))
+            .add_child(Element::new().add_child(Text::new("héllo")))
+    }
+
+    #[test]
+    fn utf16_offsets() {
+        assert_eq!(utf16_len("a😀b"), 4);
+        assert_eq!(to_utf16("a😀b", 5), Some(3));
+        assert_eq!(to_utf16("a😀b", 2), None);
+        assert_eq!(from_utf16("a😀b", 3), Some(5));
+        assert_eq!(from_utf16("a😀b", 2), None);
+        assert_eq!(from_utf16("a😀b", 4), Some(6));
+        assert_eq!(from_utf16("a😀b", 5), None);
+    }
+
+    #[test]
+    fn dom_points() {
+        let editor = editor();
+        let dom = |node: &str, offset| DomPoint {
+            node: node.to_string(),
+            offset,
+        };
+
+        assert_eq!(
+            editor.to_dom_point(&Nodes, &point(vec![0, 0], 5)),
+            Some(dom("[0, 0]:0", 3))
+        );
+        // "héllo" is split into "hé", "ll" and "o", and "é" takes two bytes
+        // but one UTF-16 unit.
+        assert_eq!(
+            editor.to_dom_point(&Nodes, &point(vec![1, 0], 3)),
+            Some(dom("[1, 0]:0", 2))
+        );
+        assert_eq!(editor.to_dom_point(&Nodes, &point(vec![1, 0], 2)), None);
+        assert_eq!(
+            editor.to_dom_point(&Nodes, &point(vec![1, 0], 5)),
+            Some(dom("[1, 0]:1", 2))
+        );
+        assert_eq!(
+            editor.to_dom_point(&Nodes, &point(vec![1, 0], 6)),
+            Some(dom("[1, 0]:2", 1))
+        );
+
+        assert_eq!(
+            editor.to_slate_point(&Nodes, &dom("[0, 0]:0", 2)),
+            Some(point(vec![0, 0], 1))
+        );
+        assert_eq!(
+            editor.to_slate_point(&Nodes, &dom("[1, 0]:2", 7)),
+            Some(point(vec![1, 0], 6))
+        );
+        assert_eq!(
+            editor.to_slate_point(&Nodes, &dom("root", 1)),
+            Some(point(vec![1, 0], 0))
+        );
+        assert_eq!(
+            editor.to_slate_point(&Nodes, &dom("root", 2)),
+            Some(point(vec![1, 0], 6))
+        );
+        assert_eq!(editor.to_slate_point(&Nodes, &dom("elsewhere", 0)), None);
+    }
+
+    #[test]
+    fn ns_ranges() {
+        let editor = editor();
+        assert_eq!(
+            editor.utf16_offset(&point(vec![1, 0], 3)),
+            Some((Path::new(vec![1]), 2))
+        );
+        assert_eq!(
+            editor.point_at_utf16_offset(&Path::new(vec![0]), 3),
+            Some(point(vec![0, 0], 5))
+        );
+
+        let range = Range::new(point(vec![0, 0], 5), point(vec![1, 0], 3));
+        assert_eq!(editor.to_ns_range(&range), Some((3, 4)));
+        assert_eq!(editor.from_ns_range(3, 4), Some(range));
+        assert_eq!(editor.from_ns_range(3, 20), None);
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::types::{Editor, Operation};
+
+impl Editor {
+    /// Run a function making changes to a copy of the editor, returning what
+    /// it returned along with the operations it made, without changing the
+    /// editor itself. This previews what a transform or command would do, or
+    /// checks the changes a client asks for before applying them.
+    ///
+    /// The copy shares every node with the editor until it changes them, so
+    /// this doesn't copy the tree. It has no subscribers, and the operations
+    /// aren't recorded for merging a fork. Ids drawn from a generator the
+    /// editor was given are used up, since copies share it.
+    pub fn with_intercepted_ops<R>(&self, f: impl FnOnce(&mut Editor) -> R) -> (R, Vec<Operation>) {
+        let mut copy = self.detached();
+
+        // Collect batches flushed by `f` as well as what's left pending.
+        let intercepted = Arc::new(Mutex::new(vec![]));
+        let sink = Arc::clone(&intercepted);
+        copy.subscribe(move |ops| {
+            let mut sink = sink.lock().unwrap_or_else(PoisonError::into_inner);
+            sink.extend_from_slice(ops);
+        });
+
+        let out = f(&mut copy);
+        copy.flush();
+        let ops = std::mem::take(&mut *intercepted.lock().unwrap_or_else(PoisonError::into_inner));
+        (out, ops)
+    }
+
+    /// Copy the editor without its pending operations and subscribers, so
+    /// that changes to the copy aren't seen by anyone watching the editor.
+    pub(crate) fn detached(&self) -> Editor {
+        let mut copy = self.clone();
+        copy.operations.clear();
+        *copy.actions = Default::default();
+        copy.fork_log = None;
+        *copy.subscribers = Default::default();
+        copy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::types::{Element, Point, Text};
+
+    use super::*;
+
+    #[test]
+    fn intercept_without_changing_editor() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        editor.select(Point {
+            path: vec![0, 0].into(),
+            offset: 1,
+        });
+        editor.flush();
+        let notified = Arc::new(AtomicUsize::new(0));
+        let count = Arc::clone(&notified);
+        editor.subscribe(move |_| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+        let before = editor.clone();
+
+        let (result, ops) = editor.with_intercepted_ops(|e| {
+            e.insert_text("x", None);
+            e.flush();
+            e.insert_break()
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(ops.len(), 3);
+        assert_eq!(editor, before);
+        assert_eq!(notified.load(Ordering::Relaxed), 0);
+
+        let mut applied = editor.clone();
+        for op in ops {
+            applied.apply(op).unwrap();
+        }
+        let mut expected = editor.clone();
+        expected.insert_text("x", None);
+        expected.insert_break().unwrap();
+        assert!(applied.content_eq(&expected));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::refs::PointRef;
+use crate::types::{text_entries, Affinity, Editor, Path, Point, Range};
+
+/// A search for text through a document, returning one match at a time.
+///
+/// The search resumes after the last match, which is kept up to date as the
+/// document is edited, so the matches can be changed between calls to
+/// `next`. Replacing a match with text containing the query doesn't match
+/// the replacement again.
+#[derive(Debug)]
+pub struct FindIter {
+    query: String,
+    cursor: PointRef,
+}
+
+impl FindIter {
+    /// Find the next match in the editor the search was started in, if any.
+    /// Matches are within a block, and can span several of its leaves.
+    pub fn next(&mut self, editor: &Editor) -> Option<Range> {
+        if self.query.is_empty() {
+            return None;
+        }
+        let (block, offset) = editor.block_offset(&self.cursor.current()?)?;
+
+        for path in editor.blocks_from(&block) {
+            let from = if path == block { offset } else { 0 };
+            let text = editor.block_text(&path)?;
+            let start = match text.get(from..).and_then(|t| t.find(&self.query)) {
+                Some(start) => from + start,
+                None => continue,
+            };
+
+            let end = editor.point_at_offset(&path, start + self.query.len())?;
+            let start = editor.point_at_offset(&path, start)?;
+            self.cursor.set(end.clone());
+            return Some(Range::new(start, end));
+        }
+
+        None
+    }
+}
+
+impl Editor {
+    /// Start a search for some text from the start of the document. See
+    /// `FindIter`.
+    pub fn find_iter(&mut self, query: &str) -> FindIter {
+        let start = text_entries(&self.children, &Path::new(vec![]))
+            .into_iter()
+            .next()
+            .map_or_else(
+                || Point {
+                    path: Path::new(vec![]),
+                    offset: 0,
+                },
+                |(_, path)| Point { path, offset: 0 },
+            );
+
+        FindIter {
+            query: query.to_string(),
+            cursor: self.point_ref(start, Affinity::Forward),
+        }
+    }
+
+    /// Get the paths of the blocks with text from the one at a path on, in
+    /// document order.
+    pub(crate) fn blocks_from(&self, from: &Path) -> Vec<Path> {
+        let mut blocks: Vec<Path> = vec![];
+
+        for (_, path) in text_entries(&self.children, &Path::new(vec![])) {
+            if let Some(block) = self.block_path(&path) {
+                if !block.is_before(from) && blocks.last() != Some(&block) {
+                    blocks.push(block);
+                }
+            }
+        }
+
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Marks, Node, Operation, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn find_across_leaves_and_blocks() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("one t"))
+                    .add_child(Text::with_marks("wo", Marks::BOLD)),
+            )
+            .add_child(Element::new().add_child(Text::new("two two")));
+        let mut find = editor.find_iter("two");
+
+        assert_eq!(
+            find.next(&editor),
+            Some(Range::new(point(vec![0, 0], 4), point(vec![0, 1], 2)))
+        );
+        assert_eq!(
+            find.next(&editor),
+            Some(Range::new(point(vec![1, 0], 0), point(vec![1, 0], 3)))
+        );
+        assert_eq!(
+            find.next(&editor),
+            Some(Range::new(point(vec![1, 0], 4), point(vec![1, 0], 7)))
+        );
+        assert_eq!(find.next(&editor), None);
+        assert_eq!(editor.find_iter("").next(&editor), None);
+    }
+
+    #[test]
+    fn find_resumes_after_edits() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("a b")))
+            .add_child(Element::new().add_child(Text::new("a a")));
+        let mut find = editor.find_iter("a");
+
+        // Replacing each match with text containing the query doesn't match
+        // it again.
+        while let Some(range) = find.next(&editor) {
+            editor.select(range);
+            editor.insert_text("[a]", None);
+        }
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new().add_child(Text::new("[a] b")).into(),
+                Element::new().add_child(Text::new("[a] [a]")).into(),
+            ]
+        );
+
+        // Removing the block the search was in resumes it before the next one.
+        let mut find = editor.find_iter("b");
+        editor.remove_node(&Path::new(vec![0]));
+        editor
+            .apply(Operation::InsertNode {
+                path: Path::new(vec![1]),
+                node: Node::Element(Element::new().add_child(Text::new("b"))),
+            })
+            .unwrap();
+        assert_eq!(
+            find.next(&editor),
+            Some(Range::new(point(vec![1, 0], 0), point(vec![1, 0], 1)))
+        );
+    }
+}
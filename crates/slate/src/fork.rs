@@ -0,0 +1,88 @@
+use crate::types::{Editor, Operation};
+
+/// The operations applied to an editor since it was forked, or since it was
+/// forked from, without selection changes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ForkLog(pub(crate) Vec<Operation>);
+
+impl Editor {
+    /// Fork the editor into an independent copy, like a draft of suggested
+    /// edits, sharing every node with it until either one changes it.
+    ///
+    /// Both editors record the operations applied to them from now on, so the
+    /// fork can be merged back with `merge_fork`. Forking again starts over.
+    /// The fork has no subscribers and no pending operations of its own.
+    pub fn fork(&mut self) -> Editor {
+        self.fork_log = Some(Box::default());
+
+        let mut fork = self.clone();
+        fork.operations.clear();
+        *fork.actions = Default::default();
+        fork.previous = None;
+        fork.subscribers = Box::default();
+        fork
+    }
+
+    /// Get the operations applied to the document since it was forked, or
+    /// since it was forked from, without selection changes. They're empty if
+    /// it never was.
+    pub fn changes_since_fork(&self) -> &[Operation] {
+        self.fork_log.as_deref().map_or(&[], |log| &log.0)
+    }
+
+    /// Merge the changes made to a fork of the editor into it, rebased onto
+    /// the changes made here since forking, which win where they conflict.
+    /// The rebased operations that applied are returned.
+    ///
+    /// The merged changes keep the authors the fork attributed them to. A fork
+    /// can only be merged once, after which changes stop being recorded.
+    pub fn merge_fork(&mut self, fork: &Editor) -> Vec<Operation> {
+        let ours = self.fork_log.take().map(|log| log.0).unwrap_or_default();
+        let (_, rebased) = Operation::transform_all(&ours, fork.changes_since_fork());
+
+        rebased
+            .into_iter()
+            .filter(|op| self.apply(op.clone()).is_ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Point, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn fork_and_merge() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("ab")))
+            .add_child(Element::new().add_child(Text::new("cd")));
+        let mut draft = editor.fork();
+
+        draft.select(point(vec![1, 0], 2));
+        draft.insert_text("!", None);
+        editor.select(point(vec![0, 0], 0));
+        editor.insert_text("> ", None);
+        assert_eq!(draft.changes_since_fork().len(), 1);
+        assert_eq!(editor.changes_since_fork().len(), 1);
+
+        let merged = editor.merge_fork(&draft);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new().add_child(Text::new("> ab")).into(),
+                Element::new().add_child(Text::new("cd!")).into(),
+            ]
+        );
+        assert_eq!(editor.changes_since_fork(), &[]);
+    }
+}
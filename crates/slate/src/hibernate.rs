@@ -0,0 +1,307 @@
+use std::error::Error;
+use std::fmt;
+
+use serde_json::{json, Map, Value};
+
+use crate::fork::ForkLog;
+use crate::input::{Action, InputType};
+use crate::serialize::json::{self, ImportWarning};
+use crate::transforms::ApplyError;
+use crate::types::{Editor, MarkRegistry, Marks, Operation, Path};
+
+/// Errors returned when waking a hibernated document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WakeError {
+    /// The hibernated state isn't one `Editor::hibernate` wrote.
+    Invalid(String),
+    /// The document couldn't be read.
+    Document(ImportWarning),
+    /// The pending operations can't be undone to get the document as it was
+    /// before them.
+    Pending(ApplyError),
+}
+
+impl fmt::Display for WakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WakeError::Invalid(what) => write!(f, "invalid hibernated {}", what),
+            WakeError::Document(e) => write!(f, "invalid hibernated document: {}", e),
+            WakeError::Pending(e) => write!(f, "pending operations don't undo: {}", e),
+        }
+    }
+}
+
+impl Error for WakeError {}
+
+/// An editor's document and the state it hasn't flushed yet, serialized to
+/// be kept out of memory while the document is idle.
+///
+/// The state is JSON, which can be written to disk or a cache as it is. It
+/// holds the document and selection, the pending operations and the actions
+/// that made them, the marks for the next insertion, and the operations
+/// logged since forking. The document as it was before the pending
+/// operations isn't stored, since waking gets it back by undoing them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HibernatedDoc(String);
+
+impl HibernatedDoc {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Get the size of the state, in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for HibernatedDoc {
+    fn from(state: String) -> Self {
+        Self(state)
+    }
+}
+
+impl Editor {
+    /// Serialize the document and its unflushed state, for a server hosting
+    /// many documents to drop the editor while it's idle and `wake` it again
+    /// later without losing anything that wasn't sent or recorded yet.
+    ///
+    /// The editor's configuration, like its schema, plugins, subscribers and
+    /// caches, isn't part of the document, so the server applies it again to
+    /// the editor it wakes.
+    pub fn hibernate(self) -> HibernatedDoc {
+        let document: Value =
+            serde_json::from_str(&json::to_string(&self)).expect("documents to be JSON");
+        let mut state = Map::new();
+        state.insert("document".into(), document);
+
+        if !self.operations.is_empty() {
+            let ops: Vec<_> = self
+                .operations
+                .iter()
+                .map(|op| json::operation_to_value_with(op, self.mark_registry()))
+                .collect();
+            state.insert("operations".into(), ops.into());
+            let actions: Vec<_> = self
+                .actions
+                .actions
+                .iter()
+                .map(|action| {
+                    json!({
+                        "input": input_name(action.input),
+                        "id": action.id,
+                        "ops": [action.ops.start, action.ops.end],
+                    })
+                })
+                .collect();
+            state.insert("actions".into(), actions.into());
+        }
+        state.insert("nextAction".into(), self.actions.next_id.into());
+        if let Some(marks) = self.marks {
+            state.insert("marks".into(), marks.bits().into());
+        }
+        if let Some(log) = &self.fork_log {
+            let ops: Vec<_> = log
+                .0
+                .iter()
+                .map(|op| json::operation_to_value_with(op, self.mark_registry()))
+                .collect();
+            state.insert("forkLog".into(), ops.into());
+        }
+        if let Some(path) = &self.autolinked {
+            state.insert("autolinked".into(), path.as_slice().into());
+        }
+
+        HibernatedDoc(Value::Object(state).to_string())
+    }
+
+    /// Get back an editor from its hibernated state, with the document and
+    /// unflushed state it had, and the default configuration.
+    pub fn wake(doc: HibernatedDoc) -> Result<Editor, WakeError> {
+        Self::wake_from(doc, None)
+    }
+
+    /// Get back an editor from its hibernated state like `wake`, reading
+    /// marks by the names in the registry it was hibernated with, which the
+    /// editor is given.
+    pub fn wake_with(doc: HibernatedDoc, registry: &MarkRegistry) -> Result<Editor, WakeError> {
+        Self::wake_from(doc, Some(registry))
+    }
+
+    fn wake_from(doc: HibernatedDoc, registry: Option<&MarkRegistry>) -> Result<Editor, WakeError> {
+        let invalid = |what: &str| WakeError::Invalid(what.into());
+        let state: Value = serde_json::from_str(&doc.0).map_err(|_| invalid("state"))?;
+        let document = state
+            .get("document")
+            .ok_or_else(|| invalid("state"))?
+            .to_string();
+        let mut editor = match registry {
+            Some(registry) => json::from_string_with(&document, registry),
+            None => json::from_string(&document),
+        }
+        .map_err(WakeError::Document)?;
+        let registry = editor.mark_registry().clone();
+
+        let operations = |key: &str| -> Result<Option<Vec<Operation>>, WakeError> {
+            match state.get(key) {
+                None => Ok(None),
+                Some(Value::Array(ops)) => ops
+                    .iter()
+                    .map(|op| {
+                        json::operation_from_value_with(op, &registry)
+                            .ok_or_else(|| invalid("operation"))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Some),
+                Some(_) => Err(invalid("operations")),
+            }
+        };
+
+        let pending = operations("operations")?.unwrap_or_default();
+        if !pending.is_empty() {
+            let mut before = Editor::new();
+            before.replace_children(editor.children.clone());
+            before.selection = editor.selection.clone();
+            for op in pending.iter().rev() {
+                before
+                    .apply(op.clone().inverse())
+                    .map_err(WakeError::Pending)?;
+            }
+            editor.previous = Some(Box::new(before.children));
+        }
+        editor.operations = pending;
+
+        for action in state
+            .get("actions")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            editor
+                .actions
+                .actions
+                .push(read_action(action).ok_or_else(|| invalid("action"))?);
+        }
+        editor.actions.next_id = state.get("nextAction").and_then(Value::as_u64).unwrap_or(0);
+        editor.marks = state
+            .get("marks")
+            .and_then(Value::as_u64)
+            .map(|bits| Marks::from_bits_retain(bits as u32));
+        editor.fork_log = operations("forkLog")?.map(|log| Box::new(ForkLog(log)));
+        editor.autolinked = match state.get("autolinked") {
+            Some(path) => Some(Box::new(
+                serde_json::from_value::<Vec<usize>>(path.clone())
+                    .map(Path::new)
+                    .map_err(|_| invalid("autolink"))?,
+            )),
+            None => None,
+        };
+
+        Ok(editor)
+    }
+}
+
+fn input_name(input: InputType) -> &'static str {
+    match input {
+        InputType::Typing => "typing",
+        InputType::Paste => "paste",
+        InputType::Drag => "drag",
+        InputType::Format => "format",
+        InputType::Programmatic => "programmatic",
+    }
+}
+
+fn read_action(value: &Value) -> Option<Action> {
+    let input = match value.get("input")?.as_str()? {
+        "typing" => InputType::Typing,
+        "paste" => InputType::Paste,
+        "drag" => InputType::Drag,
+        "format" => InputType::Format,
+        "programmatic" => InputType::Programmatic,
+        _ => return None,
+    };
+    let ops = value.get("ops")?.as_array()?;
+    let (start, end) = (ops.first()?.as_u64()?, ops.get(1)?.as_u64()?);
+
+    Some(Action {
+        input,
+        id: value.get("id").and_then(Value::as_u64),
+        ops: start as usize..end as usize,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, Point, Text};
+
+    fn editor() -> Editor {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(Element::new().add_child(Text::new("two")));
+        editor.select(Point {
+            path: Path::new(vec![0, 0]),
+            offset: 3,
+        });
+        editor.flush();
+        editor
+    }
+
+    #[test]
+    fn wake_with_unflushed_state() {
+        let mut editor = editor();
+        editor.fork();
+        editor.insert_text("!", None);
+        editor.insert_break().unwrap();
+        editor.marks = Some(Marks::BOLD);
+
+        let woken = Editor::wake(editor.clone().hibernate()).unwrap();
+        assert!(woken.content_eq(&editor));
+        assert_eq!(woken.selection(), editor.selection());
+        assert_eq!(woken.operations, editor.operations);
+        assert_eq!(woken.previous(), editor.previous());
+        assert_eq!(woken.pending_actions(), editor.pending_actions());
+        assert_eq!(woken.input_type(), InputType::Typing);
+        assert_eq!(woken.marks, Some(Marks::BOLD));
+        assert_eq!(woken.changes_since_fork(), editor.changes_since_fork());
+
+        // Flushing gives the batch that was pending when it hibernated.
+        let mut woken = woken;
+        assert_eq!(woken.flush(), editor.flush());
+    }
+
+    #[test]
+    fn wake_with_custom_marks() {
+        let mut registry = MarkRegistry::new();
+        let strike = registry.register("strikethrough").unwrap();
+        let mut editor = editor().with_mark_registry(registry.clone());
+        editor.add_mark(strike);
+        editor.insert_text("?", None);
+
+        let woken = Editor::wake_with(editor.clone().hibernate(), &registry).unwrap();
+        assert!(woken.content_eq(&editor));
+        assert_eq!(woken.operations, editor.operations);
+        assert_eq!(woken.marks, editor.marks);
+        assert_eq!(woken.mark_registry(), &registry);
+    }
+
+    #[test]
+    fn wake_rejects_invalid_state() {
+        assert_eq!(
+            Editor::wake(HibernatedDoc::from("[]".to_string())),
+            Err(WakeError::Invalid("state".into()))
+        );
+
+        let flushed = editor().hibernate();
+        assert!(!flushed.as_str().contains("operations"));
+        assert!(Editor::wake(flushed).unwrap().previous().is_none());
+    }
+}
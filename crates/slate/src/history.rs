@@ -0,0 +1,347 @@
+use std::iter::Rev;
+use std::slice;
+
+use crate::input::{Action, InputType};
+use crate::types::{Children, Editor, Operation, PathArena};
+
+/// The batches of operations applied to an editor, oldest first. Each batch
+/// is an undo boundary.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct History {
+    batches: Vec<Vec<Operation>>,
+    /// The input type of each batch, and the id of the action that made it
+    /// if it was recorded from an editor.
+    inputs: Vec<(InputType, Option<u64>)>,
+    pub group_typing: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Merge consecutive typing into one batch when recording it from an
+    /// editor, instead of making each keystroke an undo step.
+    pub fn group_typing(mut self, group_typing: bool) -> Self {
+        self.group_typing = group_typing;
+        self
+    }
+
+    /// Record a batch, like one returned by `Editor::flush`. Empty batches
+    /// aren't recorded.
+    pub fn push(&mut self, batch: Vec<Operation>) {
+        self.push_input(batch, InputType::Programmatic, None);
+    }
+
+    /// Flush an editor and record its batch, split into one batch per action
+    /// that made it, so that one paste or one formatting action is always
+    /// one undo step, however many operations or flushes it took.
+    ///
+    /// An action continuing the one the last batch was recorded from is
+    /// merged into it, as is typing following typing with `group_typing`.
+    pub fn record(&mut self, editor: &mut Editor) {
+        let actions = editor.pending_actions();
+        let ops = editor.flush();
+        if ops.is_empty() {
+            // Nothing is flushed inside a batch, and its actions are left to
+            // record when it ends.
+            return;
+        }
+        self.push_actions(&ops, actions);
+    }
+
+    fn push_actions(&mut self, ops: &[Operation], actions: Vec<Action>) {
+        for action in actions {
+            self.push_input(ops[action.ops].to_vec(), action.input, action.id);
+        }
+    }
+
+    fn push_input(&mut self, batch: Vec<Operation>, input: InputType, id: Option<u64>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let merges = self.inputs.last().is_some_and(|(last, last_id)| {
+            (id.is_some() && *last_id == id)
+                || (self.group_typing && *last == InputType::Typing && input == InputType::Typing)
+        });
+        match self.batches.last_mut() {
+            Some(last) if merges => last.extend(batch),
+            _ => {
+                self.batches.push(batch);
+                self.inputs.push((input, id));
+            }
+        }
+    }
+
+    pub fn batches(&self) -> &[Vec<Operation>] {
+        &self.batches
+    }
+
+    /// Get the input type of the batch at an index.
+    pub fn input_type(&self, i: usize) -> Option<InputType> {
+        self.inputs.get(i).map(|(input, _)| *input)
+    }
+
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Rebase the batches onto operations applied to the editor after them,
+    /// like a collaborator's, so that they still describe how the document
+    /// got to where it is and can be undone from it.
+    ///
+    /// Each batch is undone concurrently with the operations, newest first,
+    /// and the operations are carried back before it. The batches' paths are
+    /// transformed through one arena, as they mostly touch the same nodes.
+    pub fn rebase(&mut self, ops: &[Operation]) {
+        let mut paths = PathArena::new();
+        let mut ops = ops.to_vec();
+
+        for batch in self.batches.iter_mut().rev() {
+            let undo: Vec<Operation> = batch.iter().rev().map(|op| op.clone().inverse()).collect();
+            let (before, undo) = Operation::transform_all_in(&mut paths, &ops, &undo);
+            *batch = undo.into_iter().rev().map(Operation::inverse).collect();
+            ops = before;
+        }
+    }
+
+    /// Iterate over the document at each undo boundary, from the editor's
+    /// current document back to the one before the oldest batch.
+    ///
+    /// Each state is reconstructed from the one after it by applying the
+    /// inverses of a batch, so only the nodes a batch changed are copied and
+    /// nothing is computed until it's asked for. Iteration stops early if a
+    /// batch doesn't apply, which means the history isn't the editor's.
+    pub fn iter_states(&self, editor: &Editor) -> States<'_> {
+        let mut document = Editor::new();
+        document.replace_children(editor.children.clone());
+
+        States {
+            batches: self.batches.iter().rev(),
+            document: Some(document),
+            started: false,
+        }
+    }
+}
+
+impl Editor {
+    /// Record each batch the editor flushes in a history, like
+    /// `History::record` does.
+    pub fn with_history(mut self, history: History) -> Self {
+        self.subscribers.history = Some(history);
+        self
+    }
+
+    /// The history recording the editor's batches, if it has one.
+    pub fn history(&self) -> Option<&History> {
+        self.subscribers.history.as_ref()
+    }
+
+    pub fn history_mut(&mut self) -> Option<&mut History> {
+        self.subscribers.history.as_mut()
+    }
+
+    /// Record the pending operations in the editor's history, before they're
+    /// flushed.
+    pub(crate) fn record_history(&mut self) {
+        if self.subscribers.history.is_none() || self.operations.is_empty() {
+            return;
+        }
+        let actions = self.pending_actions();
+        if let Some(history) = &mut self.subscribers.history {
+            history.push_actions(&self.operations, actions);
+        }
+    }
+}
+
+/// An iterator over the document at each undo boundary of a [`History`],
+/// newest first.
+#[derive(Debug)]
+pub struct States<'a> {
+    batches: Rev<slice::Iter<'a, Vec<Operation>>>,
+    document: Option<Editor>,
+    started: bool,
+}
+
+impl Iterator for States<'_> {
+    type Item = Children;
+
+    fn next(&mut self) -> Option<Children> {
+        let document = self.document.as_mut()?;
+
+        if self.started {
+            let batch = match self.batches.next() {
+                Some(batch) => batch,
+                None => {
+                    self.document = None;
+                    return None;
+                }
+            };
+            for op in batch.iter().rev() {
+                if document.apply(op.clone().inverse()).is_err() {
+                    self.document = None;
+                    return None;
+                }
+            }
+            document.operations.clear();
+        }
+        self.started = true;
+
+        Some(document.children.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Marks, Node, Path, Point, Text};
+
+    use super::*;
+
+    fn record(editor: &mut Editor, history: &mut History, f: impl FnOnce(&mut Editor)) {
+        f(editor);
+        history.push(editor.flush());
+    }
+
+    #[test]
+    fn iter_states_walks_back_through_batches() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("hello")));
+        let mut history = History::new();
+        let start = editor.children.clone();
+
+        record(&mut editor, &mut history, |editor| {
+            editor.select(Path::new(vec![0, 0]));
+            editor.add_mark(Marks::BOLD);
+        });
+        let bold = editor.children.clone();
+        record(&mut editor, &mut history, |editor| {
+            editor
+                .insert_fragment(vec![Element::new().add_child(Text::new("two")).into()])
+                .unwrap();
+        });
+
+        let states: Vec<Children> = history.iter_states(&editor).collect();
+        assert_eq!(states, vec![editor.children.clone(), bold, start]);
+    }
+
+    #[test]
+    fn record_one_batch_per_action() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        let mut history = History::new().group_typing(true);
+        editor.select(Point {
+            path: Path::new(vec![0, 0]),
+            offset: 2,
+        });
+        history.record(&mut editor);
+
+        editor.insert_text("c", None);
+        history.record(&mut editor);
+        editor.delete_backward();
+        editor.insert_break().unwrap();
+        history.record(&mut editor);
+        editor.input(InputType::Paste, |editor| {
+            let fragment = vec![Element::new().add_child(Text::new("one")).into()];
+            editor.insert_fragment(fragment.clone()).unwrap();
+            history.record(editor);
+            editor.insert_fragment(fragment).unwrap();
+        });
+        editor
+            .insert_fragment_data(vec![Text::new("two").into()])
+            .unwrap();
+        history.record(&mut editor);
+
+        let inputs: Vec<_> = (0..history.len())
+            .map(|i| history.input_type(i).unwrap())
+            .collect();
+        assert_eq!(
+            inputs,
+            vec![
+                InputType::Programmatic,
+                InputType::Typing,
+                InputType::Paste,
+                InputType::Paste,
+            ]
+        );
+        assert_eq!(history.batches()[1].len(), 4);
+    }
+
+    #[test]
+    fn editor_records_its_flushes() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("ab")))
+            .with_history(History::new().group_typing(true));
+        editor.select(Point {
+            path: Path::new(vec![0, 0]),
+            offset: 2,
+        });
+        editor.flush();
+        editor.insert_text("c", None);
+        editor.flush();
+        editor.insert_text("d", None);
+        editor.flush();
+
+        let history = editor.history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.input_type(1), Some(InputType::Typing));
+        assert_eq!(history.batches()[1].len(), 2);
+    }
+
+    #[test]
+    fn iter_states_stops_at_mismatched_history() {
+        let editor = Editor::new().add_child(Element::new().add_child(Text::new("a")));
+        let mut history = History::new();
+        history.push(vec![Operation::InsertText {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+            text: "missing".into(),
+        }]);
+
+        assert_eq!(history.iter_states(&editor).count(), 1);
+    }
+
+    #[test]
+    fn rebase_onto_later_operations() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        let mut history = History::new();
+
+        record(&mut editor, &mut history, |editor| {
+            editor.select(Point {
+                path: Path::new(vec![0, 0]),
+                offset: 2,
+            });
+            editor.insert_text("c", None);
+        });
+
+        // A collaborator inserts a paragraph before, and text in front.
+        let remote = vec![
+            Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(Element::new().add_child(Text::new("x"))),
+            },
+            Operation::InsertText {
+                path: Path::new(vec![1, 0]),
+                offset: 0,
+                text: "y".into(),
+            },
+        ];
+        for op in &remote {
+            editor.apply(op.clone()).unwrap();
+        }
+        editor.flush();
+        history.rebase(&remote);
+
+        let mut expected = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        for op in &remote {
+            expected.apply(op.clone()).unwrap();
+        }
+        let states: Vec<Children> = history.iter_states(&editor).collect();
+        assert_eq!(
+            states,
+            vec![editor.children.clone(), expected.children.clone()]
+        );
+    }
+}
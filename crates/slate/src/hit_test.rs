@@ -0,0 +1,181 @@
+//! Turning positions in the frontend's view, like that of a click, into
+//! points. The frontend measures its glyphs, and the editor keeps track of
+//! which leaf and offset each grapheme is at.
+
+use std::cmp::Ordering;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::types::{Editor, Path, Point};
+
+/// Measures how the frontend lays out the text of blocks. Visual lines are
+/// where the editor's `LineBreaker` wraps blocks, or each block is one line
+/// without one.
+pub trait Measurer {
+    /// Find the block at a position in the view and the index of its visual
+    /// line there, or `None` if there's no block there.
+    fn line_at(&self, x: f64, y: f64) -> Option<(Path, usize)>;
+
+    /// Measure how far the text starting a visual line of a block reaches,
+    /// from the line's start to the horizontal position of its end, in the
+    /// units of the view's positions.
+    fn width(&self, block: &Path, text: &str) -> f64;
+}
+
+impl Editor {
+    /// Get the point before the grapheme at an index in the text of a block,
+    /// or at the end of the block past its last grapheme.
+    pub fn closest_point(&self, block: &Path, grapheme: usize) -> Option<Point> {
+        let text = self.block_text(block)?;
+        let offset = text
+            .grapheme_indices(true)
+            .nth(grapheme)
+            .map_or(text.len(), |(i, _)| i);
+        self.point_at_offset(block, offset)
+    }
+
+    /// Get the block containing a point, and the index of the grapheme after
+    /// the point in the block's text.
+    pub fn grapheme_index(&self, point: &Point) -> Option<(Path, usize)> {
+        let (block, offset) = self.block_offset(point)?;
+        let text = self.block_text(&block)?;
+        let index = text
+            .grapheme_indices(true)
+            .take_while(|(i, _)| *i < offset)
+            .count();
+        Some((block, index))
+    }
+
+    /// Get the point closest to a position in the view, measured by the
+    /// frontend: the grapheme boundary of the line there nearest to it. As
+    /// when moving by lines, a wrapped line's end is the start of the next
+    /// one, so the caret stays before its last character.
+    pub fn point_at_position<M: Measurer>(&self, measurer: &M, x: f64, y: f64) -> Option<Point> {
+        let (block, line) = measurer.line_at(x, y)?;
+        let lines = self.lines(&block)?;
+        let line = line.min(lines.len() - 1);
+        let (start, end) = lines[line];
+        let text = self.block_text(&block)?;
+        let content = &text[start..end];
+
+        let mut boundaries: Vec<usize> = content.grapheme_indices(true).map(|(i, _)| i).collect();
+        if line + 1 == lines.len() || boundaries.is_empty() {
+            boundaries.push(content.len());
+        }
+
+        let distance = |offset: usize| (measurer.width(&block, &content[..offset]) - x).abs();
+        let (offset, _) = boundaries
+            .into_iter()
+            .map(|offset| (offset, distance(offset)))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
+
+        self.point_at_offset(&block, start + offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Text};
+    use crate::LineBreaker;
+
+    use super::*;
+
+    /// Wraps blocks every 4 bytes.
+    struct Fixed;
+
+    impl LineBreaker for Fixed {
+        fn line_starts(&self, _: &Path, text: &str) -> Vec<usize> {
+            (4..text.len()).step_by(4).collect()
+        }
+    }
+
+    /// Lays blocks out one after another, with lines 1 high and characters
+    /// 1 wide, except for wide ones which are 2.
+    struct Grid(Vec<(Path, usize)>);
+
+    impl Measurer for Grid {
+        fn line_at(&self, _: f64, y: f64) -> Option<(Path, usize)> {
+            self.0.get(y as usize).cloned()
+        }
+
+        fn width(&self, _: &Path, text: &str) -> f64 {
+            text.chars()
+                .map(|c| if c.is_ascii() { 1.0 } else { 2.0 })
+                .sum()
+        }
+    }
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("abc"))
+                    .add_child(Text::new("defghij")),
+            )
+            .add_child(Element::new().add_child(Text::new("x日")))
+            .with_line_breaker(Fixed)
+    }
+
+    #[test]
+    fn closest_points() {
+        let editor = editor();
+        let block = Path::new(vec![1]);
+        assert_eq!(editor.closest_point(&block, 1), Some(point(vec![1, 0], 1)));
+        assert_eq!(editor.closest_point(&block, 9), Some(point(vec![1, 0], 4)));
+        assert_eq!(
+            editor.grapheme_index(&point(vec![1, 0], 4)),
+            Some((block, 2))
+        );
+        assert_eq!(
+            editor.grapheme_index(&point(vec![0, 1], 1)),
+            Some((Path::new(vec![0]), 4))
+        );
+    }
+
+    #[test]
+    fn points_at_positions() {
+        let editor = editor();
+        let grid = Grid(vec![
+            (Path::new(vec![0]), 0),
+            (Path::new(vec![0]), 1),
+            (Path::new(vec![0]), 2),
+            (Path::new(vec![1]), 0),
+        ]);
+
+        // Lines are "abcd", "efgh" and "ij", and a boundary between leaves
+        // is at the end of the first. Past the end of a wrapped line, the
+        // caret stays before its last character.
+        assert_eq!(
+            editor.point_at_position(&grid, 3.2, 0.5),
+            Some(point(vec![0, 0], 3))
+        );
+        assert_eq!(
+            editor.point_at_position(&grid, 9.0, 0.5),
+            Some(point(vec![0, 0], 3))
+        );
+        assert_eq!(
+            editor.point_at_position(&grid, 1.6, 1.5),
+            Some(point(vec![0, 1], 3))
+        );
+        assert_eq!(
+            editor.point_at_position(&grid, 9.0, 2.5),
+            Some(point(vec![0, 1], 7))
+        );
+        assert_eq!(
+            editor.point_at_position(&grid, 1.8, 3.5),
+            Some(point(vec![1, 0], 1))
+        );
+        assert_eq!(
+            editor.point_at_position(&grid, 2.2, 3.5),
+            Some(point(vec![1, 0], 4))
+        );
+        assert_eq!(editor.point_at_position(&grid, 0.0, 4.5), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Generating the ids the editor gives new nodes, like footnotes.
+//!
+//! By default ids are numbered from 1, skipping those already in the
+//! document. That's deterministic but not safe across peers editing the same
+//! document, who would give their new nodes the same ids. Peers can instead
+//! inject a generator seeded differently on each of them.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::types::Editor;
+
+/// Makes the ids of new nodes.
+pub trait IdGenerator: Send {
+    /// Get a new id. The editor skips ids already in the document, so it's
+    /// only asked again if one collides.
+    fn next_id(&mut self) -> String;
+}
+
+/// Generates ids from a pseudorandom sequence, which is the same every time
+/// for the same seed.
+///
+/// Seeding peers differently, like with their client ids, makes their ids
+/// collide only by chance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededIds {
+    state: u64,
+}
+
+impl SeededIds {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl IdGenerator for SeededIds {
+    // SplitMix64, which is fast and spreads nearby seeds apart.
+    fn next_id(&mut self) -> String {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        format!("{:016x}", z ^ (z >> 31))
+    }
+}
+
+/// The id generator an editor was configured with.
+#[derive(Clone)]
+pub(crate) struct IdGeneratorHandle(Arc<Mutex<dyn IdGenerator>>);
+
+impl fmt::Debug for IdGeneratorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("IdGenerator")
+    }
+}
+
+// The generator only affects nodes yet to be made, so it never makes two
+// editors unequal.
+impl PartialEq for IdGeneratorHandle {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Editor {
+    /// Make the ids of new nodes with `generator` instead of numbering them.
+    /// Clones and forks of the editor share the generator, so their ids don't
+    /// collide with each other either.
+    pub fn with_id_generator(mut self, generator: impl IdGenerator + 'static) -> Self {
+        self.id_generator = Some(Box::new(IdGeneratorHandle(Arc::new(Mutex::new(generator)))));
+        self
+    }
+
+    /// Get a new id starting with `prefix` that isn't one of `taken`.
+    pub(crate) fn new_id(&self, prefix: &str, taken: &[String]) -> String {
+        let mut n = 0;
+        let mut next = || match &self.id_generator {
+            Some(generator) => generator
+                .0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .next_id(),
+            None => {
+                n += 1;
+                n.to_string()
+            }
+        };
+        loop {
+            let id = format!("{}-{}", prefix, next());
+            if !taken.contains(&id) {
+                return id;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_ids_are_deterministic() {
+        let ids = |seed| {
+            let mut ids = SeededIds::new(seed);
+            (0..3).map(|_| ids.next_id()).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(1), ids(1));
+        assert_ne!(ids(1), ids(2));
+        assert_ne!(ids(1)[0], ids(1)[1]);
+    }
+
+    #[test]
+    fn new_ids_skip_taken() {
+        let editor = Editor::new();
+        assert_eq!(editor.new_id("fn", &["fn-1".into()]), "fn-2");
+
+        struct Repeat(Vec<&'static str>);
+        impl IdGenerator for Repeat {
+            fn next_id(&mut self) -> String {
+                self.0.remove(0).into()
+            }
+        }
+        let editor = Editor::new().with_id_generator(Repeat(vec!["a", "a", "b"]));
+        let fork = editor.clone();
+        assert_eq!(editor.new_id("fn", &[]), "fn-a");
+        assert_eq!(fork.new_id("fn", &["fn-a".into()]), "fn-b");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::input_rules::InputRule;
+use crate::types::{Editor, Marks, NodeProperties, Operation, Path, Point};
+
+/// An input rule turning text typed between backticks, like `` `main` ``,
+/// into text with the code mark, for `Editor::with_input_rule`.
+///
+/// The code is marked when its closing backtick is typed, if the opening one
+/// starts a word and the code doesn't start or end with whitespace. The
+/// backticks are removed, and the caret is left after the code, so typing
+/// goes on outside of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InlineCode;
+
+impl InlineCode {
+    /// Find where code closed at the end of some text starts, at its opening
+    /// backtick.
+    fn closed_at_end(text: &str) -> Option<usize> {
+        let body = text.strip_suffix('`')?;
+        let start = body.rfind('`')?;
+        let code = &body[start + 1..];
+        if code.is_empty()
+            || code.starts_with(char::is_whitespace)
+            || code.ends_with(char::is_whitespace)
+        {
+            return None;
+        }
+        if body[..start]
+            .chars()
+            .last()
+            .is_some_and(char::is_alphanumeric)
+        {
+            return None;
+        }
+        Some(start)
+    }
+}
+
+impl InputRule for InlineCode {
+    fn apply(&self, editor: &mut Editor, at: &Point) {
+        if editor.in_code(&at.path) {
+            return;
+        }
+        let (start, len) = match editor.text_at(&at.path) {
+            Some(text) => match Self::closed_at_end(&text.text()[..at.offset]) {
+                Some(start) => (start, text.text().len()),
+                None => return,
+            },
+            None => return,
+        };
+        let end = at.offset - 2;
+
+        for offset in [at.offset - 1, start] {
+            editor
+                .apply(Operation::RemoveText {
+                    path: at.path.clone(),
+                    offset,
+                    text: "`".into(),
+                })
+                .expect("removal of typed backticks to apply");
+        }
+        // Split the code off the text around it.
+        if end < len - 2 {
+            split_text(editor, &at.path, end);
+        }
+        let code = if start > 0 {
+            split_text(editor, &at.path, start);
+            at.path.next().expect("text paths are never empty")
+        } else {
+            at.path.clone()
+        };
+
+        mark_code(editor, &code);
+        editor.select(Point {
+            path: code,
+            offset: end - start,
+        });
+    }
+}
+
+fn split_text(editor: &mut Editor, path: &Path, position: usize) {
+    editor
+        .apply(Operation::SplitNode {
+            path: path.clone(),
+            position,
+            properties: editor.properties_at(path),
+        })
+        .expect("split of text at an offset in it to apply");
+}
+
+fn mark_code(editor: &mut Editor, path: &Path) {
+    let marks = match editor.text_at(path) {
+        Some(text) => text.marks(),
+        None => return,
+    };
+    let marks = match &editor.mark_rules {
+        Some(rules) => rules.add(marks, Marks::CODE),
+        None => marks | Marks::CODE,
+    };
+    editor
+        .set_node(path, NodeProperties::new().marks(marks))
+        .expect("setting the marks of a text to apply");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, Range, Text};
+
+    fn typed(text: &str) -> Editor {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .with_input_rule(InlineCode);
+        let point = Point {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+        };
+        editor.select(Range::new(point.clone(), point));
+        for c in text.chars() {
+            editor.insert_text(&c.to_string(), None);
+        }
+        editor
+    }
+
+    #[test]
+    fn backticks_mark_code() {
+        assert_eq!(
+            typed("run `cargo test` now").children(),
+            vec![Element::new()
+                .add_child(Text::new("run "))
+                .add_child(Text::with_marks("cargo test", Marks::CODE))
+                .add_child(Text::new(" now"))
+                .into()]
+        );
+        assert_eq!(
+            typed("`a`").children(),
+            vec![Element::new()
+                .add_child(Text::with_marks("a", Marks::CODE))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn backticks_inside_words_or_around_spaces_are_kept() {
+        for text in ["a`b`", "` a`", "``", "x ` `"] {
+            assert_eq!(
+                typed(text).children(),
+                vec![Element::new().add_child(Text::new(text)).into()]
+            );
+        }
+    }
+}
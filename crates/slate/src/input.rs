@@ -0,0 +1,197 @@
+use std::ops::Range;
+
+use crate::types::Editor;
+
+/// The kind of user action that made operations, for grouping them into undo
+/// steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputType {
+    /// Inserting or deleting text, or breaking a block, at the caret.
+    Typing,
+    /// Inserting content from the clipboard or from outside the editor.
+    Paste,
+    /// Moving content by dragging it. The editor has no transform for this,
+    /// so apps tag their own with `Editor::input`.
+    Drag,
+    /// Changing the marks of text or the style of blocks.
+    Format,
+    /// Anything else, like operations applied directly or a document being
+    /// normalized.
+    Programmatic,
+}
+
+/// An action that made operations, and which of the pending operations it
+/// made.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Action {
+    pub(crate) input: InputType,
+    /// Unique among the actions of an editor, so that an action spanning
+    /// several batches can be told apart from a new one of the same type.
+    /// Operations made outside of any action have none.
+    pub(crate) id: Option<u64>,
+    pub(crate) ops: Range<usize>,
+}
+
+/// The actions that made an editor's pending operations. The last one is
+/// still running while `depth` is above zero.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Actions {
+    pub(crate) actions: Vec<Action>,
+    pub(crate) next_id: u64,
+    pub(crate) depth: usize,
+}
+
+impl Editor {
+    /// Run a function making changes to the editor as one action of a type,
+    /// so that its operations are grouped into one undo step by
+    /// `History::record`, however many batches they span. The editor's
+    /// transforms tag their own, and actions run inside others are part of
+    /// the outermost one.
+    pub fn input<R>(&mut self, input: InputType, f: impl FnOnce(&mut Editor) -> R) -> R {
+        let actions = &mut self.actions;
+        if actions.depth == 0 {
+            let start = self.operations.len();
+            actions.actions.push(Action {
+                input,
+                id: Some(actions.next_id),
+                ops: start..start,
+            });
+            actions.next_id += 1;
+        }
+
+        actions.depth += 1;
+        let out = f(self);
+        self.actions.depth -= 1;
+
+        if self.actions.depth == 0 {
+            let end = self.operations.len();
+            if let Some(action) = self.actions.actions.last_mut() {
+                action.ops.end = end;
+            }
+        }
+        out
+    }
+
+    /// Get the input type of the action the pending operations were made by,
+    /// or of the first of them if there were several. Operations made
+    /// outside of any action are `Programmatic`.
+    pub fn input_type(&self) -> InputType {
+        self.pending_actions()
+            .first()
+            .map_or(InputType::Programmatic, |action| action.input)
+    }
+
+    /// Split the pending operations by the actions that made them, with runs
+    /// made outside of any action as `Programmatic` actions without an id.
+    pub(crate) fn pending_actions(&self) -> Vec<Action> {
+        let len = self.operations.len();
+        let untagged = |ops| Action {
+            input: InputType::Programmatic,
+            id: None,
+            ops,
+        };
+        let running = self.actions.depth > 0;
+        let last = self.actions.actions.len().saturating_sub(1);
+
+        let mut out = vec![];
+        let mut start = 0;
+        for (i, action) in self.actions.actions.iter().enumerate() {
+            let end = if running && i == last {
+                len
+            } else {
+                action.ops.end.min(len)
+            };
+            let begin = action.ops.start.clamp(start, end.max(start));
+            if start < begin {
+                out.push(untagged(start..begin));
+            }
+            if begin < end {
+                out.push(Action {
+                    ops: begin..end,
+                    ..action.clone()
+                });
+            }
+            start = start.max(end);
+        }
+        if start < len {
+            out.push(untagged(start..len));
+        }
+        out
+    }
+
+    /// Forget the actions of flushed operations. An action still running
+    /// carries on into the next batch.
+    pub(crate) fn flush_actions(&mut self) {
+        let running = self.actions.depth > 0;
+        let actions = &mut self.actions.actions;
+        if running {
+            actions.drain(..actions.len() - 1);
+            if let Some(action) = actions.last_mut() {
+                action.ops = 0..0;
+            }
+        } else {
+            actions.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Marks, Path, Point, Text};
+
+    use super::*;
+
+    fn editor() -> Editor {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        editor.select(Point {
+            path: Path::new(vec![0, 0]),
+            offset: 2,
+        });
+        editor.flush();
+        editor
+    }
+
+    #[test]
+    fn transforms_tag_their_actions() {
+        let mut editor = editor();
+        editor.insert_text("c", None);
+        editor.select(Path::new(vec![0]));
+        editor.add_mark(Marks::BOLD);
+
+        let actions: Vec<_> = editor
+            .pending_actions()
+            .into_iter()
+            .map(|action| (action.input, action.ops))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (InputType::Typing, 0..1),
+                (InputType::Programmatic, 1..2),
+                (InputType::Format, 2..3),
+            ]
+        );
+        assert_eq!(editor.input_type(), InputType::Typing);
+    }
+
+    #[test]
+    fn nested_actions_are_the_outermost() {
+        let mut editor = editor();
+        editor.input(InputType::Drag, |editor| {
+            editor.insert_text("c", None);
+            editor.flush();
+            editor.insert_text("d", None);
+            assert_eq!(editor.pending_actions().len(), 1);
+            assert_eq!(editor.input_type(), InputType::Drag);
+        });
+
+        let actions = editor.pending_actions();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(
+            (actions[0].input, actions[0].id),
+            (InputType::Drag, Some(0))
+        );
+        editor.flush();
+        assert_eq!(editor.input_type(), InputType::Programmatic);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::types::{Editor, Point};
+use crate::typography::Typography;
+
+/// A rule run as text is typed, like expanding an abbreviation or turning
+/// markup into formatting, for inputs the editor doesn't handle itself.
+pub trait InputRule: Send + Sync {
+    /// Make the rule's changes after text typed with `insert_text`, with the
+    /// caret right after it at `at`, using the editor's transforms.
+    fn apply(&self, editor: &mut Editor, at: &Point);
+}
+
+/// An input rule an editor was configured with.
+#[derive(Clone)]
+pub(crate) struct InputRuleHandle(Arc<dyn InputRule>);
+
+impl fmt::Debug for InputRuleHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("InputRule")
+    }
+}
+
+// Rules only change what's typed next, so they never make two editors
+// unequal.
+impl PartialEq for InputRuleHandle {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// What's done to text as it's typed: the editor's own typographic
+/// substitutions, then the rules it was given, in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct InputRules {
+    pub(crate) typography: Option<Typography>,
+    pub(crate) rules: Vec<InputRuleHandle>,
+}
+
+impl Editor {
+    /// Run `rule` on text typed with `insert_text`, after the rules added
+    /// before it and the editor's typographic substitutions.
+    pub fn with_input_rule(mut self, rule: impl InputRule + 'static) -> Self {
+        self.input_rules
+            .get_or_insert_with(Default::default)
+            .rules
+            .push(InputRuleHandle(Arc::new(rule)));
+        self
+    }
+
+    /// Run the editor's input rules after text was typed before `at`.
+    pub(crate) fn run_input_rules(&mut self, at: &Point) {
+        let rules = match &self.input_rules {
+            Some(input_rules) => input_rules.rules.clone(),
+            None => return,
+        };
+
+        let mut at = at.clone();
+        for InputRuleHandle(rule) in rules {
+            rule.apply(self, &at);
+            // Earlier rules may have moved the caret.
+            match &self.selection {
+                Some(selection) if selection.is_collapsed() => at = selection.start(),
+                _ => return,
+            }
+        }
+    }
+}
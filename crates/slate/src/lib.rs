@@ -1 +1,87 @@
+mod anchors;
+mod authorship;
+mod autolink;
+pub mod awareness;
+mod behavior;
+mod bidi;
+mod builder;
+#[cfg(feature = "bulk")]
+pub mod bulk;
+mod clipboard;
+pub mod commands;
+mod decorations;
+pub mod dom;
+mod dry_run;
+mod find;
+mod fork;
+mod hibernate;
+mod history;
+mod hit_test;
+mod ids;
+mod inline_code;
+mod input;
+mod input_rules;
+mod limits;
+mod mark_rules;
+mod memory;
+pub mod migrations;
+mod node_cache;
+pub mod plugins;
+mod policy;
+mod range_set;
+mod references;
+mod refs;
+pub mod replay;
+mod sanitize;
+mod schema;
+mod search_index;
+mod segmenter;
+pub mod serialize;
+pub mod store;
+mod subscribers;
+mod sync;
+pub mod testing;
+mod text_index;
+mod tokens;
+mod transforms;
 mod types;
+mod typography;
+
+pub use anchors::{slug, ANCHOR_ALIASES_PROPERTY, ANCHOR_PROPERTY};
+pub use behavior::{DefaultBehavior, EditorBehavior};
+pub use bidi::{Direction, DIRECTION_PROPERTY};
+pub use builder::{BuildError, EditorBuilder, Plugin};
+pub use clipboard::ClipboardPayload;
+pub use decorations::Decorator;
+pub use find::FindIter;
+pub use hibernate::{HibernatedDoc, WakeError};
+pub use history::{History, States};
+pub use hit_test::Measurer;
+pub use ids::{IdGenerator, SeededIds};
+pub use inline_code::InlineCode;
+pub use input::InputType;
+pub use input_rules::InputRule;
+pub use limits::{Limit, Limits};
+pub use mark_rules::MarkRules;
+pub use memory::MemoryFootprint;
+pub use node_cache::NodeCache;
+pub use policy::{Decision, Policy};
+pub use range_set::RangeSet;
+pub use references::{Reference, ReferenceRules};
+pub use refs::{PathRef, PointRef, RangeRef};
+pub use sanitize::{sanitize, SanitizeOptions};
+pub use schema::Schema;
+pub use search_index::IndexUnit;
+#[cfg(feature = "icu")]
+pub use segmenter::IcuSegmenter;
+pub use segmenter::{Segmenter, UnicodeSegmenter};
+pub use subscribers::SubscriptionId;
+pub use sync::{SequenceError, Sequencer};
+pub use tokens::Token;
+pub use transforms::{
+    diff, Alignment, ApplyError, LineBreaker, MergeOptions, NodeLevel, NormalizeReport,
+    SelectionAffinities, SplitOptions, Unit, ALIGN_PROPERTY, INDENT_PROPERTY, MAX_INDENT,
+    MAX_NORMALIZE_PASSES,
+};
+pub use types::*;
+pub use typography::Typography;
@@ -0,0 +1,451 @@
+use crate::transforms::ApplyError;
+use crate::types::{Children, Descendant, Editor, Node, Operation, Path};
+
+/// Upper bounds on the size of a document, enforced whenever an operation is
+/// applied. They protect processes applying operations from untrusted sources
+/// against documents built to exhaust memory.
+///
+/// The editor keeps count of the nodes and text in its document as operations
+/// are applied, so checking an operation against the limits costs as much as
+/// measuring what it inserts or removes, however big the document is.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Limits {
+    /// The maximum number of elements and text nodes in the document.
+    pub max_nodes: Option<usize>,
+    /// The maximum length of any path in the document.
+    pub max_depth: Option<usize>,
+    /// The maximum total length of text in the document, in bytes.
+    pub max_text_length: Option<usize>,
+}
+
+/// A limit that an operation would exceed, with its configured maximum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Nodes(usize),
+    Depth(usize),
+    TextLength(usize),
+}
+
+impl Limits {
+    pub fn max_nodes(mut self, max: usize) -> Self {
+        self.max_nodes = Some(max);
+        self
+    }
+
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    pub fn max_text_length(mut self, max: usize) -> Self {
+        self.max_text_length = Some(max);
+        self
+    }
+}
+
+/// The size of a document, or of a subtree when measured from its root.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Size {
+    nodes: usize,
+    /// How many levels the subtree spans below its root.
+    height: usize,
+    text_length: usize,
+}
+
+fn measure<'a>(nodes: impl IntoIterator<Item = &'a Descendant>) -> Size {
+    let mut size = Size::default();
+
+    for node in nodes {
+        let child = match node {
+            Descendant::Text(t) => Size {
+                nodes: 1,
+                height: 1,
+                text_length: t.text().len(),
+            },
+            Descendant::Element(e) => {
+                let inner = measure(e.children_ref());
+                Size {
+                    nodes: inner.nodes + 1,
+                    height: inner.height + 1,
+                    text_length: inner.text_length,
+                }
+            }
+        };
+
+        size.nodes += child.nodes;
+        size.height = size.height.max(child.height);
+        size.text_length += child.text_length;
+    }
+
+    size
+}
+
+impl Size {
+    fn grow(&mut self, by: Size) {
+        self.nodes += by.nodes;
+        self.text_length += by.text_length;
+    }
+
+    fn shrink(&mut self, by: Size) {
+        self.nodes = self.nodes.saturating_sub(by.nodes);
+        self.text_length = self.text_length.saturating_sub(by.text_length);
+    }
+}
+
+/// How an operation changes the size of a document: what it adds, and what
+/// it removes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Resize {
+    added: Size,
+    removed: Size,
+}
+
+/// An editor's limits, along with the number of nodes and the length of text
+/// in its document, which applying operations keeps up to date.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EnforcedLimits {
+    pub(crate) limits: Limits,
+    size: Size,
+}
+
+impl EnforcedLimits {
+    fn new(limits: Limits, children: &Children) -> Self {
+        Self {
+            limits,
+            size: measure(children),
+        }
+    }
+
+    /// Count a node added to the document without an operation.
+    pub(crate) fn add(&mut self, node: &Descendant) {
+        self.size.grow(measure(std::slice::from_ref(node)));
+    }
+
+    /// Measure a document that replaced the one counted so far.
+    pub(crate) fn remeasure(&mut self, children: &Children) {
+        self.size = measure(children);
+    }
+
+    pub(crate) fn resize(&mut self, resize: Resize) {
+        self.size.grow(resize.added);
+        self.size.shrink(resize.removed);
+    }
+}
+
+impl Limits {
+    /// Find a limit that a whole document exceeds, if any.
+    pub(crate) fn exceeded_by<'a>(
+        &self,
+        nodes: impl IntoIterator<Item = &'a Descendant>,
+    ) -> Option<Limit> {
+        let size = measure(nodes);
+
+        match (self.max_nodes, self.max_depth, self.max_text_length) {
+            (Some(max), _, _) if size.nodes > max => Some(Limit::Nodes(max)),
+            (_, Some(max), _) if size.height > max => Some(Limit::Depth(max)),
+            (_, _, Some(max)) if size.text_length > max => Some(Limit::TextLength(max)),
+            _ => None,
+        }
+    }
+}
+
+impl Editor {
+    /// Enforce size limits on every operation applied to the editor.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(Box::new(EnforcedLimits::new(limits, &self.children)));
+        self
+    }
+
+    pub fn limits(&self) -> Option<&Limits> {
+        self.limits.as_deref().map(|enforced| &enforced.limits)
+    }
+
+    /// Check that applying an operation keeps the document within its limits,
+    /// getting how it would change the size of the document, to be counted
+    /// once it's applied.
+    pub(crate) fn check_limits(&self, op: &Operation) -> Result<Option<Resize>, ApplyError> {
+        let enforced = match &self.limits {
+            Some(enforced) => enforced,
+            None => return Ok(None),
+        };
+
+        let removed = match op {
+            Operation::RemoveNode { path, .. } => match self.descendant_ref(path) {
+                Some(node) => measure(std::slice::from_ref(node)),
+                None => Size::default(),
+            },
+            Operation::RemoveText { text, .. } => Size {
+                text_length: text.len(),
+                ..Size::default()
+            },
+            Operation::MergeNode { .. } => Size {
+                nodes: 1,
+                ..Size::default()
+            },
+            _ => Size::default(),
+        };
+        let added = match op {
+            Operation::InsertNode { node, path } => {
+                let node = match node {
+                    Node::Element(e) => Descendant::Element(e.clone()),
+                    Node::Text(t) => Descendant::Text(t.clone()),
+                    Node::Editor(_) => return Ok(None),
+                };
+                let size = measure(&[node]);
+                Some((size, path.len().saturating_sub(1) + size.height))
+            }
+            Operation::InsertText { text, .. } => Some((
+                Size {
+                    text_length: text.len(),
+                    ..Size::default()
+                },
+                0,
+            )),
+            Operation::SplitNode { path, .. } => Some((
+                Size {
+                    nodes: 1,
+                    ..Size::default()
+                },
+                path.len(),
+            )),
+            Operation::MoveNode { path, .. } => {
+                let height = match self.descendant_ref(path) {
+                    Some(node) => measure(std::slice::from_ref(node)).height,
+                    None => return Ok(None),
+                };
+                let new_path = Path::transform(path, op, Default::default()).ok().flatten();
+                let depth = new_path.map_or(0, |p| p.len() - 1 + height);
+                Some((Size::default(), depth))
+            }
+            _ => None,
+        };
+
+        let added = match added {
+            Some((size, depth)) => {
+                self.check_growth(enforced, size, depth)?;
+                size
+            }
+            None => Size::default(),
+        };
+        Ok(Some(Resize { added, removed }))
+    }
+
+    /// Check that inserting a fragment at a path keeps the document within its
+    /// limits, before any of the operations doing so are applied.
+    pub(crate) fn check_fragment_limits(
+        &self,
+        fragment: &[Descendant],
+        at: &Path,
+    ) -> Result<(), ApplyError> {
+        let enforced = match &self.limits {
+            Some(enforced) => enforced,
+            None => return Ok(()),
+        };
+
+        let mut size = measure(fragment);
+        // Splitting the point's ancestors adds one node per level.
+        size.nodes += at.len();
+        self.check_growth(enforced, size, at.len().saturating_sub(1) + size.height)
+    }
+
+    fn check_growth(
+        &self,
+        enforced: &EnforcedLimits,
+        added: Size,
+        depth: usize,
+    ) -> Result<(), ApplyError> {
+        let (limits, current) = (&enforced.limits, enforced.size);
+        if let Some(max) = limits.max_depth {
+            if depth > max {
+                return Err(ApplyError::LimitExceeded(Limit::Depth(max)));
+            }
+        }
+
+        if let Some(max) = limits.max_nodes {
+            if added.nodes > 0 && current.nodes + added.nodes > max {
+                return Err(ApplyError::LimitExceeded(Limit::Nodes(max)));
+            }
+        }
+
+        if let Some(max) = limits.max_text_length {
+            if added.text_length > 0 && current.text_length + added.text_length > max {
+                return Err(ApplyError::LimitExceeded(Limit::TextLength(max)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, Point, Text};
+
+    fn editor(limits: Limits) -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(Element::new().add_child(Text::new("two")))
+            .with_limits(limits)
+    }
+
+    #[test]
+    fn insert_node_too_deep() {
+        let mut editor = editor(Limits::default().max_depth(3));
+        let op = Operation::InsertNode {
+            path: vec![0, 1].into(),
+            node: Node::Element(Element::new().add_child(Element::new().add_child(Text::new("")))),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::LimitExceeded(Limit::Depth(3)))
+        );
+    }
+
+    #[test]
+    fn insert_node_too_many() {
+        let mut editor = editor(Limits::default().max_nodes(5));
+        let op = Operation::InsertNode {
+            path: vec![2].into(),
+            node: Node::Element(Element::new().add_child(Text::new(""))),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::LimitExceeded(Limit::Nodes(5)))
+        );
+        assert_eq!(editor.num_children(), 2);
+    }
+
+    #[test]
+    fn insert_node_within_limits() {
+        let mut editor = editor(Limits::default().max_nodes(6).max_depth(2));
+        let op = Operation::InsertNode {
+            path: vec![2].into(),
+            node: Node::Element(Element::new().add_child(Text::new(""))),
+        };
+        assert_eq!(editor.apply(op), Ok(()));
+    }
+
+    #[test]
+    fn insert_text_too_long() {
+        let mut editor = editor(Limits::default().max_text_length(8));
+        let op = Operation::InsertText {
+            path: vec![0, 0].into(),
+            offset: 0,
+            text: "abc".into(),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::LimitExceeded(Limit::TextLength(8)))
+        );
+        assert_eq!(editor.text_at(&vec![0, 0].into()).unwrap().text(), "one");
+    }
+
+    #[test]
+    fn replaced_children_are_measured() {
+        let mut editor = editor(Limits::default().max_text_length(8));
+        editor.replace_children(vec![Element::new().add_child(Text::new("x")).into()]);
+        let op = Operation::InsertText {
+            path: vec![0, 0].into(),
+            offset: 0,
+            text: "abcdefg".into(),
+        };
+        assert_eq!(editor.apply(op), Ok(()));
+        assert_eq!(
+            editor.text_at(&vec![0, 0].into()).unwrap().text(),
+            "abcdefgx"
+        );
+    }
+
+    #[test]
+    fn move_node_too_deep() {
+        let mut editor = editor(Limits::default().max_depth(2));
+        let op = Operation::MoveNode {
+            path: vec![1].into(),
+            new_path: vec![0, 1].into(),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::LimitExceeded(Limit::Depth(2)))
+        );
+    }
+
+    #[test]
+    fn insert_fragment_too_many() {
+        let mut editor = editor(Limits::default().max_nodes(6));
+        editor.select(Point {
+            path: vec![0, 0].into(),
+            offset: 1,
+        });
+        let fragment = vec![
+            Element::new().add_child(Text::new("a")).into(),
+            Element::new().add_child(Text::new("b")).into(),
+        ];
+        assert_eq!(
+            editor.insert_fragment(fragment),
+            Err(ApplyError::LimitExceeded(Limit::Nodes(6)))
+        );
+        assert_eq!(editor.num_children(), 2);
+    }
+
+    #[test]
+    fn sizes_follow_applied_operations() {
+        let mut editor = editor(Limits::default().max_nodes(5).max_text_length(8));
+        let insert_text = |text: &str| Operation::InsertText {
+            path: vec![0, 0].into(),
+            offset: 0,
+            text: text.into(),
+        };
+
+        editor
+            .apply(Operation::RemoveText {
+                path: vec![0, 0].into(),
+                offset: 0,
+                text: "one".into(),
+            })
+            .unwrap();
+        assert_eq!(editor.apply(insert_text("abcde")), Ok(()));
+        assert_eq!(
+            editor.apply(insert_text("!")),
+            Err(ApplyError::LimitExceeded(Limit::TextLength(8)))
+        );
+
+        editor
+            .apply(Operation::RemoveNode {
+                path: vec![1].into(),
+                node: Node::Element(Element::new().add_child(Text::new("two"))),
+            })
+            .unwrap();
+        let block = Element::new().add_child(Text::new("abc"));
+        let op = Operation::InsertNode {
+            path: vec![1].into(),
+            node: Node::Element(block.clone()),
+        };
+        assert_eq!(editor.apply(op), Ok(()));
+
+        let split = Operation::SplitNode {
+            path: vec![1, 0].into(),
+            position: 1,
+            properties: Default::default(),
+        };
+        assert_eq!(editor.apply(split.clone()), Ok(()));
+        assert_eq!(
+            editor.apply(split),
+            Err(ApplyError::LimitExceeded(Limit::Nodes(5)))
+        );
+
+        // Nodes added while building the editor count too.
+        let mut editor = Editor::new()
+            .with_limits(Limits::default().max_nodes(3))
+            .add_child(block);
+        let op = Operation::InsertNode {
+            path: vec![0, 1].into(),
+            node: Node::Text(Text::new("d")),
+        };
+        assert_eq!(editor.apply(op.clone()), Ok(()));
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::LimitExceeded(Limit::Nodes(3)))
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use crate::types::{Editor, Marks};
+
+/// Relationships between marks, enforced whenever marks are added and when
+/// content is normalized.
+///
+/// Each rule says that a mark clears some others, e.g. code clears bold and
+/// italic. Mutually exclusive marks, like subscript and superscript, are a
+/// pair of rules clearing each other.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MarkRules {
+    rules: Vec<(Marks, Marks)>,
+}
+
+impl MarkRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adding `mark` removes `cleared`.
+    pub fn clears(mut self, mark: Marks, cleared: Marks) -> Self {
+        self.rules.push((mark, cleared));
+        self
+    }
+
+    /// At most one of `a` and `b` can be set. Adding either removes the other.
+    pub fn exclusive(self, a: Marks, b: Marks) -> Self {
+        self.clears(a, b).clears(b, a)
+    }
+
+    /// The marks cleared by adding `marks`.
+    pub fn cleared_by(&self, marks: Marks) -> Marks {
+        self.rules
+            .iter()
+            .filter(|(mark, _)| marks.intersects(*mark))
+            .fold(Marks::empty(), |cleared, (_, c)| cleared | *c)
+    }
+
+    /// The marks that clear any of `marks`.
+    pub fn clearing(&self, marks: Marks) -> Marks {
+        self.rules
+            .iter()
+            .filter(|(_, cleared)| marks.intersects(*cleared))
+            .fold(Marks::empty(), |clearing, (m, _)| clearing | *m)
+    }
+
+    /// Add marks to a set. Marks that conflict with the added ones, either
+    /// because an added mark clears them or because they clear an added mark,
+    /// are removed.
+    pub fn add(&self, marks: Marks, added: Marks) -> Marks {
+        let kept = marks - self.cleared_by(added) - self.clearing(added);
+        self.normalize(kept | added)
+    }
+
+    /// Resolve a set of marks that breaks some rules. Rules are applied in the
+    /// order they were declared, so of two exclusive marks the one named first
+    /// is kept.
+    pub fn normalize(&self, marks: Marks) -> Marks {
+        self.rules.iter().fold(marks, |marks, (mark, cleared)| {
+            if marks.contains(*mark) {
+                marks - (*cleared - *mark)
+            } else {
+                marks
+            }
+        })
+    }
+}
+
+impl Editor {
+    /// Enforce relationships between marks when they're added and when
+    /// content is normalized.
+    pub fn with_mark_rules(mut self, rules: MarkRules) -> Self {
+        self.mark_rules = Some(Box::new(rules));
+        self
+    }
+
+    pub fn mark_rules(&self) -> Option<&MarkRules> {
+        self.mark_rules.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_clears_marks() {
+        let rules = MarkRules::new().clears(Marks::UNDERLINE, Marks::BOLD | Marks::ITALIC);
+        assert_eq!(
+            rules.add(Marks::BOLD | Marks::ITALIC, Marks::UNDERLINE),
+            Marks::UNDERLINE
+        );
+        assert_eq!(rules.add(Marks::UNDERLINE, Marks::BOLD), Marks::BOLD);
+        assert_eq!(
+            rules.add(Marks::ITALIC, Marks::BOLD),
+            Marks::ITALIC | Marks::BOLD
+        );
+    }
+
+    #[test]
+    fn exclusive_marks() {
+        let rules = MarkRules::new().exclusive(Marks::BOLD, Marks::ITALIC);
+        assert_eq!(rules.add(Marks::BOLD, Marks::ITALIC), Marks::ITALIC);
+        assert_eq!(rules.add(Marks::ITALIC, Marks::BOLD), Marks::BOLD);
+        assert_eq!(rules.normalize(Marks::BOLD | Marks::ITALIC), Marks::BOLD);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::mem;
+
+use crate::types::{
+    Children, Descendant, Editor, Element, Node, NodeProperties, Operation, Path, Text,
+};
+
+/// An estimate of the bytes an editor takes on the heap, by what they're
+/// taken by.
+///
+/// Strings and collections are counted by their contents rather than their
+/// capacity, and nodes shared with other editors, like clones or the
+/// document before the current batch, are counted as if they weren't, so
+/// this is for comparing documents rather than for exact accounting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryFootprint {
+    /// Text nodes, with their text, marks and properties.
+    pub texts: usize,
+    /// Elements, with their kinds and properties.
+    pub elements: usize,
+    /// The text index and node caches. These can be dropped and rebuilt.
+    pub indexes: usize,
+    /// The operations of the current batch, and those logged since the
+    /// editor was forked.
+    pub history: usize,
+}
+
+impl MemoryFootprint {
+    pub fn total(&self) -> usize {
+        self.texts + self.elements + self.indexes + self.history
+    }
+}
+
+impl Editor {
+    /// Estimate how much memory the editor takes, for a process hosting
+    /// many documents to decide which to evict or snapshot and drop.
+    pub fn memory_footprint(&self) -> MemoryFootprint {
+        let mut footprint = MemoryFootprint::default();
+        count_children(&self.children, &mut footprint);
+
+        footprint.indexes = self.text_index.heap_size() + self.caches.heap_size();
+        footprint.history = self
+            .operations
+            .iter()
+            .chain(self.fork_log.iter().flat_map(|log| &log.0))
+            .map(operation_size)
+            .sum();
+
+        footprint
+    }
+}
+
+fn count_children(children: &Children, footprint: &mut MemoryFootprint) {
+    for child in children.iter() {
+        count_node(child, footprint);
+    }
+}
+
+fn count_node(node: &Descendant, footprint: &mut MemoryFootprint) {
+    match node {
+        Descendant::Element(element) => count_element(element, footprint),
+        Descendant::Text(text) => count_text(text, footprint),
+    }
+}
+
+fn count_element(element: &Element, footprint: &mut MemoryFootprint) {
+    footprint.elements +=
+        mem::size_of::<Descendant>() + element.kind().len() + map_size(element.properties());
+    count_children(element.children_ref(), footprint);
+}
+
+fn count_text(text: &Text, footprint: &mut MemoryFootprint) {
+    let meta: usize = text
+        .meta()
+        .iter()
+        .map(|m| mem::size_of::<String>() + m.len())
+        .sum();
+    footprint.texts += mem::size_of::<Descendant>()
+        + text.text().len()
+        + meta
+        + text.author().map_or(0, str::len)
+        + map_size(text.mark_values());
+}
+
+fn map_size(map: &BTreeMap<String, String>) -> usize {
+    map.iter()
+        .map(|(key, value)| 2 * mem::size_of::<String>() + key.len() + value.len())
+        .sum()
+}
+
+/// Estimate the bytes an operation takes, including the nodes it carries.
+fn operation_size(op: &Operation) -> usize {
+    let path = |path: &Path| path.len() * mem::size_of::<usize>();
+    let properties = |properties: &NodeProperties| -> usize {
+        properties.kind.as_ref().map_or(0, String::len)
+            + properties
+                .properties
+                .iter()
+                .map(|(key, value)| {
+                    2 * mem::size_of::<String>() + key.len() + value.as_ref().map_or(0, String::len)
+                })
+                .sum::<usize>()
+    };
+
+    let payload = match op {
+        Operation::InsertNode { node, path: at } | Operation::RemoveNode { node, path: at } => {
+            let mut footprint = MemoryFootprint::default();
+            match node {
+                Node::Editor(editor) => count_children(&editor.children, &mut footprint),
+                Node::Element(element) => count_element(element, &mut footprint),
+                Node::Text(text) => count_text(text, &mut footprint),
+            }
+            path(at) + footprint.texts + footprint.elements
+        }
+        Operation::InsertText { path: at, text, .. }
+        | Operation::RemoveText { path: at, text, .. } => path(at) + text.len(),
+        Operation::MergeNode {
+            path: at,
+            properties: p,
+            ..
+        }
+        | Operation::SplitNode {
+            path: at,
+            properties: p,
+            ..
+        } => path(at) + properties(p),
+        Operation::MoveNode { path: at, new_path } => path(at) + path(new_path),
+        Operation::SetNode {
+            path: at,
+            properties: p,
+            new_properties,
+        } => path(at) + properties(p) + properties(new_properties),
+        Operation::SetSelection { path: at, .. } => path(at),
+    };
+
+    mem::size_of::<Operation>() + payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footprint_grows_with_the_document() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("short")));
+        let before = editor.memory_footprint();
+        assert!(before.texts > 0 && before.elements > 0);
+        assert_eq!(before.history, 0);
+
+        editor
+            .apply(Operation::InsertText {
+                path: Path::new(vec![0, 0]),
+                offset: 5,
+                text: "x".repeat(1000),
+            })
+            .unwrap();
+        let after = editor.memory_footprint();
+        assert_eq!(after.texts, before.texts + 1000);
+        assert_eq!(after.elements, before.elements);
+        assert!(after.history > 1000);
+
+        editor.flush();
+        assert_eq!(editor.memory_footprint().history, 0);
+    }
+
+    #[test]
+    fn footprint_counts_indexes() {
+        let editor = Editor::new().add_child(Element::new().add_child(Text::new("indexed")));
+        assert_eq!(editor.memory_footprint().indexes, 0);
+
+        editor.block_text(&Path::new(vec![0]));
+        let footprint = editor.memory_footprint();
+        assert!(footprint.indexes > "indexed".len());
+        assert_eq!(
+            footprint.total(),
+            footprint.texts + footprint.elements + footprint.indexes
+        );
+    }
+}
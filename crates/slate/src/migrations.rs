@@ -0,0 +1,261 @@
+//! Versioned changes to the shape of documents, like renaming an element
+//! kind, declared once and applied to stored documents as they're loaded.
+//!
+//! A document's schema version is the number of the last migration applied
+//! to it, and documents without one are at version 0. Each migration can be
+//! applied up, to move a document to its version, or down, to move it back
+//! to the one before.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::serialize::json::{self, ImportWarning};
+use crate::types::{element_entries, text_entries, Editor, Marks, NodeProperties, Path};
+
+/// A change to the shape of documents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Turn elements of one kind into another.
+    RenameKind { from: String, to: String },
+    /// Move the value of a property to another one, on elements of `kind`,
+    /// or on every element without one.
+    MoveProperty {
+        kind: Option<String>,
+        from: String,
+        to: String,
+    },
+    /// Replace a mark on text with the marks in `into`. Going down, text with
+    /// all of `into` gets the mark back in place of them.
+    SplitMark { mark: Marks, into: Marks },
+}
+
+impl Step {
+    pub fn rename_kind(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Step::RenameKind {
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+
+    pub fn move_property(
+        kind: Option<&str>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> Self {
+        Step::MoveProperty {
+            kind: kind.map(String::from),
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+
+    pub fn split_mark(mark: Marks, into: Marks) -> Self {
+        Step::SplitMark { mark, into }
+    }
+
+    /// Apply the step to a document, up or down.
+    fn apply(&self, editor: &mut Editor, up: bool) {
+        let root = Path::new(vec![]);
+        let changes: Vec<(Path, NodeProperties)> = match self {
+            Step::RenameKind { from, to } => {
+                let (from, to) = if up { (from, to) } else { (to, from) };
+                element_entries(&editor.children, &root)
+                    .into_iter()
+                    .filter(|(e, _)| e.kind() == from)
+                    .map(|(_, path)| (path, NodeProperties::new().kind(to.clone())))
+                    .collect()
+            }
+            Step::MoveProperty { kind, from, to } => {
+                let (from, to) = if up { (from, to) } else { (to, from) };
+                element_entries(&editor.children, &root)
+                    .into_iter()
+                    .filter(|(e, _)| kind.as_ref().is_none_or(|k| e.kind() == k))
+                    .filter_map(|(e, path)| {
+                        let value = e.property(from)?;
+                        let properties = NodeProperties::new()
+                            .property(from.clone(), None::<String>)
+                            .property(to.clone(), Some(value));
+                        Some((path, properties))
+                    })
+                    .collect()
+            }
+            Step::SplitMark { mark, into } => {
+                let (from, to) = if up { (*mark, *into) } else { (*into, *mark) };
+                text_entries(&editor.children, &root)
+                    .into_iter()
+                    .filter(|(t, _)| t.marks().contains(from))
+                    .map(|(t, path)| {
+                        let marks = (t.marks() - from) | to;
+                        (path, NodeProperties::new().marks(marks))
+                    })
+                    .collect()
+            }
+        };
+
+        // Setting properties doesn't move any nodes, so the paths stay valid.
+        for (path, properties) in changes {
+            editor
+                .set_node(&path, properties)
+                .expect("setting the properties of a migrated node to apply");
+        }
+    }
+}
+
+/// Why a document couldn't be migrated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationError {
+    /// No migration is declared with a version the document has to go
+    /// through.
+    MissingVersion(u32),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::MissingVersion(v) => write!(f, "no migration to version {}", v),
+        }
+    }
+}
+
+impl Error for MigrationError {}
+
+/// The migrations of a kind of document, by version.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Migrations {
+    /// Each migration's version and steps, applied in order going up and in
+    /// reverse going down.
+    pub migrations: Vec<(u32, Vec<Step>)>,
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare the migration to a version.
+    pub fn migration(mut self, version: u32, steps: Vec<Step>) -> Self {
+        self.migrations.push((version, steps));
+        self
+    }
+
+    /// Get the newest version declared, or 0 without any migrations.
+    pub fn latest(&self) -> u32 {
+        self.migrations.iter().map(|(v, _)| *v).max().unwrap_or(0)
+    }
+
+    /// Move a document to a version, applying the migrations up or down in
+    /// between as operations. The document is left alone if one of them is
+    /// missing.
+    pub fn migrate(&self, editor: &mut Editor, target: u32) -> Result<(), MigrationError> {
+        let current = editor.schema_version();
+        let versions: Vec<u32> = if target >= current {
+            (current + 1..=target).collect()
+        } else {
+            (target + 1..=current).rev().collect()
+        };
+
+        let mut steps = vec![];
+        for version in &versions {
+            match self.migrations.iter().find(|(v, _)| v == version) {
+                Some((_, s)) => steps.push(s),
+                None => return Err(MigrationError::MissingVersion(*version)),
+            }
+        }
+
+        let up = target >= current;
+        for steps in steps {
+            if up {
+                steps.iter().for_each(|step| step.apply(editor, true));
+            } else {
+                steps
+                    .iter()
+                    .rev()
+                    .for_each(|step| step.apply(editor, false));
+            }
+        }
+        editor.schema_version = target;
+        Ok(())
+    }
+
+    /// Read a document from JSON like `json::from_string_lossy` and migrate
+    /// it to the latest version.
+    pub fn load(&self, json: &str) -> Result<(Editor, Vec<ImportWarning>), MigrationError> {
+        let (mut editor, warnings) = json::from_string_lossy(json);
+        self.migrate(&mut editor, self.latest())?;
+        Ok((editor, warnings))
+    }
+}
+
+impl Editor {
+    /// Get the version of the schema the document follows, which is the
+    /// version of the last migration applied to it.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Set the version of the schema a new document follows.
+    pub fn with_schema_version(mut self, version: u32) -> Self {
+        self.schema_version = version;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Text};
+
+    use super::*;
+
+    fn migrations() -> Migrations {
+        Migrations::new()
+            .migration(1, vec![Step::rename_kind("title", "heading-one")])
+            .migration(
+                2,
+                vec![
+                    Step::move_property(Some("heading-one"), "slug", "id"),
+                    Step::split_mark(Marks::UNDERLINE, Marks::BOLD | Marks::ITALIC),
+                ],
+            )
+    }
+
+    fn old() -> Editor {
+        Editor::new().add_child(
+            Element::with_kind("title")
+                .add_property("slug", "top")
+                .add_child(Text::with_marks("a", Marks::UNDERLINE)),
+        )
+    }
+
+    #[test]
+    fn migrate_up_and_down() {
+        let migrations = migrations();
+        let mut editor = old();
+        migrations.migrate(&mut editor, 2).unwrap();
+        assert_eq!(editor.schema_version(), 2);
+        assert_eq!(
+            editor.children(),
+            vec![Element::with_kind("heading-one")
+                .add_property("id", "top")
+                .add_child(Text::with_marks("a", Marks::BOLD | Marks::ITALIC))
+                .into()]
+        );
+
+        migrations.migrate(&mut editor, 0).unwrap();
+        assert_eq!(editor.children(), old().children());
+        assert_eq!(
+            migrations.migrate(&mut editor, 3),
+            Err(MigrationError::MissingVersion(3))
+        );
+        assert_eq!(editor.schema_version(), 0);
+    }
+
+    #[test]
+    fn load_migrates_to_latest() {
+        let (editor, _) = migrations().load(&json::to_string(&old())).unwrap();
+        assert_eq!(editor.schema_version(), 2);
+
+        let (reloaded, _) = migrations().load(&json::to_string(&editor)).unwrap();
+        assert_eq!(reloaded.children(), editor.children());
+        assert!(reloaded.operations().is_empty());
+    }
+}
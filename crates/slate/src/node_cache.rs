@@ -0,0 +1,439 @@
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+
+use crate::decorations::DecoratorHandle;
+use crate::references::ReferenceRules;
+use crate::types::{DocumentIndex, Editor, Operation, Path};
+
+/// Values computed from nodes, like spellcheck results or layout
+/// measurements, keyed by the path of the node they were computed from.
+///
+/// Applying an operation drops the entries of the nodes it changes, including
+/// the ancestors of changed nodes, and moves the rest along with their nodes.
+/// An entry that's still there is up to date with its node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeCache<T> {
+    entries: HashMap<Path, T>,
+}
+
+impl<T> Default for NodeCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T> NodeCache<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&T> {
+        self.entries.get(path)
+    }
+
+    pub fn insert(&mut self, path: Path, value: T) -> Option<T> {
+        self.entries.insert(path, value)
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<T> {
+        self.entries.remove(path)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Update the cache for an operation that has just been applied.
+    pub(crate) fn apply(&mut self, op: &Operation) {
+        self.apply_batch(std::slice::from_ref(op));
+    }
+
+    /// Estimate the bytes the cache takes on the heap, given the bytes each
+    /// value takes beyond its own size.
+    pub(crate) fn heap_size(&self, value: impl Fn(&T) -> usize) -> usize {
+        let slots = self.entries.capacity() * mem::size_of::<(Path, T)>();
+        let entries: usize = self
+            .entries
+            .iter()
+            .map(|(path, v)| path.len() * mem::size_of::<usize>() + value(v))
+            .sum();
+        slots + entries
+    }
+
+    /// Update the cache for operations that have just been applied, in
+    /// order. Entries above the depth the operations reach can't move, so
+    /// they're only checked for being changed, which keeps a batch of edits
+    /// inside blocks cheap however many blocks are cached.
+    pub(crate) fn apply_batch(&mut self, ops: &[Operation]) {
+        let depth = match ops.iter().flat_map(op_paths).map(Path::len).min() {
+            Some(depth) => depth,
+            None => return,
+        };
+        let changed: HashSet<&[usize]> = ops
+            .iter()
+            .flat_map(op_paths)
+            .flat_map(|p| (0..depth).map(move |n| &p.as_slice()[..n]))
+            .collect();
+
+        let entries = std::mem::take(&mut self.entries);
+        self.entries = entries
+            .into_iter()
+            .filter_map(|(node, value)| {
+                if node.len() < depth {
+                    return (!changed.contains(node.as_slice())).then_some((node, value));
+                }
+                let node = ops.iter().try_fold(node, |node, op| transform(&node, op))?;
+                Some((node, value))
+            })
+            .collect();
+    }
+}
+
+/// Get the paths an operation changes the children at, and the new path of
+/// a moved node.
+fn op_paths(op: &Operation) -> Vec<&Path> {
+    match op {
+        Operation::SetSelection { .. } => vec![],
+        Operation::MoveNode { path, new_path } => vec![path, new_path],
+        Operation::InsertNode { path, .. }
+        | Operation::InsertText { path, .. }
+        | Operation::MergeNode { path, .. }
+        | Operation::RemoveNode { path, .. }
+        | Operation::RemoveText { path, .. }
+        | Operation::SetNode { path, .. }
+        | Operation::SplitNode { path, .. } => vec![path],
+    }
+}
+
+/// Move the path of an entry's node through an operation, or get `None` if
+/// the operation changes the node, including as one of its ancestors.
+fn transform(node: &Path, op: &Operation) -> Option<Path> {
+    let dirty = match op {
+        Operation::SetSelection { .. } => return Some(node.clone()),
+        Operation::MergeNode { path, .. } => vec![path.clone()]
+            .into_iter()
+            .chain(path.previous())
+            .collect(),
+        // The node at an insertion's path is only moved aside.
+        Operation::InsertNode { path, .. } => path.parent().into_iter().collect(),
+        Operation::InsertText { path, .. }
+        | Operation::MoveNode { path, .. }
+        | Operation::RemoveNode { path, .. }
+        | Operation::RemoveText { path, .. }
+        | Operation::SetNode { path, .. }
+        | Operation::SplitNode { path, .. } => vec![path.clone()],
+    };
+    if dirty.iter().any(|p| node.is_common(p)) {
+        return None;
+    }
+
+    let node = Path::transform(node, op, Default::default()).ok()??;
+
+    // A moved node also changes its new ancestors.
+    if let Operation::MoveNode { path, .. } = op {
+        if let Ok(Some(moved)) = Path::transform(path, op, Default::default()) {
+            if node.is_common(&moved) {
+                return None;
+            }
+        }
+    }
+    Some(node)
+}
+
+/// A `NodeCache` of any type.
+trait AnyCache: Send + Sync {
+    fn apply(&mut self, op: &Operation);
+    fn apply_batch(&mut self, ops: &[Operation]);
+    fn clear(&mut self);
+    fn clone_box(&self) -> Box<dyn AnyCache>;
+    fn heap_size(&self) -> usize;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Clone + Send + Sync + 'static> AnyCache for NodeCache<T> {
+    fn apply(&mut self, op: &Operation) {
+        NodeCache::apply(self, op);
+    }
+
+    fn apply_batch(&mut self, ops: &[Operation]) {
+        NodeCache::apply_batch(self, ops);
+    }
+
+    fn clear(&mut self) {
+        NodeCache::clear(self);
+    }
+
+    fn clone_box(&self) -> Box<dyn AnyCache> {
+        Box::new(self.clone())
+    }
+
+    fn heap_size(&self) -> usize {
+        NodeCache::heap_size(self, |_| 0)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The caches an editor keeps up to date, one per type of value, and its
+/// index of nodes by path, along with the decorator and reference rules that
+/// fill its caches of decorations and references.
+#[derive(Default)]
+pub(crate) struct NodeCaches {
+    caches: HashMap<TypeId, Box<dyn AnyCache>>,
+    pub(crate) index: DocumentIndex,
+    pub(crate) decorator: Option<DecoratorHandle>,
+    pub(crate) references: Option<Box<ReferenceRules>>,
+}
+
+impl NodeCaches {
+    pub(crate) fn apply(&mut self, op: &Operation) {
+        for cache in self.caches.values_mut() {
+            cache.apply(op);
+        }
+    }
+
+    /// Drop every cached value and the index, for a document whose children
+    /// were replaced.
+    pub(crate) fn clear(&mut self) {
+        for cache in self.caches.values_mut() {
+            cache.clear();
+        }
+        self.index.clear();
+    }
+
+    /// Estimate the bytes the caches take on the heap. What values hold
+    /// beyond their own size, like the contents of strings, isn't counted.
+    pub(crate) fn heap_size(&self) -> usize {
+        let caches: usize = self.caches.values().map(|cache| cache.heap_size()).sum();
+        caches + self.index.heap_size()
+    }
+}
+
+impl Clone for NodeCaches {
+    fn clone(&self) -> Self {
+        Self {
+            caches: self
+                .caches
+                .iter()
+                .map(|(k, v)| (*k, v.clone_box()))
+                .collect(),
+            index: self.index.clone(),
+            decorator: self.decorator.clone(),
+            references: self.references.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for NodeCaches {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeCaches")
+            .field("count", &self.caches.len())
+            .field("decorator", &self.decorator)
+            .field("references", &self.references)
+            .finish()
+    }
+}
+
+// Caches only hold what's derived from the document, so they never make two
+// editors unequal.
+impl PartialEq for NodeCaches {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Editor {
+    /// Get the editor's cache of `T`s, if one has been created with
+    /// `cache_mut`.
+    pub fn cache<T: Clone + Send + Sync + 'static>(&self) -> Option<&NodeCache<T>> {
+        self.caches
+            .caches
+            .get(&TypeId::of::<T>())
+            .and_then(|cache| cache.as_any().downcast_ref())
+    }
+
+    /// Get the editor's cache of `T`s, creating it if there isn't one yet. The
+    /// editor keeps it up to date with the document from then on.
+    pub fn cache_mut<T: Clone + Send + Sync + 'static>(&mut self) -> &mut NodeCache<T> {
+        self.caches
+            .caches
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(NodeCache::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("caches to be keyed by their type")
+    }
+
+    /// Run a function applying many operations, like normalizing, with the
+    /// caches and the text index updated once for all of them rather than
+    /// after each one. They're empty while it runs, and `f` must not flush.
+    /// The document index is what looks nodes up while `f` runs, so it's kept
+    /// up to date throughout.
+    pub(crate) fn with_batched_caches<R>(&mut self, f: impl FnOnce(&mut Editor) -> R) -> R {
+        let start = self.operations.len();
+        let caches = std::mem::take(&mut self.caches.caches);
+        let mut index = std::mem::take(&mut *self.text_index);
+
+        let out = f(self);
+
+        let ops = &self.operations[start.min(self.operations.len())..];
+        for (kind, mut cache) in caches {
+            // Caches made while `f` ran are up to date already.
+            self.caches.caches.entry(kind).or_insert_with(|| {
+                cache.apply_batch(ops);
+                cache
+            });
+        }
+        index.apply_batch(ops);
+        *self.text_index = index;
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Node, NodeProperties, Text};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct WordCount(usize);
+
+    #[test]
+    fn edits_invalidate_changed_nodes() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one two")))
+            .add_child(Element::new().add_child(Text::new("three")));
+        assert!(editor.cache::<WordCount>().is_none());
+
+        let cache = editor.cache_mut::<WordCount>();
+        cache.insert(Path::new(vec![0]), WordCount(2));
+        cache.insert(Path::new(vec![1]), WordCount(1));
+
+        editor
+            .apply(Operation::InsertText {
+                path: Path::new(vec![1, 0]),
+                offset: 5,
+                text: " four".into(),
+            })
+            .unwrap();
+        let cache = editor.cache::<WordCount>().unwrap();
+        assert_eq!(cache.get(&Path::new(vec![0])), Some(&WordCount(2)));
+        assert_eq!(cache.get(&Path::new(vec![1])), None);
+
+        editor
+            .apply(Operation::SplitNode {
+                path: Path::new(vec![0]),
+                position: 0,
+                properties: NodeProperties::new(),
+            })
+            .unwrap();
+        assert!(editor.cache::<WordCount>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn replacing_children_drops_what_is_cached() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("one")));
+        editor
+            .cache_mut::<WordCount>()
+            .insert(Path::new(vec![0]), WordCount(1));
+        assert!(editor.block_text(&Path::new(vec![0])).is_some());
+        assert!(editor.descendant_ref(&Path::new(vec![0, 0])).is_some());
+
+        editor.replace_children(vec![Element::new()
+            .add_child(Text::new("two three"))
+            .into()]);
+        assert!(editor.cache::<WordCount>().unwrap().is_empty());
+        assert_eq!(
+            editor.block_text(&Path::new(vec![0])),
+            Some("two three".into())
+        );
+        assert_eq!(
+            editor.text_at(&Path::new(vec![0, 0])).map(Text::text),
+            Some("two three")
+        );
+    }
+
+    #[test]
+    fn batches_match_single_operations() {
+        let mut cache = NodeCache::new();
+        for path in [
+            vec![],
+            vec![0],
+            vec![1],
+            vec![2],
+            vec![1, 0],
+            vec![1, 1],
+            vec![2, 0],
+        ]
+        .iter()
+        {
+            cache.insert(Path::new(path.clone()), path.len());
+        }
+        let ops = vec![
+            Operation::RemoveNode {
+                path: Path::new(vec![1, 0]),
+                node: Node::Text(Text::new("")),
+            },
+            Operation::MergeNode {
+                path: Path::new(vec![2, 1]),
+                position: 1,
+                properties: NodeProperties::new(),
+            },
+            Operation::MoveNode {
+                path: Path::new(vec![3, 0]),
+                new_path: Path::new(vec![2, 2]),
+            },
+        ];
+
+        let mut single = cache.clone();
+        for op in &ops {
+            single.apply(op);
+        }
+        cache.apply_batch(&ops);
+        assert_eq!(cache, single);
+        assert_eq!(cache.get(&Path::new(vec![0])), Some(&1));
+        assert_eq!(cache.get(&Path::new(vec![1])), None);
+        assert_eq!(cache.get(&Path::new(vec![1, 0])), Some(&2));
+    }
+
+    #[test]
+    fn entries_move_with_their_nodes() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("a")))
+            .add_child(Element::new().add_child(Text::new("b")));
+        editor
+            .cache_mut::<WordCount>()
+            .insert(Path::new(vec![1]), WordCount(1));
+
+        editor
+            .apply(Operation::RemoveNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(Element::new().add_child(Text::new("a"))),
+            })
+            .unwrap();
+
+        let cache = editor.cache::<WordCount>().unwrap();
+        assert_eq!(cache.get(&Path::new(vec![0])), Some(&WordCount(1)));
+        assert_eq!(cache.len(), 1);
+    }
+}
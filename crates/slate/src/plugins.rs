@@ -0,0 +1,55 @@
+//! Optional kinds of content built from plain elements, with the transforms
+//! and normalization that keep them consistent.
+
+pub mod conflicts;
+pub mod emoji;
+pub mod footnotes;
+pub mod front_matter;
+pub mod math;
+pub mod redaction;
+pub mod transclusion;
+
+use crate::types::{Descendant, Editor, Element, Operation, Point};
+
+impl Editor {
+    /// Insert an inline element at the selection, deleting any selected
+    /// content, and put the caret after it.
+    ///
+    /// Returns whether the element was inserted, which it isn't if the
+    /// selection isn't in text.
+    pub(crate) fn insert_inline(&mut self, element: Element) -> bool {
+        let selection = match &self.selection {
+            Some(selection) => selection.clone(),
+            None => return false,
+        };
+        if selection.is_expanded() {
+            self.delete_range(&selection);
+        }
+        let point = match &self.selection {
+            Some(selection) => selection.start(),
+            None => return false,
+        };
+        if self.text_at(&point.path).is_none() {
+            return false;
+        }
+
+        self.apply(Operation::SplitNode {
+            path: point.path.clone(),
+            position: point.offset,
+            properties: self.properties_at(&point.path),
+        })
+        .expect("split at a point in text to apply");
+        let at = point.path.next().unwrap();
+        self.apply(Operation::InsertNode {
+            path: at.clone(),
+            node: Descendant::from(element).into(),
+        })
+        .expect("insert next to a text to apply");
+
+        self.select(Point {
+            path: at.next().unwrap(),
+            offset: 0,
+        });
+        true
+    }
+}
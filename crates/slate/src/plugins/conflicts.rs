@@ -0,0 +1,130 @@
+//! Merge conflicts, as elements holding both versions of the conflicting
+//! content so that they can be resolved inside the editor.
+//!
+//! A conflict has two children: the blocks of our version, then the blocks of
+//! theirs, each wrapped in an element of its own kind.
+
+use crate::transforms::ApplyError;
+use crate::types::{element_entries, Descendant, Editor, Element, Node, Operation, Path};
+
+/// The kind of conflicts.
+pub const KIND: &str = "conflict";
+/// The kind of the element holding our version of a conflict.
+pub const OURS: &str = "conflict-ours";
+/// The kind of the element holding their version of a conflict.
+pub const THEIRS: &str = "conflict-theirs";
+
+/// Which version of a conflict to keep when resolving it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Ours followed by theirs.
+    Both,
+}
+
+/// Create a conflict between two versions of some blocks.
+pub fn conflict(ours: Vec<Descendant>, theirs: Vec<Descendant>) -> Element {
+    let side = |kind, blocks: Vec<Descendant>| {
+        blocks
+            .into_iter()
+            .fold(Element::with_kind(kind), |e, block| e.add_child(block))
+    };
+
+    Element::with_kind(KIND)
+        .add_child(side(OURS, ours))
+        .add_child(side(THEIRS, theirs))
+}
+
+impl Editor {
+    /// Get the paths of the conflicts in the document, in document order.
+    pub fn conflicts(&self) -> Vec<Path> {
+        element_entries(&self.children, &Path::new(vec![]))
+            .into_iter()
+            .filter(|(e, _)| e.kind() == KIND)
+            .map(|(_, path)| path)
+            .collect()
+    }
+
+    /// Resolve the conflict at a path, replacing it with the blocks of the
+    /// versions `resolution` keeps. Rejecting one version is keeping the
+    /// other.
+    pub fn resolve_conflict(
+        &mut self,
+        path: &Path,
+        resolution: Resolution,
+    ) -> Result<(), ApplyError> {
+        let sides = match self.descendant_ref(path) {
+            Some(Descendant::Element(e)) if e.kind() == KIND => e.children_ref().to_vec(),
+            _ => return Err(ApplyError::InvalidNode(path.clone())),
+        };
+        let kept: Vec<Descendant> = sides
+            .into_iter()
+            .filter_map(|side| match side {
+                Descendant::Element(e) => match (e.kind(), resolution) {
+                    (OURS, Resolution::Ours | Resolution::Both)
+                    | (THEIRS, Resolution::Theirs | Resolution::Both) => Some(e.children()),
+                    _ => None,
+                },
+                Descendant::Text(_) => None,
+            })
+            .flatten()
+            .collect();
+
+        self.remove_node(path);
+        let mut at = path.clone();
+        for block in kept {
+            self.apply(Operation::InsertNode {
+                path: at.clone(),
+                node: Node::from(&block),
+            })?;
+            at = at.next().expect("a block path to have a next sibling");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Text;
+
+    use super::*;
+
+    fn block(text: &str) -> Descendant {
+        Element::new().add_child(Text::new(text)).into()
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(block("a"))
+            .add_child(conflict(vec![block("b")], vec![block("c"), block("d")]))
+            .add_child(block("e"))
+    }
+
+    #[test]
+    fn resolve_each_way() {
+        let path = Path::new(vec![1]);
+
+        let mut ours = editor();
+        assert_eq!(ours.conflicts(), vec![path.clone()]);
+        ours.resolve_conflict(&path, Resolution::Ours).unwrap();
+        assert_eq!(ours.children(), vec![block("a"), block("b"), block("e")]);
+        assert_eq!(ours.conflicts(), vec![]);
+
+        let mut theirs = editor();
+        theirs.resolve_conflict(&path, Resolution::Theirs).unwrap();
+        assert_eq!(
+            theirs.children(),
+            vec![block("a"), block("c"), block("d"), block("e")]
+        );
+
+        let mut both = editor();
+        both.resolve_conflict(&path, Resolution::Both).unwrap();
+        assert_eq!(both.num_children(), 5);
+
+        assert_eq!(
+            both.resolve_conflict(&Path::new(vec![0]), Resolution::Ours),
+            Err(ApplyError::InvalidNode(Path::new(vec![0])))
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Emoji shortcodes, like `:smile:`, expanded as they're typed into emoji
+//! text or into void elements holding the emoji, from a table the app
+//! supplies.
+//!
+//! A shortcode is expanded when its closing colon is typed, if it starts at
+//! a word boundary and is in the table. Nothing is expanded in code.
+
+use std::collections::HashMap;
+
+use crate::input_rules::InputRule;
+use crate::types::{Editor, Element, Operation, Point, Text};
+
+/// The kind of emoji elements.
+pub const EMOJI: &str = "emoji";
+/// The property of an emoji element holding its shortcode, without colons.
+pub const SHORTCODE: &str = "shortcode";
+/// The property of an emoji element holding the emoji itself.
+pub const TEXT: &str = "text";
+
+/// Create an emoji element.
+pub fn emoji(shortcode: &str, text: &str) -> Element {
+    Element::with_kind(EMOJI)
+        .add_property(SHORTCODE, shortcode)
+        .add_property(TEXT, text)
+        .add_child(Text::new(""))
+}
+
+/// An input rule expanding shortcodes from a table, for
+/// `Editor::with_input_rule`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Shortcodes {
+    table: HashMap<String, String>,
+    voids: bool,
+}
+
+impl Shortcodes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expand `:<shortcode>:` into `emoji`.
+    pub fn add(mut self, shortcode: &str, emoji: &str) -> Self {
+        self.table.insert(shortcode.to_string(), emoji.to_string());
+        self
+    }
+
+    /// Whether shortcodes become emoji elements rather than text.
+    pub fn voids(mut self, voids: bool) -> Self {
+        self.voids = voids;
+        self
+    }
+
+    /// Get the emoji of a shortcode, without colons.
+    pub fn get(&self, shortcode: &str) -> Option<&str> {
+        self.table.get(shortcode).map(String::as_str)
+    }
+
+    /// Find where a shortcode in the table that's closed at the end of some
+    /// text starts.
+    fn closed_at_end(&self, text: &str) -> Option<usize> {
+        let body = text.strip_suffix(':')?;
+        let start = body.rfind(':')?;
+        let shortcode = &body[start + 1..];
+        let word = |c: char| c.is_alphanumeric() || "_+-".contains(c);
+        if shortcode.is_empty() || !shortcode.chars().all(word) {
+            return None;
+        }
+        if body[..start].chars().last().is_some_and(word) {
+            return None;
+        }
+        self.get(shortcode).map(|_| start)
+    }
+}
+
+impl InputRule for Shortcodes {
+    fn apply(&self, editor: &mut Editor, at: &Point) {
+        if editor.in_code(&at.path) {
+            return;
+        }
+        let text = match editor.text_at(&at.path) {
+            Some(text) => &text.text()[..at.offset],
+            None => return,
+        };
+        let start = match self.closed_at_end(text) {
+            Some(start) => start,
+            None => return,
+        };
+        let shortcode = &text[start + 1..text.len() - 1];
+        let value = self.get(shortcode).unwrap_or_default().to_string();
+        let remove = Operation::RemoveText {
+            path: at.path.clone(),
+            offset: start,
+            text: text[start..].to_string(),
+        };
+        let shortcode = shortcode.to_string();
+
+        editor
+            .apply(remove)
+            .expect("removal of typed text to apply");
+        if self.voids {
+            editor.insert_inline(emoji(&shortcode, &value));
+        } else {
+            editor
+                .apply(Operation::InsertText {
+                    path: at.path.clone(),
+                    offset: start,
+                    text: value,
+                })
+                .expect("insertion where text was removed to apply");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Path, Range};
+
+    use super::*;
+
+    fn editor(shortcodes: Shortcodes) -> Editor {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .with_input_rule(shortcodes.add("smile", "😄").add("+1", "👍"));
+        let point = Point {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+        };
+        editor.select(Range::new(point.clone(), point));
+        editor
+    }
+
+    fn typed(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.insert_text(&c.to_string(), None);
+        }
+    }
+
+    #[test]
+    fn expand_shortcodes_into_text() {
+        let mut editor = editor(Shortcodes::new());
+        typed(&mut editor, "hi :smile: a:+1: :nope: :+1:!");
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("hi 😄 a:+1: :nope: 👍!"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn expand_shortcodes_into_voids() {
+        let mut editor = editor(Shortcodes::new().voids(true));
+        typed(&mut editor, "a :smile:b");
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("a "))
+                .add_child(emoji("smile", "😄"))
+                .add_child(Text::new("b"))
+                .into()]
+        );
+    }
+}
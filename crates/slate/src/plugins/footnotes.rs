@@ -0,0 +1,181 @@
+//! Footnotes, as inline references in the text linked by id to definition
+//! blocks at the end of the document.
+//!
+//! A reference is a void element: its only child is an empty text, and it's
+//! displayed as its number. References and definitions are numbered by the
+//! order the references appear in, which normalization keeps up to date as
+//! references are added, removed or moved. Definitions without a reference
+//! are left unnumbered rather than removed, so cutting and pasting a
+//! reference doesn't lose its footnote.
+
+use std::collections::HashMap;
+
+use crate::types::{
+    default_block, element_entries, Descendant, Editor, Element, Node, NodeProperties, Operation,
+    Path, Text,
+};
+
+/// The kind of footnote references.
+pub const REFERENCE: &str = "footnote-ref";
+/// The kind of footnote definitions.
+pub const DEFINITION: &str = "footnote";
+/// The property of a reference holding the id of its definition.
+pub const REFERENCE_ID: &str = "footnote";
+/// The property of a definition holding its id.
+pub const DEFINITION_ID: &str = "id";
+/// The property of references and definitions holding their number.
+pub const NUMBER: &str = "number";
+
+/// Create a reference to the footnote with an id.
+pub fn reference(id: &str) -> Element {
+    Element::with_kind(REFERENCE)
+        .add_property(REFERENCE_ID, id)
+        .add_child(Text::new(""))
+}
+
+/// Create an empty definition of the footnote with an id.
+pub fn definition(id: &str) -> Element {
+    Element::with_kind(DEFINITION).add_property(DEFINITION_ID, id)
+}
+
+impl Editor {
+    /// Insert a footnote at the selection, deleting any selected content: a
+    /// reference where the selection was, and a definition holding `content`
+    /// at the end of the document. Without content, the definition holds an
+    /// empty block.
+    ///
+    /// Returns the footnote's id, or `None` if the selection isn't in text.
+    pub fn insert_footnote(&mut self, content: Vec<Descendant>) -> Option<String> {
+        let ids: Vec<String> = element_entries(&self.children, &Path::new(vec![]))
+            .into_iter()
+            .filter_map(|(e, _)| e.property(DEFINITION_ID).or(e.property(REFERENCE_ID)))
+            .map(String::from)
+            .collect();
+        let id = self.new_id("fn", &ids);
+
+        if !self.insert_inline(reference(&id)) {
+            return None;
+        }
+
+        let content = if content.is_empty() {
+            vec![default_block().into()]
+        } else {
+            content
+        };
+        let definition = content
+            .into_iter()
+            .fold(definition(&id), |d, child| d.add_child(child));
+        self.apply(Operation::InsertNode {
+            path: Path::new(vec![self.children.len()]),
+            node: Node::Element(definition),
+        })
+        .expect("insert at the end of the document to apply");
+
+        self.normalize_footnotes();
+        Some(id)
+    }
+
+    /// Number footnote references by the order they appear in, and their
+    /// definitions to match. References to the same footnote share a number.
+    pub(crate) fn normalize_footnotes(&mut self) {
+        let mut numbers = HashMap::new();
+        let mut changes = vec![];
+
+        for (e, path) in element_entries(&self.children, &Path::new(vec![])) {
+            if e.kind() != REFERENCE {
+                continue;
+            }
+            if let Some(id) = e.property(REFERENCE_ID) {
+                let next = numbers.len() + 1;
+                let number = *numbers.entry(id.to_string()).or_insert(next);
+                changes.push((path, Some(number.to_string())));
+            }
+        }
+
+        for (e, path) in element_entries(&self.children, &Path::new(vec![])) {
+            if e.kind() == DEFINITION {
+                let number = e
+                    .property(DEFINITION_ID)
+                    .and_then(|id| numbers.get(id))
+                    .map(|n| n.to_string());
+                changes.push((path, number));
+            }
+        }
+
+        // Setting properties doesn't move any nodes, so the paths stay valid.
+        for (path, number) in changes {
+            self.set_node(&path, NodeProperties::new().property(NUMBER, number))
+                .expect("setting the number of a footnote to apply");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Point, Range};
+
+    use super::*;
+
+    fn caret(path: Vec<usize>, offset: usize) -> Range {
+        let point = Point {
+            path: path.into(),
+            offset,
+        };
+        Range::new(point.clone(), point)
+    }
+
+    fn number(editor: &Editor, path: Vec<usize>) -> Option<String> {
+        match editor.descendant_ref(&path.into()) {
+            Some(Descendant::Element(e)) => e.property(NUMBER).map(String::from),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn insert_footnote() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        editor.select(caret(vec![0, 0], 1));
+
+        assert_eq!(
+            editor.insert_footnote(vec![Element::new().add_child(Text::new("note")).into()]),
+            Some("fn-1".into())
+        );
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new()
+                    .add_child(Text::new("a"))
+                    .add_child(reference("fn-1").add_property(NUMBER, "1"))
+                    .add_child(Text::new("b"))
+                    .into(),
+                definition("fn-1")
+                    .add_property(NUMBER, "1")
+                    .add_child(Element::new().add_child(Text::new("note")))
+                    .into(),
+            ]
+        );
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 2], 0)));
+    }
+
+    #[test]
+    fn normalization_renumbers() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        editor.select(caret(vec![0, 0], 2));
+        editor.insert_footnote(vec![]);
+        editor.select(caret(vec![0, 0], 1));
+        editor.insert_footnote(vec![]);
+
+        // The second footnote's reference comes first.
+        assert_eq!(number(&editor, vec![0, 1]), Some("1".into()));
+        assert_eq!(number(&editor, vec![0, 3]), Some("2".into()));
+        assert_eq!(number(&editor, vec![1]), Some("2".into()));
+        assert_eq!(number(&editor, vec![2]), Some("1".into()));
+
+        // Removing a reference leaves its definition unnumbered.
+        editor.remove_node(&Path::new(vec![0, 1]));
+        editor.normalize();
+        assert_eq!(number(&editor, vec![0, 1]), Some("1".into()));
+        assert_eq!(number(&editor, vec![1]), Some("1".into()));
+        assert_eq!(number(&editor, vec![2]), None);
+    }
+}
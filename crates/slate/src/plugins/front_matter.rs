@@ -0,0 +1,244 @@
+//! Front matter: the properties of a document, like its title or author,
+//! kept as the key-value properties of a void element at the very start of
+//! the document.
+//!
+//! Normalization keeps the element first and merges any others into it, and
+//! deleting content never removes it.
+
+use crate::transforms::ApplyError;
+use crate::types::{Descendant, Editor, Element, Node, NodeProperties, Operation, Path, Text};
+
+/// The kind of the front matter element.
+pub const KIND: &str = "front-matter";
+
+/// Create front matter holding some properties.
+pub fn front_matter<'a>(properties: impl IntoIterator<Item = (&'a str, &'a str)>) -> Element {
+    properties
+        .into_iter()
+        .fold(Element::with_kind(KIND), |e, (key, value)| {
+            e.add_property(key, value)
+        })
+        .add_child(Text::new(""))
+}
+
+/// Write front matter as a YAML front matter block, like the ones Markdown
+/// documents start with.
+pub fn to_yaml(element: &Element) -> String {
+    let mut out = String::from("---\n");
+
+    for (key, value) in element.properties() {
+        out.push_str(key);
+        out.push_str(": ");
+        // JSON strings are YAML strings, and quoting every value keeps ones
+        // like `yes` or `1.0` strings.
+        out.push_str(&serde_json::to_string(value).expect("strings to serialize"));
+        out.push('\n');
+    }
+
+    out.push_str("---\n");
+    out
+}
+
+/// Read the YAML front matter block at the start of some text, if it has one,
+/// returning the front matter and the text after the block.
+///
+/// Only flat mappings of keys to strings, numbers and other scalars are read,
+/// and lines that aren't `key: value` pairs are skipped.
+pub fn from_yaml(text: &str) -> Option<(Element, &str)> {
+    let body = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))?;
+
+    let mut element = Element::with_kind(KIND);
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line == "---" || line == "..." {
+            return Some((element.add_child(Text::new("")), &body[offset..]));
+        }
+
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) if !key.trim().is_empty() && !key.starts_with('#') => {
+                (key.trim(), value.trim())
+            }
+            _ => continue,
+        };
+        let value = match value.chars().next() {
+            Some('"') => serde_json::from_str(value).unwrap_or_else(|_| value.to_string()),
+            Some('\'') if value.len() > 1 && value.ends_with('\'') => {
+                value[1..value.len() - 1].replace("''", "'")
+            }
+            _ => value.to_string(),
+        };
+        element = element.add_property(key, value);
+    }
+
+    // The block was never closed.
+    None
+}
+
+impl Editor {
+    /// Get the document's front matter.
+    pub fn front_matter(&self) -> Option<&Element> {
+        match self.children.get(0) {
+            Some(Descendant::Element(e)) if e.kind() == KIND => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Set a property of the document's front matter, or remove it with
+    /// `None`, adding front matter first if there isn't any.
+    pub fn set_front_matter(&mut self, key: &str, value: Option<&str>) -> Result<(), ApplyError> {
+        if self.front_matter().is_none() {
+            if value.is_none() {
+                return Ok(());
+            }
+            self.apply(Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(front_matter(vec![])),
+            })?;
+        }
+
+        self.set_node(
+            &Path::new(vec![0]),
+            NodeProperties::new().property(key, value),
+        )
+    }
+
+    /// Whether a point is in the front matter, where deletions can't start.
+    pub(crate) fn in_front_matter(&self, path: &Path) -> bool {
+        self.front_matter().is_some() && path.as_slice().first() == Some(&0)
+    }
+
+    /// Keep the document's front matter first: front matter elsewhere at the
+    /// top level is merged into the first one, which is moved to the start of
+    /// the document. Properties already in the first one take priority.
+    pub(crate) fn normalize_front_matter(&mut self) {
+        let found: Vec<usize> = self
+            .children
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| matches!(c, Descendant::Element(e) if e.kind() == KIND))
+            .map(|(i, _)| i)
+            .collect();
+        let (first, rest) = match found.split_first() {
+            Some(found) => found,
+            None => return,
+        };
+
+        if *first != 0 {
+            self.apply(Operation::MoveNode {
+                path: Path::new(vec![*first]),
+                new_path: Path::new(vec![0]),
+            })
+            .expect("move to the start of the document to apply");
+        }
+
+        // Moving the first one forward doesn't change the indices after it.
+        for i in rest.iter().rev() {
+            let path = Path::new(vec![*i]);
+            let properties = match self.descendant_ref(&path) {
+                Some(Descendant::Element(e)) => e.properties().clone(),
+                _ => continue,
+            };
+            let kept = self.front_matter().unwrap().properties().clone();
+            let mut merged = NodeProperties::new();
+            for (key, value) in properties {
+                if !kept.contains_key(&key) {
+                    merged = merged.property(key, Some(value));
+                }
+            }
+            self.set_node(&Path::new(vec![0]), merged)
+                .expect("setting front matter properties to apply");
+            self.remove_node(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Point, Range};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let element = front_matter(vec![("title", "A: \"b\""), ("draft", "yes")]);
+        let yaml = to_yaml(&element);
+        assert_eq!(yaml, "---\ndraft: \"yes\"\ntitle: \"A: \\\"b\\\"\"\n---\n");
+        assert_eq!(
+            from_yaml(&format!("{}# Heading\n", yaml)),
+            Some((element, "# Heading\n"))
+        );
+
+        assert_eq!(
+            from_yaml("---\ntitle: plain\nquote: 'it''s'\n# comment\n...\nrest"),
+            Some((
+                front_matter(vec![("title", "plain"), ("quote", "it's")]),
+                "rest"
+            ))
+        );
+        assert_eq!(from_yaml("---\ntitle: x\n"), None);
+        assert_eq!(from_yaml("# title"), None);
+    }
+
+    #[test]
+    fn set_front_matter_adds_it_first() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("a")));
+
+        editor.set_front_matter("title", Some("T")).unwrap();
+        editor.set_front_matter("author", Some("ann")).unwrap();
+        editor.set_front_matter("title", None).unwrap();
+        assert_eq!(
+            editor.front_matter(),
+            Some(&front_matter(vec![("author", "ann")]))
+        );
+        assert_eq!(editor.num_children(), 2);
+    }
+
+    #[test]
+    fn normalization_pins_front_matter() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("a")))
+            .add_child(front_matter(vec![("title", "T")]))
+            .add_child(front_matter(vec![("title", "U"), ("author", "ann")]));
+
+        editor.normalize();
+        assert_eq!(
+            editor.children(),
+            vec![
+                front_matter(vec![("title", "T"), ("author", "ann")]).into(),
+                Element::new().add_child(Text::new("a")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn deletion_keeps_front_matter() {
+        let mut editor = Editor::new()
+            .add_child(front_matter(vec![("title", "T")]))
+            .add_child(Element::new().add_child(Text::new("ab")));
+
+        editor.select(point(vec![1, 0], 0));
+        editor.delete_backward();
+        assert_eq!(editor.num_children(), 2);
+
+        editor.select(Range::new(point(vec![0, 0], 0), point(vec![1, 0], 1)));
+        editor.delete_backward();
+        assert_eq!(
+            editor.children(),
+            vec![
+                front_matter(vec![("title", "T")]).into(),
+                Element::new().add_child(Text::new("b")).into(),
+            ]
+        );
+    }
+}
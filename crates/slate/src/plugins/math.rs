@@ -0,0 +1,119 @@
+//! Math, as void elements holding TeX source, either inline in the text or
+//! as blocks of their own. Rendering the source is left to the frontend.
+
+use crate::transforms::ApplyError;
+use crate::types::{Descendant, Editor, Element, Node, NodeProperties, Operation, Path, Text};
+
+/// The kind of inline math.
+pub const INLINE: &str = "math";
+/// The kind of math blocks, displayed on their own.
+pub const BLOCK: &str = "math-block";
+/// The property holding the TeX source of math.
+pub const SOURCE: &str = "tex";
+
+/// Create inline math from its TeX source.
+pub fn inline(tex: &str) -> Element {
+    Element::with_kind(INLINE)
+        .add_property(SOURCE, tex)
+        .add_child(Text::new(""))
+}
+
+/// Create a math block from its TeX source.
+pub fn block(tex: &str) -> Element {
+    Element::with_kind(BLOCK)
+        .add_property(SOURCE, tex)
+        .add_child(Text::new(""))
+}
+
+/// Whether an element is math of either kind.
+pub fn is_math(element: &Element) -> bool {
+    element.kind() == INLINE || element.kind() == BLOCK
+}
+
+impl Editor {
+    /// Insert inline math at the selection, deleting any selected content,
+    /// and put the caret after it.
+    ///
+    /// Returns whether it was inserted, which it isn't if the selection isn't
+    /// in text.
+    pub fn insert_math(&mut self, tex: &str) -> bool {
+        self.insert_inline(inline(tex))
+    }
+
+    /// Insert a math block after the block the selection starts in.
+    ///
+    /// Returns whether it was inserted, which it isn't without a selection.
+    pub fn insert_math_block(&mut self, tex: &str) -> bool {
+        let current = match &self.selection {
+            Some(selection) => self.block_path(&selection.start().path),
+            None => None,
+        };
+        let at = match current.and_then(|b| b.next()) {
+            Some(at) => at,
+            None => return false,
+        };
+
+        self.apply(Operation::InsertNode {
+            path: at,
+            node: Node::Element(block(tex)),
+        })
+        .is_ok()
+    }
+
+    /// Replace the TeX source of the math at a path.
+    pub fn set_math_source(&mut self, path: &Path, tex: &str) -> Result<(), ApplyError> {
+        match self.descendant_ref(path) {
+            Some(Descendant::Element(e)) if is_math(e) => {}
+            _ => return Err(ApplyError::InvalidNode(path.clone())),
+        }
+        self.set_node(path, NodeProperties::new().property(SOURCE, Some(tex)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Point, Range};
+
+    use super::*;
+
+    fn caret(path: Vec<usize>, offset: usize) -> Range {
+        let point = Point {
+            path: path.into(),
+            offset,
+        };
+        Range::new(point.clone(), point)
+    }
+
+    #[test]
+    fn insert_inline_and_block_math() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab")));
+        editor.select(caret(vec![0, 0], 1));
+
+        assert!(editor.insert_math("x^2"));
+        assert!(editor.insert_math_block("\\int_0^1 x"));
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new()
+                    .add_child(Text::new("a"))
+                    .add_child(inline("x^2"))
+                    .add_child(Text::new("b"))
+                    .into(),
+                block("\\int_0^1 x").into(),
+            ]
+        );
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 2], 0)));
+    }
+
+    #[test]
+    fn set_math_source() {
+        let mut editor = Editor::new().add_child(block("a"));
+
+        editor.set_math_source(&Path::new(vec![0]), "b").unwrap();
+        assert_eq!(editor.children(), vec![block("b").into()]);
+        assert_eq!(
+            editor.set_math_source(&Path::new(vec![0, 0]), "c"),
+            Err(ApplyError::InvalidNode(Path::new(vec![0, 0])))
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Redaction, replacing content with inline elements that keep none of it,
+//! for sharing documents with parts withheld.
+//!
+//! Each text in a redacted range is redacted on its own, so the blocks and
+//! inlines around the content keep their structure. A redaction is either
+//! void, showing only where content was, or holds the content masked, showing
+//! how long it was. Exports that mustn't show either drop the redactions
+//! altogether with `Editor::without_redactions`.
+
+use crate::types::{
+    element_entries, text_entries, Descendant, Editor, Element, Operation, Path, Range, Text,
+};
+
+/// The kind of redactions.
+pub const REDACTION: &str = "redaction";
+/// The property of a redaction holding its style, `void` or `mask`.
+pub const STYLE: &str = "style";
+
+/// How redacted content is replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionStyle {
+    /// By a void element.
+    Void,
+    /// By an element holding the content with each character replaced by a
+    /// mask character, like `█`.
+    Mask(char),
+}
+
+/// Create a redaction of some text.
+pub fn redaction(text: &str, style: RedactionStyle) -> Element {
+    match style {
+        RedactionStyle::Void => Element::with_kind(REDACTION)
+            .add_property(STYLE, "void")
+            .add_child(Text::new("")),
+        RedactionStyle::Mask(mask) => {
+            let masked: String = text.chars().map(|_| mask).collect();
+            Element::with_kind(REDACTION)
+                .add_property(STYLE, "mask")
+                .add_child(Text::new(&masked))
+        }
+    }
+}
+
+/// Whether an element is a redaction.
+pub fn is_redaction(element: &Element) -> bool {
+    element.kind() == REDACTION
+}
+
+impl Editor {
+    /// Redact the content of some ranges, which may overlap, replacing the
+    /// part of each text they cover with a redaction. Content that's already
+    /// redacted is left as it is.
+    ///
+    /// Returns how many redactions were inserted.
+    pub fn redact(&mut self, ranges: &[Range], style: RedactionStyle) -> usize {
+        let root = Path::new(vec![]);
+        let mut slices = vec![];
+        for range in ranges {
+            let (start, end) = (range.start(), range.end());
+            for (text, path) in text_entries(&self.children, &root) {
+                if path.is_before(&start.path) || path.is_after(&end.path) {
+                    continue;
+                }
+                let from = if path == start.path { start.offset } else { 0 };
+                let to = if path == end.path {
+                    end.offset
+                } else {
+                    text.text().len()
+                };
+                if from < to {
+                    slices.push((path, from, to));
+                }
+            }
+        }
+
+        // Merge overlapping slices, then redact them in reverse document
+        // order, so that splitting a text only moves the ones already done.
+        slices.sort_by(|a, b| (a.0.as_slice(), a.1).cmp(&(b.0.as_slice(), b.1)));
+        let mut merged: Vec<(Path, usize, usize)> = vec![];
+        for (path, from, to) in slices {
+            match merged.last_mut() {
+                Some(last) if last.0 == path && from <= last.2 => last.2 = last.2.max(to),
+                _ => merged.push((path, from, to)),
+            }
+        }
+
+        let mut count = 0;
+        for (path, from, to) in merged.into_iter().rev() {
+            let redacted = match self.parent_ref(&path) {
+                Some(Descendant::Element(parent)) => is_redaction(parent),
+                _ => false,
+            };
+            if redacted {
+                continue;
+            }
+
+            let text = self.text_at(&path).unwrap().text()[from..to].to_string();
+            self.remove_text(&path, from, to);
+            self.apply(Operation::SplitNode {
+                path: path.clone(),
+                position: from,
+                properties: self.properties_at(&path),
+            })
+            .expect("split at a point in text to apply");
+            self.apply(Operation::InsertNode {
+                path: path.next().unwrap(),
+                node: Descendant::from(redaction(&text, style)).into(),
+            })
+            .expect("insert next to a text to apply");
+            count += 1;
+        }
+        count
+    }
+
+    /// Get a copy of the document with its redactions removed, leaving no
+    /// trace of the redacted content, for exports. The editor itself is left
+    /// as it is.
+    pub fn without_redactions(&self) -> Editor {
+        let mut copy = self.detached();
+        let root = Path::new(vec![]);
+        let paths: Vec<_> = element_entries(&copy.children, &root)
+            .into_iter()
+            .filter(|(element, _)| is_redaction(element))
+            .map(|(_, path)| path)
+            .collect();
+
+        // Redactions don't nest, so removing them in reverse keeps the paths
+        // of the rest valid.
+        for path in paths.iter().rev() {
+            copy.remove_node(path);
+        }
+        copy.normalize();
+        copy.flush();
+        copy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Point;
+
+    use super::*;
+
+    fn range(start: (Vec<usize>, usize), end: (Vec<usize>, usize)) -> Range {
+        let point = |(path, offset): (Vec<usize>, usize)| Point {
+            path: path.into(),
+            offset,
+        };
+        Range::new(point(start), point(end))
+    }
+
+    fn doc() -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("secret code")))
+            .add_child(Element::new().add_child(Text::new("more secrets")))
+    }
+
+    #[test]
+    fn redact_each_text_in_place() {
+        let mut editor = doc();
+        let count = editor.redact(
+            &[
+                range((vec![0, 0], 7), (vec![1, 0], 4)),
+                range((vec![0, 0], 0), (vec![0, 0], 6)),
+            ],
+            RedactionStyle::Mask('█'),
+        );
+
+        assert_eq!(count, 3);
+        let mask = |text| redaction(text, RedactionStyle::Mask('█'));
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(mask("secret"))
+                    .add_child(Text::new(" "))
+                    .add_child(mask("code"))
+                    .add_child(Text::new(""))
+                    .into(),
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(mask("more"))
+                    .add_child(Text::new(" secrets"))
+                    .into(),
+            ]
+        );
+
+        // Redacting again leaves the masks as they are.
+        let before = editor.children();
+        assert_eq!(
+            editor.redact(
+                &[range((vec![0, 1, 0], 0), (vec![0, 1, 0], 3))],
+                RedactionStyle::Void
+            ),
+            0
+        );
+        assert_eq!(editor.children(), before);
+    }
+
+    #[test]
+    fn export_without_redactions() {
+        let mut editor = doc();
+        editor.redact(
+            &[range((vec![0, 0], 0), (vec![0, 0], 7))],
+            RedactionStyle::Void,
+        );
+        let redacted = editor.children();
+
+        let export = editor.without_redactions();
+        assert_eq!(
+            export.children(),
+            vec![
+                Element::new().add_child(Text::new("code")).into(),
+                Element::new().add_child(Text::new("more secrets")).into(),
+            ]
+        );
+        assert_eq!(editor.children(), redacted);
+    }
+}
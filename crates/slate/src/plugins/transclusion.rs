@@ -0,0 +1,338 @@
+//! Transclusion, embedding content from another document that stays linked
+//! to where it came from.
+//!
+//! A transclusion is a block holding a copy of the content it references:
+//! the element with a key in another document, or a range of that element's
+//! text. The copy is only updated when the transclusion is refreshed from
+//! the source document, and records the hash of the content it was copied
+//! from, so hosts can tell which transclusions are out of date. Detaching a
+//! transclusion keeps the copy as ordinary content.
+
+use std::error::Error;
+use std::fmt;
+use std::ops;
+
+use crate::anchors::ANCHOR_PROPERTY;
+use crate::transforms::ApplyError;
+use crate::types::{
+    default_block, element_entries, ContentHash, Descendant, Editor, Element, Node, NodeProperties,
+    Operation, Path, Range,
+};
+
+/// The kind of transclusions.
+pub const TRANSCLUSION: &str = "transclusion";
+/// The property of a transclusion holding the id of the source document.
+pub const DOCUMENT: &str = "document";
+/// The property of a transclusion holding the key of the source element:
+/// its `id`, or its anchor if it's a heading.
+pub const KEY: &str = "key";
+/// The property of a transclusion holding the range of the source element's
+/// text it embeds, as byte offsets like `4-20`. Without it, the whole
+/// element is embedded.
+pub const RANGE: &str = "range";
+/// The property of a transclusion holding the hash of the content it was
+/// last refreshed from.
+pub const SOURCE_HASH: &str = "source-hash";
+
+/// Errors returned by the transforms of transclusions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransclusionError {
+    /// There's no transclusion at a path.
+    NotTransclusion(Path),
+    /// The source document has no element with the key, or its text is
+    /// shorter than the range.
+    Unresolved(Source),
+    /// Replacing the embedded content was rejected, like for exceeding the
+    /// editor's limits.
+    Apply(ApplyError),
+}
+
+impl fmt::Display for TransclusionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransclusionError::NotTransclusion(p) => write!(f, "no transclusion at {:?}", p),
+            TransclusionError::Unresolved(source) => write!(
+                f,
+                "{:?} has no content for key {:?}",
+                source.document, source.key
+            ),
+            TransclusionError::Apply(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for TransclusionError {}
+
+impl From<ApplyError> for TransclusionError {
+    fn from(e: ApplyError) -> Self {
+        TransclusionError::Apply(e)
+    }
+}
+
+/// What a transclusion references.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Source {
+    /// The id of the source document, as the host names documents.
+    pub document: String,
+    /// The key of the source element.
+    pub key: String,
+    /// The range of the source element's text, or `None` for all of it.
+    pub range: Option<ops::Range<usize>>,
+}
+
+impl Source {
+    pub fn new(document: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            document: document.into(),
+            key: key.into(),
+            range: None,
+        }
+    }
+
+    pub fn range(mut self, range: ops::Range<usize>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Get what a transclusion references, or `None` if the element isn't
+    /// one.
+    pub fn of(element: &Element) -> Option<Self> {
+        if element.kind() != TRANSCLUSION {
+            return None;
+        }
+        let range = match element.property(RANGE) {
+            Some(range) => {
+                let (start, end) = range.split_once('-')?;
+                Some(start.parse().ok()?..end.parse().ok()?)
+            }
+            None => None,
+        };
+
+        Some(Self {
+            document: element.property(DOCUMENT)?.to_string(),
+            key: element.property(KEY)?.to_string(),
+            range,
+        })
+    }
+
+    /// Get the content referenced in the source document: the element with
+    /// the key, clipped to the range if there is one.
+    pub fn resolve(&self, source: &Editor) -> Option<Vec<Descendant>> {
+        let element = element_entries(&source.children, &Path::new(vec![]))
+            .into_iter()
+            .map(|(e, _)| e)
+            .find(|e| {
+                e.kind() != TRANSCLUSION
+                    && (e.property("id") == Some(&self.key)
+                        || e.property(ANCHOR_PROPERTY) == Some(&self.key))
+            })?;
+        let range = match &self.range {
+            Some(range) if range.start <= range.end => range,
+            Some(_) => return None,
+            None => return Some(vec![element.clone().into()]),
+        };
+
+        // Clip the element on its own, so the fragment doesn't bring along
+        // the elements around it.
+        let mut scratch = Editor::new();
+        scratch.replace_children(vec![Descendant::from(element.clone())]);
+        let block = Path::new(vec![0]);
+        let start = scratch.point_at_offset(&block, range.start)?;
+        let end = scratch.point_at_offset(&block, range.end)?;
+        Some(scratch.fragment(Range::new(start, end)))
+    }
+
+    /// Hash the content referenced in the source document, to compare with
+    /// the hash a transclusion was refreshed from.
+    pub fn hash(&self, source: &Editor) -> Option<ContentHash> {
+        let content = self.resolve(source)?;
+        Some(
+            content
+                .into_iter()
+                .fold(Element::new(), |e, child| e.add_child(child))
+                .content_hash(),
+        )
+    }
+}
+
+/// Create a transclusion of a source, holding an empty block until it's
+/// refreshed.
+pub fn transclusion(source: &Source) -> Element {
+    let element = Element::with_kind(TRANSCLUSION)
+        .add_property(DOCUMENT, &source.document)
+        .add_property(KEY, &source.key);
+    let element = match &source.range {
+        Some(range) => element.add_property(RANGE, format!("{}-{}", range.start, range.end)),
+        None => element,
+    };
+    element.add_child(default_block())
+}
+
+impl Editor {
+    /// Get the transclusions of the document with what they reference, in
+    /// document order.
+    pub fn transclusions(&self) -> Vec<(Path, Source)> {
+        element_entries(&self.children, &Path::new(vec![]))
+            .into_iter()
+            .filter_map(|(e, path)| Some((path, Source::of(e)?)))
+            .collect()
+    }
+
+    /// Get the transclusions of a source document whose content changed
+    /// since they were refreshed, including those that were never refreshed
+    /// and those whose content is gone from the source.
+    pub fn stale_transclusions(&self, document: &str, source: &Editor) -> Vec<Path> {
+        element_entries(&self.children, &Path::new(vec![]))
+            .into_iter()
+            .filter_map(|(e, path)| {
+                let reference = Source::of(e).filter(|s| s.document == document)?;
+                let hash = reference.hash(source).map(|h| h.to_string());
+                (e.property(SOURCE_HASH) != hash.as_deref()).then_some(path)
+            })
+            .collect()
+    }
+
+    /// Replace the content of the transclusion at a path with the content it
+    /// references in `source`, and record its hash.
+    ///
+    /// Returns whether the content changed. If the source no longer has the
+    /// content, the transclusion is left as it is.
+    pub fn refresh_transclusion(
+        &mut self,
+        at: &Path,
+        source: &Editor,
+    ) -> Result<bool, TransclusionError> {
+        let (reference, current) = match self.descendant_ref(at) {
+            Some(Descendant::Element(e)) => match Source::of(e) {
+                Some(reference) => (reference, e.property(SOURCE_HASH).map(String::from)),
+                None => return Err(TransclusionError::NotTransclusion(at.clone())),
+            },
+            _ => return Err(TransclusionError::NotTransclusion(at.clone())),
+        };
+        let unresolved = || TransclusionError::Unresolved(reference.clone());
+        let content = reference.resolve(source).ok_or_else(unresolved)?;
+        let hash = reference.hash(source).ok_or_else(unresolved)?.to_string();
+        if current.as_deref() == Some(hash.as_str()) {
+            return Ok(false);
+        }
+        self.check_fragment_limits(&content, &at.concat(0))?;
+
+        let len = self.children_at(at).map_or(0, |c| c.len());
+        for (i, node) in content.into_iter().enumerate() {
+            self.apply(Operation::InsertNode {
+                path: at.concat(len + i),
+                node: Node::from(node),
+            })?;
+        }
+        for _ in 0..len {
+            self.remove_node(&at.concat(0));
+        }
+        self.set_node(at, NodeProperties::new().property(SOURCE_HASH, Some(hash)))?;
+        Ok(true)
+    }
+
+    /// Turn the transclusion at a path into the content it holds, which is
+    /// then no longer linked to its source.
+    pub fn detach_transclusion(&mut self, at: &Path) -> Result<(), TransclusionError> {
+        match self.descendant_ref(at) {
+            Some(Descendant::Element(e)) if e.kind() == TRANSCLUSION => {
+                self.unwrap_node(at);
+                Ok(())
+            }
+            _ => Err(TransclusionError::NotTransclusion(at.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Point, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn source() -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("intro")))
+            .add_child(
+                Element::new()
+                    .add_property("id", "quote")
+                    .add_child(Text::new("to be or not to be")),
+            )
+    }
+
+    fn editor(reference: &Source) -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("as they said:")))
+            .add_child(transclusion(reference))
+    }
+
+    fn texts(editor: &Editor, at: &Path) -> Vec<String> {
+        editor
+            .tokens(at.clone())
+            .map(|token| token.text.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn refresh_from_source() {
+        let reference = Source::new("hamlet", "quote").range(9..18);
+        let mut editor = editor(&reference);
+        let at = Path::new(vec![1]);
+        assert_eq!(editor.transclusions(), vec![(at.clone(), reference)]);
+        assert_eq!(
+            editor.stale_transclusions("hamlet", &source()),
+            vec![at.clone()]
+        );
+
+        assert_eq!(editor.refresh_transclusion(&at, &source()), Ok(true));
+        assert_eq!(texts(&editor, &at), vec!["not to be"]);
+        assert!(editor.stale_transclusions("hamlet", &source()).is_empty());
+        assert_eq!(editor.refresh_transclusion(&at, &source()), Ok(false));
+
+        // Editing outside the range doesn't change the transclusion.
+        let mut changed = source();
+        changed.insert_text("!", Some(point(vec![1, 0], 18).into()));
+        assert!(editor.stale_transclusions("hamlet", &changed).is_empty());
+        changed.insert_text("2B ", Some(point(vec![1, 0], 0).into()));
+        assert_eq!(editor.stale_transclusions("hamlet", &changed), vec![at]);
+    }
+
+    #[test]
+    fn refresh_whole_element_and_detach() {
+        let mut editor = editor(&Source::new("hamlet", "quote"));
+        let at = Path::new(vec![1]);
+        editor.refresh_transclusion(&at, &source()).unwrap();
+        assert_eq!(texts(&editor, &at), vec!["to be or not to be"]);
+
+        editor.detach_transclusion(&at).unwrap();
+        assert!(editor.transclusions().is_empty());
+        assert_eq!(
+            editor.block_text(&at).as_deref(),
+            Some("to be or not to be")
+        );
+        assert_eq!(
+            editor.detach_transclusion(&at),
+            Err(TransclusionError::NotTransclusion(at))
+        );
+    }
+
+    #[test]
+    fn unresolved_sources_are_kept() {
+        let reference = Source::new("hamlet", "missing");
+        let mut editor = editor(&reference);
+        let at = Path::new(vec![1]);
+
+        assert_eq!(
+            editor.refresh_transclusion(&at, &source()),
+            Err(TransclusionError::Unresolved(reference))
+        );
+        assert_eq!(texts(&editor, &at), vec![""]);
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::types::{Editor, Operation, Path};
+
+/// What a policy decides about an operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    /// Apply the operation as it is.
+    Allow,
+    /// Reject the operation, and the rest of the batch with it.
+    Deny,
+    /// Apply these operations instead, like the operation with its content
+    /// marked as a suggestion. They're authorized in turn, and must be
+    /// allowed as they are, and later operations in the batch are rebased
+    /// onto them.
+    Transform(Vec<Operation>),
+}
+
+/// Decides which operations users may make, for a document edited by users
+/// with different permissions, like commenters who may only add
+/// suggestions. Sequencers consult it for each operation they're submitted.
+pub trait Policy: Send + Sync {
+    /// Decide on an operation `user` made, rebased onto `document`, which it
+    /// would be applied to next. `paths` are the paths of the nodes it
+    /// changes.
+    fn authorize(&self, document: &Editor, user: &str, op: &Operation, paths: &[Path]) -> Decision;
+}
+
+impl<P: Policy + ?Sized> Policy for Arc<P> {
+    fn authorize(&self, document: &Editor, user: &str, op: &Operation, paths: &[Path]) -> Decision {
+        (**self).authorize(document, user, op, paths)
+    }
+}
+
+/// The policy a sequencer was configured with.
+#[derive(Clone)]
+pub(crate) struct PolicyHandle(pub(crate) Arc<dyn Policy>);
+
+impl fmt::Debug for PolicyHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Policy")
+    }
+}
+
+/// Get the paths of the nodes an operation changes: the node it targets, and
+/// the sibling a merge merges into or the path a move moves to.
+pub(crate) fn target_paths(op: &Operation) -> Vec<Path> {
+    match op {
+        Operation::MergeNode { path, .. } => path
+            .previous()
+            .into_iter()
+            .chain(Some(path.clone()))
+            .collect(),
+        Operation::MoveNode { path, new_path } => vec![path.clone(), new_path.clone()],
+        Operation::InsertNode { path, .. }
+        | Operation::InsertText { path, .. }
+        | Operation::RemoveNode { path, .. }
+        | Operation::RemoveText { path, .. }
+        | Operation::SetNode { path, .. }
+        | Operation::SplitNode { path, .. } => vec![path.clone()],
+        Operation::SetSelection { .. } => vec![],
+    }
+}
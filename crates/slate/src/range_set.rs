@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+
+use crate::types::{Decoration, Operation, Path, Point, Range, RangeAffinity};
+
+/// A collection of tagged ranges, like decorations, comments, search
+/// highlights or collaborators' selections, kept in document order.
+///
+/// The ranges are moved through operations together, and can be queried for
+/// the ones in part of the document. Ranges are stored as given, so backward
+/// ones stay backward, but are ordered by their start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeSet<T> {
+    entries: Vec<(Range, T)>,
+    affinity: RangeAffinity,
+}
+
+impl<T> Default for RangeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> RangeSet<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![],
+            affinity: RangeAffinity::Inward,
+        }
+    }
+
+    /// Set how the ranges move when content is inserted at their edges.
+    /// They're `Inward` by default, so that text typed right after a
+    /// highlight isn't highlighted.
+    pub fn affinity(mut self, affinity: RangeAffinity) -> Self {
+        self.affinity = affinity;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the ranges and their tags, in order of their start.
+    pub fn iter(&self) -> impl Iterator<Item = (&Range, &T)> {
+        self.entries.iter().map(|(range, tag)| (range, tag))
+    }
+
+    /// Add a range with a tag, after the ranges starting at the same point.
+    pub fn insert(&mut self, range: Range, tag: T) {
+        let start = range.start();
+        let i = self
+            .entries
+            .partition_point(|(r, _)| !r.start().is_after(&start));
+        self.entries.insert(i, (range, tag));
+    }
+
+    /// Keep only the ranges `f` is true for.
+    pub fn retain(&mut self, mut f: impl FnMut(&Range, &T) -> bool) {
+        self.entries.retain(|(range, tag)| f(range, tag));
+    }
+
+    /// Move every range through an operation that was applied to the
+    /// document, dropping the ones whose content it removed.
+    pub fn transform(&mut self, op: &Operation) {
+        let affinity = self.affinity;
+        let entries = std::mem::take(&mut self.entries);
+        self.entries = entries
+            .into_iter()
+            .filter_map(|(range, tag)| Some((Range::transform(&range, op, affinity)?, tag)))
+            .collect();
+
+        // Moving nodes can reorder the ranges.
+        if matches!(op, Operation::MoveNode { .. }) {
+            self.entries.sort_by_key(|(range, _)| range.start());
+        }
+    }
+
+    /// Move every range through operations applied in order.
+    pub fn transform_all(&mut self, ops: &[Operation]) {
+        for op in ops {
+            self.transform(op);
+        }
+    }
+
+    /// Iterate over the ranges with some of their content, or an edge, in
+    /// another range, in order of their start.
+    pub fn intersecting<'a>(&'a self, range: &Range) -> impl Iterator<Item = (&'a Range, &'a T)> {
+        let (start, end) = (range.start(), range.end());
+        self.before(move |point| !point.is_after(&end))
+            .filter(move |(r, _)| !r.end().is_before(&start))
+    }
+
+    /// Iterate over the ranges with some of their content, or an edge, in the
+    /// node at a path, like the ones a block needs to render, in order of
+    /// their start.
+    pub fn in_node<'a>(&'a self, path: &Path) -> impl Iterator<Item = (&'a Range, &'a T)> {
+        let path = path.clone();
+        let end = path.clone();
+        self.before(move |point| !point.path.is_after(&end))
+            .filter(move |(r, _)| !r.end().path.is_before(&path))
+    }
+
+    /// Iterate over the ranges starting at points `f` is true for, which must
+    /// be a prefix of them.
+    fn before(&self, f: impl Fn(&Point) -> bool) -> impl Iterator<Item = (&Range, &T)> {
+        let count = self.entries.partition_point(|(r, _)| f(&r.start()));
+        self.entries[..count]
+            .iter()
+            .map(|(range, tag)| (range, tag))
+    }
+}
+
+impl<T: PartialEq> RangeSet<T> {
+    /// Merge the ranges that overlap or touch another with an equal tag into
+    /// one forward range covering both.
+    pub fn merge(&mut self) {
+        let mut merged: Vec<(Range, T)> = Vec::with_capacity(self.entries.len());
+
+        for (range, tag) in std::mem::take(&mut self.entries) {
+            let (start, end) = (range.start(), range.end());
+            // Ranges with equal tags are disjoint once merged, so only the
+            // last one can overlap.
+            match merged.iter_mut().rev().find(|(_, t)| *t == tag) {
+                Some((r, _)) if !r.end().is_before(&start) => {
+                    if end.is_after(&r.end()) {
+                        *r = Range::new(r.start(), end);
+                    }
+                }
+                _ => merged.push((range, tag)),
+            }
+        }
+
+        self.entries = merged;
+    }
+}
+
+impl<T: ToString> RangeSet<T> {
+    /// Get the decorations of the text at a path, for `Text::decorations`,
+    /// with the tags of each range intersecting it as its decoration.
+    pub fn decorations(&self, path: &Path) -> Vec<Decoration> {
+        self.in_node(path)
+            .map(|(range, tag)| {
+                // Ranges running on past the text cover it up to its edge.
+                let edge = |point: Point, offset| {
+                    if point.path == *path {
+                        point
+                    } else {
+                        Point {
+                            path: path.clone(),
+                            offset,
+                        }
+                    }
+                };
+                let (start, end) = (edge(range.start(), 0), edge(range.end(), usize::MAX));
+                let tags: HashSet<String> = Some(tag.to_string()).into_iter().collect();
+                (Range::new(start, end), tags)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Node, Text};
+
+    use super::*;
+
+    fn range(start: (Vec<usize>, usize), end: (Vec<usize>, usize)) -> Range {
+        Range::new(
+            Point {
+                path: start.0.into(),
+                offset: start.1,
+            },
+            Point {
+                path: end.0.into(),
+                offset: end.1,
+            },
+        )
+    }
+
+    fn set() -> RangeSet<&'static str> {
+        let mut set = RangeSet::new();
+        set.insert(range((vec![1, 0], 0), (vec![1, 0], 4)), "b");
+        set.insert(range((vec![0, 0], 2), (vec![2, 0], 1)), "a");
+        set.insert(range((vec![0, 0], 1), (vec![0, 0], 3)), "c");
+        set
+    }
+
+    #[test]
+    fn insert_in_order_and_query() {
+        let set = set();
+        let tags: Vec<_> = set.iter().map(|(_, t)| *t).collect();
+        assert_eq!(tags, vec!["c", "a", "b"]);
+
+        let tags: Vec<_> = set.in_node(&Path::new(vec![1])).map(|(_, t)| *t).collect();
+        assert_eq!(tags, vec!["a", "b"]);
+        let query = range((vec![0, 0], 0), (vec![0, 0], 1));
+        let tags: Vec<_> = set.intersecting(&query).map(|(_, t)| *t).collect();
+        assert_eq!(tags, vec!["c"]);
+    }
+
+    #[test]
+    fn transform_and_drop_removed() {
+        let mut set = set();
+        set.transform_all(&[
+            Operation::InsertText {
+                path: vec![0, 0].into(),
+                offset: 0,
+                text: "xx".into(),
+            },
+            Operation::RemoveNode {
+                path: vec![1].into(),
+                node: Node::Element(Element::new().add_child(Text::new("text"))),
+            },
+        ]);
+
+        let ranges: Vec<_> = set.iter().map(|(r, t)| (r.clone(), *t)).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (range((vec![0, 0], 3), (vec![0, 0], 5)), "c"),
+                (range((vec![0, 0], 4), (vec![1, 0], 1)), "a"),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_equal_tags() {
+        let mut set = RangeSet::new();
+        set.insert(range((vec![0, 0], 0), (vec![0, 0], 2)), "a");
+        set.insert(range((vec![0, 0], 4), (vec![0, 0], 1)), "a");
+        set.insert(range((vec![0, 0], 1), (vec![0, 0], 3)), "b");
+        set.insert(range((vec![0, 0], 5), (vec![0, 0], 6)), "a");
+        set.merge();
+
+        let ranges: Vec<_> = set.iter().map(|(r, t)| (r.clone(), *t)).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (range((vec![0, 0], 0), (vec![0, 0], 4)), "a"),
+                (range((vec![0, 0], 1), (vec![0, 0], 3)), "b"),
+                (range((vec![0, 0], 5), (vec![0, 0], 6)), "a"),
+            ]
+        );
+
+        let text = Text::new("abcdefg").decorations(set.decorations(&Path::new(vec![0, 0])));
+        assert_eq!(text.len(), 6);
+    }
+}
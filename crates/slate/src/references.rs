@@ -0,0 +1,217 @@
+use crate::types::{element_entries, Descendant, Editor, Element, Path};
+
+/// The properties of elements that point outside the document, like the
+/// URLs of links and the sources of images.
+///
+/// By default these are a link's `url`, an image's `src` and a mention's
+/// `id`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReferenceRules {
+    properties: Vec<(String, String)>,
+}
+
+impl Default for ReferenceRules {
+    fn default() -> Self {
+        Self::new()
+            .property("link", "url")
+            .property("image", "src")
+            .property("mention", "id")
+    }
+}
+
+impl ReferenceRules {
+    /// Rules without any referencing properties.
+    pub fn new() -> Self {
+        Self { properties: vec![] }
+    }
+
+    /// The `property` of elements of a kind is a reference.
+    pub fn property(mut self, kind: &str, property: &str) -> Self {
+        self.properties
+            .push((kind.to_string(), property.to_string()));
+        self
+    }
+
+    /// The referencing properties of elements of a kind.
+    pub fn properties<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a str> {
+        self.properties
+            .iter()
+            .filter(move |(k, _)| k == kind)
+            .map(|(_, property)| property.as_str())
+    }
+}
+
+/// A reference from an element to something outside the document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    /// The path of the element.
+    pub path: Path,
+    /// The element's kind.
+    pub kind: String,
+    /// The property holding the reference.
+    pub property: String,
+    /// What's referenced, like a URL or an id.
+    pub value: String,
+}
+
+/// The references of a top-level block, with paths relative to it so that
+/// they stay valid as the block moves.
+#[derive(Debug, Clone, PartialEq)]
+struct BlockReferences(Vec<Reference>);
+
+impl Editor {
+    /// Collect references with `rules` rather than the default ones.
+    pub fn with_reference_rules(mut self, rules: ReferenceRules) -> Self {
+        self.caches.references = Some(Box::new(rules));
+        self.cache_mut::<BlockReferences>().clear();
+        self
+    }
+
+    pub fn reference_rules(&self) -> Option<&ReferenceRules> {
+        self.caches.references.as_deref()
+    }
+
+    /// Get the references of the document to things outside it, like the
+    /// images it shows and the users it mentions, in document order. Hosts
+    /// use them to find the uploads nothing refers to anymore, or the assets
+    /// to fetch ahead of rendering.
+    ///
+    /// The references of each top-level block are cached until an operation
+    /// changes it, so only edited blocks are scanned again.
+    pub fn references(&mut self) -> Vec<Reference> {
+        let rules = self.caches.references.clone().unwrap_or_default();
+        let mut out = vec![];
+
+        for i in 0..self.children.len() {
+            let path = Path::new(vec![i]);
+            let cached = self
+                .cache::<BlockReferences>()
+                .and_then(|cache| cache.get(&path))
+                .cloned();
+            let block = match cached {
+                Some(block) => block,
+                None => {
+                    let block = scan(&self.children[i], &rules);
+                    self.cache_mut::<BlockReferences>()
+                        .insert(path.clone(), block.clone());
+                    block
+                }
+            };
+
+            out.extend(block.0.into_iter().map(|reference| {
+                Reference {
+                    path: Path::new(
+                        path.as_slice()
+                            .iter()
+                            .chain(reference.path.as_slice())
+                            .copied()
+                            .collect(),
+                    ),
+                    ..reference
+                }
+            }));
+        }
+
+        out
+    }
+}
+
+/// Collect the references of a block and its descendants, relative to it.
+fn scan(block: &Descendant, rules: &ReferenceRules) -> BlockReferences {
+    let mut out = vec![];
+    let mut visit = |element: &Element, path: Path| {
+        for property in rules.properties(element.kind()) {
+            if let Some(value) = element.property(property) {
+                out.push(Reference {
+                    path: path.clone(),
+                    kind: element.kind().to_string(),
+                    property: property.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+    };
+
+    if let Descendant::Element(element) = block {
+        let root = Path::new(vec![]);
+        visit(element, root.clone());
+        for (element, path) in element_entries(element.children_ref(), &root) {
+            visit(element, path);
+        }
+    }
+
+    BlockReferences(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Node, Operation, Text};
+
+    use super::*;
+
+    fn image(src: &str) -> Element {
+        Element::with_kind("image")
+            .add_property("src", src)
+            .add_child(Text::new(""))
+    }
+
+    #[test]
+    fn collect_references_in_order() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new().add_child(Text::new("see ")).add_child(
+                    Element::with_kind("link")
+                        .add_property("url", "https://a.example")
+                        .add_child(Text::new("a")),
+                ),
+            )
+            .add_child(image("upload/1.png"));
+
+        let values: Vec<_> = editor
+            .references()
+            .into_iter()
+            .map(|r| (r.path, r.value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (Path::new(vec![0, 1]), "https://a.example".to_string()),
+                (Path::new(vec![1]), "upload/1.png".to_string()),
+            ]
+        );
+
+        let mut editor = editor.with_reference_rules(ReferenceRules::new().property("link", "url"));
+        assert_eq!(editor.references().len(), 1);
+    }
+
+    #[test]
+    fn cached_references_follow_operations() {
+        let mut editor = Editor::new()
+            .add_child(image("upload/1.png"))
+            .add_child(Element::new().add_child(Text::new("text")));
+        editor.references();
+
+        editor
+            .apply(Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(image("upload/2.png")),
+            })
+            .unwrap();
+        editor
+            .apply(Operation::RemoveNode {
+                path: Path::new(vec![1]),
+                node: Node::Element(image("upload/1.png")),
+            })
+            .unwrap();
+
+        let values: Vec<_> = editor
+            .references()
+            .into_iter()
+            .map(|r| (r.path, r.value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![(Path::new(vec![0]), "upload/2.png".to_string())]
+        );
+    }
+}
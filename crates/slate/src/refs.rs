@@ -0,0 +1,265 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+use crate::types::{Affinity, Editor, Operation, Path, Point, Range, RangeAffinity};
+
+type Slot<T> = Mutex<Option<T>>;
+
+/// Lock a slot. Slots only ever hold a value, so one left behind by a panic
+/// is still used.
+fn lock<T>(slot: &Slot<T>) -> MutexGuard<'_, Option<T>> {
+    slot.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A point that's moved along with the content it points into as operations
+/// are applied, the way the selection is, like `Editor.pointRef` in
+/// Slate.js. It's `None` once the content is removed and there's no text
+/// around it to fall back to.
+///
+/// The editor stops tracking the point once the ref is dropped.
+#[derive(Debug)]
+pub struct PointRef(Arc<Slot<Point>>);
+
+impl PointRef {
+    pub fn current(&self) -> Option<Point> {
+        lock(&self.0).clone()
+    }
+
+    /// Stop tracking the point, getting where it is now.
+    pub fn unref(self) -> Option<Point> {
+        self.current()
+    }
+
+    pub(crate) fn set(&self, point: Point) {
+        *lock(&self.0) = Some(point);
+    }
+}
+
+/// A path that's moved along with the node at it as operations are applied.
+/// It's `None` once the node is removed.
+///
+/// The editor stops tracking the path once the ref is dropped.
+#[derive(Debug)]
+pub struct PathRef(Arc<Slot<Path>>);
+
+impl PathRef {
+    pub fn current(&self) -> Option<Path> {
+        lock(&self.0).clone()
+    }
+
+    /// Stop tracking the path, getting where it is now.
+    pub fn unref(self) -> Option<Path> {
+        self.current()
+    }
+}
+
+/// A range whose points are moved along with the content they point into
+/// as operations are applied, the way the selection is. It's `None` once the
+/// content of either point is removed and there's no text around it to fall
+/// back to.
+///
+/// The editor stops tracking the range once the ref is dropped.
+#[derive(Debug)]
+pub struct RangeRef(Arc<Slot<Range>>);
+
+impl RangeRef {
+    pub fn current(&self) -> Option<Range> {
+        lock(&self.0).clone()
+    }
+
+    /// Stop tracking the range, getting where it is now.
+    pub fn unref(self) -> Option<Range> {
+        self.current()
+    }
+}
+
+/// The refs of an editor. Dropped refs are forgotten the next time an
+/// operation is applied.
+#[derive(Default)]
+pub(crate) struct Refs {
+    points: Vec<(Weak<Slot<Point>>, Affinity)>,
+    paths: Vec<(Weak<Slot<Path>>, Affinity)>,
+    ranges: Vec<(Weak<Slot<Range>>, RangeAffinity)>,
+}
+
+impl fmt::Debug for Refs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Refs")
+            .field("points", &self.points.len())
+            .field("paths", &self.paths.len())
+            .field("ranges", &self.ranges.len())
+            .finish()
+    }
+}
+
+// A clone of an editor applies its own operations, which the refs of the
+// original don't point into.
+impl Clone for Refs {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+// Refs aren't part of the document, so they never make two editors unequal.
+impl PartialEq for Refs {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Refs {
+    /// Whether any point of a live point or range ref is somewhere `f` is
+    /// true for.
+    pub(crate) fn any(&self, f: impl Fn(&Point) -> bool) -> bool {
+        let points = live(&self.points).any(|point| f(&point));
+        points || live(&self.ranges).any(|range| f(range.anchor()) || f(range.focus()))
+    }
+
+    /// Move each live ref through an operation that has just been applied,
+    /// moving points with `point`, and forget the dropped ones.
+    pub(crate) fn transform(
+        &mut self,
+        op: &Operation,
+        point: impl Fn(&Point, Affinity) -> Option<Point>,
+    ) {
+        retain(&mut self.points, |p, affinity| point(p, affinity));
+        retain(&mut self.paths, |path, affinity| {
+            Path::transform(path, op, affinity).ok()?
+        });
+        retain(&mut self.ranges, |range, affinity| {
+            let (anchor, focus) = affinity.point_affinities(range);
+            Some(Range::new(
+                point(range.anchor(), anchor)?,
+                point(range.focus(), focus)?,
+            ))
+        });
+    }
+}
+
+/// Get the current values of the live refs of a kind.
+fn live<T: Clone, A>(refs: &[(Weak<Slot<T>>, A)]) -> impl Iterator<Item = T> + '_ {
+    refs.iter().filter_map(|(slot, _)| {
+        let slot = slot.upgrade()?;
+        let value = lock(&slot).clone();
+        value
+    })
+}
+
+/// Move the live refs of a kind with `transform`, forgetting the dropped
+/// ones.
+fn retain<T, A: Copy>(refs: &mut Vec<(Weak<Slot<T>>, A)>, transform: impl Fn(&T, A) -> Option<T>) {
+    refs.retain(|(slot, affinity)| {
+        let slot = match slot.upgrade() {
+            Some(slot) => slot,
+            None => return false,
+        };
+        let mut value = lock(&slot);
+        *value = value.take().and_then(|v| transform(&v, *affinity));
+        true
+    });
+}
+
+impl Editor {
+    /// Track a point through the operations applied from now on. A point at
+    /// an insertion or split moves as `affinity` says.
+    pub fn point_ref(&mut self, point: Point, affinity: Affinity) -> PointRef {
+        let slot = Arc::new(Mutex::new(Some(point)));
+        self.refs.points.push((Arc::downgrade(&slot), affinity));
+        PointRef(slot)
+    }
+
+    /// Track a path through the operations applied from now on. A path where
+    /// a node is inserted moves as `affinity` says.
+    pub fn path_ref(&mut self, path: Path, affinity: Affinity) -> PathRef {
+        let slot = Arc::new(Mutex::new(Some(path)));
+        self.refs.paths.push((Arc::downgrade(&slot), affinity));
+        PathRef(slot)
+    }
+
+    /// Track a range through the operations applied from now on. Its points
+    /// move as `affinity` says, like the selection does with the editor's
+    /// selection affinities.
+    pub fn range_ref(&mut self, range: Range, affinity: RangeAffinity) -> RangeRef {
+        let slot = Arc::new(Mutex::new(Some(range)));
+        self.refs.ranges.push((Arc::downgrade(&slot), affinity));
+        RangeRef(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Node, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn refs_follow_edits() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("ab")))
+            .add_child(Element::new().add_child(Text::new("cd")));
+        let forward = editor.point_ref(point(vec![1, 0], 1), Affinity::Forward);
+        let backward = editor.point_ref(point(vec![1, 0], 1), Affinity::Backward);
+        let dropped = editor.point_ref(point(vec![0, 0], 0), Affinity::Forward);
+        drop(dropped);
+
+        editor.select(point(vec![1, 0], 1));
+        editor.insert_text("x", None);
+        assert_eq!(forward.current(), Some(point(vec![1, 0], 2)));
+        assert_eq!(backward.current(), Some(point(vec![1, 0], 1)));
+        assert_eq!(editor.refs.points.len(), 2);
+
+        // Points in a removed node fall back to the end of the text before it.
+        editor.remove_node(&Path::new(vec![1]));
+        assert_eq!(forward.current(), Some(point(vec![0, 0], 2)));
+    }
+
+    #[test]
+    fn path_and_range_refs() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("ab")))
+            .add_child(Element::new().add_child(Text::new("cd")));
+        let second = editor.path_ref(Path::new(vec![1]), Affinity::Forward);
+        let range = Range::new(point(vec![1, 0], 0), point(vec![1, 0], 2));
+        let inward = editor.range_ref(range.clone(), RangeAffinity::Inward);
+        let outward = editor.range_ref(range, RangeAffinity::Outward);
+
+        editor
+            .apply(Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(Element::new().add_child(Text::new("new"))),
+            })
+            .unwrap();
+        assert_eq!(second.current(), Some(Path::new(vec![2])));
+
+        editor.select(point(vec![2, 0], 0));
+        editor.insert_text("x", None);
+        editor.select(point(vec![2, 0], 3));
+        editor.insert_text("y", None);
+        // Text typed at the edges is left out of an inward range, and taken
+        // in by an outward one.
+        assert_eq!(
+            inward.current(),
+            Some(Range::new(point(vec![2, 0], 1), point(vec![2, 0], 3)))
+        );
+        assert_eq!(
+            outward.unref(),
+            Some(Range::new(point(vec![2, 0], 0), point(vec![2, 0], 4)))
+        );
+
+        editor.remove_node(&Path::new(vec![2]));
+        assert_eq!(second.unref(), None);
+        assert_eq!(
+            inward.current(),
+            Some(Range::new(point(vec![1, 0], 2), point(vec![1, 0], 2)))
+        );
+        // The range that was unreffed is forgotten.
+        assert_eq!(editor.refs.ranges.len(), 1);
+    }
+}
@@ -0,0 +1,445 @@
+//! Recording the batches applied to a document, and replaying them step by
+//! step, for debugging a session that went wrong, like a collaboration bug
+//! reported from production.
+//!
+//! A [`Recording`] holds the document a session started from and each batch
+//! applied to it, with where the batch came from and a hash of the document
+//! it made. A [`ReplaySession`] applies the batches one at a time to a fresh
+//! editor, checking after each that it made the same document and that the
+//! invariants hold, and describes the first step where they don't.
+
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use serde_json::{json, Value};
+
+use crate::serialize::json;
+use crate::testing::pretty;
+use crate::transforms::ApplyError;
+use crate::types::{ContentHash, Editor, Operation};
+
+/// A batch of operations applied during a recorded session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedBatch {
+    /// Where the batch came from, like the user or peer who made it.
+    pub origin: String,
+    /// The operations, in the order they were applied.
+    pub operations: Vec<Operation>,
+    /// The hash of the document after the batch was applied.
+    pub hash: ContentHash,
+}
+
+/// The batches applied to a document, in order, starting from its content
+/// when recording began.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    initial: Editor,
+    batches: Vec<RecordedBatch>,
+}
+
+impl Recording {
+    /// Start recording a document. Only its content and selection are kept,
+    /// so the batches replay the same way whatever the editor was
+    /// configured with.
+    pub fn new(editor: &Editor) -> Self {
+        let mut initial = Editor::new();
+        initial.replace_children(editor.children.clone());
+        initial.selection = editor.selection.clone();
+
+        Self {
+            initial,
+            batches: vec![],
+        }
+    }
+
+    /// Record a batch applied to the document, with the editor it was
+    /// applied to, like from a subscriber or after `Editor::flush`.
+    pub fn record(
+        &mut self,
+        origin: impl Into<String>,
+        operations: Vec<Operation>,
+        after: &Editor,
+    ) {
+        self.batches.push(RecordedBatch {
+            origin: origin.into(),
+            operations,
+            hash: after.content_hash(),
+        });
+    }
+
+    /// Get the document the recording starts from.
+    pub fn initial(&self) -> &Editor {
+        &self.initial
+    }
+
+    /// Get the recorded batches, in the order they were applied.
+    pub fn batches(&self) -> &[RecordedBatch] {
+        &self.batches
+    }
+
+    /// Serialize the recording to JSON, to attach to a bug report.
+    pub fn to_json(&self) -> String {
+        let initial: Value =
+            serde_json::from_str(&json::to_string(&self.initial)).expect("documents to be JSON");
+        let batches: Vec<_> = self
+            .batches
+            .iter()
+            .map(|batch| {
+                let operations: Vec<_> = batch
+                    .operations
+                    .iter()
+                    .map(json::operation_to_value)
+                    .collect();
+                json!({
+                    "origin": batch.origin,
+                    "operations": operations,
+                    "hash": batch.hash.to_string(),
+                })
+            })
+            .collect();
+
+        json!({ "initial": initial, "batches": batches }).to_string()
+    }
+
+    /// Deserialize a recording from the JSON `to_json` writes.
+    pub fn from_json(recording: &str) -> Result<Self, json::ImportWarning> {
+        let invalid = |what: &str| json::ImportWarning::InvalidJson(format!("invalid {}", what));
+        let value: Value = serde_json::from_str(recording)
+            .map_err(|e| json::ImportWarning::InvalidJson(e.to_string()))?;
+
+        let initial = value.get("initial").ok_or_else(|| invalid("document"))?;
+        let initial = json::from_string(&initial.to_string())?;
+
+        let batches = value
+            .get("batches")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("batches"))?
+            .iter()
+            .map(|batch| {
+                let operations = batch
+                    .get("operations")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| invalid("batch"))?
+                    .iter()
+                    .map(|op| json::operation_from_value(op).ok_or_else(|| invalid("operation")))
+                    .collect::<Result<_, _>>()?;
+                Ok(RecordedBatch {
+                    origin: batch
+                        .get("origin")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| invalid("batch"))?
+                        .to_string(),
+                    operations,
+                    hash: batch
+                        .get("hash")
+                        .and_then(Value::as_str)
+                        .and_then(ContentHash::from_hex)
+                        .ok_or_else(|| invalid("hash"))?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { initial, batches })
+    }
+}
+
+/// Why a replayed step diverged from the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DivergenceReason {
+    /// The operation at this index of the batch didn't apply.
+    Apply(usize, ApplyError),
+    /// The batch made a document other than the one recorded.
+    Hash,
+    /// An invariant doesn't hold after the batch.
+    Invariant(String),
+}
+
+impl fmt::Display for DivergenceReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DivergenceReason::Apply(i, e) => write!(f, "operation {} didn't apply: {}", i, e),
+            DivergenceReason::Hash => write!(f, "the document differs from the recording"),
+            DivergenceReason::Invariant(message) => write!(f, "invariant failed: {}", message),
+        }
+    }
+}
+
+/// The first step of a replay that diverged from its recording, with the
+/// document before and after it, as `testing::pretty` prints them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// The index of the batch that diverged.
+    pub step: usize,
+    /// Where the batch came from.
+    pub origin: String,
+    pub reason: DivergenceReason,
+    pub before: String,
+    pub after: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "step {} from {:?} diverged: {}\nbefore:\n{}after:\n{}",
+            self.step, self.origin, self.reason, self.before, self.after
+        )
+    }
+}
+
+impl Error for Divergence {}
+
+type Check = dyn Fn(&Editor) -> Result<(), String>;
+
+/// A check run on the document after every replayed step.
+#[derive(Clone)]
+struct Invariant(Rc<Check>);
+
+impl fmt::Debug for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Invariant")
+    }
+}
+
+/// A recording being replayed, one batch at a time.
+///
+/// After each step the document must have the recorded hash, the selection
+/// must point into text, and every invariant added must hold.
+#[derive(Debug, Clone)]
+pub struct ReplaySession {
+    recording: Recording,
+    editor: Editor,
+    position: usize,
+    invariants: Vec<Invariant>,
+}
+
+impl ReplaySession {
+    pub fn new(recording: Recording) -> Self {
+        let editor = recording.initial.clone();
+
+        Self {
+            recording,
+            editor,
+            position: 0,
+            invariants: vec![],
+        }
+    }
+
+    /// Check something after every step, like a rule of the app's schema.
+    /// An error's message describes what doesn't hold.
+    pub fn with_invariant(
+        mut self,
+        invariant: impl Fn(&Editor) -> Result<(), String> + 'static,
+    ) -> Self {
+        self.invariants.push(Invariant(Rc::new(invariant)));
+        self
+    }
+
+    /// Get the document as of the steps replayed so far.
+    pub fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    /// Get the recording being replayed.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Get the number of steps replayed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Check whether every step has been replayed.
+    pub fn is_done(&self) -> bool {
+        self.position == self.recording.batches.len()
+    }
+
+    /// Replay the next batch, returning the batch, or `None` once every step
+    /// has been replayed.
+    ///
+    /// If the step diverges, the session stays on it, with the document as
+    /// the step left it, to be inspected.
+    pub fn step(&mut self) -> Result<Option<&RecordedBatch>, Divergence> {
+        let batch = match self.recording.batches.get(self.position) {
+            Some(batch) => batch,
+            None => return Ok(None),
+        };
+        let before = pretty(&self.editor);
+
+        let mut reason = None;
+        for (i, op) in batch.operations.iter().enumerate() {
+            if let Err(e) = self.editor.apply(op.clone()) {
+                reason = Some(DivergenceReason::Apply(i, e));
+                break;
+            }
+        }
+        self.editor.flush();
+
+        let reason = reason
+            .or_else(|| (!self.editor.verify(&batch.hash)).then_some(DivergenceReason::Hash))
+            .or_else(|| {
+                std::iter::once(&Invariant(Rc::new(check_selection)))
+                    .chain(&self.invariants)
+                    .find_map(|invariant| (invariant.0)(&self.editor).err())
+                    .map(DivergenceReason::Invariant)
+            });
+
+        if let Some(reason) = reason {
+            return Err(Divergence {
+                step: self.position,
+                origin: batch.origin.clone(),
+                reason,
+                before,
+                after: pretty(&self.editor),
+            });
+        }
+
+        self.position += 1;
+        Ok(Some(batch))
+    }
+
+    /// Replay every remaining step, stopping at the first that diverges.
+    pub fn run(&mut self) -> Result<(), Divergence> {
+        while self.step()?.is_some() {}
+        Ok(())
+    }
+}
+
+/// Check that the selection's points are in text nodes of the document.
+fn check_selection(editor: &Editor) -> Result<(), String> {
+    let selection = match editor.selection() {
+        Some(selection) => selection,
+        None => return Ok(()),
+    };
+
+    for point in &[selection.anchor(), selection.focus()] {
+        match editor.text_at(&point.path) {
+            Some(text) if point.offset <= text.text().len() => {}
+            _ => {
+                return Err(format!(
+                    "selection point {:?} is outside of the document",
+                    point
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, Node, Path, Point, Range, Text};
+
+    fn doc() -> Editor {
+        Editor::new().add_child(Element::new().add_child(Text::new("one")))
+    }
+
+    fn insert_text(offset: usize, text: &str) -> Operation {
+        Operation::InsertText {
+            path: Path::new(vec![0, 0]),
+            offset,
+            text: text.to_string(),
+        }
+    }
+
+    fn recording() -> Recording {
+        let mut editor = doc();
+        let mut recording = Recording::new(&editor);
+
+        for (origin, ops) in [
+            ("alice", vec![insert_text(3, " two")]),
+            (
+                "bob",
+                vec![Operation::InsertNode {
+                    path: Path::new(vec![1]),
+                    node: Node::Element(Element::new().add_child(Text::new("three"))),
+                }],
+            ),
+        ] {
+            for op in &ops {
+                editor.apply(op.clone()).unwrap();
+            }
+            editor.flush();
+            recording.record(origin, ops, &editor);
+        }
+
+        recording
+    }
+
+    #[test]
+    fn replay_step_by_step() {
+        let mut session = ReplaySession::new(recording());
+
+        assert_eq!(session.step().unwrap().unwrap().origin, "alice");
+        assert_eq!(
+            session.editor().block_text(&Path::new(vec![0])).unwrap(),
+            "one two"
+        );
+        assert_eq!(session.step().unwrap().unwrap().origin, "bob");
+        assert!(session.is_done());
+        assert_eq!(session.step(), Ok(None));
+        assert_eq!(session.position(), 2);
+    }
+
+    #[test]
+    fn recordings_round_trip_through_json() {
+        let recording = recording();
+        let read = Recording::from_json(&recording.to_json()).unwrap();
+
+        assert!(read.initial().content_eq(recording.initial()));
+        assert_eq!(read.batches(), recording.batches());
+        assert!(Recording::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn report_divergence() {
+        let mut recording = recording();
+        recording.batches[1].hash = doc().content_hash();
+        let mut session = ReplaySession::new(recording);
+
+        let divergence = session.run().unwrap_err();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.origin, "bob");
+        assert_eq!(divergence.reason, DivergenceReason::Hash);
+        assert_eq!(divergence.before, "paragraph\n  \"one two\"\n");
+        assert_eq!(
+            divergence.after,
+            "paragraph\n  \"one two\"\nparagraph\n  \"three\"\n"
+        );
+        assert_eq!(session.position(), 1);
+    }
+
+    #[test]
+    fn check_invariants_after_each_step() {
+        let mut session =
+            ReplaySession::new(recording()).with_invariant(|editor| match editor.children.len() {
+                1 => Ok(()),
+                n => Err(format!("{} blocks", n)),
+            });
+        session.step().unwrap();
+        let divergence = session.step().unwrap_err();
+        assert_eq!(
+            divergence.reason,
+            DivergenceReason::Invariant("2 blocks".to_string())
+        );
+
+        let mut recording = recording();
+        let point = Point {
+            path: Path::new(vec![0, 0]),
+            offset: 20,
+        };
+        recording.batches[0]
+            .operations
+            .push(Operation::SetSelection {
+                path: Path::new(vec![]),
+                properties: None,
+                new_properties: Some(Range::new(point.clone(), point)),
+            });
+        let divergence = ReplaySession::new(recording).step().unwrap_err();
+        assert!(matches!(divergence.reason, DivergenceReason::Invariant(_)));
+    }
+}
@@ -0,0 +1,515 @@
+use std::collections::HashSet;
+
+use crate::input::InputType;
+use crate::transforms::ApplyError;
+use crate::types::{
+    has_inlines, Descendant, Editor, Element, Marks, Text, BACKGROUND_MARK, COLOR_MARK,
+    DEFAULT_KIND,
+};
+
+/// Rules applied to content coming from outside the editor, like pastes, so
+/// that it can't violate the app's schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizeOptions {
+    /// The element kinds allowed, or `None` to allow any kind. Disallowed
+    /// elements are unwrapped, except for blocks holding inline content which
+    /// become elements of `default_kind` instead, if that kind is allowed.
+    pub allowed_kinds: Option<HashSet<String>>,
+    /// The kind given to disallowed blocks holding inline content.
+    pub default_kind: String,
+    /// The deepest an element may be nested, with top-level elements at depth
+    /// one. Elements nested deeper are unwrapped into their parent.
+    pub max_depth: Option<usize>,
+    /// The marks allowed on text, or `None` to allow any mark, including
+    /// custom ones. Any others are dropped, while valued marks and authors
+    /// are kept.
+    pub allowed_marks: Option<Marks>,
+    /// The valued marks allowed on text, like `color`, or `None` to allow any.
+    /// Colors and backgrounds that aren't plain CSS colors are always dropped,
+    /// as they're written into styles.
+    pub allowed_mark_values: Option<HashSet<String>>,
+    /// The element properties allowed, or `None` to allow any. Others are
+    /// removed.
+    pub allowed_properties: Option<HashSet<String>>,
+    /// The element properties holding URLs, whose schemes are checked.
+    pub url_properties: HashSet<String>,
+    /// The URL schemes allowed in `url_properties`, or `None` to allow any.
+    /// URLs with other schemes, like `javascript:`, are removed, while
+    /// relative ones are kept. By default `http`, `https`, `mailto` and `tel`
+    /// are allowed.
+    pub allowed_schemes: Option<HashSet<String>>,
+    /// The maximum length of text, in bytes. Content past the limit is dropped.
+    pub max_length: Option<usize>,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        Self {
+            allowed_kinds: None,
+            default_kind: DEFAULT_KIND.into(),
+            max_depth: None,
+            allowed_marks: None,
+            allowed_mark_values: None,
+            allowed_properties: None,
+            url_properties: strings(["url", "href", "src"]),
+            allowed_schemes: Some(strings(["http", "https", "mailto", "tel"])),
+            max_length: None,
+        }
+    }
+}
+
+fn strings<'a>(values: impl IntoIterator<Item = &'a str>) -> HashSet<String> {
+    values.into_iter().map(String::from).collect()
+}
+
+impl SanitizeOptions {
+    pub fn allow_kinds<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_kinds = Some(kinds.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn allow_marks(mut self, marks: Marks) -> Self {
+        self.allowed_marks = Some(marks);
+        self
+    }
+
+    pub fn allow_mark_values<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_mark_values = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn allow_properties<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_properties = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn allow_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_schemes = Some(
+            schemes
+                .into_iter()
+                .map(|scheme| scheme.into().to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    pub fn max_length(mut self, length: usize) -> Self {
+        self.max_length = Some(length);
+        self
+    }
+
+    fn allows(&self, kind: &str) -> bool {
+        self.allowed_kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.contains(kind))
+    }
+
+    fn allows_property(&self, key: &str, value: &str) -> bool {
+        let allowed = self
+            .allowed_properties
+            .as_ref()
+            .is_none_or(|keys| keys.contains(key));
+        let safe = !self.url_properties.contains(key)
+            || match (&self.allowed_schemes, scheme(value)) {
+                (Some(schemes), Some(scheme)) => schemes.contains(&scheme),
+                _ => true,
+            };
+        allowed && safe
+    }
+
+    fn allows_mark_value(&self, key: &str, value: &str) -> bool {
+        let allowed = self
+            .allowed_mark_values
+            .as_ref()
+            .is_none_or(|keys| keys.contains(key));
+        let safe = (key != COLOR_MARK && key != BACKGROUND_MARK) || is_css_color(value);
+        allowed && safe
+    }
+}
+
+/// Get the scheme of a URL, lowercased, or `None` if it's relative.
+/// Whitespace and control characters are ignored, as browsers do.
+fn scheme(url: &str) -> Option<String> {
+    let url: String = url.chars().filter(|c| *c > ' ' && *c != '\u{7f}').collect();
+    let end = url.find([':', '/', '?', '#'])?;
+    let scheme = &url[..end];
+    let valid = url[end..].starts_with(':')
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then(|| scheme.to_ascii_lowercase())
+}
+
+/// Whether a value is a CSS color, as a name, a hex color or a color
+/// function, and nothing that could escape its declaration.
+fn is_css_color(value: &str) -> bool {
+    let value = value.trim().to_ascii_lowercase();
+    let plain = !value.is_empty()
+        && value.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '#' | '%' | '.' | ',' | ' ' | '-' | '(' | ')' | '/')
+        });
+    let function = match value.find('(') {
+        Some(i) => {
+            ["rgb", "rgba", "hsl", "hsla"].contains(&&value[..i])
+                && value.ends_with(')')
+                && value.matches('(').count() == 1
+                && value.matches(')').count() == 1
+        }
+        None => !value.contains(')'),
+    };
+    plain && function
+}
+
+/// Sanitize a fragment according to a set of rules.
+pub fn sanitize(fragment: Vec<Descendant>, options: &SanitizeOptions) -> Vec<Descendant> {
+    let nodes = sanitize_nodes(fragment, options, 1);
+
+    match options.max_length {
+        Some(max) => truncate(nodes, &mut { max }),
+        None => nodes,
+    }
+}
+
+fn sanitize_nodes(
+    nodes: Vec<Descendant>,
+    options: &SanitizeOptions,
+    depth: usize,
+) -> Vec<Descendant> {
+    let inline = has_inlines(&nodes);
+    let mut out = vec![];
+
+    for node in nodes {
+        match node {
+            Descendant::Text(mut t) => {
+                if let Some(allowed) = options.allowed_marks {
+                    *t.marks_mut() &= allowed;
+                }
+                sanitize_mark_values(&mut t, options);
+                out.push(t.into());
+            }
+            Descendant::Element(e) => {
+                let too_deep = options.max_depth.is_some_and(|max| depth > max);
+
+                if too_deep {
+                    out.extend(sanitize_nodes(e.children(), options, depth));
+                    continue;
+                }
+
+                let children = sanitize_nodes(e.children(), options, depth + 1);
+
+                if options.allows(e.kind()) {
+                    let mut e = e.with_children(children);
+                    sanitize_properties(&mut e, options);
+                    out.push(e.into());
+                } else if !inline && has_inlines(&children) && options.allows(&options.default_kind)
+                {
+                    let block = Element::with_kind(options.default_kind.as_str());
+                    out.push(block.with_children(children).into());
+                } else {
+                    out.extend(children);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn sanitize_properties(element: &mut Element, options: &SanitizeOptions) {
+    element
+        .properties_mut()
+        .retain(|key, value| options.allows_property(key, value));
+}
+
+fn sanitize_mark_values(text: &mut Text, options: &SanitizeOptions) {
+    text.mark_values_mut()
+        .retain(|key, value| options.allows_mark_value(key, value));
+}
+
+/// Drop content after `remaining` bytes of text, counting down as text is
+/// kept.
+fn truncate(nodes: Vec<Descendant>, remaining: &mut usize) -> Vec<Descendant> {
+    let mut out = vec![];
+
+    for node in nodes {
+        if *remaining == 0 {
+            break;
+        }
+
+        match node {
+            Descendant::Text(t) => {
+                let mut end = t.text().len().min(*remaining);
+                while !t.text().is_char_boundary(end) {
+                    end -= 1;
+                }
+                *remaining = if end < t.text().len() {
+                    0
+                } else {
+                    *remaining - end
+                };
+                out.push(t.with_text(&t.text()[..end]).into());
+            }
+            Descendant::Element(e) => {
+                let children = truncate(e.children(), remaining);
+                out.push(e.with_children(children).into());
+            }
+        }
+    }
+
+    out
+}
+
+impl Editor {
+    /// Sanitize content inserted from outside the editor with a set of rules.
+    pub fn with_paste_sanitizer(mut self, options: SanitizeOptions) -> Self {
+        self.paste_sanitizer = Some(Box::new(options));
+        self
+    }
+
+    /// Insert a fragment that came from outside the editor at the selection,
+    /// sanitizing it first if the editor has a paste sanitizer.
+    pub fn insert_fragment_data(&mut self, fragment: Vec<Descendant>) -> Result<(), ApplyError> {
+        let fragment = match &self.paste_sanitizer {
+            Some(options) => sanitize(fragment, options),
+            None => fragment,
+        };
+
+        self.input(InputType::Paste, |editor| editor.insert_fragment(fragment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Point, Text};
+
+    #[test]
+    fn sanitize_disallowed_block() {
+        let fragment = vec![Element::with_kind("heading-one")
+            .add_child(Text::new("title"))
+            .into()];
+        let options = SanitizeOptions::default().allow_kinds(vec!["paragraph"]);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![Element::new().add_child(Text::new("title")).into()]
+        );
+    }
+
+    #[test]
+    fn sanitize_disallowed_container() {
+        let fragment = vec![Element::with_kind("block-quote")
+            .add_child(Element::new().add_child(Text::new("a")))
+            .add_child(Element::new().add_child(Text::new("b")))
+            .into()];
+        let options = SanitizeOptions::default().allow_kinds(vec!["paragraph"]);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![
+                Element::new().add_child(Text::new("a")).into(),
+                Element::new().add_child(Text::new("b")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn sanitize_disallowed_inline() {
+        let fragment = vec![Element::new()
+            .add_child(Text::new("a "))
+            .add_child(
+                Element::with_kind("link")
+                    .add_property("url", "javascript:alert(1)")
+                    .add_child(Text::new("link")),
+            )
+            .into()];
+        let options = SanitizeOptions::default().allow_kinds(vec!["paragraph"]);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![Element::new()
+                .add_child(Text::new("a "))
+                .add_child(Text::new("link"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn sanitize_max_depth() {
+        let fragment = vec![Element::with_kind("bulleted-list")
+            .add_child(
+                Element::with_kind("list-item")
+                    .add_child(Element::new().add_child(Text::new("a")))
+                    .add_child(Element::new().add_child(Text::new("b"))),
+            )
+            .into()];
+        let options = SanitizeOptions::default().max_depth(2);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![Element::with_kind("bulleted-list")
+                .add_child(
+                    Element::with_kind("list-item")
+                        .add_child(Text::new("a"))
+                        .add_child(Text::new("b"))
+                )
+                .into()]
+        );
+    }
+
+    #[test]
+    fn sanitize_marks() {
+        let fragment = vec![Element::new()
+            .add_child(Text::with_marks("x", Marks::BOLD | Marks::UNDERLINE))
+            .into()];
+        let options = SanitizeOptions::default().allow_marks(Marks::BOLD);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![Element::new()
+                .add_child(Text::with_marks("x", Marks::BOLD))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn sanitize_keeps_custom_and_valued_marks() {
+        let strike = crate::types::MarkRegistry::new()
+            .register("strikethrough")
+            .unwrap();
+        let text = Text::with_marks("x", Marks::BOLD | strike)
+            .add_mark_value("color", "red")
+            .authored_by("ann");
+        let fragment = vec![Element::new().add_child(text.clone()).into()];
+        assert_eq!(
+            sanitize(fragment.clone(), &SanitizeOptions::default()),
+            fragment
+        );
+
+        let options = SanitizeOptions::default().allow_marks(strike);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![Element::new()
+                .add_child(
+                    Text::with_marks("x", strike)
+                        .add_mark_value("color", "red")
+                        .authored_by("ann")
+                )
+                .into()]
+        );
+    }
+
+    #[test]
+    fn sanitize_properties_and_urls() {
+        let link = |url: &str| {
+            Element::with_kind("link")
+                .add_property("url", url)
+                .add_property("target", "_blank")
+                .add_child(Text::new("link"))
+        };
+        let fragment = vec![Element::new()
+            .add_child(link("javascript:alert(1)"))
+            .add_child(link(" JavaScript:alert(1)"))
+            .add_child(link("java\tscript:alert(1)"))
+            .add_child(link("https://a.example"))
+            .add_child(link("/a?b=c:d"))
+            .into()];
+        let options = SanitizeOptions::default().allow_properties(vec!["url"]);
+        let bare = Element::with_kind("link").add_child(Text::new("link"));
+        let to = |url: &str| bare.clone().add_property("url", url);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![Element::new()
+                .add_child(bare.clone())
+                .add_child(bare.clone())
+                .add_child(bare.clone())
+                .add_child(to("https://a.example"))
+                .add_child(to("/a?b=c:d"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn sanitize_mark_values() {
+        let text = |color: &str| {
+            Text::new("x")
+                .add_mark_value("color", color)
+                .add_mark_value("background", "rgb(0, 0, 0)")
+                .add_mark_value("font", "serif")
+        };
+        let fragment = vec![Element::new()
+            .add_child(text("red"))
+            .add_child(text("red; position: fixed"))
+            .add_child(text("url(javascript:alert(1))"))
+            .into()];
+        let options = SanitizeOptions::default().allow_mark_values(vec!["color", "background"]);
+        let background = Text::new("x").add_mark_value("background", "rgb(0, 0, 0)");
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![Element::new()
+                .add_child(background.clone().add_mark_value("color", "red"))
+                .add_child(background.clone())
+                .add_child(background)
+                .into()]
+        );
+    }
+
+    #[test]
+    fn sanitize_max_length() {
+        let fragment = vec![
+            Element::new().add_child(Text::new("abc")).into(),
+            Element::new().add_child(Text::new("déf")).into(),
+            Element::new().add_child(Text::new("ghi")).into(),
+        ];
+        let options = SanitizeOptions::default().max_length(5);
+        assert_eq!(
+            sanitize(fragment, &options),
+            vec![
+                Element::new().add_child(Text::new("abc")).into(),
+                Element::new().add_child(Text::new("d")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_fragment_data_sanitizes() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .with_paste_sanitizer(SanitizeOptions::default().allow_marks(Marks::empty()));
+        editor.select(Point {
+            path: vec![0, 0].into(),
+            offset: 0,
+        });
+        editor
+            .insert_fragment_data(vec![Element::new()
+                .add_child(Text::with_marks("x", Marks::ITALIC))
+                .into()])
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new(""))
+                .add_child(Text::new("x"))
+                .add_child(Text::new(""))
+                .into()]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+
+use crate::types::{Descendant, Editor, DEFAULT_KIND};
+
+/// The kinds of content elements hold, used by normalization to repair
+/// elements holding the wrong kind of children without losing any of them.
+///
+/// The document holds blocks. An element of an inline kind holds inline
+/// content, which is text and inline elements, and a container holds blocks.
+/// Any other element holds whatever its first child is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    /// The element kinds that are inline.
+    pub inline_kinds: HashSet<String>,
+    /// The element kinds that hold blocks, like quotes and lists.
+    pub container_kinds: HashSet<String>,
+    /// The element kinds that are isolating, like table cells: the caret
+    /// doesn't move across their edges and content isn't merged across them.
+    pub isolating_kinds: HashSet<String>,
+    /// The kind of the blocks inline content is wrapped in where blocks are
+    /// expected.
+    pub default_block: String,
+    /// The kind blocks are turned into where inline content is expected.
+    pub default_inline: String,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self {
+            inline_kinds: ["link", "footnote-ref", "math", "redaction", "emoji"]
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+            container_kinds: ["block-quote", "bulleted-list", "numbered-list"]
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+            isolating_kinds: ["table-cell"].iter().map(|k| k.to_string()).collect(),
+            default_block: DEFAULT_KIND.into(),
+            default_inline: "span".into(),
+        }
+    }
+}
+
+impl Schema {
+    pub fn inline_kinds<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inline_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn container_kinds<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.container_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn isolating_kinds<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.isolating_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn default_block(mut self, kind: impl Into<String>) -> Self {
+        self.default_block = kind.into();
+        self
+    }
+
+    pub fn default_inline(mut self, kind: impl Into<String>) -> Self {
+        self.default_inline = kind.into();
+        self
+    }
+
+    /// Whether a node is inline content.
+    pub fn is_inline(&self, node: &Descendant) -> bool {
+        match node {
+            Descendant::Text(_) => true,
+            Descendant::Element(e) => self.inline_kinds.contains(e.kind()),
+        }
+    }
+
+    /// Whether elements of a kind are isolating.
+    pub fn is_isolating(&self, kind: &str) -> bool {
+        self.isolating_kinds.contains(kind)
+    }
+
+    /// Whether an element of a kind holds blocks, given its first child.
+    pub(crate) fn holds_blocks(&self, kind: &str, first: Option<&Descendant>) -> bool {
+        if self.inline_kinds.contains(kind) {
+            false
+        } else if self.container_kinds.contains(kind) {
+            true
+        } else {
+            first.is_some_and(|c| !self.is_inline(c))
+        }
+    }
+}
+
+impl Editor {
+    /// Repair elements holding the wrong kind of children when normalizing.
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(Box::new(schema));
+        self
+    }
+
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_deref()
+    }
+}
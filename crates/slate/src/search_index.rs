@@ -0,0 +1,159 @@
+use std::ops;
+
+use crate::anchors::ANCHOR_PROPERTY;
+use crate::types::{text_entries, Descendant, Editor, Marks, Path, Range};
+
+/// The text of a block, as a document for a search index like tantivy or
+/// meilisearch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexUnit {
+    /// The block's anchor or id if it has one, or else its path with the
+    /// indexes separated by dots, like `2.0`.
+    pub key: String,
+    /// The path of the block.
+    pub path: Path,
+    /// The block's plain text.
+    pub text: String,
+    /// The runs of marked text, as byte ranges in `text`.
+    pub marks: Vec<(ops::Range<usize>, Marks)>,
+    /// Where `text` starts in the text of the whole document, where blocks
+    /// are separated by newlines.
+    pub offset: usize,
+}
+
+impl IndexUnit {
+    /// The range of the document text the unit covers.
+    pub fn span(&self) -> ops::Range<usize> {
+        self.offset..self.offset + self.text.len()
+    }
+}
+
+impl Editor {
+    /// Get the text of each block with text as a unit to index, in document
+    /// order. Empty blocks are included, so that the units' offsets add up.
+    pub fn index_units(&self) -> Vec<IndexUnit> {
+        let mut offset = 0;
+        let mut out = vec![];
+
+        for path in self.blocks_from(&Path::new(vec![])) {
+            let block = match self.descendant_ref(&path) {
+                Some(Descendant::Element(block)) => block,
+                _ => continue,
+            };
+            let key = match block
+                .property(ANCHOR_PROPERTY)
+                .or_else(|| block.property("id"))
+            {
+                Some(key) => key.to_string(),
+                None => {
+                    let indexes: Vec<_> = path.as_slice().iter().map(usize::to_string).collect();
+                    indexes.join(".")
+                }
+            };
+
+            let mut text = String::new();
+            let mut marks: Vec<(ops::Range<usize>, Marks)> = vec![];
+            for (leaf, _) in text_entries(block.children_ref(), &path) {
+                let start = text.len();
+                text.push_str(leaf.text());
+                if leaf.marks().is_empty() || leaf.text().is_empty() {
+                    continue;
+                }
+                match marks.last_mut() {
+                    Some((run, m)) if run.end == start && *m == leaf.marks() => {
+                        run.end = text.len()
+                    }
+                    _ => marks.push((start..text.len(), leaf.marks())),
+                }
+            }
+
+            let len = text.len();
+            out.push(IndexUnit {
+                key,
+                path,
+                text,
+                marks,
+                offset,
+            });
+            offset += len + 1;
+        }
+
+        out
+    }
+
+    /// Get the range of some text of the document, as offsets like those of
+    /// `index_units`, to highlight a search hit. A range reaching into the
+    /// newline between two blocks ends at the end of the first.
+    pub fn index_range(&self, hit: ops::Range<usize>) -> Option<Range> {
+        let point = |offset: usize| {
+            let mut start = 0;
+            for path in self.blocks_from(&Path::new(vec![])) {
+                let len = self.block_text(&path)?.len();
+                if offset <= start + len {
+                    return self.point_at_offset(&path, offset - start);
+                }
+                start += len + 1;
+            }
+            None
+        };
+
+        Some(Range::new(point(hit.start)?, point(hit.end)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Point, Text};
+
+    use super::*;
+
+    fn doc() -> Editor {
+        Editor::new()
+            .add_child(
+                Element::with_kind("heading-one")
+                    .add_property(ANCHOR_PROPERTY, "intro")
+                    .add_child(Text::new("Intro")),
+            )
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("some "))
+                    .add_child(Text::with_marks("bold", Marks::BOLD))
+                    .add_child(
+                        Element::with_kind("link")
+                            .add_property("url", "a")
+                            .add_child(Text::with_marks("er", Marks::BOLD)),
+                    )
+                    .add_child(Text::new(" text")),
+            )
+    }
+
+    #[test]
+    fn index_units_of_blocks() {
+        let units = doc().index_units();
+
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].key, "intro");
+        assert_eq!(units[1].key, "1");
+        assert_eq!(units[1].text, "some bolder text");
+        assert_eq!(units[1].offset, 6);
+        assert_eq!(units[1].marks, vec![(5..11, Marks::BOLD)]);
+    }
+
+    #[test]
+    fn map_hits_back_to_ranges() {
+        let editor = doc();
+        let text: Vec<_> = editor.index_units().into_iter().map(|u| u.text).collect();
+        let text = text.join("\n");
+        let hit = text.find("bolder").unwrap();
+
+        let point = |path: Vec<usize>, offset| Point {
+            path: path.into(),
+            offset,
+        };
+        assert_eq!(
+            editor.index_range(hit..hit + "bolder".len()),
+            Some(Range::new(point(vec![1, 0], 5), point(vec![1, 2, 0], 2)))
+        );
+        assert_eq!(editor.index_range(0..100), None);
+    }
+}
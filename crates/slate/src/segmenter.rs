@@ -0,0 +1,350 @@
+use std::fmt;
+use std::sync::Arc;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::types::{Editor, Path, Point, Range};
+
+/// Splits text into words and sentences, for moving, selecting and deleting
+/// by those units.
+///
+/// The default follows the rules of Unicode Standard Annex #29, which don't
+/// find the words of languages written without spaces, like Thai or Japanese.
+/// With the `icu` feature, [`IcuSegmenter`] uses dictionaries for those.
+pub trait Segmenter: Send + Sync {
+    /// Get the offsets in `text` between words, and between words and the
+    /// spaces and punctuation around them, including 0 and the length of
+    /// the text.
+    fn word_boundaries(&self, text: &str) -> Vec<usize>;
+
+    /// Get the offsets in `text` between sentences, including 0 and the
+    /// length of the text.
+    fn sentence_boundaries(&self, text: &str) -> Vec<usize>;
+}
+
+/// Segments text by the default rules of the `unicode-segmentation` crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodeSegmenter;
+
+impl Segmenter for UnicodeSegmenter {
+    fn word_boundaries(&self, text: &str) -> Vec<usize> {
+        let starts = text.split_word_bound_indices().map(|(i, _)| i);
+        starts.chain(Some(text.len())).collect()
+    }
+
+    fn sentence_boundaries(&self, text: &str) -> Vec<usize> {
+        let starts = text.split_sentence_bound_indices().map(|(i, _)| i);
+        starts.chain(Some(text.len())).collect()
+    }
+}
+
+/// Segments text with ICU4X, which finds words in languages written without
+/// spaces.
+#[cfg(feature = "icu")]
+pub struct IcuSegmenter {
+    words: icu_segmenter::WordSegmenter,
+    sentences: icu_segmenter::SentenceSegmenter,
+}
+
+#[cfg(feature = "icu")]
+impl IcuSegmenter {
+    pub fn new() -> Self {
+        Self {
+            words: icu_segmenter::WordSegmenter::new_auto(),
+            sentences: icu_segmenter::SentenceSegmenter::new(),
+        }
+    }
+}
+
+#[cfg(feature = "icu")]
+impl Default for IcuSegmenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "icu")]
+impl fmt::Debug for IcuSegmenter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("IcuSegmenter")
+    }
+}
+
+#[cfg(feature = "icu")]
+impl Segmenter for IcuSegmenter {
+    fn word_boundaries(&self, text: &str) -> Vec<usize> {
+        self.words.segment_str(text).collect()
+    }
+
+    fn sentence_boundaries(&self, text: &str) -> Vec<usize> {
+        self.sentences.segment_str(text).collect()
+    }
+}
+
+/// The segmenter an editor was configured with.
+#[derive(Clone)]
+pub(crate) struct SegmenterHandle(Arc<dyn Segmenter>);
+
+impl Default for SegmenterHandle {
+    fn default() -> Self {
+        Self(Arc::new(UnicodeSegmenter))
+    }
+}
+
+impl fmt::Debug for SegmenterHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Segmenter")
+    }
+}
+
+// The segmenter only describes how text is split up, so it never makes two
+// editors unequal.
+impl PartialEq for SegmenterHandle {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// A unit text is segmented into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Word,
+    Sentence,
+}
+
+impl Segment {
+    /// Whether a segment counts as one of this unit. Only segments with a
+    /// letter or digit are words, so moving by words skips over spaces and
+    /// punctuation.
+    fn counts(self, segment: &str) -> bool {
+        match self {
+            Segment::Word => segment.chars().any(char::is_alphanumeric),
+            Segment::Sentence => !segment.trim().is_empty(),
+        }
+    }
+}
+
+impl Editor {
+    /// Split text into words and sentences with `segmenter` instead of the
+    /// default Unicode rules.
+    pub fn with_segmenter(mut self, segmenter: impl Segmenter + 'static) -> Self {
+        self.segmenter = Box::new(SegmenterHandle(Arc::new(segmenter)));
+        self
+    }
+
+    /// Get the range of the word at a point, as selected by double-clicking
+    /// there. A point between a word and something else is in the word, and
+    /// elsewhere the range is of the spaces or punctuation at the point.
+    pub fn word_range_at(&self, point: &Point) -> Option<Range> {
+        let (block, offset) = self.block_offset(point)?;
+        let segments = self.segments(&block, Segment::Word)?;
+
+        let (start, end) = segments
+            .iter()
+            .find(|(start, end, word)| *word && *start <= offset && offset <= *end)
+            .or_else(|| segments.iter().find(|(_, end, _)| offset < *end))
+            .map(|(start, end, _)| (*start, *end))?;
+
+        Some(Range::new(
+            self.point_at_offset(&block, start)?,
+            self.point_at_offset(&block, end)?,
+        ))
+    }
+
+    /// Get the range of the block containing a point, as selected by
+    /// triple-clicking there.
+    pub fn block_range_at(&self, point: &Point) -> Option<Range> {
+        self.range(self.block_path(&point.path)?)
+    }
+
+    /// Delete the selected content, or from a collapsed selection to the
+    /// start of the word before it or the end of the word after it.
+    pub fn delete_word(&mut self, reverse: bool) {
+        let selection = match &self.selection {
+            Some(selection) => selection.clone(),
+            None => return,
+        };
+        if selection.is_expanded() {
+            self.delete_range(&selection);
+            return;
+        }
+
+        let caret = selection.anchor().clone();
+        let to = match self.segment_position(&caret, Segment::Word, reverse) {
+            Some(to) => to,
+            None => return,
+        };
+        if self.above_isolating(&to.path) == self.above_isolating(&caret.path) {
+            self.delete_range(&Range::new(caret, to));
+        }
+    }
+
+    /// Find the point a unit away from a point: the end of the next one, or
+    /// the start of the previous one in `reverse`. At the edge of a block, the
+    /// point moves into the next one.
+    pub(crate) fn segment_position(
+        &self,
+        point: &Point,
+        unit: Segment,
+        reverse: bool,
+    ) -> Option<Point> {
+        let (block, offset) = self.block_offset(point)?;
+        let segments = self.segments(&block, unit)?;
+
+        let target = if reverse {
+            segments
+                .iter()
+                .rev()
+                .find(|(start, _, counts)| *counts && *start < offset)
+                .map_or(0, |(start, _, _)| *start)
+        } else {
+            segments
+                .iter()
+                .find(|(_, end, counts)| *counts && *end > offset)
+                .map_or_else(|| segments.last().map_or(0, |s| s.1), |(_, end, _)| *end)
+        };
+
+        if target == offset {
+            return self.positions(point, reverse).next();
+        }
+        self.point_at_offset(&block, target)
+    }
+
+    /// Split the text of a block into units, as their start and end offsets
+    /// and whether they count as a unit.
+    fn segments(&self, block: &Path, unit: Segment) -> Option<Vec<(usize, usize, bool)>> {
+        let text = self.block_text(block)?;
+        let SegmenterHandle(segmenter) = self.segmenter.as_ref();
+        let mut bounds = match unit {
+            Segment::Word => segmenter.word_boundaries(&text),
+            Segment::Sentence => segmenter.sentence_boundaries(&text),
+        };
+        bounds.retain(|b| *b <= text.len() && text.is_char_boundary(*b));
+        bounds.extend([0, text.len()].iter());
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        Some(
+            bounds
+                .windows(2)
+                .map(|w| (w[0], w[1], unit.counts(&text[w[0]..w[1]])))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn caret(path: Vec<usize>, offset: usize) -> Range {
+        Range::new(point(path.clone(), offset), point(path, offset))
+    }
+
+    /// Treats every character as a word.
+    struct Characters;
+
+    impl Segmenter for Characters {
+        fn word_boundaries(&self, text: &str) -> Vec<usize> {
+            text.char_indices()
+                .map(|(i, _)| i)
+                .chain(Some(text.len()))
+                .collect()
+        }
+
+        fn sentence_boundaries(&self, text: &str) -> Vec<usize> {
+            vec![0, text.len()]
+        }
+    }
+
+    #[test]
+    fn word_at_point() {
+        let editor = Editor::new().add_child(Element::new().add_child(Text::new("one, two three")));
+
+        assert_eq!(
+            editor.word_range_at(&point(vec![0, 0], 6)),
+            Some(Range::new(point(vec![0, 0], 5), point(vec![0, 0], 8)))
+        );
+        assert_eq!(
+            editor.word_range_at(&point(vec![0, 0], 3)),
+            Some(Range::new(point(vec![0, 0], 0), point(vec![0, 0], 3)))
+        );
+
+        let editor = editor.with_segmenter(Characters);
+        assert_eq!(
+            editor.word_range_at(&point(vec![0, 0], 6)),
+            Some(Range::new(point(vec![0, 0], 5), point(vec![0, 0], 6)))
+        );
+    }
+
+    #[test]
+    fn block_range_at_point() {
+        let editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Element::with_kind("link").add_child(Text::new("b")))
+                .add_child(Text::new("c")),
+        );
+
+        assert_eq!(
+            editor.block_range_at(&point(vec![0, 1, 0], 1)),
+            Some(Range::new(point(vec![0, 0], 0), point(vec![0, 2], 1)))
+        );
+        assert_eq!(editor.block_range_at(&point(vec![1, 0], 0)), None);
+    }
+
+    #[test]
+    fn delete_word_skips_punctuation() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("one, two")));
+        editor.select(point(vec![0, 0], 8));
+
+        editor.delete_word(true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 5)));
+        editor.delete_word(true);
+        assert_eq!(
+            editor.children(),
+            vec![Element::new().add_child(Text::new("")).into()]
+        );
+    }
+
+    #[test]
+    fn segment_position_crosses_blocks() {
+        let editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("a b")))
+            .add_child(Element::new().add_child(Text::new("c")));
+
+        assert_eq!(
+            editor.segment_position(&point(vec![0, 0], 1), Segment::Word, false),
+            Some(point(vec![0, 0], 3))
+        );
+        assert_eq!(
+            editor.segment_position(&point(vec![0, 0], 3), Segment::Word, false),
+            Some(point(vec![1, 0], 0))
+        );
+        assert_eq!(
+            editor.segment_position(&point(vec![0, 0], 0), Segment::Sentence, false),
+            Some(point(vec![0, 0], 3))
+        );
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn icu_finds_words_without_spaces() {
+        let text = "\u{e20}\u{e32}\u{e29}\u{e32}\u{e44}\u{e17}\u{e22}";
+        let editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new(text)))
+            .with_segmenter(IcuSegmenter::new());
+
+        let word = editor.word_range_at(&point(vec![0, 0], 0)).unwrap();
+        assert_eq!(word.focus().offset, "\u{e20}\u{e32}\u{e29}\u{e32}".len());
+    }
+}
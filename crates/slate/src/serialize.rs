@@ -0,0 +1,96 @@
+//! Conversions between documents and external formats.
+
+pub mod csv;
+#[cfg(feature = "docx")]
+pub mod docx;
+pub mod html;
+pub mod json;
+pub mod legacy;
+pub mod pandoc;
+pub mod plain;
+
+/// How whitespace in imported text is handled. The default is how browsers
+/// render HTML: runs of whitespace are one space, except in code, and there's
+/// none at the edges of blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhitespaceOptions {
+    /// Whether each run of whitespace becomes a single space, including runs
+    /// split between texts with different marks. Soft breaks are kept.
+    pub collapse: bool,
+    /// Whether whitespace in code is kept as it is, even when collapsing.
+    pub preserve_in_code: bool,
+    /// Whether whitespace at the start and end of blocks is removed. Soft
+    /// breaks are kept.
+    pub trim_block_edges: bool,
+}
+
+impl Default for WhitespaceOptions {
+    fn default() -> Self {
+        Self {
+            collapse: true,
+            preserve_in_code: true,
+            trim_block_edges: true,
+        }
+    }
+}
+
+impl WhitespaceOptions {
+    /// Keep all whitespace as it is.
+    pub fn preserve() -> Self {
+        Self {
+            collapse: false,
+            preserve_in_code: true,
+            trim_block_edges: false,
+        }
+    }
+
+    pub fn collapse(mut self, collapse: bool) -> Self {
+        self.collapse = collapse;
+        self
+    }
+
+    pub fn preserve_in_code(mut self, preserve: bool) -> Self {
+        self.preserve_in_code = preserve;
+        self
+    }
+
+    pub fn trim_block_edges(mut self, trim: bool) -> Self {
+        self.trim_block_edges = trim;
+        self
+    }
+
+    /// Whether whitespace is collapsed in content that may be code.
+    fn collapses(&self, code: bool) -> bool {
+        self.collapse && !(code && self.preserve_in_code)
+    }
+
+    /// Whether block edges are trimmed in content that may be code.
+    fn trims(&self, code: bool) -> bool {
+        self.trim_block_edges && !(code && self.preserve_in_code)
+    }
+}
+
+/// Whether a character is whitespace that HTML collapses, which doesn't
+/// include non-breaking spaces.
+fn collapsible(c: char) -> bool {
+    c.is_ascii_whitespace()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut space = false;
+
+    for c in text.chars() {
+        if collapsible(c) {
+            if !space {
+                out.push(' ');
+            }
+            space = true;
+        } else {
+            out.push(c);
+            space = false;
+        }
+    }
+
+    out
+}
@@ -0,0 +1,207 @@
+//! CSV, for exchanging tables with spreadsheets.
+//!
+//! A table is an element of kind `table` holding `table-row` elements, which
+//! hold `table-cell` elements. Each row is a line and each cell a field.
+
+use super::{html, plain};
+use crate::types::{Descendant, Element, Text};
+
+/// How tables are written to and read from CSV.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// The character separating fields.
+    pub delimiter: char,
+    /// Whether cells are plain text. Otherwise they are written and read as
+    /// HTML, which keeps their marks and structure.
+    pub strip_marks: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            strip_marks: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn strip_marks(mut self, strip: bool) -> Self {
+        self.strip_marks = strip;
+        self
+    }
+}
+
+/// Serialize the rows of a table to CSV. Children that aren't rows or cells
+/// are skipped.
+pub fn to_string(table: &Element, options: &CsvOptions) -> String {
+    let mut out = String::new();
+
+    for row in table.children_ref() {
+        let row = match row {
+            Descendant::Element(row) if row.kind() == "table-row" => row,
+            _ => continue,
+        };
+
+        let fields: Vec<_> = row
+            .children_ref()
+            .iter()
+            .filter_map(|cell| match cell {
+                Descendant::Element(cell) if cell.kind() == "table-cell" => {
+                    let children = cell.children();
+                    let text = if options.strip_marks {
+                        plain::to_string(&children)
+                    } else {
+                        html::to_string(&children)
+                    };
+                    Some(quote(&text, options.delimiter))
+                }
+                _ => None,
+            })
+            .collect();
+
+        out.push_str(&fields.join(&options.delimiter.to_string()));
+        out.push('\n');
+    }
+
+    out
+}
+
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Deserialize CSV into a table. Rows shorter than the longest are padded with
+/// empty cells.
+pub fn from_string(csv: &str, options: &CsvOptions) -> Element {
+    let rows = parse(csv, options.delimiter);
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut table = Element::with_kind("table");
+
+    for fields in rows {
+        let mut row = Element::with_kind("table-row");
+
+        for i in 0..width {
+            let field = fields.get(i).map_or("", String::as_str);
+            let mut cell = Element::with_kind("table-cell");
+
+            if options.strip_marks || field.is_empty() {
+                cell = cell.add_child(Text::new(field));
+            } else {
+                for block in html::from_string(field) {
+                    cell = cell.add_child(block);
+                }
+            }
+
+            row = row.add_child(cell);
+        }
+
+        table = table.add_child(row);
+    }
+
+    table
+}
+
+/// Split CSV into rows of fields, unquoting quoted fields. A line ending
+/// inside quotes is part of the field.
+fn parse(csv: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Marks;
+
+    fn table(rows: &[&[Descendant]]) -> Element {
+        rows.iter()
+            .fold(Element::with_kind("table"), |table, cells| {
+                table.add_child(
+                    cells
+                        .iter()
+                        .fold(Element::with_kind("table-row"), |row, cell| {
+                            row.add_child(Element::with_kind("table-cell").add_child(cell.clone()))
+                        }),
+                )
+            })
+    }
+
+    #[test]
+    fn round_trip() {
+        let table = table(&[
+            &[Text::new("a,b").into(), Text::new("say \"hi\"").into()],
+            &[Text::new("two\nlines").into(), Text::new("").into()],
+        ]);
+        let csv = to_string(&table, &CsvOptions::default());
+
+        assert_eq!(csv, "\"a,b\",\"say \"\"hi\"\"\"\n\"two\nlines\",\n");
+        assert_eq!(from_string(&csv, &CsvOptions::default()), table);
+    }
+
+    #[test]
+    fn strip_marks_and_delimiter() {
+        let table = table(&[&[
+            Text::with_marks("bold", Marks::BOLD).into(),
+            Text::new("a,b").into(),
+        ]]);
+
+        assert_eq!(to_string(&table, &CsvOptions::default()), "bold,\"a,b\"\n");
+
+        let options = CsvOptions::default().delimiter(';').strip_marks(false);
+        assert_eq!(to_string(&table, &options), "<strong>bold</strong>;a,b\n");
+    }
+
+    #[test]
+    fn pads_short_rows() {
+        let table = from_string("a,b\r\nc\r\n", &CsvOptions::default());
+        let empty = Element::with_kind("table-cell").add_child(Text::new(""));
+        assert_eq!(
+            table.child(1),
+            Some(
+                &Element::with_kind("table-row")
+                    .add_child(Element::with_kind("table-cell").add_child(Text::new("c")))
+                    .add_child(empty)
+                    .into()
+            )
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Word documents, written as Office Open XML.
+//!
+//! Paragraphs, headings, quotes, lists and tables keep their structure, and
+//! bold, italic and underline are kept as run properties. Other elements are
+//! written as their content.
+
+use std::io::{Cursor, Write};
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::html::escape;
+use crate::types::{has_inlines, Descendant, Editor, Element, Location, Marks, Text};
+use crate::{Direction, DIRECTION_PROPERTY};
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/><Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/><Override PartName="/word/numbering.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml"/></Types>"#;
+
+const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+const DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering" Target="numbering.xml"/></Relationships>"#;
+
+const NAMESPACE: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+/// Element kinds and the paragraph styles they are written with.
+const STYLES: &[(&str, &str, &str)] = &[
+    ("heading-one", "Heading1", "heading 1"),
+    ("heading-two", "Heading2", "heading 2"),
+    ("heading-three", "Heading3", "heading 3"),
+    ("heading-four", "Heading4", "heading 4"),
+    ("heading-five", "Heading5", "heading 5"),
+    ("heading-six", "Heading6", "heading 6"),
+    ("block-quote", "Quote", "Quote"),
+    ("code-block", "Code", "Code"),
+];
+
+/// Marks and the run properties they are written as.
+const MARKS: &[(Marks, &str)] = &[
+    (Marks::BOLD, "<w:b/>"),
+    (Marks::ITALIC, "<w:i/>"),
+    (Marks::UNDERLINE, r#"<w:u w:val="single"/>"#),
+];
+
+/// Serialize the part of a document in a location to the bytes of a `.docx`
+/// file, with the text at its edges clipped to it.
+pub fn to_bytes_at(editor: &Editor, at: impl Into<Location>) -> Vec<u8> {
+    to_bytes(&editor.fragment(at))
+}
+
+/// Serialize nodes to the bytes of a `.docx` file.
+pub fn to_bytes(nodes: &[Descendant]) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.write_blocks(nodes, None, 0);
+
+    let document = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="{}"><w:body>{}<w:sectPr/></w:body></w:document>"#,
+        NAMESPACE, writer.body
+    );
+
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", RELS.to_string()),
+        ("word/_rels/document.xml.rels", DOCUMENT_RELS.to_string()),
+        ("word/document.xml", document),
+        ("word/styles.xml", styles()),
+        ("word/numbering.xml", writer.numbering()),
+    ];
+
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, content) in parts.iter() {
+        zip.start_file(*name, options)
+            .and_then(|_| Ok(zip.write_all(content.as_bytes())?))
+            .expect("writing to memory to succeed");
+    }
+
+    zip.finish()
+        .expect("writing to memory to succeed")
+        .into_inner()
+}
+
+fn styles() -> String {
+    let mut out = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="{}"><w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/></w:style>"#,
+        NAMESPACE
+    );
+
+    for (_, id, name) in STYLES {
+        out.push_str(&format!(
+            r#"<w:style w:type="paragraph" w:styleId="{}"><w:name w:val="{}"/><w:basedOn w:val="Normal"/><w:qFormat/></w:style>"#,
+            id, name
+        ));
+    }
+
+    out.push_str("</w:styles>");
+    out
+}
+
+#[derive(Default)]
+struct Writer {
+    body: String,
+    /// Whether each list written is numbered, in the order of their ids.
+    lists: Vec<bool>,
+    /// Whether the blocks being written are right to left, as set by the
+    /// direction property of an enclosing element.
+    rtl: bool,
+}
+
+impl Writer {
+    /// Write blocks, in the paragraph style of an enclosing element if they
+    /// have none of their own. `depth` is how many lists they're nested in.
+    fn write_blocks(&mut self, nodes: &[Descendant], style: Option<&str>, depth: usize) {
+        if has_inlines(nodes) {
+            self.write_paragraph(nodes, style, None);
+            return;
+        }
+
+        for node in nodes {
+            if let Descendant::Element(e) = node {
+                self.write_block(e, style, depth);
+            }
+        }
+    }
+
+    fn write_block(&mut self, element: &Element, style: Option<&str>, depth: usize) {
+        let rtl = self.rtl;
+        if let Some(dir) = element
+            .property(DIRECTION_PROPERTY)
+            .and_then(Direction::from_property)
+        {
+            self.rtl = dir == Direction::Rtl;
+        }
+        self.write_block_content(element, style, depth);
+        self.rtl = rtl;
+    }
+
+    fn write_block_content(&mut self, element: &Element, style: Option<&str>, depth: usize) {
+        let children = element.children();
+        let style = STYLES
+            .iter()
+            .find(|(kind, _, _)| *kind == element.kind())
+            .map(|(_, id, _)| *id)
+            .or(style);
+
+        match element.kind() {
+            "bulleted-list" | "numbered-list" => {
+                self.lists.push(element.kind() == "numbered-list");
+                let list = (self.lists.len(), depth);
+
+                for item in &children {
+                    match item {
+                        Descendant::Element(item) if !has_inlines(item.children_ref()) => {
+                            self.write_list_item(&item.children(), style, list);
+                        }
+                        _ => self.write_paragraph(std::slice::from_ref(item), style, Some(list)),
+                    }
+                }
+            }
+            "table" => self.write_table(&children, depth),
+            _ if has_inlines(&children) => self.write_paragraph(&children, style, None),
+            _ => self.write_blocks(&children, style, depth),
+        }
+    }
+
+    /// Write the blocks of a list item. The first is numbered, and nested
+    /// lists are a level deeper.
+    fn write_list_item(
+        &mut self,
+        blocks: &[Descendant],
+        style: Option<&str>,
+        list: (usize, usize),
+    ) {
+        for (i, block) in blocks.iter().enumerate() {
+            match block {
+                Descendant::Element(e) if i == 0 && has_inlines(e.children_ref()) => {
+                    self.write_paragraph(&e.children(), style, Some(list));
+                }
+                Descendant::Element(e) => self.write_block(e, style, list.1 + 1),
+                Descendant::Text(_) => {}
+            }
+        }
+    }
+
+    fn write_table(&mut self, rows: &[Descendant], depth: usize) {
+        self.body
+            .push_str("<w:tbl><w:tblPr><w:tblW w:w=\"0\" w:type=\"auto\"/><w:tblBorders>");
+        for side in ["top", "left", "bottom", "right", "insideH", "insideV"].iter() {
+            self.body
+                .push_str(&format!("<w:{} w:val=\"single\" w:sz=\"4\"/>", side));
+        }
+        self.body.push_str("</w:tblBorders></w:tblPr>");
+
+        for row in rows {
+            let cells = match row {
+                Descendant::Element(row) => row.children(),
+                Descendant::Text(_) => continue,
+            };
+            self.body.push_str("<w:tr>");
+
+            for cell in &cells {
+                self.body.push_str("<w:tc>");
+                if let Descendant::Element(cell) = cell {
+                    self.write_blocks(&cell.children(), None, depth);
+                }
+                // Word requires every cell to end with a paragraph.
+                if !self.body.ends_with("</w:p>") {
+                    self.body.push_str("<w:p/>");
+                }
+                self.body.push_str("</w:tc>");
+            }
+
+            self.body.push_str("</w:tr>");
+        }
+
+        self.body.push_str("</w:tbl>");
+    }
+
+    /// Write inline content as a paragraph, numbered as an item of a list at
+    /// a level if given.
+    fn write_paragraph(
+        &mut self,
+        inlines: &[Descendant],
+        style: Option<&str>,
+        list: Option<(usize, usize)>,
+    ) {
+        self.body.push_str("<w:p>");
+
+        if style.is_some() || list.is_some() || self.rtl {
+            self.body.push_str("<w:pPr>");
+            if let Some(style) = style {
+                self.body
+                    .push_str(&format!("<w:pStyle w:val=\"{}\"/>", style));
+            }
+            if let Some((id, level)) = list {
+                self.body.push_str(&format!(
+                    "<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>",
+                    level.min(8),
+                    id
+                ));
+            }
+            if self.rtl {
+                self.body.push_str("<w:bidi/>");
+            }
+            self.body.push_str("</w:pPr>");
+        }
+
+        self.write_inlines(inlines);
+        self.body.push_str("</w:p>");
+    }
+
+    fn write_inlines(&mut self, inlines: &[Descendant]) {
+        for node in inlines {
+            match node {
+                Descendant::Text(t) => self.write_run(t),
+                Descendant::Element(e) => self.write_inlines(&e.children()),
+            }
+        }
+    }
+
+    fn write_run(&mut self, text: &Text) {
+        if text.text().is_empty() {
+            return;
+        }
+
+        self.body.push_str("<w:r>");
+
+        let marks: String = MARKS
+            .iter()
+            .filter(|(mark, _)| text.marks().contains(*mark))
+            .map(|(_, xml)| *xml)
+            .collect();
+        if !marks.is_empty() {
+            self.body.push_str(&format!("<w:rPr>{}</w:rPr>", marks));
+        }
+
+        for (i, line) in text.text().split('\n').enumerate() {
+            if i > 0 {
+                self.body.push_str("<w:br/>");
+            }
+            self.body.push_str(&format!(
+                "<w:t xml:space=\"preserve\">{}</w:t>",
+                escape(line)
+            ));
+        }
+
+        self.body.push_str("</w:r>");
+    }
+
+    /// The numbering part, with a bulleted and a numbered definition and an
+    /// instance of one of them for each list, so that every numbered list
+    /// starts from one.
+    fn numbering(&self) -> String {
+        let mut out = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:numbering xmlns:w="{}">"#,
+            NAMESPACE
+        );
+
+        for (id, numbered) in [(0, false), (1, true)].iter() {
+            out.push_str(&format!("<w:abstractNum w:abstractNumId=\"{}\">", id));
+            for level in 0..9 {
+                let (format, text) = if *numbered {
+                    ("decimal", format!("%{}.", level + 1))
+                } else {
+                    ("bullet", "\u{2022}".to_string())
+                };
+                out.push_str(&format!(
+                    r#"<w:lvl w:ilvl="{0}"><w:start w:val="1"/><w:numFmt w:val="{1}"/><w:lvlText w:val="{2}"/><w:lvlJc w:val="left"/><w:pPr><w:ind w:left="{3}" w:hanging="360"/></w:pPr></w:lvl>"#,
+                    level,
+                    format,
+                    text,
+                    720 * (level + 1)
+                ));
+            }
+            out.push_str("</w:abstractNum>");
+        }
+
+        for (i, numbered) in self.lists.iter().enumerate() {
+            out.push_str(&format!(
+                r#"<w:num w:numId="{}"><w:abstractNumId w:val="{}"/>"#,
+                i + 1,
+                *numbered as usize
+            ));
+            if *numbered {
+                for level in 0..9 {
+                    out.push_str(&format!(
+                        r#"<w:lvlOverride w:ilvl="{}"><w:startOverride w:val="1"/></w:lvlOverride>"#,
+                        level
+                    ));
+                }
+            }
+            out.push_str("</w:num>");
+        }
+
+        out.push_str("</w:numbering>");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    fn part(docx: &[u8], name: &str) -> String {
+        let mut archive = ZipArchive::new(Cursor::new(docx)).unwrap();
+        let mut out = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn paragraphs_and_marks() {
+        let docx = to_bytes(&[
+            Element::with_kind("heading-one")
+                .add_child(Text::new("Title"))
+                .into(),
+            Element::new()
+                .add_child(Text::new("a "))
+                .add_child(Text::with_marks("<b>", Marks::BOLD | Marks::UNDERLINE))
+                .into(),
+        ]);
+        let document = part(&docx, "word/document.xml");
+
+        assert!(document.contains(
+            r#"<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t xml:space="preserve">Title</w:t></w:r></w:p>"#
+        ));
+        assert!(document.contains(
+            r#"<w:r><w:rPr><w:b/><w:u w:val="single"/></w:rPr><w:t xml:space="preserve">&lt;b&gt;</w:t></w:r>"#
+        ));
+        assert!(part(&docx, "word/styles.xml").contains(r#"w:styleId="Heading1""#));
+    }
+
+    #[test]
+    fn lists() {
+        let item = |text: &str| Element::with_kind("list-item").add_child(Text::new(text));
+        let docx = to_bytes(&[
+            Element::with_kind("bulleted-list")
+                .add_child(item("a"))
+                .add_child(
+                    Element::with_kind("list-item")
+                        .add_child(Element::new().add_child(Text::new("b")))
+                        .add_child(Element::with_kind("numbered-list").add_child(item("c"))),
+                )
+                .into(),
+            Element::with_kind("numbered-list")
+                .add_child(item("d"))
+                .into(),
+        ]);
+        let document = part(&docx, "word/document.xml");
+        let numbered = |text: &str, level: usize, id: usize| {
+            format!(
+                r#"<w:p><w:pPr><w:numPr><w:ilvl w:val="{}"/><w:numId w:val="{}"/></w:numPr></w:pPr><w:r><w:t xml:space="preserve">{}</w:t></w:r></w:p>"#,
+                level, id, text
+            )
+        };
+
+        assert!(document.contains(&numbered("a", 0, 1)));
+        assert!(document.contains(&numbered("b", 0, 1)));
+        assert!(document.contains(&numbered("c", 1, 2)));
+        assert!(document.contains(&numbered("d", 0, 3)));
+
+        let numbering = part(&docx, "word/numbering.xml");
+        assert!(numbering.contains(r#"<w:num w:numId="3"><w:abstractNumId w:val="1"/>"#));
+    }
+
+    #[test]
+    fn tables() {
+        let cell = |text: &str| Element::with_kind("table-cell").add_child(Text::new(text));
+        let docx = to_bytes(&[Element::with_kind("table")
+            .add_child(
+                Element::with_kind("table-row")
+                    .add_child(cell("a"))
+                    .add_child(Element::with_kind("table-cell")),
+            )
+            .into()]);
+        let document = part(&docx, "word/document.xml");
+
+        assert!(document.contains(
+            r#"<w:tr><w:tc><w:p><w:r><w:t xml:space="preserve">a</w:t></w:r></w:p></w:tc><w:tc><w:p/></w:tc></w:tr>"#
+        ));
+    }
+
+    #[test]
+    fn right_to_left_paragraphs() {
+        let docx = to_bytes(&[Element::with_kind("block-quote")
+            .add_property("dir", "rtl")
+            .add_child(Element::new().add_child(Text::new("a")))
+            .add_child(
+                Element::new()
+                    .add_property("dir", "ltr")
+                    .add_child(Text::new("b")),
+            )
+            .into()]);
+        let document = part(&docx, "word/document.xml");
+
+        assert!(document.contains(
+            r#"<w:p><w:pPr><w:pStyle w:val="Quote"/><w:bidi/></w:pPr><w:r><w:t xml:space="preserve">a</w:t>"#
+        ));
+        assert!(document.contains(
+            r#"<w:p><w:pPr><w:pStyle w:val="Quote"/></w:pPr><w:r><w:t xml:space="preserve">b</w:t>"#
+        ));
+    }
+}
@@ -0,0 +1,1385 @@
+//! HTML, using semantic tags for the standard element kinds and marks.
+
+use std::collections::BTreeMap;
+
+use super::{collapse_whitespace, collapsible, WhitespaceOptions};
+use crate::plugins::{emoji, footnotes, math};
+use crate::types::{
+    has_inlines, ContentHash, Descendant, Editor, Element, Location, MarkRegistry, Marks, Path,
+    Text, BACKGROUND_MARK, COLOR_MARK,
+};
+use crate::{
+    Alignment, Direction, ALIGN_PROPERTY, DIRECTION_PROPERTY, INDENT_PROPERTY, MAX_INDENT,
+};
+
+/// Valued marks and the CSS properties they are serialized as.
+const STYLES: &[(&str, &str)] = &[(COLOR_MARK, "color"), (BACKGROUND_MARK, "background-color")];
+
+/// Element kinds and the tags they are serialized as.
+const ELEMENTS: &[(&str, &str)] = &[
+    ("paragraph", "p"),
+    ("heading-one", "h1"),
+    ("heading-two", "h2"),
+    ("heading-three", "h3"),
+    ("heading-four", "h4"),
+    ("heading-five", "h5"),
+    ("heading-six", "h6"),
+    ("block-quote", "blockquote"),
+    ("bulleted-list", "ul"),
+    ("numbered-list", "ol"),
+    ("list-item", "li"),
+    ("code-block", "pre"),
+    ("link", "a"),
+];
+
+/// Marks and the tags they are serialized as, outermost first.
+const MARKS: &[(Marks, &str)] = &[
+    (Marks::BOLD, "strong"),
+    (Marks::ITALIC, "em"),
+    (Marks::UNDERLINE, "u"),
+    (Marks::CODE, "code"),
+];
+
+/// How wide each level of indentation of a block is, in ems.
+const INDENT_EM: usize = 2;
+
+/// Tags that never have children.
+const VOID_TAGS: &[&str] = &["br", "hr", "img", "input", "link", "meta", "wbr"];
+
+/// Element kinds that are void, with an empty text as their only child.
+pub(crate) const VOID_KINDS: &[&str] = &[
+    footnotes::REFERENCE,
+    math::INLINE,
+    math::BLOCK,
+    emoji::EMOJI,
+];
+
+/// Tags that implicitly close an open `p`, as in browsers.
+const BLOCK_TAGS: &[&str] = &[
+    "blockquote",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "ul",
+];
+
+/// Tags whose content is never part of the document.
+const SKIPPED_TAGS: &[&str] = &["head", "script", "style", "template", "title"];
+
+/// Serialize nodes to HTML.
+pub fn to_string(nodes: &[Descendant]) -> String {
+    to_string_with(nodes, &MarkRegistry::default())
+}
+
+/// A change to the HTML of a document's blocks, for updating a rendering of
+/// it without rendering it all again. Patches are applied in order, like
+/// operations, so each path is in the document as the patches before it left
+/// it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlPatch {
+    /// Replace the HTML of the top-level block at a path.
+    Replace { path: Path, html: String },
+    /// Insert the HTML of a new top-level block at a path.
+    Insert { path: Path, html: String },
+    /// Remove the top-level block at a path.
+    Remove { path: Path },
+}
+
+/// Get the patches that turn the HTML of `prev` into that of `next`.
+///
+/// Blocks are compared by their content hashes, which elements cache, and
+/// the longest run of blocks both have in order is left alone, so edits far
+/// apart only render the blocks they touch. Between the blocks left alone,
+/// each new block replaces one of `prev`, and those left over are removed or
+/// inserted.
+pub fn diff_render(prev: &[Descendant], next: &[Descendant]) -> Vec<HtmlPatch> {
+    let prev: Vec<_> = prev.iter().map(|b| (b.content_hash(), b)).collect();
+    let next: Vec<_> = next.iter().map(|b| (b.content_hash(), b)).collect();
+    let html = |block: &Descendant| to_string(std::slice::from_ref(block));
+    let path = |i: usize| Path::new(vec![i]);
+
+    let mut patches = vec![];
+    let mut at = 0;
+    let (mut i, mut j) = (0, 0);
+    for (kept_i, kept_j) in common_blocks(&prev, &next) {
+        let paired = (kept_i - i).min(kept_j - j);
+        for (offset, (_, block)) in next[j..j + paired].iter().enumerate() {
+            patches.push(HtmlPatch::Replace {
+                path: path(at + offset),
+                html: html(block),
+            });
+        }
+        at += paired;
+        patches.extend((i + paired..kept_i).map(|_| HtmlPatch::Remove { path: path(at) }));
+        for (_, block) in &next[j + paired..kept_j] {
+            patches.push(HtmlPatch::Insert {
+                path: path(at),
+                html: html(block),
+            });
+            at += 1;
+        }
+        // Step over the block left alone.
+        at += 1;
+        i = kept_i + 1;
+        j = kept_j + 1;
+    }
+    patches
+}
+
+/// Get the indexes of the longest run of blocks `prev` and `next` both have
+/// in order, as pairs of their indexes in each, ending with the pair past
+/// the end of both.
+fn common_blocks<T>(prev: &[(ContentHash, T)], next: &[(ContentHash, T)]) -> Vec<(usize, usize)> {
+    // Blocks equal at the start and end are common, and the ones between
+    // are usually few, so only they need the quadratic table.
+    let prefix = prev
+        .iter()
+        .zip(next)
+        .take_while(|(a, b)| a.0 == b.0)
+        .count();
+    let suffix = prev[prefix..]
+        .iter()
+        .rev()
+        .zip(next[prefix..].iter().rev())
+        .take_while(|(a, b)| a.0 == b.0)
+        .count();
+    let a = &prev[prefix..prev.len() - suffix];
+    let b = &next[prefix..next.len() - suffix];
+
+    // `lengths[i][j]` is the length of the longest common run of `a[i..]`
+    // and `b[j..]`.
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i].0 == b[j].0 {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut common: Vec<_> = (0..prefix).map(|i| (i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].0 == b[j].0 {
+            common.push((prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    common.extend((0..=suffix).map(|k| (prev.len() - suffix + k, next.len() - suffix + k)));
+    common
+}
+
+/// Serialize the part of a document in a location to HTML, with the text at
+/// its edges clipped to it, as when copying the selection.
+pub fn to_string_at(editor: &Editor, at: impl Into<Location>) -> String {
+    to_string(&editor.fragment(at))
+}
+
+/// Serialize nodes to HTML, writing marks without a semantic tag as spans
+/// named by `registry`.
+pub fn to_string_with(nodes: &[Descendant], registry: &MarkRegistry) -> String {
+    let mut out = String::new();
+    write_nodes(nodes, false, registry, &mut out);
+    out
+}
+
+fn write_nodes<'a>(
+    nodes: impl IntoIterator<Item = &'a Descendant> + Copy,
+    inline: bool,
+    registry: &MarkRegistry,
+    out: &mut String,
+) {
+    let inline = inline || has_inlines(nodes);
+
+    for node in nodes {
+        match node {
+            Descendant::Text(t) => write_text(t, registry, out),
+            Descendant::Element(e) => write_element(e, inline, registry, out),
+        }
+    }
+}
+
+fn write_text(text: &Text, registry: &MarkRegistry, out: &mut String) {
+    let marks: Vec<_> = MARKS
+        .iter()
+        .filter(|(mark, _)| text.marks().contains(*mark))
+        .collect();
+    let custom: Vec<_> = registry
+        .iter()
+        .filter(|(mark, _)| text.marks().contains(*mark))
+        .filter(|(mark, _)| !MARKS.iter().any(|(m, _)| m == mark))
+        .map(|(_, name)| name)
+        .collect();
+
+    let style: Vec<_> = STYLES
+        .iter()
+        .filter_map(|(mark, property)| {
+            let value = text.mark_value(mark)?;
+            Some(format!("{}: {}", property, escape(value)))
+        })
+        .collect();
+
+    for (_, tag) in marks.iter() {
+        out.push_str(&format!("<{}>", tag));
+    }
+    for name in custom.iter() {
+        out.push_str(&format!("<span data-mark=\"{}\">", escape(name)));
+    }
+    if !style.is_empty() {
+        out.push_str(&format!("<span style=\"{}\">", style.join("; ")));
+    }
+
+    // Soft breaks are line breaks, as `br`s are read.
+    for (i, line) in text.text().split('\n').enumerate() {
+        if i > 0 {
+            out.push_str("<br>");
+        }
+        out.push_str(&escape(line));
+    }
+
+    if !style.is_empty() {
+        out.push_str("</span>");
+    }
+    for _ in custom.iter() {
+        out.push_str("</span>");
+    }
+    for (_, tag) in marks.iter().rev() {
+        out.push_str(&format!("</{}>", tag));
+    }
+}
+
+fn write_element(element: &Element, inline: bool, registry: &MarkRegistry, out: &mut String) {
+    let tag = ELEMENTS
+        .iter()
+        .find(|(kind, _)| *kind == element.kind())
+        .map(|(_, tag)| *tag)
+        .unwrap_or(match element.kind() {
+            footnotes::REFERENCE => "sup",
+            _ if inline => "span",
+            _ => "div",
+        });
+
+    out.push('<');
+    out.push_str(tag);
+
+    if !ELEMENTS.iter().any(|(kind, _)| *kind == element.kind()) {
+        out.push_str(&format!(" data-kind=\"{}\"", escape(element.kind())));
+    }
+
+    let mut style = vec![];
+    for (key, value) in element.properties() {
+        if tag == "a" && key == "url" {
+            out.push_str(&format!(" href=\"{}\"", escape(value)));
+        } else if key == ALIGN_PROPERTY && Alignment::from_property(value).is_some() {
+            style.push(format!("text-align: {}", value));
+        } else if key == INDENT_PROPERTY && value.parse::<usize>().is_ok() {
+            style.push(format!(
+                "margin-left: {}em",
+                value.parse::<usize>().unwrap() * INDENT_EM
+            ));
+        } else if key == DIRECTION_PROPERTY {
+            out.push_str(&format!(" dir=\"{}\"", escape(value)));
+        } else {
+            out.push_str(&format!(" data-{}=\"{}\"", key, escape(value)));
+        }
+    }
+    if !style.is_empty() {
+        out.push_str(&format!(" style=\"{}\"", style.join("; ")));
+    }
+
+    match element.kind() {
+        // References are void, and display their number as a link to their
+        // definition.
+        footnotes::REFERENCE => {
+            let id = element
+                .property(footnotes::REFERENCE_ID)
+                .unwrap_or_default();
+            let number = element.property(footnotes::NUMBER).unwrap_or_default();
+            out.push_str(&format!(
+                "><a href=\"#{}\">{}</a></sup>",
+                escape(id),
+                escape(number)
+            ));
+            return;
+        }
+        footnotes::DEFINITION => {
+            let id = element
+                .property(footnotes::DEFINITION_ID)
+                .unwrap_or_default();
+            out.push_str(&format!(" id=\"{}\"", escape(id)));
+        }
+        // Emoji are void, and display the emoji.
+        emoji::EMOJI => {
+            let text = element.property(emoji::TEXT).unwrap_or_default();
+            out.push_str(&format!(">{}</{}>", escape(text), tag));
+            return;
+        }
+        // Math is void, and displays its source in the delimiters renderers
+        // like MathJax and KaTeX look for.
+        math::INLINE | math::BLOCK => {
+            let tex = element.property(math::SOURCE).unwrap_or_default();
+            let (open, close) = if element.kind() == math::INLINE {
+                ("\\(", "\\)")
+            } else {
+                ("\\[", "\\]")
+            };
+            out.push_str(&format!(">{}{}{}</{}>", open, escape(tex), close, tag));
+            return;
+        }
+        _ => {}
+    }
+
+    out.push('>');
+    write_nodes(element.children_ref(), inline, registry, out);
+    out.push_str(&format!("</{}>", tag));
+}
+
+/// Split an inline style into its properties and values.
+fn declarations(style: &str) -> impl Iterator<Item = (&str, &str)> {
+    style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(property, value)| (property.trim(), value.trim()))
+}
+
+/// Read the valued marks of text from the inline style of a span.
+fn text_style(style: &str) -> Vec<(&'static str, &str)> {
+    declarations(style)
+        .filter(|(_, value)| !value.is_empty())
+        .filter_map(|(property, value)| {
+            let (mark, _) = STYLES.iter().find(|(_, p)| *p == property)?;
+            Some((*mark, value))
+        })
+        .collect()
+}
+
+/// Read the alignment and indentation of a block from its inline style.
+fn block_style(style: &str) -> Vec<(&'static str, String)> {
+    let mut out = vec![];
+
+    for (property, value) in declarations(style) {
+        match property {
+            "text-align" if Alignment::from_property(value).is_some() => {
+                out.push((ALIGN_PROPERTY, value.to_string()));
+            }
+            "margin-left" => {
+                let level = value
+                    .strip_suffix("em")
+                    .and_then(|em| em.trim().parse::<f64>().ok())
+                    .map_or(0, |em| (em / INDENT_EM as f64).round() as usize)
+                    .min(MAX_INDENT);
+                if level > 0 {
+                    out.push((INDENT_PROPERTY, level.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Deserialize HTML into a list of blocks. Unknown tags are treated as
+/// transparent, so their content is kept while the tags themselves are dropped.
+pub fn from_string(html: &str) -> Vec<Descendant> {
+    from_string_with(html, &MarkRegistry::default())
+}
+
+/// Deserialize HTML into a list of blocks, reading spans with a `data-mark`
+/// attribute as the marks named by `registry`.
+pub fn from_string_with(html: &str, registry: &MarkRegistry) -> Vec<Descendant> {
+    from_string_with_whitespace(html, registry, &WhitespaceOptions::default())
+}
+
+/// Deserialize HTML into a list of blocks, handling whitespace in its text
+/// as `whitespace` says rather than as browsers do.
+pub fn from_string_with_whitespace(
+    html: &str,
+    registry: &MarkRegistry,
+    whitespace: &WhitespaceOptions,
+) -> Vec<Descendant> {
+    let dom = parse(html);
+    let context = Context {
+        registry,
+        whitespace,
+    };
+    let items = deserialize(&dom, &Text::new(""), false, &context);
+    blockify(items, false, whitespace)
+}
+
+/// What deserializing depends on besides the nodes at hand.
+struct Context<'a> {
+    registry: &'a MarkRegistry,
+    whitespace: &'a WhitespaceOptions,
+}
+
+/// A node of the minimal DOM built while parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Dom {
+    Element {
+        tag: String,
+        attributes: BTreeMap<String, String>,
+        children: Vec<Dom>,
+    },
+    Text(String),
+}
+
+/// A deserialized node, tagged with whether it is a block.
+enum Item {
+    Block(Descendant),
+    Inline(Descendant),
+}
+
+/// Deserialize nodes into items, giving their text the marks of `format`.
+fn deserialize(nodes: &[Dom], format: &Text, preformatted: bool, context: &Context) -> Vec<Item> {
+    let registry = context.registry;
+    let mut items = vec![];
+
+    for node in nodes {
+        match node {
+            Dom::Text(text) => {
+                let text = if context.whitespace.collapses(preformatted) {
+                    collapse_whitespace(text)
+                } else {
+                    text.clone()
+                };
+                items.push(Item::Inline(format.with_text(text).into()));
+            }
+            Dom::Element {
+                tag,
+                attributes,
+                children,
+            } => {
+                let tag = tag.as_str();
+
+                if tag == "br" {
+                    items.push(Item::Inline(format.with_text("\n").into()));
+                    continue;
+                }
+
+                if let Some((mark, _)) = MARKS.iter().find(|(_, t)| *t == tag) {
+                    // The code of a `pre` is the code block's, not a mark.
+                    let format = match *mark {
+                        Marks::CODE if preformatted => format.clone(),
+                        mark => with_mark(format, mark),
+                    };
+                    items.extend(deserialize(children, &format, preformatted, context));
+                    continue;
+                }
+
+                let mark = match tag {
+                    "b" => Some(Marks::BOLD),
+                    "i" => Some(Marks::ITALIC),
+                    "span" => attributes
+                        .get("data-mark")
+                        .and_then(|name| registry.get(name)),
+                    _ => None,
+                };
+                if let Some(mark) = mark {
+                    let format = with_mark(format, mark);
+                    items.extend(deserialize(children, &format, preformatted, context));
+                    continue;
+                }
+
+                let values = match attributes.get("style") {
+                    Some(style) if tag == "span" && !attributes.contains_key("data-kind") => {
+                        text_style(style)
+                    }
+                    _ => vec![],
+                };
+                if !values.is_empty() {
+                    let format = values.into_iter().fold(format.clone(), |t, (mark, value)| {
+                        t.add_mark_value(mark, value)
+                    });
+                    items.extend(deserialize(children, &format, preformatted, context));
+                    continue;
+                }
+
+                let kind = attributes.get("data-kind").map(String::as_str).or_else(|| {
+                    ELEMENTS
+                        .iter()
+                        .find(|(_, t)| *t == tag)
+                        .map(|(kind, _)| *kind)
+                });
+                let kind = match kind {
+                    Some(kind) => kind,
+                    None => {
+                        items.extend(deserialize(children, format, preformatted, context));
+                        continue;
+                    }
+                };
+
+                let mut element = Element::with_kind(kind);
+                for (key, value) in attributes {
+                    if tag == "a" && key == "href" {
+                        element = element.add_property("url", value.as_str());
+                    } else if key == "dir" && Direction::from_property(value).is_some() {
+                        element = element.add_property(DIRECTION_PROPERTY, value.as_str());
+                    } else if key == "style" {
+                        for (key, value) in block_style(value) {
+                            element = element.add_property(key, value);
+                        }
+                    } else if let Some(key) = key.strip_prefix("data-") {
+                        if key != "kind" {
+                            element = element.add_property(key, value.as_str());
+                        }
+                    }
+                }
+
+                // The content of void elements is only there to display them.
+                if VOID_KINDS.contains(&kind) {
+                    let element = element.add_child(Text::new("")).into();
+                    items.push(if tag == "div" {
+                        Item::Block(element)
+                    } else {
+                        Item::Inline(element)
+                    });
+                    continue;
+                }
+
+                let preformatted = preformatted || tag == "pre";
+                let children = deserialize(children, format, preformatted, context);
+
+                if tag == "a" || tag == "span" {
+                    let children = inlines(children)
+                        .into_iter()
+                        .fold(element, |e, child| e.add_child(child));
+                    items.push(Item::Inline(children.into()));
+                } else {
+                    let children = if children.iter().any(|i| matches!(i, Item::Block(_))) {
+                        blockify(children, preformatted, context.whitespace)
+                    } else {
+                        let mut children = inlines(children);
+                        tidy_whitespace(&mut children, preformatted, context.whitespace);
+                        children
+                    };
+                    items.push(Item::Block(
+                        children
+                            .into_iter()
+                            .fold(element, |e, child| e.add_child(child))
+                            .into(),
+                    ));
+                }
+            }
+        }
+    }
+
+    items
+}
+
+fn with_mark(format: &Text, mark: Marks) -> Text {
+    let mut format = format.clone();
+    *format.marks_mut() |= mark;
+    format
+}
+
+/// Turn a mix of blocks and inline content into blocks, wrapping runs of
+/// inline content in elements of the default kind.
+fn blockify(
+    items: Vec<Item>,
+    preformatted: bool,
+    whitespace: &WhitespaceOptions,
+) -> Vec<Descendant> {
+    let mut blocks = vec![];
+    let mut run = vec![];
+
+    for item in items {
+        match item {
+            Item::Block(block) => {
+                flush_run(&mut run, &mut blocks, preformatted, whitespace);
+                blocks.push(block);
+            }
+            inline => run.push(inline),
+        }
+    }
+
+    flush_run(&mut run, &mut blocks, preformatted, whitespace);
+    blocks
+}
+
+fn flush_run(
+    run: &mut Vec<Item>,
+    blocks: &mut Vec<Descendant>,
+    preformatted: bool,
+    whitespace: &WhitespaceOptions,
+) {
+    let blank = |item: &Item| match item {
+        Item::Inline(Descendant::Text(t)) => t.text().trim().is_empty(),
+        _ => false,
+    };
+
+    // Whitespace between blocks in the source isn't content of either.
+    while run.last().is_some_and(blank) {
+        run.pop();
+    }
+    let start = run
+        .iter()
+        .position(|item| !blank(item))
+        .unwrap_or(run.len());
+    run.drain(..start);
+
+    if !run.is_empty() {
+        let mut children = inlines(std::mem::take(run));
+        tidy_whitespace(&mut children, preformatted, whitespace);
+        blocks.push(
+            children
+                .into_iter()
+                .fold(Element::new(), |e, child| e.add_child(child))
+                .into(),
+        );
+    }
+}
+
+/// Turn items into inline content, joining texts split only by tags without
+/// a meaning of their own, like `br`s.
+fn inlines(items: Vec<Item>) -> Vec<Descendant> {
+    let mut nodes: Vec<Descendant> = vec![];
+    for item in items {
+        let node = match item {
+            Item::Block(n) | Item::Inline(n) => n,
+        };
+        match (nodes.last_mut(), node) {
+            (Some(Descendant::Text(prev)), Descendant::Text(t)) if prev.same_format(&t) => {
+                prev.text_mut().push_str(t.text());
+            }
+            (_, node) => nodes.push(node),
+        }
+    }
+
+    if nodes.is_empty() {
+        nodes.push(Text::new("").into());
+    }
+
+    nodes
+}
+
+/// Collapse whitespace across the texts of a block's inline content, whose
+/// runs have each been collapsed to a space already, and trim it at the
+/// block's edges. Void elements count as content.
+fn tidy_whitespace(nodes: &mut Vec<Descendant>, code: bool, whitespace: &WhitespaceOptions) {
+    let trim = whitespace.trims(code);
+    let edge = |c: char| collapsible(c) && c != '\n';
+
+    if whitespace.collapses(code) {
+        let mut space = trim;
+        for node in nodes.iter_mut() {
+            visit_texts(node, false, &mut |text| match text {
+                Some(text) => {
+                    let text = text.text_mut();
+                    let mut out = String::with_capacity(text.len());
+                    for c in text.chars() {
+                        // Spaces next to soft breaks aren't rendered either.
+                        if c == '\n' && out.ends_with(' ') {
+                            out.pop();
+                        }
+                        if !(c == ' ' && space) {
+                            out.push(c);
+                        }
+                        space = collapsible(c);
+                    }
+                    *text = out;
+                }
+                None => space = false,
+            });
+        }
+    }
+
+    if trim {
+        for reverse in [false, true] {
+            let mut done = false;
+            let mut f = |text: Option<&mut Text>| match text {
+                Some(text) if !done => {
+                    let text = text.text_mut();
+                    *text = if reverse {
+                        text.trim_end_matches(edge).to_string()
+                    } else {
+                        text.trim_start_matches(edge).to_string()
+                    };
+                    done = !text.is_empty();
+                }
+                _ => done = true,
+            };
+            if reverse {
+                nodes
+                    .iter_mut()
+                    .rev()
+                    .for_each(|node| visit_texts(node, true, &mut f));
+            } else {
+                nodes
+                    .iter_mut()
+                    .for_each(|node| visit_texts(node, false, &mut f));
+            }
+        }
+    }
+
+    // Drop the texts left empty, unless they're needed around inlines.
+    let len = nodes.len();
+    let keep: Vec<bool> = (0..len)
+        .map(|i| match &nodes[i] {
+            Descendant::Text(text) if text.text().is_empty() => {
+                let element = |j: Option<usize>| {
+                    matches!(j.and_then(|j| nodes.get(j)), Some(Descendant::Element(_)))
+                };
+                len == 1 || element(i.checked_sub(1)) || element(Some(i + 1))
+            }
+            _ => true,
+        })
+        .collect();
+    let mut keep = keep.into_iter();
+    nodes.retain(|_| keep.next().unwrap());
+    if nodes.is_empty() {
+        nodes.push(Text::new("").into());
+    }
+}
+
+/// Call `f` with each text in a node, in order or in `reverse`, or with
+/// `None` for void elements.
+fn visit_texts(node: &mut Descendant, reverse: bool, f: &mut impl FnMut(Option<&mut Text>)) {
+    match node {
+        Descendant::Text(text) => f(Some(text)),
+        Descendant::Element(element) if VOID_KINDS.contains(&element.kind()) => f(None),
+        Descendant::Element(element) => {
+            let children = element.children_mut();
+            let len = children.len();
+            for i in 0..len {
+                let i = if reverse { len - 1 - i } else { i };
+                visit_texts(&mut children[i], reverse, f);
+            }
+        }
+    }
+}
+
+/// Parse HTML into a minimal DOM. The parser is forgiving: unclosed tags are
+/// closed at the end of their parent and stray closing tags are ignored.
+pub(crate) fn parse(html: &str) -> Vec<Dom> {
+    // Each entry is an open tag with its attributes and the children gathered
+    // so far. The first entry collects the top-level nodes.
+    let mut stack: Vec<(String, BTreeMap<String, String>, Vec<Dom>)> =
+        vec![(String::new(), BTreeMap::new(), vec![])];
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |i| &after[i + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |i| &rest[i + 1..]);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').unwrap_or(after.len());
+            let tag = after[..end].trim().to_ascii_lowercase();
+            rest = after.get(end + 1..).unwrap_or("");
+
+            if let Some(i) = stack.iter().rposition(|(t, _, _)| *t == tag) {
+                while stack.len() > i {
+                    close(&mut stack);
+                }
+            }
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let end = tag_end(rest);
+            let (tag, attributes, self_closing) = parse_tag(&rest[1..end]);
+            rest = rest.get(end + 1..).unwrap_or("");
+
+            if SKIPPED_TAGS.contains(&tag.as_str()) {
+                let closing = format!("</{}", tag);
+                rest = find_ignore_case(rest, &closing).map_or("", |i| {
+                    rest[i..].find('>').map_or("", |j| &rest[i + j + 1..])
+                });
+                continue;
+            }
+
+            if self_closing || VOID_TAGS.contains(&tag.as_str()) {
+                let children = &mut stack.last_mut().unwrap().2;
+                children.push(Dom::Element {
+                    tag,
+                    attributes,
+                    children: vec![],
+                });
+            } else {
+                let open = stack.last().unwrap().0.as_str();
+                if (open == "p" && BLOCK_TAGS.contains(&tag.as_str()))
+                    || (open == "li" && tag == "li")
+                {
+                    close(&mut stack);
+                }
+                stack.push((tag, attributes, vec![]));
+            }
+        } else {
+            let end = rest[1..].find('<').map_or(rest.len(), |i| i + 1);
+            let text = decode_entities(&rest[..end]);
+            stack.last_mut().unwrap().2.push(Dom::Text(text));
+            rest = &rest[end..];
+        }
+    }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+
+    stack.pop().unwrap().2
+}
+
+fn close(stack: &mut Vec<(String, BTreeMap<String, String>, Vec<Dom>)>) {
+    let (tag, attributes, children) = stack.pop().unwrap();
+    stack.last_mut().unwrap().2.push(Dom::Element {
+        tag,
+        attributes,
+        children,
+    });
+}
+
+/// Find the index of the `>` ending the tag at the start of `html`, skipping
+/// over quoted attribute values.
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return i,
+            _ => {}
+        }
+    }
+
+    html.len()
+}
+
+fn parse_tag(source: &str) -> (String, BTreeMap<String, String>, bool) {
+    let self_closing = source.ends_with('/');
+    let source = source.trim_end_matches('/');
+    let name_end = source
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(source.len());
+    let tag = source[..name_end].to_ascii_lowercase();
+    let mut attributes = BTreeMap::new();
+    let mut rest = source[name_end..].trim_start();
+
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+
+        let value = if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(q) if q == '"' || q == '\'' => {
+                    let end = after[1..].find(q).map_or(after.len(), |i| i + 1);
+                    rest = after.get(end + 1..).unwrap_or("");
+                    &after[1..end]
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace())
+                        .unwrap_or(after.len());
+                    rest = &after[end..];
+                    &after[..end]
+                }
+            }
+        } else {
+            ""
+        };
+
+        if !key.is_empty() {
+            attributes.insert(key, decode_entities(value));
+        }
+        rest = rest.trim_start();
+    }
+
+    (tag, attributes, self_closing)
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let code = if let Some(hex) = entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(std::char::from_u32)
+            }
+        };
+
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Point, Range};
+
+    use super::*;
+
+    #[test]
+    fn from_string_blocks() {
+        assert_eq!(
+            from_string("<h1>Title</h1><p>Some <strong>bold</strong> text</p>"),
+            vec![
+                Element::with_kind("heading-one")
+                    .add_child(Text::new("Title"))
+                    .into(),
+                Element::new()
+                    .add_child(Text::new("Some "))
+                    .add_child(Text::with_marks("bold", Marks::BOLD))
+                    .add_child(Text::new(" text"))
+                    .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_string_entities() {
+        assert_eq!(
+            from_string("<p>a &amp; b &lt;c&gt; &#65;&#x42;</p>"),
+            vec![Element::new().add_child(Text::new("a & b <c> AB")).into()]
+        );
+    }
+
+    #[test]
+    fn from_string_link() {
+        assert_eq!(
+            from_string("<p><a href=\"https://example.com\">link</a></p>"),
+            vec![Element::new()
+                .add_child(
+                    Element::with_kind("link")
+                        .add_property("url", "https://example.com")
+                        .add_child(Text::new("link"))
+                )
+                .into()]
+        );
+    }
+
+    #[test]
+    fn from_string_loose_text() {
+        assert_eq!(
+            from_string("<div>loose <i>text</i></div>\n<p>para</p>"),
+            vec![
+                Element::new()
+                    .add_child(Text::new("loose "))
+                    .add_child(Text::with_marks("text", Marks::ITALIC))
+                    .into(),
+                Element::new().add_child(Text::new("para")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_string_skips_head() {
+        assert_eq!(
+            from_string("<html><head><title>x</title><style>p {}</style></head><body><p>a</p></body></html>"),
+            vec![Element::new().add_child(Text::new("a")).into()]
+        );
+    }
+
+    #[test]
+    fn parse_unclosed() {
+        assert_eq!(
+            parse("<p>one<p>two"),
+            vec![
+                Dom::Element {
+                    tag: "p".into(),
+                    attributes: BTreeMap::new(),
+                    children: vec![Dom::Text("one".into())],
+                },
+                Dom::Element {
+                    tag: "p".into(),
+                    attributes: BTreeMap::new(),
+                    children: vec![Dom::Text("two".into())],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let nodes = vec![
+            Element::with_kind("heading-two")
+                .add_child(Text::new("a < b"))
+                .into(),
+            Element::with_kind("bulleted-list")
+                .add_child(
+                    Element::with_kind("list-item")
+                        .add_child(Text::with_marks("x", Marks::BOLD | Marks::ITALIC)),
+                )
+                .into(),
+            Element::with_kind("callout")
+                .add_property("tone", "warning")
+                .add_child(Text::new("careful"))
+                .into(),
+        ];
+        assert_eq!(from_string(&to_string(&nodes)), nodes);
+    }
+
+    #[test]
+    fn direction_round_trip() {
+        let nodes = vec![Element::new()
+            .add_property("dir", "rtl")
+            .add_child(Text::new("a"))
+            .into()];
+        let html = to_string(&nodes);
+        assert_eq!(html, "<p dir=\"rtl\">a</p>");
+        assert_eq!(from_string(&html), nodes);
+        assert_eq!(
+            from_string("<p dir=\"auto\">a</p>"),
+            vec![Element::new().add_child(Text::new("a")).into()]
+        );
+    }
+
+    #[test]
+    fn footnotes_round_trip() {
+        let nodes = vec![
+            Element::new()
+                .add_child(Text::new("a"))
+                .add_child(footnotes::reference("fn-1").add_property("number", "1"))
+                .into(),
+            footnotes::definition("fn-1")
+                .add_property("number", "1")
+                .add_child(Element::new().add_child(Text::new("note")))
+                .into(),
+        ];
+        let html = to_string(&nodes);
+        assert_eq!(
+            html,
+            "<p>a<sup data-kind=\"footnote-ref\" data-footnote=\"fn-1\" data-number=\"1\">\
+             <a href=\"#fn-1\">1</a></sup></p>\
+             <div data-kind=\"footnote\" data-id=\"fn-1\" data-number=\"1\" id=\"fn-1\">\
+             <p>note</p></div>"
+        );
+        assert_eq!(from_string(&html), nodes);
+    }
+
+    #[test]
+    fn math_round_trip() {
+        let nodes = vec![
+            Element::new()
+                .add_child(Text::new("so "))
+                .add_child(math::inline("a<b"))
+                .into(),
+            math::block("x^2").into(),
+        ];
+        let html = to_string(&nodes);
+        assert_eq!(
+            html,
+            "<p>so <span data-kind=\"math\" data-tex=\"a&lt;b\">\\(a&lt;b\\)</span></p>\
+             <div data-kind=\"math-block\" data-tex=\"x^2\">\\[x^2\\]</div>"
+        );
+        assert_eq!(from_string(&html), nodes);
+    }
+
+    #[test]
+    fn block_style_round_trip() {
+        let nodes = vec![Element::new()
+            .add_property("align", "center")
+            .add_property("indent", "2")
+            .add_child(Text::new("a"))
+            .into()];
+        let html = to_string(&nodes);
+        assert_eq!(
+            html,
+            "<p style=\"text-align: center; margin-left: 4em\">a</p>"
+        );
+        assert_eq!(from_string(&html), nodes);
+        assert_eq!(
+            from_string("<p style=\"color: red;text-align:right\">a</p>"),
+            vec![Element::new()
+                .add_property("align", "right")
+                .add_child(Text::new("a"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn colors_round_trip() {
+        let nodes = vec![Element::new()
+            .add_child(
+                Text::with_marks("x", Marks::BOLD)
+                    .add_mark_value(COLOR_MARK, "#00f")
+                    .add_mark_value(BACKGROUND_MARK, "yellow"),
+            )
+            .into()];
+        let html = to_string(&nodes);
+        assert_eq!(
+            html,
+            "<p><strong><span style=\"color: #00f; background-color: yellow\">x</span></strong></p>"
+        );
+        assert_eq!(from_string(&html), nodes);
+    }
+
+    #[test]
+    fn to_string_marks() {
+        let nodes = vec![Element::new()
+            .add_child(Text::with_marks("x", Marks::BOLD | Marks::UNDERLINE))
+            .into()];
+        assert_eq!(to_string(&nodes), "<p><strong><u>x</u></strong></p>");
+    }
+
+    #[test]
+    fn code_marks() {
+        let nodes = vec![Element::new()
+            .add_child(Text::new("run "))
+            .add_child(Text::with_marks("main", Marks::BOLD | Marks::CODE))
+            .into()];
+        let html = to_string(&nodes);
+        assert_eq!(html, "<p>run <strong><code>main</code></strong></p>");
+        assert_eq!(from_string(&html), nodes);
+
+        // The code of a code block isn't marked.
+        assert_eq!(
+            from_string("<pre><code>main</code></pre>"),
+            vec![Element::with_kind("code-block")
+                .add_child(Text::new("main"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn custom_marks_round_trip() {
+        let mut registry = MarkRegistry::new();
+        let strike = registry.register("strikethrough").unwrap();
+        let nodes = vec![Element::new()
+            .add_child(Text::with_marks("x", Marks::BOLD | strike))
+            .into()];
+        let html = to_string_with(&nodes, &registry);
+        assert_eq!(
+            html,
+            "<p><strong><span data-mark=\"strikethrough\">x</span></strong></p>"
+        );
+        assert_eq!(from_string_with(&html, &registry), nodes);
+        assert_eq!(
+            from_string(&html),
+            vec![Element::new()
+                .add_child(Text::with_marks("x", Marks::BOLD))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn to_string_link() {
+        let nodes = vec![Element::new()
+            .add_child(Text::new("see "))
+            .add_child(
+                Element::with_kind("link")
+                    .add_property("url", "https://example.com/?a=1&b=2")
+                    .add_child(Text::new("here")),
+            )
+            .into()];
+        assert_eq!(
+            to_string(&nodes),
+            "<p>see <a href=\"https://example.com/?a=1&amp;b=2\">here</a></p>"
+        );
+    }
+
+    #[test]
+    fn soft_breaks_round_trip() {
+        let nodes = vec![Element::new().add_child(Text::new("one\ntwo")).into()];
+        assert_eq!(to_string(&nodes), "<p>one<br>two</p>");
+        assert_eq!(from_string(&to_string(&nodes)), nodes);
+    }
+
+    #[test]
+    fn from_string_whitespace_like_browsers() {
+        let html = "<p>\n  Some <b> bold </b>  text <br> next\n</p><pre>  a\n  b</pre>";
+        assert_eq!(
+            from_string(html),
+            vec![
+                Element::new()
+                    .add_child(Text::new("Some "))
+                    .add_child(Text::with_marks("bold ", Marks::BOLD))
+                    .add_child(Text::new("text\nnext"))
+                    .into(),
+                Element::with_kind("code-block")
+                    .add_child(Text::new("  a\n  b"))
+                    .into(),
+            ]
+        );
+
+        let options = WhitespaceOptions::default()
+            .trim_block_edges(false)
+            .preserve_in_code(false);
+        assert_eq!(
+            from_string_with_whitespace(html, &MarkRegistry::default(), &options),
+            vec![
+                Element::new()
+                    .add_child(Text::new(" Some "))
+                    .add_child(Text::with_marks("bold ", Marks::BOLD))
+                    .add_child(Text::new("text\nnext "))
+                    .into(),
+                Element::with_kind("code-block")
+                    .add_child(Text::new(" a b"))
+                    .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_string_at_range() {
+        let editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::new("some "))
+                .add_child(Text::with_marks("bold", Marks::BOLD)),
+        );
+        let point = |child, offset| Point {
+            path: vec![0, child].into(),
+            offset,
+        };
+        let range = Range::new(point(0, 2), point(1, 2));
+
+        assert_eq!(
+            to_string_at(&editor, range),
+            "<p>me <strong>bo</strong></p>"
+        );
+    }
+
+    #[test]
+    fn diff_render_changed_blocks() {
+        let block = |text: &str| -> Descendant { Element::new().add_child(Text::new(text)).into() };
+        let prev = vec![block("a"), block("b"), block("c"), block("d")];
+        let next = vec![block("a"), block("B"), block("x"), block("y"), block("d")];
+
+        assert_eq!(
+            diff_render(&prev, &next),
+            vec![
+                HtmlPatch::Replace {
+                    path: Path::new(vec![1]),
+                    html: "<p>B</p>".into()
+                },
+                HtmlPatch::Replace {
+                    path: Path::new(vec![2]),
+                    html: "<p>x</p>".into()
+                },
+                HtmlPatch::Insert {
+                    path: Path::new(vec![3]),
+                    html: "<p>y</p>".into()
+                },
+            ]
+        );
+        assert_eq!(
+            diff_render(&next[..3], &prev[..1]),
+            vec![
+                HtmlPatch::Remove {
+                    path: Path::new(vec![1])
+                },
+                HtmlPatch::Remove {
+                    path: Path::new(vec![1])
+                },
+            ]
+        );
+        assert!(diff_render(&prev, &prev).is_empty());
+    }
+
+    #[test]
+    fn diff_render_distant_edits() {
+        let block = |text: &str| -> Descendant { Element::new().add_child(Text::new(text)).into() };
+        let prev = vec![block("a"), block("b"), block("c"), block("d"), block("e")];
+        let next = vec![block("a"), block("B"), block("c"), block("d"), block("E")];
+
+        // The blocks between the edits aren't rendered again.
+        assert_eq!(
+            diff_render(&prev, &next),
+            vec![
+                HtmlPatch::Replace {
+                    path: Path::new(vec![1]),
+                    html: "<p>B</p>".into()
+                },
+                HtmlPatch::Replace {
+                    path: Path::new(vec![4]),
+                    html: "<p>E</p>".into()
+                },
+            ]
+        );
+
+        let next = vec![block("b"), block("c"), block("x"), block("d"), block("e")];
+        assert_eq!(
+            diff_render(&prev, &next),
+            vec![
+                HtmlPatch::Remove {
+                    path: Path::new(vec![0])
+                },
+                HtmlPatch::Insert {
+                    path: Path::new(vec![2]),
+                    html: "<p>x</p>".into()
+                },
+            ]
+        );
+    }
+}
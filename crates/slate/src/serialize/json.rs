@@ -0,0 +1,943 @@
+//! JSON, in the shape Slate.js stores documents: elements hold their kind as
+//! `type`, their properties and their `children`, and text nodes hold their
+//! `text`, `true` for each of their marks and the values of their valued
+//! marks. The selection, and the document's schema version if it has one, are
+//! stored next to the children.
+//!
+//! Marks are named by the editor's `MarkRegistry`, so custom marks are
+//! written like the built-in ones. The `_with` functions take the registry
+//! for JSON that isn't read into or written from an editor. Properties and
+//! mark values named like a key the node uses itself, such as `type` or
+//! `text`, or like a mark, are written with a `$` in front, as are those
+//! already starting with one, so they're read back as they were.
+//!
+//! Operations are in the shape of Slate.js operations too, so they can be
+//! exchanged with its collaboration backends. `Operation` implements serde's
+//! `Serialize` and `Deserialize` in this shape as well.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde_json::{json, Map, Value};
+
+use crate::types::{
+    Descendant, Editor, Element, MarkRegistry, Marks, Node, NodeProperties, Operation, Path, Point,
+    Range, Text, DEFAULT_KIND,
+};
+
+/// A problem found in a stored document. Lossy imports repair it and report
+/// it, while strict imports fail on it.
+///
+/// Paths are those of the nodes in the stored document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportWarning {
+    /// The input isn't a JSON array of nodes or an object holding one, so
+    /// nothing could be imported.
+    InvalidJson(String),
+    /// A value that is neither an element nor a text node was dropped.
+    InvalidNode(Path),
+    /// An element without a kind was given the default kind.
+    MissingKind(Path),
+    /// An element without children was given an empty text node.
+    MissingText(Path),
+    /// A text node at the top of the document was wrapped in a block.
+    LooseText(Path),
+    /// An element property that isn't a string was converted to one, or
+    /// dropped if it isn't a scalar.
+    InvalidProperty(Path, String),
+    /// A text property that isn't a known mark set to `true` or a string for
+    /// a valued mark was dropped.
+    InvalidMark(Path, String),
+    /// The selection doesn't point into text nodes, so it was dropped.
+    InvalidSelection,
+    /// A selection offset past the end of its text, or inside a character,
+    /// was moved back to the nearest valid offset.
+    OffsetOutOfRange(Point),
+}
+
+impl fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportWarning::InvalidJson(e) => write!(f, "invalid document: {}", e),
+            ImportWarning::InvalidNode(p) => write!(f, "dropped invalid node at {:?}", p),
+            ImportWarning::MissingKind(p) => write!(f, "element at {:?} has no type", p),
+            ImportWarning::MissingText(p) => write!(f, "element at {:?} has no children", p),
+            ImportWarning::LooseText(p) => write!(f, "wrapped top-level text at {:?}", p),
+            ImportWarning::InvalidProperty(p, key) => {
+                write!(f, "invalid property {:?} at {:?}", key, p)
+            }
+            ImportWarning::InvalidMark(p, key) => write!(f, "invalid mark {:?} at {:?}", key, p),
+            ImportWarning::InvalidSelection => write!(f, "dropped invalid selection"),
+            ImportWarning::OffsetOutOfRange(p) => write!(f, "clamped selection point {:?}", p),
+        }
+    }
+}
+
+impl std::error::Error for ImportWarning {}
+
+/// The keys elements use for their own content.
+const ELEMENT_KEYS: [&str; 3] = ["type", "children", "text"];
+/// The keys text nodes use for their own content, besides their marks.
+const TEXT_KEYS: [&str; 2] = ["text", "author"];
+
+/// Escape a property or mark value's key if it's `reserved`, or starts with
+/// the escape.
+fn escape_key(key: &str, reserved: bool) -> Cow<'_, str> {
+    if reserved || key.starts_with('$') {
+        Cow::Owned(format!("${}", key))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+fn unescape_key(key: &str) -> &str {
+    key.strip_prefix('$').unwrap_or(key)
+}
+
+/// Serialize an editor's children, selection and schema version to JSON.
+pub fn to_string(editor: &Editor) -> String {
+    let registry = editor.mark_registry();
+    let selection = match editor.selection() {
+        Some(s) => json!({ "anchor": point_value(s.anchor()), "focus": point_value(s.focus()) }),
+        None => Value::Null,
+    };
+    let children: Vec<_> = editor
+        .children
+        .iter()
+        .map(|node| node_value(node, registry))
+        .collect();
+
+    let mut document = json!({ "children": children, "selection": selection });
+    if editor.schema_version() > 0 {
+        document["schemaVersion"] = editor.schema_version().into();
+    }
+    document.to_string()
+}
+
+pub(crate) fn node_value(node: &Descendant, registry: &MarkRegistry) -> Value {
+    match node {
+        Descendant::Element(e) => element_value(e, Some(registry)),
+        Descendant::Text(t) => text_value(t, registry),
+    }
+}
+
+/// Get the value of an element, and of its children if given the registry to
+/// write them with.
+fn element_value(element: &Element, children: Option<&MarkRegistry>) -> Value {
+    let mut object = Map::new();
+    object.insert("type".into(), element.kind().into());
+    for (key, value) in element.properties() {
+        let key = escape_key(key, ELEMENT_KEYS.contains(&key.as_str()));
+        object.insert(key.into_owned(), value.as_str().into());
+    }
+
+    if let Some(registry) = children {
+        let children: Vec<_> = element
+            .children_ref()
+            .iter()
+            .map(|node| node_value(node, registry))
+            .collect();
+        object.insert("children".into(), children.into());
+    }
+
+    Value::Object(object)
+}
+
+fn text_value(text: &Text, registry: &MarkRegistry) -> Value {
+    let mut object = Map::new();
+    object.insert("text".into(), text.text().into());
+    for name in registry.names(text.marks()) {
+        object.insert(name.into(), true.into());
+    }
+    if let Some(author) = text.author() {
+        object.insert("author".into(), author.into());
+    }
+    for (key, value) in text.mark_values() {
+        let reserved = TEXT_KEYS.contains(&key.as_str()) || registry.get(key).is_some();
+        object.insert(
+            escape_key(key, reserved).into_owned(),
+            value.as_str().into(),
+        );
+    }
+
+    Value::Object(object)
+}
+
+/// Get the value of node properties, with every registered mark if they set
+/// the marks, so the marks they don't have are cleared.
+pub(crate) fn properties_value(properties: &NodeProperties, registry: &MarkRegistry) -> Value {
+    let mut object = Map::new();
+    if let Some(kind) = &properties.kind {
+        object.insert("type".into(), kind.as_str().into());
+    }
+    for (key, value) in &properties.properties {
+        let reserved = key == "type" || registry.get(key).is_some();
+        object.insert(
+            escape_key(key, reserved).into_owned(),
+            value.as_deref().into(),
+        );
+    }
+    if let Some(marks) = properties.marks {
+        for (mark, name) in registry.iter() {
+            object.insert(name.into(), marks.contains(mark).then_some(true).into());
+        }
+    }
+
+    Value::Object(object)
+}
+
+/// Read node properties written by `properties_value` or Slate.js. Returns
+/// `None` if a value isn't a string, a boolean or null.
+pub(crate) fn read_properties(value: &Value, registry: &MarkRegistry) -> Option<NodeProperties> {
+    let mut properties = NodeProperties::new();
+    for (key, value) in value.as_object()? {
+        match (registry.get(key), value) {
+            (Some(mark), Value::Bool(true)) => {
+                properties.marks = Some(properties.marks.unwrap_or_else(Marks::empty) | mark);
+            }
+            (Some(_), Value::Bool(false)) | (Some(_), Value::Null) => {
+                properties.marks = Some(properties.marks.unwrap_or_else(Marks::empty));
+            }
+            (_, Value::String(kind)) if key == "type" => properties.kind = Some(kind.clone()),
+            (_, Value::Null) if key == "type" => {}
+            (_, Value::String(value)) => {
+                let key = unescape_key(key).to_string();
+                properties.properties.insert(key, Some(value.clone()));
+            }
+            (_, Value::Null) => {
+                properties.properties.insert(unescape_key(key).into(), None);
+            }
+            (None, Value::Bool(_)) => {}
+            _ => return None,
+        }
+    }
+
+    Some(properties)
+}
+
+fn point_value(point: &Point) -> Value {
+    json!({ "path": point.path.as_slice(), "offset": point.offset })
+}
+
+/// Serialize an operation to JSON, with its kind as `type`, like
+/// `insert_text`. Nodes are written like in documents, and the editor node,
+/// which no operation can insert or remove, as `null`.
+///
+/// Only the built-in marks are written, so operations on text with custom
+/// marks are written with `operation_to_value_with`.
+pub fn operation_to_value(op: &Operation) -> Value {
+    operation_to_value_with(op, &MarkRegistry::default())
+}
+
+/// Serialize an operation to JSON like `operation_to_value`, naming marks
+/// with a registry.
+pub fn operation_to_value_with(op: &Operation, registry: &MarkRegistry) -> Value {
+    let properties = |p: &NodeProperties| properties_value(p, registry);
+    let range = |r: &Option<Range>| match r {
+        Some(r) => json!({ "anchor": point_value(r.anchor()), "focus": point_value(r.focus()) }),
+        None => Value::Null,
+    };
+    let node = |n: &Node| match n {
+        Node::Element(e) => element_value(e, Some(registry)),
+        Node::Text(t) => text_value(t, registry),
+        Node::Editor(_) => Value::Null,
+    };
+
+    match op {
+        Operation::InsertNode { path, node: n } => {
+            json!({ "type": "insert_node", "path": path.as_slice(), "node": node(n) })
+        }
+        Operation::InsertText { path, offset, text } => json!({
+            "type": "insert_text", "path": path.as_slice(), "offset": offset, "text": text,
+        }),
+        Operation::MergeNode {
+            path,
+            position,
+            properties: p,
+        } => json!({
+            "type": "merge_node", "path": path.as_slice(), "position": position,
+            "properties": properties(p),
+        }),
+        Operation::MoveNode { path, new_path } => json!({
+            "type": "move_node", "path": path.as_slice(), "newPath": new_path.as_slice(),
+        }),
+        Operation::RemoveNode { path, node: n } => {
+            json!({ "type": "remove_node", "path": path.as_slice(), "node": node(n) })
+        }
+        Operation::RemoveText { path, offset, text } => json!({
+            "type": "remove_text", "path": path.as_slice(), "offset": offset, "text": text,
+        }),
+        Operation::SetNode {
+            path,
+            properties: p,
+            new_properties,
+        } => json!({
+            "type": "set_node", "path": path.as_slice(), "properties": properties(p),
+            "newProperties": properties(new_properties),
+        }),
+        Operation::SetSelection {
+            properties,
+            new_properties,
+            ..
+        } => json!({
+            "type": "set_selection", "properties": range(properties),
+            "newProperties": range(new_properties),
+        }),
+        Operation::SplitNode {
+            path,
+            position,
+            properties: p,
+        } => json!({
+            "type": "split_node", "path": path.as_slice(), "position": position,
+            "properties": properties(p),
+        }),
+    }
+}
+
+/// Deserialize an operation from JSON written by `operation_to_value` or
+/// Slate.js. Returns `None` if it isn't a valid operation, including when a
+/// node it holds has a mark that isn't known.
+pub fn operation_from_value(value: &Value) -> Option<Operation> {
+    operation_from_value_with(value, &MarkRegistry::default())
+}
+
+/// Deserialize an operation from JSON like `operation_from_value`, reading
+/// marks by the names in a registry.
+pub fn operation_from_value_with(value: &Value, registry: &MarkRegistry) -> Option<Operation> {
+    let path = |key: &str| read_path(value.get(key)?);
+    let usize_at = |key: &str| value.get(key)?.as_u64().map(|n| n as usize);
+    let text = |key: &str| value.get(key)?.as_str().map(String::from);
+    let properties = |key: &str| read_properties(value.get(key)?, registry);
+    // Nodes have to be valid, rather than repaired, so unknown marks aren't
+    // dropped.
+    let node = |key: &str| {
+        let mut warnings = vec![];
+        let node = read_node(value.get(key)?, &Path::new(vec![]), registry, &mut warnings)?;
+        warnings.is_empty().then(|| Node::from(node))
+    };
+    let range = |key: &str| match value.get(key)? {
+        Value::Null => Some(None),
+        range => {
+            let point = |key: &str| {
+                Some(Point {
+                    path: read_path(range.get(key)?.get("path")?)?,
+                    offset: range.get(key)?.get("offset")?.as_u64()? as usize,
+                })
+            };
+            Some(Some(Range::new(point("anchor")?, point("focus")?)))
+        }
+    };
+
+    let op = match value.get("type")?.as_str()? {
+        "insert_node" => Operation::InsertNode {
+            path: path("path")?,
+            node: node("node")?,
+        },
+        "insert_text" => Operation::InsertText {
+            path: path("path")?,
+            offset: usize_at("offset")?,
+            text: text("text")?,
+        },
+        "merge_node" => Operation::MergeNode {
+            path: path("path")?,
+            position: usize_at("position")?,
+            properties: properties("properties").unwrap_or_default(),
+        },
+        "move_node" => Operation::MoveNode {
+            path: path("path")?,
+            new_path: path("newPath")?,
+        },
+        "remove_node" => Operation::RemoveNode {
+            path: path("path")?,
+            node: node("node")?,
+        },
+        "remove_text" => Operation::RemoveText {
+            path: path("path")?,
+            offset: usize_at("offset")?,
+            text: text("text")?,
+        },
+        "set_node" => Operation::SetNode {
+            path: path("path")?,
+            properties: properties("properties")?,
+            new_properties: properties("newProperties")?,
+        },
+        "set_selection" => Operation::SetSelection {
+            path: Path::new(vec![]),
+            properties: range("properties")?,
+            new_properties: range("newProperties")?,
+        },
+        "split_node" => Operation::SplitNode {
+            path: path("path")?,
+            position: usize_at("position")?,
+            properties: properties("properties").unwrap_or_default(),
+        },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn read_path(value: &Value) -> Option<Path> {
+    let indexes = value
+        .as_array()?
+        .iter()
+        .map(|i| i.as_u64().map(|i| i as usize))
+        .collect::<Option<_>>()?;
+    Some(Path::new(indexes))
+}
+
+/// Serialize nodes to canonical JSON, which is the same for equal nodes, so
+/// it can be hashed to identify content.
+///
+/// Object keys, including properties and marks, are sorted by their UTF-8
+/// bytes, and there is no whitespace outside strings.
+pub fn to_canonical_string(nodes: &[Descendant]) -> String {
+    to_canonical_string_with(nodes, &MarkRegistry::default())
+}
+
+/// Serialize nodes to canonical JSON like `to_canonical_string`, naming
+/// marks with a registry.
+pub fn to_canonical_string_with(nodes: &[Descendant], registry: &MarkRegistry) -> String {
+    let mut out = String::new();
+    let nodes = nodes
+        .iter()
+        .map(|node| node_value(node, registry))
+        .collect();
+    write_canonical(&nodes, &mut out);
+    out
+}
+
+/// The canonical JSON of an element without its children.
+pub(crate) fn canonical_element(element: &Element) -> String {
+    let mut out = String::new();
+    write_canonical(&element_value(element, None), &mut out);
+    out
+}
+
+/// The canonical JSON of a text node, with its built-in marks.
+pub(crate) fn canonical_text(text: &Text) -> String {
+    let mut out = String::new();
+    write_canonical(&text_value(text, &MarkRegistry::default()), &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(object) => {
+            // Sorted here rather than relying on the map, whose order depends
+            // on serde_json's features.
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+/// Deserialize an editor from JSON, failing on the first problem found.
+pub fn from_string(json: &str) -> Result<Editor, ImportWarning> {
+    first_warning(from_string_lossy(json))
+}
+
+/// Deserialize an editor from JSON like `from_string`, reading marks by the
+/// names in a registry, which the editor is given.
+pub fn from_string_with(json: &str, registry: &MarkRegistry) -> Result<Editor, ImportWarning> {
+    first_warning(from_string_lossy_with(json, registry))
+}
+
+fn first_warning(
+    (editor, mut warnings): (Editor, Vec<ImportWarning>),
+) -> Result<Editor, ImportWarning> {
+    if warnings.is_empty() {
+        Ok(editor)
+    } else {
+        Err(warnings.remove(0))
+    }
+}
+
+/// Deserialize an editor from JSON, repairing a structurally invalid document
+/// instead of failing. Every repair made is reported.
+///
+/// The input may be an object with `children` and `selection`, or just the
+/// array of children.
+pub fn from_string_lossy(json: &str) -> (Editor, Vec<ImportWarning>) {
+    read_document(json, &MarkRegistry::default())
+}
+
+/// Deserialize an editor from JSON like `from_string_lossy`, reading marks by
+/// the names in a registry, which the editor is given.
+pub fn from_string_lossy_with(json: &str, registry: &MarkRegistry) -> (Editor, Vec<ImportWarning>) {
+    let (editor, warnings) = read_document(json, registry);
+    (editor.with_mark_registry(registry.clone()), warnings)
+}
+
+fn read_document(json: &str, registry: &MarkRegistry) -> (Editor, Vec<ImportWarning>) {
+    let mut warnings = vec![];
+
+    let value: Value = match serde_json::from_str(json) {
+        Ok(value) => value,
+        Err(e) => {
+            warnings.push(ImportWarning::InvalidJson(e.to_string()));
+            return (Editor::with_default(), warnings);
+        }
+    };
+
+    let (children, selection) = match &value {
+        Value::Array(children) => (children, None),
+        Value::Object(object) => match object.get("children") {
+            Some(Value::Array(children)) => (children, object.get("selection")),
+            _ => {
+                warnings.push(ImportWarning::InvalidJson("missing children".into()));
+                return (Editor::with_default(), warnings);
+            }
+        },
+        _ => {
+            warnings.push(ImportWarning::InvalidJson("not a document".into()));
+            return (Editor::with_default(), warnings);
+        }
+    };
+
+    let root = Path::new(vec![]);
+    let mut editor = Editor::new();
+    let mut loose: Option<Element> = None;
+
+    for (i, value) in children.iter().enumerate() {
+        let path = root.concat(i);
+        match read_node(value, &path, registry, &mut warnings) {
+            Some(Descendant::Text(t)) => {
+                if loose.is_none() {
+                    warnings.push(ImportWarning::LooseText(path));
+                }
+                loose = Some(loose.unwrap_or_default().add_child(t));
+            }
+            Some(node) => {
+                if let Some(block) = loose.take() {
+                    editor = editor.add_child(block);
+                }
+                editor = editor.add_child(node);
+            }
+            None => {}
+        }
+    }
+    if let Some(block) = loose {
+        editor = editor.add_child(block);
+    }
+
+    if !editor.has_children() {
+        editor = Editor::with_default();
+    }
+
+    match selection {
+        None | Some(Value::Null) => {}
+        Some(value) => editor.selection = read_selection(&editor, value, &mut warnings),
+    }
+    if let Some(version) = value.get("schemaVersion").and_then(Value::as_u64) {
+        editor.schema_version = version as u32;
+    }
+
+    (editor, warnings)
+}
+
+pub(crate) fn read_node(
+    value: &Value,
+    path: &Path,
+    registry: &MarkRegistry,
+    warnings: &mut Vec<ImportWarning>,
+) -> Option<Descendant> {
+    let object = match value {
+        Value::Object(object) => object,
+        _ => {
+            warnings.push(ImportWarning::InvalidNode(path.clone()));
+            return None;
+        }
+    };
+
+    match object.get("text") {
+        Some(Value::String(text)) => {
+            return Some(read_text(text, object, path, registry, warnings).into())
+        }
+        Some(_) => {
+            warnings.push(ImportWarning::InvalidNode(path.clone()));
+            return None;
+        }
+        None => {}
+    }
+
+    if !object.contains_key("children") && !object.contains_key("type") {
+        warnings.push(ImportWarning::InvalidNode(path.clone()));
+        return None;
+    }
+
+    let kind = match object.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        _ => {
+            warnings.push(ImportWarning::MissingKind(path.clone()));
+            DEFAULT_KIND
+        }
+    };
+    let mut element = Element::with_kind(kind);
+
+    for (key, value) in object {
+        if key == "type" || key == "children" {
+            continue;
+        }
+
+        let property = unescape_key(key);
+        match value {
+            Value::String(value) => element = element.add_property(property, value.as_str()),
+            Value::Bool(_) | Value::Number(_) => {
+                warnings.push(ImportWarning::InvalidProperty(path.clone(), key.clone()));
+                element = element.add_property(property, value.to_string());
+            }
+            _ => warnings.push(ImportWarning::InvalidProperty(path.clone(), key.clone())),
+        }
+    }
+
+    if let Some(Value::Array(children)) = object.get("children") {
+        for (i, child) in children.iter().enumerate() {
+            if let Some(child) = read_node(child, &path.concat(i), registry, warnings) {
+                element = element.add_child(child);
+            }
+        }
+    }
+
+    if !element.has_children() {
+        warnings.push(ImportWarning::MissingText(path.clone()));
+        element = element.add_child(Text::new(""));
+    }
+
+    Some(element.into())
+}
+
+fn read_text(
+    text: &str,
+    object: &Map<String, Value>,
+    path: &Path,
+    registry: &MarkRegistry,
+    warnings: &mut Vec<ImportWarning>,
+) -> Text {
+    let mut marks = Marks::empty();
+    let mut author = None;
+    let mut values = vec![];
+
+    for (key, value) in object {
+        match (registry.get(key), value) {
+            _ if key == "text" => {}
+            (_, Value::String(name)) if key == "author" => author = Some(name.as_str()),
+            (None, Value::String(value)) => values.push((unescape_key(key), value)),
+            (Some(mark), Value::Bool(true)) => marks |= mark,
+            (Some(_), Value::Bool(false)) | (Some(_), Value::Null) => {}
+            _ => warnings.push(ImportWarning::InvalidMark(path.clone(), key.clone())),
+        }
+    }
+
+    let text = values
+        .into_iter()
+        .fold(Text::with_marks(text, marks), |t, (key, value)| {
+            t.add_mark_value(key, value)
+        });
+    match author {
+        Some(author) => text.authored_by(author),
+        None => text,
+    }
+}
+
+fn read_selection(
+    editor: &Editor,
+    value: &Value,
+    warnings: &mut Vec<ImportWarning>,
+) -> Option<Range> {
+    let anchor = read_point(editor, value.get("anchor"), warnings);
+    let focus = read_point(editor, value.get("focus"), warnings);
+
+    match (anchor, focus) {
+        (Some(anchor), Some(focus)) => Some(Range::new(anchor, focus)),
+        _ => {
+            warnings.push(ImportWarning::InvalidSelection);
+            None
+        }
+    }
+}
+
+fn read_point(
+    editor: &Editor,
+    value: Option<&Value>,
+    warnings: &mut Vec<ImportWarning>,
+) -> Option<Point> {
+    let value = value?;
+    let path = read_path(value.get("path")?)?;
+    let offset = value.get("offset")?.as_u64()? as usize;
+
+    let text = editor.text_at(&path)?.text();
+    let mut valid = offset.min(text.len());
+    while !text.is_char_boundary(valid) {
+        valid -= 1;
+    }
+
+    if valid != offset {
+        warnings.push(ImportWarning::OffsetOutOfRange(Point {
+            path: path.clone(),
+            offset,
+        }));
+    }
+
+    Some(Point {
+        path,
+        offset: valid,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn canonical() {
+        let a = Element::new()
+            .add_property("z", "1")
+            .add_property("a", "\"2\"")
+            .add_child(Text::with_marks("x y", Marks::UNDERLINE | Marks::BOLD));
+        let b = Element::new()
+            .add_property("a", "\"2\"")
+            .add_property("z", "1")
+            .add_child(Text::with_marks("x y", Marks::BOLD | Marks::UNDERLINE));
+
+        let json = to_canonical_string(&[a.into()]);
+        assert_eq!(
+            json,
+            r#"[{"a":"\"2\"","children":[{"bold":true,"text":"x y","underline":true}],"type":"paragraph","z":"1"}]"#
+        );
+        assert_eq!(json, to_canonical_string(&[b.into()]));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::with_kind("heading-one")
+                    .add_property("id", "top")
+                    .add_child(
+                        Text::with_marks("Title", Marks::BOLD).add_mark_value("color", "red"),
+                    ),
+            )
+            .add_child(Element::new().add_child(Text::new("body")));
+        editor.select(point(vec![1, 0], 2));
+
+        let json = to_string(&editor);
+        let read = from_string(&json).unwrap();
+        assert_eq!(read.children(), editor.children());
+        assert_eq!(read.selection(), editor.selection());
+    }
+
+    #[test]
+    fn slate_shape() {
+        let editor = Editor::new().add_child(
+            Element::new().add_child(Text::with_marks("a", Marks::BOLD | Marks::ITALIC)),
+        );
+        let value: Value = serde_json::from_str(&to_string(&editor)).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "children": [{
+                    "type": "paragraph",
+                    "children": [{ "text": "a", "bold": true, "italic": true }],
+                }],
+                "selection": null,
+            })
+        );
+    }
+
+    #[test]
+    fn lossy_repairs() {
+        let json = r#"{
+            "children": [
+                { "text": "loose" },
+                { "type": "paragraph", "children": [] },
+                { "children": [{ "text": "x", "bold": true, "color": 1 }], "level": 2 },
+                42
+            ],
+            "selection": {
+                "anchor": { "path": [2, 0], "offset": 0 },
+                "focus": { "path": [2, 0], "offset": 9 }
+            }
+        }"#;
+        let (editor, warnings) = from_string_lossy(json);
+
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new().add_child(Text::new("loose")).into(),
+                Element::new().add_child(Text::new("")).into(),
+                Element::new()
+                    .add_property("level", "2")
+                    .add_child(Text::with_marks("x", Marks::BOLD))
+                    .into(),
+            ]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![2, 0], 0), point(vec![2, 0], 1)))
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                ImportWarning::LooseText(vec![0].into()),
+                ImportWarning::MissingText(vec![1].into()),
+                ImportWarning::MissingKind(vec![2].into()),
+                ImportWarning::InvalidProperty(vec![2].into(), "level".into()),
+                ImportWarning::InvalidMark(vec![2, 0].into(), "color".into()),
+                ImportWarning::InvalidNode(vec![3].into()),
+                ImportWarning::OffsetOutOfRange(point(vec![2, 0], 9)),
+            ]
+        );
+        assert_eq!(
+            from_string(json).unwrap_err(),
+            ImportWarning::LooseText(vec![0].into())
+        );
+    }
+
+    #[test]
+    fn custom_marks_and_reserved_keys() {
+        let mut registry = MarkRegistry::new();
+        let strike = registry.register("strikethrough").unwrap();
+        let editor = Editor::new()
+            .with_mark_registry(registry.clone())
+            .add_child(
+                Element::new()
+                    .add_property("text", "a")
+                    .add_property("$ref", "b")
+                    .add_child(
+                        Text::with_marks("x", strike)
+                            .add_mark_value("author", "c")
+                            .add_mark_value("bold", "d"),
+                    ),
+            );
+
+        let json = to_string(&editor);
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value["children"][0],
+            json!({
+                "type": "paragraph",
+                "$text": "a",
+                "$$ref": "b",
+                "children": [{ "text": "x", "strikethrough": true, "$author": "c", "$bold": "d" }],
+            })
+        );
+        let read = from_string_with(&json, &registry).unwrap();
+        assert_eq!(read.children(), editor.children());
+        assert_eq!(read.mark_registry(), &registry);
+        assert_eq!(
+            from_string(&json).unwrap_err(),
+            ImportWarning::InvalidMark(vec![0, 0].into(), "strikethrough".into())
+        );
+
+        let op = Operation::SetNode {
+            path: vec![0, 0].into(),
+            properties: NodeProperties::new()
+                .marks(Marks::empty())
+                .property("bold", None::<String>),
+            new_properties: NodeProperties::new()
+                .marks(strike)
+                .property("bold", Some("e")),
+        };
+        let value = operation_to_value_with(&op, &registry);
+        assert_eq!(value["newProperties"]["strikethrough"], json!(true));
+        assert_eq!(value["newProperties"]["$bold"], json!("e"));
+        assert_eq!(operation_from_value_with(&value, &registry), Some(op));
+    }
+
+    #[test]
+    fn lossy_invalid_json() {
+        let (editor, warnings) = from_string_lossy("{");
+        assert_eq!(editor.children(), Editor::with_default().children());
+        assert!(matches!(warnings[..], [ImportWarning::InvalidJson(_)]));
+    }
+
+    #[test]
+    fn operations_round_trip() {
+        let ops = vec![
+            Operation::InsertNode {
+                path: vec![1].into(),
+                node: Node::Element(Element::new().add_child(Text::with_marks("a", Marks::BOLD))),
+            },
+            Operation::InsertText {
+                path: vec![0, 0].into(),
+                offset: 1,
+                text: "b".into(),
+            },
+            Operation::MergeNode {
+                path: vec![0, 1].into(),
+                position: 2,
+                properties: NodeProperties::new().marks(Marks::ITALIC),
+            },
+            Operation::MoveNode {
+                path: vec![0].into(),
+                new_path: vec![2].into(),
+            },
+            Operation::RemoveNode {
+                path: vec![0, 0].into(),
+                node: Node::Text(Text::new("c")),
+            },
+            Operation::SetNode {
+                path: vec![0].into(),
+                properties: NodeProperties::new().kind("paragraph"),
+                new_properties: NodeProperties::new()
+                    .kind("heading-one")
+                    .property("id", Some("top")),
+            },
+            Operation::SetSelection {
+                path: vec![].into(),
+                properties: None,
+                new_properties: Some(Range::new(point(vec![0, 0], 0), point(vec![0, 0], 1))),
+            },
+            Operation::SplitNode {
+                path: vec![0].into(),
+                position: 1,
+                properties: NodeProperties::new().kind("paragraph"),
+            },
+        ];
+        for op in ops {
+            assert_eq!(operation_from_value(&operation_to_value(&op)), Some(op));
+        }
+
+        let value = operation_to_value(&Operation::MoveNode {
+            path: vec![0].into(),
+            new_path: vec![1].into(),
+        });
+        assert_eq!(
+            value,
+            json!({ "type": "move_node", "path": [0], "newPath": [1] })
+        );
+        assert_eq!(
+            operation_from_value(&json!({ "type": "insert_text" })),
+            None
+        );
+    }
+}
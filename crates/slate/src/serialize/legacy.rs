@@ -0,0 +1,226 @@
+//! JSON stored by Slate 0.47 and earlier, for migrating old documents.
+//!
+//! Those versions wrap the document in a `value`, tag every node with its
+//! `object` (or `kind`, before 0.32), keep element properties in `data`, and
+//! hold marks as objects in a list, either on text nodes or on the `leaves`
+//! they were split into. Blocks and inlines both become elements, and element
+//! data that isn't a string is kept as its JSON text. Selections are keyed by
+//! node in the oldest versions, so they aren't imported.
+
+use serde_json::Value;
+
+use super::json::ImportWarning;
+use crate::types::{Descendant, Editor, Element, MarkRegistry, Marks, Path, Text, DEFAULT_KIND};
+
+/// Convert a legacy document to an editor.
+pub fn from_string(json: &str) -> Result<Editor, ImportWarning> {
+    from_string_with(json, &MarkRegistry::default())
+}
+
+/// Convert a legacy document to an editor, looking up mark types in
+/// `registry`. A mark missing from it is an error.
+pub fn from_string_with(json: &str, registry: &MarkRegistry) -> Result<Editor, ImportWarning> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| ImportWarning::InvalidJson(e.to_string()))?;
+
+    let document = match object_type(&value) {
+        Some("value") => value.get("document").unwrap_or(&Value::Null),
+        _ => &value,
+    };
+    if object_type(document) != Some("document") {
+        return Err(ImportWarning::InvalidJson("not a document".into()));
+    }
+
+    let mut editor = Editor::new();
+    for (i, node) in nodes(document).iter().enumerate() {
+        for child in read_node(node, &Path::new(vec![i]), registry)? {
+            editor = editor.add_child(child);
+        }
+    }
+
+    if !editor.has_children() {
+        editor = Editor::with_default();
+    }
+
+    Ok(editor)
+}
+
+/// The type of a legacy value, named `object` since 0.32 and `kind` before.
+fn object_type(value: &Value) -> Option<&str> {
+    value
+        .get("object")
+        .or_else(|| value.get("kind"))
+        .and_then(Value::as_str)
+}
+
+fn nodes(value: &Value) -> &[Value] {
+    value
+        .get("nodes")
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Read a node, which is a list of them for a text split into leaves.
+fn read_node(
+    value: &Value,
+    path: &Path,
+    registry: &MarkRegistry,
+) -> Result<Vec<Descendant>, ImportWarning> {
+    match object_type(value) {
+        Some("block") | Some("inline") => {
+            let kind = value.get("type").and_then(Value::as_str);
+            let mut element = Element::with_kind(kind.unwrap_or(DEFAULT_KIND));
+
+            if let Some(Value::Object(data)) = value.get("data") {
+                for (key, value) in data {
+                    element = match value {
+                        Value::String(s) => element.add_property(key.as_str(), s.as_str()),
+                        Value::Null => element,
+                        _ => element.add_property(key.as_str(), value.to_string()),
+                    };
+                }
+            }
+
+            for (i, node) in nodes(value).iter().enumerate() {
+                for child in read_node(node, &path.concat(i), registry)? {
+                    element = element.add_child(child);
+                }
+            }
+
+            if !element.has_children() {
+                element = element.add_child(Text::new(""));
+            }
+
+            Ok(vec![element.into()])
+        }
+        Some("text") => match value.get("leaves").or_else(|| value.get("ranges")) {
+            Some(Value::Array(leaves)) if !leaves.is_empty() => leaves
+                .iter()
+                .map(|leaf| read_text(leaf, path, registry).map(Into::into))
+                .collect(),
+            _ => Ok(vec![read_text(value, path, registry)?.into()]),
+        },
+        _ => Err(ImportWarning::InvalidNode(path.clone())),
+    }
+}
+
+fn read_text(value: &Value, path: &Path, registry: &MarkRegistry) -> Result<Text, ImportWarning> {
+    let text = value.get("text").and_then(Value::as_str).unwrap_or("");
+    let mut marks = Marks::empty();
+
+    for mark in value
+        .get("marks")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice)
+    {
+        let name = mark
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ImportWarning::InvalidMark(path.clone(), mark.to_string()))?;
+
+        marks |= registry
+            .get(name)
+            .ok_or_else(|| ImportWarning::InvalidMark(path.clone(), name.into()))?;
+    }
+
+    Ok(Text::with_marks(text, marks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_value() {
+        let json = r#"{
+            "object": "value",
+            "document": {
+                "object": "document",
+                "data": {},
+                "nodes": [
+                    {
+                        "object": "block",
+                        "type": "heading-one",
+                        "data": { "align": "center", "level": 1 },
+                        "nodes": [
+                            {
+                                "object": "text",
+                                "text": "Hi ",
+                                "marks": [{ "object": "mark", "type": "bold", "data": {} }]
+                            },
+                            {
+                                "object": "inline",
+                                "type": "link",
+                                "data": { "href": "https://a" },
+                                "nodes": [{ "object": "text", "text": "there", "marks": [] }]
+                            }
+                        ]
+                    },
+                    { "object": "block", "type": "paragraph", "nodes": [] }
+                ]
+            }
+        }"#;
+
+        assert_eq!(
+            from_string(json).unwrap().children(),
+            vec![
+                Element::with_kind("heading-one")
+                    .add_property("align", "center")
+                    .add_property("level", "1")
+                    .add_child(Text::with_marks("Hi ", Marks::BOLD))
+                    .add_child(
+                        Element::with_kind("link")
+                            .add_property("href", "https://a")
+                            .add_child(Text::new("there"))
+                    )
+                    .into(),
+                Element::new().add_child(Text::new("")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_kind_and_leaves() {
+        let json = r#"{
+            "kind": "document",
+            "nodes": [{
+                "kind": "block",
+                "type": "paragraph",
+                "nodes": [{
+                    "kind": "text",
+                    "leaves": [
+                        { "kind": "leaf", "text": "a", "marks": [] },
+                        { "kind": "leaf", "text": "b", "marks": [{ "kind": "mark", "type": "strikethrough" }] }
+                    ]
+                }]
+            }]
+        }"#;
+
+        assert_eq!(
+            from_string(json).unwrap_err(),
+            ImportWarning::InvalidMark(vec![0, 0].into(), "strikethrough".into())
+        );
+
+        let mut registry = MarkRegistry::default();
+        let strike = registry.register("strikethrough").unwrap();
+        assert_eq!(
+            from_string_with(json, &registry).unwrap().children(),
+            vec![Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Text::with_marks("b", strike))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn invalid_documents() {
+        assert!(matches!(
+            from_string(r#"{ "children": [] }"#),
+            Err(ImportWarning::InvalidJson(_))
+        ));
+        assert_eq!(
+            from_string(r#"{ "object": "document", "nodes": [{ "text": "a" }] }"#).unwrap_err(),
+            ImportWarning::InvalidNode(vec![0].into())
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Pandoc's JSON AST, so documents can be converted by `pandoc -f json` to
+//! formats that need layout, like PDF, LaTeX and EPUB.
+//!
+//! Elements without a Pandoc equivalent become a `Div` or `Span` classed with
+//! their kind, holding their properties as attributes.
+
+use serde_json::{json, Value};
+
+use super::plain::inline_text;
+use crate::types::{has_inlines, Descendant, Editor, Element, Location, Marks, Text};
+
+/// The version of the Pandoc types the AST is written for.
+const API_VERSION: [u32; 3] = [1, 23, 1];
+
+const HEADINGS: &[&str] = &[
+    "heading-one",
+    "heading-two",
+    "heading-three",
+    "heading-four",
+    "heading-five",
+    "heading-six",
+];
+
+/// Marks and the inline constructors they are written as, outermost first.
+const MARKS: &[(Marks, &str)] = &[
+    (Marks::BOLD, "Strong"),
+    (Marks::ITALIC, "Emph"),
+    (Marks::UNDERLINE, "Underline"),
+];
+
+/// Serialize nodes to a Pandoc JSON document.
+pub fn to_string(nodes: &[Descendant]) -> String {
+    json!({
+        "pandoc-api-version": API_VERSION,
+        "meta": {},
+        "blocks": blocks(nodes),
+    })
+    .to_string()
+}
+
+/// Serialize the part of a document in a location to a Pandoc JSON
+/// document, with the text at its edges clipped to it.
+pub fn to_string_at(editor: &Editor, at: impl Into<Location>) -> String {
+    to_string(&editor.fragment(at))
+}
+
+fn blocks(nodes: &[Descendant]) -> Vec<Value> {
+    if has_inlines(nodes) {
+        return vec![json!({ "t": "Plain", "c": inlines(nodes) })];
+    }
+
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            Descendant::Element(e) => Some(block(e)),
+            Descendant::Text(_) => None,
+        })
+        .collect()
+}
+
+fn block(element: &Element) -> Value {
+    let children = element.children();
+    let inline = has_inlines(&children);
+
+    match element.kind() {
+        "paragraph" if inline => json!({ "t": "Para", "c": inlines(&children) }),
+        kind if inline && HEADINGS.contains(&kind) => {
+            let level = HEADINGS.iter().position(|h| *h == kind).unwrap() + 1;
+            json!({ "t": "Header", "c": [level, attr(element, false), inlines(&children)] })
+        }
+        "code-block" => {
+            json!({ "t": "CodeBlock", "c": [attr(element, false), inline_text(&children)] })
+        }
+        "block-quote" if inline => {
+            json!({ "t": "BlockQuote", "c": [{ "t": "Para", "c": inlines(&children) }] })
+        }
+        "block-quote" => json!({ "t": "BlockQuote", "c": blocks(&children) }),
+        "bulleted-list" => json!({ "t": "BulletList", "c": items(&children) }),
+        "numbered-list" => json!({
+            "t": "OrderedList",
+            "c": [[1, { "t": "Decimal" }, { "t": "Period" }], items(&children)],
+        }),
+        _ => json!({ "t": "Div", "c": [attr(element, true), blocks(&children)] }),
+    }
+}
+
+/// The blocks of each list item.
+fn items(children: &[Descendant]) -> Vec<Vec<Value>> {
+    children
+        .iter()
+        .map(|item| match item {
+            Descendant::Element(e) => blocks(&e.children()),
+            Descendant::Text(_) => blocks(std::slice::from_ref(item)),
+        })
+        .collect()
+}
+
+fn inlines(nodes: &[Descendant]) -> Vec<Value> {
+    let mut out = vec![];
+
+    for node in nodes {
+        match node {
+            Descendant::Text(t) => out.extend(text(t)),
+            Descendant::Element(e) if e.kind() == "link" => {
+                let url = e.property("url").unwrap_or("");
+                out.push(json!({
+                    "t": "Link",
+                    "c": [attr(e, false), inlines(&e.children()), [url, ""]],
+                }));
+            }
+            Descendant::Element(e) => {
+                out.push(json!({ "t": "Span", "c": [attr(e, true), inlines(&e.children())] }));
+            }
+        }
+    }
+
+    out
+}
+
+/// A text node as words separated by spaces and line breaks, or as inline
+/// code, inside the constructors of its marks.
+fn text(text: &Text) -> Vec<Value> {
+    let mut out = if text.marks().contains(Marks::CODE) && !text.text().is_empty() {
+        vec![json!({ "t": "Code", "c": [["", [], []], text.text()] })]
+    } else {
+        words(text.text())
+    };
+
+    for (mark, constructor) in MARKS.iter().rev() {
+        if text.marks().contains(*mark) && !out.is_empty() {
+            out = vec![json!({ "t": constructor, "c": out })];
+        }
+    }
+
+    out
+}
+
+fn words(text: &str) -> Vec<Value> {
+    let mut out = vec![];
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push(json!({ "t": "LineBreak" }));
+        }
+
+        for (j, word) in line.split(' ').enumerate() {
+            if j > 0 {
+                out.push(json!({ "t": "Space" }));
+            }
+            if !word.is_empty() {
+                out.push(json!({ "t": "Str", "c": word }));
+            }
+        }
+    }
+
+    out
+}
+
+/// The attributes of an element: an empty identifier, its kind as a class if
+/// it has no equivalent, and its properties.
+fn attr(element: &Element, classed: bool) -> Value {
+    let classes: Vec<&str> = if classed {
+        vec![element.kind()]
+    } else {
+        vec![]
+    };
+    let properties: Vec<_> = element
+        .properties()
+        .iter()
+        .filter(|(key, _)| !(element.kind() == "link" && *key == "url"))
+        .map(|(key, value)| json!([key, value]))
+        .collect();
+
+    json!(["", classes, properties])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks_of(nodes: &[Descendant]) -> Value {
+        let doc: Value = serde_json::from_str(&to_string(nodes)).unwrap();
+        assert_eq!(doc["pandoc-api-version"], json!([1, 23, 1]));
+        doc["blocks"].clone()
+    }
+
+    #[test]
+    fn paragraphs_and_headings() {
+        let blocks = blocks_of(&[
+            Element::with_kind("heading-two")
+                .add_child(Text::new("Title"))
+                .into(),
+            Element::new()
+                .add_child(Text::new("a "))
+                .add_child(Text::with_marks("b c", Marks::BOLD | Marks::ITALIC))
+                .add_child(
+                    Element::with_kind("link")
+                        .add_property("url", "https://a")
+                        .add_child(Text::new("d")),
+                )
+                .into(),
+        ]);
+
+        assert_eq!(
+            blocks,
+            json!([
+                { "t": "Header", "c": [2, ["", [], []], [{ "t": "Str", "c": "Title" }]] },
+                { "t": "Para", "c": [
+                    { "t": "Str", "c": "a" },
+                    { "t": "Space" },
+                    { "t": "Strong", "c": [{ "t": "Emph", "c": [
+                        { "t": "Str", "c": "b" },
+                        { "t": "Space" },
+                        { "t": "Str", "c": "c" },
+                    ] }] },
+                    { "t": "Link", "c": [["", [], []], [{ "t": "Str", "c": "d" }], ["https://a", ""]] },
+                ] },
+            ])
+        );
+    }
+
+    #[test]
+    fn inline_code() {
+        let blocks = blocks_of(&[Element::new()
+            .add_child(Text::new("run "))
+            .add_child(Text::with_marks("cargo test", Marks::CODE | Marks::ITALIC))
+            .into()]);
+
+        assert_eq!(
+            blocks,
+            json!([{ "t": "Para", "c": [
+                { "t": "Str", "c": "run" },
+                { "t": "Space" },
+                { "t": "Emph", "c": [{ "t": "Code", "c": [["", [], []], "cargo test"] }] },
+            ] }])
+        );
+    }
+
+    #[test]
+    fn lists_and_unknown_elements() {
+        let blocks = blocks_of(&[
+            Element::with_kind("numbered-list")
+                .add_child(Element::with_kind("list-item").add_child(Text::new("a")))
+                .into(),
+            Element::with_kind("callout")
+                .add_property("tone", "warn")
+                .add_child(Element::new().add_child(Text::new("b")))
+                .into(),
+        ]);
+
+        assert_eq!(
+            blocks,
+            json!([
+                { "t": "OrderedList", "c": [
+                    [1, { "t": "Decimal" }, { "t": "Period" }],
+                    [[{ "t": "Plain", "c": [{ "t": "Str", "c": "a" }] }]],
+                ] },
+                { "t": "Div", "c": [
+                    ["", ["callout"], [["tone", "warn"]]],
+                    [{ "t": "Para", "c": [{ "t": "Str", "c": "b" }] }],
+                ] },
+            ])
+        );
+    }
+}
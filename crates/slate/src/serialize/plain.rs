@@ -0,0 +1,149 @@
+//! Plain text, with one line per block.
+
+use super::{collapse_whitespace, collapsible, WhitespaceOptions};
+use crate::types::{has_inlines, Descendant, Editor, Element, Location, Text};
+
+/// Serialize nodes to plain text, separating blocks with newlines. Soft
+/// breaks are newlines too, so they're read back as separate blocks.
+pub fn to_string(nodes: &[Descendant]) -> String {
+    let mut lines = vec![];
+    collect_lines(nodes, &mut lines);
+    lines.join("\n")
+}
+
+/// Serialize the part of a document in a location to plain text, with the
+/// text at its edges clipped to it, as when copying the selection.
+pub fn to_string_at(editor: &Editor, at: impl Into<Location>) -> String {
+    to_string(&editor.fragment(at))
+}
+
+/// Deserialize plain text into one block of the default kind per line,
+/// keeping its whitespace as it is.
+pub fn from_string(text: &str) -> Vec<Descendant> {
+    from_string_with(text, &WhitespaceOptions::preserve())
+}
+
+/// Deserialize plain text into one block of the default kind per line,
+/// handling its whitespace as `whitespace` says. Plain text has no code, so
+/// `preserve_in_code` doesn't apply.
+pub fn from_string_with(text: &str, whitespace: &WhitespaceOptions) -> Vec<Descendant> {
+    text.lines()
+        .map(|line| {
+            let line = if whitespace.collapses(false) {
+                collapse_whitespace(line)
+            } else {
+                line.to_string()
+            };
+            let line = if whitespace.trims(false) {
+                line.trim_matches(collapsible)
+            } else {
+                &line
+            };
+            Element::new().add_child(Text::new(line)).into()
+        })
+        .collect()
+}
+
+/// Get the text content of inline nodes.
+pub(crate) fn inline_text<'a>(nodes: impl IntoIterator<Item = &'a Descendant>) -> String {
+    let mut out = String::new();
+
+    for node in nodes {
+        match node {
+            Descendant::Text(t) => out.push_str(t.text()),
+            Descendant::Element(e) => out.push_str(&inline_text(e.children_ref())),
+        }
+    }
+
+    out
+}
+
+fn collect_lines<'a>(
+    nodes: impl IntoIterator<Item = &'a Descendant> + Copy,
+    lines: &mut Vec<String>,
+) {
+    if has_inlines(nodes) {
+        lines.push(inline_text(nodes));
+        return;
+    }
+
+    for node in nodes {
+        if let Descendant::Element(e) = node {
+            collect_lines(e.children_ref(), lines);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Path, Point, Range};
+
+    use super::*;
+
+    #[test]
+    fn from_string_lines() {
+        assert_eq!(
+            from_string("one\ntwo"),
+            vec![
+                Element::new().add_child(Text::new("one")).into(),
+                Element::new().add_child(Text::new("two")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn from_string_with_whitespace() {
+        let text = "  a \t b  \n\tc";
+        assert_eq!(
+            from_string_with(text, &WhitespaceOptions::default()),
+            vec![
+                Element::new().add_child(Text::new("a b")).into(),
+                Element::new().add_child(Text::new("c")).into(),
+            ]
+        );
+        assert_eq!(
+            from_string_with(text, &WhitespaceOptions::default().trim_block_edges(false)),
+            vec![
+                Element::new().add_child(Text::new(" a b ")).into(),
+                Element::new().add_child(Text::new(" c")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_string_at_range() {
+        let editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(Element::new().add_child(Text::new("two")));
+        let point = |block, offset| Point {
+            path: vec![block, 0].into(),
+            offset,
+        };
+        let range = Range::new(point(0, 1), point(1, 2));
+
+        assert_eq!(to_string_at(&editor, range), "ne\ntw");
+        assert_eq!(to_string_at(&editor, Path::new(vec![1])), "two");
+    }
+
+    #[test]
+    fn to_string_inlines() {
+        let nodes = vec![Element::new()
+            .add_child(Text::new("a "))
+            .add_child(Element::with_kind("link").add_child(Text::new("link")))
+            .add_child(Text::new(" b"))
+            .into()];
+        assert_eq!(to_string(&nodes), "a link b");
+    }
+
+    #[test]
+    fn to_string_nested_blocks() {
+        let nodes = vec![
+            Element::new().add_child(Text::new("one")).into(),
+            Element::with_kind("bulleted-list")
+                .add_child(Element::with_kind("list-item").add_child(Text::new("two")))
+                .add_child(Element::with_kind("list-item").add_child(Text::new("three")))
+                .into(),
+        ];
+        assert_eq!(to_string(&nodes), "one\ntwo\nthree");
+    }
+}
@@ -0,0 +1,269 @@
+//! Persisting documents as snapshots and the operations applied since, in
+//! whatever database an app uses.
+//!
+//! A document's revision is the number of operations appended to it. A
+//! snapshot records the children at a revision, so loading a document takes
+//! its latest snapshot and the operations appended after it, and saving
+//! snapshots now and then keeps that list short.
+//!
+//! The store's methods return futures, so a backend can wait on its database
+//! without the crate depending on an async runtime. Stores are shared between
+//! threads and their futures are `Send`, so a server can await them on a
+//! multi-threaded runtime.
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::transforms::ApplyError;
+use crate::types::{Descendant, Editor, Operation};
+
+/// The children of a document at a revision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub revision: u64,
+    pub children: Vec<Descendant>,
+}
+
+/// A document as loaded from a store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stored {
+    /// The latest snapshot, or an empty document at revision 0 without one.
+    pub snapshot: Snapshot,
+    /// The operations appended after the snapshot, oldest first.
+    pub operations: Vec<Operation>,
+}
+
+impl Stored {
+    /// Get the revision of the document, after all of its operations.
+    pub fn revision(&self) -> u64 {
+        self.snapshot.revision + self.operations.len() as u64
+    }
+
+    /// Rebuild the document by applying the operations to the snapshot.
+    pub fn into_editor(self) -> Result<Editor, ApplyError> {
+        let mut editor = Editor::new();
+        editor.replace_children(self.snapshot.children);
+        for op in self.operations {
+            editor.apply(op)?;
+        }
+        editor.operations.clear();
+        editor.previous = None;
+        Ok(editor)
+    }
+}
+
+/// Where documents are persisted, by id.
+pub trait DocumentStore: Send + Sync {
+    type Error;
+
+    /// Load a document, or `None` if nothing was ever stored under its id.
+    fn load(&self, id: &str) -> impl Future<Output = Result<Option<Stored>, Self::Error>> + Send;
+
+    /// Save a snapshot of a document, which must be at a revision it has
+    /// reached.
+    fn save_snapshot(
+        &self,
+        id: &str,
+        snapshot: Snapshot,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Append operations to a document, like a batch returned by
+    /// `Editor::flush`. Returns the document's new revision.
+    fn append_operations(
+        &self,
+        id: &str,
+        operations: Vec<Operation>,
+    ) -> impl Future<Output = Result<u64, Self::Error>> + Send;
+
+    /// List the revisions a document has snapshots of, oldest first.
+    fn revisions(&self, id: &str) -> impl Future<Output = Result<Vec<u64>, Self::Error>> + Send;
+}
+
+/// Errors returned by a [`MemoryStore`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryStoreError {
+    /// A snapshot is of a revision past the document's latest one.
+    UnknownRevision(u64),
+}
+
+impl fmt::Display for MemoryStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemoryStoreError::UnknownRevision(r) => write!(f, "unknown revision {}", r),
+        }
+    }
+}
+
+impl Error for MemoryStoreError {}
+
+/// A store keeping documents in memory, for tests and as a reference for
+/// other backends.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    documents: Mutex<HashMap<String, Record>>,
+}
+
+#[derive(Debug, Default)]
+struct Record {
+    snapshots: Vec<Snapshot>,
+    operations: Vec<Operation>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn documents(&self) -> MutexGuard<'_, HashMap<String, Record>> {
+        self.documents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl DocumentStore for MemoryStore {
+    type Error = MemoryStoreError;
+
+    async fn load(&self, id: &str) -> Result<Option<Stored>, MemoryStoreError> {
+        let documents = self.documents();
+        let record = match documents.get(id) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        let snapshot = record.snapshots.last().cloned().unwrap_or(Snapshot {
+            revision: 0,
+            children: vec![],
+        });
+        let operations = record.operations[snapshot.revision as usize..].to_vec();
+        Ok(Some(Stored {
+            snapshot,
+            operations,
+        }))
+    }
+
+    async fn save_snapshot(&self, id: &str, snapshot: Snapshot) -> Result<(), MemoryStoreError> {
+        let mut documents = self.documents();
+        let record = documents.entry(id.to_string()).or_default();
+        if snapshot.revision > record.operations.len() as u64 {
+            return Err(MemoryStoreError::UnknownRevision(snapshot.revision));
+        }
+
+        // Keep the snapshots ordered by revision, replacing any of the same
+        // one.
+        let snapshots = &mut record.snapshots;
+        match snapshots.binary_search_by_key(&snapshot.revision, |s| s.revision) {
+            Ok(i) => snapshots[i] = snapshot,
+            Err(i) => snapshots.insert(i, snapshot),
+        }
+        Ok(())
+    }
+
+    async fn append_operations(
+        &self,
+        id: &str,
+        operations: Vec<Operation>,
+    ) -> Result<u64, MemoryStoreError> {
+        let mut documents = self.documents();
+        let record = documents.entry(id.to_string()).or_default();
+        record.operations.extend(operations);
+        Ok(record.operations.len() as u64)
+    }
+
+    async fn revisions(&self, id: &str) -> Result<Vec<u64>, MemoryStoreError> {
+        let documents = self.documents();
+        Ok(documents
+            .get(id)
+            .map(|record| record.snapshots.iter().map(|s| s.revision).collect())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use crate::types::{Element, Path, Text};
+
+    use super::*;
+
+    /// Run a future that's ready without waiting, as the memory store's are.
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        let mut cx = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut cx) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("the future to be ready"),
+        }
+    }
+
+    fn insert_text(offset: usize, text: &str) -> Operation {
+        Operation::InsertText {
+            path: Path::new(vec![0, 0]),
+            offset,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn load_snapshot_and_operations() {
+        let store = MemoryStore::new();
+        assert_eq!(ready(store.load("doc")), Ok(None));
+
+        let children = vec![Element::new().add_child(Text::new("")).into()];
+        ready(store.save_snapshot(
+            "doc",
+            Snapshot {
+                revision: 0,
+                children,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            ready(store.append_operations("doc", vec![insert_text(0, "ab")])),
+            Ok(1)
+        );
+
+        let stored = ready(store.load("doc")).unwrap().unwrap();
+        assert_eq!(stored.revision(), 1);
+        let editor = stored.into_editor().unwrap();
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("ab".into()));
+        assert!(editor.operations().is_empty());
+
+        ready(store.save_snapshot(
+            "doc",
+            Snapshot {
+                revision: 1,
+                children: editor.children(),
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            ready(store.append_operations("doc", vec![insert_text(2, "c")])),
+            Ok(2)
+        );
+        let stored = ready(store.load("doc")).unwrap().unwrap();
+        assert_eq!(stored.snapshot.revision, 1);
+        assert_eq!(stored.operations, vec![insert_text(2, "c")]);
+        assert_eq!(ready(store.revisions("doc")), Ok(vec![0, 1]));
+    }
+
+    #[test]
+    fn snapshots_of_unknown_revisions() {
+        let store = MemoryStore::new();
+        let snapshot = Snapshot {
+            revision: 3,
+            children: vec![],
+        };
+        assert_eq!(
+            ready(store.save_snapshot("doc", snapshot)),
+            Err(MemoryStoreError::UnknownRevision(3))
+        );
+    }
+}
@@ -0,0 +1,585 @@
+//! A store keeping documents in SQLite, a row per top-level block.
+//!
+//! Each block is stored under a key the store gives it when it's created,
+//! which stays the same as other blocks are inserted, removed or moved
+//! around it. Appending operations applies them to the store's copy of the
+//! document and rewrites only the blocks they changed, so a keystroke in a
+//! long document writes one row. Blocks that only moved just have their
+//! position updated.
+//!
+//! Only the latest revision of a document is kept, so its operations aren't
+//! stored and saving a snapshot rewrites all of its blocks. The store
+//! assumes it's the only one writing to its tables.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::serialize::json;
+use crate::transforms::ApplyError;
+use crate::types::{Affinity, Editor, MarkRegistry, Operation, Path};
+
+use super::{DocumentStore, Snapshot, Stored};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS slate_documents (
+        id TEXT PRIMARY KEY,
+        revision INTEGER NOT NULL,
+        next_key INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS slate_blocks (
+        document TEXT NOT NULL,
+        key INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        revision INTEGER NOT NULL,
+        node TEXT NOT NULL,
+        PRIMARY KEY (document, key)
+    );
+";
+
+/// Errors returned by a [`SqliteStore`].
+#[derive(Debug)]
+pub enum SqliteStoreError {
+    Sqlite(rusqlite::Error),
+    /// An appended operation doesn't apply to the stored document.
+    Apply(ApplyError),
+    /// A snapshot isn't of the document's latest revision, which is the only
+    /// one the store keeps.
+    UnknownRevision(u64),
+    /// The stored block at a position isn't a valid node.
+    InvalidBlock(usize),
+}
+
+impl fmt::Display for SqliteStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SqliteStoreError::Sqlite(e) => write!(f, "sqlite error: {}", e),
+            SqliteStoreError::Apply(e) => write!(f, "operation doesn't apply: {}", e),
+            SqliteStoreError::UnknownRevision(r) => write!(f, "unknown revision {}", r),
+            SqliteStoreError::InvalidBlock(i) => write!(f, "invalid block at {}", i),
+        }
+    }
+}
+
+impl Error for SqliteStoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SqliteStoreError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for SqliteStoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        SqliteStoreError::Sqlite(e)
+    }
+}
+
+/// A store keeping documents in a SQLite database.
+#[derive(Debug)]
+pub struct SqliteStore {
+    /// Locked after `documents` when both are.
+    connection: Mutex<Connection>,
+    /// The documents loaded so far, as they are in the database.
+    documents: Mutex<HashMap<String, Document>>,
+    registry: MarkRegistry,
+}
+
+#[derive(Debug)]
+struct Document {
+    editor: Editor,
+    /// The key of each top-level block, in order.
+    keys: Vec<i64>,
+    next_key: i64,
+    revision: u64,
+}
+
+/// The blocks operations have changed.
+#[derive(Debug, Default)]
+struct Changes {
+    dirty: HashSet<i64>,
+    removed: Vec<i64>,
+}
+
+impl Document {
+    fn new() -> Self {
+        Self {
+            editor: Editor::new(),
+            keys: vec![],
+            next_key: 0,
+            revision: 0,
+        }
+    }
+
+    fn new_key(&mut self) -> i64 {
+        self.next_key += 1;
+        self.next_key
+    }
+
+    /// Apply an operation, updating the keys of the top-level blocks and
+    /// recording which it changes.
+    fn apply(&mut self, op: Operation, changes: &mut Changes) -> Result<(), ApplyError> {
+        let dirty = |keys: &[i64], i: usize, changes: &mut Changes| {
+            if let Some(key) = keys.get(i) {
+                changes.dirty.insert(*key);
+            }
+        };
+
+        match &op {
+            Operation::InsertNode { path, .. } if path.len() == 1 => {
+                let key = self.new_key();
+                self.keys
+                    .insert(path.as_slice()[0].min(self.keys.len()), key);
+                changes.dirty.insert(key);
+            }
+            Operation::RemoveNode { path, .. } if path.len() == 1 => {
+                if path.as_slice()[0] < self.keys.len() {
+                    changes.removed.push(self.keys.remove(path.as_slice()[0]));
+                }
+            }
+            Operation::SplitNode { path, .. } if path.len() == 1 => {
+                let i = path.as_slice()[0];
+                let key = self.new_key();
+                self.keys.insert((i + 1).min(self.keys.len()), key);
+                dirty(&self.keys, i, changes);
+                changes.dirty.insert(key);
+            }
+            Operation::MergeNode { path, .. } if path.len() == 1 => {
+                let i = path.as_slice()[0];
+                if i > 0 && i < self.keys.len() {
+                    changes.removed.push(self.keys.remove(i));
+                    dirty(&self.keys, i - 1, changes);
+                }
+            }
+            Operation::MoveNode { path, .. } => {
+                let to = match Path::transform(path, &op, Affinity::Forward) {
+                    Ok(Some(to)) if !to.is_empty() && !path.is_empty() => to,
+                    _ => return self.editor.apply(op),
+                };
+                let (from, to_index) = (path.as_slice()[0], to.as_slice()[0]);
+
+                let moved = if path.len() == 1 && from < self.keys.len() {
+                    Some(self.keys.remove(from))
+                } else {
+                    dirty(&self.keys, from, changes);
+                    None
+                };
+                if to.len() == 1 {
+                    let key = match moved {
+                        Some(key) => key,
+                        None => {
+                            let key = self.new_key();
+                            changes.dirty.insert(key);
+                            key
+                        }
+                    };
+                    self.keys.insert(to_index.min(self.keys.len()), key);
+                } else {
+                    changes.removed.extend(moved);
+                    dirty(&self.keys, to_index, changes);
+                }
+            }
+            Operation::SetSelection { .. } => {}
+            Operation::InsertNode { path, .. }
+            | Operation::RemoveNode { path, .. }
+            | Operation::SplitNode { path, .. }
+            | Operation::MergeNode { path, .. }
+            | Operation::InsertText { path, .. }
+            | Operation::RemoveText { path, .. }
+            | Operation::SetNode { path, .. } => {
+                if let Some(i) = path.get(0) {
+                    dirty(&self.keys, i, changes);
+                }
+            }
+        }
+
+        self.editor.apply(op)
+    }
+}
+
+impl SqliteStore {
+    /// Keep documents in a database, creating the store's tables if they
+    /// don't exist yet.
+    pub fn new(connection: Connection) -> Result<Self, SqliteStoreError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            documents: Mutex::default(),
+            registry: MarkRegistry::default(),
+        })
+    }
+
+    /// Name the marks of the stored text with a registry, so custom marks
+    /// are kept.
+    pub fn with_mark_registry(mut self, registry: MarkRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Keep documents in the database file at a path, creating it if needed.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, SqliteStoreError> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn into_connection(self) -> Connection {
+        self.connection
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn documents(&self) -> MutexGuard<'_, HashMap<String, Document>> {
+        self.documents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Read a document from the database, or `None` if it isn't there.
+    fn read(&self, id: &str) -> Result<Option<Document>, SqliteStoreError> {
+        let connection = self.connection();
+        let row = connection
+            .query_row(
+                "SELECT revision, next_key FROM slate_documents WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .optional()?;
+        let (revision, next_key) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let mut statement = connection
+            .prepare("SELECT key, node FROM slate_blocks WHERE document = ?1 ORDER BY position")?;
+        let rows = statement.query_map(params![id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut document = Document::new();
+        let mut children = vec![];
+        for (i, row) in rows.enumerate() {
+            let (key, node) = row?;
+            let invalid = || SqliteStoreError::InvalidBlock(i);
+            let value: Value = serde_json::from_str(&node).map_err(|_| invalid())?;
+            let node = json::read_node(&value, &Path::new(vec![i]), &self.registry, &mut vec![])
+                .ok_or_else(invalid)?;
+            children.push(node);
+            document.keys.push(key);
+        }
+        document.editor.replace_children(children);
+        document.next_key = next_key;
+        document.revision = revision as u64;
+        Ok(Some(document))
+    }
+
+    /// Write the blocks of a document that changed, and the positions of
+    /// those that moved.
+    fn write(
+        &self,
+        id: &str,
+        document: &Document,
+        changes: &Changes,
+        positions: &HashMap<i64, usize>,
+    ) -> Result<(), SqliteStoreError> {
+        let connection = self.connection();
+        let transaction = connection.unchecked_transaction()?;
+        transaction.execute(
+            "INSERT INTO slate_documents (id, revision, next_key) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id) DO UPDATE SET revision = ?2, next_key = ?3",
+            params![id, document.revision as i64, document.next_key],
+        )?;
+
+        for key in &changes.removed {
+            transaction.execute(
+                "DELETE FROM slate_blocks WHERE document = ?1 AND key = ?2",
+                params![id, key],
+            )?;
+        }
+
+        for (i, (key, node)) in document
+            .keys
+            .iter()
+            .zip(document.editor.children.iter())
+            .enumerate()
+        {
+            if changes.dirty.contains(key) {
+                transaction.execute(
+                    "INSERT OR REPLACE INTO slate_blocks (document, key, position, revision, node)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        id,
+                        key,
+                        i as i64,
+                        document.revision as i64,
+                        json::node_value(node, &self.registry).to_string()
+                    ],
+                )?;
+            } else if positions.get(key) != Some(&i) {
+                transaction.execute(
+                    "UPDATE slate_blocks SET position = ?3 WHERE document = ?1 AND key = ?2",
+                    params![id, key, i as i64],
+                )?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Run `update` on a document, loading it first if needed, and forget it
+    /// if that fails so it's read again from the database next time.
+    fn update<T>(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut Document) -> Result<T, SqliteStoreError>,
+    ) -> Result<T, SqliteStoreError> {
+        let mut documents = self.documents();
+        let mut document = match documents.remove(id) {
+            Some(document) => document,
+            None => self.read(id)?.unwrap_or_else(Document::new),
+        };
+        let result = update(&mut document)?;
+        documents.insert(id.to_string(), document);
+        Ok(result)
+    }
+}
+
+impl DocumentStore for SqliteStore {
+    type Error = SqliteStoreError;
+
+    async fn load(&self, id: &str) -> Result<Option<Stored>, SqliteStoreError> {
+        let mut documents = self.documents();
+        if !documents.contains_key(id) {
+            match self.read(id)? {
+                Some(document) => documents.insert(id.into(), document),
+                None => return Ok(None),
+            };
+        }
+
+        let document = &documents[id];
+        Ok(Some(Stored {
+            snapshot: Snapshot {
+                revision: document.revision,
+                children: document.editor.children(),
+            },
+            operations: vec![],
+        }))
+    }
+
+    async fn save_snapshot(&self, id: &str, snapshot: Snapshot) -> Result<(), SqliteStoreError> {
+        self.update(id, |document| {
+            if snapshot.revision != document.revision {
+                return Err(SqliteStoreError::UnknownRevision(snapshot.revision));
+            }
+
+            let mut changes = Changes {
+                removed: document.keys.drain(..).collect(),
+                ..Changes::default()
+            };
+            for _ in &snapshot.children {
+                let key = document.new_key();
+                document.keys.push(key);
+                changes.dirty.insert(key);
+            }
+            document.editor.replace_children(snapshot.children);
+            self.write(id, document, &changes, &HashMap::new())
+        })
+    }
+
+    async fn append_operations(
+        &self,
+        id: &str,
+        operations: Vec<Operation>,
+    ) -> Result<u64, SqliteStoreError> {
+        self.update(id, |document| {
+            let positions = document
+                .keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| (*key, i))
+                .collect();
+            let mut changes = Changes::default();
+
+            document.revision += operations.len() as u64;
+            for op in operations {
+                document
+                    .apply(op, &mut changes)
+                    .map_err(SqliteStoreError::Apply)?;
+            }
+            document.editor.operations.clear();
+            document.editor.previous = None;
+
+            self.write(id, document, &changes, &positions)?;
+            Ok(document.revision)
+        })
+    }
+
+    async fn revisions(&self, id: &str) -> Result<Vec<u64>, SqliteStoreError> {
+        Ok(self
+            .load(id)
+            .await?
+            .map(|stored| vec![stored.snapshot.revision])
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use crate::types::{Descendant, Element, Node, Text};
+
+    use super::*;
+
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        let mut cx = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut cx) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("the future to be ready"),
+        }
+    }
+
+    /// Get the nodes of a document as its top-level blocks.
+    fn blocks(store: &SqliteStore, id: &str) -> Vec<(i64, i64, i64, Descendant)> {
+        let connection = store.connection();
+        let mut statement = connection
+            .prepare(
+                "SELECT key, position, revision, node FROM slate_blocks WHERE document = ?1
+                 ORDER BY position",
+            )
+            .unwrap();
+        statement
+            .query_map(params![id], |row| {
+                let node: String = row.get(3)?;
+                let value = serde_json::from_str(&node).unwrap();
+                let node =
+                    json::read_node(&value, &Path::new(vec![0]), &store.registry, &mut vec![])
+                        .unwrap();
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, node))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn block(text: &str) -> Descendant {
+        Element::new().add_child(Text::new(text)).into()
+    }
+
+    fn store() -> SqliteStore {
+        let store = SqliteStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        let snapshot = Snapshot {
+            revision: 0,
+            children: vec![block("a"), block("b"), block("c")],
+        };
+        ready(store.save_snapshot("doc", snapshot)).unwrap();
+        store
+    }
+
+    #[test]
+    fn write_only_changed_blocks() {
+        let store = store();
+        let ops = vec![
+            Operation::InsertText {
+                path: Path::new(vec![1, 0]),
+                offset: 1,
+                text: "!".into(),
+            },
+            Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: block("new").into(),
+            },
+        ];
+        assert_eq!(ready(store.append_operations("doc", ops)).unwrap(), 2);
+
+        // Only the edited and inserted blocks were written at revision 2, and
+        // the others kept their keys while moving down.
+        assert_eq!(
+            blocks(&store, "doc"),
+            vec![
+                (4, 0, 2, block("new")),
+                (1, 1, 0, block("a")),
+                (2, 2, 2, block("b!")),
+                (3, 3, 0, block("c")),
+            ]
+        );
+
+        // A new store reads the document back from the database.
+        let reopened = SqliteStore::new(store.into_connection()).unwrap();
+        let stored = ready(reopened.load("doc")).unwrap().unwrap();
+        assert_eq!(stored.revision(), 2);
+        assert_eq!(
+            stored.snapshot.children,
+            vec![block("new"), block("a"), block("b!"), block("c")]
+        );
+        assert_eq!(ready(reopened.revisions("doc")).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn track_moves_splits_and_merges() {
+        let store = store();
+        let ops = vec![
+            Operation::MoveNode {
+                path: Path::new(vec![0]),
+                new_path: Path::new(vec![2]),
+            },
+            Operation::SplitNode {
+                path: Path::new(vec![0, 0]),
+                position: 0,
+                properties: Default::default(),
+            },
+            Operation::RemoveNode {
+                path: Path::new(vec![0, 1]),
+                node: Node::Text(Text::new("b")),
+            },
+            Operation::MergeNode {
+                path: Path::new(vec![1]),
+                position: 1,
+                properties: Default::default(),
+            },
+        ];
+        ready(store.append_operations("doc", ops)).unwrap();
+
+        let stored = ready(store.load("doc")).unwrap().unwrap();
+        assert_eq!(
+            blocks(&store, "doc")
+                .into_iter()
+                .map(|(key, position, _, node)| (key, position, node))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    2,
+                    0,
+                    Element::new()
+                        .add_child(Text::new(""))
+                        .add_child(Text::new("c"))
+                        .into()
+                ),
+                (1, 1, block("a")),
+            ]
+        );
+        assert_eq!(stored.snapshot.children.len(), 2);
+
+        let bad = vec![Operation::RemoveNode {
+            path: Path::new(vec![5]),
+            node: block("x").into(),
+        }];
+        assert!(matches!(
+            ready(store.append_operations("doc", bad)),
+            Err(SqliteStoreError::Apply(_))
+        ));
+        assert_eq!(ready(store.load("doc")).unwrap().unwrap().revision(), 4);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::history::History;
+use crate::types::{Editor, Operation};
+
+/// A handle to a subscription, used to unsubscribe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(usize);
+
+type Callback = Arc<dyn Fn(&[Operation]) + Send + Sync>;
+
+/// The callbacks notified when a batch of operations is flushed, the history
+/// recording the batches, and how many batches are open.
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers {
+    callbacks: Vec<(SubscriptionId, Callback)>,
+    next_id: usize,
+    depth: usize,
+    pub(crate) history: Option<History>,
+}
+
+impl fmt::Debug for Subscribers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Subscribers")
+            .field("count", &self.callbacks.len())
+            .field("depth", &self.depth)
+            .field("history", &self.history.as_ref().map(History::len))
+            .finish()
+    }
+}
+
+// Subscribers aren't part of the document, so they never make two editors
+// unequal.
+impl PartialEq for Subscribers {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Subscribers {
+    pub(crate) fn notify(&self, ops: &[Operation]) {
+        for (_, callback) in &self.callbacks {
+            callback(ops);
+        }
+    }
+}
+
+impl Editor {
+    /// Call `callback` with the operations of each batch when it's flushed.
+    ///
+    /// Clones of the editor keep the subscriptions made before cloning.
+    pub fn subscribe(
+        &mut self,
+        callback: impl Fn(&[Operation]) + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let subscribers = &mut self.subscribers;
+        let id = SubscriptionId(subscribers.next_id);
+        subscribers.next_id += 1;
+        subscribers.callbacks.push((id, Arc::new(callback)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        self.subscribers.callbacks.retain(|(i, _)| *i != id);
+    }
+
+    /// Run a function making changes to the editor as a single batch, which is
+    /// flushed when it returns, so subscribers are notified once for all of
+    /// its operations. Batches can be nested, in which case only the outermost
+    /// one flushes. Flushing inside a batch does nothing, and if `f` panics,
+    /// its operations are left for the next flush.
+    pub fn batch<R>(&mut self, f: impl FnOnce(&mut Editor) -> R) -> R {
+        self.subscribers.depth += 1;
+        let batch = Batch(self);
+        let out = f(batch.0);
+        drop(batch);
+
+        if !self.in_batch() {
+            self.flush();
+        }
+
+        out
+    }
+
+    /// Whether a batch is open, so flushing is left to it.
+    pub(crate) fn in_batch(&self) -> bool {
+        self.subscribers.depth > 0
+    }
+}
+
+/// Closes a batch when dropped, even when unwinding from a panic in it.
+struct Batch<'a>(&'a mut Editor);
+
+impl Drop for Batch<'_> {
+    fn drop(&mut self) {
+        self.0.subscribers.depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::types::{Element, Marks, Path, Point, Range, Text};
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn record(editor: &mut Editor) -> (SubscriptionId, Arc<Mutex<Vec<usize>>>) {
+        let batches = Arc::new(Mutex::new(vec![]));
+        let recorded = batches.clone();
+        let id = editor.subscribe(move |ops| recorded.lock().unwrap().push(ops.len()));
+        (id, batches)
+    }
+
+    #[test]
+    fn batch_notifies_once() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abc")));
+        let (_, batches) = record(&mut editor);
+
+        editor.batch(|editor| {
+            editor.select(Range::new(point(vec![0, 0], 1), point(vec![0, 0], 2)));
+            editor.batch(|editor| editor.add_mark(Marks::BOLD));
+        });
+
+        // A selection, two splits, a selection and a set.
+        assert_eq!(*batches.lock().unwrap(), vec![5]);
+        assert!(editor.operations().is_empty());
+        assert!(editor.previous().is_some());
+    }
+
+    #[test]
+    fn empty_flush_and_unsubscribe() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abc")));
+        let (id, batches) = record(&mut editor);
+
+        editor.flush();
+        editor.select(Path::new(vec![0]));
+        editor.unsubscribe(id);
+        editor.flush();
+
+        assert!(batches.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn flush_inside_a_batch_waits_for_it() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abc")));
+        let (_, batches) = record(&mut editor);
+
+        editor.batch(|editor| {
+            editor.select(Path::new(vec![0]));
+            assert!(editor.flush().is_empty());
+            editor.insert_text("x", None);
+        });
+
+        // A selection, then replacing the selected text.
+        assert_eq!(*batches.lock().unwrap(), vec![3]);
+    }
+
+    #[test]
+    fn panicking_batch_closes() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abc")));
+        let (_, batches) = record(&mut editor);
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            editor.batch(|editor| {
+                editor.select(Path::new(vec![0]));
+                panic!("in a batch");
+            })
+        }));
+        assert!(panicked.is_err());
+
+        // What the batch applied is flushed by the next flush.
+        assert_eq!(editor.flush().len(), 1);
+        assert_eq!(*batches.lock().unwrap(), vec![1]);
+    }
+}
@@ -0,0 +1,675 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use crate::policy::{target_paths, Decision, Policy, PolicyHandle};
+use crate::store::{Snapshot, Stored};
+use crate::transforms::ApplyError;
+use crate::types::{Descendant, Editor, Operation, PathArena};
+
+impl Editor {
+    /// Bring the document back in line with an authoritative snapshot, like
+    /// one sent by a server after its content hash and ours diverge.
+    ///
+    /// `pending` are the local operations the snapshot doesn't include yet,
+    /// which were applied after the last operation it does. They are undone,
+    /// the operations turning the document into the snapshot are applied, and
+    /// they are applied again rebased onto those. The rebased operations that
+    /// still apply are returned, to be sent in place of `pending`.
+    pub fn resync(&mut self, snapshot: &[Descendant], pending: &[Operation]) -> Vec<Operation> {
+        let local = self.clone();
+
+        for op in pending.iter().rev() {
+            if self.apply(op.clone().inverse()).is_err() {
+                // The pending operations don't describe how the document got
+                // here, so they can't be kept.
+                *self = local;
+                self.apply_all(self.diff(snapshot));
+                return vec![];
+            }
+        }
+
+        let corrections = self.diff(snapshot);
+        self.apply_all(corrections.clone());
+
+        // The snapshot takes priority where it and local edits conflict.
+        let (_, rebased) = Operation::transform_all(&corrections, pending);
+        rebased
+            .into_iter()
+            .filter(|op| self.apply(op.clone()).is_ok())
+            .collect()
+    }
+
+    fn apply_all(&mut self, ops: Vec<Operation>) {
+        for op in ops {
+            self.apply(op).expect("operations from a diff to apply");
+        }
+    }
+}
+
+/// How many distinct paths a sequencer keeps interned before starting over.
+const MAX_PATHS: usize = 1 << 16;
+
+/// Errors returned when operations can't be sequenced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SequenceError {
+    /// The operations were made on a revision the sequencer doesn't have the
+    /// operations since, either because it's in the future or because it's
+    /// older than the document the sequencer started from.
+    UnknownRevision(u64),
+    /// A rebased operation doesn't apply to the document, so none of them
+    /// were sequenced.
+    Apply(ApplyError),
+    /// The sequencer's policy denied a rebased operation, so none of them
+    /// were sequenced.
+    Denied(Box<Operation>),
+    /// Rebasing, authorizing or applying the operations panicked, so none of
+    /// them were sequenced.
+    Panicked(String),
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SequenceError::UnknownRevision(r) => write!(f, "unknown revision {}", r),
+            SequenceError::Apply(e) => write!(f, "operation doesn't apply: {}", e),
+            SequenceError::Denied(op) => write!(f, "operation denied: {:?}", op),
+            SequenceError::Panicked(message) => write!(f, "sequencing panicked: {}", message),
+        }
+    }
+}
+
+impl Error for SequenceError {}
+
+/// Puts the operations of the clients editing a document in one order, as
+/// the authority they sync through.
+///
+/// Clients send the operations they made on top of a revision, the number of
+/// operations sequenced before them. Operations sequenced since are applied
+/// first, so the new ones are rebased onto them, and then they're sent to
+/// the other clients to be rebased onto their own pending operations.
+#[derive(Debug, Clone)]
+pub struct Sequencer {
+    document: Editor,
+    /// The revision the operations in `history` start at.
+    base: u64,
+    history: Vec<Operation>,
+    policy: Option<PolicyHandle>,
+    /// The paths of the operations rebased, kept across submissions as the
+    /// clients on a revision rebase onto the same operations.
+    paths: PathArena,
+}
+
+impl Sequencer {
+    /// Start sequencing a stored document from its latest revision.
+    pub fn new(stored: Stored) -> Result<Self, ApplyError> {
+        let mut document = Editor::new();
+        document.replace_children(stored.snapshot.children);
+        for op in &stored.operations {
+            document.apply(op.clone())?;
+        }
+        document.operations.clear();
+
+        Ok(Self {
+            document,
+            base: stored.snapshot.revision,
+            history: stored.operations,
+            policy: None,
+            paths: PathArena::new(),
+        })
+    }
+
+    /// Authorize the operations submitted with `policy`.
+    pub fn with_policy(mut self, policy: impl Policy + 'static) -> Self {
+        self.policy = Some(PolicyHandle(Arc::new(policy)));
+        self
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.base + self.history.len() as u64
+    }
+
+    /// Get the document at the latest revision.
+    pub fn document(&self) -> &Editor {
+        &self.document
+    }
+
+    /// Get a snapshot of the document at the latest revision, to be saved.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            revision: self.revision(),
+            children: self.document.children(),
+        }
+    }
+
+    /// Get the operations sequenced since a revision, to bring a client that
+    /// has it up to date.
+    pub fn operations_since(&self, revision: u64) -> Option<&[Operation]> {
+        let start = revision.checked_sub(self.base)? as usize;
+        self.history.get(start..)
+    }
+
+    /// Sequence operations a client made on top of a revision, returning them
+    /// rebased onto the operations sequenced since. Where they conflict, the
+    /// operations sequenced first win, and rebased operations that no longer
+    /// have an effect are dropped.
+    ///
+    /// Selection changes are the client's own, so they aren't sequenced. The
+    /// operations are authorized as an anonymous user's, with an empty id.
+    pub fn submit(
+        &mut self,
+        revision: u64,
+        ops: &[Operation],
+    ) -> Result<Vec<Operation>, SequenceError> {
+        self.submit_as("", revision, ops)
+    }
+
+    /// Sequence operations `user` made on top of a revision, like `submit`,
+    /// with each rebased operation authorized by the sequencer's policy. The
+    /// operations returned are the ones the policy let through, with those it
+    /// transformed replaced.
+    pub fn submit_as(
+        &mut self,
+        user: &str,
+        revision: u64,
+        ops: &[Operation],
+    ) -> Result<Vec<Operation>, SequenceError> {
+        if self.operations_since(revision).is_none() {
+            return Err(SequenceError::UnknownRevision(revision));
+        }
+        let ops: Vec<Operation> = ops
+            .iter()
+            .filter(|op| !matches!(op, Operation::SetSelection { .. }))
+            .cloned()
+            .collect();
+
+        // The batch is sequenced whole or not at all, even if a policy or a
+        // malformed operation panics, so the batches sequenced after it are
+        // unaffected. Children are structurally shared, so keeping the
+        // document to go back to doesn't copy the tree.
+        let before = self.document.clone();
+        let mut applied = vec![];
+        let sequenced = panic::catch_unwind(AssertUnwindSafe(|| {
+            let start = (revision - self.base) as usize;
+            let concurrent = self.history.get(start..).unwrap_or_default();
+            let (_, rebased) = Operation::transform_all_in(&mut self.paths, concurrent, &ops);
+            self.apply_authorized(user, rebased, &mut applied)
+        }))
+        .unwrap_or_else(|payload| {
+            self.paths.clear();
+            Err(SequenceError::Panicked(panic_message(payload)))
+        });
+        if self.paths.len() > MAX_PATHS {
+            self.paths.clear();
+        }
+        if let Err(e) = sequenced {
+            self.document = before;
+            return Err(e);
+        }
+        self.document.operations.clear();
+
+        self.history.extend(applied.iter().cloned());
+        Ok(applied)
+    }
+
+    /// Apply the operations the policy allows, collecting the ones applied,
+    /// until one is denied or doesn't apply. The operations a policy replaces
+    /// one with must be allowed as they are, and the rest of the batch is
+    /// rebased onto them.
+    fn apply_authorized(
+        &mut self,
+        user: &str,
+        ops: Vec<Operation>,
+        applied: &mut Vec<Operation>,
+    ) -> Result<(), SequenceError> {
+        let mut ops = ops.into_iter();
+        while let Some(op) = ops.next() {
+            let replacement = match self.authorize(user, &op) {
+                Decision::Allow => vec![op],
+                Decision::Deny => return Err(SequenceError::Denied(Box::new(op))),
+                Decision::Transform(replacement) => {
+                    let rest =
+                        rebase_onto_replacement(&mut self.paths, &op, &replacement, ops.collect());
+                    ops = rest.into_iter();
+                    for op in &replacement[..] {
+                        if self.authorize(user, op) != Decision::Allow {
+                            return Err(SequenceError::Denied(Box::new(op.clone())));
+                        }
+                        self.apply_sequenced(op.clone(), applied)?;
+                    }
+                    continue;
+                }
+            };
+            for op in replacement {
+                self.apply_sequenced(op, applied)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn authorize(&self, user: &str, op: &Operation) -> Decision {
+        match &self.policy {
+            Some(PolicyHandle(policy)) => {
+                policy.authorize(&self.document, user, op, &target_paths(op))
+            }
+            None => Decision::Allow,
+        }
+    }
+
+    fn apply_sequenced(
+        &mut self,
+        op: Operation,
+        applied: &mut Vec<Operation>,
+    ) -> Result<(), SequenceError> {
+        self.document
+            .apply(op.clone())
+            .map_err(SequenceError::Apply)?;
+        applied.push(op);
+        Ok(())
+    }
+}
+
+/// Get the message a panic was raised with.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+/// Rebase the operations made after `op` onto the operations a policy
+/// replaced it with. A replacement starting with an operation leaving
+/// positions where `op` does, like the same insertion with other properties,
+/// keeps the operations after it where they were, so they only move for the
+/// operations after that one. Otherwise they're rebased onto `op` being
+/// undone, and then onto the replacement.
+fn rebase_onto_replacement(
+    paths: &mut PathArena,
+    op: &Operation,
+    replacement: &[Operation],
+    ops: Vec<Operation>,
+) -> Vec<Operation> {
+    let onto: Vec<Operation> = match replacement.split_first() {
+        Some((first, rest)) if same_positions(op, first) => rest.to_vec(),
+        _ => std::iter::once(op.clone().inverse())
+            .chain(replacement.iter().cloned())
+            .collect(),
+    };
+    Operation::transform_all_in(paths, &onto, &ops).1
+}
+
+/// Check whether two operations move every position in a document the same
+/// way, whatever the content or properties they carry.
+fn same_positions(a: &Operation, b: &Operation) -> bool {
+    use Operation::*;
+
+    match (a, b) {
+        (InsertNode { path: p, .. }, InsertNode { path: q, .. })
+        | (RemoveNode { path: p, .. }, RemoveNode { path: q, .. })
+        | (SetNode { path: p, .. }, SetNode { path: q, .. }) => p == q,
+        (
+            InsertText {
+                path: p,
+                offset: o,
+                text: t,
+            },
+            InsertText {
+                path: q,
+                offset: r,
+                text: u,
+            },
+        )
+        | (
+            RemoveText {
+                path: p,
+                offset: o,
+                text: t,
+            },
+            RemoveText {
+                path: q,
+                offset: r,
+                text: u,
+            },
+        ) => p == q && o == r && t.len() == u.len(),
+        (
+            SplitNode {
+                path: p,
+                position: o,
+                ..
+            },
+            SplitNode {
+                path: q,
+                position: r,
+                ..
+            },
+        )
+        | (
+            MergeNode {
+                path: p,
+                position: o,
+                ..
+            },
+            MergeNode {
+                path: q,
+                position: r,
+                ..
+            },
+        ) => p == q && o == r,
+        (
+            MoveNode {
+                path: p,
+                new_path: o,
+            },
+            MoveNode {
+                path: q,
+                new_path: r,
+            },
+        ) => p == q && o == r,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Node, Operation, Path, Text};
+
+    use super::*;
+
+    fn insert_text(path: Vec<usize>, offset: usize, text: &str) -> Operation {
+        Operation::InsertText {
+            path: path.into(),
+            offset,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn resync_keeps_pending_edits() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("hello")));
+        let pending = vec![insert_text(vec![0, 0], 5, "!")];
+        editor.apply(pending[0].clone()).unwrap();
+
+        let snapshot = vec![
+            Element::with_kind("heading-one")
+                .add_child(Text::new("well hello"))
+                .into(),
+            Element::new().add_child(Text::new("there")).into(),
+        ];
+        let rebased = editor.resync(&snapshot, &pending);
+
+        assert_eq!(rebased, vec![insert_text(vec![0, 0], 10, "!")]);
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::with_kind("heading-one")
+                    .add_child(Text::new("well hello!"))
+                    .into(),
+                Element::new().add_child(Text::new("there")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn resync_drops_pending_edits_of_removed_content() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("a")))
+            .add_child(Element::new().add_child(Text::new("b")));
+        let pending = vec![insert_text(vec![1, 0], 1, "c")];
+        editor.apply(pending[0].clone()).unwrap();
+
+        let snapshot = vec![Element::new().add_child(Text::new("a")).into()];
+        assert!(editor.resync(&snapshot, &pending).is_empty());
+        assert_eq!(editor.children(), snapshot);
+        assert!(editor.descendant_ref(&Path::new(vec![1])).is_none());
+    }
+
+    #[test]
+    fn sequence_concurrent_operations() {
+        let children = vec![Element::new().add_child(Text::new("ab")).into()];
+        let stored = Stored {
+            snapshot: Snapshot {
+                revision: 4,
+                children,
+            },
+            operations: vec![insert_text(vec![0, 0], 2, "c")],
+        };
+        let mut sequencer = Sequencer::new(stored).unwrap();
+        assert_eq!(sequencer.revision(), 5);
+
+        // Made on revision 4, before "c" was inserted after "ab".
+        let rebased = sequencer
+            .submit(4, &[insert_text(vec![0, 0], 2, "d")])
+            .unwrap();
+        assert_eq!(rebased, vec![insert_text(vec![0, 0], 3, "d")]);
+        assert_eq!(sequencer.revision(), 6);
+        assert_eq!(
+            sequencer.document().block_text(&Path::new(vec![0])),
+            Some("abcd".into())
+        );
+        assert_eq!(
+            sequencer.operations_since(5),
+            Some(&[insert_text(vec![0, 0], 3, "d")][..])
+        );
+
+        assert_eq!(
+            sequencer.submit(3, &[]),
+            Err(SequenceError::UnknownRevision(3))
+        );
+        assert!(matches!(
+            sequencer.submit(
+                6,
+                &[
+                    insert_text(vec![0, 0], 0, "e"),
+                    insert_text(vec![0, 0], 9, "f")
+                ]
+            ),
+            Err(SequenceError::Apply(_))
+        ));
+        assert_eq!(sequencer.snapshot().revision, 6);
+        assert_eq!(
+            sequencer.document().block_text(&Path::new(vec![0])),
+            Some("abcd".into())
+        );
+    }
+
+    #[test]
+    fn failed_batch_leaves_document_unchanged() {
+        let children: Vec<Descendant> = vec![
+            Element::new().add_child(Text::new("one")).into(),
+            Element::new().add_child(Text::new("two")).into(),
+        ];
+        let stored = Stored {
+            snapshot: Snapshot {
+                revision: 0,
+                children: children.clone(),
+            },
+            operations: vec![],
+        };
+        let mut sequencer = Sequencer::new(stored).unwrap();
+
+        let moved = Operation::MoveNode {
+            path: Path::new(vec![0]),
+            new_path: Path::new(vec![9, 0]),
+        };
+        assert!(matches!(
+            sequencer.submit(0, &[insert_text(vec![1, 0], 3, "!"), moved]),
+            Err(SequenceError::Apply(_))
+        ));
+        assert_eq!(sequencer.revision(), 0);
+        assert_eq!(sequencer.document().children(), children);
+        assert!(sequencer.document().operations().is_empty());
+    }
+
+    /// Lets commenters only insert nodes, which become suggestions.
+    struct Commenters;
+
+    impl Policy for Commenters {
+        fn authorize(&self, _: &Editor, user: &str, op: &Operation, _: &[Path]) -> Decision {
+            match op {
+                _ if user != "commenter" => Decision::Allow,
+                Operation::InsertNode {
+                    node: Node::Element(element),
+                    ..
+                } if element.property("suggestion") == Some(user) => Decision::Allow,
+                Operation::InsertNode {
+                    path,
+                    node: Node::Element(element),
+                } => Decision::Transform(vec![Operation::InsertNode {
+                    path: path.clone(),
+                    node: Node::Element(element.clone().add_property("suggestion", user)),
+                }]),
+                _ => Decision::Deny,
+            }
+        }
+    }
+
+    #[test]
+    fn authorize_operations_with_policy() {
+        let stored = Stored {
+            snapshot: Snapshot {
+                revision: 0,
+                children: vec![Element::new().add_child(Text::new("a")).into()],
+            },
+            operations: vec![],
+        };
+        let mut sequencer = Sequencer::new(stored).unwrap().with_policy(Commenters);
+        let insert = Operation::InsertNode {
+            path: Path::new(vec![1]),
+            node: Node::Element(Element::new().add_child(Text::new("b"))),
+        };
+
+        let edit = insert_text(vec![0, 0], 1, "!");
+        assert_eq!(
+            sequencer.submit_as("commenter", 0, &[insert.clone(), edit.clone()]),
+            Err(SequenceError::Denied(Box::new(edit.clone())))
+        );
+        assert_eq!(sequencer.revision(), 0);
+        assert_eq!(sequencer.document().num_children(), 1);
+
+        let sequenced = sequencer.submit_as("commenter", 0, &[insert]).unwrap();
+        assert_eq!(
+            sequenced,
+            vec![Operation::InsertNode {
+                path: Path::new(vec![1]),
+                node: Node::Element(
+                    Element::new()
+                        .add_property("suggestion", "commenter")
+                        .add_child(Text::new("b"))
+                ),
+            }]
+        );
+        assert!(sequencer.submit(1, &[edit]).is_ok());
+    }
+
+    /// Quotes the text guests insert, and never lets them remove any.
+    struct Quoting;
+
+    impl Policy for Quoting {
+        fn authorize(&self, _: &Editor, user: &str, op: &Operation, _: &[Path]) -> Decision {
+            match op {
+                _ if user != "guest" => Decision::Allow,
+                Operation::InsertText { text, .. } if text.starts_with('"') => Decision::Allow,
+                Operation::InsertText { path, offset, text } => {
+                    Decision::Transform(vec![insert_text(
+                        path.as_slice().to_vec(),
+                        *offset,
+                        &format!("\"{}\"", text),
+                    )])
+                }
+                // Replacements are authorized again, so this is still denied.
+                op => Decision::Transform(vec![op.clone()]),
+            }
+        }
+    }
+
+    #[test]
+    fn transformed_operations_are_authorized_and_rebased() {
+        let stored = Stored {
+            snapshot: Snapshot {
+                revision: 0,
+                children: vec![Element::new().add_child(Text::new("xy")).into()],
+            },
+            operations: vec![],
+        };
+        let mut sequencer = Sequencer::new(stored).unwrap().with_policy(Quoting);
+
+        let remove = Operation::RemoveText {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+            text: "x".into(),
+        };
+        assert_eq!(
+            sequencer.submit_as("guest", 0, std::slice::from_ref(&remove)),
+            Err(SequenceError::Denied(Box::new(remove)))
+        );
+
+        // The second insertion was made after the first, and stays after it
+        // once that's quoted.
+        let ops = [
+            insert_text(vec![0, 0], 0, "ab"),
+            insert_text(vec![0, 0], 2, "c"),
+        ];
+        let sequenced = sequencer.submit_as("guest", 0, &ops).unwrap();
+        assert_eq!(
+            sequenced,
+            vec![
+                insert_text(vec![0, 0], 0, "\"ab\""),
+                insert_text(vec![0, 0], 4, "\"c\""),
+            ]
+        );
+        assert_eq!(
+            sequencer.document().block_text(&Path::new(vec![0])),
+            Some("\"ab\"\"c\"xy".into())
+        );
+    }
+
+    /// Panics on removals, like a policy with a bug.
+    struct Panicking;
+
+    impl Policy for Panicking {
+        fn authorize(&self, _: &Editor, _: &str, op: &Operation, _: &[Path]) -> Decision {
+            match op {
+                Operation::RemoveText { .. } => panic!("can't authorize a removal"),
+                _ => Decision::Allow,
+            }
+        }
+    }
+
+    #[test]
+    fn panics_only_reject_their_batch() {
+        let stored = Stored {
+            snapshot: Snapshot {
+                revision: 0,
+                children: vec![Element::new().add_child(Text::new("xy")).into()],
+            },
+            operations: vec![],
+        };
+        let mut sequencer = Sequencer::new(stored).unwrap().with_policy(Panicking);
+
+        let remove = Operation::RemoveText {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+            text: "x".into(),
+        };
+        assert_eq!(
+            sequencer.submit(0, &[insert_text(vec![0, 0], 2, "z"), remove]),
+            Err(SequenceError::Panicked("can't authorize a removal".into()))
+        );
+        assert_eq!(sequencer.revision(), 0);
+        assert_eq!(
+            sequencer.document().block_text(&Path::new(vec![0])),
+            Some("xy".into())
+        );
+
+        assert!(sequencer
+            .submit(0, &[insert_text(vec![0, 0], 0, "a")])
+            .is_ok());
+        assert_eq!(
+            sequencer.document().block_text(&Path::new(vec![0])),
+            Some("axy".into())
+        );
+    }
+}
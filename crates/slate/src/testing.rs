@@ -0,0 +1,510 @@
+//! Helpers for testing code that produces operations, for testing how an
+//! editor behaves as a user edits it, and for comparing documents against
+//! snapshots stored as fixtures.
+
+mod snapshot;
+
+pub use snapshot::{
+    assert_snapshot, canonical_json, fixtures_dir, load_fixture, pretty, snapshot_path,
+    UPDATE_SNAPSHOTS,
+};
+
+use crate::clipboard::ClipboardPayload;
+use crate::history::History;
+use crate::serialize::plain;
+use crate::transforms::Unit;
+use crate::types::{Children, Descendant, Editor, Location, Marks, Operation, Path, Range};
+
+/// Assert that two editors hold the same document, as `Editor::content_eq`
+/// checks, showing a line diff of the documents if they don't.
+#[macro_export]
+macro_rules! assert_doc_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(diff) = $crate::testing::doc_diff(&$left, &$right) {
+            panic!("documents differ (- left, + right):\n{}", diff);
+        }
+    };
+}
+
+/// Describe how the documents of two editors differ, as an outline of each
+/// with one node per line, diffed line by line. Returns `None` if they have
+/// the same content.
+pub fn doc_diff(left: &Editor, right: &Editor) -> Option<String> {
+    if left.content_eq(right) {
+        return None;
+    }
+
+    let (mut a, mut b) = (vec![], vec![]);
+    outline(&left.children, 0, &mut a);
+    outline(&right.children, 0, &mut b);
+
+    let mut out = String::new();
+    for (sign, line) in diff_lines(&a, &b) {
+        out.push(sign);
+        out.push(' ');
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    Some(out)
+}
+
+fn outline(children: &Children, depth: usize, out: &mut Vec<String>) {
+    for child in children.iter() {
+        let indent = "  ".repeat(depth);
+        match child {
+            Descendant::Element(e) => {
+                let mut line = format!("{}{}", indent, e.kind());
+                for (key, value) in e.properties() {
+                    line.push_str(&format!(" {}={:?}", key, value));
+                }
+                out.push(line);
+                outline(e.children_ref(), depth + 1, out);
+            }
+            Descendant::Text(t) => {
+                let mut line = format!("{}{:?}", indent, t.text());
+                if t.marks() != Marks::empty() {
+                    line.push(' ');
+                    bitflags::parser::to_writer(&t.marks(), &mut line)
+                        .expect("writing to a string to succeed");
+                }
+                if let Some(author) = t.author() {
+                    line.push_str(&format!(" by {}", author));
+                }
+                out.push(line);
+            }
+        }
+    }
+}
+
+/// Diff two lists of lines by their longest common subsequence, marking
+/// lines only in `a` with `-`, only in `b` with `+`, and shared ones with a
+/// space.
+fn diff_lines<'a>(a: &'a [String], b: &'a [String]) -> Vec<(char, &'a str)> {
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and
+    // b[j..].
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut out = vec![];
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push((' ', a[i].as_str()));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push(('-', a[i].as_str()));
+            i += 1;
+        } else {
+            out.push(('+', b[j].as_str()));
+            j += 1;
+        }
+    }
+
+    out
+}
+
+/// Check that two sequences of operations made concurrently against `doc`
+/// converge: applying `ops_a` then `ops_b` rebased onto it gives the same
+/// document as applying `ops_b` then `ops_a` rebased onto it.
+///
+/// Returns `false` if the documents differ or if any operation fails to
+/// apply. This is meant to be run against generated operations, to check
+/// that the operations an app creates transform cleanly.
+pub fn converges(doc: &Editor, ops_a: &[Operation], ops_b: &[Operation]) -> bool {
+    let (a_after_b, b_after_a) = Operation::transform_all(ops_a, ops_b);
+
+    let first = apply_all(doc, ops_a.iter().chain(&b_after_a));
+    let second = apply_all(doc, ops_b.iter().chain(&a_after_b));
+
+    match (first, second) {
+        (Some(first), Some(second)) => first.children == second.children,
+        _ => false,
+    }
+}
+
+fn apply_all<'a>(doc: &Editor, ops: impl IntoIterator<Item = &'a Operation>) -> Option<Editor> {
+    let mut editor = doc.clone();
+
+    for op in ops {
+        editor.apply(op.clone()).ok()?;
+    }
+
+    Some(editor)
+}
+
+/// A key a [`Session`] presses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Tab,
+}
+
+/// A simulated user editing a document through the editor's real transforms,
+/// as a frontend would drive them, so tests can check how plugins and
+/// configuration behave.
+///
+/// Each action is flushed as its own batch, like one event in a frontend,
+/// and recorded in the session's history. Actions return the session so
+/// they can be chained.
+#[derive(Debug, Clone)]
+pub struct Session {
+    editor: Editor,
+    history: History,
+    clipboard: Option<ClipboardPayload>,
+}
+
+impl Session {
+    /// Start editing, with the caret at the start of the document if the
+    /// editor has no selection.
+    pub fn new(mut editor: Editor) -> Self {
+        if editor.selection.is_none() {
+            if let Some(start) = editor.start(Path::new(vec![])) {
+                editor.select(start);
+            }
+        }
+        editor.flush();
+
+        Self {
+            editor,
+            history: History::new(),
+            clipboard: None,
+        }
+    }
+
+    pub fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    pub fn into_editor(self) -> Editor {
+        self.editor
+    }
+
+    /// Get the batches of operations the session's actions made.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn selection(&self) -> Option<&Range> {
+        self.editor.selection()
+    }
+
+    /// Get the text of the document, with one line per block.
+    pub fn text(&self) -> String {
+        plain::to_string(&self.editor.children())
+    }
+
+    fn act(&mut self, action: impl FnOnce(&mut Editor)) -> &mut Self {
+        action(&mut self.editor);
+        self.history.push(self.editor.flush());
+        self
+    }
+
+    /// Type text one character at a time, pressing enter for line breaks.
+    pub fn type_text(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            if c == '\n' {
+                self.press(Key::Enter);
+            } else {
+                self.act(|e| e.insert_text(c.encode_utf8(&mut [0; 4]), None));
+            }
+        }
+        self
+    }
+
+    pub fn press(&mut self, key: Key) -> &mut Self {
+        self.act(|e| match key {
+            Key::Enter => {
+                // A break past the editor's limits is ignored, as a frontend
+                // would.
+                let _ = e.insert_break();
+            }
+            Key::Backspace => e.delete_backward(),
+            Key::Delete => e.delete_forward(),
+            Key::Left => e.move_by(Unit::Character, true),
+            Key::Right => e.move_by(Unit::Character, false),
+            Key::Up => e.move_by(Unit::Line, true),
+            Key::Down => e.move_by(Unit::Line, false),
+            Key::Home => e.move_by(Unit::LineBoundary, true),
+            Key::End => e.move_by(Unit::LineBoundary, false),
+            Key::Tab => {
+                e.run_key("Tab");
+            }
+        })
+    }
+
+    /// Press a key with modifiers, like `"Mod-b"`, running the command bound
+    /// to it.
+    pub fn shortcut(&mut self, key: &str) -> &mut Self {
+        self.act(|e| {
+            e.run_key(key);
+        })
+    }
+
+    /// Run a command by name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the editor has no command of the name.
+    pub fn run(&mut self, command: &str) -> &mut Self {
+        self.act(|e| e.run_command(command).unwrap())
+    }
+
+    pub fn select(&mut self, target: impl Into<Location>) -> &mut Self {
+        let target = target.into();
+        self.act(|e| e.select(target))
+    }
+
+    /// Copy the selection to the session's clipboard.
+    pub fn copy(&mut self) -> &mut Self {
+        self.clipboard = self.editor.copy(None);
+        self
+    }
+
+    /// Cut the selection to the session's clipboard.
+    pub fn cut(&mut self) -> &mut Self {
+        let mut clipboard = None;
+        self.act(|e| clipboard = e.cut(None));
+        self.clipboard = clipboard;
+        self
+    }
+
+    /// Paste what was last copied or cut.
+    pub fn paste_clipboard(&mut self) -> &mut Self {
+        match self.clipboard.clone() {
+            Some(payload) => self.paste_payload(payload),
+            None => self,
+        }
+    }
+
+    /// Paste HTML, as copied from another app.
+    pub fn paste(&mut self, html: &str) -> &mut Self {
+        self.paste_payload(ClipboardPayload {
+            html: Some(html.into()),
+            ..ClipboardPayload::default()
+        })
+    }
+
+    pub fn paste_text(&mut self, text: &str) -> &mut Self {
+        self.paste_payload(ClipboardPayload {
+            plain_text: Some(text.into()),
+            ..ClipboardPayload::default()
+        })
+    }
+
+    fn paste_payload(&mut self, payload: ClipboardPayload) -> &mut Self {
+        // Pastes past the editor's limits are dropped, as a frontend would.
+        self.act(|e| {
+            let _ = e.paste(payload);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, Marks, Node, NodeProperties, Text};
+
+    fn doc() -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("hello world")))
+            .add_child(Element::new().add_child(Text::new("second")))
+    }
+
+    fn insert_text(path: Vec<usize>, offset: usize, text: &str) -> Operation {
+        Operation::InsertText {
+            path: path.into(),
+            offset,
+            text: text.into(),
+        }
+    }
+
+    fn remove_text(path: Vec<usize>, offset: usize, text: &str) -> Operation {
+        Operation::RemoveText {
+            path: path.into(),
+            offset,
+            text: text.into(),
+        }
+    }
+
+    fn split(path: Vec<usize>, position: usize) -> Operation {
+        Operation::SplitNode {
+            path: path.into(),
+            position,
+            properties: NodeProperties::new(),
+        }
+    }
+
+    #[test]
+    fn inserts_at_same_offset() {
+        assert!(converges(
+            &doc(),
+            &[insert_text(vec![0, 0], 5, "a")],
+            &[insert_text(vec![0, 0], 5, "b")],
+        ));
+    }
+
+    #[test]
+    fn insert_inside_removal() {
+        assert!(converges(
+            &doc(),
+            &[remove_text(vec![0, 0], 2, "llo w")],
+            &[insert_text(vec![0, 0], 4, "x")],
+        ));
+    }
+
+    #[test]
+    fn overlapping_removals() {
+        assert!(converges(
+            &doc(),
+            &[remove_text(vec![0, 0], 0, "hello")],
+            &[remove_text(vec![0, 0], 3, "lo wo")],
+        ));
+    }
+
+    #[test]
+    fn split_inside_removal() {
+        assert!(converges(
+            &doc(),
+            &[remove_text(vec![0, 0], 3, "lo wo")],
+            &[split(vec![0, 0], 5), insert_text(vec![0, 1], 0, "!")],
+        ));
+    }
+
+    #[test]
+    fn merge_and_insert_into_previous() {
+        let merge = Operation::MergeNode {
+            path: vec![1].into(),
+            position: 1,
+            properties: NodeProperties::new(),
+        };
+        let insert = Operation::InsertNode {
+            path: vec![0, 1].into(),
+            node: Node::Text(Text::new("!")),
+        };
+        assert!(converges(&doc(), &[merge], &[insert]));
+    }
+
+    #[test]
+    fn merge_and_edit_merged_node() {
+        let merge = || Operation::MergeNode {
+            path: vec![1].into(),
+            position: 1,
+            properties: NodeProperties::new(),
+        };
+        assert!(converges(
+            &doc(),
+            &[merge()],
+            &[split(vec![1, 0], 3), split(vec![1], 1)]
+        ));
+        assert!(converges(&doc(), &[split(vec![1], 1)], &[merge()]));
+
+        let set = Operation::SetNode {
+            path: vec![1].into(),
+            properties: NodeProperties::new().property("indent", None::<String>),
+            new_properties: NodeProperties::new().property("indent", Some("1")),
+        };
+        assert!(converges(&doc(), &[merge()], &[set]));
+    }
+
+    #[test]
+    fn set_node_on_the_same_node() {
+        let set = |key: &str, value: &str, marks: Marks| Operation::SetNode {
+            path: vec![1, 0].into(),
+            properties: NodeProperties::new()
+                .property(key, None::<String>)
+                .marks(Marks::empty()),
+            new_properties: NodeProperties::new()
+                .property(key, Some(value))
+                .marks(marks),
+        };
+
+        // Different keys and marks are both kept.
+        let a = set("color", "red", Marks::BOLD);
+        let b = set("background", "blue", Marks::ITALIC);
+        assert!(converges(&doc(), std::slice::from_ref(&a), &[b]));
+
+        // The same key is settled by priority.
+        let b = set("color", "blue", Marks::BOLD);
+        assert!(converges(&doc(), &[a], &[b]));
+    }
+
+    #[test]
+    fn doc_diff_shows_changed_lines() {
+        let left = doc();
+        let right = Editor::new()
+            .add_child(Element::new().add_child(Text::with_marks("hello world", Marks::BOLD)))
+            .add_child(Element::new().add_child(Text::new("second")));
+
+        assert_eq!(doc_diff(&left, &left.clone()), None);
+        assert_eq!(
+            doc_diff(&left, &right).unwrap(),
+            [
+                "  paragraph",
+                "-   \"hello world\"",
+                "+   \"hello world\" BOLD",
+                "  paragraph",
+                "    \"second\"",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn session_edits_like_a_user() {
+        let mut session = Session::new(Editor::with_default());
+        session
+            .type_text("hello\nworld")
+            .press(Key::Home)
+            .press(Key::Backspace)
+            .type_text(" ");
+        assert_eq!(session.text(), "hello world");
+        assert_eq!(session.history().len(), 14);
+
+        session
+            .select(Path::new(vec![0]))
+            .shortcut("Mod-b")
+            .cut()
+            .paste("<p>a <em>b</em></p>")
+            .press(Key::End)
+            .paste_clipboard();
+        assert_eq!(session.text(), "a bhello world");
+
+        // Pasting splits texts without merging them back, as normalizing does.
+        let mut editor = session.into_editor();
+        editor.normalize();
+        crate::assert_doc_eq!(
+            editor,
+            Editor::new().add_child(
+                Element::new()
+                    .add_child(Text::new("a "))
+                    .add_child(Text::with_marks("b", Marks::ITALIC))
+                    .add_child(Text::with_marks("hello world", Marks::BOLD))
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "documents differ")]
+    fn assert_doc_eq_panics() {
+        crate::assert_doc_eq!(doc(), Editor::with_default());
+    }
+}
@@ -0,0 +1,210 @@
+//! Golden-file snapshots of documents, for tests whose expected documents
+//! are too long to spell out with builders.
+//!
+//! Fixtures live in a crate's `tests/fixtures` directory. Documents to start
+//! tests from are `<name>.json`, in the shape `serialize::json` reads, and
+//! snapshots are under `snapshots/`, as `<name>.txt` for outlines and
+//! `<name>.json` for canonical JSON. Names may hold slashes to group the
+//! fixtures of a suite, like `normalize/merge_texts`.
+//!
+//! A missing snapshot is written by the first run of its test. A snapshot
+//! that doesn't match fails with a diff, unless `SLATE_UPDATE_SNAPSHOTS` is
+//! set, in which case it's overwritten, for the changes to be reviewed in
+//! version control.
+
+use std::env;
+use std::fs;
+use std::path::{Path as FsPath, PathBuf};
+
+use serde_json::Value;
+
+use super::{diff_lines, outline};
+use crate::serialize::json;
+use crate::types::{Editor, Point};
+
+/// The environment variable that makes snapshots be overwritten.
+pub const UPDATE_SNAPSHOTS: &str = "SLATE_UPDATE_SNAPSHOTS";
+
+/// Assert that a document matches a snapshot of its outline, as
+/// `testing::pretty` writes it, stored in the calling crate's fixtures.
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($name:expr, $editor:expr $(,)?) => {
+        $crate::testing::assert_snapshot(
+            &$crate::testing::snapshot_path(env!("CARGO_MANIFEST_DIR"), $name, "txt"),
+            &$crate::testing::pretty(&$editor),
+        )
+    };
+}
+
+/// Assert that a document matches a snapshot of its canonical JSON, stored
+/// in the calling crate's fixtures.
+#[macro_export]
+macro_rules! assert_json_snapshot {
+    ($name:expr, $editor:expr $(,)?) => {
+        $crate::testing::assert_snapshot(
+            &$crate::testing::snapshot_path(env!("CARGO_MANIFEST_DIR"), $name, "json"),
+            &$crate::testing::canonical_json(&$editor),
+        )
+    };
+}
+
+/// Load a document from the calling crate's fixtures.
+#[macro_export]
+macro_rules! fixture {
+    ($name:expr) => {
+        $crate::testing::load_fixture(
+            &$crate::testing::fixtures_dir(env!("CARGO_MANIFEST_DIR")),
+            $name,
+        )
+    };
+}
+
+/// Write a document as an outline, with one node per line as `doc_diff`
+/// shows them, followed by the selection if there is one.
+pub fn pretty(editor: &Editor) -> String {
+    let mut lines = vec![];
+    outline(&editor.children, 0, &mut lines);
+
+    let point = |p: &Point| format!("{:?}:{}", p.path.as_slice(), p.offset);
+    if let Some(selection) = editor.selection() {
+        let mut line = format!("selection {}", point(selection.anchor()));
+        if !selection.is_collapsed() {
+            line.push_str(&format!(" -> {}", point(selection.focus())));
+        }
+        lines.push(line);
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Write a document's canonical JSON, indented so that snapshots of it diff
+/// by line.
+pub fn canonical_json(editor: &Editor) -> String {
+    let canonical = json::to_canonical_string(&editor.children());
+    let value: Value = serde_json::from_str(&canonical).expect("canonical JSON to parse");
+    serde_json::to_string_pretty(&value).expect("JSON to serialize") + "\n"
+}
+
+/// Get the fixtures directory of the crate with a manifest directory.
+pub fn fixtures_dir(manifest_dir: &str) -> PathBuf {
+    FsPath::new(manifest_dir).join("tests").join("fixtures")
+}
+
+/// Get where a snapshot is stored in the fixtures of the crate with a
+/// manifest directory.
+pub fn snapshot_path(manifest_dir: &str, name: &str, extension: &str) -> PathBuf {
+    fixtures_dir(manifest_dir)
+        .join("snapshots")
+        .join(format!("{}.{}", name, extension))
+}
+
+/// Load the document `<name>.json` from a fixtures directory, panicking if
+/// it's missing or isn't a valid document.
+pub fn load_fixture(dir: &FsPath, name: &str) -> Editor {
+    let path = dir.join(format!("{}.json", name));
+    let json = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("reading fixture {}: {}", path.display(), e));
+
+    json::from_string(&json).unwrap_or_else(|e| panic!("loading fixture {}: {}", path.display(), e))
+}
+
+/// Assert that text matches the snapshot stored at a path, writing it if
+/// there's none yet or `SLATE_UPDATE_SNAPSHOTS` is set.
+pub fn assert_snapshot(path: &FsPath, actual: &str) {
+    let expected = fs::read_to_string(path).ok();
+    if expected.as_deref() == Some(actual) {
+        return;
+    }
+
+    match expected {
+        Some(expected) if env::var_os(UPDATE_SNAPSHOTS).is_none() => {
+            let (a, b): (Vec<_>, Vec<_>) = (
+                expected.lines().map(String::from).collect(),
+                actual.lines().map(String::from).collect(),
+            );
+            let mut diff = String::new();
+            for (sign, line) in diff_lines(&a, &b) {
+                diff.push_str(&format!("{} {}\n", sign, line));
+            }
+            panic!(
+                "snapshot {} differs (- stored, + actual), set {} to update it:\n{}",
+                path.display(),
+                UPDATE_SNAPSHOTS,
+                diff
+            );
+        }
+        _ => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).expect("creating the snapshot directory");
+            }
+            fs::write(path, actual)
+                .unwrap_or_else(|e| panic!("writing snapshot {}: {}", path.display(), e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Marks, Path, Range, Text};
+
+    use super::*;
+
+    fn doc() -> Editor {
+        let mut editor = Editor::new().add_child(
+            Element::with_kind("heading")
+                .add_property("level", "1")
+                .add_child(Text::new("hello "))
+                .add_child(Text::with_marks("world", Marks::BOLD)),
+        );
+        editor.select(Range::new(
+            Point {
+                path: Path::new(vec![0, 0]),
+                offset: 1,
+            },
+            Point {
+                path: Path::new(vec![0, 1]),
+                offset: 2,
+            },
+        ));
+        editor
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("slate-snapshot-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn pretty_prints_outline_and_selection() {
+        assert_eq!(
+            pretty(&doc()),
+            "heading level=\"1\"\n  \"hello \"\n  \"world\" BOLD\nselection [0, 0]:1 -> [0, 1]:2\n"
+        );
+        assert!(canonical_json(&doc()).contains("\"bold\": true"));
+    }
+
+    #[test]
+    fn snapshots_are_written_then_compared() {
+        let path = temp_dir("compare").join("suite/doc.txt");
+        assert_snapshot(&path, &pretty(&doc()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), pretty(&doc()));
+        assert_snapshot(&path, &pretty(&doc()));
+
+        let changed = pretty(&Editor::with_default());
+        let result = std::panic::catch_unwind(|| assert_snapshot(&path, &changed));
+        let message = result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("- heading level=\"1\""), "{}", message);
+    }
+
+    #[test]
+    fn load_fixtures() {
+        let dir = temp_dir("fixtures");
+        fs::create_dir_all(dir.join("suite")).unwrap();
+        fs::write(dir.join("suite/doc.json"), json::to_string(&doc())).unwrap();
+
+        let loaded = load_fixture(&dir, "suite/doc");
+        assert_eq!(pretty(&loaded), pretty(&doc()));
+    }
+}
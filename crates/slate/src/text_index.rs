@@ -0,0 +1,268 @@
+use std::mem;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::node_cache::NodeCache;
+use crate::types::{text_entries, Children, Descendant, Editor, Operation, Path, Point};
+
+/// The text of an element's leaves concatenated in document order, along with
+/// the offset each leaf starts at.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct BlockText {
+    text: String,
+    /// Each leaf's path relative to the element, and its offset in `text`.
+    leaves: Vec<(Path, usize)>,
+}
+
+impl BlockText {
+    fn new(children: &Children) -> Self {
+        let mut block = BlockText::default();
+
+        for (t, path) in text_entries(children, &Path::new(vec![])) {
+            block.leaves.push((path, block.text.len()));
+            block.text.push_str(t.text());
+        }
+
+        block
+    }
+
+    /// Find the leaf an offset falls in and the offset within it. Offsets on
+    /// the boundary between two leaves resolve to the end of the first.
+    fn leaf_at(&self, offset: usize) -> Option<(&Path, usize)> {
+        if offset > self.text.len() {
+            return None;
+        }
+
+        let i = self.leaves.partition_point(|(_, start)| *start < offset);
+        let (path, start) = self.leaves.get(i.saturating_sub(1))?;
+        Some((path, offset - start))
+    }
+}
+
+/// A cache of the text of blocks, so that converting between points and
+/// offsets in a block doesn't need to walk its leaves every time.
+///
+/// Entries are keyed by the path of the element they index, and only the
+/// blocks that were edited get rebuilt.
+///
+/// Lookups index blocks through a shared reference, so the blocks are behind
+/// a lock for editors to be shared across threads.
+#[derive(Debug, Default)]
+pub(crate) struct TextIndex {
+    blocks: Mutex<NodeCache<BlockText>>,
+}
+
+impl Clone for TextIndex {
+    fn clone(&self) -> Self {
+        Self {
+            blocks: Mutex::new(self.blocks().clone()),
+        }
+    }
+}
+
+// The index only caches what's in the document, so it never makes two editors
+// unequal.
+impl PartialEq for TextIndex {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl TextIndex {
+    /// Lock the indexed blocks. The index is only a cache, so one left behind
+    /// by a panic is still used.
+    fn blocks(&self) -> MutexGuard<'_, NodeCache<BlockText>> {
+        self.blocks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn blocks_mut(&mut self) -> &mut NodeCache<BlockText> {
+        self.blocks
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Update the index for an operation that has just been applied.
+    pub(crate) fn apply(&mut self, op: &Operation) {
+        self.blocks_mut().apply(op);
+    }
+
+    pub(crate) fn apply_batch(&mut self, ops: &[Operation]) {
+        self.blocks_mut().apply_batch(ops);
+    }
+
+    /// Estimate the bytes the index takes on the heap.
+    pub(crate) fn heap_size(&self) -> usize {
+        self.blocks().heap_size(|block| {
+            let leaves: usize = block
+                .leaves
+                .iter()
+                .map(|(path, _)| path.len() * mem::size_of::<usize>())
+                .sum();
+            block.text.capacity()
+                + block.leaves.capacity() * mem::size_of::<(Path, usize)>()
+                + leaves
+        })
+    }
+}
+
+impl Editor {
+    /// Run `f` on the indexed text of the element at `block`, indexing it first
+    /// if it isn't already.
+    fn with_block_text<T>(&self, block: &Path, f: impl FnOnce(&BlockText) -> T) -> Option<T> {
+        let mut blocks = self.text_index.blocks();
+
+        if blocks.get(block).is_none() {
+            let children = match self.descendant_ref(block)? {
+                Descendant::Element(e) => e.children_ref(),
+                Descendant::Text(_) => return None,
+            };
+            blocks.insert(block.clone(), BlockText::new(children));
+        }
+
+        blocks.get(block).map(f)
+    }
+
+    /// Get the text of the block at a path, with the text of all of its leaves
+    /// concatenated.
+    pub fn block_text(&self, block: &Path) -> Option<String> {
+        self.with_block_text(block, |b| b.text.clone())
+    }
+
+    /// Get the block containing a point, and the point's offset in the block's
+    /// text.
+    pub fn block_offset(&self, point: &Point) -> Option<(Path, usize)> {
+        let block = self.block_path(&point.path)?;
+        let leaf = point.path.relative(&block)?;
+
+        let start = self.with_block_text(&block, |b| {
+            b.leaves
+                .iter()
+                .find(|(path, _)| path == &leaf)
+                .map(|(_, start)| *start)
+        })??;
+
+        Some((block, start + point.offset))
+    }
+
+    /// Get the point at an offset in the text of a block. Offsets between two
+    /// leaves resolve to the end of the first one.
+    pub fn point_at_offset(&self, block: &Path, offset: usize) -> Option<Point> {
+        self.with_block_text(block, |b| {
+            b.leaf_at(offset).map(|(leaf, offset)| Point {
+                path: Path::new([block.as_slice(), leaf.as_slice()].concat()),
+                offset,
+            })
+        })?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, NodeProperties, Text};
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("one"))
+                    .add_child(Element::with_kind("link").add_child(Text::new("two")))
+                    .add_child(Text::new("three")),
+            )
+            .add_child(Element::new().add_child(Text::new("four")))
+    }
+
+    #[test]
+    fn block_text_concatenates_leaves() {
+        let editor = editor();
+        assert_eq!(
+            editor.block_text(&vec![0].into()),
+            Some("onetwothree".into())
+        );
+    }
+
+    #[test]
+    fn block_offset_in_inline() {
+        let editor = editor();
+        assert_eq!(
+            editor.block_offset(&point(vec![0, 1, 0], 2)),
+            Some((vec![0].into(), 5))
+        );
+    }
+
+    #[test]
+    fn point_at_offset_boundary() {
+        let editor = editor();
+        assert_eq!(
+            editor.point_at_offset(&vec![0].into(), 3),
+            Some(point(vec![0, 0], 3))
+        );
+        assert_eq!(
+            editor.point_at_offset(&vec![0].into(), 6),
+            Some(point(vec![0, 1, 0], 3))
+        );
+        assert_eq!(editor.point_at_offset(&vec![0].into(), 12), None);
+    }
+
+    #[test]
+    fn edits_rebuild_block() {
+        let mut editor = editor();
+        editor.block_text(&vec![1].into());
+        editor
+            .apply(Operation::InsertText {
+                path: vec![1, 0].into(),
+                offset: 4,
+                text: "!".into(),
+            })
+            .unwrap();
+        assert_eq!(editor.block_text(&vec![1].into()), Some("four!".into()));
+    }
+
+    #[test]
+    fn removing_sibling_moves_entry() {
+        let mut editor = editor();
+        editor.block_text(&vec![1].into());
+        let node = editor.node_at(&vec![0].into()).unwrap();
+        editor
+            .apply(Operation::RemoveNode {
+                path: vec![0].into(),
+                node,
+            })
+            .unwrap();
+        assert_eq!(
+            editor
+                .text_index
+                .blocks()
+                .get(&vec![0].into())
+                .cloned()
+                .as_ref(),
+            Some(&BlockText {
+                text: "four".into(),
+                leaves: vec![(vec![0].into(), 0)],
+            })
+        );
+    }
+
+    #[test]
+    fn merge_rebuilds_previous_block() {
+        let mut editor = editor();
+        editor.block_text(&vec![0].into());
+        editor
+            .apply(Operation::MergeNode {
+                path: vec![1].into(),
+                position: 3,
+                properties: NodeProperties::new(),
+            })
+            .unwrap();
+        assert_eq!(
+            editor.block_text(&vec![0].into()),
+            Some("onetwothreefour".into())
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use crate::types::{
+    text_entries, Descendant, Editor, Element, Location, Marks, Path, Point, Range,
+};
+
+/// A run of text with everything it's inside of, for consumers like exporters,
+/// search indexes and text-to-speech that want the document as a stream
+/// rather than a tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+    /// The text, clipped to the location the tokens are of.
+    pub text: &'a str,
+    /// The marks of the text.
+    pub marks: Marks,
+    /// The elements the text is in, with their paths, outermost first.
+    pub element_stack: Vec<(&'a Element, Path)>,
+    /// Where the text is in the document.
+    pub range: Range,
+}
+
+impl Editor {
+    /// Get the texts in a location as tokens, in document order.
+    ///
+    /// Texts the location only touches are left out, but empty texts inside
+    /// it aren't, so that empty blocks and voids still show up in the stream.
+    pub fn tokens(&self, at: impl Into<Location>) -> impl Iterator<Item = Token<'_>> {
+        let range = self.range(at);
+        let entries = match &range {
+            Some(_) => text_entries(&self.children, &Path::new(vec![])),
+            None => vec![],
+        };
+
+        entries.into_iter().filter_map(move |(text, path)| {
+            let (start, end) = range.as_ref()?.edges(false);
+            if path.is_before(&start.path) || path.is_after(&end.path) {
+                return None;
+            }
+
+            let len = text.text().len();
+            let from = if path == start.path { start.offset } else { 0 };
+            let to = if path == end.path { end.offset } else { len };
+            let (from, to) = (from.min(len), to.min(len));
+            if from == to && len > 0 {
+                return None;
+            }
+
+            let element_stack = path
+                .ancestors(false)
+                .into_iter()
+                .skip(1)
+                .filter_map(|ancestor| match self.descendant_ref(&ancestor) {
+                    Some(Descendant::Element(element)) => Some((element, ancestor)),
+                    _ => None,
+                })
+                .collect();
+            let point = |offset| Point {
+                path: path.clone(),
+                offset,
+            };
+
+            Some(Token {
+                text: &text.text()[from..to],
+                marks: text.marks(),
+                element_stack,
+                range: Range::new(point(from), point(to)),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Text;
+
+    use super::*;
+
+    fn doc() -> Editor {
+        Editor::new()
+            .add_child(
+                Element::with_kind("paragraph")
+                    .add_child(Text::new("see "))
+                    .add_child(
+                        Element::with_kind("link")
+                            .add_property("url", "https://a.example")
+                            .add_child(Text::with_marks("here", Marks::BOLD)),
+                    ),
+            )
+            .add_child(Element::with_kind("paragraph").add_child(Text::new("")))
+            .add_child(Element::with_kind("paragraph").add_child(Text::new("end")))
+    }
+
+    #[test]
+    fn tokens_carry_their_context() {
+        let editor = doc();
+        let tokens: Vec<_> = editor.tokens(Path::new(vec![])).collect();
+
+        let texts: Vec<_> = tokens.iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["see ", "here", "", "end"]);
+
+        let link = &tokens[1];
+        assert_eq!(link.marks, Marks::BOLD);
+        let stack: Vec<_> = link
+            .element_stack
+            .iter()
+            .map(|(element, path)| (element.kind(), path.clone()))
+            .collect();
+        assert_eq!(
+            stack,
+            vec![
+                ("paragraph", Path::new(vec![0])),
+                ("link", Path::new(vec![0, 1])),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_are_clipped_to_the_location() {
+        let editor = doc();
+        let point = |path: Vec<usize>, offset| Point {
+            path: path.into(),
+            offset,
+        };
+        let tokens: Vec<_> = editor
+            .tokens(Range::new(point(vec![0, 1, 0], 2), point(vec![2, 0], 1)))
+            .collect();
+
+        let texts: Vec<_> = tokens.iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["re", "", "e"]);
+        assert_eq!(
+            tokens[0].range,
+            Range::new(point(vec![0, 1, 0], 2), point(vec![0, 1, 0], 4))
+        );
+    }
+}
@@ -0,0 +1,17 @@
+mod block_style;
+pub mod diff;
+mod general;
+mod marks;
+mod movement;
+mod node;
+mod normalize;
+mod selection;
+mod text;
+
+pub use block_style::{Alignment, ALIGN_PROPERTY, INDENT_PROPERTY, MAX_INDENT};
+pub use general::ApplyError;
+pub(crate) use movement::LineBreakerHandle;
+pub use movement::{LineBreaker, Unit};
+pub use node::{MergeOptions, NodeLevel, SplitOptions};
+pub use normalize::{NormalizeReport, MAX_NORMALIZE_PASSES};
+pub use selection::SelectionAffinities;
@@ -0,0 +1,188 @@
+use crate::input::InputType;
+use crate::types::{text_entries, Descendant, Editor, NodeProperties, Path};
+
+/// The property of a block holding its alignment.
+pub const ALIGN_PROPERTY: &str = "align";
+/// The property of a block holding its indentation level.
+pub const INDENT_PROPERTY: &str = "indent";
+/// The deepest a block can be indented.
+pub const MAX_INDENT: usize = 8;
+
+/// How the lines of a block are aligned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl Alignment {
+    /// Parse the value of a block's alignment property.
+    pub fn from_property(value: &str) -> Option<Self> {
+        match value {
+            "left" => Some(Alignment::Left),
+            "center" => Some(Alignment::Center),
+            "right" => Some(Alignment::Right),
+            "justify" => Some(Alignment::Justify),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Alignment::Left => "left",
+            Alignment::Center => "center",
+            Alignment::Right => "right",
+            Alignment::Justify => "justify",
+        }
+    }
+}
+
+impl Editor {
+    /// Align the blocks in the selection, or remove their alignment with
+    /// `None` so they're aligned by the direction of their text.
+    pub fn set_alignment(&mut self, alignment: Option<Alignment>) {
+        let value = alignment.map(Alignment::as_str);
+        self.input(InputType::Format, |editor| {
+            for block in editor.selected_blocks() {
+                editor
+                    .set_node(
+                        &block,
+                        NodeProperties::new().property(ALIGN_PROPERTY, value),
+                    )
+                    .expect("setting the alignment of a block to apply");
+            }
+        });
+    }
+
+    /// Indent the blocks in the selection one level more, up to
+    /// `MAX_INDENT`.
+    pub fn increase_indent(&mut self) {
+        self.update_indent(|level| (level + 1).min(MAX_INDENT));
+    }
+
+    /// Indent the blocks in the selection one level less. Blocks that end up
+    /// unindented lose their indentation property.
+    pub fn decrease_indent(&mut self) {
+        self.update_indent(|level| level.saturating_sub(1));
+    }
+
+    /// Get the indentation level of the block at a path.
+    pub fn indent(&self, block: &Path) -> usize {
+        match self.descendant_ref(block) {
+            Some(Descendant::Element(e)) => e
+                .property(INDENT_PROPERTY)
+                .and_then(|i| i.parse().ok())
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn update_indent(&mut self, update: impl Fn(usize) -> usize) {
+        self.input(InputType::Format, |editor| {
+            for block in editor.selected_blocks() {
+                let level = update(editor.indent(&block));
+                let value = Some(level.to_string()).filter(|_| level > 0);
+                editor
+                    .set_node(
+                        &block,
+                        NodeProperties::new().property(INDENT_PROPERTY, value),
+                    )
+                    .expect("setting the indentation of a block to apply");
+            }
+        });
+    }
+
+    /// Get the paths of the blocks with text in the selection, in document
+    /// order.
+    fn selected_blocks(&self) -> Vec<Path> {
+        let selection = match &self.selection {
+            Some(selection) => selection,
+            None => return vec![],
+        };
+        let (start, end) = selection.edges(false);
+
+        let mut blocks: Vec<Path> = vec![];
+        for (_, path) in text_entries(&self.children, &Path::new(vec![])) {
+            if path.is_before(&start.path) || path.is_after(&end.path) {
+                continue;
+            }
+            if let Some(block) = self.block_path(&path) {
+                if blocks.last() != Some(&block) {
+                    blocks.push(block);
+                }
+            }
+        }
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Point, Range, Text};
+
+    use super::*;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("a")))
+            .add_child(
+                Element::with_kind("block-quote")
+                    .add_child(Element::new().add_child(Text::new("b")))
+                    .add_child(Element::new().add_child(Text::new("c"))),
+            )
+    }
+
+    #[test]
+    fn set_alignment_of_selected_blocks() {
+        let mut editor = editor();
+        editor.select(Range::new(point(vec![0, 0], 1), point(vec![1, 0, 0], 0)));
+
+        editor.set_alignment(Some(Alignment::Center));
+        let align = |editor: &Editor, path: Vec<usize>| match editor.descendant_ref(&path.into()) {
+            Some(Descendant::Element(e)) => e.property(ALIGN_PROPERTY).map(String::from),
+            _ => None,
+        };
+        assert_eq!(align(&editor, vec![0]), Some("center".into()));
+        assert_eq!(align(&editor, vec![1]), None);
+        assert_eq!(align(&editor, vec![1, 0]), Some("center".into()));
+        assert_eq!(align(&editor, vec![1, 1]), None);
+
+        editor.set_alignment(None);
+        assert_eq!(align(&editor, vec![0]), None);
+    }
+
+    #[test]
+    fn indent_and_outdent() {
+        let mut editor = editor();
+        editor.select(point(vec![1, 1, 0], 0));
+
+        editor.increase_indent();
+        editor.increase_indent();
+        assert_eq!(editor.indent(&Path::new(vec![1, 1])), 2);
+
+        editor.decrease_indent();
+        editor.decrease_indent();
+        editor.decrease_indent();
+        assert_eq!(
+            editor.children()[1],
+            Element::with_kind("block-quote")
+                .add_child(Element::new().add_child(Text::new("b")))
+                .add_child(Element::new().add_child(Text::new("c")))
+                .into()
+        );
+
+        for _ in 0..MAX_INDENT + 1 {
+            editor.increase_indent();
+        }
+        assert_eq!(editor.indent(&Path::new(vec![1, 1])), MAX_INDENT);
+    }
+}
@@ -0,0 +1,524 @@
+//! Diffing documents and text into the operations that turn one into the
+//! other.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::anchors::ANCHOR_PROPERTY;
+use crate::types::{Children, Descendant, Editor, Element, NodeProperties, Operation, Path, Text};
+
+impl Editor {
+    /// Get the operations that turn the document into `target` when applied
+    /// in order.
+    ///
+    /// Each list of children is aligned as `Node::zip_entries` aligns them,
+    /// and the children without a counterpart are removed or inserted. The
+    /// paired ones are compared: texts are edited in place, elements have
+    /// their properties set and their children diffed, and nodes of
+    /// different types are replaced.
+    pub fn diff(&self, target: &[Descendant]) -> Vec<Operation> {
+        let mut ops = vec![];
+        let target = Children::from(target.to_vec());
+        diff_children(&self.children, &target, &Path::new(vec![]), &mut ops);
+        ops
+    }
+}
+
+fn diff_children(current: &Children, target: &Children, parent: &Path, ops: &mut Vec<Operation>) {
+    // The index in the current children, as they are after the operations
+    // so far.
+    let mut k = 0;
+    for pair in align(current, target) {
+        match pair {
+            (Some(i), Some(j)) => {
+                diff_node(&current[i], &target[j], &parent.concat(k), ops);
+                k += 1;
+            }
+            (Some(i), None) => ops.push(Operation::RemoveNode {
+                path: parent.concat(k),
+                node: current[i].clone().into(),
+            }),
+            (None, Some(j)) => {
+                ops.push(Operation::InsertNode {
+                    path: parent.concat(k),
+                    node: target[j].clone().into(),
+                });
+                k += 1;
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Pair up the indexes of two lists of children, in order, with `None` on
+/// the side a child has no counterpart on.
+///
+/// Equal children at the start and end are paired. In between, elements
+/// with a key, their `id` or anchor, are only paired with the element with
+/// the same key, and the rest are paired in order, elements with elements
+/// and texts with texts, keeping as many pairs as possible.
+pub(crate) fn align(current: &Children, target: &Children) -> Vec<(Option<usize>, Option<usize>)> {
+    let prefix = current
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = current
+        .iter()
+        .skip(prefix)
+        .rev()
+        .zip(target.iter().skip(prefix).rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (current_end, target_end) = (current.len() - suffix, target.len() - suffix);
+
+    let mut pairs: Vec<_> = (0..prefix).map(|i| (Some(i), Some(i))).collect();
+
+    let a: Vec<String> = (prefix..current_end)
+        .map(|i| signature(&current[i]))
+        .collect();
+    let b: Vec<String> = (prefix..target_end)
+        .map(|j| signature(&target[j]))
+        .collect();
+    let a: Vec<&str> = a.iter().map(String::as_str).collect();
+    let b: Vec<&str> = b.iter().map(String::as_str).collect();
+
+    let (mut i, mut j) = (prefix, prefix);
+    let (mut removed, mut inserted) = (vec![], vec![]);
+    // An equal signature after a change, or the end, closes the change.
+    for edit in myers(&a, &b).into_iter().chain(Some(Edit::Equal)) {
+        match edit {
+            Edit::Delete => {
+                removed.push(i);
+                i += 1;
+            }
+            Edit::Insert => {
+                inserted.push(j);
+                j += 1;
+            }
+            Edit::Equal => {
+                let paired = removed.len().min(inserted.len());
+                for (&r, &n) in removed.iter().zip(&inserted) {
+                    if replaceable(&current[r], &target[n]) {
+                        pairs.push((Some(r), Some(n)));
+                    } else {
+                        pairs.push((Some(r), None));
+                        pairs.push((None, Some(n)));
+                    }
+                }
+                pairs.extend(removed.drain(..).skip(paired).map(|r| (Some(r), None)));
+                pairs.extend(inserted.drain(..).skip(paired).map(|n| (None, Some(n))));
+                if i < current_end && j < target_end {
+                    pairs.push((Some(i), Some(j)));
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    pairs.extend((0..suffix).map(|s| (Some(current_end + s), Some(target_end + s))));
+    pairs
+}
+
+/// Get the key of an element that identifies it across versions of a
+/// document: its `id`, or its anchor if it's a heading.
+fn key(node: &Descendant) -> Option<&str> {
+    let element = node.as_element()?;
+    element
+        .property("id")
+        .or_else(|| element.property(ANCHOR_PROPERTY))
+}
+
+/// Get what a child is matched by when aligning children.
+fn signature(node: &Descendant) -> String {
+    match (key(node), node) {
+        (Some(key), _) => format!("#{}", key),
+        (None, Descendant::Element(_)) => "<".into(),
+        (None, Descendant::Text(_)) => "\"".into(),
+    }
+}
+
+/// Whether two children that weren't matched can still be paired, as the
+/// same node changed in place.
+fn replaceable(current: &Descendant, target: &Descendant) -> bool {
+    match (current, target) {
+        (Descendant::Text(_), Descendant::Text(_)) => true,
+        (Descendant::Element(_), Descendant::Element(_)) => {
+            key(current).is_none() && key(target).is_none()
+        }
+        _ => false,
+    }
+}
+
+fn diff_node(current: &Descendant, target: &Descendant, path: &Path, ops: &mut Vec<Operation>) {
+    match (current, target) {
+        _ if current == target => {}
+        (Descendant::Text(a), Descendant::Text(b))
+            if a.meta() == b.meta() && a.author() == b.author() =>
+        {
+            diff_text(a, b, path, ops);
+            set_properties(current, target, path, ops);
+        }
+        (Descendant::Element(a), Descendant::Element(b)) => {
+            set_properties(current, target, path, ops);
+            diff_children(a.children_ref(), b.children_ref(), path, ops);
+        }
+        _ => {
+            ops.push(Operation::RemoveNode {
+                path: path.clone(),
+                node: current.clone().into(),
+            });
+            ops.push(Operation::InsertNode {
+                path: path.clone(),
+                node: target.clone().into(),
+            });
+        }
+    }
+}
+
+fn diff_text(current: &Text, target: &Text, path: &Path, ops: &mut Vec<Operation>) {
+    ops.extend(text_block(current.text(), target.text(), path));
+}
+
+/// A step of an edit script turning one list of tokens into another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Get the operations that turn the text of the text node at `path` from
+/// `old` into `new` when applied in order.
+///
+/// The texts are compared word by word, so each changed run of words,
+/// whitespace and punctuation is removed and replaced as a whole, and the
+/// fewest of them are changed.
+pub fn text_block(old: &str, new: &str, path: &Path) -> Vec<Operation> {
+    let a: Vec<&str> = old.split_word_bounds().collect();
+    let b: Vec<&str> = new.split_word_bounds().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![];
+    let mut offset: usize = a[..prefix].iter().map(|t| t.len()).sum();
+    let (mut i, mut j) = (0, 0);
+    let (mut removed, mut inserted) = (String::new(), String::new());
+
+    // An equal token after a change, or the end, closes the change.
+    for edit in myers(a_mid, b_mid).into_iter().chain(Some(Edit::Equal)) {
+        match edit {
+            Edit::Delete => {
+                removed.push_str(a_mid[i]);
+                i += 1;
+            }
+            Edit::Insert => {
+                inserted.push_str(b_mid[j]);
+                j += 1;
+            }
+            Edit::Equal => {
+                if !removed.is_empty() {
+                    ops.push(Operation::RemoveText {
+                        path: path.clone(),
+                        offset,
+                        text: std::mem::take(&mut removed),
+                    });
+                }
+                if !inserted.is_empty() {
+                    offset += inserted.len();
+                    ops.push(Operation::InsertText {
+                        path: path.clone(),
+                        offset: offset - inserted.len(),
+                        text: std::mem::take(&mut inserted),
+                    });
+                }
+                if let Some(token) = a_mid.get(i) {
+                    offset += token.len();
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    ops
+}
+
+/// Find the shortest edit script turning `a` into `b` with Myers' algorithm.
+fn myers(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let index = |k: isize| (k + max) as usize;
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace = vec![];
+
+    // Find how far each diagonal `k = x - y` reaches with `d` edits.
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back from the end through the diagonals the search took.
+    let mut edits = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    edits.reverse();
+    edits
+}
+
+/// Set the properties that differ between two nodes of the same type.
+fn set_properties(
+    current: &Descendant,
+    target: &Descendant,
+    path: &Path,
+    ops: &mut Vec<Operation>,
+) {
+    let mut new_properties = NodeProperties::new();
+
+    match (current, target) {
+        (Descendant::Element(a), Descendant::Element(b)) => {
+            new_properties = element_changes(a, b);
+        }
+        (Descendant::Text(a), Descendant::Text(b)) => {
+            if a.marks() != b.marks() {
+                new_properties = new_properties.marks(b.marks());
+            }
+            if a.mark_values() != b.mark_values() {
+                new_properties.properties = NodeProperties::between(current, target).properties;
+            }
+        }
+        _ => {}
+    }
+
+    if !new_properties.is_empty() {
+        ops.push(Operation::SetNode {
+            path: path.clone(),
+            properties: new_properties.current(current),
+            new_properties,
+        });
+    }
+}
+
+fn element_changes(current: &Element, target: &Element) -> NodeProperties {
+    let mut props = NodeProperties::new();
+
+    if current.kind() != target.kind() {
+        props = props.kind(target.kind());
+    }
+    for key in current.properties().keys() {
+        if target.property(key).is_none() {
+            props = props.property(key.as_str(), None::<String>);
+        }
+    }
+    for (key, value) in target.properties() {
+        if current.property(key) != Some(value.as_str()) {
+            props = props.property(key.as_str(), Some(value.as_str()));
+        }
+    }
+
+    props
+}
+
+#[cfg(test)]
+mod tests {
+    use super::text_block;
+    use crate::types::{Descendant, Editor, Element, Marks, Operation, Path, Text};
+
+    fn assert_diff(current: Editor, target: Vec<Descendant>) -> Vec<Operation> {
+        let mut editor = current;
+        let ops = editor.diff(&target);
+        for op in ops.iter() {
+            editor.apply(op.clone()).unwrap();
+        }
+        assert_eq!(editor.children(), target);
+        ops
+    }
+
+    #[test]
+    fn diff_text_in_place() {
+        let ops = assert_diff(
+            Editor::new().add_child(Element::new().add_child(Text::new("héllo world"))),
+            vec![Element::new()
+                .add_child(Text::new("héllo there world"))
+                .into()],
+        );
+
+        assert_eq!(
+            ops,
+            vec![Operation::InsertText {
+                path: Path::new(vec![0, 0]),
+                offset: 7,
+                text: "there ".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_aligns_by_key() {
+        let current = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_property("id", "a")
+                    .add_child(Text::new("one")),
+            )
+            .add_child(Element::new().add_child(Text::new("two")));
+        let inserted: Descendant = Element::new().add_child(Text::new("zero")).into();
+        let ops = assert_diff(
+            current,
+            vec![
+                inserted.clone(),
+                Element::new()
+                    .add_property("id", "a")
+                    .add_child(Text::new("one"))
+                    .into(),
+                Element::new().add_child(Text::new("two!")).into(),
+            ],
+        );
+
+        assert_eq!(
+            ops,
+            vec![
+                Operation::InsertNode {
+                    path: Path::new(vec![0]),
+                    node: inserted.into(),
+                },
+                Operation::InsertText {
+                    path: Path::new(vec![2, 0]),
+                    offset: 3,
+                    text: "!".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn text_block_by_words() {
+        let path = Path::new(vec![0, 0]);
+        let old = "the quick brown fox jumps";
+        let new = "the slow brown dog jumps high";
+        let ops = text_block(old, new, &path);
+
+        assert_eq!(
+            ops,
+            vec![
+                Operation::RemoveText {
+                    path: path.clone(),
+                    offset: 4,
+                    text: "quick".into(),
+                },
+                Operation::InsertText {
+                    path: path.clone(),
+                    offset: 4,
+                    text: "slow".into(),
+                },
+                Operation::RemoveText {
+                    path: path.clone(),
+                    offset: 15,
+                    text: "fox".into(),
+                },
+                Operation::InsertText {
+                    path: path.clone(),
+                    offset: 15,
+                    text: "dog".into(),
+                },
+                Operation::InsertText {
+                    path: path.clone(),
+                    offset: 24,
+                    text: " high".into(),
+                },
+            ]
+        );
+
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new(old)));
+        for op in ops {
+            editor.apply(op).unwrap();
+        }
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some(new.into()));
+
+        assert_eq!(text_block("same", "same", &path), vec![]);
+        assert_eq!(
+            text_block("", "new words", &path),
+            vec![Operation::InsertText {
+                path,
+                offset: 0,
+                text: "new words".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_structure() {
+        assert_diff(
+            Editor::new()
+                .add_child(Element::new().add_child(Text::new("a")))
+                .add_child(Element::new().add_child(Text::new("b")))
+                .add_child(Element::new().add_child(Text::new("c"))),
+            vec![
+                Element::new().add_child(Text::new("a")).into(),
+                Element::with_kind("heading-one")
+                    .add_property("id", "x")
+                    .add_child(Text::with_marks("b", Marks::BOLD))
+                    .add_child(Element::with_kind("link").add_child(Text::new("l")))
+                    .into(),
+                Element::new().add_child(Text::new("new")).into(),
+                Element::new().add_child(Text::new("c")).into(),
+            ],
+        );
+
+        assert_diff(
+            Editor::new()
+                .add_child(Element::new().add_child(Text::new("a")))
+                .add_child(Element::new().add_child(Text::new("b")))
+                .add_child(Element::new().add_child(Text::new("c"))),
+            vec![Element::new().add_child(Text::new("c")).into()],
+        );
+    }
+}
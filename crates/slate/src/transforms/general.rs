@@ -0,0 +1,532 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::limits::Limit;
+use crate::types::{
+    text_entries, Affinity, Children, Descendant, Editor, Node, Operation, Path, Point, Range, Text,
+};
+
+/// Errors returned when an operation can't be applied to a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyError {
+    /// No node exists at the path.
+    PathNotFound(Path),
+    /// The node at the path is of the wrong kind for the operation, e.g. an
+    /// `InsertText` targeting an element.
+    InvalidNode(Path),
+    /// An offset or position lies outside of the node at the path.
+    OffsetOutOfRange(Path, usize),
+    /// A node can't be moved inside of itself.
+    MoveIntoSelf(Path),
+    /// The operation would grow the document past one of the editor's limits.
+    LimitExceeded(Limit),
+    /// The node at the path is isolating, so content can't be merged across
+    /// its edges.
+    Isolating(Path),
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApplyError::PathNotFound(p) => write!(f, "no node at path {:?}", p),
+            ApplyError::InvalidNode(p) => write!(f, "invalid node for operation at path {:?}", p),
+            ApplyError::OffsetOutOfRange(p, o) => {
+                write!(f, "offset {} out of range at path {:?}", o, p)
+            }
+            ApplyError::MoveIntoSelf(p) => write!(f, "cannot move node at {:?} into itself", p),
+            ApplyError::Isolating(p) => write!(f, "cannot merge across isolating node at {:?}", p),
+            ApplyError::LimitExceeded(limit) => match limit {
+                Limit::Nodes(max) => write!(f, "document would exceed {} nodes", max),
+                Limit::Depth(max) => write!(f, "document would exceed a depth of {}", max),
+                Limit::TextLength(max) => write!(f, "document would exceed {} bytes of text", max),
+            },
+        }
+    }
+}
+
+impl Error for ApplyError {}
+
+/// Move a point through an operation that has just been applied, to
+/// `fallback` if it was in a removed node.
+fn transform_point(
+    point: &Point,
+    op: &Operation,
+    affinity: Affinity,
+    fallback: Option<&Point>,
+) -> Option<Point> {
+    Point::transform(point, op, affinity).or_else(|| {
+        let fallback = fallback?;
+        Some(Point {
+            path: Path::transform(&fallback.path, op, Affinity::Forward).ok()??,
+            offset: fallback.offset,
+        })
+    })
+}
+
+impl Editor {
+    /// Apply an operation to the document and record it in `operations`. The
+    /// selection is moved along with the content it points into. This is how
+    /// operations from elsewhere, like a collaborator's, are applied, so the
+    /// operation is applied as it is, without attributing what it inserts.
+    ///
+    /// The first operation of a batch snapshots the document for `previous`.
+    /// Children are structurally shared, so this doesn't copy the tree.
+    pub fn apply(&mut self, op: Operation) -> Result<(), ApplyError> {
+        let resize = self.check_limits(&op)?;
+
+        let snapshot = self.operations.is_empty().then(|| self.children.clone());
+        let fallback = match &op {
+            Operation::RemoveNode { path, .. } => self.removal_fallback(path),
+            _ => None,
+        };
+
+        match &op {
+            Operation::InsertNode { node, path } => {
+                let node = node
+                    .clone()
+                    .into_descendant()
+                    .ok_or_else(|| ApplyError::InvalidNode(path.clone()))?;
+                let (children, index) = self.siblings_mut(path)?;
+                if index > children.len() {
+                    return Err(ApplyError::PathNotFound(path.clone()));
+                }
+                children.insert(index, node);
+            }
+            Operation::InsertText { path, offset, text } => {
+                let content = self.text_mut(path)?;
+                if !content.is_char_boundary(*offset) {
+                    return Err(ApplyError::OffsetOutOfRange(path.clone(), *offset));
+                }
+                content.insert_str(*offset, text);
+            }
+            Operation::MergeNode { path, .. } => {
+                let prev_path = path
+                    .previous()
+                    .ok_or_else(|| ApplyError::PathNotFound(path.clone()))?;
+                let (children, index) = self.siblings_mut(path)?;
+                if index >= children.len() {
+                    return Err(ApplyError::PathNotFound(path.clone()));
+                }
+
+                match (&children[index - 1], &children[index]) {
+                    (Descendant::Text(_), Descendant::Text(_))
+                    | (Descendant::Element(_), Descendant::Element(_)) => {}
+                    _ => return Err(ApplyError::InvalidNode(prev_path)),
+                }
+
+                match (children.remove(index), &mut children[index - 1]) {
+                    (Descendant::Text(t), Descendant::Text(prev)) => {
+                        prev.text_mut().push_str(t.text());
+                    }
+                    (Descendant::Element(e), Descendant::Element(prev)) => {
+                        prev.children_mut().append(e.children_ref().clone());
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Operation::MoveNode { path, new_path } => {
+                if path.is_ancestor(new_path) {
+                    return Err(ApplyError::MoveIntoSelf(path.clone()));
+                }
+
+                let (children, index) = self.siblings_mut(path)?;
+                if index >= children.len() {
+                    return Err(ApplyError::PathNotFound(path.clone()));
+                }
+                let node = children.remove(index);
+
+                // Compute where the moved node ends up once it has been
+                // removed from its original location. If there's no room
+                // there, the node is put back so the document is unchanged.
+                let true_path = Path::transform(path, &op, Default::default())
+                    .ok()
+                    .flatten();
+                let fits = true_path.as_ref().is_some_and(|true_path| {
+                    self.siblings_mut(true_path)
+                        .is_ok_and(|(children, i)| i <= children.len())
+                });
+                let at = match true_path {
+                    Some(true_path) if fits => true_path,
+                    _ => {
+                        let (children, _) = self.siblings_mut(path)?;
+                        children.insert(index, node);
+                        return Err(ApplyError::PathNotFound(new_path.clone()));
+                    }
+                };
+                let (children, index) = self.siblings_mut(&at)?;
+                children.insert(index, node);
+            }
+            Operation::RemoveNode { path, .. } => {
+                let (children, index) = self.siblings_mut(path)?;
+                if index >= children.len() {
+                    return Err(ApplyError::PathNotFound(path.clone()));
+                }
+                children.remove(index);
+            }
+            Operation::RemoveText { path, offset, text } => {
+                let content = self.text_mut(path)?;
+                let end = offset
+                    .checked_add(text.len())
+                    .filter(|end| *end <= content.len())
+                    .ok_or_else(|| ApplyError::OffsetOutOfRange(path.clone(), *offset))?;
+                if !content.is_char_boundary(*offset) || !content.is_char_boundary(end) {
+                    return Err(ApplyError::OffsetOutOfRange(path.clone(), *offset));
+                }
+                content.replace_range(*offset..end, "");
+            }
+            Operation::SetNode {
+                path,
+                new_properties,
+                ..
+            } => {
+                let node = self
+                    .descendant_mut(path)
+                    .ok_or_else(|| ApplyError::PathNotFound(path.clone()))?;
+
+                if !new_properties.applies_to(node) {
+                    return Err(ApplyError::InvalidNode(path.clone()));
+                }
+
+                new_properties.apply_to(node);
+            }
+            Operation::SetSelection { new_properties, .. } => {
+                self.selection = new_properties.clone();
+            }
+            Operation::SplitNode {
+                path,
+                position,
+                properties,
+            } => {
+                let (children, index) = self.siblings_mut(path)?;
+                let node = children
+                    .get_mut(index)
+                    .ok_or_else(|| ApplyError::PathNotFound(path.clone()))?;
+
+                if !properties.applies_to(node) {
+                    return Err(ApplyError::InvalidNode(path.clone()));
+                }
+
+                let mut new_node = match node {
+                    Descendant::Text(t) => {
+                        if !t.text().is_char_boundary(*position) {
+                            return Err(ApplyError::OffsetOutOfRange(path.clone(), *position));
+                        }
+                        let after = t.text_mut().split_off(*position);
+                        Descendant::Text(t.with_text(after))
+                    }
+                    Descendant::Element(e) => {
+                        if *position > e.num_children() {
+                            return Err(ApplyError::OffsetOutOfRange(path.clone(), *position));
+                        }
+                        let after = e.children_mut().split_off(*position);
+                        Descendant::Element(e.with_children(after))
+                    }
+                };
+
+                properties.apply_to(&mut new_node);
+                children.insert(index + 1, new_node);
+            }
+        }
+        self.caches.index.apply(&op, &self.children);
+
+        if let Some(snapshot) = snapshot {
+            self.previous = Some(Box::new(snapshot));
+        }
+
+        if !matches!(op, Operation::SetSelection { .. }) {
+            self.transform_selection(&op, fallback.as_ref());
+            self.refs.transform(&op, |p, affinity| {
+                transform_point(p, &op, affinity, fallback.as_ref())
+            });
+        }
+
+        if let (Some(limits), Some(resize)) = (&mut self.limits, resize) {
+            limits.resize(resize);
+        }
+        self.text_index.apply(&op);
+        self.caches.apply(&op);
+        if let Some(log) = &mut self.fork_log {
+            if !matches!(op, Operation::SetSelection { .. }) {
+                log.0.push(op.clone());
+            }
+        }
+        self.operations.push(op);
+        Ok(())
+    }
+
+    /// Move the selection through an operation that has just been applied.
+    /// Points in a removed node move to `fallback`, and the selection is unset
+    /// if there is none. Points move as the editor's selection affinity for
+    /// the operation's type says.
+    fn transform_selection(&mut self, op: &Operation, fallback: Option<&Point>) {
+        let selection = match &self.selection {
+            Some(selection) => selection,
+            None => return,
+        };
+        let (anchor_affinity, focus_affinity) =
+            self.selection_affinities.of(op).point_affinities(selection);
+
+        let anchor = transform_point(selection.anchor(), op, anchor_affinity, fallback);
+        let focus = transform_point(selection.focus(), op, focus_affinity, fallback);
+        self.selection = match (anchor, focus) {
+            (Some(anchor), Some(focus)) => Some(Range::new(anchor, focus)),
+            _ => None,
+        };
+    }
+
+    /// Find where the selection and refs should go if the node at a path is
+    /// removed while they're in it: the end of the text before the node, or
+    /// else the start of the text after it.
+    fn removal_fallback(&self, path: &Path) -> Option<Point> {
+        let inside = |p: &Path| p == path || path.is_ancestor(p);
+        let selected = self.selection.as_ref().is_some_and(|selection| {
+            let (anchor, focus) = selection.points();
+            inside(&anchor.path) || inside(&focus.path)
+        });
+        if !selected && !self.refs.any(|p| inside(&p.path)) {
+            return None;
+        }
+
+        let texts = text_entries(&self.children, &Path::new(vec![]));
+        let prev = texts
+            .iter()
+            .rev()
+            .find(|(_, p)| p.is_before(path) && !inside(p))
+            .map(|(t, p)| Point {
+                path: p.clone(),
+                offset: t.text().len(),
+            });
+        let next = || {
+            texts
+                .iter()
+                .find(|(_, p)| p.is_after(path) && !inside(p))
+                .map(|(_, p)| Point {
+                    path: p.clone(),
+                    offset: 0,
+                })
+        };
+
+        prev.or_else(next)
+    }
+
+    /// End the current batch, notifying subscribers and returning the
+    /// operations applied during it. The document as it was before the batch
+    /// stays available from `previous` until the next operation is applied.
+    /// The batch is recorded in the editor's history, if it has one.
+    ///
+    /// Subscribers aren't notified of empty batches. Inside `batch`, nothing
+    /// is flushed until the batch ends, and no operations are returned.
+    pub fn flush(&mut self) -> Vec<Operation> {
+        if self.in_batch() {
+            return vec![];
+        }
+
+        self.record_history();
+        let ops = std::mem::take(&mut self.operations);
+        self.flush_actions();
+
+        if !ops.is_empty() {
+            self.subscribers.notify(&ops);
+        }
+
+        ops
+    }
+
+    /// Get the list of siblings a path points into, along with the path's index
+    /// in that list. The index isn't checked against the list's length.
+    fn siblings_mut(&mut self, path: &Path) -> Result<(&mut Children, usize), ApplyError> {
+        let not_found = || ApplyError::PathNotFound(path.clone());
+        let parent = path.parent().ok_or_else(not_found)?;
+        let index = path.last().ok_or_else(not_found)?;
+        let children = self.children_at_mut(&parent).ok_or_else(not_found)?;
+        Ok((children, index))
+    }
+
+    fn text_mut(&mut self, path: &Path) -> Result<&mut String, ApplyError> {
+        match self.descendant_mut(path) {
+            Some(Descendant::Text(t)) => Ok(t.text_mut()),
+            Some(Descendant::Element(_)) => Err(ApplyError::InvalidNode(path.clone())),
+            None => Err(ApplyError::PathNotFound(path.clone())),
+        }
+    }
+
+    /// Get the node at a path as an operation payload.
+    pub(crate) fn node_at(&self, path: &Path) -> Option<Node> {
+        self.descendant_ref(path).map(Node::from)
+    }
+
+    /// Get the text node at a path.
+    pub(crate) fn text_at(&self, path: &Path) -> Option<&Text> {
+        self.descendant_ref(path)?.as_text()
+    }
+
+    /// Check that a point is in a text node of the document, at an offset on
+    /// a character boundary of its text.
+    pub(crate) fn check_point(&self, point: &Point) -> Result<(), ApplyError> {
+        let text = self
+            .text_at(&point.path)
+            .ok_or_else(|| ApplyError::PathNotFound(point.path.clone()))?;
+        if !text.text().is_char_boundary(point.offset) {
+            return Err(ApplyError::OffsetOutOfRange(
+                point.path.clone(),
+                point.offset,
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Element, NodeProperties};
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(Element::new().add_child(Text::new("two")))
+    }
+
+    #[test]
+    fn apply_insert_text() {
+        let mut editor = editor();
+        let op = Operation::InsertText {
+            path: vec![0, 0].into(),
+            offset: 1,
+            text: "x".into(),
+        };
+        editor.apply(op.clone()).unwrap();
+        assert_eq!(editor.text_at(&vec![0, 0].into()).unwrap().text(), "oxne");
+        assert_eq!(editor.operations(), &[op]);
+    }
+
+    #[test]
+    fn previous_spans_batch() {
+        let mut editor = editor();
+        let before = editor.children.clone();
+        for offset in 0..2 {
+            editor
+                .apply(Operation::InsertText {
+                    path: vec![0, 0].into(),
+                    offset,
+                    text: "x".into(),
+                })
+                .unwrap();
+        }
+        assert_eq!(editor.previous(), Some(&before));
+        assert_eq!(editor.flush().len(), 2);
+        assert_eq!(editor.previous(), Some(&before));
+
+        let after = editor.children.clone();
+        editor
+            .apply(Operation::RemoveText {
+                path: vec![0, 0].into(),
+                offset: 0,
+                text: "x".into(),
+            })
+            .unwrap();
+        assert_eq!(editor.previous(), Some(&after));
+    }
+
+    #[test]
+    fn apply_insert_text_out_of_range() {
+        let mut editor = editor();
+        let op = Operation::InsertText {
+            path: vec![0, 0].into(),
+            offset: 9,
+            text: "x".into(),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::OffsetOutOfRange(vec![0, 0].into(), 9))
+        );
+        assert!(editor.operations().is_empty());
+    }
+
+    #[test]
+    fn apply_remove_text_overflowing() {
+        let mut editor = editor();
+        let op = Operation::RemoveText {
+            path: vec![0, 0].into(),
+            offset: usize::MAX,
+            text: "ne".into(),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::OffsetOutOfRange(vec![0, 0].into(), usize::MAX))
+        );
+        assert!(editor.operations().is_empty());
+    }
+
+    #[test]
+    fn apply_merge_node() {
+        let mut editor = editor();
+        editor
+            .apply(Operation::MergeNode {
+                path: vec![1].into(),
+                position: 1,
+                properties: NodeProperties::new(),
+            })
+            .unwrap();
+        assert_eq!(
+            editor,
+            Editor {
+                operations: editor.operations.clone(),
+                previous: editor.previous.clone(),
+                ..Editor::new().add_child(
+                    Element::new()
+                        .add_child(Text::new("one"))
+                        .add_child(Text::new("two"))
+                )
+            }
+        );
+    }
+
+    #[test]
+    fn apply_remove_node_missing() {
+        let mut editor = editor();
+        let op = Operation::RemoveNode {
+            path: vec![5].into(),
+            node: Node::Element(Element::new()),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::PathNotFound(vec![5].into()))
+        );
+    }
+
+    #[test]
+    fn apply_move_node_missing_destination() {
+        let mut editor = editor();
+        let before = editor.children.clone();
+        let op = Operation::MoveNode {
+            path: vec![0].into(),
+            new_path: vec![9, 0].into(),
+        };
+        assert_eq!(
+            editor.apply(op),
+            Err(ApplyError::PathNotFound(vec![9, 0].into()))
+        );
+        assert_eq!(editor.children, before);
+        assert!(editor.operations().is_empty());
+    }
+
+    #[test]
+    fn apply_split_node_text() {
+        let mut editor = editor();
+        editor
+            .apply(Operation::SplitNode {
+                path: vec![0, 0].into(),
+                position: 2,
+                properties: NodeProperties::new(),
+            })
+            .unwrap();
+        assert_eq!(
+            editor.child(0).unwrap(),
+            &Element::new()
+                .add_child(Text::new("on"))
+                .add_child(Text::new("e"))
+                .into()
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use crate::input::InputType;
+use crate::mark_rules::MarkRules;
+use crate::types::{
+    text_entries, Affinity, Editor, Marks, NodeProperties, Operation, Path, Point, Range,
+    BACKGROUND_MARK, COLOR_MARK,
+};
+
+impl Editor {
+    /// Add marks to the selected text, splitting text nodes at the edges of
+    /// the selection. With a collapsed selection the marks are added to the
+    /// ones the next inserted text gets instead.
+    ///
+    /// Marks cleared by the added ones under the editor's mark rules are
+    /// removed.
+    pub fn add_mark(&mut self, marks: Marks) {
+        self.input(InputType::Format, |editor| {
+            editor.update_marks(|rules, current| match rules {
+                Some(rules) => rules.add(current, marks),
+                None => current | marks,
+            })
+        });
+    }
+
+    /// Remove marks from the selected text, or from the ones the next inserted
+    /// text gets with a collapsed selection.
+    pub fn remove_mark(&mut self, marks: Marks) {
+        self.input(InputType::Format, |editor| {
+            editor.update_marks(|_, current| current - marks)
+        });
+    }
+
+    /// Set a valued mark, like `COLOR_MARK`, on the selected text, or remove
+    /// it with `None`, splitting text nodes at the edges of the selection.
+    /// Nothing is changed with a collapsed selection.
+    pub fn set_mark_value(&mut self, key: &str, value: Option<&str>) {
+        self.input(InputType::Format, |editor| {
+            editor.set_selected_mark_value(key, value)
+        });
+    }
+
+    fn set_selected_mark_value(&mut self, key: &str, value: Option<&str>) {
+        let selection = match self.selection.clone() {
+            Some(selection) if !selection.is_collapsed() => selection,
+            _ => return,
+        };
+        let (start, end) = match self.split_range(&selection) {
+            Some(edges) => edges,
+            None => return,
+        };
+
+        for path in self.texts_between(&start, &end) {
+            if self.text_at(&path).and_then(|t| t.mark_value(key)) != value {
+                self.set_node(&path, NodeProperties::new().property(key, value))
+                    .expect("setting a mark value of a text to apply");
+            }
+        }
+    }
+
+    /// Get the value of a valued mark at the selection: that of the text the
+    /// caret is in, or the one all of the selected text shares. Selected text
+    /// with different values, or without it, has none.
+    pub fn active_mark_value(&self, key: &str) -> Option<&str> {
+        let selection = self.selection.as_ref()?;
+        if selection.is_collapsed() {
+            return self.text_at(&selection.anchor().path)?.mark_value(key);
+        }
+
+        let (start, end) = selection.edges(false);
+        let mut values = self
+            .texts_between(start, end)
+            .into_iter()
+            .map(|path| self.text_at(&path).and_then(|t| t.mark_value(key)));
+        let first = values.next()??;
+        values.all(|v| v == Some(first)).then_some(first)
+    }
+
+    /// Get the color of the text at the selection, for a toolbar to show.
+    pub fn active_color(&self) -> Option<&str> {
+        self.active_mark_value(COLOR_MARK)
+    }
+
+    /// Get the background color of the text at the selection.
+    pub fn active_background(&self) -> Option<&str> {
+        self.active_mark_value(BACKGROUND_MARK)
+    }
+
+    /// Fix the marks of every text node that breaks the editor's mark rules.
+    pub fn normalize_marks(&mut self) {
+        let rules = match &self.mark_rules {
+            Some(rules) => rules.clone(),
+            None => return,
+        };
+
+        let texts: Vec<_> = text_entries(&self.children, &Path::new(vec![]))
+            .into_iter()
+            .filter(|(t, _)| rules.normalize(t.marks()) != t.marks())
+            .map(|(_, path)| path)
+            .collect();
+
+        for path in texts {
+            self.set_text_marks(&path, |marks| rules.normalize(marks));
+        }
+    }
+
+    fn update_marks(&mut self, update: impl Fn(Option<&MarkRules>, Marks) -> Marks) {
+        let rules = self.mark_rules.clone();
+        let selection = match self.selection.clone() {
+            Some(selection) => selection,
+            None => return,
+        };
+
+        if selection.is_collapsed() {
+            let current = self
+                .marks
+                .or_else(|| self.text_at(&selection.anchor().path).map(|t| t.marks()));
+            self.marks = Some(update(
+                rules.as_deref(),
+                current.unwrap_or_else(Marks::empty),
+            ));
+            return;
+        }
+
+        let (start, end) = match self.split_range(&selection) {
+            Some(edges) => edges,
+            None => return,
+        };
+
+        for path in self.texts_between(&start, &end) {
+            self.set_text_marks(&path, |marks| update(rules.as_deref(), marks));
+        }
+    }
+
+    /// Get the paths of the text nodes with some of their text between two
+    /// points.
+    fn texts_between(&self, start: &Point, end: &Point) -> Vec<Path> {
+        // Edges at the end of the start text or the start of the end text
+        // don't cover any of it.
+        let start_len = self.text_at(&start.path).map_or(0, |t| t.text().len());
+        text_entries(&self.children, &Path::new(vec![]))
+            .into_iter()
+            .map(|(_, path)| path)
+            .filter(|path| {
+                (path.is_after(&start.path) || (*path == start.path && start.offset < start_len))
+                    && (path.is_before(&end.path) || (*path == end.path && end.offset > 0))
+            })
+            .collect()
+    }
+
+    /// Split the text nodes at the edges of a range so that it covers whole
+    /// nodes, returning the new edges. The selection is moved along.
+    fn split_range(&mut self, range: &Range) -> Option<(Point, Point)> {
+        let (start, end) = range.edges(false);
+        let (mut start, mut end) = (start.clone(), end.clone());
+
+        for edge in [end.clone(), start.clone()] {
+            let len = self.text_at(&edge.path)?.text().len();
+            if edge.offset == 0 || edge.offset >= len {
+                continue;
+            }
+
+            let op = Operation::SplitNode {
+                path: edge.path.clone(),
+                position: edge.offset,
+                properties: self.properties_at(&edge.path),
+            };
+            self.apply(op.clone()).ok()?;
+
+            start = Point::transform(&start, &op, Affinity::Forward)?;
+            end = Point::transform(&end, &op, Affinity::Backward)?;
+        }
+
+        if range.is_backward() {
+            self.select(Range::new(end.clone(), start.clone()));
+        } else {
+            self.select(Range::new(start.clone(), end.clone()));
+        }
+
+        Some((start, end))
+    }
+
+    fn set_text_marks(&mut self, path: &Path, update: impl Fn(Marks) -> Marks) {
+        let marks = match self.text_at(path) {
+            Some(text) => update(text.marks()),
+            None => return,
+        };
+
+        self.set_node(path, NodeProperties::new().marks(marks))
+            .expect("setting the marks of a text to apply");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mark_rules::MarkRules;
+    use crate::types::{Editor, Element, Marks, Point, Range, Text, COLOR_MARK};
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn add_mark_splits_text() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abcd")));
+        editor.select(Range::new(point(vec![0, 0], 1), point(vec![0, 0], 3)));
+        editor.add_mark(Marks::BOLD);
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Text::with_marks("bc", Marks::BOLD))
+                .add_child(Text::new("d"))
+                .into()]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 1], 0), point(vec![0, 1], 2)))
+        );
+    }
+
+    #[test]
+    fn add_mark_follows_rules() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::with_marks("ab", Marks::BOLD)))
+            .with_mark_rules(MarkRules::new().exclusive(Marks::BOLD, Marks::ITALIC));
+        editor.select(point(vec![0, 0], 1));
+        editor.add_mark(Marks::ITALIC);
+        assert_eq!(editor.marks(), Some(Marks::ITALIC));
+
+        editor.select(Range::new(point(vec![0, 0], 0), point(vec![0, 0], 2)));
+        editor.add_mark(Marks::ITALIC);
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::with_marks("ab", Marks::ITALIC))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn remove_mark_across_blocks() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::with_marks("ab", Marks::BOLD)))
+            .add_child(Element::new().add_child(Text::with_marks("cd", Marks::BOLD)));
+        editor.select(Range::new(point(vec![0, 0], 1), point(vec![1, 0], 1)));
+        editor.remove_mark(Marks::BOLD);
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new()
+                    .add_child(Text::with_marks("a", Marks::BOLD))
+                    .add_child(Text::new("b"))
+                    .into(),
+                Element::new()
+                    .add_child(Text::new("c"))
+                    .add_child(Text::with_marks("d", Marks::BOLD))
+                    .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_color_and_query_it() {
+        let mut editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::new("ab"))
+                .add_child(Text::with_marks("cd", Marks::BOLD)),
+        );
+        editor.select(Range::new(point(vec![0, 0], 1), point(vec![0, 1], 1)));
+        editor.set_mark_value(COLOR_MARK, Some("#f00"));
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Text::new("b").add_mark_value(COLOR_MARK, "#f00"))
+                .add_child(Text::with_marks("c", Marks::BOLD).add_mark_value(COLOR_MARK, "#f00"))
+                .add_child(Text::with_marks("d", Marks::BOLD))
+                .into()]
+        );
+        assert_eq!(editor.active_color(), Some("#f00"));
+        assert_eq!(editor.active_background(), None);
+
+        editor.select(point(vec![0, 0], 1));
+        assert_eq!(editor.active_color(), None);
+        editor.select(Range::new(point(vec![0, 0], 0), point(vec![0, 2], 1)));
+        assert_eq!(editor.active_color(), None);
+
+        editor.select(Range::new(point(vec![0, 1], 0), point(vec![0, 2], 1)));
+        editor.set_mark_value(COLOR_MARK, None);
+        assert_eq!(editor.active_color(), None);
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Text::new("b"))
+                .add_child(Text::with_marks("c", Marks::BOLD))
+                .add_child(Text::with_marks("d", Marks::BOLD))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn normalize_marks_fixes_conflicts() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new().add_child(Text::with_marks("ab", Marks::BOLD | Marks::ITALIC)),
+            )
+            .with_mark_rules(MarkRules::new().exclusive(Marks::ITALIC, Marks::BOLD));
+        editor.normalize_marks();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::with_marks("ab", Marks::ITALIC))
+                .into()]
+        );
+        assert_eq!(editor.operations().len(), 1);
+    }
+}
@@ -0,0 +1,334 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::segmenter::Segment;
+use crate::types::{Editor, Path, Point, Range};
+
+/// How far to move the selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// A grapheme, as with the left and right arrow keys.
+    Character,
+    /// A word, as with the arrow keys while holding control or option.
+    Word,
+    /// A sentence.
+    Sentence,
+    /// A visual line, as with the up and down arrow keys. Lines end at soft
+    /// breaks as well as where the frontend wraps them.
+    Line,
+    /// The start or end of a visual line, as with the home and end keys.
+    LineBoundary,
+    /// The start or end of a block, as with the up and down arrow keys while
+    /// holding option.
+    Block,
+}
+
+/// Tells the editor where the frontend wraps the text of blocks, so it can
+/// move the caret between visual lines while layout stays in the UI.
+pub trait LineBreaker: Send + Sync {
+    /// Get the offsets in the text of the block at `block` where visual lines
+    /// other than the first one start.
+    fn line_starts(&self, block: &Path, text: &str) -> Vec<usize>;
+}
+
+/// The line breaker an editor was configured with.
+#[derive(Clone)]
+pub(crate) struct LineBreakerHandle(Arc<dyn LineBreaker>);
+
+impl fmt::Debug for LineBreakerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LineBreaker")
+    }
+}
+
+// The line breaker only describes how the document is laid out, so it never
+// makes two editors unequal.
+impl PartialEq for LineBreakerHandle {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Editor {
+    /// Ask `breaker` where blocks wrap when moving by lines. Without one,
+    /// blocks are only broken into lines at their soft breaks.
+    pub fn with_line_breaker(mut self, breaker: impl LineBreaker + 'static) -> Self {
+        self.line_breaker = Some(Box::new(LineBreakerHandle(Arc::new(breaker))));
+        self
+    }
+
+    /// Move the caret by a unit, forward or in `reverse`, collapsing the
+    /// selection. An expanded selection collapses to its start or end when
+    /// moving by characters, and moves from there when moving by lines.
+    ///
+    /// Moving by lines keeps the caret's column, counted in characters from
+    /// the start of its line, where the target line is long enough. Neither
+    /// unit moves the caret across the edge of an isolating element.
+    pub fn move_by(&mut self, unit: Unit, reverse: bool) {
+        let selection = match &self.selection {
+            Some(selection) => selection.clone(),
+            None => return,
+        };
+        let from = if reverse {
+            selection.start()
+        } else {
+            selection.end()
+        };
+
+        let to = match unit {
+            Unit::Character if selection.is_expanded() => Some(from),
+            unit => self.unit_position(&from, unit, reverse),
+        };
+
+        if let Some(to) = to {
+            self.select(Range::new(to.clone(), to));
+        }
+    }
+
+    /// Find the point a unit before or after a point.
+    pub(crate) fn unit_position(&self, from: &Point, unit: Unit, reverse: bool) -> Option<Point> {
+        match unit {
+            Unit::Character => self.positions(from, reverse).next(),
+            Unit::Word => self.segment_position(from, Segment::Word, reverse),
+            Unit::Sentence => self.segment_position(from, Segment::Sentence, reverse),
+            Unit::Line => self.line_position(from, reverse),
+            Unit::LineBoundary => self.line_boundary(from, reverse),
+            Unit::Block => self.block_boundary(from, reverse),
+        }
+    }
+
+    /// Find the start or end of a point's block, or the position before or
+    /// after it if the point is there already.
+    fn block_boundary(&self, point: &Point, reverse: bool) -> Option<Point> {
+        let (block, offset) = self.block_offset(point)?;
+        let edge = if reverse {
+            0
+        } else {
+            self.block_text(&block)?.len()
+        };
+        if offset == edge {
+            return self.positions(point, reverse).next();
+        }
+        self.point_at_offset(&block, edge)
+    }
+
+    /// Find the point in the line above or below a point's line with the same
+    /// column.
+    fn line_position(&self, point: &Point, reverse: bool) -> Option<Point> {
+        let (block, offset) = self.block_offset(point)?;
+        let lines = self.lines(&block)?;
+        let line = lines.partition_point(|(start, _)| *start <= offset) - 1;
+        let (start, _) = lines[line];
+        let text = self.block_text(&block)?;
+        let column = text[start..offset].chars().count();
+
+        let target = if reverse {
+            line.checked_sub(1)
+        } else {
+            Some(line + 1).filter(|l| *l < lines.len())
+        };
+        if let Some(target) = target {
+            return self.point_in_line(&block, &lines, target, column);
+        }
+
+        // Continue into the first or last line of the next block.
+        let edge = if reverse {
+            self.start(block)?
+        } else {
+            self.end(block)?
+        };
+        let next = self.positions(&edge, reverse).next()?;
+        let (block, _) = self.block_offset(&next)?;
+        let lines = self.lines(&block)?;
+        let target = if reverse { lines.len() - 1 } else { 0 };
+        self.point_in_line(&block, &lines, target, column)
+    }
+
+    /// Find the start or end of a point's line. The start of a line is its
+    /// logical start whatever the direction of its text, so this is also
+    /// where home and end go in right-to-left text.
+    fn line_boundary(&self, point: &Point, reverse: bool) -> Option<Point> {
+        let (block, offset) = self.block_offset(point)?;
+        let lines = self.lines(&block)?;
+        let line = lines.partition_point(|(start, _)| *start <= offset) - 1;
+
+        if reverse {
+            self.point_at_offset(&block, lines[line].0)
+        } else {
+            self.point_in_line(&block, &lines, line, usize::MAX)
+        }
+    }
+
+    /// Get the start and end offsets of each visual line of a block. A line
+    /// ending in a soft break holds it, so that its end is before the break.
+    pub(crate) fn lines(&self, block: &Path) -> Option<Vec<(usize, usize)>> {
+        let text = self.block_text(block)?;
+        let mut starts = match self.line_breaker.as_deref() {
+            Some(LineBreakerHandle(breaker)) => breaker.line_starts(block, &text),
+            None => vec![],
+        };
+        starts.retain(|s| *s > 0 && *s < text.len() && text.is_char_boundary(*s));
+        // A soft break at the end of a block still starts an empty line.
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        starts.sort_unstable();
+        starts.dedup();
+        starts.insert(0, 0);
+
+        let ends = starts.iter().skip(1).copied().chain(Some(text.len()));
+        Some(starts.iter().copied().zip(ends).collect())
+    }
+
+    /// Get the point at a column of a line, or at the end of the line if it's
+    /// shorter. A wrapped line ends before its last character, since its end
+    /// is the start of the next line.
+    fn point_in_line(
+        &self,
+        block: &Path,
+        lines: &[(usize, usize)],
+        line: usize,
+        column: usize,
+    ) -> Option<Point> {
+        let (start, end) = lines[line];
+        let text = self.block_text(block)?;
+        let content = &text[start..end];
+
+        let mut offset = content
+            .char_indices()
+            .nth(column)
+            .map_or(content.len(), |(i, _)| i);
+        if line + 1 < lines.len() && offset == content.len() {
+            offset = content.char_indices().last().map_or(0, |(i, _)| i);
+        }
+
+        self.point_at_offset(block, start + offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Text};
+
+    use super::*;
+
+    /// Wraps blocks every `width` bytes.
+    struct Fixed(usize);
+
+    impl LineBreaker for Fixed {
+        fn line_starts(&self, _: &Path, text: &str) -> Vec<usize> {
+            (self.0..text.len()).step_by(self.0).collect()
+        }
+    }
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    fn caret(path: Vec<usize>, offset: usize) -> Range {
+        Range::new(point(path.clone(), offset), point(path, offset))
+    }
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("abcdefghij")))
+            .add_child(Element::new().add_child(Text::new("xy")))
+    }
+
+    #[test]
+    fn move_by_line_within_and_across_blocks() {
+        let mut editor = editor().with_line_breaker(Fixed(4));
+        editor.select(point(vec![0, 0], 2));
+
+        editor.move_by(Unit::Line, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 6)));
+        editor.move_by(Unit::Line, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 10)));
+        editor.move_by(Unit::Line, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![1, 0], 2)));
+
+        editor.move_by(Unit::Line, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 10)));
+        editor.move_by(Unit::Line, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 6)));
+    }
+
+    #[test]
+    fn move_by_line_without_breaker() {
+        let mut editor = editor();
+        editor.select(point(vec![0, 0], 7));
+
+        editor.move_by(Unit::Line, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![1, 0], 2)));
+        editor.move_by(Unit::Line, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![1, 0], 2)));
+    }
+
+    #[test]
+    fn move_by_line_across_soft_breaks() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abcd\nef\n")));
+        editor.select(point(vec![0, 0], 3));
+
+        editor.move_by(Unit::Line, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 7)));
+        editor.move_by(Unit::Line, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 8)));
+        editor.move_by(Unit::Line, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 5)));
+        editor.move_by(Unit::LineBoundary, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 7)));
+        editor.move_by(Unit::Line, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 2)));
+    }
+
+    #[test]
+    fn move_by_line_boundary() {
+        let mut editor = editor().with_line_breaker(Fixed(4));
+        editor.select(point(vec![0, 0], 5));
+
+        editor.move_by(Unit::LineBoundary, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 4)));
+        editor.move_by(Unit::LineBoundary, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 7)));
+    }
+
+    #[test]
+    fn move_by_block() {
+        let mut editor = editor().with_line_breaker(Fixed(4));
+        editor.select(point(vec![0, 0], 5));
+
+        editor.move_by(Unit::Block, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 10)));
+        editor.move_by(Unit::Block, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![1, 0], 0)));
+        editor.move_by(Unit::Block, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![1, 0], 2)));
+        editor.move_by(Unit::Block, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![1, 0], 0)));
+    }
+
+    #[test]
+    fn move_by_word() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("ab, cd")));
+        editor.select(point(vec![0, 0], 1));
+
+        editor.move_by(Unit::Word, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 2)));
+        editor.move_by(Unit::Word, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 6)));
+        editor.move_by(Unit::Word, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 4)));
+    }
+
+    #[test]
+    fn move_by_character() {
+        let mut editor = editor();
+        editor.select(Path::new(vec![0]));
+
+        editor.move_by(Unit::Character, true);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 0)));
+        editor.move_by(Unit::Character, false);
+        assert_eq!(editor.selection(), Some(&caret(vec![0, 0], 1)));
+    }
+}
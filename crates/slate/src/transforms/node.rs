@@ -0,0 +1,893 @@
+use crate::transforms::ApplyError;
+use crate::types::{
+    Descendant, Editor, Element, Location, Node, NodeEntry, NodeProperties, Operation, Path, Point,
+};
+
+/// Which of a point's ancestors a transform applies to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NodeLevel {
+    /// The lowest block.
+    #[default]
+    Block,
+    /// The lowest element of a kind.
+    Kind(String),
+    /// The ancestor at a depth, 1 being a top-level node.
+    Depth(usize),
+}
+
+/// Options for `Editor::split_nodes`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SplitOptions {
+    /// The highest node to split, the lowest block by default.
+    pub level: NodeLevel,
+    /// Whether to split at the edge of the node too, leaving an empty node
+    /// on one side, as pressing enter does.
+    pub always: bool,
+}
+
+impl SplitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn level(mut self, level: NodeLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn always(mut self, always: bool) -> Self {
+        self.always = always;
+        self
+    }
+}
+
+/// Options for `Editor::merge_nodes`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// The node to merge, the lowest block by default.
+    pub level: NodeLevel,
+}
+
+impl MergeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn level(mut self, level: NodeLevel) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl Editor {
+    /// Set properties on the node at a path. Properties mapped to `None` are
+    /// removed, and nothing is applied if no property changes.
+    pub fn set_node(&mut self, path: &Path, props: NodeProperties) -> Result<(), ApplyError> {
+        let node = self
+            .descendant_ref(path)
+            .ok_or_else(|| ApplyError::PathNotFound(path.clone()))?;
+        let properties = props.current(node);
+
+        if properties == props {
+            return Ok(());
+        }
+
+        self.apply(Operation::SetNode {
+            path: path.clone(),
+            properties,
+            new_properties: props,
+        })
+    }
+
+    /// Split the nodes from a location's leaf up to the node at a level, as
+    /// `Transforms.splitNodes` does in Slate, so that the content after the
+    /// location ends up in new siblings of each. A range's content is deleted
+    /// first, and the split is where it started. At a path, its parent is
+    /// split before it.
+    ///
+    /// Nothing is split at the edge of the node unless the options say to
+    /// always split, nor if no node at the level holds the location. Nodes
+    /// can't be split across the edge of an isolating element.
+    pub fn split_nodes(&mut self, at: Location, options: SplitOptions) -> Result<(), ApplyError> {
+        let (point, level, always) = match at {
+            Location::Path(path) => {
+                let point = self
+                    .start(path.clone())
+                    .ok_or_else(|| ApplyError::PathNotFound(path.clone()))?;
+                (point, NodeLevel::Depth(path.len() - 1), true)
+            }
+            Location::Point(point) => (point, options.level, options.always),
+            Location::Range(range) => {
+                let point = if range.is_expanded() {
+                    self.delete_range(&range);
+                    self.selection.as_ref().map_or(range.start(), |s| s.start())
+                } else {
+                    range.start()
+                };
+                (point, options.level, options.always)
+            }
+        };
+        if self.text_at(&point.path).is_none() {
+            return Err(ApplyError::PathNotFound(point.path));
+        }
+        let target = match self.level_path(&point.path, &level) {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        if let Some(isolating) = self.above_isolating(&point.path) {
+            if target.len() <= isolating.len() {
+                return Err(ApplyError::Isolating(isolating));
+            }
+        }
+
+        let at_edge = self.start(target.clone()) == Some(point.clone())
+            || self.end(target.clone()) == Some(point.clone());
+        if always || !at_edge {
+            self.split_block(&point, &target);
+        }
+        Ok(())
+    }
+
+    /// Merge the node at a location's level into its previous sibling, as
+    /// `Transforms.mergeNodes` does in Slate, appending its text or children
+    /// to the sibling's. A range's content is deleted first, and the merge is
+    /// where it started. At a path, the node there is merged.
+    ///
+    /// Nothing is merged if the node at the level is the first of its
+    /// siblings, or if no node at the level holds the location. An isolating
+    /// element can't be merged with its sibling, since that would move
+    /// content across its edges.
+    pub fn merge_nodes(&mut self, at: Location, options: MergeOptions) -> Result<(), ApplyError> {
+        let point = match at {
+            Location::Path(path) => return self.merge_node(&path),
+            Location::Point(point) => point,
+            Location::Range(range) if range.is_expanded() => {
+                self.delete_range(&range);
+                self.selection.as_ref().map_or(range.start(), |s| s.start())
+            }
+            Location::Range(range) => range.start(),
+        };
+        match self.level_path(&point.path, &options.level) {
+            Some(path) if path.has_previous() => self.merge_node(&path),
+            _ => Ok(()),
+        }
+    }
+
+    /// Wrap the nodes at a level that a location spans in a new element, as
+    /// `Transforms.wrapNodes` does in Slate. The element's own children are
+    /// left out. At a path, the node there is wrapped.
+    ///
+    /// The nodes wrapped are the siblings from the node at the level at the
+    /// start of the location to the one at its end, or from their ancestors
+    /// below the nodes they're both in if they aren't siblings. Nothing is
+    /// wrapped if no node at the level holds the location.
+    pub fn wrap_nodes(
+        &mut self,
+        element: Element,
+        at: Location,
+        level: NodeLevel,
+    ) -> Result<(), ApplyError> {
+        match self.sibling_span(at, &level)? {
+            Some((parent, first, last)) => self.wrap_children(
+                &parent,
+                first,
+                last - first + 1,
+                element.with_children(vec![]),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Unwrap the elements in a location that `matches` accepts, as
+    /// `Transforms.unwrapNodes` does in Slate, putting their children in
+    /// their place. Matches are found as by `remove_nodes`, and whole
+    /// elements are unwrapped even if the location only covers part of them.
+    pub fn unwrap_nodes(&mut self, at: Location, matches: impl Fn(&NodeEntry) -> bool) {
+        let range = match self.range(at) {
+            Some(range) => range,
+            None => return,
+        };
+        let (start, end) = range.edges(false);
+        let mut paths = vec![];
+        matching_paths(
+            self.children.iter(),
+            &Path::new(vec![]),
+            (&start.path, &end.path),
+            &|entry: &NodeEntry| matches!(entry.0.as_ref(), Node::Element(_)) && matches(entry),
+            &mut paths,
+        );
+
+        // Unwrapping only moves later siblings, so going in reverse keeps the
+        // paths of the rest valid.
+        for path in paths.iter().rev() {
+            self.unwrap_node(path);
+        }
+    }
+
+    /// Lift the nodes at a level that a location spans out of their parent,
+    /// as `Transforms.liftNodes` does in Slate. The parent is split around
+    /// them if they're in its middle, and removed if they were all of its
+    /// children. The nodes are found as by `wrap_nodes`.
+    ///
+    /// Top-level nodes can't be lifted, so nothing changes for them. Nodes
+    /// can't be lifted out of an isolating element.
+    pub fn lift_nodes(&mut self, at: Location, level: NodeLevel) -> Result<(), ApplyError> {
+        let (parent, first, last) = match self.sibling_span(at, &level)? {
+            Some(span) => span,
+            None => return Ok(()),
+        };
+        let (grandparent, index) = match (parent.parent(), parent.last()) {
+            (Some(grandparent), Some(index)) => (grandparent, index),
+            _ => return Ok(()),
+        };
+        if self.above_isolating(&parent).as_ref() == Some(&parent) {
+            return Err(ApplyError::Isolating(parent));
+        }
+
+        let len = self.children_at(&parent).map_or(0, |c| c.len());
+        if first == 0 && last + 1 == len {
+            self.unwrap_node(&parent);
+        } else if first == 0 {
+            for k in 0..=last {
+                self.apply(Operation::MoveNode {
+                    path: grandparent.concat(index + k).concat(0),
+                    new_path: grandparent.concat(index + k),
+                })?;
+            }
+        } else {
+            if last + 1 < len {
+                self.apply(Operation::SplitNode {
+                    path: parent.clone(),
+                    position: last + 1,
+                    properties: self.properties_at(&parent),
+                })?;
+            }
+            for k in (first..=last).rev() {
+                self.apply(Operation::MoveNode {
+                    path: parent.concat(k),
+                    new_path: grandparent.concat(index + 1),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the parent and the first and last index of the siblings at a level
+    /// that a location spans, as `wrap_nodes` finds them.
+    fn sibling_span(
+        &self,
+        at: Location,
+        level: &NodeLevel,
+    ) -> Result<Option<(Path, usize, usize)>, ApplyError> {
+        let (first, last) = match at {
+            Location::Path(path) => {
+                self.descendant_ref(&path)
+                    .ok_or_else(|| ApplyError::PathNotFound(path.clone()))?;
+                (path.clone(), path)
+            }
+            at => {
+                let range = match self.range(at) {
+                    Some(range) => range,
+                    None => return Ok(None),
+                };
+                let (start, end) = range.edges(false);
+                match (
+                    self.level_path(&start.path, level),
+                    self.level_path(&end.path, level),
+                ) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => return Ok(None),
+                }
+            }
+        };
+
+        let depth = if first == last {
+            first.len()
+        } else {
+            (first.common(&last).len() + 1)
+                .min(first.len())
+                .min(last.len())
+        };
+        if depth == 0 {
+            return Ok(None);
+        }
+        let (first, last) = (first.as_slice(), last.as_slice());
+        Ok(Some((
+            Path::new(first[..depth - 1].to_vec()),
+            first[depth - 1],
+            last[depth - 1],
+        )))
+    }
+
+    /// Move the children of the element at a path into its place.
+    pub(crate) fn unwrap_node(&mut self, path: &Path) {
+        let (parent, index) = match (path.parent(), path.last()) {
+            (Some(parent), Some(index)) => (parent, index),
+            _ => return,
+        };
+        let len = self.children_at(path).map_or(0, |c| c.len());
+        for k in 0..len {
+            self.apply(Operation::MoveNode {
+                path: parent.concat(index + k).concat(0),
+                new_path: parent.concat(index + k),
+            })
+            .expect("move out of a parent to apply");
+        }
+        self.remove_node(&parent.concat(index + len));
+    }
+
+    /// Get the path of the ancestor of a path at a level, or of the node
+    /// itself for a depth.
+    fn level_path(&self, path: &Path, level: &NodeLevel) -> Option<Path> {
+        match level {
+            NodeLevel::Block => self.block_path(path),
+            NodeLevel::Kind(kind) => path.ancestors(true).into_iter().find(|ancestor| {
+                matches!(
+                    self.descendant_ref(ancestor),
+                    Some(Descendant::Element(e)) if !ancestor.is_empty() && e.kind() == kind
+                )
+            }),
+            NodeLevel::Depth(depth) if (1..=path.len()).contains(depth) => {
+                Some(Path::new(path.as_slice()[..*depth].to_vec()))
+            }
+            NodeLevel::Depth(_) => None,
+        }
+    }
+
+    /// Merge the node at a path into its previous sibling.
+    pub(crate) fn merge_node(&mut self, path: &Path) -> Result<(), ApplyError> {
+        let not_found = || ApplyError::PathNotFound(path.clone());
+        let prev_path = path.previous().ok_or_else(not_found)?;
+        let prev = self.previous_sibling_ref(path).ok_or_else(not_found)?;
+        self.descendant_ref(path).ok_or_else(not_found)?;
+
+        for p in [path, &prev_path] {
+            let isolating = self.above_isolating(p);
+            if isolating.as_ref() == Some(p) {
+                return Err(ApplyError::Isolating(p.clone()));
+            }
+        }
+
+        let position = match (prev, self.descendant_ref(path)) {
+            // Merging text by different authors would lose who wrote part of
+            // it.
+            (Descendant::Text(prev), Some(Descendant::Text(t))) if prev.author() != t.author() => {
+                return Err(ApplyError::InvalidNode(path.clone()));
+            }
+            (Descendant::Text(t), _) => t.text().len(),
+            (Descendant::Element(e), _) => e.num_children(),
+        };
+        self.apply(Operation::MergeNode {
+            path: path.clone(),
+            position,
+            properties: self.merge_properties_at(path),
+        })
+    }
+
+    /// Insert nodes at a location, as `Transforms.insertNodes` does in Slate.
+    ///
+    /// At a path, the nodes are inserted there. At a point, inline nodes are
+    /// inserted next to its text, and blocks next to its block, splitting the
+    /// text or block if the point is inside it. A range's content is deleted
+    /// first, and the nodes are inserted where it started.
+    ///
+    /// Nothing is changed if the nodes would grow the document past the
+    /// editor's limits.
+    pub fn insert_nodes(&mut self, nodes: Vec<Node>, at: Location) -> Result<(), ApplyError> {
+        let at_path = match &at {
+            Location::Path(path) => path.clone(),
+            Location::Point(point) => point.path.clone(),
+            Location::Range(range) => range.start().path,
+        };
+        let nodes = nodes
+            .into_iter()
+            .map(|node| {
+                node.into_descendant()
+                    .ok_or_else(|| ApplyError::InvalidNode(at_path.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if nodes.is_empty() {
+            return Ok(());
+        }
+        self.check_fragment_limits(&nodes, &at_path)?;
+
+        let inline = nodes.iter().all(|n| self.is_inline(n));
+        let mut path = match at {
+            Location::Path(path) => path,
+            Location::Point(point) => self.insertion_path(&point, inline)?,
+            Location::Range(range) => {
+                let point = if range.is_expanded() {
+                    self.delete_range(&range);
+                    self.selection.as_ref().map_or(range.start(), |s| s.start())
+                } else {
+                    range.start()
+                };
+                self.insertion_path(&point, inline)?
+            }
+        };
+
+        for node in nodes {
+            self.apply_attributed(Operation::InsertNode {
+                path: path.clone(),
+                node: node.into(),
+            })?;
+            path = path.next().unwrap();
+        }
+        Ok(())
+    }
+
+    /// Get the path to insert nodes at for a point, splitting its text, or
+    /// its block for blocks, when the point is inside it.
+    fn insertion_path(&mut self, point: &Point, inline: bool) -> Result<Path, ApplyError> {
+        let len = match self.descendant_ref(&point.path) {
+            Some(Descendant::Text(text)) => text.text().len(),
+            Some(_) => return Err(ApplyError::InvalidNode(point.path.clone())),
+            None => return Err(ApplyError::PathNotFound(point.path.clone())),
+        };
+        if point.offset > len {
+            return Err(ApplyError::OffsetOutOfRange(
+                point.path.clone(),
+                point.offset,
+            ));
+        }
+
+        if inline {
+            if point.offset == 0 {
+                return Ok(point.path.clone());
+            }
+            if point.offset < len {
+                self.apply(Operation::SplitNode {
+                    path: point.path.clone(),
+                    position: point.offset,
+                    properties: self.properties_at(&point.path),
+                })?;
+            }
+            return Ok(point.path.next().unwrap());
+        }
+
+        let block = self
+            .block_path(&point.path)
+            .ok_or_else(|| ApplyError::InvalidNode(point.path.clone()))?;
+        let (_, offset) = self
+            .block_offset(point)
+            .ok_or_else(|| ApplyError::InvalidNode(point.path.clone()))?;
+        let block_len = self.block_text(&block).map_or(0, |text| text.len());
+        if offset == 0 {
+            return Ok(block);
+        }
+        if offset < block_len {
+            self.split_block(point, &block);
+        }
+        Ok(block.next().unwrap())
+    }
+
+    /// Remove the nodes in a location that `matches` accepts, as
+    /// `Transforms.removeNodes` does in Slate. The location's ancestors are
+    /// in it too, so matching them removes it whole. A matching node's
+    /// descendants go with it, without being matched themselves.
+    pub fn remove_nodes(&mut self, at: Location, matches: impl Fn(&NodeEntry) -> bool) {
+        let range = match self.range(at) {
+            Some(range) => range,
+            None => return,
+        };
+        let (start, end) = range.edges(false);
+        let mut paths = vec![];
+        matching_paths(
+            self.children.iter(),
+            &Path::new(vec![]),
+            (&start.path, &end.path),
+            &matches,
+            &mut paths,
+        );
+
+        // Matches never nest, so removing them in reverse keeps the paths of
+        // the rest valid.
+        for path in paths.iter().rev() {
+            self.remove_node(path);
+        }
+    }
+}
+
+/// Collect the paths of the highest nodes between two paths that `matches`
+/// accepts, in document order.
+fn matching_paths<'a>(
+    children: impl IntoIterator<Item = &'a Descendant>,
+    parent: &Path,
+    (start, end): (&Path, &Path),
+    matches: &impl Fn(&NodeEntry) -> bool,
+    out: &mut Vec<Path>,
+) {
+    for (i, child) in children.into_iter().enumerate() {
+        let path = parent.concat(i);
+        if path.is_before(start) || path.is_after(end) {
+            continue;
+        }
+
+        let entry = (Box::new(Node::from(child)), path);
+        if matches(&entry) {
+            out.push(entry.1);
+        } else if let Descendant::Element(element) = child {
+            matching_paths(element.children_ref(), &entry.1, (start, end), matches, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::Schema;
+    use crate::transforms::ApplyError;
+    use crate::transforms::{MergeOptions, NodeLevel, SplitOptions};
+    use crate::types::{
+        Editor, Element, Marks, Node, NodeEntry, NodeProperties, Path, Point, Range, Text,
+    };
+
+    fn editor() -> Editor {
+        Editor::new().add_child(
+            Element::with_kind("link")
+                .add_property("url", "a")
+                .add_child(Text::new("x")),
+        )
+    }
+
+    #[test]
+    fn set_node_and_invert() {
+        let mut editor = editor();
+        let path = Path::new(vec![0]);
+        editor
+            .set_node(
+                &path,
+                NodeProperties::new()
+                    .property("url", None::<String>)
+                    .property("title", Some("t")),
+            )
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::with_kind("link")
+                .add_property("title", "t")
+                .add_child(Text::new("x"))
+                .into()]
+        );
+
+        let inverse = editor.flush().pop().unwrap().inverse();
+        editor.apply(inverse).unwrap();
+        assert_eq!(editor.children(), self::editor().children());
+    }
+
+    #[test]
+    fn set_node_unchanged() {
+        let mut editor = editor();
+        editor
+            .set_node(&Path::new(vec![0]), NodeProperties::new().kind("link"))
+            .unwrap();
+        assert!(editor.operations().is_empty());
+    }
+
+    #[test]
+    fn set_node_marks_on_element() {
+        let mut editor = editor();
+        let path = Path::new(vec![0]);
+        assert_eq!(
+            editor.set_node(&path, NodeProperties::new().marks(Marks::BOLD)),
+            Err(ApplyError::InvalidNode(path))
+        );
+    }
+
+    #[test]
+    fn merge_nodes_refuses_isolating_elements() {
+        let cell = |text: &str| Element::with_kind("table-cell").add_child(Text::new(text));
+        let mut editor = Editor::new()
+            .add_child(
+                Element::with_kind("table-row")
+                    .add_child(cell("a"))
+                    .add_child(cell("b")),
+            )
+            .add_child(Element::new().add_child(Text::new("c")))
+            .add_child(Element::new().add_child(Text::new("d")))
+            .with_schema(Schema::default());
+
+        let path = Path::new(vec![0, 1]);
+        assert_eq!(
+            editor.merge_nodes(path.clone().into(), MergeOptions::new()),
+            Err(ApplyError::Isolating(path))
+        );
+
+        editor
+            .merge_nodes(Path::new(vec![2]).into(), MergeOptions::new())
+            .unwrap();
+        assert_eq!(
+            editor.child(1),
+            Some(
+                &Element::new()
+                    .add_child(Text::new("c"))
+                    .add_child(Text::new("d"))
+                    .into()
+            )
+        );
+    }
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn insert_nodes_splits_text_for_inlines() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abcd")));
+        let mention = Element::with_kind("link")
+            .add_property("url", "b")
+            .add_child(Text::new("x"));
+
+        editor
+            .insert_nodes(
+                vec![Node::Element(mention.clone())],
+                point(vec![0, 0], 2).into(),
+            )
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("ab"))
+                .add_child(mention)
+                .add_child(Text::new("cd"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn insert_nodes_splits_blocks_for_blocks() {
+        let block = |text: &str| Element::new().add_child(Text::new(text));
+        let mut editor = Editor::new().add_child(block("abcd"));
+
+        editor
+            .insert_nodes(
+                vec![Node::Element(block("x")), Node::Element(block("y"))],
+                Range::new(point(vec![0, 0], 1), point(vec![0, 0], 3)).into(),
+            )
+            .unwrap();
+        let texts: Vec<_> = (0..4)
+            .map(|i| editor.block_text(&Path::new(vec![i])).unwrap())
+            .collect();
+        assert_eq!(texts, vec!["a", "x", "y", "d"]);
+
+        // At the edge of a block, nothing is split.
+        editor
+            .insert_nodes(vec![Node::Element(block("z"))], point(vec![3, 0], 1).into())
+            .unwrap();
+        editor
+            .insert_nodes(vec![Node::Element(block("0"))], Path::new(vec![0]).into())
+            .unwrap();
+        assert_eq!(editor.num_children(), 6);
+        assert_eq!(editor.block_text(&Path::new(vec![5])), Some("z".into()));
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("0".into()));
+
+        assert_eq!(
+            editor.insert_nodes(vec![Node::Editor(Editor::new())], Path::new(vec![0]).into()),
+            Err(ApplyError::InvalidNode(Path::new(vec![0])))
+        );
+    }
+
+    #[test]
+    fn remove_matching_nodes_in_location() {
+        let link = |text: &str| {
+            Element::with_kind("link")
+                .add_property("url", text)
+                .add_child(Text::new(text))
+        };
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(link("a"))
+                    .add_child(Text::new(""))
+                    .add_child(link("b"))
+                    .add_child(Text::new("")),
+            )
+            .add_child(
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(link("c"))
+                    .add_child(Text::new("")),
+            );
+        let is_link =
+            |(node, _): &NodeEntry| matches!(node.as_ref(), Node::Element(e) if e.kind() == "link");
+
+        editor.remove_nodes(
+            Range::new(point(vec![0, 3, 0], 0), point(vec![1, 2], 0)).into(),
+            is_link,
+        );
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(link("a"))
+                    .add_child(Text::new(""))
+                    .add_child(Text::new(""))
+                    .into(),
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(Text::new(""))
+                    .into(),
+            ]
+        );
+
+        editor.remove_nodes(Path::new(vec![1]).into(), |_| true);
+        assert_eq!(editor.num_children(), 1);
+    }
+
+    fn quote() -> Editor {
+        Editor::new().add_child(
+            Element::with_kind("block-quote")
+                .add_child(Element::new().add_child(Text::new("abcd")))
+                .add_child(Element::new().add_child(Text::new("ef"))),
+        )
+    }
+
+    #[test]
+    fn split_nodes_up_to_level() {
+        let mut editor = quote();
+        editor
+            .split_nodes(point(vec![0, 0, 0], 2).into(), SplitOptions::new())
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::with_kind("block-quote")
+                .add_child(Element::new().add_child(Text::new("ab")))
+                .add_child(Element::new().add_child(Text::new("cd")))
+                .add_child(Element::new().add_child(Text::new("ef")))
+                .into()]
+        );
+
+        let mut editor = quote();
+        editor.select(point(vec![0, 1, 0], 1));
+        editor
+            .split_nodes(
+                point(vec![0, 0, 0], 2).into(),
+                SplitOptions::new().level(NodeLevel::Kind("block-quote".into())),
+            )
+            .unwrap();
+        assert_eq!(editor.num_children(), 2);
+        assert_eq!(editor.block_text(&Path::new(vec![1, 0])), Some("cd".into()));
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(
+                point(vec![1, 1, 0], 1),
+                point(vec![1, 1, 0], 1)
+            ))
+        );
+    }
+
+    #[test]
+    fn split_nodes_at_edges_only_if_always() {
+        let mut editor = quote();
+        let end = || point(vec![0, 1, 0], 2).into();
+        editor.split_nodes(end(), SplitOptions::new()).unwrap();
+        assert_eq!(editor.children(), quote().children());
+
+        editor
+            .split_nodes(end(), SplitOptions::new().always(true))
+            .unwrap();
+        assert_eq!(editor.block_text(&Path::new(vec![0, 2])), Some("".into()));
+    }
+
+    #[test]
+    fn merge_nodes_at_point() {
+        let mut editor = quote();
+        editor
+            .merge_nodes(point(vec![0, 0, 0], 0).into(), MergeOptions::new())
+            .unwrap();
+        assert_eq!(editor.children(), quote().children());
+
+        editor
+            .merge_nodes(point(vec![0, 1, 0], 0).into(), MergeOptions::new())
+            .unwrap();
+        assert_eq!(
+            editor.block_text(&Path::new(vec![0, 0])),
+            Some("abcdef".into())
+        );
+        assert_eq!(
+            editor.children_at(&Path::new(vec![0])).map(|c| c.len()),
+            Some(1)
+        );
+    }
+
+    fn paragraph(text: &str) -> Element {
+        Element::new().add_child(Text::new(text))
+    }
+
+    #[test]
+    fn wrap_and_unwrap_nodes() {
+        let mut editor = Editor::new()
+            .add_child(paragraph("a"))
+            .add_child(paragraph("b"))
+            .add_child(paragraph("c"));
+        let quote = Element::with_kind("block-quote").add_property("cite", "x");
+
+        editor
+            .wrap_nodes(
+                quote.clone(),
+                Range::new(point(vec![0, 0], 0), point(vec![1, 0], 1)).into(),
+                NodeLevel::Block,
+            )
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![
+                quote
+                    .clone()
+                    .add_child(paragraph("a"))
+                    .add_child(paragraph("b"))
+                    .into(),
+                paragraph("c").into(),
+            ]
+        );
+
+        editor.unwrap_nodes(
+            point(vec![0, 1, 0], 0).into(),
+            |(node, _): &NodeEntry| matches!(node.as_ref(), Node::Element(e) if e.kind() == "block-quote"),
+        );
+        assert_eq!(
+            editor.children(),
+            vec![
+                paragraph("a").into(),
+                paragraph("b").into(),
+                paragraph("c").into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn lift_nodes_out_of_parent() {
+        let quote = || {
+            Element::with_kind("block-quote")
+                .add_child(paragraph("a"))
+                .add_child(paragraph("b"))
+                .add_child(paragraph("c"))
+        };
+
+        let mut editor = Editor::new().add_child(quote());
+        editor
+            .lift_nodes(point(vec![0, 1, 0], 0).into(), NodeLevel::Block)
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::with_kind("block-quote")
+                    .add_child(paragraph("a"))
+                    .into(),
+                paragraph("b").into(),
+                Element::with_kind("block-quote")
+                    .add_child(paragraph("c"))
+                    .into(),
+            ]
+        );
+
+        let mut editor = Editor::new().add_child(quote());
+        editor
+            .lift_nodes(
+                Range::new(point(vec![0, 0, 0], 0), point(vec![0, 1, 0], 0)).into(),
+                NodeLevel::Block,
+            )
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![
+                paragraph("a").into(),
+                paragraph("b").into(),
+                Element::with_kind("block-quote")
+                    .add_child(paragraph("c"))
+                    .into(),
+            ]
+        );
+
+        // Top-level nodes stay where they are.
+        let before = editor.children();
+        editor
+            .lift_nodes(Path::new(vec![0]).into(), NodeLevel::Block)
+            .unwrap();
+        assert_eq!(editor.children(), before);
+    }
+}
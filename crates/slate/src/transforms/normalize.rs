@@ -0,0 +1,370 @@
+use crate::schema::Schema;
+use crate::transforms::ApplyError;
+use crate::types::{Descendant, Editor, Element, Node, NodeProperties, Operation, Path, Text};
+
+/// The most passes `Editor::normalize` makes over the document. A pass
+/// only leaves fixes to be made by the next one when one kind of fix undoes
+/// another, so this is only reached if that loops.
+pub const MAX_NORMALIZE_PASSES: usize = 8;
+
+/// What normalizing a document took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NormalizeReport {
+    /// The passes made over the document, including the last one, which
+    /// found nothing to fix unless `MAX_NORMALIZE_PASSES` was reached.
+    pub passes: usize,
+    /// The operations applied to fix the document.
+    pub fixes: usize,
+}
+
+impl Editor {
+    /// Normalize the whole document: move front matter to the start, repair
+    /// elements holding the wrong kind of children under the schema, fix
+    /// marks that break the mark rules, give empty elements an empty text
+    /// node, remove empty text nodes that have siblings, merge adjacent text nodes with the same marks, metadata and
+    /// author, number footnotes, and give headings anchors if the editor
+    /// does.
+    ///
+    /// Passes are made until one finds nothing to fix. The fixes of a pass
+    /// are applied as one batch, with the editor's caches updated once for
+    /// all of them, so normalizing a large imported document stays fast.
+    ///
+    /// Every change is made through operations, so normalizing can be undone
+    /// and sent to collaborators like any other edit.
+    pub fn normalize(&mut self) -> NormalizeReport {
+        let mut report = NormalizeReport::default();
+
+        while report.passes < MAX_NORMALIZE_PASSES {
+            let fixes = self.with_batched_caches(|editor| {
+                let start = editor.operations.len();
+                editor.normalize_pass();
+                editor.operations.len() - start
+            });
+            report.passes += 1;
+            report.fixes += fixes;
+            if fixes == 0 {
+                break;
+            }
+        }
+
+        report
+    }
+
+    fn normalize_pass(&mut self) {
+        self.normalize_front_matter();
+        if let Some(schema) = self.schema.clone() {
+            self.normalize_children(&schema, &Path::new(vec![]), true);
+        }
+        self.normalize_marks();
+        self.normalize_texts(&Path::new(vec![]));
+        self.normalize_footnotes();
+        self.normalize_anchors();
+        self.normalize_nodes();
+    }
+
+    /// Make the children of the node at a path the kind of content it holds.
+    /// Where blocks are expected, runs of inline content are wrapped in
+    /// default blocks. Where inline content is expected, blocks are turned into
+    /// elements of the default inline kind. Elements left without children get
+    /// an empty text.
+    fn normalize_children(&mut self, schema: &Schema, parent: &Path, blocks: bool) {
+        let mut i = 0;
+
+        while i < self.children_at(parent).map_or(0, |c| c.len()) {
+            let children = self.children_at(parent).unwrap();
+
+            if blocks {
+                let run = children
+                    .iter()
+                    .skip(i)
+                    .take_while(|c| self.is_inline(c))
+                    .count();
+                if run > 0 {
+                    let wrapper = Element::with_kind(schema.default_block.clone());
+                    self.wrap_children(parent, i, run, wrapper)
+                        .expect("insert of a wrapper to apply");
+                }
+            } else if let Descendant::Element(e) = &children[i] {
+                if !self.is_inline(&children[i]) {
+                    let kind = schema.default_inline.clone();
+                    if e.kind() != kind {
+                        self.set_node(&parent.concat(i), NodeProperties::new().kind(kind))
+                            .expect("setting the kind of an element to apply");
+                    }
+                }
+            }
+
+            if let Some(Descendant::Element(e)) = self.children_at(parent).unwrap().get(i) {
+                let path = parent.concat(i);
+                if !e.has_children() {
+                    self.apply(Operation::InsertNode {
+                        path: path.concat(0),
+                        node: Node::Text(Text::new("")),
+                    })
+                    .expect("insert into an empty element to apply");
+                } else {
+                    let holds_blocks = schema.holds_blocks(e.kind(), e.child(0));
+                    self.normalize_children(schema, &path, holds_blocks);
+                }
+            }
+
+            i += 1;
+        }
+    }
+
+    /// Wrap `count` children of a node, starting at `index`, in a new element
+    /// without children.
+    pub(crate) fn wrap_children(
+        &mut self,
+        parent: &Path,
+        index: usize,
+        count: usize,
+        wrapper: Element,
+    ) -> Result<(), ApplyError> {
+        let path = parent.concat(index);
+        self.apply(Operation::InsertNode {
+            path: path.clone(),
+            node: Node::Element(wrapper),
+        })?;
+
+        for k in 0..count {
+            self.apply(Operation::MoveNode {
+                path: parent.concat(index + 1),
+                new_path: path.concat(k),
+            })
+            .expect("move into a wrapper to apply");
+        }
+        Ok(())
+    }
+
+    fn normalize_texts(&mut self, parent: &Path) {
+        let len = self.children_at(parent).map_or(0, |c| c.len());
+
+        // An element needs a child for the selection to point into.
+        if len == 0 && !parent.is_empty() {
+            self.apply(Operation::InsertNode {
+                path: parent.concat(0),
+                node: Node::Text(Text::new("")),
+            })
+            .expect("insert into an empty element to apply");
+            return;
+        }
+
+        // Later siblings are the only ones whose paths change, so going
+        // backwards keeps the paths still to be visited valid.
+        for i in (0..len).rev() {
+            let path = parent.concat(i);
+            if let Some(Descendant::Element(_)) = self.descendant_ref(&path) {
+                self.normalize_texts(&path);
+            }
+        }
+
+        for i in (0..len).rev() {
+            let children = self.children_at(parent).unwrap();
+            let empty = matches!(children.get(i), Some(Descendant::Text(t)) if t.text().is_empty());
+
+            if empty && children.len() > 1 {
+                self.remove_node(&parent.concat(i));
+            }
+        }
+
+        let len = self.children_at(parent).map_or(0, |c| c.len());
+
+        for i in (1..len).rev() {
+            let children = self.children_at(parent).unwrap();
+            let (prev, text) = match (&children[i - 1], &children[i]) {
+                (Descendant::Text(prev), Descendant::Text(text)) => (prev, text),
+                _ => continue,
+            };
+
+            if !prev.same_format(text) {
+                continue;
+            }
+
+            let path = parent.concat(i);
+            self.apply(Operation::MergeNode {
+                position: prev.text().len(),
+                properties: self.merge_properties_at(&path),
+                path,
+            })
+            .expect("merge of adjacent texts to apply");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NormalizeReport;
+    use crate::mark_rules::MarkRules;
+    use crate::schema::Schema;
+    use crate::types::{Editor, Element, Marks, Operation, Point, Text};
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn normalize_merges_texts() {
+        let mut editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Text::new("b"))
+                .add_child(Text::with_marks("c", Marks::BOLD))
+                .add_child(Text::new(""))
+                .add_child(Text::with_marks("d", Marks::BOLD)),
+        );
+        editor.select(point(vec![0, 4], 1));
+        editor.normalize();
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("ab"))
+                .add_child(Text::with_marks("cd", Marks::BOLD))
+                .into()]
+        );
+        assert_eq!(
+            editor.selection().map(|s| s.anchor().clone()),
+            Some(point(vec![0, 1], 2))
+        );
+    }
+
+    #[test]
+    fn normalize_reports_passes_and_updates_caches() {
+        let block = || {
+            Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Text::new(""))
+                .add_child(Text::new("b"))
+        };
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("clean")))
+            .add_child(block())
+            .add_child(block());
+        editor.cache_mut::<usize>().insert(vec![0].into(), 0);
+        editor.cache_mut::<usize>().insert(vec![2].into(), 2);
+
+        let report = editor.normalize();
+        assert_eq!(
+            report,
+            NormalizeReport {
+                passes: 2,
+                fixes: 4
+            }
+        );
+        let cache = editor.cache::<usize>().unwrap();
+        assert_eq!(cache.get(&vec![0].into()), Some(&0));
+        assert_eq!(cache.len(), 1);
+
+        assert_eq!(
+            editor.normalize(),
+            NormalizeReport {
+                passes: 1,
+                fixes: 0
+            }
+        );
+    }
+
+    #[test]
+    fn normalize_texts_fixture() {
+        let mut editor = crate::fixture!("normalize/texts");
+        editor.normalize();
+        crate::assert_snapshot!("normalize/texts", editor);
+    }
+
+    #[test]
+    fn normalize_keeps_only_child() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .add_child(
+                Element::new()
+                    .add_child(Text::new(""))
+                    .add_child(Text::new("")),
+            );
+        editor.normalize();
+
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new().add_child(Text::new("")).into(),
+                Element::new().add_child(Text::new("")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_wraps_inlines_in_blocks() {
+        let mut editor = Editor::new()
+            .add_child(Text::new("loose"))
+            .add_child(
+                Element::with_kind("block-quote")
+                    .add_child(Text::new("a"))
+                    .add_child(Element::new().add_child(Text::new("b"))),
+            )
+            .with_schema(Schema::default());
+        editor.normalize();
+
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new().add_child(Text::new("loose")).into(),
+                Element::with_kind("block-quote")
+                    .add_child(Element::new().add_child(Text::new("a")))
+                    .add_child(Element::new().add_child(Text::new("b")))
+                    .into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_turns_blocks_into_inlines() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("a"))
+                    .add_child(Element::with_kind("heading-one").add_child(Text::new("b")))
+                    .add_child(Element::with_kind("link")),
+            )
+            .with_schema(Schema::default());
+        editor.normalize();
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Element::with_kind("span").add_child(Text::new("b")))
+                .add_child(Element::with_kind("link").add_child(Text::new("")))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn normalize_is_undoable() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::with_marks("a", Marks::BOLD | Marks::ITALIC))
+                    .add_child(Text::new(""))
+                    .add_child(Text::with_marks("b", Marks::BOLD)),
+            )
+            .with_mark_rules(MarkRules::new().exclusive(Marks::BOLD, Marks::ITALIC));
+        let before = editor.children();
+        editor.normalize();
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::with_marks("ab", Marks::BOLD))
+                .into()]
+        );
+
+        let ops: Vec<Operation> = editor.flush();
+        for op in ops.into_iter().rev() {
+            editor.apply(op.inverse()).unwrap();
+        }
+        assert_eq!(editor.children(), before);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::types::{Editor, Location, Operation, Path, RangeAffinity};
+
+/// Where the selection goes when an operation changes the document at one of
+/// its points, for each type of operation.
+///
+/// `Forward`, the default, moves a point after what was inserted or into
+/// the new node of a split, and `Backward` keeps it before or in the node
+/// that was split. `Inward` and `Outward` move the edges of an expanded
+/// selection into or out of it, and `None` unsets the selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionAffinities {
+    pub insert_node: RangeAffinity,
+    pub insert_text: RangeAffinity,
+    pub merge_node: RangeAffinity,
+    pub move_node: RangeAffinity,
+    pub remove_node: RangeAffinity,
+    pub remove_text: RangeAffinity,
+    pub set_node: RangeAffinity,
+    pub split_node: RangeAffinity,
+}
+
+impl Default for SelectionAffinities {
+    fn default() -> Self {
+        Self {
+            insert_node: RangeAffinity::Forward,
+            insert_text: RangeAffinity::Forward,
+            merge_node: RangeAffinity::Forward,
+            move_node: RangeAffinity::Forward,
+            remove_node: RangeAffinity::Forward,
+            remove_text: RangeAffinity::Forward,
+            set_node: RangeAffinity::Forward,
+            split_node: RangeAffinity::Forward,
+        }
+    }
+}
+
+impl SelectionAffinities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_node(mut self, affinity: RangeAffinity) -> Self {
+        self.insert_node = affinity;
+        self
+    }
+
+    pub fn insert_text(mut self, affinity: RangeAffinity) -> Self {
+        self.insert_text = affinity;
+        self
+    }
+
+    pub fn merge_node(mut self, affinity: RangeAffinity) -> Self {
+        self.merge_node = affinity;
+        self
+    }
+
+    pub fn move_node(mut self, affinity: RangeAffinity) -> Self {
+        self.move_node = affinity;
+        self
+    }
+
+    pub fn remove_node(mut self, affinity: RangeAffinity) -> Self {
+        self.remove_node = affinity;
+        self
+    }
+
+    pub fn remove_text(mut self, affinity: RangeAffinity) -> Self {
+        self.remove_text = affinity;
+        self
+    }
+
+    pub fn set_node(mut self, affinity: RangeAffinity) -> Self {
+        self.set_node = affinity;
+        self
+    }
+
+    pub fn split_node(mut self, affinity: RangeAffinity) -> Self {
+        self.split_node = affinity;
+        self
+    }
+
+    /// Get the affinity for an operation's type. Selection operations don't
+    /// move the selection through them, so they get `Forward`.
+    pub fn of(&self, op: &Operation) -> RangeAffinity {
+        match op {
+            Operation::InsertNode { .. } => self.insert_node,
+            Operation::InsertText { .. } => self.insert_text,
+            Operation::MergeNode { .. } => self.merge_node,
+            Operation::MoveNode { .. } => self.move_node,
+            Operation::RemoveNode { .. } => self.remove_node,
+            Operation::RemoveText { .. } => self.remove_text,
+            Operation::SetNode { .. } => self.set_node,
+            Operation::SetSelection { .. } => RangeAffinity::Forward,
+            Operation::SplitNode { .. } => self.split_node,
+        }
+    }
+}
+
+impl Editor {
+    /// Choose where the selection goes when each type of operation changes
+    /// the document where it points.
+    pub fn with_selection_affinities(mut self, affinities: SelectionAffinities) -> Self {
+        self.selection_affinities = affinities;
+        self
+    }
+
+    pub fn selection_affinities(&self) -> &SelectionAffinities {
+        &self.selection_affinities
+    }
+
+    /// Choose where the selection goes when a node it's in is split where it
+    /// points, like when Enter is pressed with the caret in a block. This
+    /// sets the `split_node` affinity of the editor's selection affinities.
+    pub fn with_split_affinity(mut self, affinity: RangeAffinity) -> Self {
+        self.selection_affinities.split_node = affinity;
+        self
+    }
+
+    pub fn split_affinity(&self) -> RangeAffinity {
+        self.selection_affinities.split_node
+    }
+
+    /// Set the selection to a new value.
+    pub fn select(&mut self, target: impl Into<Location>) {
+        let range = match self.range(target) {
+            Some(range) => range,
+            None => return,
+        };
+
+        if self.selection.as_ref() == Some(&range) {
+            return;
+        }
+
+        self.apply(Operation::SetSelection {
+            path: Path::new(vec![]),
+            properties: self.selection.clone(),
+            new_properties: Some(range),
+        })
+        .expect("selection operations always apply");
+    }
+
+    /// Unset the selection.
+    pub fn deselect(&mut self) {
+        if self.selection.is_none() {
+            return;
+        }
+
+        self.apply(Operation::SetSelection {
+            path: Path::new(vec![]),
+            properties: self.selection.clone(),
+            new_properties: None,
+        })
+        .expect("selection operations always apply");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SelectionAffinities;
+    use crate::types::{
+        Editor, Element, NodeProperties, Operation, Path, Point, Range, RangeAffinity, Text,
+    };
+
+    fn split_block_at_caret(editor: Editor) -> Option<Range> {
+        let mut editor = editor.add_child(Element::new().add_child(Text::new("onetwo")));
+        let caret = Point {
+            path: vec![0, 0].into(),
+            offset: 3,
+        };
+        editor.select(Range::new(caret.clone(), caret));
+
+        for (path, position) in [(vec![0, 0], 3), (vec![0], 1)] {
+            editor
+                .apply(Operation::SplitNode {
+                    path: path.into(),
+                    position,
+                    properties: NodeProperties::new(),
+                })
+                .unwrap();
+        }
+
+        editor.selection().cloned()
+    }
+
+    fn caret(path: Vec<usize>, offset: usize) -> Option<Range> {
+        let point = Point {
+            path: path.into(),
+            offset,
+        };
+        Some(Range::new(point.clone(), point))
+    }
+
+    #[test]
+    fn select_path() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        editor.select(Path::new(vec![0]));
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(
+                Point {
+                    path: vec![0, 0].into(),
+                    offset: 0
+                },
+                Point {
+                    path: vec![0, 0].into(),
+                    offset: 4
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn deselect_success() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        editor.select(Path::new(vec![0]));
+        editor.deselect();
+        assert_eq!(editor.selection(), None);
+        assert_eq!(editor.operations().len(), 2);
+    }
+
+    #[test]
+    fn affinity_per_operation() {
+        let insert = Operation::InsertText {
+            path: vec![0, 0].into(),
+            offset: 3,
+            text: "-".into(),
+        };
+        let insert_at_caret = |editor: Editor| {
+            let mut editor = editor.add_child(Element::new().add_child(Text::new("onetwo")));
+            editor.select(caret(vec![0, 0], 3).unwrap());
+            editor.apply(insert.clone()).unwrap();
+            editor.selection().cloned()
+        };
+        let affinities = SelectionAffinities::new().insert_text(RangeAffinity::Backward);
+
+        assert_eq!(insert_at_caret(Editor::new()), caret(vec![0, 0], 4));
+        assert_eq!(
+            insert_at_caret(Editor::new().with_selection_affinities(affinities)),
+            caret(vec![0, 0], 3)
+        );
+        assert_eq!(affinities.of(&insert), RangeAffinity::Backward);
+
+        // Other types of operations keep their own affinity.
+        assert_eq!(
+            split_block_at_caret(Editor::new().with_selection_affinities(affinities)),
+            caret(vec![1, 0], 0)
+        );
+    }
+
+    #[test]
+    fn split_affinity() {
+        assert_eq!(split_block_at_caret(Editor::new()), caret(vec![1, 0], 0));
+        assert_eq!(
+            split_block_at_caret(Editor::new().with_split_affinity(RangeAffinity::Backward)),
+            caret(vec![0, 0], 3)
+        );
+        assert_eq!(
+            split_block_at_caret(Editor::new().with_split_affinity(RangeAffinity::Inward)),
+            caret(vec![1, 0], 0)
+        );
+        assert_eq!(
+            split_block_at_caret(Editor::new().with_split_affinity(RangeAffinity::None)),
+            None
+        );
+    }
+}
@@ -0,0 +1,960 @@
+use crate::input::InputType;
+use crate::refs::RangeRef;
+use crate::transforms::{ApplyError, Unit};
+use crate::types::{
+    default_block, has_inlines, Descendant, Editor, Element, Location, Marks, Node, NodeProperties,
+    Operation, Path, Point, Range, Text,
+};
+
+impl Editor {
+    /// Delete the content of a range, merging the blocks at its edges and
+    /// collapsing the selection to where the range started. Front matter is
+    /// never deleted, so a range starting in it starts after it instead.
+    pub(crate) fn delete_range(&mut self, range: &Range) {
+        let (start, end) = range.edges(false);
+        let (start, end) = (start.clone(), end.clone());
+
+        if self.in_front_matter(&end.path) {
+            return;
+        }
+        let start = if self.in_front_matter(&start.path) {
+            match self.start(Path::new(vec![1])) {
+                Some(start) => start,
+                None => return,
+            }
+        } else {
+            start
+        };
+
+        if self.text_at(&start.path).is_none() || self.text_at(&end.path).is_none() {
+            return;
+        }
+
+        if start.path == end.path {
+            self.remove_text(&start.path, start.offset, end.offset);
+            self.select(start);
+            return;
+        }
+
+        // Text removal doesn't change any paths, so trim both edges first.
+        let start_len = self.text_at(&start.path).unwrap().text().len();
+        self.remove_text(&end.path, 0, end.offset);
+        self.remove_text(&start.path, start.offset, start_len);
+
+        // Remove every node between the two edges, in reverse document order so
+        // that the paths still to be removed stay valid.
+        let common = start.path.common(&end.path);
+        let c = common.len();
+        let (s, e) = (start.path.as_slice(), end.path.as_slice());
+
+        for d in (c + 1..e.len()).rev() {
+            let parent = Path::new(e[..d].into());
+            for i in (0..e[d]).rev() {
+                self.remove_node(&parent.concat(i));
+            }
+        }
+
+        for i in (s[c] + 1..e[c]).rev() {
+            self.remove_node(&common.concat(i));
+        }
+
+        for d in c + 1..s.len() {
+            let parent = Path::new(s[..d].into());
+            let len = self.children_at(&parent).map_or(0, |c| c.len());
+            for i in (s[d] + 1..len).rev() {
+                self.remove_node(&parent.concat(i));
+            }
+        }
+
+        // With everything in between gone, the end leaf is the first node in
+        // each level below the common ancestor, right after the start's branch.
+        let mut new_end = e.to_vec();
+        new_end[c] = s[c] + 1;
+        for i in new_end.iter_mut().skip(c + 1) {
+            *i = 0;
+        }
+
+        let start_block = self.block_path(&start.path);
+        let end_block = self.block_path(&Path::new(new_end));
+
+        if let (Some(start_block), Some(end_block)) = (start_block, end_block) {
+            let isolated = self.above_isolating(&start_block) != self.above_isolating(&end_block);
+            if start_block != end_block && !isolated {
+                self.merge_block_into(&end_block, &start_block);
+            }
+        }
+
+        self.select(start);
+    }
+
+    /// Delete the selected content, or the grapheme before a collapsed
+    /// selection, merging its block into the previous one at the start of a
+    /// block. Nothing is merged across the edge of an isolating element.
+    pub fn delete_backward(&mut self) {
+        self.behavior().delete_backward(self);
+    }
+
+    pub(crate) fn delete_before_caret(&mut self) {
+        let selection = match &self.selection {
+            Some(selection) => selection.clone(),
+            None => return,
+        };
+        if selection.is_expanded() {
+            self.delete_range(&selection);
+            return;
+        }
+
+        let caret = selection.anchor().clone();
+        let positions = self.positions_in_scope(&caret, false);
+        let previous = positions
+            .iter()
+            .rev()
+            .find(|p| p.is_before(&caret) && !self.is_same_position(p, &caret));
+
+        if let Some(previous) = previous {
+            if self.above_isolating(&previous.path) == self.above_isolating(&caret.path) {
+                self.delete_range(&Range::new(previous.clone(), caret));
+            }
+        }
+    }
+
+    /// Delete the selected content, or the grapheme after a collapsed
+    /// selection, merging the next block into its block at the end of a
+    /// block. Nothing is merged across the edge of an isolating element.
+    pub fn delete_forward(&mut self) {
+        self.behavior().delete_forward(self);
+    }
+
+    pub(crate) fn delete_after_caret(&mut self) {
+        let selection = match &self.selection {
+            Some(selection) => selection.clone(),
+            None => return,
+        };
+        if selection.is_expanded() {
+            self.delete_range(&selection);
+            return;
+        }
+
+        let caret = selection.anchor().clone();
+        let positions = self.positions_in_scope(&caret, false);
+        let next = positions
+            .iter()
+            .find(|p| p.is_after(&caret) && !self.is_same_position(&caret, p));
+
+        if let Some(next) = next {
+            if self.above_isolating(&next.path) == self.above_isolating(&caret.path) {
+                self.delete_range(&Range::new(caret, next.clone()));
+            }
+        }
+    }
+
+    /// Delete at a location, as `Transforms.delete` does in Slate: a range's
+    /// content, a path's node, or from a point to a unit before or after it.
+    /// Deleting by lines deletes to the start or end of the visual line, as
+    /// command-backspace does, and by blocks to the start or end of the block.
+    /// At that edge already, the grapheme past it is deleted instead, merging
+    /// blocks.
+    ///
+    /// The selection collapses to where the deleted content started. Nothing
+    /// is merged across the edge of an isolating element.
+    pub fn delete(&mut self, at: Location, unit: Unit, reverse: bool) {
+        self.input(InputType::Typing, |editor| match at {
+            Location::Path(path) => {
+                if editor.node_at(&path).is_some() {
+                    editor.remove_node(&path);
+                }
+            }
+            Location::Range(range) if range.is_expanded() => editor.delete_range(&range),
+            Location::Range(range) => editor.delete_by(range.anchor().clone(), unit, reverse),
+            Location::Point(point) => editor.delete_by(point, unit, reverse),
+        });
+    }
+
+    fn delete_by(&mut self, point: Point, unit: Unit, reverse: bool) {
+        let unit = match unit {
+            Unit::Line => Unit::LineBoundary,
+            unit => unit,
+        };
+        let other = match self.unit_position(&point, unit, reverse) {
+            Some(other) if other != point => other,
+            _ => match self.positions(&point, reverse).next() {
+                Some(other) => other,
+                None => return,
+            },
+        };
+
+        if self.above_isolating(&other.path) == self.above_isolating(&point.path) {
+            self.delete_range(&Range::new(point, other));
+        }
+    }
+
+    /// Split the block at the selection in two, replacing any selected
+    /// content, as pressing enter does. The caret ends up at the start of the
+    /// second block.
+    ///
+    /// Nothing is changed if the split would grow the document past the
+    /// editor's limits.
+    pub fn insert_break(&mut self) -> Result<(), ApplyError> {
+        self.behavior().insert_break(self)
+    }
+
+    /// Break the line at the selection without splitting its block, replacing
+    /// any selected content, as pressing shift+enter does. Soft breaks are
+    /// newlines in the text, which start a new line when moving by lines and
+    /// are serialized as line breaks.
+    pub fn insert_soft_break(&mut self) {
+        self.insert_text("\n", None);
+    }
+
+    pub(crate) fn break_block(&mut self) -> Result<(), ApplyError> {
+        let selection = match self.selection.clone() {
+            Some(selection) => selection,
+            None => return Ok(()),
+        };
+        self.check_point(selection.anchor())?;
+        self.check_point(selection.focus())?;
+        self.check_fragment_limits(&[], &selection.start().path)?;
+        if selection.is_expanded() {
+            self.delete_range(&selection);
+        }
+
+        let point = self.selection.as_ref().map(Range::start).unwrap();
+        let block = match (self.text_at(&point.path), self.block_path(&point.path)) {
+            (Some(_), Some(block)) => block,
+            _ => return Ok(()),
+        };
+        self.split_block(&point, &block);
+
+        let mut caret = block.next().unwrap();
+        for _ in block.len()..point.path.len() {
+            caret = caret.concat(0);
+        }
+        self.select(Point {
+            path: caret,
+            offset: 0,
+        });
+        Ok(())
+    }
+
+    /// Split everything from a point's leaf up to its block, so that the
+    /// block's content after the point ends up in a new sibling block.
+    pub(crate) fn split_block(&mut self, point: &Point, block: &Path) {
+        let mut path = point.path.clone();
+        let mut position = point.offset;
+        loop {
+            self.apply(Operation::SplitNode {
+                path: path.clone(),
+                position,
+                properties: self.properties_at(&path),
+            })
+            .expect("split at a valid point to apply");
+
+            if &path == block {
+                break;
+            }
+
+            position = path.last().unwrap() + 1;
+            path = path.parent().unwrap();
+        }
+    }
+
+    /// Insert text at a location, or at the selection if none is given,
+    /// replacing any content there, and make the editor's typographic
+    /// substitutions in it. A URL right before inserted whitespace is linked
+    /// if the editor autolinks.
+    ///
+    /// Text inserted at a location is a `Programmatic` change rather than
+    /// typing, and leaves the selection where it was, only moving it along
+    /// with the content it points into.
+    pub fn insert_text(&mut self, text: &str, at: Option<Location>) {
+        let at = match at {
+            Some(at) => at,
+            None => {
+                return self.input(InputType::Typing, |editor| {
+                    editor.replace_selection_with_text(text)
+                })
+            }
+        };
+
+        self.input(InputType::Programmatic, |editor| {
+            let range = match editor.range(at) {
+                Some(range) => range,
+                None => return,
+            };
+            let affinity = editor.selection_affinities.insert_text;
+            let selection = editor
+                .selection
+                .clone()
+                .map(|selection| editor.range_ref(selection, affinity));
+
+            editor.select(range);
+            editor.replace_selection_with_text(text);
+            match selection.and_then(RangeRef::unref) {
+                Some(selection) => editor.select(selection),
+                None => editor.deselect(),
+            }
+        });
+    }
+
+    fn replace_selection_with_text(&mut self, text: &str) {
+        self.autolinked = None;
+        let selection = match self.selection.clone().and_then(|s| self.range(s)) {
+            Some(selection) => selection,
+            None => return,
+        };
+        if selection.is_expanded() {
+            self.delete_range(&selection);
+        }
+
+        let point = match &self.selection {
+            Some(selection) => selection.start(),
+            None => return,
+        };
+        if text.is_empty() || self.text_at(&point.path).is_none() {
+            return;
+        }
+        let point = self.escape_code(point);
+
+        let op = Operation::InsertText {
+            path: point.path.clone(),
+            offset: point.offset,
+            text: text.into(),
+        };
+        if self.apply_attributed(op).is_err() {
+            return;
+        }
+        self.typeset(&point.path, point.offset, point.offset + text.len());
+        if text.starts_with(char::is_whitespace) {
+            self.autolink_before(&point.path, point.offset);
+        }
+        if let Some(caret) = self.selection.as_ref().map(|s| s.start()) {
+            self.run_input_rules(&caret);
+        }
+    }
+
+    /// Get where text typed at a point goes. At the edges of text with the
+    /// code mark, it goes out of the code, into the text next to it or a new
+    /// one without the mark, and the caret is moved there. With the code
+    /// mark added to the marks of the next insertion, it stays in the code.
+    fn escape_code(&mut self, point: Point) -> Point {
+        let (len, marks) = match self.text_at(&point.path) {
+            Some(text) if text.marks().contains(Marks::CODE) => (text.text().len(), text.marks()),
+            _ => return point,
+        };
+        let at_end = point.offset == len;
+        if (point.offset > 0 && !at_end) || self.marks.is_some_and(|m| m.contains(Marks::CODE)) {
+            return point;
+        }
+
+        let sibling = if at_end {
+            point.path.next()
+        } else {
+            point.path.previous()
+        };
+        let escaped = sibling.and_then(|path| {
+            let text = self.text_at(&path)?;
+            let offset = if at_end { 0 } else { text.text().len() };
+            (!text.marks().contains(Marks::CODE)).then_some(Point { path, offset })
+        });
+        let escaped = match escaped {
+            Some(escaped) => escaped,
+            None => {
+                let path = if at_end {
+                    point.path.next().expect("text paths are never empty")
+                } else {
+                    point.path.clone()
+                };
+                let op = Operation::InsertNode {
+                    path: path.clone(),
+                    node: Node::Text(Text::with_marks("", marks - Marks::CODE)),
+                };
+                if self.apply_attributed(op).is_err() {
+                    return point;
+                }
+                Point { path, offset: 0 }
+            }
+        };
+
+        self.select(escaped.clone());
+        escaped
+    }
+
+    /// Move the children of `block` to the end of `into`, which must come
+    /// before it in the document, removing ancestors left empty by the move.
+    fn merge_block_into(&mut self, block: &Path, into: &Path) {
+        let target = into.next().expect("block paths are never empty");
+        let mut old_parent = block.parent().expect("block paths are never empty");
+
+        if block != &target {
+            let op = Operation::MoveNode {
+                path: block.clone(),
+                new_path: target.clone(),
+            };
+            old_parent = Path::transform(&old_parent, &op, Default::default())
+                .unwrap()
+                .unwrap();
+            self.apply(op)
+                .expect("move to a sibling position to be valid");
+        }
+
+        let op = Operation::MergeNode {
+            path: target.clone(),
+            position: self.children_at(into).map_or(0, |c| c.len()),
+            properties: self.merge_properties_at(&target),
+        };
+        old_parent = Path::transform(&old_parent, &op, Default::default())
+            .unwrap()
+            .unwrap();
+        self.apply(op).expect("merge of two blocks to be valid");
+
+        while !old_parent.is_empty() && self.children_at(&old_parent).is_some_and(|c| c.is_empty())
+        {
+            self.remove_node(&old_parent);
+            old_parent = old_parent.parent().unwrap();
+        }
+    }
+
+    /// Insert a fragment of blocks at the current selection, deleting any
+    /// selected content first. The first and last blocks of the fragment are
+    /// merged into the block the selection is in when both hold inline content.
+    ///
+    /// Nothing is changed if the fragment would grow the document past the
+    /// editor's limits.
+    pub fn insert_fragment(&mut self, fragment: Vec<Descendant>) -> Result<(), ApplyError> {
+        let selection = match self.selection.clone() {
+            Some(selection) => selection,
+            None => return Ok(()),
+        };
+
+        self.check_point(selection.anchor())?;
+        self.check_point(selection.focus())?;
+
+        let blocks = wrap_loose_inlines(fragment);
+        if blocks.is_empty() {
+            if selection.is_expanded() {
+                self.delete_range(&selection);
+            }
+            return Ok(());
+        }
+
+        // Deleting the selection only shrinks the document, so the check can
+        // be made up front without leaving a half-applied paste behind.
+        self.check_fragment_limits(&blocks, &selection.start().path)?;
+
+        if selection.is_expanded() {
+            self.delete_range(&selection);
+        }
+
+        let point = self.selection.as_ref().map(Range::start).unwrap();
+        let block = match (self.text_at(&point.path), self.block_path(&point.path)) {
+            (Some(_), Some(block)) => block,
+            _ => return Ok(()),
+        };
+
+        self.split_block(&point, &block);
+
+        let depth = point.path.len() - block.len();
+        let count = blocks.len();
+        let mut next = block.next().unwrap();
+
+        for node in blocks {
+            self.apply_attributed(Operation::InsertNode {
+                node: node.into(),
+                path: next.clone(),
+            })
+            .expect("insert after a block to apply");
+            next = next.next().unwrap();
+        }
+
+        let mut merged = 0;
+        if self.has_inlines_at(&block) && self.has_inlines_at(&block.next().unwrap()) {
+            self.merge_block_into(&block.next().unwrap(), &block);
+            merged = 1;
+        }
+
+        // The right half of the split block now follows the inserted blocks.
+        let rest = sibling(&block, count - merged + 1);
+        let last = sibling(&block, count - merged);
+        let mut caret = rest.clone();
+
+        if self.has_inlines_at(&last) && self.has_inlines_at(&rest) {
+            let position = self.children_at(&last).map_or(0, |c| c.len());
+            self.merge_block_into(&rest, &last);
+            caret = last.concat(position);
+            for _ in 1..depth {
+                caret = caret.concat(0);
+            }
+        } else {
+            for _ in 0..depth {
+                caret = caret.concat(0);
+            }
+        }
+
+        self.select(Point {
+            path: caret,
+            offset: 0,
+        });
+        Ok(())
+    }
+
+    /// Replace the whole document with the smallest valid one, a single empty
+    /// block, and put the cursor in it. This goes through operations, so it's
+    /// recorded like any other edit.
+    pub fn reset(&mut self) -> Result<(), ApplyError> {
+        self.deselect();
+
+        // Removing everything first keeps the new block within any limits.
+        for i in (0..self.children.len()).rev() {
+            self.remove_node(&Path::new(vec![i]));
+        }
+
+        self.apply(Operation::InsertNode {
+            path: Path::new(vec![0]),
+            node: Node::Element(default_block()),
+        })?;
+        self.select(Path::new(vec![0]));
+        Ok(())
+    }
+
+    pub(crate) fn remove_text(&mut self, path: &Path, from: usize, to: usize) {
+        if from >= to {
+            return;
+        }
+
+        let text = match self.text_at(path).and_then(|t| t.text().get(from..to)) {
+            Some(text) => text.to_string(),
+            None => return,
+        };
+        self.apply(Operation::RemoveText {
+            path: path.clone(),
+            offset: from,
+            text,
+        })
+        .expect("removal of existing text to apply");
+    }
+
+    pub(crate) fn remove_node(&mut self, path: &Path) {
+        let node = self.node_at(path).expect("node to remove to exist");
+        self.apply(Operation::RemoveNode {
+            path: path.clone(),
+            node,
+        })
+        .expect("removal of an existing node to apply");
+    }
+
+    /// Get the properties for a split of the node at a path, which give the
+    /// new node the same properties as the node itself.
+    pub(crate) fn properties_at(&self, path: &Path) -> NodeProperties {
+        let node = self.descendant_ref(path).expect("node to exist");
+        NodeProperties::between(node, node)
+    }
+
+    /// Get the properties for a merge of the node at a path into its previous
+    /// sibling, which turn the sibling back into the node.
+    pub(crate) fn merge_properties_at(&self, path: &Path) -> NodeProperties {
+        let prev = self.previous_sibling_ref(path);
+        let node = self.descendant_ref(path).expect("node to exist");
+        NodeProperties::between(prev.expect("node to have a previous sibling"), node)
+    }
+
+    fn has_inlines_at(&self, path: &Path) -> bool {
+        match self.descendant_ref(path) {
+            Some(Descendant::Element(e)) => has_inlines(e.children_ref()),
+            _ => false,
+        }
+    }
+}
+
+/// Get the path `n` siblings after `path`.
+fn sibling(path: &Path, n: usize) -> Path {
+    let mut parts = path.as_slice().to_vec();
+    *parts.last_mut().unwrap() += n;
+    Path::new(parts)
+}
+
+/// Wrap runs of top-level text nodes in a fragment into default elements, so
+/// that the fragment only contains blocks.
+fn wrap_loose_inlines(fragment: Vec<Descendant>) -> Vec<Descendant> {
+    let mut blocks = vec![];
+    let mut loose: Option<Element> = None;
+
+    for node in fragment {
+        match node {
+            Descendant::Text(_) => {
+                loose = Some(loose.unwrap_or_default().add_child(node));
+            }
+            Descendant::Element(_) => {
+                if let Some(e) = loose.take() {
+                    blocks.push(e.into());
+                }
+                blocks.push(node);
+            }
+        }
+    }
+
+    if let Some(e) = loose {
+        blocks.push(e.into());
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+    use crate::types::{Marks, Text};
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn typing_escapes_code_at_its_edges() {
+        let mut editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::with_marks("code", Marks::CODE))
+                .add_child(Text::new(" after")),
+        );
+        editor.select(point(vec![0, 0], 4));
+        editor.insert_text("!", None);
+        editor.select(point(vec![0, 0], 0));
+        editor.insert_text("<", None);
+        editor.select(point(vec![0, 1], 1));
+        editor.insert_text("d", None);
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("<"))
+                .add_child(Text::with_marks("cdode", Marks::CODE))
+                .add_child(Text::new("! after"))
+                .into()]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 1], 2), point(vec![0, 1], 2)))
+        );
+
+        // With the code mark added for the next insertion, typing stays in
+        // the code.
+        editor.select(point(vec![0, 1], 5));
+        editor.add_mark(Marks::CODE);
+        editor.insert_text("s", None);
+        assert_eq!(
+            editor.block_text(&Path::new(vec![0])).unwrap(),
+            "<cdodes! after"
+        );
+        assert_eq!(
+            editor.text_at(&Path::new(vec![0, 1])).unwrap().text(),
+            "cdodes"
+        );
+    }
+
+    #[test]
+    fn delete_range_across_blocks() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(Element::new().add_child(Text::new("two")))
+            .add_child(Element::new().add_child(Text::new("three")));
+        editor.delete_range(&Range::new(point(vec![0, 0], 1), point(vec![2, 0], 2)));
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("o"))
+                .add_child(Text::new("ree"))
+                .into()]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 0], 1), point(vec![0, 0], 1)))
+        );
+    }
+
+    #[test]
+    fn delete_range_nested_end() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(
+                Element::with_kind("block-quote")
+                    .add_child(Element::new().add_child(Text::new("two"))),
+            );
+        editor.delete_range(&Range::new(point(vec![0, 0], 2), point(vec![1, 0, 0], 1)));
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("on"))
+                .add_child(Text::new("wo"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn delete_range_single_text() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        editor.delete_range(&Range::new(point(vec![0, 0], 3), point(vec![0, 0], 1)));
+        assert_eq!(
+            editor.children(),
+            vec![Element::new().add_child(Text::new("wd")).into()]
+        );
+    }
+
+    #[test]
+    fn insert_fragment_inline() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        editor.select(point(vec![0, 0], 2));
+        editor
+            .insert_fragment(vec![Element::new().add_child(Text::new("xy")).into()])
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("wo"))
+                .add_child(Text::new("xy"))
+                .add_child(Text::new("rd"))
+                .into()]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 2], 0), point(vec![0, 2], 0)))
+        );
+    }
+
+    #[test]
+    fn insert_fragment_multiple_blocks() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        editor.select(point(vec![0, 0], 2));
+        editor
+            .insert_fragment(vec![
+                Element::new().add_child(Text::new("a")).into(),
+                Element::with_kind("heading-one")
+                    .add_child(Text::new("b"))
+                    .into(),
+                Element::new().add_child(Text::new("c")).into(),
+            ])
+            .unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new()
+                    .add_child(Text::new("wo"))
+                    .add_child(Text::new("a"))
+                    .into(),
+                Element::with_kind("heading-one")
+                    .add_child(Text::new("b"))
+                    .into(),
+                Element::new()
+                    .add_child(Text::new("c"))
+                    .add_child(Text::new("rd"))
+                    .into(),
+            ]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![2, 1], 0), point(vec![2, 1], 0)))
+        );
+    }
+
+    #[test]
+    fn insert_fragment_replaces_selection() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        editor.select(Range::new(point(vec![0, 0], 1), point(vec![0, 0], 3)));
+        editor.insert_fragment(vec![Text::new("x").into()]).unwrap();
+        assert_eq!(
+            editor.children(),
+            vec![Element::new()
+                .add_child(Text::new("w"))
+                .add_child(Text::new("x"))
+                .add_child(Text::new("d"))
+                .into()]
+        );
+    }
+
+    #[test]
+    fn reset_replaces_document() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(Element::new().add_child(Text::new("two")));
+        editor.select(point(vec![1, 0], 2));
+        editor.reset().unwrap();
+        assert_eq!(editor.children(), Editor::with_default().children());
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 0], 0), point(vec![0, 0], 0)))
+        );
+    }
+
+    #[test]
+    fn delete_forward_merges_next_block() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("ab"))
+                    .add_child(Text::with_marks("c", Marks::BOLD)),
+            )
+            .add_child(Element::new().add_child(Text::new("d")));
+
+        editor.select(point(vec![0, 0], 2));
+        editor.delete_forward();
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("ab".into()));
+
+        editor.delete_forward();
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("abd".into()));
+        assert_eq!(editor.num_children(), 1);
+
+        editor.select(point(vec![0, 1], 1));
+        editor.delete_forward();
+        assert_eq!(editor.block_text(&Path::new(vec![0])), Some("abd".into()));
+    }
+
+    #[test]
+    fn delete_by_unit_at_location() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one two three")))
+            .add_child(Element::new().add_child(Text::new("four")));
+        let text = |editor: &Editor, i| editor.block_text(&Path::new(vec![i]));
+
+        editor.delete(point(vec![0, 0], 7).into(), Unit::Word, true);
+        assert_eq!(text(&editor, 0), Some("one  three".into()));
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 0], 4), point(vec![0, 0], 4)))
+        );
+
+        editor.delete(point(vec![0, 0], 4).into(), Unit::Character, false);
+        assert_eq!(text(&editor, 0), Some("one three".into()));
+
+        editor.delete(point(vec![0, 0], 3).into(), Unit::Block, false);
+        assert_eq!(text(&editor, 0), Some("one".into()));
+
+        // At the end of the block, the next one is merged in.
+        editor.delete(point(vec![0, 0], 3).into(), Unit::Line, false);
+        assert_eq!(text(&editor, 0), Some("onefour".into()));
+        assert_eq!(editor.num_children(), 1);
+
+        editor.delete(point(vec![0, 0], 3).into(), Unit::Line, true);
+        assert_eq!(text(&editor, 0), Some("four".into()));
+
+        editor.delete(Path::new(vec![0]).into(), Unit::Character, false);
+        assert_eq!(editor.num_children(), 0);
+    }
+
+    #[test]
+    fn insert_break_splits_block() {
+        let mut editor = Editor::new().add_child(
+            Element::with_kind("heading")
+                .add_property("level", "2")
+                .add_child(Text::new("abcd")),
+        );
+        editor.select(Range::new(point(vec![0, 0], 1), point(vec![0, 0], 3)));
+        editor.insert_break().unwrap();
+
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::with_kind("heading")
+                    .add_property("level", "2")
+                    .add_child(Text::new("a"))
+                    .into(),
+                Element::with_kind("heading")
+                    .add_property("level", "2")
+                    .add_child(Text::new("d"))
+                    .into(),
+            ]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![1, 0], 0), point(vec![1, 0], 0)))
+        );
+    }
+
+    #[test]
+    fn insert_text_at_location() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("abcd")))
+            .add_child(Element::new().add_child(Text::new("ef")));
+        editor.select(point(vec![1, 0], 0));
+
+        let range = Range::new(point(vec![0, 0], 1), point(vec![0, 0], 3));
+        editor.insert_text("x", Some(range.into()));
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![1, 0], 0), point(vec![1, 0], 0)))
+        );
+        editor.insert_text("y", None);
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![1, 0], 1), point(vec![1, 0], 1)))
+        );
+
+        editor.flush();
+        editor.insert_text("z", Some(Path::new(vec![1]).into()));
+        assert_eq!(editor.input_type(), InputType::Programmatic);
+        assert_eq!(
+            editor.children(),
+            vec![
+                Element::new().add_child(Text::new("axd")).into(),
+                Element::new().add_child(Text::new("z")).into(),
+            ]
+        );
+        // The caret stays in the text, after what replaced the text before it.
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![1, 0], 1), point(vec![1, 0], 1)))
+        );
+    }
+
+    #[test]
+    fn insert_soft_break_keeps_block() {
+        let mut editor = Editor::new().add_child(Element::new().add_child(Text::new("abcd")));
+        editor.select(Range::new(point(vec![0, 0], 1), point(vec![0, 0], 3)));
+        editor.insert_soft_break();
+
+        assert_eq!(
+            editor.children(),
+            vec![Element::new().add_child(Text::new("a\nd")).into()]
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![0, 0], 2), point(vec![0, 0], 2)))
+        );
+    }
+
+    #[test]
+    fn delete_backward_merges_blocks_but_not_cells() {
+        let mut editor = Editor::new()
+            .add_child(
+                Element::with_kind("table").add_child(
+                    Element::with_kind("table-row")
+                        .add_child(Element::with_kind("table-cell").add_child(Text::new("a"))),
+                ),
+            )
+            .add_child(Element::new().add_child(Text::new("bc")))
+            .add_child(Element::new().add_child(Text::new("d")))
+            .with_schema(Schema::default());
+
+        editor.select(point(vec![2, 0], 0));
+        editor.delete_backward();
+        assert_eq!(
+            editor.selection(),
+            Some(&Range::new(point(vec![1, 0], 2), point(vec![1, 0], 2)))
+        );
+
+        editor.select(point(vec![1, 0], 1));
+        editor.delete_backward();
+        let after = editor.children();
+        editor.delete_backward();
+
+        assert_eq!(editor.children(), after);
+        assert_eq!(
+            editor.children()[1],
+            Element::new()
+                .add_child(Text::new("c"))
+                .add_child(Text::new("d"))
+                .into()
+        );
+    }
+}
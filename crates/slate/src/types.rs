@@ -1,16 +1,36 @@
+mod children;
+mod content_hash;
+mod document_index;
 mod editor;
 mod element;
 mod location;
+mod mark_registry;
 mod node;
 mod operation;
 mod path;
+mod path_arena;
 mod point;
+mod properties;
 mod range;
 mod text;
 
+pub use children::Children;
+pub use content_hash::ContentHash;
+pub(crate) use document_index::DocumentIndex;
+pub use document_index::NodeRef;
+pub(crate) use editor::default_block;
+pub use editor::Editor;
+pub use element::{Element, ElementEntry, DEFAULT_KIND};
 pub use location::{Location, Span};
+pub use mark_registry::MarkRegistry;
+pub use node::{Ancestor, Descendant, Node, NodeEntry, ZippedEntry};
 pub use operation::Operation;
-pub use path::Path;
+pub use path::{Affinity, Path, TransformError};
+pub use path_arena::{PathArena, PathId};
 pub use point::Point;
-pub use range::Range;
-pub use text::Text;
+pub use properties::{NodeProperties, PropertiesSeed, RegisteredProperties};
+pub use range::{Affinity as RangeAffinity, Range};
+pub use slate_core::{AsEdit, Edit};
+pub use text::{Decoration, Marks, Text, BACKGROUND_MARK, COLOR_MARK};
+
+pub(crate) use node::{element_entries, has_inlines, text_entries};
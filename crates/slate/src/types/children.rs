@@ -0,0 +1,141 @@
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut};
+
+use super::node::Descendant;
+
+/// The children of an element or editor.
+///
+/// Children are kept in a balanced tree rather than a contiguous list, so that
+/// inserting or removing a child anywhere costs O(log n) even for nodes with
+/// tens of thousands of children, like long chat logs. Clones share structure
+/// with the original.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Children(im::Vector<Descendant>);
+
+impl Children {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&Descendant> {
+        self.0.get(i)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Descendant> + ExactSizeIterator {
+        self.0.iter()
+    }
+
+    pub fn to_vec(&self) -> Vec<Descendant> {
+        self.0.iter().cloned().collect()
+    }
+
+    pub(crate) fn get_mut(&mut self, i: usize) -> Option<&mut Descendant> {
+        self.0.get_mut(i)
+    }
+
+    pub(crate) fn push(&mut self, child: Descendant) {
+        self.0.push_back(child);
+    }
+
+    /// Insert a child at an index, which must be at most the number of
+    /// children.
+    pub(crate) fn insert(&mut self, i: usize, child: Descendant) {
+        self.0.insert(i, child);
+    }
+
+    /// Remove the child at an index, which must be in bounds.
+    pub(crate) fn remove(&mut self, i: usize) -> Descendant {
+        self.0.remove(i)
+    }
+
+    /// Split the children in two at an index, returning the children after it.
+    pub(crate) fn split_off(&mut self, i: usize) -> Children {
+        Children(self.0.split_off(i))
+    }
+
+    pub(crate) fn append(&mut self, other: Children) {
+        self.0.append(other.0);
+    }
+}
+
+impl Index<usize> for Children {
+    type Output = Descendant;
+
+    fn index(&self, i: usize) -> &Descendant {
+        &self.0[i]
+    }
+}
+
+impl IndexMut<usize> for Children {
+    fn index_mut(&mut self, i: usize) -> &mut Descendant {
+        &mut self.0[i]
+    }
+}
+
+impl From<Vec<Descendant>> for Children {
+    fn from(val: Vec<Descendant>) -> Self {
+        val.into_iter().collect()
+    }
+}
+
+impl FromIterator<Descendant> for Children {
+    fn from_iter<I: IntoIterator<Item = Descendant>>(iter: I) -> Self {
+        Children(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Children {
+    type Item = Descendant;
+    type IntoIter = im::vector::ConsumingIter<Descendant>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Children {
+    type Item = &'a Descendant;
+    type IntoIter = im::vector::Iter<'a, Descendant>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Text;
+
+    fn children(n: usize) -> Children {
+        (0..n).map(|i| Text::new(i.to_string()).into()).collect()
+    }
+
+    #[test]
+    fn insert_and_remove_at_front() {
+        let mut c = children(10_000);
+        c.insert(0, Text::new("first").into());
+        assert_eq!(c.len(), 10_001);
+        assert_eq!(c[0], Text::new("first").into());
+        assert_eq!(c.remove(0), Text::new("first").into());
+        assert_eq!(c[0], Text::new("0").into());
+    }
+
+    #[test]
+    fn split_off_and_append() {
+        let mut c = children(5);
+        let after = c.split_off(2);
+        assert_eq!(c, children(2));
+        assert_eq!(after.len(), 3);
+        c.append(after);
+        assert_eq!(c, children(5));
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use sha2::{Digest, Sha256};
+
+use super::editor::Editor;
+use super::element::Element;
+use super::node::{Descendant, Node};
+use super::text::{Marks, Text};
+use crate::serialize::json::{canonical_element, canonical_text};
+
+/// A SHA-256 hash of the canonical JSON of a node's content, so nodes with
+/// equal content have equal hashes on every peer.
+///
+/// An element's hash covers its kind and properties and the hashes of its
+/// children, which lets it be computed incrementally: elements cache their
+/// hash, and a change only clears the caches of the changed node's ancestors.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Read a hash written by `Display`, as lowercase hex.
+    pub(crate) fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return None;
+        }
+
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        Some(Self(bytes))
+    }
+
+    fn of(tag: &[u8], content: &str, children: impl IntoIterator<Item = ContentHash>) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(tag);
+        hasher.update(content.as_bytes());
+        for child in children {
+            hasher.update(child.0);
+        }
+
+        Self(hasher.finalize().into())
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ContentHash({})", self)
+    }
+}
+
+/// The cached hash of an element, cleared whenever it's borrowed mutably.
+#[derive(Clone, Default)]
+pub(crate) struct HashCache(OnceLock<ContentHash>);
+
+impl HashCache {
+    pub(crate) fn clear(&mut self) {
+        self.0.take();
+    }
+}
+
+impl fmt::Debug for HashCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.get().fmt(f)
+    }
+}
+
+// The cache is derived from the element, so it never makes two elements
+// unequal.
+impl PartialEq for HashCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Text {
+    pub fn content_hash(&self) -> ContentHash {
+        // Custom marks aren't named without a registry, so they're hashed by
+        // their bits, which peers registering them in the same order share.
+        let mut content = canonical_text(self);
+        let custom = self.marks() - Marks::BUILT_IN;
+        if !custom.is_empty() {
+            content.push_str(&format!("\0{}", custom.bits()));
+        }
+        ContentHash::of(b"text\0", &content, None)
+    }
+}
+
+impl Element {
+    pub fn content_hash(&self) -> ContentHash {
+        *self.hash_cache().0.get_or_init(|| {
+            let children = self.children_ref().iter().map(Descendant::content_hash);
+            ContentHash::of(b"element\0", &canonical_element(self), children)
+        })
+    }
+}
+
+impl Descendant {
+    pub fn content_hash(&self) -> ContentHash {
+        match self {
+            Descendant::Element(e) => e.content_hash(),
+            Descendant::Text(t) => t.content_hash(),
+        }
+    }
+}
+
+impl Node {
+    pub fn content_hash(&self) -> ContentHash {
+        match self {
+            Node::Editor(e) => e.content_hash(),
+            Node::Element(e) => e.content_hash(),
+            Node::Text(t) => t.content_hash(),
+        }
+    }
+}
+
+impl Editor {
+    /// Hash the document. The selection and pending operations aren't part
+    /// of it.
+    pub fn content_hash(&self) -> ContentHash {
+        let children = self.children.iter().map(Descendant::content_hash);
+        ContentHash::of(b"editor\0", "", children)
+    }
+
+    /// Check whether the document matches a hash, like one sent by a peer.
+    pub fn verify(&self, hash: &ContentHash) -> bool {
+        self.content_hash() == *hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Marks, NodeProperties, Operation, Path};
+
+    #[test]
+    fn equal_content_equal_hash() {
+        let a = Element::new()
+            .add_property("a", "1")
+            .add_child(Text::new("x"));
+        let b = Element::new()
+            .add_child(Text::new("x"))
+            .add_property("a", "1");
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(
+            a.content_hash(),
+            Element::new().add_child(Text::new("x")).content_hash()
+        );
+        assert_eq!(a.content_hash().to_string().len(), 64);
+    }
+
+    #[test]
+    fn custom_marks_change_the_hash() {
+        let strike = crate::types::MarkRegistry::new()
+            .register("strikethrough")
+            .unwrap();
+        assert_ne!(
+            Text::with_marks("x", strike).content_hash(),
+            Text::new("x").content_hash()
+        );
+    }
+
+    #[test]
+    fn edits_clear_cached_hashes() {
+        let mut editor = Editor::new().add_child(
+            Element::with_kind("block-quote").add_child(Element::new().add_child(Text::new("abc"))),
+        );
+        let before = editor.content_hash();
+        assert!(editor.verify(&before));
+
+        editor
+            .apply(Operation::SetNode {
+                path: Path::new(vec![0, 0, 0]),
+                properties: NodeProperties::new().marks(Marks::empty()),
+                new_properties: NodeProperties::new().marks(Marks::BOLD),
+            })
+            .unwrap();
+        assert!(!editor.verify(&before));
+
+        let expected = Editor::new().add_child(
+            Element::with_kind("block-quote")
+                .add_child(Element::new().add_child(Text::with_marks("abc", Marks::BOLD))),
+        );
+        assert!(editor.verify(&expected.content_hash()));
+    }
+}
@@ -0,0 +1,355 @@
+use std::sync::OnceLock;
+
+use super::children::Children;
+use super::editor::Editor;
+use super::element::Element;
+use super::node::{Descendant, Node};
+use super::operation::Operation;
+use super::path::Path;
+use super::text::Text;
+use crate::node_cache::NodeCache;
+
+/// A borrowed reference to a node of any kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeRef<'a> {
+    Editor(&'a Editor),
+    Element(&'a Element),
+    Text(&'a Text),
+}
+
+impl<'a> NodeRef<'a> {
+    pub fn to_node(self) -> Node {
+        match self {
+            NodeRef::Editor(e) => Node::Editor(e.clone()),
+            NodeRef::Element(e) => Node::Element(e.clone()),
+            NodeRef::Text(t) => Node::Text(t.clone()),
+        }
+    }
+
+    pub(crate) fn children(self) -> Option<&'a Children> {
+        match self {
+            NodeRef::Editor(e) => Some(&e.children),
+            NodeRef::Element(e) => Some(e.children_ref()),
+            NodeRef::Text(_) => None,
+        }
+    }
+
+    /// Get the node at a path relative to this one, descending by reference.
+    pub(crate) fn descend(self, path: &Path) -> Option<NodeRef<'a>> {
+        let mut node = self;
+
+        for i in path.as_slice() {
+            node = node.children()?.get(*i)?.into();
+        }
+
+        Some(node)
+    }
+}
+
+impl<'a> From<&'a Editor> for NodeRef<'a> {
+    fn from(val: &'a Editor) -> Self {
+        NodeRef::Editor(val)
+    }
+}
+
+impl<'a> From<&'a Node> for NodeRef<'a> {
+    fn from(val: &'a Node) -> Self {
+        match val {
+            Node::Editor(e) => NodeRef::Editor(e),
+            Node::Element(e) => NodeRef::Element(e),
+            Node::Text(t) => NodeRef::Text(t),
+        }
+    }
+}
+
+impl<'a> From<&'a Descendant> for NodeRef<'a> {
+    fn from(val: &'a Descendant) -> Self {
+        match val {
+            Descendant::Element(e) => NodeRef::Element(e),
+            Descendant::Text(t) => NodeRef::Text(t),
+        }
+    }
+}
+
+/// An index of a document's nodes by path, which the editor keeps so that
+/// looking up a node, its parent or its siblings is a single hash lookup
+/// instead of a descent from the root.
+///
+/// The index is built the first time a node is looked up, and applying an
+/// operation updates it like a `NodeCache`: the nodes it changes and their
+/// ancestors are indexed again, and the rest move along with their nodes.
+/// Clones of an editor start without one, as it's only derived from the
+/// document, and build their own if they look a node up.
+#[derive(Debug, Default)]
+pub(crate) struct DocumentIndex {
+    nodes: OnceLock<NodeCache<Descendant>>,
+}
+
+impl Clone for DocumentIndex {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl DocumentIndex {
+    fn nodes(&self, children: &Children) -> &NodeCache<Descendant> {
+        self.nodes.get_or_init(|| {
+            let mut nodes = NodeCache::new();
+            for (i, child) in children.iter().enumerate() {
+                index_subtree(&mut nodes, Path::new(vec![i]), child);
+            }
+            nodes
+        })
+    }
+
+    /// Get the descendant at a path in a document's children.
+    pub(crate) fn get<'a>(&'a self, children: &Children, path: &Path) -> Option<&'a Descendant> {
+        self.nodes(children).get(path)
+    }
+
+    /// Check if there's a descendant at a path in a document's children.
+    pub(crate) fn has(&self, children: &Children, path: &Path) -> bool {
+        self.get(children, path).is_some()
+    }
+
+    /// Get the parent of the descendant at a path, if it isn't the document
+    /// itself.
+    pub(crate) fn parent<'a>(&'a self, children: &Children, path: &Path) -> Option<&'a Descendant> {
+        self.get(children, &path.parent().filter(|p| !p.is_empty())?)
+    }
+
+    pub(crate) fn next_sibling<'a>(
+        &'a self,
+        children: &Children,
+        path: &Path,
+    ) -> Option<&'a Descendant> {
+        self.get(children, &path.next()?)
+    }
+
+    pub(crate) fn previous_sibling<'a>(
+        &'a self,
+        children: &Children,
+        path: &Path,
+    ) -> Option<&'a Descendant> {
+        self.get(children, &path.previous()?)
+    }
+
+    /// Update the index for an operation that has just been applied to a
+    /// document's children, if it's been built.
+    pub(crate) fn apply(&mut self, op: &Operation, children: &Children) {
+        let nodes = match self.nodes.get_mut() {
+            Some(nodes) => nodes,
+            None => return,
+        };
+        nodes.apply(op);
+
+        let after = |path: &Path| Path::transform(path, op, Default::default()).ok().flatten();
+        match op {
+            Operation::InsertNode { path, .. } => {
+                if let Some(node) = descend(children, path) {
+                    index_subtree(nodes, path.clone(), node);
+                }
+                index_ancestors(nodes, children, path);
+            }
+            Operation::RemoveNode { path, .. } => index_ancestors(nodes, children, path),
+            Operation::MergeNode { path, .. } => {
+                if let Some(previous) = path.previous() {
+                    index_node(nodes, children, &previous);
+                }
+                index_ancestors(nodes, children, path);
+            }
+            Operation::SplitNode { path, .. } => {
+                index_node(nodes, children, path);
+                if let Some(next) = path.next() {
+                    index_node(nodes, children, &next);
+                }
+                index_ancestors(nodes, children, path);
+            }
+            Operation::MoveNode { path, .. } => {
+                if let Some(moved) = after(path) {
+                    index_node(nodes, children, &moved);
+                    index_ancestors(nodes, children, &moved);
+                }
+                if let Some(parent) = path.parent().as_ref().and_then(after) {
+                    index_node(nodes, children, &parent);
+                    index_ancestors(nodes, children, &parent);
+                }
+            }
+            Operation::InsertText { path, .. }
+            | Operation::RemoveText { path, .. }
+            | Operation::SetNode { path, .. } => {
+                index_node(nodes, children, path);
+                index_ancestors(nodes, children, path);
+            }
+            Operation::SetSelection { .. } => {}
+        }
+    }
+
+    /// Drop the index, for a document whose children were replaced.
+    pub(crate) fn clear(&mut self) {
+        self.nodes.take();
+    }
+
+    /// Estimate the bytes the index takes on the heap. Indexed nodes share
+    /// their contents with the document, so only their slots are counted.
+    pub(crate) fn heap_size(&self) -> usize {
+        self.nodes.get().map_or(0, |nodes| nodes.heap_size(|_| 0))
+    }
+}
+
+/// Index a node and every node inside of it.
+fn index_subtree(nodes: &mut NodeCache<Descendant>, path: Path, node: &Descendant) {
+    if let Some(children) = node.children_ref() {
+        for (i, child) in children.iter().enumerate() {
+            index_subtree(nodes, path.concat(i), child);
+        }
+    }
+    nodes.insert(path, node.clone());
+}
+
+/// Index the node at a path again, if there's one. The nodes inside of it
+/// are moved by the operation or indexed on their own.
+fn index_node(nodes: &mut NodeCache<Descendant>, children: &Children, path: &Path) {
+    if let Some(node) = descend(children, path) {
+        nodes.insert(path.clone(), node.clone());
+    }
+}
+
+fn index_ancestors(nodes: &mut NodeCache<Descendant>, children: &Children, path: &Path) {
+    for ancestor in path.ancestors(false).iter().filter(|p| !p.is_empty()) {
+        index_node(nodes, children, ancestor);
+    }
+}
+
+fn descend<'a>(children: &'a Children, path: &Path) -> Option<&'a Descendant> {
+    let (first, rest) = path.as_slice().split_first()?;
+    let mut node = children.get(*first)?;
+
+    for i in rest {
+        node = node.children_ref()?.get(*i)?;
+    }
+
+    Some(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::NodeProperties;
+
+    fn editor() -> Editor {
+        Editor::new()
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("a"))
+                    .add_child(Text::new("b")),
+            )
+            .add_child(Element::new().add_child(Text::new("c")))
+    }
+
+    fn indexed(editor: &Editor, path: Vec<usize>) -> Option<Descendant> {
+        let nodes = editor.caches.index.nodes.get()?;
+        nodes.get(&path.into()).cloned()
+    }
+
+    #[test]
+    fn lookups_are_indexed() {
+        let editor = editor();
+        let root = Node::Editor(editor.clone());
+        assert_eq!(
+            root.get(&vec![0, 1].into()).map(|n| *n),
+            Some(Node::Text(Text::new("b")))
+        );
+        assert!(!root.has(&vec![1, 1].into()));
+        assert_eq!(root.get(&vec![].into()).map(|n| *n), Some(root.clone()));
+
+        let Node::Editor(indexed_editor) = root else {
+            unreachable!()
+        };
+        assert_eq!(
+            indexed(&indexed_editor, vec![1, 0]),
+            Some(Text::new("c").into())
+        );
+        assert_eq!(indexed(&indexed_editor, vec![1, 1]), None);
+        assert_eq!(indexed(&editor, vec![0]), None);
+    }
+
+    #[test]
+    fn parents_and_siblings() {
+        let editor = editor();
+        let index = &editor.caches.index;
+        let path = Path::new(vec![0, 1]);
+        assert_eq!(
+            index.parent(&editor.children, &path),
+            editor.children.get(0)
+        );
+        assert_eq!(
+            index.previous_sibling(&editor.children, &path),
+            Some(&Text::new("a").into())
+        );
+        assert_eq!(index.next_sibling(&editor.children, &path), None);
+        assert_eq!(index.parent(&editor.children, &Path::new(vec![1])), None);
+        assert_eq!(
+            index.next_sibling(&editor.children, &Path::new(vec![0])),
+            editor.children.get(1)
+        );
+    }
+
+    #[test]
+    fn applying_updates_the_index() {
+        let mut editor = editor();
+        editor.descendant_ref(&Path::new(vec![0]));
+
+        editor
+            .apply(Operation::InsertText {
+                path: Path::new(vec![0, 0]),
+                offset: 1,
+                text: "!".into(),
+            })
+            .unwrap();
+        assert_eq!(indexed(&editor, vec![0, 0]), Some(Text::new("a!").into()));
+        assert_eq!(indexed(&editor, vec![0]), editor.children.get(0).cloned());
+
+        editor
+            .apply(Operation::InsertNode {
+                path: Path::new(vec![0]),
+                node: Node::Element(Element::new().add_child(Text::new("new"))),
+            })
+            .unwrap();
+        assert_eq!(indexed(&editor, vec![0, 0]), Some(Text::new("new").into()));
+        assert_eq!(indexed(&editor, vec![1, 1]), Some(Text::new("b").into()));
+        assert_eq!(indexed(&editor, vec![2, 0]), Some(Text::new("c").into()));
+
+        for op in [
+            Operation::SplitNode {
+                path: Path::new(vec![1]),
+                position: 1,
+                properties: NodeProperties::default(),
+            },
+            Operation::MoveNode {
+                path: Path::new(vec![2, 0]),
+                new_path: Path::new(vec![0, 1]),
+            },
+            Operation::MergeNode {
+                path: Path::new(vec![2]),
+                position: 1,
+                properties: NodeProperties::default(),
+            },
+            Operation::RemoveNode {
+                path: Path::new(vec![0, 0]),
+                node: Node::Text(Text::new("new")),
+            },
+        ] {
+            editor.apply(op).unwrap();
+            let rebuilt = rebuilt(&editor);
+            assert_eq!(editor.caches.index.nodes.get(), rebuilt.nodes.get());
+        }
+    }
+
+    /// Get the index built from scratch for an editor's document.
+    fn rebuilt(editor: &Editor) -> DocumentIndex {
+        let index = DocumentIndex::default();
+        index.nodes(&editor.children);
+        index
+    }
+}
@@ -1,46 +1,742 @@
-use super::node::Descendant;
+use crate::behavior::BehaviorHandle;
+use crate::commands::Commands;
+use crate::fork::ForkLog;
+use crate::ids::IdGeneratorHandle;
+use crate::input::Actions;
+use crate::input_rules::InputRules;
+use crate::limits::EnforcedLimits;
+use crate::mark_rules::MarkRules;
+use crate::node_cache::NodeCaches;
+use crate::refs::Refs;
+use crate::sanitize::SanitizeOptions;
+use crate::schema::Schema;
+use crate::segmenter::SegmenterHandle;
+use crate::subscribers::Subscribers;
+use crate::text_index::TextIndex;
+use crate::transforms::{LineBreakerHandle, SelectionAffinities};
+
+use super::children::Children;
+use super::element::Element;
+use super::location::Location;
+use super::mark_registry::MarkRegistry;
+use super::node::{self, Descendant, Node, NodeEntry};
 use super::operation::Operation;
+use super::path::Path;
+use super::point::Point;
 use super::range::Range;
-use super::text::Marks;
+use super::text::{Marks, Text};
 
 type Selection = Option<Range>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Editor {
-    children: Vec<Descendant>,
-    selection: Selection,
-    operations: Vec<Operation>,
-    marks: Option<Marks>,
+    pub(crate) children: Children,
+    pub(crate) selection: Selection,
+    pub(crate) operations: Vec<Operation>,
+    /// The children as they were before the current batch of operations.
+    pub(crate) previous: Option<Box<Children>>,
+    pub(crate) marks: Option<Marks>,
+    pub(crate) paste_sanitizer: Option<Box<SanitizeOptions>>,
+    pub(crate) limits: Option<Box<EnforcedLimits>>,
+    pub(crate) mark_rules: Option<Box<MarkRules>>,
+    pub(crate) mark_registry: Option<Box<MarkRegistry>>,
+    pub(crate) schema: Option<Box<Schema>>,
+    pub(crate) author: Option<Box<str>>,
+    pub(crate) selection_affinities: SelectionAffinities,
+    pub(crate) line_breaker: Option<Box<LineBreakerHandle>>,
+    pub(crate) segmenter: Box<SegmenterHandle>,
+    pub(crate) id_generator: Option<Box<IdGeneratorHandle>>,
+    pub(crate) input_rules: Option<Box<InputRules>>,
+    pub(crate) commands: Option<Box<Commands>>,
+    pub(crate) behavior: Option<Box<BehaviorHandle>>,
+    pub(crate) autolink: bool,
+    /// The link most recently made by autolinking, until text is inserted.
+    pub(crate) autolinked: Option<Box<Path>>,
+    pub(crate) heading_anchors: bool,
+    /// The operations applied since the editor was last forked, if it was.
+    pub(crate) fork_log: Option<Box<ForkLog>>,
+    /// The version of the last migration applied to the document.
+    pub(crate) schema_version: u32,
+    pub(crate) text_index: Box<TextIndex>,
+    pub(crate) caches: Box<NodeCaches>,
+    pub(crate) subscribers: Box<Subscribers>,
+    pub(crate) refs: Box<Refs>,
+    /// The actions the pending operations were made by.
+    pub(crate) actions: Box<Actions>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Editor {
     pub fn new() -> Self {
         Self {
-            children: vec![],
+            children: Children::new(),
             selection: None,
             operations: vec![],
+            previous: None,
             marks: None,
+            paste_sanitizer: None,
+            limits: None,
+            mark_rules: None,
+            mark_registry: None,
+            schema: None,
+            author: None,
+            selection_affinities: SelectionAffinities::default(),
+            line_breaker: None,
+            segmenter: Box::default(),
+            id_generator: None,
+            input_rules: None,
+            commands: None,
+            behavior: None,
+            autolink: false,
+            autolinked: None,
+            heading_anchors: false,
+            fork_log: None,
+            schema_version: 0,
+            text_index: Box::default(),
+            caches: Box::default(),
+            subscribers: Box::default(),
+            refs: Box::default(),
+            actions: Box::default(),
         }
     }
 
+    /// Create an editor holding the smallest valid document, a single block of
+    /// the default kind with an empty text node.
+    pub fn with_default() -> Self {
+        Self::new().add_child(default_block())
+    }
+
     pub fn add_child(mut self, child: impl Into<Descendant>) -> Self {
-        self.children.push(child.into());
+        let child = child.into();
+        if let Some(limits) = &mut self.limits {
+            limits.add(&child);
+        }
+        self.children.push(child);
+        self.caches.index.clear();
         self
     }
 
+    /// Replace the document's children without an operation, like when it's
+    /// loaded from a store. Its size is measured again for the limits, and
+    /// what's cached of the old children is dropped.
+    pub(crate) fn replace_children(&mut self, children: impl Into<Children>) {
+        self.children = children.into();
+        if let Some(limits) = &mut self.limits {
+            limits.remeasure(&self.children);
+        }
+        *self.text_index = TextIndex::default();
+        self.caches.clear();
+    }
+
     pub fn child(&self, i: usize) -> Option<&Descendant> {
         self.children.get(i)
     }
 
     pub fn children(&self) -> Vec<Descendant> {
-        self.children.clone()
+        self.children.to_vec()
     }
 
     pub fn has_children(&self) -> bool {
-        self.children.len() > 0
+        !self.children.is_empty()
     }
 
     pub fn num_children(&self) -> usize {
         self.children.len()
     }
+
+    pub fn selection(&self) -> Option<&Range> {
+        self.selection.as_ref()
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Get the document's children as they were before the current batch of
+    /// operations, or `None` if nothing has been applied yet. The snapshot
+    /// shares every node the batch didn't change with the current document.
+    pub fn previous(&self) -> Option<&Children> {
+        self.previous.as_deref()
+    }
+
+    pub fn marks(&self) -> Option<Marks> {
+        self.marks
+    }
+
+    /// Check whether two editors hold the same document, ignoring their
+    /// selections, pending operations and configuration.
+    pub fn content_eq(&self, other: &Editor) -> bool {
+        self.children == other.children
+    }
+}
+
+impl Editor {
+    /// Get a reference to the descendant at a path, from the document index.
+    /// The empty path refers to the editor itself, which isn't a descendant.
+    pub(crate) fn descendant_ref(&self, path: &Path) -> Option<&Descendant> {
+        self.caches.index.get(&self.children, path)
+    }
+
+    /// Get a reference to the parent of the descendant at a path, unless it's
+    /// the editor.
+    pub(crate) fn parent_ref(&self, path: &Path) -> Option<&Descendant> {
+        self.caches.index.parent(&self.children, path)
+    }
+
+    pub(crate) fn next_sibling_ref(&self, path: &Path) -> Option<&Descendant> {
+        self.caches.index.next_sibling(&self.children, path)
+    }
+
+    pub(crate) fn previous_sibling_ref(&self, path: &Path) -> Option<&Descendant> {
+        self.caches.index.previous_sibling(&self.children, path)
+    }
+
+    pub(crate) fn descendant_mut(&mut self, path: &Path) -> Option<&mut Descendant> {
+        let (first, rest) = path.as_slice().split_first()?;
+        let mut node = self.children.get_mut(*first)?;
+
+        for i in rest {
+            node = node.children_mut()?.get_mut(*i)?;
+        }
+
+        Some(node)
+    }
+
+    /// Get the children of the node at `path`, which may be the editor itself.
+    pub(crate) fn children_at(&self, path: &Path) -> Option<&Children> {
+        if path.is_empty() {
+            return Some(&self.children);
+        }
+
+        match self.descendant_ref(path)? {
+            Descendant::Element(e) => Some(e.children_ref()),
+            Descendant::Text(_) => None,
+        }
+    }
+
+    pub(crate) fn children_at_mut(&mut self, path: &Path) -> Option<&mut Children> {
+        if path.is_empty() {
+            return Some(&mut self.children);
+        }
+
+        self.descendant_mut(path)?.children_mut()
+    }
+
+    /// Get the start point of a location, if it's in the document.
+    pub fn start(&self, at: impl Into<Location>) -> Option<Point> {
+        match at.into() {
+            Location::Path(path) => {
+                let children = self.children_at(&path);
+                let texts = match children {
+                    Some(children) => node::text_entries(children, &path),
+                    None => vec![(self.descendant_ref(&path)?.as_text()?, path)],
+                };
+                let (_, path) = texts.into_iter().next()?;
+                Some(Point { path, offset: 0 })
+            }
+            Location::Point(point) => self.check_point(&point).is_ok().then_some(point),
+            Location::Range(range) => self.range(range).map(|range| range.start()),
+        }
+    }
+
+    /// Get the end point of a location, if it's in the document.
+    pub fn end(&self, at: impl Into<Location>) -> Option<Point> {
+        match at.into() {
+            Location::Path(path) => {
+                let children = self.children_at(&path);
+                let texts = match children {
+                    Some(children) => node::text_entries(children, &path),
+                    None => vec![(self.descendant_ref(&path)?.as_text()?, path)],
+                };
+                let (text, path) = texts.into_iter().last()?;
+                Some(Point {
+                    path,
+                    offset: text.text().len(),
+                })
+            }
+            Location::Point(point) => self.check_point(&point).is_ok().then_some(point),
+            Location::Range(range) => self.range(range).map(|range| range.end()),
+        }
+    }
+
+    /// Get a range spanning a location. Points and ranges are only ones if
+    /// their points are in text nodes of the document, on a character
+    /// boundary of their text.
+    pub fn range(&self, at: impl Into<Location>) -> Option<Range> {
+        match at.into() {
+            Location::Range(range) => {
+                let (anchor, focus) = range.points();
+                (self.check_point(anchor).is_ok() && self.check_point(focus).is_ok())
+                    .then_some(range)
+            }
+            at => {
+                let start = self.start(at.clone())?;
+                let end = self.end(at)?;
+                Some(Range::new(start, end))
+            }
+        }
+    }
+
+    /// Get the path of the closest isolating element containing `path`,
+    /// including the node at `path` itself. Elements are isolating if the
+    /// editor's schema says so.
+    pub fn above_isolating(&self, path: &Path) -> Option<Path> {
+        let schema = self.schema.as_deref()?;
+
+        path.levels(true).into_iter().find(|p| {
+            matches!(self.descendant_ref(p), Some(Descendant::Element(e)) if schema.is_isolating(e.kind()))
+        })
+    }
+
+    /// Get the points the caret can move to from `from`, one grapheme at a
+    /// time, going forward or in `reverse`. The end of one text and the start
+    /// of the next one in the same block are the same position.
+    ///
+    /// Positions don't leave the closest isolating element containing `from`,
+    /// or enter isolating elements that don't contain it.
+    pub fn positions(&self, from: &Point, reverse: bool) -> impl Iterator<Item = Point> {
+        let positions = self.positions_in_scope(from, true);
+        let split = if reverse {
+            positions.partition_point(|p| p.is_before(from) && !self.is_same_position(p, from))
+        } else {
+            positions.partition_point(|p| !p.is_after(from))
+        };
+
+        let (before, after) = positions.split_at(split);
+        let out: Vec<Point> = if reverse {
+            before.iter().rev().cloned().collect()
+        } else {
+            after.to_vec()
+        };
+        out.into_iter()
+    }
+
+    /// Get every caret position in the closest isolating element containing
+    /// `from`, in document order, leaving out those in other isolating
+    /// elements if `skip_isolated` is set.
+    pub(crate) fn positions_in_scope(&self, from: &Point, skip_isolated: bool) -> Vec<Point> {
+        let scope = self.above_isolating(&from.path).unwrap_or_default();
+        let children = match self.children_at(&scope) {
+            Some(children) => children,
+            None => return vec![],
+        };
+
+        let mut out: Vec<Point> = vec![];
+        let mut block = None;
+        for (text, path) in node::text_entries(children, &scope) {
+            if skip_isolated && self.above_isolating(&path).unwrap_or_default() != scope {
+                continue;
+            }
+
+            let this_block = self.block_path(&path);
+            let same_block = block.is_some() && block == this_block;
+            block = this_block;
+
+            let offsets = text.positions().skip(if same_block { 1 } else { 0 });
+            out.extend(offsets.map(|offset| Point {
+                path: path.clone(),
+                offset,
+            }));
+        }
+
+        out
+    }
+
+    /// Whether a point is the end of the text right before `point`'s text in
+    /// the same block, when `point` is at the start of its text.
+    pub(crate) fn is_same_position(&self, p: &Point, point: &Point) -> bool {
+        point.offset == 0
+            && point.path.previous() == Some(p.path.clone())
+            && self
+                .text_at(&p.path)
+                .is_some_and(|t| t.text().len() == p.offset)
+            && self.block_path(&p.path) == self.block_path(&point.path)
+    }
+
+    /// Get the path of the lowest block element containing `path`. A block is
+    /// any element whose siblings aren't inline content.
+    pub(crate) fn block_path(&self, path: &Path) -> Option<Path> {
+        for ancestor in path.levels(true) {
+            if ancestor.is_empty() {
+                break;
+            }
+
+            if !matches!(self.descendant_ref(&ancestor)?, Descendant::Element(_)) {
+                continue;
+            }
+
+            let parent = ancestor.parent()?;
+            if parent.is_empty() || !node::has_inlines(self.children_at(&parent)?) {
+                return Some(ancestor);
+            }
+        }
+
+        None
+    }
+
+    /// Get the fragment of the document contained in a location, with text at
+    /// its edges clipped to the location's boundaries.
+    pub fn fragment(&self, at: impl Into<Location>) -> Vec<Descendant> {
+        let range = match self.range(at) {
+            Some(range) => range,
+            None => return vec![],
+        };
+        let (start, end) = range.edges(false);
+        clip(&self.children, &Path::new(vec![]), start, end)
+    }
+
+    /// Get the path of a location: a path itself, the path of a point's
+    /// text, or the closest common ancestor of a range's points.
+    pub fn path(&self, at: impl Into<Location>) -> Path {
+        match at.into() {
+            Location::Path(path) => path,
+            Location::Point(point) => point.path,
+            Location::Range(range) => range.anchor().path.common(&range.focus().path),
+        }
+    }
+
+    /// Get the node at a location, as `Editor::path` resolves it. The root
+    /// path gets the editor itself.
+    pub fn node(&self, at: impl Into<Location>) -> Option<NodeEntry> {
+        let path = self.path(at);
+        let node = match path.is_empty() {
+            true => Node::Editor(self.clone()),
+            false => Node::from(self.descendant_ref(&path)?),
+        };
+        Some((Box::new(node), path))
+    }
+
+    /// Get the parent of the node at a location.
+    pub fn parent(&self, at: impl Into<Location>) -> Option<NodeEntry> {
+        let path = self.path(at);
+        self.node(path.parent()?)
+    }
+
+    /// Get the closest ancestor of a location that `matches` accepts, the
+    /// way slate.js's `Editor.above` does. Texts and the node at the
+    /// location itself are skipped, and for a range, only ancestors of both
+    /// of its points are considered.
+    pub fn above(
+        &self,
+        at: impl Into<Location>,
+        matches: impl Fn(&Node, &Path) -> bool,
+    ) -> Option<NodeEntry> {
+        let at = at.into();
+        let path = self.path(at.clone());
+
+        for level in path.levels(true) {
+            let above = match &at {
+                Location::Range(range) => {
+                    level.is_ancestor(&range.anchor().path)
+                        && level.is_ancestor(&range.focus().path)
+                }
+                _ => level != path,
+            };
+            if !above || matches!(self.descendant_ref(&level), Some(Descendant::Text(_))) {
+                continue;
+            }
+
+            let (node, level) = self.node(level)?;
+            if matches(&node, &level) {
+                return Some((node, level));
+            }
+        }
+
+        None
+    }
+
+    /// Get the text at a location: the text at a path or point, or at the
+    /// start of a range.
+    pub fn leaf(&self, at: impl Into<Location>) -> Option<(Text, Path)> {
+        let path = match at.into() {
+            Location::Range(range) => range.start().path,
+            at => self.path(at),
+        };
+        Some((self.text_at(&path)?.clone(), path))
+    }
+
+    /// Get the text content of a location, with text at its edges clipped to
+    /// the location's boundaries. Blocks aren't separated.
+    pub fn string(&self, at: impl Into<Location>) -> String {
+        let fragment = Children::from(self.fragment(at));
+        node::text_entries(&fragment, &Path::new(vec![]))
+            .into_iter()
+            .map(|(text, _)| text.text())
+            .collect()
+    }
+}
+
+/// The only block of an empty document.
+pub(crate) fn default_block() -> Element {
+    Element::new().add_child(Text::new(""))
+}
+
+fn clip(children: &Children, parent: &Path, start: &Point, end: &Point) -> Vec<Descendant> {
+    let mut out = vec![];
+
+    for (i, child) in children.iter().enumerate() {
+        let path = parent.concat(i);
+
+        if path.is_before(&start.path) || path.is_after(&end.path) {
+            continue;
+        }
+
+        match child {
+            Descendant::Text(t) => {
+                let len = t.text().len();
+                let from = if path == start.path {
+                    start.offset.min(len)
+                } else {
+                    0
+                };
+                let to = if path == end.path {
+                    end.offset.min(len)
+                } else {
+                    len
+                };
+                let text = t.text().get(from..to.max(from)).unwrap_or("");
+                out.push(Descendant::Text(t.with_text(text)));
+            }
+            Descendant::Element(e) => {
+                let children = clip(e.children_ref(), &path, start, end);
+                out.push(Descendant::Element(e.with_children(children)));
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn editor_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Editor>();
+    }
+
+    #[test]
+    fn with_default_content() {
+        assert_eq!(
+            Editor::with_default().children(),
+            vec![Element::new().add_child(Text::new("")).into()]
+        );
+    }
+
+    #[test]
+    fn block_path_inline() {
+        let editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::new("a"))
+                .add_child(Element::with_kind("link").add_child(Text::new("b")))
+                .add_child(Text::new("c")),
+        );
+        assert_eq!(
+            editor.block_path(&vec![0, 1, 0].into()),
+            Some(vec![0].into())
+        );
+    }
+
+    #[test]
+    fn block_path_nested() {
+        let editor = Editor::new().add_child(
+            Element::with_kind("block-quote").add_child(Element::new().add_child(Text::new("a"))),
+        );
+        assert_eq!(
+            editor.block_path(&vec![0, 0, 0].into()),
+            Some(vec![0, 0].into())
+        );
+    }
+
+    #[test]
+    fn end_path() {
+        let editor = Editor::new().add_child(
+            Element::new()
+                .add_child(Text::new("one"))
+                .add_child(Text::new("two")),
+        );
+        assert_eq!(editor.end(Path::new(vec![0])), Some(point(vec![0, 1], 3)));
+    }
+
+    #[test]
+    fn fragment_across_blocks() {
+        let editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("one")))
+            .add_child(Element::new().add_child(Text::new("two")))
+            .add_child(Element::new().add_child(Text::new("three")));
+        let range = Range::new(point(vec![0, 0], 1), point(vec![1, 0], 2));
+        assert_eq!(
+            editor.fragment(range),
+            vec![
+                Element::new().add_child(Text::new("ne")).into(),
+                Element::new().add_child(Text::new("tw")).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn fragment_backward() {
+        let editor = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        let range = Range::new(point(vec![0, 0], 3), point(vec![0, 0], 1));
+        assert_eq!(
+            editor.fragment(range),
+            vec![Element::new().add_child(Text::new("or")).into()]
+        );
+    }
+
+    #[test]
+    fn queries_resolve_locations() {
+        let editor = Editor::new()
+            .add_child(
+                Element::with_kind("block-quote")
+                    .add_child(Element::new().add_child(Text::new("one")))
+                    .add_child(Element::new().add_child(Text::new("two"))),
+            )
+            .add_child(Element::new().add_child(Text::new("three")));
+        let kind = |entry: Option<NodeEntry>| match entry.map(|(node, _)| *node) {
+            Some(Node::Element(e)) => Some(e.kind().to_string()),
+            Some(Node::Editor(_)) => Some("editor".into()),
+            _ => None,
+        };
+
+        let inside = Range::new(point(vec![0, 0, 0], 1), point(vec![0, 1, 0], 2));
+        assert_eq!(editor.path(inside.clone()), Path::new(vec![0]));
+        assert_eq!(
+            kind(editor.node(inside.clone())),
+            Some("block-quote".into())
+        );
+        assert_eq!(
+            kind(editor.parent(point(vec![0, 1, 0], 0))),
+            Some("paragraph".into())
+        );
+        assert_eq!(
+            kind(editor.parent(Path::new(vec![1]))),
+            Some("editor".into())
+        );
+        assert!(editor.parent(Path::new(vec![])).is_none());
+
+        // The node at the location itself isn't above it, but the common
+        // ancestor of a range's points is.
+        let quote =
+            |node: &Node, _: &Path| matches!(node, Node::Element(e) if e.kind() == "block-quote");
+        assert!(editor.above(Path::new(vec![0]), quote).is_none());
+        assert_eq!(
+            editor.above(point(vec![0, 1, 0], 0), quote).map(|(_, p)| p),
+            Some(Path::new(vec![0]))
+        );
+        assert_eq!(
+            editor.above(inside.clone(), |_, _| true).map(|(_, p)| p),
+            Some(Path::new(vec![0]))
+        );
+
+        let (text, path) = editor.leaf(inside.clone()).unwrap();
+        assert_eq!((text.text(), path), ("one", Path::new(vec![0, 0, 0])));
+        assert!(editor.leaf(Path::new(vec![0])).is_none());
+
+        assert_eq!(editor.string(inside), "netw");
+        assert_eq!(editor.string(Path::new(vec![1])), "three");
+    }
+
+    #[test]
+    fn start_path() {
+        let editor = Editor::new().add_child(
+            Element::with_kind("block-quote").add_child(Element::new().add_child(Text::new("a"))),
+        );
+        assert_eq!(
+            editor.start(Path::new(vec![0])),
+            Some(point(vec![0, 0, 0], 0))
+        );
+    }
+
+    #[test]
+    fn content_eq_ignores_selection() {
+        let a = Editor::new().add_child(Element::new().add_child(Text::new("word")));
+        let mut b = a.clone();
+        b.select(Path::new(vec![0]));
+
+        assert_ne!(a, b);
+        assert!(a.content_eq(&b));
+        assert!(!a.content_eq(&Editor::with_default()));
+    }
+
+    fn table_doc() -> Editor {
+        let cell = |text: &str| Element::with_kind("table-cell").add_child(Text::new(text));
+        Editor::new()
+            .add_child(Element::new().add_child(Text::new("a")))
+            .add_child(
+                Element::with_kind("table").add_child(
+                    Element::with_kind("table-row")
+                        .add_child(cell("b"))
+                        .add_child(cell("c")),
+                ),
+            )
+            .add_child(
+                Element::new()
+                    .add_child(Text::new("d"))
+                    .add_child(Text::with_marks("e", Marks::BOLD)),
+            )
+            .with_schema(Schema::default())
+    }
+
+    #[test]
+    fn above_isolating_finds_cells() {
+        let editor = table_doc();
+        assert_eq!(
+            editor.above_isolating(&vec![1, 0, 1, 0].into()),
+            Some(vec![1, 0, 1].into())
+        );
+        assert_eq!(editor.above_isolating(&vec![2, 0].into()), None);
+    }
+
+    #[test]
+    fn positions_stay_out_of_isolating_elements() {
+        let editor = table_doc();
+        let forward: Vec<Point> = editor.positions(&point(vec![0, 0], 1), false).collect();
+        assert_eq!(
+            forward,
+            vec![
+                point(vec![2, 0], 0),
+                point(vec![2, 0], 1),
+                point(vec![2, 1], 1)
+            ]
+        );
+
+        let back: Vec<Point> = editor.positions(&point(vec![2, 1], 0), true).collect();
+        assert_eq!(
+            back,
+            vec![
+                point(vec![2, 0], 0),
+                point(vec![0, 0], 1),
+                point(vec![0, 0], 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn positions_stay_inside_isolating_elements() {
+        let editor = table_doc();
+        assert_eq!(
+            editor.positions(&point(vec![1, 0, 0, 0], 1), false).next(),
+            None
+        );
+        assert_eq!(
+            editor.positions(&point(vec![1, 0, 1, 0], 1), true).next(),
+            Some(point(vec![1, 0, 1, 0], 0))
+        );
+    }
 }
@@ -1,42 +1,122 @@
+use std::collections::BTreeMap;
+
+use super::children::Children;
+use super::content_hash::HashCache;
 use super::node::Descendant;
 use super::Path;
 
+/// The kind given to elements created without an explicit one.
+pub const DEFAULT_KIND: &str = "paragraph";
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Element {
-    children: Vec<Descendant>,
+    kind: String,
+    properties: BTreeMap<String, String>,
+    children: Children,
+    hash: HashCache,
 }
 
-impl Into<Descendant> for Element {
-    fn into(self) -> Descendant {
-        Descendant::Element(self)
+impl From<Element> for Descendant {
+    fn from(val: Element) -> Self {
+        Descendant::Element(val)
+    }
+}
+
+impl Default for Element {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Element {
     pub fn new() -> Self {
-        Self { children: vec![] }
+        Self::with_kind(DEFAULT_KIND)
+    }
+
+    pub fn with_kind(kind: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            properties: BTreeMap::new(),
+            children: Children::new(),
+            hash: HashCache::default(),
+        }
     }
 
     pub fn add_child(mut self, child: impl Into<Descendant>) -> Self {
+        self.hash.clear();
         self.children.push(child.into());
         self
     }
 
+    pub fn add_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.hash.clear();
+        self.properties.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    pub fn properties(&self) -> &BTreeMap<String, String> {
+        &self.properties
+    }
+
     pub fn child(&self, i: usize) -> Option<&Descendant> {
         self.children.get(i)
     }
 
     pub fn children(&self) -> Vec<Descendant> {
-        self.children.clone()
+        self.children.to_vec()
+    }
+
+    pub(crate) fn hash_cache(&self) -> &HashCache {
+        &self.hash
+    }
+
+    // The mutable accessors clear the cached hash. Changing a descendant goes
+    // through them on each of its ancestors, clearing theirs too.
+    pub(crate) fn kind_mut(&mut self) -> &mut String {
+        self.hash.clear();
+        &mut self.kind
+    }
+
+    pub(crate) fn properties_mut(&mut self) -> &mut BTreeMap<String, String> {
+        self.hash.clear();
+        &mut self.properties
+    }
+
+    pub(crate) fn children_ref(&self) -> &Children {
+        &self.children
+    }
+
+    pub(crate) fn children_mut(&mut self) -> &mut Children {
+        self.hash.clear();
+        &mut self.children
     }
 
     pub fn has_children(&self) -> bool {
-        self.children.len() > 0
+        !self.children.is_empty()
     }
 
     pub fn num_children(&self) -> usize {
         self.children.len()
     }
+
+    /// Create an element with the same kind and properties but the given
+    /// children.
+    pub(crate) fn with_children(&self, children: impl Into<Children>) -> Self {
+        Self {
+            kind: self.kind.clone(),
+            properties: self.properties.clone(),
+            children: children.into(),
+            hash: HashCache::default(),
+        }
+    }
 }
 
 /// `ElementEntry` objects refer to an `Element` and the `Path` where it can be
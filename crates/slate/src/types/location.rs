@@ -1,9 +1,28 @@
 use super::{Path, Point, Range};
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Location {
     Path(Path),
     Point(Point),
     Range(Range),
 }
 
-pub struct Span(Path, Path);
+impl From<Path> for Location {
+    fn from(val: Path) -> Self {
+        Location::Path(val)
+    }
+}
+
+impl From<Point> for Location {
+    fn from(val: Point) -> Self {
+        Location::Point(val)
+    }
+}
+
+impl From<Range> for Location {
+    fn from(val: Range) -> Self {
+        Location::Range(val)
+    }
+}
+
+pub struct Span(pub Path, pub Path);
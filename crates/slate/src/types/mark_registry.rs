@@ -0,0 +1,152 @@
+use std::sync::LazyLock;
+
+use super::editor::Editor;
+use super::text::Marks;
+
+static BUILT_IN: LazyLock<MarkRegistry> = LazyLock::new(MarkRegistry::default);
+
+/// The names marks are serialized as, and the bits of custom marks.
+///
+/// `Marks` only defines the built-in marks. Other marks, like strikethrough or
+/// superscript, are registered by name and given one of the unused bits, so they can
+/// be stored on text and serialized like the built-in ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkRegistry {
+    marks: Vec<(Marks, String)>,
+}
+
+impl Default for MarkRegistry {
+    fn default() -> Self {
+        Self {
+            marks: vec![
+                (Marks::BOLD, "bold".into()),
+                (Marks::ITALIC, "italic".into()),
+                (Marks::UNDERLINE, "underline".into()),
+                (Marks::CODE, "code".into()),
+            ],
+        }
+    }
+}
+
+impl MarkRegistry {
+    /// Create a registry with the built-in marks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a mark by name, returning its bit. Registering a name that is
+    /// already known returns the existing mark. Returns `None` if every bit is
+    /// taken.
+    pub fn register(&mut self, name: impl Into<String>) -> Option<Marks> {
+        let name = name.into();
+
+        if let Some(mark) = self.get(&name) {
+            return Some(mark);
+        }
+
+        let used = self.all();
+        let bit = (0..32).map(|i| 1 << i).find(|bit| used.bits() & bit == 0)?;
+        let mark = Marks::from_bits_retain(bit);
+
+        self.marks.push((mark, name));
+        Some(mark)
+    }
+
+    /// Get a mark by name.
+    pub fn get(&self, name: &str) -> Option<Marks> {
+        self.marks.iter().find(|(_, n)| n == name).map(|(m, _)| *m)
+    }
+
+    /// Get the name of a single mark.
+    pub fn name(&self, mark: Marks) -> Option<&str> {
+        self.marks
+            .iter()
+            .find(|(m, _)| *m == mark)
+            .map(|(_, n)| n.as_str())
+    }
+
+    /// Every registered mark with its name, in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = (Marks, &str)> {
+        self.marks.iter().map(|(m, n)| (*m, n.as_str()))
+    }
+
+    /// The names of the registered marks set in `marks`, in registration
+    /// order.
+    pub fn names(&self, marks: Marks) -> impl Iterator<Item = &str> {
+        self.iter()
+            .filter(move |(m, _)| marks.contains(*m))
+            .map(|(_, n)| n)
+    }
+
+    /// Combine marks by name. Returns `None` if any name isn't registered.
+    pub fn parse<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Option<Marks> {
+        names
+            .into_iter()
+            .try_fold(Marks::empty(), |marks, name| Some(marks | self.get(name)?))
+    }
+
+    /// Every registered mark.
+    pub fn all(&self) -> Marks {
+        self.marks
+            .iter()
+            .fold(Marks::empty(), |marks, (m, _)| marks | *m)
+    }
+}
+
+impl Editor {
+    /// Name the marks of the document's text, including custom ones, when
+    /// it's serialized, hashed or hibernated.
+    pub fn with_mark_registry(mut self, registry: MarkRegistry) -> Self {
+        // Editors with the built-in marks don't hold a registry, so they're
+        // equal whether they were given one or not.
+        self.mark_registry = (registry != *BUILT_IN).then(|| Box::new(registry));
+        self
+    }
+
+    /// Get the editor's mark registry, or one of the built-in marks without
+    /// one.
+    pub fn mark_registry(&self) -> &MarkRegistry {
+        self.mark_registry.as_deref().unwrap_or(&BUILT_IN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_custom_marks() {
+        let mut registry = MarkRegistry::new();
+        let strike = registry.register("strikethrough").unwrap();
+        let sup = registry.register("superscript").unwrap();
+        assert_ne!(strike, sup);
+        assert!((Marks::BUILT_IN & (strike | sup)).is_empty());
+        assert_eq!(registry.register("superscript"), Some(sup));
+        assert_eq!(registry.get("bold"), Some(Marks::BOLD));
+        assert_eq!(registry.name(strike), Some("strikethrough"));
+        assert_eq!(
+            registry.names(Marks::BOLD | sup).collect::<Vec<_>>(),
+            vec!["bold", "superscript"]
+        );
+        assert_eq!(
+            registry.parse(vec!["italic", "superscript"]),
+            Some(Marks::ITALIC | sup)
+        );
+        assert_eq!(registry.parse(vec!["missing"]), None);
+
+        // Custom marks are kept by the set operations on marks.
+        assert!(Marks::all().contains(strike));
+        assert_eq!(!(!strike), strike);
+        assert_eq!((Marks::BOLD | strike) - Marks::BOLD, strike);
+        assert_eq!(format!("{:?}", strike), "Marks(0x1)");
+    }
+
+    #[test]
+    fn register_until_full() {
+        let mut registry = MarkRegistry::new();
+        for i in 0..28 {
+            assert!(registry.register(format!("mark-{}", i)).is_some());
+        }
+        assert_eq!(registry.register("one-more"), None);
+    }
+}
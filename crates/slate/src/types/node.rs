@@ -1,6 +1,11 @@
 use std::collections::HashSet;
 
-use super::{editor::Editor, element::Element, path::Path, range::Range, text::Text};
+use crate::transforms::diff::align;
+
+use super::{
+    children::Children, document_index::NodeRef, editor::Editor, element::Element, path::Path,
+    text::Text,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Descendant {
@@ -8,18 +13,93 @@ pub enum Descendant {
     Element(Element),
 }
 
-impl Into<Node> for &Descendant {
-    fn into(self) -> Node {
+impl Descendant {
+    pub fn as_text(&self) -> Option<&Text> {
+        match self {
+            Descendant::Text(t) => Some(t),
+            Descendant::Element(_) => None,
+        }
+    }
+
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Descendant::Element(e) => Some(e),
+            Descendant::Text(_) => None,
+        }
+    }
+
+    pub(crate) fn children_ref(&self) -> Option<&Children> {
         match self {
+            Descendant::Element(e) => Some(e.children_ref()),
+            Descendant::Text(_) => None,
+        }
+    }
+
+    pub(crate) fn children_mut(&mut self) -> Option<&mut Children> {
+        match self {
+            Descendant::Element(e) => Some(e.children_mut()),
+            Descendant::Text(_) => None,
+        }
+    }
+}
+
+/// Check if a list of children holds inline content (text nodes and inline
+/// elements) rather than blocks.
+pub(crate) fn has_inlines<'a>(children: impl IntoIterator<Item = &'a Descendant>) -> bool {
+    children
+        .into_iter()
+        .any(|c| matches!(c, Descendant::Text(_)))
+}
+
+/// Collect every text node below `children` in document order, along with its
+/// path. `parent` is the path of the node `children` belong to.
+pub(crate) fn text_entries<'a>(
+    children: impl IntoIterator<Item = &'a Descendant>,
+    parent: &Path,
+) -> Vec<(&'a Text, Path)> {
+    let mut out = vec![];
+
+    for (i, child) in children.into_iter().enumerate() {
+        let path = parent.concat(i);
+        match child {
+            Descendant::Text(t) => out.push((t, path)),
+            Descendant::Element(e) => out.extend(text_entries(e.children_ref(), &path)),
+        }
+    }
+
+    out
+}
+
+/// Get the elements in some nodes with their paths, in document order.
+pub(crate) fn element_entries<'a>(
+    children: impl IntoIterator<Item = &'a Descendant>,
+    parent: &Path,
+) -> Vec<(&'a Element, Path)> {
+    let mut out = vec![];
+
+    for (i, child) in children.into_iter().enumerate() {
+        if let Descendant::Element(e) = child {
+            let path = parent.concat(i);
+            out.push((e, path.clone()));
+            out.extend(element_entries(e.children_ref(), &path));
+        }
+    }
+
+    out
+}
+
+impl From<&Descendant> for Node {
+    fn from(val: &Descendant) -> Self {
+        match val {
             Descendant::Text(t) => Node::Text(t.clone()),
             Descendant::Element(e) => Node::Element(e.clone()),
         }
     }
 }
 
-impl Into<Node> for Descendant {
-    fn into(self) -> Node {
-        match self {
+impl From<Descendant> for Node {
+    fn from(val: Descendant) -> Self {
+        match val {
             Descendant::Text(t) => Node::Text(t),
             Descendant::Element(e) => Node::Element(e),
         }
@@ -33,24 +113,24 @@ pub enum Ancestor {
 }
 
 impl Ancestor {
-    fn children(&self) -> Vec<Descendant> {
+    pub fn children(&self) -> Vec<Descendant> {
         match self {
             Ancestor::Editor(e) => e.children(),
             Ancestor::Element(e) => e.children(),
         }
     }
 
-    fn child(&self, i: usize) -> Option<Descendant> {
+    pub fn child(&self, i: usize) -> Option<Descendant> {
         match self {
-            Ancestor::Editor(n) => n.child(i).map(|desc| desc.clone()),
-            Ancestor::Element(n) => n.child(i).map(|desc| desc.clone()),
+            Ancestor::Editor(n) => n.child(i).cloned(),
+            Ancestor::Element(n) => n.child(i).cloned(),
         }
     }
 }
 
-impl Into<Node> for Box<Ancestor> {
-    fn into(self) -> Node {
-        match self.as_ref() {
+impl From<Box<Ancestor>> for Node {
+    fn from(val: Box<Ancestor>) -> Self {
+        match val.as_ref() {
             Ancestor::Editor(e) => Node::Editor(e.clone()),
             Ancestor::Element(e) => Node::Element(e.clone()),
         }
@@ -65,11 +145,24 @@ pub enum Node {
 }
 
 impl Node {
-    fn child_node(&self, i: usize) -> Option<Node> {
+    /// Convert a node into a `Descendant`, failing for the editor root.
+    pub fn into_descendant(self) -> Option<Descendant> {
         match self {
-            Node::Editor(n) => n.child(i).map(|desc| desc.into()),
-            Node::Element(n) => n.child(i).map(|desc| desc.into()),
-            Node::Text(_) => None,
+            Node::Element(e) => Some(Descendant::Element(e)),
+            Node::Text(t) => Some(Descendant::Text(t)),
+            Node::Editor(_) => None,
+        }
+    }
+
+    /// Check whether two nodes have the same shape: the same kinds of
+    /// elements with the same number of children, nested the same way. Text
+    /// content, marks and properties are ignored.
+    pub fn structural_eq(&self, other: &Node) -> bool {
+        match (self, other) {
+            (Node::Editor(a), Node::Editor(b)) => children_structural_eq(&a.children, &b.children),
+            (Node::Element(a), Node::Element(b)) => element_structural_eq(a, b),
+            (Node::Text(_), Node::Text(_)) => true,
+            _ => false,
         }
     }
 
@@ -90,8 +183,21 @@ impl Node {
     }
 }
 
+fn element_structural_eq(a: &Element, b: &Element) -> bool {
+    a.kind() == b.kind() && children_structural_eq(a.children_ref(), b.children_ref())
+}
+
+fn children_structural_eq(a: &Children, b: &Children) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b.iter()).all(|pair| match pair {
+            (Descendant::Element(a), Descendant::Element(b)) => element_structural_eq(a, b),
+            (Descendant::Text(_), Descendant::Text(_)) => true,
+            _ => false,
+        })
+}
+
 impl Node {
-    fn ancestor(&self, path: &Path) -> Option<Box<Ancestor>> {
+    pub fn ancestor(&self, path: &Path) -> Option<Box<Ancestor>> {
         let node = self.get(path)?;
 
         match node.as_ref() {
@@ -101,7 +207,7 @@ impl Node {
         }
     }
 
-    fn ancestors(&self, path: &Path, reverse: bool) -> Vec<(Box<Ancestor>, Path)> {
+    pub fn ancestors(&self, path: &Path, reverse: bool) -> Vec<(Box<Ancestor>, Path)> {
         let mut ancestors = vec![];
 
         for path in path.ancestors(reverse) {
@@ -112,15 +218,15 @@ impl Node {
         ancestors
     }
 
-    fn child(&self, i: usize) -> Option<Descendant> {
+    pub fn child(&self, i: usize) -> Option<Descendant> {
         match self {
-            Node::Editor(n) => n.child(i).map(|desc| desc.clone()),
-            Node::Element(n) => n.child(i).map(|desc| desc.clone()),
+            Node::Editor(n) => n.child(i).cloned(),
+            Node::Element(n) => n.child(i).cloned(),
             Node::Text(_) => None,
         }
     }
 
-    fn children(&self, path: &Path, reverse: bool) -> Vec<(Descendant, Path)> {
+    pub fn children(&self, path: &Path, reverse: bool) -> Vec<(Descendant, Path)> {
         let ancestor = self.ancestor(path).unwrap();
         let children = ancestor.children();
         let mut out = vec![];
@@ -139,13 +245,13 @@ impl Node {
     }
 
     /// Get an entry for the common ancesetor node of two paths.
-    fn common(&self, path: &Path, another: &Path) -> Option<(Box<Node>, Path)> {
+    pub fn common(&self, path: &Path, another: &Path) -> Option<(Box<Node>, Path)> {
         let p = path.common(another);
         let n = self.get(&p)?;
         Some((n, p))
     }
 
-    fn descendant(&self, path: &Path) -> Option<Box<Descendant>> {
+    pub fn descendant(&self, path: &Path) -> Option<Box<Descendant>> {
         let node = self.get(path)?;
 
         match node.as_ref() {
@@ -155,7 +261,7 @@ impl Node {
         }
     }
 
-    fn descendants(&self, path: &Path, reverse: bool) -> Vec<(Box<Descendant>, Path)> {
+    pub fn descendants(&self, path: &Path, reverse: bool) -> Vec<(Box<Descendant>, Path)> {
         let mut descendants = vec![];
 
         for path in path.ancestors(reverse) {
@@ -168,44 +274,30 @@ impl Node {
 
     /// Get the descendant node referred to by a specific path. If the path is an
     /// empty array, it refers to the root node itself.
-    fn get(&self, path: &Path) -> Option<Box<Node>> {
-        let mut node = self.clone();
-
-        for i in 0..path.len() {
-            let p = path.get(i)?;
-
-            if let Some(c) = node.child_node(p).take() {
-                node = c.clone();
-            } else {
-                return None;
+    ///
+    /// An editor's descendants are looked up in its index.
+    pub fn get(&self, path: &Path) -> Option<Box<Node>> {
+        match self {
+            Node::Editor(e) if !path.is_empty() => {
+                let node = e.caches.index.get(&e.children, path)?;
+                Some(Box::new(node.clone().into()))
+            }
+            _ => {
+                let node = NodeRef::from(self).descend(path)?;
+                Some(Box::new(node.to_node()))
             }
         }
-
-        Some(Box::new(node))
     }
 
     /// Check if a descendant node exists at a specific path.
-    fn has(&self, path: &Path) -> bool {
-        let mut node = self.clone();
-
-        for i in 0..path.len() {
-            let p = match path.get(i) {
-                Some(p) => p,
-                None => return false,
-            };
-
-            let next = node.child(p);
-            if matches!(node, Node::Text(_)) || next.is_none() {
-                return false;
-            }
-
-            node = next.unwrap().into();
+    pub fn has(&self, path: &Path) -> bool {
+        match self {
+            Node::Editor(e) if !path.is_empty() => e.caches.index.has(&e.children, path),
+            _ => NodeRef::from(self).descend(path).is_some(),
         }
-
-        true
     }
 
-    fn nodes(&self) -> NodeIterator {
+    pub fn nodes(&self) -> NodeIterator {
         NodeIterator {
             root: self.clone(),
             n: Box::new(self.clone()),
@@ -218,12 +310,12 @@ impl Node {
         }
     }
 
-    fn nodes_span(&self, from: Path, to: Path) -> NodeIterator {
+    pub fn nodes_span(&self, from: Path, to: Path) -> NodeIterator {
         NodeIterator {
             root: self.clone(),
             n: Box::new(self.clone()),
             p: Path::new(vec![]),
-            from: from,
+            from,
             to: Some(to),
             reverse: false,
             pass: None,
@@ -231,7 +323,7 @@ impl Node {
         }
     }
 
-    fn nodes_with_filter(&self, pass: fn(NodeEntry) -> bool) -> NodeIterator {
+    pub fn nodes_with_filter(&self, pass: fn(NodeEntry) -> bool) -> NodeIterator {
         NodeIterator {
             root: self.clone(),
             n: Box::new(self.clone()),
@@ -250,7 +342,88 @@ impl Node {
 /// node in the document.
 pub type NodeEntry = (Box<Node>, Path);
 
-struct NodeIterator {
+/// A pair of entries for the same node in two trees, with `None` on the side
+/// of the tree it isn't in.
+pub type ZippedEntry = (Option<NodeEntry>, Option<NodeEntry>);
+
+impl Node {
+    /// Walk two trees together, pairing each node with the node it
+    /// corresponds to in the other tree, like a draft with the version that
+    /// was published.
+    ///
+    /// The roots are paired, and then the children of each pair of nodes are
+    /// aligned as `Editor::diff` aligns them: elements with a key, their `id`
+    /// or anchor, are paired by key, and other nodes by their type and order.
+    /// Nodes without a counterpart are paired with `None`, and so are their
+    /// descendants. Pairs come in document order, parents before children.
+    pub fn zip_entries(&self, other: &Node) -> Vec<ZippedEntry> {
+        let root = Path::new(vec![]);
+        let mut out = vec![(
+            Some((Box::new(self.clone()), root.clone())),
+            Some((Box::new(other.clone()), root.clone())),
+        )];
+        zip_children(
+            root_children(self).map(|c| (c, root.clone())),
+            root_children(other).map(|c| (c, root.clone())),
+            &mut out,
+        );
+        out
+    }
+}
+
+fn root_children(node: &Node) -> Option<&Children> {
+    match node {
+        Node::Editor(e) => Some(&e.children),
+        Node::Element(e) => Some(e.children_ref()),
+        Node::Text(_) => None,
+    }
+}
+
+fn zip_children(
+    a: Option<(&Children, Path)>,
+    b: Option<(&Children, Path)>,
+    out: &mut Vec<ZippedEntry>,
+) {
+    let pairs = match (&a, &b) {
+        (Some((a, _)), Some((b, _))) => align(a, b),
+        (Some((a, _)), None) => (0..a.len()).map(|i| (Some(i), None)).collect(),
+        (None, Some((b, _))) => (0..b.len()).map(|j| (None, Some(j))).collect(),
+        (None, None) => return,
+    };
+
+    for (i, j) in pairs {
+        zip_descendants(child(&a, i), child(&b, j), out);
+    }
+}
+
+fn zip_descendants(
+    a: Option<(&Descendant, Path)>,
+    b: Option<(&Descendant, Path)>,
+    out: &mut Vec<ZippedEntry>,
+) {
+    let entry = |side: &Option<(&Descendant, Path)>| {
+        let (node, path) = side.as_ref()?;
+        Some((Box::new(Node::from(*node)), path.clone()))
+    };
+    out.push((entry(&a), entry(&b)));
+
+    zip_children(a.and_then(children), b.and_then(children), out);
+}
+
+fn children((node, path): (&Descendant, Path)) -> Option<(&Children, Path)> {
+    Some((node.children_ref()?, path))
+}
+
+fn child<'a>(
+    side: &Option<(&'a Children, Path)>,
+    i: Option<usize>,
+) -> Option<(&'a Descendant, Path)> {
+    let (children, parent) = side.as_ref()?;
+    let i = i?;
+    Some((&children[i], parent.concat(i)))
+}
+
+pub struct NodeIterator {
     root: Node,
     n: Box<Node>,
     p: Path,
@@ -311,7 +484,7 @@ impl Iterator for NodeIterator {
         }
 
         // If we're at the root and we can't go down, we're done.
-        if self.p.len() == 0 {
+        if self.p.is_empty() {
             println!("2");
             return out;
         }
@@ -354,6 +527,59 @@ impl Iterator for NodeIterator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Marks;
+
+    #[test]
+    fn zip_entries_by_key_and_kind() {
+        let published = Node::Editor(
+            Editor::new()
+                .add_child(
+                    Element::new()
+                        .add_property("id", "a")
+                        .add_child(Text::new("one")),
+                )
+                .add_child(Element::new().add_child(Text::new("two"))),
+        );
+        let draft = Node::Editor(
+            Editor::new()
+                .add_child(Element::new().add_child(Text::new("zero")))
+                .add_child(
+                    Element::new()
+                        .add_property("id", "a")
+                        .add_child(Text::new("one")),
+                )
+                .add_child(Element::new().add_child(Text::new("two!"))),
+        );
+
+        let paths: Vec<_> = published
+            .zip_entries(&draft)
+            .into_iter()
+            .map(|(a, b)| (a.map(|(_, p)| p), b.map(|(_, p)| p)))
+            .collect();
+        let path = |p: Vec<usize>| Some(Path::new(p));
+        assert_eq!(
+            paths,
+            vec![
+                (path(vec![]), path(vec![])),
+                (None, path(vec![0])),
+                (None, path(vec![0, 0])),
+                (path(vec![0]), path(vec![1])),
+                (path(vec![0, 0]), path(vec![1, 0])),
+                (path(vec![1]), path(vec![2])),
+                (path(vec![1, 0]), path(vec![2, 0])),
+            ]
+        );
+
+        let (a, b) = &published.zip_entries(&draft)[6];
+        assert_eq!(
+            a.as_ref().unwrap().0,
+            Box::new(Node::Text(Text::new("two")))
+        );
+        assert_eq!(
+            b.as_ref().unwrap().0,
+            Box::new(Node::Text(Text::new("two!")))
+        );
+    }
 
     #[test]
     fn ancestor_success() {
@@ -551,4 +777,22 @@ mod tests {
             want
         );
     }
+
+    #[test]
+    fn structural_eq_ignores_text() {
+        let a = Node::Element(
+            Element::with_kind("list")
+                .add_property("start", "3")
+                .add_child(Element::with_kind("item").add_child(Text::new("one"))),
+        );
+        let b =
+            Node::Element(Element::with_kind("list").add_child(
+                Element::with_kind("item").add_child(Text::with_marks("two", Marks::BOLD)),
+            ));
+        assert!(a.structural_eq(&b));
+        assert!(!a.structural_eq(&Node::Element(
+            Element::with_kind("list").add_child(Element::with_kind("item"))
+        )));
+        assert!(!a.structural_eq(&Node::Text(Text::new("one"))));
+    }
 }
@@ -1,6 +1,14 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+use slate_core::{AsEdit, Edit};
+
 use super::node::Node;
-use super::path::Path;
+use super::path::{Affinity, Path};
+use super::path_arena::PathArena;
+use super::properties::NodeProperties;
 use super::range::Range;
+use super::text::Marks;
+use crate::serialize::json;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Operation {
@@ -16,7 +24,7 @@ pub enum Operation {
     MergeNode {
         path: Path,
         position: usize,
-        properties: Node,
+        properties: NodeProperties,
     },
     MoveNode {
         path: Path,
@@ -33,8 +41,8 @@ pub enum Operation {
     },
     SetNode {
         path: Path,
-        properties: Option<Node>,
-        new_properties: Option<Node>,
+        properties: NodeProperties,
+        new_properties: NodeProperties,
     },
     SetSelection {
         path: Path,
@@ -44,7 +52,7 @@ pub enum Operation {
     SplitNode {
         path: Path,
         position: usize,
-        properties: Node,
+        properties: NodeProperties,
     },
 }
 
@@ -93,12 +101,14 @@ impl Operation {
                     },
                     Default::default(),
                 )
+                .unwrap()
                 .unwrap();
                 let inverse_new_path = Path::transform(
                     &path.next().unwrap(),
                     &Operation::MoveNode { path, new_path },
                     Default::default(),
                 )
+                .unwrap()
                 .unwrap();
 
                 Operation::MoveNode {
@@ -157,10 +167,495 @@ impl Operation {
     }
 }
 
+impl Operation {
+    /// Transform an operation so that it applies after `against`, where both
+    /// were made concurrently against the same document. When both insert at
+    /// the same place, the one with `priority` ends up first.
+    ///
+    /// This can return no operations, if `against` already did the same thing
+    /// or removed what the operation targets, or several, if `against` split
+    /// the text the operation removes.
+    pub fn transform(&self, against: &Operation, priority: bool) -> Vec<Operation> {
+        self.transform_in(against, priority, &mut PathArena::new())
+    }
+
+    /// Transform an operation like `transform`, with its paths transformed
+    /// through an arena.
+    fn transform_in(
+        &self,
+        against: &Operation,
+        priority: bool,
+        paths: &mut PathArena,
+    ) -> Vec<Operation> {
+        let mut op = self.clone();
+
+        let kept = match &mut op {
+            Operation::InsertText { path, offset, .. } => match against {
+                // Text inserted in the middle of removed text is removed too.
+                Operation::RemoveText {
+                    path: p,
+                    offset: o,
+                    text,
+                } if p == path && *offset > *o && *offset - o < text.len() => false,
+                _ => transform_point(path, offset, against, priority, paths),
+            },
+            Operation::RemoveText { path, offset, text } => {
+                return transform_remove_text(path, *offset, text, against, paths);
+            }
+            Operation::InsertNode { path, .. } => match against {
+                Operation::InsertNode { path: p, .. } if p == path && priority => true,
+                Operation::RemoveNode { path: p, .. } if p == path => true,
+                Operation::SplitNode {
+                    path: p, position, ..
+                } if priority && path.parent().as_ref() == Some(p) => {
+                    path.last() == Some(*position) || transform_path(path, against, paths)
+                }
+                _ => transform_path(path, against, paths),
+            },
+            Operation::SplitNode { path, position, .. } => {
+                if let Operation::SplitNode {
+                    path: p,
+                    position: q,
+                    ..
+                } = against
+                {
+                    if p == path && q == position {
+                        return vec![];
+                    }
+                }
+                transform_point(path, position, against, priority, paths)
+            }
+            Operation::MergeNode { path, position, .. } => {
+                transform_merge(path, position, against, paths)
+            }
+            Operation::SetNode {
+                path,
+                properties,
+                new_properties,
+            } => match against {
+                Operation::SetNode {
+                    path: p,
+                    properties: old,
+                    new_properties: new,
+                } if p == path => merge_set_node(properties, new_properties, old, new, priority),
+                // The properties of a merged node are lost with it, so they
+                // aren't set on the node it's merged into.
+                Operation::MergeNode { path: p, .. } if p == path => false,
+                _ => transform_path(path, against, paths),
+            },
+            Operation::RemoveNode { path, .. } => transform_path(path, against, paths),
+            Operation::MoveNode { path, new_path } => {
+                transform_path(path, against, paths) && transform_path(new_path, against, paths)
+            }
+            Operation::SetSelection { .. } => true,
+        };
+
+        if kept {
+            vec![op]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Transform two sequences of concurrent operations against each other,
+    /// returning `a` rebased onto `b` and `b` rebased onto `a`. Applying `a`
+    /// then the rebased `b` leaves a document the same as applying `b` then
+    /// the rebased `a`. Operations in `a` have priority.
+    pub fn transform_all(a: &[Operation], b: &[Operation]) -> (Vec<Operation>, Vec<Operation>) {
+        Operation::transform_all_in(&mut PathArena::new(), a, b)
+    }
+
+    /// Transform two sequences of concurrent operations like `transform_all`,
+    /// with their paths transformed through an arena. Rebasing operations on
+    /// a few nodes through many operations, or through the same operations
+    /// again, transforms each of their distinct paths once.
+    pub fn transform_all_in(
+        paths: &mut PathArena,
+        a: &[Operation],
+        b: &[Operation],
+    ) -> (Vec<Operation>, Vec<Operation>) {
+        match (a, b) {
+            ([], _) | (_, []) => (a.to_vec(), b.to_vec()),
+            ([x], [y]) => (
+                x.transform_in(y, true, paths),
+                y.transform_in(x, false, paths),
+            ),
+            ([_], [y, rest @ ..]) => {
+                let (a, mut y) = Operation::transform_all_in(paths, a, std::slice::from_ref(y));
+                let (a, rest) = Operation::transform_all_in(paths, &a, rest);
+                y.extend(rest);
+                (a, y)
+            }
+            ([x, rest @ ..], _) => {
+                let (mut x, b) = Operation::transform_all_in(paths, std::slice::from_ref(x), b);
+                let (rest, b) = Operation::transform_all_in(paths, rest, &b);
+                x.extend(rest);
+                (x, b)
+            }
+        }
+    }
+}
+
+impl AsEdit for Operation {
+    fn as_edit(&self) -> Edit<'_> {
+        match self {
+            Operation::InsertNode { path, .. } => Edit::InsertNode { path },
+            Operation::InsertText { path, offset, text } => Edit::InsertText {
+                path,
+                offset: *offset,
+                len: text.len(),
+            },
+            Operation::MergeNode { path, position, .. } => Edit::MergeNode {
+                path,
+                position: *position,
+            },
+            Operation::MoveNode { path, new_path } => Edit::MoveNode { path, new_path },
+            Operation::RemoveNode { path, .. } => Edit::RemoveNode { path },
+            Operation::RemoveText { path, offset, text } => Edit::RemoveText {
+                path,
+                offset: *offset,
+                len: text.len(),
+            },
+            Operation::SplitNode { path, position, .. } => Edit::SplitNode {
+                path,
+                position: *position,
+            },
+            Operation::SetNode { .. } | Operation::SetSelection { .. } => Edit::None,
+        }
+    }
+}
+
+/// Transform a path with `against`, returning `false` if it removed the node
+/// or couldn't have been applied alongside it.
+fn transform_path(path: &mut Path, against: &Operation, paths: &mut PathArena) -> bool {
+    paths
+        .transform_mut(path, against, Affinity::Forward)
+        .unwrap_or(false)
+}
+
+/// Transform a position in a node, like an offset in a text node or an index
+/// in an element's children. Positions where `against` inserts stay in front
+/// of the insertion if `before` is set.
+///
+/// Returns `false` too if the position would overflow, which only an
+/// operation that can't be applied can make it do.
+fn transform_point(
+    path: &mut Path,
+    offset: &mut usize,
+    against: &Operation,
+    before: bool,
+    paths: &mut PathArena,
+) -> bool {
+    match against {
+        Operation::InsertText {
+            path: p,
+            offset: o,
+            text,
+        } if p == path => {
+            if *o < *offset || (*o == *offset && !before) {
+                match offset.checked_add(text.len()) {
+                    Some(moved) => *offset = moved,
+                    None => return false,
+                }
+            }
+        }
+        Operation::RemoveText {
+            path: p,
+            offset: o,
+            text,
+        } if p == path => {
+            if *offset > *o {
+                *offset -= (*offset - o).min(text.len());
+            }
+        }
+        Operation::InsertNode { path: p, .. } if p.parent().as_ref() == Some(path) => {
+            let i = p.last().unwrap();
+            if i < *offset || (i == *offset && !before) {
+                match offset.checked_add(1) {
+                    Some(moved) => *offset = moved,
+                    None => return false,
+                }
+            }
+        }
+        Operation::RemoveNode { path: p, .. } if p.parent().as_ref() == Some(path) => {
+            if p.last().unwrap() < *offset {
+                *offset -= 1;
+            }
+        }
+        Operation::SplitNode {
+            path: p, position, ..
+        } if p == path => {
+            if *offset > *position || (*offset == *position && !before) {
+                *path = path.next().unwrap();
+                *offset -= position;
+            }
+        }
+        Operation::MergeNode {
+            path: p, position, ..
+        } if p == path => {
+            *path = path.previous().unwrap();
+            match offset.checked_add(*position) {
+                Some(moved) => *offset = moved,
+                None => return false,
+            }
+        }
+        _ => return transform_path(path, against, paths),
+    }
+
+    true
+}
+
+/// Transform a `SetNode` against another one on the same node, keeping the
+/// properties only it sets. Where both set the same property, the one with
+/// `priority` wins, and the other's value becomes the one it replaces.
+/// Marks are merged by the marks each one toggles.
+///
+/// Returns `false` if nothing is left to set.
+fn merge_set_node(
+    properties: &mut NodeProperties,
+    new_properties: &mut NodeProperties,
+    against_properties: &NodeProperties,
+    against_new: &NodeProperties,
+    priority: bool,
+) -> bool {
+    if let Some(kind) = &against_new.kind {
+        if priority && new_properties.kind.is_some() {
+            properties.kind = Some(kind.clone());
+        } else {
+            properties.kind = None;
+            new_properties.kind = None;
+        }
+    }
+
+    for (key, value) in &against_new.properties {
+        if !new_properties.properties.contains_key(key) {
+            continue;
+        }
+        if priority {
+            properties.properties.insert(key.clone(), value.clone());
+        } else {
+            properties.properties.remove(key);
+            new_properties.properties.remove(key);
+        }
+    }
+
+    if let (Some(old), Some(new), Some(theirs)) =
+        (properties.marks, new_properties.marks, against_new.marks)
+    {
+        let toggled = old ^ new;
+        let taken = match against_properties.marks {
+            Some(theirs_old) if !priority => toggled - (theirs_old ^ theirs),
+            // Without the marks they replaced, every mark may have changed.
+            None if !priority => Marks::empty(),
+            _ => toggled,
+        };
+        if taken.is_empty() {
+            properties.marks = None;
+            new_properties.marks = None;
+        } else {
+            properties.marks = Some(theirs);
+            new_properties.marks = Some((theirs - taken) | (new & taken));
+        }
+    }
+
+    !new_properties.is_empty()
+}
+
+/// Transform the length of the node before a merged node, which is what the
+/// merge's `position` is.
+fn transform_merge(
+    path: &mut Path,
+    position: &mut usize,
+    against: &Operation,
+    paths: &mut PathArena,
+) -> bool {
+    if let Operation::MergeNode { path: p, .. } = against {
+        if p == path {
+            return false;
+        }
+    }
+
+    // The position is the end of the previous node, so it moves with content
+    // added or removed there.
+    if let Some(mut previous) = path.previous() {
+        transform_point(&mut previous, position, against, false, paths);
+    }
+
+    match against {
+        // The first half of a split node stays where it is, and it's the one
+        // to merge into the previous node.
+        Operation::SplitNode { path: p, .. } if p == path => true,
+        _ => transform_path(path, against, paths),
+    }
+}
+
+/// Transform a removal of text, which may need to turn into two removals if
+/// `against` splits the node in the middle of the removed text.
+///
+/// The removal is dropped if its end overflows, or if `against` edits the
+/// text in the middle of one of its characters, as one of the two can't be
+/// applied to the document.
+fn transform_remove_text(
+    path: &Path,
+    offset: usize,
+    text: &str,
+    against: &Operation,
+    paths: &mut PathArena,
+) -> Vec<Operation> {
+    let end = match offset.checked_add(text.len()) {
+        Some(end) => end,
+        None => return vec![],
+    };
+    let remove = |path: Path, offset: usize, text: String| {
+        if text.is_empty() {
+            vec![]
+        } else {
+            vec![Operation::RemoveText { path, offset, text }]
+        }
+    };
+
+    match against {
+        Operation::InsertText {
+            path: p,
+            offset: o,
+            text: inserted,
+        } if p == path && *o > offset && *o < end => {
+            // Remove the text inserted in the middle of the removal too, since
+            // the other side drops the insertion.
+            match (text.get(..o - offset), text.get(o - offset..)) {
+                (Some(before), Some(after)) => {
+                    remove(path.clone(), offset, [before, inserted, after].concat())
+                }
+                _ => vec![],
+            }
+        }
+        Operation::RemoveText {
+            path: p,
+            offset: o,
+            text: removed,
+        } if p == path => {
+            let (o, e) = (*o, o.saturating_add(removed.len()));
+            let mut kept = String::new();
+            if offset < o {
+                match text.get(..(o.min(end) - offset)) {
+                    Some(before) => kept.push_str(before),
+                    None => return vec![],
+                }
+            }
+            if end > e {
+                match text.get((e.max(offset) - offset)..) {
+                    Some(after) => kept.push_str(after),
+                    None => return vec![],
+                }
+            }
+            let start = if offset <= o {
+                offset
+            } else {
+                offset - (offset - o).min(removed.len())
+            };
+            remove(path.clone(), start, kept)
+        }
+        Operation::SplitNode {
+            path: p, position, ..
+        } if p == path && *position > offset && *position < end => {
+            let i = position - offset;
+            match (text.get(..i), text.get(i..)) {
+                (Some(before), Some(after)) => {
+                    let mut ops = remove(path.clone(), offset, before.to_string());
+                    ops.extend(remove(path.next().unwrap(), 0, after.to_string()));
+                    ops
+                }
+                _ => vec![],
+            }
+        }
+        _ => {
+            let (mut path, mut offset) = (path.clone(), offset);
+            if transform_point(&mut path, &mut offset, against, false, paths) {
+                remove(path, offset, text.to_string())
+            } else {
+                vec![]
+            }
+        }
+    }
+}
+
+// Operations are (de)serialized in the shape of Slate.js operations, like
+// `{"type": "insert_text", "path": [0, 0], "offset": 3, "text": "x"}`, so
+// they can be streamed to and from Slate.js clients.
+impl Serialize for Operation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Operation::InsertNode {
+            node: Node::Editor(_),
+            ..
+        }
+        | Operation::RemoveNode {
+            node: Node::Editor(_),
+            ..
+        } = self
+        {
+            return Err(ser::Error::custom("the editor node can't be serialized"));
+        }
+        json::operation_to_value(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Operation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        json::operation_from_value(&value).ok_or_else(|| match value.get("type") {
+            Some(serde_json::Value::String(kind)) => {
+                de::Error::custom(format_args!("invalid {} operation", kind))
+            }
+            _ => de::Error::custom("invalid operation"),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::path::Path;
     use super::*;
+    use crate::types::{Element, Text};
+
+    #[test]
+    fn slate_js_wire_format() {
+        let op: Operation =
+            serde_json::from_str(r#"{"type":"insert_text","path":[0,0],"offset":3,"text":"x"}"#)
+                .unwrap();
+        assert_eq!(
+            op,
+            Operation::InsertText {
+                path: Path::new(vec![0, 0]),
+                offset: 3,
+                text: "x".into(),
+            }
+        );
+
+        let ops = vec![
+            op,
+            Operation::InsertNode {
+                path: Path::new(vec![1]),
+                node: Node::Element(Element::with_kind("quote").add_child(Text::new("q"))),
+            },
+        ];
+        let wire = serde_json::to_string(&ops).unwrap();
+        assert_eq!(
+            wire,
+            r#"[{"offset":3,"path":[0,0],"text":"x","type":"insert_text"},{"node":{"children":[{"text":"q"}],"type":"quote"},"path":[1],"type":"insert_node"}]"#
+        );
+        assert_eq!(serde_json::from_str::<Vec<Operation>>(&wire).unwrap(), ops);
+
+        let error = serde_json::from_str::<Operation>(r#"{"type":"move_node","path":[0]}"#);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "invalid move_node operation"
+        );
+        assert!(serde_json::to_string(&Operation::RemoveNode {
+            path: Path::new(vec![]),
+            node: Node::Editor(Default::default()),
+        })
+        .is_err());
+    }
 
     #[test]
     fn inverse_move_node_backward_in_parent() {
@@ -281,4 +776,47 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn transform_remove_text_through_edits_inside_a_character() {
+        let remove = Operation::RemoveText {
+            path: Path::new(vec![0, 0]),
+            offset: 1,
+            text: "éé".into(),
+        };
+        let insert = Operation::InsertText {
+            path: Path::new(vec![0, 0]),
+            offset: 2,
+            text: "x".into(),
+        };
+        let split = Operation::SplitNode {
+            path: Path::new(vec![0, 0]),
+            position: 2,
+            properties: NodeProperties::default(),
+        };
+        assert_eq!(remove.transform(&insert, false), vec![]);
+        assert_eq!(remove.transform(&split, false), vec![]);
+    }
+
+    #[test]
+    fn transform_offsets_that_overflow() {
+        let path = Path::new(vec![0, 0]);
+        let insert = Operation::InsertText {
+            path: path.clone(),
+            offset: 0,
+            text: "x".into(),
+        };
+        let remove = Operation::RemoveText {
+            path: path.clone(),
+            offset: usize::MAX,
+            text: "ab".into(),
+        };
+        let far_insert = Operation::InsertText {
+            path,
+            offset: usize::MAX,
+            text: "y".into(),
+        };
+        assert_eq!(remove.transform(&insert, false), vec![]);
+        assert_eq!(far_insert.transform(&insert, false), vec![]);
+    }
 }
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::operation::Operation;
+use super::path::{Affinity, Path, TransformError};
+use slate_core::{AsEdit, Edit};
+
+/// A handle to a path interned in a `PathArena`. Handles are only meaningful
+/// for the arena that created them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PathId(u32);
+
+/// The part of an operation that moves paths, with its paths interned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Through {
+    Insert(PathId),
+    Remove(PathId),
+    Merge(PathId, usize),
+    Split(PathId, usize),
+    Move(PathId, PathId),
+}
+
+/// An interning pool for paths, remembering how they transform.
+///
+/// Workloads that transform large numbers of paths through many operations,
+/// like rebasing concurrent operations or tracking refs, mostly deal with a
+/// small set of distinct paths, moved by a small set of distinct operations.
+/// Interning them means each distinct path is allocated once, and each one is
+/// only transformed once through operations moving paths the same way.
+#[derive(Debug, Clone, Default)]
+pub struct PathArena {
+    paths: Vec<Arc<[usize]>>,
+    ids: HashMap<Arc<[usize]>, PathId>,
+    transforms: HashMap<(PathId, Through, Affinity), Option<PathId>>,
+    scratch: Path,
+}
+
+impl PathArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of distinct paths in the arena.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Get the handle for a path, adding it to the arena if it's new.
+    pub fn intern(&mut self, path: &Path) -> PathId {
+        self.intern_slice(path.as_slice())
+    }
+
+    fn intern_slice(&mut self, path: &[usize]) -> PathId {
+        if let Some(id) = self.ids.get(path) {
+            return *id;
+        }
+
+        let id = PathId(self.paths.len() as u32);
+        let path: Arc<[usize]> = path.into();
+        self.paths.push(path.clone());
+        self.ids.insert(path, id);
+        id
+    }
+
+    /// Get the indexes of an interned path.
+    pub fn get(&self, id: PathId) -> &[usize] {
+        &self.paths[id.0 as usize]
+    }
+
+    pub fn to_path(&self, id: PathId) -> Path {
+        Path::new(self.get(id).to_vec())
+    }
+
+    /// Transform an interned path by an operation, like `Path::transform`.
+    pub fn transform(
+        &mut self,
+        id: PathId,
+        op: &Operation,
+        affinity: Affinity,
+    ) -> Result<Option<PathId>, TransformError> {
+        let through = match self.through(op) {
+            Some(through) => through,
+            None => return Ok(Some(id)),
+        };
+        if let Some(out) = self.transforms.get(&(id, through, affinity)) {
+            return Ok(*out);
+        }
+
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.reset(self.get(id));
+        let out = scratch
+            .transform_mut(op, affinity)
+            .map(|kept| kept.then(|| self.intern_slice(scratch.as_slice())));
+        self.scratch = scratch;
+
+        if let Ok(out) = out {
+            self.transforms.insert((id, through, affinity), out);
+        }
+        out
+    }
+
+    /// Transform many interned paths by an operation, transforming each
+    /// distinct path only once.
+    pub fn transform_all(
+        &mut self,
+        ids: &[PathId],
+        op: &Operation,
+        affinity: Affinity,
+    ) -> Result<Vec<Option<PathId>>, TransformError> {
+        ids.iter()
+            .map(|id| self.transform(*id, op, affinity))
+            .collect()
+    }
+
+    /// Transform a path in place, like `Path::transform_mut`, through the
+    /// arena. The path's allocation is reused, so once its path and the one
+    /// it moves to are interned, transforming it again allocates nothing.
+    pub fn transform_mut(
+        &mut self,
+        path: &mut Path,
+        op: &Operation,
+        affinity: Affinity,
+    ) -> Result<bool, TransformError> {
+        if self.through(op).is_none() {
+            return Ok(true);
+        }
+
+        let id = self.intern(path);
+        match self.transform(id, op, affinity)? {
+            Some(to) => {
+                path.reset(&self.paths[to.0 as usize]);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Remove every path from the arena, invalidating all of its handles.
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.ids.clear();
+        self.transforms.clear();
+    }
+
+    /// Intern what of an operation moves paths, or get `None` if it doesn't
+    /// move any.
+    fn through(&mut self, op: &Operation) -> Option<Through> {
+        Some(match op.as_edit() {
+            Edit::InsertNode { path } => Through::Insert(self.intern(path)),
+            Edit::RemoveNode { path } => Through::Remove(self.intern(path)),
+            Edit::MergeNode { path, position } => Through::Merge(self.intern(path), position),
+            Edit::SplitNode { path, position } => Through::Split(self.intern(path), position),
+            Edit::MoveNode { path, new_path } => {
+                Through::Move(self.intern(path), self.intern(new_path))
+            }
+            Edit::InsertText { .. } | Edit::RemoveText { .. } | Edit::None => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Node, Text};
+
+    #[test]
+    fn intern_dedupes() {
+        let mut arena = PathArena::new();
+        let a = arena.intern(&vec![0, 1].into());
+        let b = arena.intern(&vec![0, 1].into());
+        let c = arena.intern(&vec![0, 2].into());
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(c), &[0, 2]);
+    }
+
+    #[test]
+    fn transform_matches_path_transform() {
+        let mut arena = PathArena::new();
+        let op = Operation::MoveNode {
+            path: vec![0].into(),
+            new_path: vec![2, 0].into(),
+        };
+        let path: Path = vec![0, 1].into();
+        let id = arena.intern(&path);
+        let out = arena
+            .transform(id, &op, Affinity::Forward)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Ok(Some(arena.to_path(out))),
+            Path::transform(&path, &op, Affinity::Forward)
+        );
+
+        let mut moved = path.clone();
+        assert_eq!(
+            arena.transform_mut(&mut moved, &op, Affinity::Forward),
+            Ok(true)
+        );
+        assert_eq!(moved, arena.to_path(out));
+    }
+
+    #[test]
+    fn transform_all_removed() {
+        let mut arena = PathArena::new();
+        let op = Operation::RemoveNode {
+            path: vec![1].into(),
+            node: Node::Text(Text::new("")),
+        };
+        let ids = vec![
+            arena.intern(&vec![1, 0].into()),
+            arena.intern(&vec![2].into()),
+            arena.intern(&vec![2].into()),
+        ];
+        let out = arena.transform_all(&ids, &op, Affinity::Forward).unwrap();
+        let one = arena.intern(&vec![1].into());
+        assert_eq!(out, vec![None, Some(one), Some(one)]);
+    }
+
+    #[test]
+    fn transforms_are_remembered_per_operation_shape() {
+        let mut arena = PathArena::new();
+        let remove = |text: &str| Operation::RemoveNode {
+            path: vec![0].into(),
+            node: Node::Text(Text::new(text)),
+        };
+        let id = arena.intern(&vec![1].into());
+        let zero = arena
+            .transform(id, &remove("a"), Affinity::Forward)
+            .unwrap();
+        let paths = arena.len();
+
+        // Removing other content at the same path moves paths the same way.
+        assert_eq!(
+            arena.transform(id, &remove("b"), Affinity::Forward),
+            Ok(zero)
+        );
+        assert_eq!(arena.len(), paths);
+    }
+}
@@ -1,112 +1,31 @@
-use std::cmp::{min, Ord, Ordering};
+pub use slate_core::Point;
 
-use super::operation::Operation;
-use super::path::{Affinity, Path};
-
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Point {
-    pub path: Path,
-    pub offset: usize,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Affinity, Element, Node, NodeProperties, Operation, Path, Text};
 
-impl Ord for Point {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let result = self.path.cmp(&other.path);
-        if result == Ordering::Equal {
-            return self.offset.cmp(&other.offset);
+    fn point(path: Vec<usize>, offset: usize) -> Point {
+        Point {
+            path: path.into(),
+            offset,
         }
-        result
-    }
-}
-
-impl PartialOrd for Point {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
     }
-}
 
-impl Point {
-    pub fn is_after(&self, another: &Point) -> bool {
-        self > another
-    }
-
-    pub fn is_before(&self, another: &Point) -> bool {
-        self < another
-    }
-
-    pub fn transform(point: &Point, op: &Operation, affinity: Affinity) -> Option<Point> {
-        let Point { path, offset } = point;
-        let mut point = point.clone();
-
-        match &op {
-            op @ Operation::InsertNode { .. } | op @ Operation::MoveNode { .. } => {
-                point.path = Path::transform(path, op, affinity).unwrap();
-            }
-            Operation::InsertText {
-                path: oppath,
-                offset: opoffset,
-                text,
-            } => {
-                if oppath == path && opoffset <= offset {
-                    point.offset += text.len();
-                }
-            }
-            Operation::MergeNode {
-                path: oppath,
-                position: oppos,
-                ..
-            } => {
-                if oppath == path {
-                    point.offset += oppos;
-                }
-                point.path = Path::transform(oppath, op, affinity).unwrap();
-            }
-            Operation::RemoveText {
-                path: oppath,
-                offset: opoffset,
-                text,
-                ..
-            } => {
-                if oppath == path || oppath.is_ancestor(path) {
-                    point.offset -= min(opoffset - opoffset, text.len());
-                }
-                point.path = Path::transform(oppath, op, affinity).unwrap();
-            }
-            Operation::RemoveNode { path: oppath, .. } => {
-                if oppath == path || oppath.is_ancestor(path) {
-                    return None;
-                }
-                point.path = Path::transform(oppath, op, affinity).unwrap();
-            }
-            Operation::SplitNode {
-                path: oppath,
-                position: opposition,
-                ..
-            } => {
-                if oppath == path {
-                    if opposition == offset && matches!(affinity, Affinity::None) {
-                        return None;
-                    } else if opposition < offset
-                        || (opposition == offset && matches!(affinity, Affinity::Forward))
-                    {
-                        point.offset -= opposition;
-
-                        point.path = Path::transform(path, op, Affinity::Forward).unwrap();
-                    }
-                } else {
-                    point.path = Path::transform(path, op, affinity).unwrap();
-                }
-            }
-            _ => {}
+    /// Check how points are transformed through an operation, as
+    /// `(before, affinity, after)`.
+    fn assert_transforms(op: Operation, cases: Vec<(Point, Affinity, Option<Point>)>) {
+        for (before, affinity, after) in cases {
+            assert_eq!(
+                Point::transform(&before, &op, affinity),
+                after,
+                "{:?} through {:?} with {:?}",
+                before,
+                op,
+                affinity
+            );
         }
-
-        Some(point)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
     fn compare_path_after_offset_after() {
@@ -224,4 +143,380 @@ mod tests {
         };
         assert!(a == b);
     }
+
+    #[test]
+    fn transform_by_edit_elsewhere() {
+        let point = Point {
+            path: Path::new(vec![1, 0]),
+            offset: 2,
+        };
+        let remove = Operation::RemoveText {
+            path: Path::new(vec![0, 0]),
+            offset: 0,
+            text: "ab".into(),
+        };
+        let merge = Operation::MergeNode {
+            path: Path::new(vec![1]),
+            position: 3,
+            properties: Default::default(),
+        };
+        assert_eq!(
+            Point::transform(&point, &remove, Affinity::Forward),
+            Some(point.clone())
+        );
+        assert_eq!(
+            Point::transform(&point, &merge, Affinity::Forward),
+            Some(Point {
+                path: Path::new(vec![0, 3]),
+                offset: 2
+            })
+        );
+    }
+
+    #[test]
+    fn transform_insert_text() {
+        let op = Operation::InsertText {
+            path: vec![0, 0].into(),
+            offset: 2,
+            text: "ab".into(),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 1),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 1)),
+                ),
+                (
+                    point(vec![0, 0], 2),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 4)),
+                ),
+                (
+                    point(vec![0, 0], 2),
+                    Affinity::Backward,
+                    Some(point(vec![0, 0], 2)),
+                ),
+                (
+                    point(vec![0, 0], 2),
+                    Affinity::None,
+                    Some(point(vec![0, 0], 2)),
+                ),
+                (
+                    point(vec![0, 0], 3),
+                    Affinity::Backward,
+                    Some(point(vec![0, 0], 5)),
+                ),
+                (
+                    point(vec![0, 1], 2),
+                    Affinity::Forward,
+                    Some(point(vec![0, 1], 2)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn transform_remove_text() {
+        let op = Operation::RemoveText {
+            path: vec![0, 0].into(),
+            offset: 2,
+            text: "abc".into(),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 1),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 1)),
+                ),
+                (
+                    point(vec![0, 0], 2),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 2)),
+                ),
+                (
+                    point(vec![0, 0], 3),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 2)),
+                ),
+                (
+                    point(vec![0, 0], 5),
+                    Affinity::Backward,
+                    Some(point(vec![0, 0], 2)),
+                ),
+                (
+                    point(vec![0, 0], 6),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 3)),
+                ),
+                (
+                    point(vec![0, 1], 4),
+                    Affinity::Forward,
+                    Some(point(vec![0, 1], 4)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn transform_split_text() {
+        let op = Operation::SplitNode {
+            path: vec![0, 0].into(),
+            position: 2,
+            properties: NodeProperties::new(),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 1),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 1)),
+                ),
+                (
+                    point(vec![0, 0], 2),
+                    Affinity::Forward,
+                    Some(point(vec![0, 1], 0)),
+                ),
+                (
+                    point(vec![0, 0], 2),
+                    Affinity::Backward,
+                    Some(point(vec![0, 0], 2)),
+                ),
+                (point(vec![0, 0], 2), Affinity::None, None),
+                (
+                    point(vec![0, 0], 4),
+                    Affinity::Backward,
+                    Some(point(vec![0, 1], 2)),
+                ),
+                (
+                    point(vec![0, 1], 3),
+                    Affinity::Forward,
+                    Some(point(vec![0, 2], 3)),
+                ),
+                (
+                    point(vec![1, 0], 3),
+                    Affinity::Forward,
+                    Some(point(vec![1, 0], 3)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn transform_split_element() {
+        let op = Operation::SplitNode {
+            path: vec![0].into(),
+            position: 1,
+            properties: NodeProperties::new(),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 2),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 2)),
+                ),
+                (
+                    point(vec![0, 1], 2),
+                    Affinity::Backward,
+                    Some(point(vec![1, 0], 2)),
+                ),
+                (
+                    point(vec![0, 2], 0),
+                    Affinity::Forward,
+                    Some(point(vec![1, 1], 0)),
+                ),
+                (
+                    point(vec![1, 0], 0),
+                    Affinity::Forward,
+                    Some(point(vec![2, 0], 0)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn transform_merge() {
+        let op = Operation::MergeNode {
+            path: vec![0, 1].into(),
+            position: 3,
+            properties: NodeProperties::new(),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 1),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 1)),
+                ),
+                (
+                    point(vec![0, 1], 0),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 3)),
+                ),
+                (
+                    point(vec![0, 1], 2),
+                    Affinity::Backward,
+                    Some(point(vec![0, 0], 5)),
+                ),
+                (
+                    point(vec![0, 2], 0),
+                    Affinity::Forward,
+                    Some(point(vec![0, 1], 0)),
+                ),
+            ],
+        );
+
+        let op = Operation::MergeNode {
+            path: vec![1].into(),
+            position: 2,
+            properties: NodeProperties::new(),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 1], 4),
+                    Affinity::Forward,
+                    Some(point(vec![0, 1], 4)),
+                ),
+                (
+                    point(vec![1, 0], 4),
+                    Affinity::Forward,
+                    Some(point(vec![0, 2], 4)),
+                ),
+                (
+                    point(vec![1, 1], 0),
+                    Affinity::Forward,
+                    Some(point(vec![0, 3], 0)),
+                ),
+                (
+                    point(vec![2, 0], 0),
+                    Affinity::Forward,
+                    Some(point(vec![1, 0], 0)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn transform_move() {
+        let op = Operation::MoveNode {
+            path: vec![0, 1].into(),
+            new_path: vec![1, 0].into(),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 1),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 1)),
+                ),
+                (
+                    point(vec![0, 1], 3),
+                    Affinity::Forward,
+                    Some(point(vec![1, 0], 3)),
+                ),
+                (
+                    point(vec![0, 2], 1),
+                    Affinity::Backward,
+                    Some(point(vec![0, 1], 1)),
+                ),
+                (
+                    point(vec![1, 0], 0),
+                    Affinity::Forward,
+                    Some(point(vec![1, 1], 0)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn transform_insert_and_remove_node() {
+        let op = Operation::InsertNode {
+            path: vec![0, 1].into(),
+            node: Node::Text(Text::new("x")),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 3),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 3)),
+                ),
+                (
+                    point(vec![0, 1], 3),
+                    Affinity::Backward,
+                    Some(point(vec![0, 2], 3)),
+                ),
+                (
+                    point(vec![1, 0], 0),
+                    Affinity::Forward,
+                    Some(point(vec![1, 0], 0)),
+                ),
+            ],
+        );
+
+        let op = Operation::RemoveNode {
+            path: vec![0].into(),
+            node: Node::Element(Element::new()),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (point(vec![0, 2], 1), Affinity::Forward, None),
+                (
+                    point(vec![1, 0], 2),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 2)),
+                ),
+            ],
+        );
+
+        let op = Operation::RemoveNode {
+            path: vec![0, 1].into(),
+            node: Node::Text(Text::new("x")),
+        };
+        assert_transforms(
+            op,
+            vec![
+                (
+                    point(vec![0, 0], 1),
+                    Affinity::Forward,
+                    Some(point(vec![0, 0], 1)),
+                ),
+                (point(vec![0, 1], 1), Affinity::Forward, None),
+                (
+                    point(vec![0, 2], 1),
+                    Affinity::Forward,
+                    Some(point(vec![0, 1], 1)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn transform_ignores_set_operations() {
+        let op = Operation::SetNode {
+            path: vec![0, 0].into(),
+            properties: NodeProperties::new(),
+            new_properties: NodeProperties::new(),
+        };
+        assert_transforms(
+            op,
+            vec![(
+                point(vec![0, 0], 1),
+                Affinity::Forward,
+                Some(point(vec![0, 0], 1)),
+            )],
+        );
+    }
 }
@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::serialize::json;
+
+use super::element::Element;
+use super::mark_registry::MarkRegistry;
+use super::node::Descendant;
+use super::text::{Marks, Text};
+
+/// Some of the properties of a node, apart from its content.
+///
+/// A `SetNode` operation carries two of these: the values it sets, and the
+/// values they replace so that it can be inverted. Only the properties that
+/// change are included. A property mapped to `None` is absent, so setting it
+/// removes the property and the inverse adds it back.
+///
+/// Properties also hold the valued marks of text nodes, like their color.
+///
+/// `SplitNode` carries the properties that turn the node being split into the
+/// new node after it. `MergeNode` carries the properties that turn the node
+/// merged into back into the merged one, for its inverse.
+///
+/// They serialize like the `properties` objects of Slate.js operations: the
+/// kind as `type`, removed properties as `null`, and marks as `true` or
+/// `null` under their names. Only the built-in marks can be named that way,
+/// so properties with custom marks fail to serialize and unknown marks fail
+/// to deserialize; `with_registry` and `PropertiesSeed` name them with the
+/// editor's registry instead.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NodeProperties {
+    /// The kind of an element.
+    pub kind: Option<String>,
+    /// Properties of an element, or valued marks of a text node.
+    pub properties: BTreeMap<String, Option<String>>,
+    /// The marks of a text node.
+    pub marks: Option<Marks>,
+}
+
+impl NodeProperties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = Some(kind.into());
+        self
+    }
+
+    /// Set an element property or a valued mark, or remove it with `None`.
+    pub fn property(mut self, key: impl Into<String>, value: Option<impl Into<String>>) -> Self {
+        self.properties.insert(key.into(), value.map(Into::into));
+        self
+    }
+
+    pub fn marks(mut self, marks: Marks) -> Self {
+        self.marks = Some(marks);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kind.is_none() && self.properties.is_empty() && self.marks.is_none()
+    }
+
+    /// Get the properties that turn `from` into `to`. Nodes of different types
+    /// can't be turned into each other, so this is empty for them.
+    pub fn between(from: &Descendant, to: &Descendant) -> Self {
+        match (from, to) {
+            (Descendant::Element(from), Descendant::Element(to)) => {
+                let mut properties: BTreeMap<_, _> = from
+                    .properties()
+                    .keys()
+                    .map(|key| (key.clone(), None))
+                    .collect();
+                for (key, value) in to.properties() {
+                    properties.insert(key.clone(), Some(value.clone()));
+                }
+
+                Self {
+                    kind: Some(to.kind().to_string()),
+                    properties,
+                    marks: None,
+                }
+            }
+            (Descendant::Text(from), Descendant::Text(to)) => {
+                let mut properties: BTreeMap<_, _> = from
+                    .mark_values()
+                    .keys()
+                    .map(|key| (key.clone(), None))
+                    .collect();
+                for (key, value) in to.mark_values() {
+                    properties.insert(key.clone(), Some(value.clone()));
+                }
+
+                Self {
+                    kind: None,
+                    properties,
+                    marks: Some(to.marks()),
+                }
+            }
+            _ => Self::default(),
+        }
+    }
+
+    /// Get the current values of the properties set by `self` on a node, which
+    /// are what a `SetNode` operation replaces.
+    pub fn current(&self, node: &Descendant) -> Self {
+        match node {
+            Descendant::Element(e) => Self {
+                kind: self.kind.as_ref().map(|_| e.kind().to_string()),
+                properties: self
+                    .properties
+                    .keys()
+                    .map(|key| (key.clone(), e.property(key).map(String::from)))
+                    .collect(),
+                marks: None,
+            },
+            Descendant::Text(t) => Self {
+                kind: None,
+                properties: self
+                    .properties
+                    .keys()
+                    .map(|key| (key.clone(), t.mark_value(key).map(String::from)))
+                    .collect(),
+                marks: self.marks.map(|_| t.marks()),
+            },
+        }
+    }
+
+    /// Serialize the properties naming their marks with a registry, so custom
+    /// marks are written too.
+    pub fn with_registry<'a>(&'a self, registry: &'a MarkRegistry) -> RegisteredProperties<'a> {
+        RegisteredProperties {
+            properties: self,
+            registry,
+        }
+    }
+
+    /// Whether the properties can be set on a node. Marks only apply to text,
+    /// and kinds only to elements.
+    pub(crate) fn applies_to(&self, node: &Descendant) -> bool {
+        match node {
+            Descendant::Element(_) => self.marks.is_none(),
+            Descendant::Text(_) => self.kind.is_none(),
+        }
+    }
+
+    /// Set the properties on a node. They must apply to it.
+    pub(crate) fn apply_to(&self, node: &mut Descendant) {
+        match node {
+            Descendant::Element(e) => self.apply_to_element(e),
+            Descendant::Text(t) => self.apply_to_text(t),
+        }
+    }
+
+    fn apply_to_element(&self, element: &mut Element) {
+        if let Some(kind) = &self.kind {
+            *element.kind_mut() = kind.clone();
+        }
+
+        for (key, value) in &self.properties {
+            match value {
+                Some(value) => element.properties_mut().insert(key.clone(), value.clone()),
+                None => element.properties_mut().remove(key),
+            };
+        }
+    }
+
+    fn apply_to_text(&self, text: &mut Text) {
+        if let Some(marks) = self.marks {
+            *text.marks_mut() = marks;
+        }
+
+        for (key, value) in &self.properties {
+            match value {
+                Some(value) => text.mark_values_mut().insert(key.clone(), value.clone()),
+                None => text.mark_values_mut().remove(key),
+            };
+        }
+    }
+}
+
+/// Node properties with the registry naming their marks, from
+/// `NodeProperties::with_registry`.
+#[derive(Debug, Clone, Copy)]
+pub struct RegisteredProperties<'a> {
+    properties: &'a NodeProperties,
+    registry: &'a MarkRegistry,
+}
+
+impl Serialize for RegisteredProperties<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let unnamed = self.properties.marks.unwrap_or_else(Marks::empty) - self.registry.all();
+        if !unnamed.is_empty() {
+            return Err(ser::Error::custom(format_args!(
+                "marks {:#x} aren't registered",
+                unnamed.bits()
+            )));
+        }
+        json::properties_value(self.properties, self.registry).serialize(serializer)
+    }
+}
+
+/// Deserializes node properties naming their marks with a registry, so
+/// custom marks are read too.
+#[derive(Debug, Clone, Copy)]
+pub struct PropertiesSeed<'a>(pub &'a MarkRegistry);
+
+impl<'de> DeserializeSeed<'de> for PropertiesSeed<'_> {
+    type Value = NodeProperties;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<NodeProperties, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        // Marks the registry doesn't know would otherwise be dropped.
+        let unknown = value.as_object().and_then(|object| {
+            object
+                .iter()
+                .find(|(key, value)| **value == Value::Bool(true) && self.0.get(key).is_none())
+        });
+        if let Some((key, _)) = unknown {
+            return Err(de::Error::custom(format_args!(
+                "mark `{}` isn't registered",
+                key
+            )));
+        }
+        json::read_properties(&value, self.0)
+            .ok_or_else(|| de::Error::custom("invalid node properties"))
+    }
+}
+
+// In the shape of Slate.js properties, with the built-in marks.
+impl Serialize for NodeProperties {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_registry(&MarkRegistry::default())
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeProperties {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PropertiesSeed(&MarkRegistry::default()).deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_values() {
+        let node = Descendant::Element(Element::with_kind("link").add_property("url", "a"));
+        let props = NodeProperties::new()
+            .kind("heading-one")
+            .property("url", None::<String>)
+            .property("title", Some("b"));
+        assert_eq!(
+            props.current(&node),
+            NodeProperties::new()
+                .kind("link")
+                .property("url", Some("a"))
+                .property("title", None::<String>)
+        );
+    }
+
+    #[test]
+    fn between_elements() {
+        let from = Descendant::Element(Element::new().add_property("a", "1"));
+        let to = Descendant::Element(Element::with_kind("link").add_property("b", "2"));
+        let props = NodeProperties::between(&from, &to);
+        assert_eq!(
+            props,
+            NodeProperties::new()
+                .kind("link")
+                .property("a", None::<String>)
+                .property("b", Some("2"))
+        );
+
+        let mut node = from;
+        props.apply_to(&mut node);
+        assert_eq!(node, to);
+    }
+
+    #[test]
+    fn serialize_like_slate() {
+        let props = NodeProperties::new()
+            .kind("link")
+            .property("url", Some("a"))
+            .property("title", None::<String>);
+        let json = serde_json::to_value(&props).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "link", "url": "a", "title": null })
+        );
+        assert_eq!(
+            serde_json::from_value::<NodeProperties>(json).unwrap(),
+            props
+        );
+
+        let marks = NodeProperties::new().marks(Marks::BOLD);
+        let json = serde_json::to_value(&marks).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "bold": true, "italic": null, "underline": null, "code": null })
+        );
+        assert_eq!(
+            serde_json::from_value::<NodeProperties>(json).unwrap(),
+            marks
+        );
+        assert_eq!(
+            serde_json::from_str::<NodeProperties>(r#"{ "italic": true }"#).unwrap(),
+            NodeProperties::new().marks(Marks::ITALIC)
+        );
+    }
+
+    #[test]
+    fn serialize_custom_marks_with_their_registry() {
+        let mut registry = MarkRegistry::default();
+        let highlight = registry.register("highlight").unwrap();
+        let marks = NodeProperties::new().marks(Marks::BOLD | highlight);
+
+        // Without the registry, the custom mark can't be named.
+        assert!(serde_json::to_value(&marks).is_err());
+        assert!(serde_json::from_str::<NodeProperties>(r#"{ "highlight": true }"#).is_err());
+
+        let json = serde_json::to_value(marks.with_registry(&registry)).unwrap();
+        assert_eq!(json["highlight"], true);
+        assert_eq!(PropertiesSeed(&registry).deserialize(json).unwrap(), marks);
+    }
+
+    #[test]
+    fn applies_to() {
+        let text = Descendant::Text(Text::new(""));
+        let element = Descendant::Element(Element::new());
+        let marks = NodeProperties::new().marks(Marks::BOLD);
+        let kind = NodeProperties::new().kind("link");
+        let color = NodeProperties::new().property("color", Some("red"));
+        assert!(marks.applies_to(&text));
+        assert!(color.applies_to(&text));
+        assert!(!marks.applies_to(&element));
+        assert!(kind.applies_to(&element));
+        assert!(!kind.applies_to(&text));
+    }
+}
@@ -1,37 +1,157 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::iter;
+
+use unicode_segmentation::UnicodeSegmentation;
 
 use super::{node::Descendant, Range};
 
 pub type Decoration = (Range, HashSet<String>);
 
 bitflags::bitflags! {
+    /// The formatting marks of text. Bits outside of the built-in marks are
+    /// custom marks registered in a `MarkRegistry`, so every bit is kept by
+    /// the set operations.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
     pub struct Marks: u32 {
         const BOLD = 1 << 1;
         const ITALIC = 1 << 2;
         const UNDERLINE = 1 << 3;
+        const CODE = 1 << 4;
+
+        const _ = !0;
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Text(String, Marks, HashSet<String>);
+impl Marks {
+    /// The marks every editor knows, without a registry.
+    pub const BUILT_IN: Marks = Marks::BOLD
+        .union(Marks::ITALIC)
+        .union(Marks::UNDERLINE)
+        .union(Marks::CODE);
+}
+
+/// The valued mark holding the color of text.
+pub const COLOR_MARK: &str = "color";
+/// The valued mark holding the background color of text.
+pub const BACKGROUND_MARK: &str = "background";
 
-impl Into<Descendant> for Text {
-    fn into(self) -> Descendant {
-        Descendant::Text(self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text(
+    String,
+    Marks,
+    HashSet<String>,
+    Option<String>,
+    BTreeMap<String, String>,
+);
+
+impl From<Text> for Descendant {
+    fn from(val: Text) -> Self {
+        Descendant::Text(val)
     }
 }
 
 impl Text {
     pub fn new(text: impl Into<String>) -> Self {
-        Self(text.into(), Marks::empty(), HashSet::new())
+        Self::with_marks(text, Marks::empty())
     }
 
     pub fn with_meta(text: impl Into<String>, meta: HashSet<String>) -> Self {
-        Self(text.into(), Marks::empty(), meta)
+        Self(text.into(), Marks::empty(), meta, None, BTreeMap::new())
     }
 
     pub fn with_marks(text: impl Into<String>, marks: Marks) -> Self {
-        Self(text.into(), marks, HashSet::new())
+        Self(text.into(), marks, HashSet::new(), None, BTreeMap::new())
+    }
+
+    /// Add a valued mark, like `COLOR_MARK`. Unlike `Marks`, which are on or
+    /// off, these hold a string.
+    pub fn add_mark_value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.4.insert(key.into(), value.into());
+        self
+    }
+
+    /// Attribute the text to the user who inserted it.
+    pub fn authored_by(mut self, author: impl Into<String>) -> Self {
+        self.3 = Some(author.into());
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.0
+    }
+
+    pub fn marks(&self) -> Marks {
+        self.1
+    }
+
+    pub fn meta(&self) -> &HashSet<String> {
+        &self.2
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.3.as_deref()
+    }
+
+    pub fn mark_value(&self, key: &str) -> Option<&str> {
+        self.4.get(key).map(String::as_str)
+    }
+
+    pub fn mark_values(&self) -> &BTreeMap<String, String> {
+        &self.4
+    }
+
+    /// Whether two text nodes have the same marks, metadata and author, so
+    /// they can be merged without losing any of them.
+    pub(crate) fn same_format(&self, other: &Text) -> bool {
+        self.1 == other.1 && self.2 == other.2 && self.3 == other.3 && self.4 == other.4
+    }
+
+    pub(crate) fn text_mut(&mut self) -> &mut String {
+        &mut self.0
+    }
+
+    pub(crate) fn marks_mut(&mut self) -> &mut Marks {
+        &mut self.1
+    }
+
+    pub(crate) fn author_mut(&mut self) -> &mut Option<String> {
+        &mut self.3
+    }
+
+    pub(crate) fn mark_values_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.4
+    }
+
+    /// Create a text node with the same marks, metadata and author but
+    /// different text.
+    pub(crate) fn with_text(&self, text: impl Into<String>) -> Self {
+        Self(
+            text.into(),
+            self.1,
+            self.2.clone(),
+            self.3.clone(),
+            self.4.clone(),
+        )
+    }
+
+    /// Get the offsets a caret can be at in the text, which are the boundaries
+    /// between grapheme clusters, from `0` to the text's length. Anything that
+    /// moves by or deletes a "character" should step between these.
+    pub fn positions(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        let boundaries = self.0.grapheme_indices(true).map(|(i, _)| i);
+        let end = iter::once(self.0.len());
+
+        iter::once(0).chain(boundaries.chain(end).filter(|i| *i > 0))
+    }
+
+    /// Get the caret position after an offset, if any.
+    pub fn next_position(&self, offset: usize) -> Option<usize> {
+        self.positions().find(|p| *p > offset)
+    }
+
+    /// Get the caret position before an offset, if any.
+    pub fn previous_position(&self, offset: usize) -> Option<usize> {
+        self.positions().rev().find(|p| *p < offset)
     }
 
     /// Check if two Text nodes have the same **marks** (doesn't compare values values).
@@ -55,7 +175,8 @@ impl Text {
 
                 // If the range encompases the entire leaf, add the range.
                 if start.offset <= offset && end.offset >= offset + len {
-                    next.push(Text(leaf.0, leaf.1, leaf.2.union(&dec).cloned().collect()));
+                    let meta = leaf.2.union(&dec).cloned().collect();
+                    next.push(Text(leaf.0, leaf.1, meta, leaf.3, leaf.4));
                     continue;
                 }
 
@@ -77,14 +198,14 @@ impl Text {
 
                 if end.offset < offset + len {
                     let off = end.offset - offset;
-                    after = Some(Text(middle.0[off..].into(), middle.1, middle.2.clone()));
-                    middle = Text(middle.0[..off].into(), middle.1, middle.2);
+                    after = Some(middle.with_text(&middle.0[off..]));
+                    middle = middle.with_text(&middle.0[..off]);
                 }
 
                 if start.offset > offset {
                     let off = start.offset - offset;
-                    before = Some(Text(middle.0[..off].into(), middle.1, middle.2.clone()));
-                    middle = Text(middle.0[off..].into(), middle.1, middle.2);
+                    before = Some(middle.with_text(&middle.0[..off]));
+                    middle = middle.with_text(&middle.0[off..]);
                 }
 
                 middle.2 = middle.2.union(&dec).cloned().collect();
@@ -112,27 +233,77 @@ mod tests {
     use super::super::Point;
     use super::*;
 
+    #[test]
+    fn positions_graphemes() {
+        let text = Text::new("e\u{301}👍🏽!");
+        assert_eq!(text.positions().collect::<Vec<_>>(), vec![0, 3, 11, 12]);
+        assert_eq!(text.next_position(0), Some(3));
+        assert_eq!(text.previous_position(11), Some(3));
+        assert_eq!(text.previous_position(0), None);
+    }
+
+    #[test]
+    fn positions_empty() {
+        assert_eq!(Text::new("").positions().collect::<Vec<_>>(), vec![0]);
+    }
+
     #[test]
     fn matches_empty_true() {
         assert!(Text::matches(
-            Text("".into(), Marks::BOLD, vec![].iter().cloned().collect()),
-            Text("".into(), Marks::empty(), vec![].iter().cloned().collect())
+            Text(
+                "".into(),
+                Marks::BOLD,
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
+            ),
+            Text(
+                "".into(),
+                Marks::empty(),
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
+            )
         ));
     }
 
     #[test]
     fn matches_false() {
         assert!(!Text::matches(
-            Text("".into(), Marks::BOLD, vec![].iter().cloned().collect()),
-            Text("".into(), Marks::ITALIC, vec![].iter().cloned().collect())
+            Text(
+                "".into(),
+                Marks::BOLD,
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
+            ),
+            Text(
+                "".into(),
+                Marks::ITALIC,
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
+            )
         ));
     }
 
     #[test]
     fn matches_true() {
         assert!(Text::matches(
-            Text("".into(), Marks::BOLD, vec![].iter().cloned().collect()),
-            Text("".into(), Marks::BOLD, vec![].iter().cloned().collect())
+            Text(
+                "".into(),
+                Marks::BOLD,
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
+            ),
+            Text(
+                "".into(),
+                Marks::BOLD,
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
+            )
         ));
     }
 
@@ -142,12 +313,16 @@ mod tests {
             Text(
                 "".into(),
                 Marks::BOLD | Marks::ITALIC,
-                vec![].iter().cloned().collect()
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
             ),
             Text(
                 "".into(),
                 Marks::UNDERLINE,
-                vec![].iter().cloned().collect()
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
             )
         ));
     }
@@ -158,9 +333,17 @@ mod tests {
             Text(
                 "".into(),
                 Marks::BOLD | Marks::ITALIC,
-                vec![].iter().cloned().collect()
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
             ),
-            Text("".into(), Marks::BOLD, vec![].iter().cloned().collect())
+            Text(
+                "".into(),
+                Marks::BOLD,
+                [].iter().cloned().collect(),
+                None,
+                BTreeMap::new()
+            )
         ));
     }
 
@@ -177,13 +360,15 @@ mod tests {
                     offset: 3,
                 },
             ),
-            vec!["decoration".into()].iter().cloned().collect(),
+            ["decoration".into()].iter().cloned().collect(),
         )];
 
         let input = Text(
             "abc".into(),
             Marks::BOLD,
-            vec!["test".into()].iter().cloned().collect(),
+            ["test".into()].iter().cloned().collect(),
+            None,
+            BTreeMap::new(),
         );
 
         assert_eq!(
@@ -192,15 +377,19 @@ mod tests {
                 Text(
                     "ab".into(),
                     Marks::BOLD,
-                    vec!["test".into()].iter().cloned().collect()
+                    ["test".into()].iter().cloned().collect(),
+                    None,
+                    BTreeMap::new()
                 ),
                 Text(
                     "c".into(),
                     Marks::BOLD,
-                    vec!["decoration".into(), "test".into()]
+                    ["decoration".into(), "test".into()]
                         .iter()
                         .cloned()
-                        .collect()
+                        .collect(),
+                    None,
+                    BTreeMap::new()
                 ),
             ]
         );
@@ -219,13 +408,15 @@ mod tests {
                     offset: 2,
                 },
             ),
-            vec!["decoration".into()].iter().cloned().collect(),
+            ["decoration".into()].iter().cloned().collect(),
         )];
 
         let input = Text(
             "abc".into(),
             Marks::empty(),
-            vec!["test".into()].iter().cloned().collect(),
+            ["test".into()].iter().cloned().collect(),
+            None,
+            BTreeMap::new(),
         );
 
         assert_eq!(
@@ -234,20 +425,26 @@ mod tests {
                 Text(
                     "a".into(),
                     Marks::empty(),
-                    vec!["test".into()].iter().cloned().collect()
+                    ["test".into()].iter().cloned().collect(),
+                    None,
+                    BTreeMap::new()
                 ),
                 Text(
                     "b".into(),
                     Marks::empty(),
-                    vec!["decoration".into(), "test".into()]
+                    ["decoration".into(), "test".into()]
                         .iter()
                         .cloned()
-                        .collect()
+                        .collect(),
+                    None,
+                    BTreeMap::new()
                 ),
                 Text(
                     "c".into(),
                     Marks::empty(),
-                    vec!["test".into()].iter().cloned().collect()
+                    ["test".into()].iter().cloned().collect(),
+                    None,
+                    BTreeMap::new()
                 ),
             ]
         );
@@ -267,7 +464,7 @@ mod tests {
                         offset: 2,
                     },
                 ),
-                vec!["decoration1".into()].iter().cloned().collect(),
+                ["decoration1".into()].iter().cloned().collect(),
             ),
             (
                 Range::new(
@@ -280,11 +477,17 @@ mod tests {
                         offset: 3,
                     },
                 ),
-                vec!["decoration2".into()].iter().cloned().collect(),
+                ["decoration2".into()].iter().cloned().collect(),
             ),
         ];
 
-        let input = Text("abc".into(), Marks::BOLD, vec![].iter().cloned().collect());
+        let input = Text(
+            "abc".into(),
+            Marks::BOLD,
+            [].iter().cloned().collect(),
+            None,
+            BTreeMap::new(),
+        );
 
         assert_eq!(
             input.decorations(decs),
@@ -292,20 +495,26 @@ mod tests {
                 Text(
                     "a".into(),
                     Marks::BOLD,
-                    vec!["decoration2".into()].iter().cloned().collect()
+                    ["decoration2".into()].iter().cloned().collect(),
+                    None,
+                    BTreeMap::new()
                 ),
                 Text(
                     "b".into(),
                     Marks::BOLD,
-                    vec!["decoration1".into(), "decoration2".into()]
+                    ["decoration1".into(), "decoration2".into()]
                         .iter()
                         .cloned()
-                        .collect()
+                        .collect(),
+                    None,
+                    BTreeMap::new()
                 ),
                 Text(
                     "c".into(),
                     Marks::BOLD,
-                    vec!["decoration2".into()].iter().cloned().collect()
+                    ["decoration2".into()].iter().cloned().collect(),
+                    None,
+                    BTreeMap::new()
                 ),
             ]
         );
@@ -324,10 +533,16 @@ mod tests {
                     offset: 1,
                 },
             ),
-            vec!["decoration".into()].iter().cloned().collect(),
+            ["decoration".into()].iter().cloned().collect(),
         )];
 
-        let input = Text("abc".into(), Marks::BOLD, HashSet::new());
+        let input = Text(
+            "abc".into(),
+            Marks::BOLD,
+            HashSet::new(),
+            None,
+            BTreeMap::new(),
+        );
 
         assert_eq!(
             input.decorations(decs),
@@ -335,9 +550,17 @@ mod tests {
                 Text(
                     "a".into(),
                     Marks::BOLD,
-                    vec!["decoration".into()].iter().cloned().collect()
+                    ["decoration".into()].iter().cloned().collect(),
+                    None,
+                    BTreeMap::new()
+                ),
+                Text(
+                    "bc".into(),
+                    Marks::BOLD,
+                    HashSet::new(),
+                    None,
+                    BTreeMap::new()
                 ),
-                Text("bc".into(), Marks::BOLD, HashSet::new()),
             ]
         );
     }
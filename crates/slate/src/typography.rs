@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+
+use crate::types::{Descendant, Editor, Marks, Operation, Path};
+
+/// Typographic substitutions made as text is typed: straight quotes become
+/// curly ones and three periods become an ellipsis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typography {
+    /// The opening and closing double quotes.
+    pub double_quotes: (char, char),
+    /// The opening and closing single quotes. The closing one is also used
+    /// for apostrophes.
+    pub single_quotes: (char, char),
+    /// Whether `...` becomes `…`.
+    pub ellipsis: bool,
+    /// The element kinds nothing is substituted in, like code blocks.
+    pub code_kinds: HashSet<String>,
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Self {
+            double_quotes: ('\u{201c}', '\u{201d}'),
+            single_quotes: ('\u{2018}', '\u{2019}'),
+            ellipsis: true,
+            code_kinds: ["code-block", "code"]
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Typography {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the substitutions for a locale, like `"de"` or `"fr-CA"`, using
+    /// its quotation marks. Unknown locales use English ones.
+    pub fn for_locale(locale: &str) -> Self {
+        let language = locale
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let typography = Self::new();
+        match language.as_str() {
+            "de" => typography
+                .double_quotes('\u{201e}', '\u{201c}')
+                .single_quotes('\u{201a}', '\u{2018}'),
+            "fr" | "ru" => typography
+                .double_quotes('\u{ab}', '\u{bb}')
+                .single_quotes('\u{2039}', '\u{203a}'),
+            _ => typography,
+        }
+    }
+
+    pub fn double_quotes(mut self, open: char, close: char) -> Self {
+        self.double_quotes = (open, close);
+        self
+    }
+
+    pub fn single_quotes(mut self, open: char, close: char) -> Self {
+        self.single_quotes = (open, close);
+        self
+    }
+
+    pub fn ellipsis(mut self, ellipsis: bool) -> Self {
+        self.ellipsis = ellipsis;
+        self
+    }
+
+    pub fn code_kinds<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.code_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Substitute the characters of `text`, which follows `before`.
+    fn apply(&self, before: Option<char>, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut prev = before;
+
+        for c in text.chars() {
+            let opening = prev.is_none_or(|p| {
+                p.is_whitespace()
+                    || "([{-\u{2013}\u{2014}".contains(p)
+                    || p == self.double_quotes.0
+                    || p == self.single_quotes.0
+            });
+            let c = match c {
+                '"' if opening => self.double_quotes.0,
+                '"' => self.double_quotes.1,
+                '\'' if opening => self.single_quotes.0,
+                '\'' => self.single_quotes.1,
+                c => c,
+            };
+            out.push(c);
+            prev = Some(c);
+        }
+
+        if self.ellipsis {
+            out = out.replace("...", "\u{2026}");
+        }
+        out
+    }
+}
+
+impl Editor {
+    /// Make typographic substitutions in text inserted with `insert_text`.
+    pub fn with_typography(mut self, typography: Typography) -> Self {
+        self.input_rules
+            .get_or_insert_with(Default::default)
+            .typography = Some(typography);
+        self
+    }
+
+    pub fn typography(&self) -> Option<&Typography> {
+        self.input_rules.as_ref()?.typography.as_ref()
+    }
+
+    /// Make typographic substitutions in text just inserted between two
+    /// offsets of the text at a path, unless it's code.
+    ///
+    /// The substitution replaces the inserted characters with operations of
+    /// its own, applied after the insertion, so undoing them brings back what
+    /// was typed.
+    pub(crate) fn typeset(&mut self, path: &Path, start: usize, end: usize) {
+        let typography = match self.typography() {
+            Some(typography) => typography,
+            None => return,
+        };
+        let text = match self.text_at(path) {
+            Some(text) if !self.in_code(path) => text.text(),
+            _ => return,
+        };
+
+        // An ellipsis can be finished by the inserted text.
+        let from = start
+            - text[..start]
+                .bytes()
+                .rev()
+                .take(2)
+                .take_while(|b| *b == b'.')
+                .count();
+        let old = &text[from..end];
+        let new = typography.apply(text[..from].chars().last(), old);
+        if new == old {
+            return;
+        }
+        let old = old.to_string();
+
+        let remove = Operation::RemoveText {
+            path: path.clone(),
+            offset: from,
+            text: old,
+        };
+        let insert = Operation::InsertText {
+            path: path.clone(),
+            offset: from,
+            text: new,
+        };
+        for op in [remove, insert] {
+            self.apply(op)
+                .expect("replacement of inserted text to apply");
+        }
+    }
+
+    /// Get the element kinds no substitutions are made in: those of the
+    /// editor's typography, or the default ones without any.
+    pub(crate) fn code_kinds(&self) -> HashSet<String> {
+        match self.typography() {
+            Some(typography) => typography.code_kinds.clone(),
+            None => Typography::default().code_kinds,
+        }
+    }
+
+    /// Whether a node is code: text with the code mark, or a node in an
+    /// element of one of the code kinds.
+    pub(crate) fn in_code(&self, path: &Path) -> bool {
+        if self
+            .text_at(path)
+            .is_some_and(|text| text.marks().contains(Marks::CODE))
+        {
+            return true;
+        }
+        let kinds = self.code_kinds();
+
+        path.levels(false)
+            .iter()
+            .any(|p| match self.descendant_ref(p) {
+                Some(Descendant::Element(e)) => kinds.contains(e.kind()),
+                _ => false,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Element, Point, Range, Text};
+
+    use super::*;
+
+    fn caret(path: Vec<usize>, offset: usize) -> Range {
+        let point = Point {
+            path: path.into(),
+            offset,
+        };
+        Range::new(point.clone(), point)
+    }
+
+    fn typed(editor: &mut Editor, text: &str) -> String {
+        for c in text.chars() {
+            editor.insert_text(&c.to_string(), None);
+        }
+        editor
+            .text_at(&Path::new(vec![0, 0]))
+            .unwrap()
+            .text()
+            .into()
+    }
+
+    #[test]
+    fn quotes_and_ellipsis() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .with_typography(Typography::new());
+        editor.select(caret(vec![0, 0], 0));
+
+        assert_eq!(
+            typed(&mut editor, "\"it's 'so'...\""),
+            "\u{201c}it\u{2019}s \u{2018}so\u{2019}\u{2026}\u{201d}"
+        );
+        assert_eq!(
+            editor.selection(),
+            Some(&caret(
+                vec![0, 0],
+                editor.block_text(&Path::new(vec![0])).unwrap().len()
+            ))
+        );
+    }
+
+    #[test]
+    fn locale_quotes() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("")))
+            .with_typography(Typography::for_locale("de-AT"));
+        editor.select(caret(vec![0, 0], 0));
+
+        assert_eq!(typed(&mut editor, "\"a\""), "\u{201e}a\u{201c}");
+    }
+
+    #[test]
+    fn code_is_left_alone() {
+        let mut editor = Editor::new()
+            .add_child(Element::with_kind("code-block").add_child(Text::new("")))
+            .with_typography(Typography::new());
+        editor.select(caret(vec![0, 0], 0));
+
+        assert_eq!(typed(&mut editor, "\"...\""), "\"...\"");
+    }
+
+    #[test]
+    fn substitution_is_its_own_operations() {
+        let mut editor = Editor::new()
+            .add_child(Element::new().add_child(Text::new("a")))
+            .with_typography(Typography::new());
+        editor.select(caret(vec![0, 0], 1));
+        editor.flush();
+
+        editor.insert_text("'", None);
+        let ops = editor.flush();
+        assert_eq!(ops.len(), 3);
+
+        // Undoing the substitution leaves what was typed.
+        let mut literal = editor.clone();
+        for op in ops[1..].iter().rev() {
+            literal.apply(op.clone().inverse()).unwrap();
+        }
+        assert_eq!(
+            literal.text_at(&Path::new(vec![0, 0])).unwrap().text(),
+            "a'"
+        );
+    }
+}